@@ -0,0 +1,27 @@
+use std::{env, fs, path::Path};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+	// Compile the gRPC/protobuf client
+	tonic_build::configure()
+		.build_client(true)
+		.build_server(false)
+		.compile_well_known_types(true)
+		.compile_protos(&["../proto/links.proto"], &["../proto"])?;
+
+	// Disable pedantic clippy lints in the generated file
+	let out_dir = env::var_os("OUT_DIR").unwrap();
+	let proto_path = Path::new(&out_dir).join("links.rs");
+	let proto = fs::read_to_string(&proto_path)?;
+	fs::write(
+		&proto_path,
+		"#[allow(clippy::pedantic, clippy::nursery, missing_docs, reason = \"generated \
+		 code\")]\npub mod rpc {\n"
+			.to_string()
+			+ &proto + "}\n",
+	)?;
+
+	println!("cargo::rerun-if-changed=../proto/links.proto");
+	println!("cargo::rerun-if-changed=../proto/*");
+
+	Ok(())
+}
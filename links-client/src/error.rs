@@ -0,0 +1,37 @@
+//! The error type returned by the links client
+
+use thiserror::Error as ThisError;
+use tonic::{transport::Error as TransportError, Code, Status};
+
+/// An error returned by the links [`Client`][crate::Client]
+#[derive(Debug, ThisError)]
+#[non_exhaustive]
+pub enum Error {
+	/// The server URL could not be constructed from the configuration
+	#[error("the server host or port is invalid")]
+	InvalidUrl,
+	/// The API token is not a valid gRPC metadata value
+	#[error("the API token contains invalid characters")]
+	InvalidToken,
+	/// The connection to the server could not be established
+	#[error("could not connect to the server")]
+	Transport(#[from] TransportError),
+	/// The token was rejected by the server
+	#[error("the API token was rejected by the server")]
+	Unauthenticated(Box<Status>),
+	/// The server returned an error
+	#[error("the API call failed: {}", .0.message())]
+	Status(Box<Status>),
+	/// The server returned a response that could not be parsed
+	#[error("the server returned an invalid {0}")]
+	InvalidResponse(&'static str),
+}
+
+impl From<Status> for Error {
+	fn from(status: Status) -> Self {
+		match status.code() {
+			Code::Unauthenticated => Self::Unauthenticated(Box::new(status)),
+			_ => Self::Status(Box::new(status)),
+		}
+	}
+}
@@ -0,0 +1,782 @@
+//! A typed client for the gRPC API of the links redirector server.
+//!
+//! This crate wraps the generated tonic client, so that programs talking to a
+//! links server don't need to deal with raw protobuf messages. Instead, the
+//! [`Client`] methods take and return the types used by links itself, like
+//! [`Id`], [`Link`], and [`Normalized`]. The client also takes care of
+//! authentication, TLS setup, and retrying read-only calls that fail because
+//! the server is (temporarily) unavailable.
+//!
+//! # Example usage
+//!
+//! ```rust,no_run
+//! use links_client::{Client, ClientConfig};
+//! use links_id::Id;
+//! use links_normalized::{Link, Normalized};
+//!
+//! # async fn example() -> Result<(), links_client::Error> {
+//! let config = ClientConfig::new("links.example.com", "secret api token").tls(true);
+//! let client = Client::connect(config).await?;
+//!
+//! let id = client
+//! 	.create_link(
+//! 		Link::new("https://example.com/").unwrap(),
+//! 		Some(Normalized::new("example")),
+//! 	)
+//! 	.await?;
+//!
+//! assert_eq!(
+//! 	client.get_vanity(Normalized::new("example")).await?,
+//! 	Some(id)
+//! );
+//! let statistics = client.stats_for(id).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(
+	clippy::pedantic,
+	clippy::cargo,
+	clippy::nursery,
+	missing_docs,
+	rustdoc::missing_crate_level_docs
+)]
+#![allow(
+	clippy::multiple_crate_versions,
+	clippy::tabs_in_doc_comments,
+	clippy::module_name_repetitions
+)]
+
+mod error;
+mod retry;
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use tokio::time::sleep;
+use tonic::{
+	codegen::{CompressionEncoding, InterceptedService},
+	metadata::AsciiMetadataValue,
+	service::Interceptor,
+	transport::{Certificate, Channel, ClientTlsConfig},
+	Request, Response, Status,
+};
+
+pub use self::{error::Error, retry::RetryPolicy};
+use crate::rpc::{
+	links_client::LinksClient, GetRedirectRequest, GetStatisticsRequest, GetVanityRequest,
+	Redirect, RemRedirectRequest, RemRedirectsRequest, RemStatisticsRequest, RemVanityRequest,
+	SetRedirectRequest, SetRedirectsRequest, SetVanitiesRequest, SetVanityRequest,
+	StatisticWithValue, Vanity,
+};
+pub use crate::rpc_wrapper::rpc;
+
+/// A wrapper around the generated tonic code, which allows disabling lints for
+/// the generated code only
+mod rpc_wrapper {
+	tonic::include_proto!("links");
+}
+
+/// The underlying generated tonic client, with authentication already set up
+pub type RawClient = LinksClient<InterceptedService<Channel, Auth>>;
+
+/// The default port of unencrypted gRPC API listeners
+pub const GRPC_DEFAULT_PORT: u16 = 50051;
+/// The default port of TLS-encrypted gRPC API listeners
+pub const GRPCS_DEFAULT_PORT: u16 = 530;
+
+/// Configuration for connecting a [`Client`] to a links server
+#[derive(Clone)]
+pub struct ClientConfig {
+	/// The host name or IP address of the links server
+	pub host: String,
+	/// The port of the links gRPC API, or `None` to use the default port
+	/// depending on whether TLS is used
+	pub port: Option<u16>,
+	/// Whether to use TLS
+	pub tls: bool,
+	/// The PEM-encoded CA certificate(s) to trust instead of the system's root
+	/// certificates when using TLS, or `None` to use the system's roots
+	pub ca_certificate: Option<Vec<u8>>,
+	/// The domain name to verify the server's certificate against when using
+	/// TLS, or `None` to use the host
+	pub tls_domain: Option<String>,
+	/// The API token used to authenticate with the server
+	pub token: String,
+	/// The timeout for individual RPC calls, or `None` for no timeout
+	pub timeout: Option<Duration>,
+	/// How failed read-only calls should be retried
+	pub retry: RetryPolicy,
+}
+
+impl ClientConfig {
+	/// Create a new configuration for connecting to the links server at `host`
+	/// with the API token `token`, without TLS, on the default port, with the
+	/// default [`RetryPolicy`] and no timeout
+	#[must_use]
+	pub fn new(host: impl Into<String>, token: impl Into<String>) -> Self {
+		Self {
+			host: host.into(),
+			port: None,
+			tls: false,
+			ca_certificate: None,
+			tls_domain: None,
+			token: token.into(),
+			timeout: None,
+			retry: RetryPolicy::default(),
+		}
+	}
+
+	/// Set the port of the gRPC API
+	#[must_use]
+	pub const fn port(mut self, port: u16) -> Self {
+		self.port = Some(port);
+		self
+	}
+
+	/// Enable or disable TLS
+	#[must_use]
+	pub const fn tls(mut self, tls: bool) -> Self {
+		self.tls = tls;
+		self
+	}
+
+	/// Trust only the provided PEM-encoded CA certificate(s) instead of the
+	/// system's root certificates, e.g. for servers using self-signed
+	/// certificates. This has no effect unless TLS is enabled.
+	#[must_use]
+	pub fn ca_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+		self.ca_certificate = Some(pem.into());
+		self
+	}
+
+	/// Verify the server's certificate against `domain` instead of the host,
+	/// e.g. when connecting to the server via its IP address. This has no
+	/// effect unless TLS is enabled.
+	#[must_use]
+	pub fn tls_domain(mut self, domain: impl Into<String>) -> Self {
+		self.tls_domain = Some(domain.into());
+		self
+	}
+
+	/// Set the API token used to authenticate with the server
+	#[must_use]
+	pub fn token(mut self, token: impl Into<String>) -> Self {
+		self.token = token.into();
+		self
+	}
+
+	/// Set the timeout for individual RPC calls
+	#[must_use]
+	pub const fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	/// Set the [`RetryPolicy`]
+	#[must_use]
+	pub const fn retry(mut self, retry: RetryPolicy) -> Self {
+		self.retry = retry;
+		self
+	}
+
+	/// Get the URL of the server's gRPC API
+	#[must_use]
+	pub fn url(&self) -> String {
+		let (scheme, default_port) = if self.tls {
+			("https", GRPCS_DEFAULT_PORT)
+		} else {
+			("http", GRPC_DEFAULT_PORT)
+		};
+
+		format!(
+			"{scheme}://{}:{}",
+			self.host,
+			self.port.unwrap_or(default_port)
+		)
+	}
+}
+
+impl Debug for ClientConfig {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("ClientConfig")
+			.field("host", &self.host)
+			.field("port", &self.port)
+			.field("tls", &self.tls)
+			.field(
+				"ca_certificate",
+				&self.ca_certificate.as_ref().map(|_| "[custom]"),
+			)
+			.field("tls_domain", &self.tls_domain)
+			.field("token", &"[redacted]")
+			.field("timeout", &self.timeout)
+			.field("retry", &self.retry)
+			.finish()
+	}
+}
+
+/// A tonic interceptor adding the API token to every request
+#[derive(Clone)]
+pub struct Auth {
+	token: AsciiMetadataValue,
+}
+
+impl Interceptor for Auth {
+	fn call(&mut self, mut req: Request<()>) -> Result<Request<()>, Status> {
+		req.metadata_mut().insert("auth", self.token.clone());
+		Ok(req)
+	}
+}
+
+impl Debug for Auth {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("Auth")
+			.field("token", &"[redacted]")
+			.finish()
+	}
+}
+
+/// A filter for statistics, used when getting or removing them. Fields that
+/// are `None` match any value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatisticsFilter {
+	/// The link (ID or vanity path) of the statistics
+	pub link: Option<String>,
+	/// The type of the statistics (e.g. `host_request`)
+	pub stat_type: Option<String>,
+	/// The data of the statistics
+	pub data: Option<String>,
+	/// The time of the statistics (e.g. `2022-10-01T16:30:00Z`)
+	pub time: Option<String>,
+}
+
+/// A typed client for the links gRPC API
+///
+/// Cloning a client is cheap, and all clones share the same underlying
+/// connection.
+#[derive(Debug, Clone)]
+pub struct Client {
+	inner: RawClient,
+	retry: RetryPolicy,
+}
+
+impl Client {
+	/// Connect to a links server using the provided configuration
+	///
+	/// # Errors
+	/// Returns an error if the configuration is invalid (e.g. the token
+	/// contains control characters) or the connection could not be
+	/// established.
+	pub async fn connect(config: ClientConfig) -> Result<Self, Error> {
+		let mut endpoint = Channel::from_shared(config.url()).map_err(|_| Error::InvalidUrl)?;
+
+		if config.tls {
+			let mut tls_config = match config.ca_certificate {
+				Some(ref pem) => ClientTlsConfig::new().ca_certificate(Certificate::from_pem(pem)),
+				None => ClientTlsConfig::new().with_native_roots(),
+			};
+
+			if let Some(ref domain) = config.tls_domain {
+				tls_config = tls_config.domain_name(domain);
+			}
+
+			endpoint = endpoint.tls_config(tls_config)?;
+		}
+
+		if let Some(timeout) = config.timeout {
+			endpoint = endpoint.timeout(timeout);
+		}
+
+		let channel = endpoint.connect().await?;
+
+		Self::from_channel(channel, &config.token, config.retry)
+	}
+
+	/// Create a client from an already-established [`Channel`], e.g. one using
+	/// a custom connector
+	///
+	/// # Errors
+	/// Returns an error if the token is not a valid gRPC metadata value (i.e.
+	/// it contains control characters)
+	pub fn from_channel(channel: Channel, token: &str, retry: RetryPolicy) -> Result<Self, Error> {
+		let auth = Auth {
+			token: token.parse().map_err(|_| Error::InvalidToken)?,
+		};
+
+		let inner = LinksClient::with_interceptor(channel, auth)
+			.send_compressed(CompressionEncoding::Gzip)
+			.accept_compressed(CompressionEncoding::Gzip);
+
+		Ok(Self { inner, retry })
+	}
+
+	/// Get the underlying generated client, e.g. for access to API calls not
+	/// (yet) wrapped by this client
+	#[must_use]
+	pub fn raw(&self) -> RawClient {
+		self.inner.clone()
+	}
+
+	/// Perform an API call, retrying it according to the `retry` policy
+	///
+	/// Calls which change data on the server are never retried (i.e. use
+	/// [`RetryPolicy::NONE`]), because they may have been applied even if they
+	/// failed (e.g. if the connection was lost before the response arrived),
+	/// and repeating them could then have a different result.
+	async fn call<Req, Res, F, Fut>(
+		&self,
+		retry: RetryPolicy,
+		message: Req,
+		call: F,
+	) -> Result<Res, Error>
+	where
+		Req: Clone + Send,
+		F: Fn(RawClient, Request<Req>) -> Fut + Send + Sync,
+		Fut: Future<Output = Result<Response<Res>, Status>> + Send,
+	{
+		let mut attempt = 0;
+
+		loop {
+			match call(self.inner.clone(), Request::new(message.clone())).await {
+				Ok(res) => return Ok(res.into_inner()),
+				Err(status) if attempt < retry.max_retries && RetryPolicy::retryable(&status) => {
+					sleep(retry.backoff(attempt)).await;
+					attempt += 1;
+				}
+				Err(status) => return Err(status.into()),
+			}
+		}
+	}
+
+	/// Get the destination of the redirect with the ID `id`
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn get_redirect(&self, id: Id) -> Result<Option<Link>, Error> {
+		let res = self
+			.call(
+				self.retry,
+				GetRedirectRequest { id: id.to_string() },
+				|mut c, r| async move { c.get_redirect(r).await },
+			)
+			.await?;
+
+		res.link.as_deref().map(parse_link).transpose()
+	}
+
+	/// Set the destination of the redirect with the ID `id` to `link`,
+	/// returning the old destination, if any
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn set_redirect(&self, id: Id, link: Link) -> Result<Option<Link>, Error> {
+		let req = SetRedirectRequest {
+			id: id.to_string(),
+			link: link.into_string(),
+			expires_at: None,
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.set_redirect(r).await
+			})
+			.await?;
+
+		res.link.as_deref().map(parse_link).transpose()
+	}
+
+	/// Set the destination of the redirect with the ID `id` to `link` until
+	/// `expires_at`, after which the redirect stops working, returning the old
+	/// destination, if any
+	///
+	/// # Errors
+	/// Returns an error if the API call fails (e.g. because `expires_at` is in
+	/// the past or the server's store doesn't support expiring redirects) or
+	/// the server's response is invalid
+	pub async fn set_redirect_with_expiry(
+		&self,
+		id: Id,
+		link: Link,
+		expires_at: SystemTime,
+	) -> Result<Option<Link>, Error> {
+		let expires_at = expires_at
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |time| i64::try_from(time.as_secs()).unwrap_or(i64::MAX));
+
+		let req = SetRedirectRequest {
+			id: id.to_string(),
+			link: link.into_string(),
+			expires_at: Some(expires_at),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.set_redirect(r).await
+			})
+			.await?;
+
+		res.link.as_deref().map(parse_link).transpose()
+	}
+
+	/// Create a new redirect to `link` from a random unused ID, optionally also
+	/// pointing the vanity path `vanity` to it, returning the new ID. Only
+	/// checking whether an ID is unused is retried, creating the redirect and
+	/// vanity path never is.
+	///
+	/// # Errors
+	/// Returns an error if any of the API calls fail or the server's response
+	/// is invalid. If setting the vanity path fails, the redirect will already
+	/// have been created.
+	pub async fn create_link(&self, link: Link, vanity: Option<Normalized>) -> Result<Id, Error> {
+		let id = loop {
+			let id = Id::new();
+
+			if self.get_redirect(id).await?.is_none() {
+				break id;
+			}
+		};
+
+		self.set_redirect(id, link).await?;
+
+		if let Some(vanity) = vanity {
+			self.set_vanity(vanity, id).await?;
+		}
+
+		Ok(id)
+	}
+
+	/// Set the destinations of many redirects at once, returning their old
+	/// destinations, if any, in the same order. This takes only one API call,
+	/// so is much faster than [`set_redirect`][Self::set_redirect] when e.g.
+	/// importing links. The server accepts at most 1000 redirects per call.
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn set_redirects(
+		&self,
+		redirects: Vec<(Id, Link)>,
+	) -> Result<Vec<Option<Link>>, Error> {
+		let req = SetRedirectsRequest {
+			redirects: redirects
+				.into_iter()
+				.map(|(id, link)| Redirect {
+					id: id.to_string(),
+					link: link.into_string(),
+				})
+				.collect(),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.set_redirects(r).await
+			})
+			.await?;
+
+		res.redirects
+			.iter()
+			.map(|res| res.link.as_deref().map(parse_link).transpose())
+			.collect()
+	}
+
+	/// Remove the redirect with the ID `id`, returning its destination, if any
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn rem_redirect(&self, id: Id) -> Result<Option<Link>, Error> {
+		let res = self
+			.call(
+				RetryPolicy::NONE,
+				RemRedirectRequest { id: id.to_string() },
+				|mut c, r| async move { c.rem_redirect(r).await },
+			)
+			.await?;
+
+		res.link.as_deref().map(parse_link).transpose()
+	}
+
+	/// Remove many redirects at once, returning their destinations, if any, in
+	/// the same order. The server accepts at most 1000 IDs per call.
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn rem_redirects(&self, ids: Vec<Id>) -> Result<Vec<Option<Link>>, Error> {
+		let req = RemRedirectsRequest {
+			ids: ids.iter().map(ToString::to_string).collect(),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.rem_redirects(r).await
+			})
+			.await?;
+
+		res.redirects
+			.iter()
+			.map(|res| res.link.as_deref().map(parse_link).transpose())
+			.collect()
+	}
+
+	/// Get the ID that the vanity path `vanity` points to
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn get_vanity(&self, vanity: Normalized) -> Result<Option<Id>, Error> {
+		let req = GetVanityRequest {
+			vanity: vanity.into_string(),
+		};
+
+		let res = self
+			.call(
+				self.retry,
+				req,
+				|mut c, r| async move { c.get_vanity(r).await },
+			)
+			.await?;
+
+		res.id.as_deref().map(parse_id).transpose()
+	}
+
+	/// Point the vanity path `vanity` to the ID `id`, returning the ID it
+	/// pointed to previously, if any
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn set_vanity(&self, vanity: Normalized, id: Id) -> Result<Option<Id>, Error> {
+		let req = SetVanityRequest {
+			vanity: vanity.into_string(),
+			id: id.to_string(),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.set_vanity(r).await
+			})
+			.await?;
+
+		res.id.as_deref().map(parse_id).transpose()
+	}
+
+	/// Set many vanity paths at once, returning the IDs they pointed to, if
+	/// any, in the same order. The server accepts at most 1000 vanity paths
+	/// per call.
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn set_vanities(
+		&self,
+		vanities: Vec<(Normalized, Id)>,
+	) -> Result<Vec<Option<Id>>, Error> {
+		let req = SetVanitiesRequest {
+			vanities: vanities
+				.into_iter()
+				.map(|(vanity, id)| Vanity {
+					vanity: vanity.into_string(),
+					id: id.to_string(),
+				})
+				.collect(),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.set_vanities(r).await
+			})
+			.await?;
+
+		res.vanities
+			.iter()
+			.map(|res| res.id.as_deref().map(parse_id).transpose())
+			.collect()
+	}
+
+	/// Remove the vanity path `vanity`, returning the ID it pointed to, if any
+	///
+	/// # Errors
+	/// Returns an error if the API call fails or the server's response is
+	/// invalid
+	pub async fn rem_vanity(&self, vanity: Normalized) -> Result<Option<Id>, Error> {
+		let req = RemVanityRequest {
+			vanity: vanity.into_string(),
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.rem_vanity(r).await
+			})
+			.await?;
+
+		res.id.as_deref().map(parse_id).transpose()
+	}
+
+	/// Get all statistics matching the filter along with their values
+	///
+	/// # Errors
+	/// Returns an error if the API call fails
+	pub async fn get_statistics(
+		&self,
+		filter: StatisticsFilter,
+	) -> Result<Vec<StatisticWithValue>, Error> {
+		let req = GetStatisticsRequest {
+			link: filter.link,
+			r#type: filter.stat_type,
+			data: filter.data,
+			time: filter.time,
+			cursor: None,
+			limit: None,
+		};
+
+		let res = self
+			.call(self.retry, req, |mut c, r| async move {
+				c.get_statistics(r).await
+			})
+			.await?;
+
+		Ok(res.statistics)
+	}
+
+	/// Get all statistics of the link `link` (an ID or vanity path) along with
+	/// their values
+	///
+	/// # Errors
+	/// Returns an error if the API call fails
+	pub async fn stats_for(&self, link: impl ToString) -> Result<Vec<StatisticWithValue>, Error> {
+		self.get_statistics(StatisticsFilter {
+			link: Some(link.to_string()),
+			..StatisticsFilter::default()
+		})
+		.await
+	}
+
+	/// Remove all statistics matching the filter, returning them along with
+	/// their values (if available)
+	///
+	/// # Errors
+	/// Returns an error if the API call fails
+	pub async fn rem_statistics(
+		&self,
+		filter: StatisticsFilter,
+	) -> Result<Vec<StatisticWithValue>, Error> {
+		let req = RemStatisticsRequest {
+			link: filter.link,
+			r#type: filter.stat_type,
+			data: filter.data,
+			time: filter.time,
+		};
+
+		let res = self
+			.call(RetryPolicy::NONE, req, |mut c, r| async move {
+				c.rem_statistics(r).await
+			})
+			.await?;
+
+		Ok(res.statistics)
+	}
+}
+
+/// Parse a link returned by the server
+fn parse_link(link: &str) -> Result<Link, Error> {
+	Link::new(link).map_err(|_| Error::InvalidResponse("link"))
+}
+
+/// Parse an ID returned by the server
+fn parse_id(id: &str) -> Result<Id, Error> {
+	Id::try_from(id).map_err(|_| Error::InvalidResponse("ID"))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use super::*;
+
+	#[test]
+	fn config_url() {
+		let config = ClientConfig::new("localhost", "abc123");
+		assert_eq!(config.url(), "http://localhost:50051");
+
+		let config = config.tls(true);
+		assert_eq!(config.url(), "https://localhost:530");
+
+		let config = config.port(8443);
+		assert_eq!(config.url(), "https://localhost:8443");
+
+		assert!(!format!("{config:?}").contains("abc123"));
+	}
+
+	#[test]
+	fn config_tls_and_auth() {
+		let config = ClientConfig::new("127.0.0.1", "abc123")
+			.tls(true)
+			.ca_certificate(b"-----BEGIN CERTIFICATE-----".as_slice())
+			.tls_domain("localhost")
+			.token("def456");
+
+		assert_eq!(config.url(), "https://127.0.0.1:530");
+		assert_eq!(config.tls_domain.as_deref(), Some("localhost"));
+		assert_eq!(config.token, "def456");
+
+		let debug = format!("{config:?}");
+		assert!(!debug.contains("def456"));
+		assert!(!debug.contains("BEGIN CERTIFICATE"));
+	}
+
+	#[test]
+	fn auth() {
+		let mut auth = Auth {
+			token: "abc123".parse().unwrap(),
+		};
+
+		let req = auth.call(Request::new(())).unwrap();
+
+		assert_eq!(req.metadata().get("auth").unwrap(), "abc123");
+		assert!(!format!("{auth:?}").contains("abc123"));
+	}
+
+	#[tokio::test]
+	async fn invalid_token() {
+		let channel = Channel::from_static("http://localhost").connect_lazy();
+
+		assert!(matches!(
+			Client::from_channel(channel, "tok\nen", RetryPolicy::default()),
+			Err(Error::InvalidToken)
+		));
+	}
+
+	#[tokio::test]
+	async fn retry() {
+		let channel = Channel::from_static("http://localhost").connect_lazy();
+		let policy = RetryPolicy {
+			max_retries: 2,
+			..RetryPolicy::NONE
+		};
+		let client = &Client::from_channel(channel, "abc123", policy).unwrap();
+
+		let attempts = &AtomicU32::new(0);
+		let call = move |retry| {
+			attempts.store(0, Ordering::Relaxed);
+			client.call(retry, (), move |_, _| async move {
+				attempts.fetch_add(1, Ordering::Relaxed);
+				Err::<Response<()>, _>(Status::unavailable(""))
+			})
+		};
+
+		assert!(call(client.retry).await.is_err());
+		assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+		assert!(call(RetryPolicy::NONE).await.is_err());
+		assert_eq!(attempts.load(Ordering::Relaxed), 1);
+	}
+}
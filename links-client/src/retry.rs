@@ -0,0 +1,83 @@
+//! Retrying of failed API calls
+
+use std::time::Duration;
+
+use tonic::{Code, Status};
+
+/// How and when API calls that failed because of temporary problems are
+/// retried
+///
+/// Only read-only calls failing with the `UNAVAILABLE` or `ABORTED` status
+/// codes are retried. Calls which change data on the server are never retried,
+/// because they may have been applied even if they failed. The delay between
+/// attempts starts at `initial_backoff` and doubles after every attempt, up to
+/// `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// The maximum number of retries after the initial attempt
+	pub max_retries: u32,
+	/// The delay before the first retry
+	pub initial_backoff: Duration,
+	/// The maximum delay between retries
+	pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+	/// A policy that never retries any calls
+	pub const NONE: Self = Self {
+		max_retries: 0,
+		initial_backoff: Duration::ZERO,
+		max_backoff: Duration::ZERO,
+	};
+
+	/// Get the delay before retry number `attempt` (starting at 0)
+	#[must_use]
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		self.initial_backoff
+			.saturating_mul(2_u32.saturating_pow(attempt))
+			.min(self.max_backoff)
+	}
+
+	/// Check whether a call that failed with `status` should be retried
+	#[must_use]
+	pub fn retryable(status: &Status) -> bool {
+		matches!(status.code(), Code::Unavailable | Code::Aborted)
+	}
+}
+
+impl Default for RetryPolicy {
+	/// Retry up to 3 times, starting with a delay of 100 ms, up to 2 s
+	fn default() -> Self {
+		Self {
+			max_retries: 3,
+			initial_backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(2),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backoff() {
+		let policy = RetryPolicy::default();
+
+		assert_eq!(policy.backoff(0), Duration::from_millis(100));
+		assert_eq!(policy.backoff(1), Duration::from_millis(200));
+		assert_eq!(policy.backoff(4), Duration::from_millis(1600));
+		assert_eq!(policy.backoff(5), Duration::from_secs(2));
+		assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(2));
+
+		assert_eq!(RetryPolicy::NONE.backoff(3), Duration::ZERO);
+	}
+
+	#[test]
+	fn retryable() {
+		assert!(RetryPolicy::retryable(&Status::unavailable("")));
+		assert!(RetryPolicy::retryable(&Status::aborted("")));
+		assert!(!RetryPolicy::retryable(&Status::unauthenticated("")));
+		assert!(!RetryPolicy::retryable(&Status::invalid_argument("")));
+	}
+}
@@ -0,0 +1,192 @@
+//! Property-based tests of [`DomainMap`] invariants
+//!
+//! These tests check the behavior of a [`DomainMap`] against a simple model
+//! (a [`BTreeMap`] with a linear search for matching domains) after arbitrary
+//! sequences of operations, as well as the internal invariants of the map (see
+//! [`DomainMap::debug_validate`]). Domains are generated from a small set of
+//! labels, so that collisions, wildcard matches, and overrides are common.
+
+extern crate std;
+
+use alloc::{
+	collections::BTreeMap,
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+
+use proptest::prelude::*;
+
+use crate::{Domain, DomainMap};
+
+/// Labels used to generate domain names
+const LABELS: &[&str] = &["a", "b", "_x", "a-b", "example", "com", "xn--fsqu00a"];
+
+/// An operation modifying a [`DomainMap`]
+#[derive(Debug, Clone)]
+enum Op {
+	Set(Domain, u32),
+	Remove(Domain),
+	Clear,
+}
+
+fn label() -> impl Strategy<Value = &'static str> {
+	prop::sample::select(LABELS)
+}
+
+/// A presented identifier, possibly with a wildcard
+fn presented() -> impl Strategy<Value = Domain> {
+	(any::<bool>(), prop::collection::vec(label(), 1..4)).prop_map(|(wildcard, labels)| {
+		let domain = labels.join(".");
+		let domain = if wildcard {
+			format!("*.{domain}")
+		} else {
+			domain
+		};
+
+		Domain::presented(&domain).expect("generated domain is valid")
+	})
+}
+
+/// A reference identifier
+fn reference() -> impl Strategy<Value = Domain> {
+	prop::collection::vec(label(), 1..5)
+		.prop_map(|labels| Domain::reference(&labels.join(".")).expect("generated domain is valid"))
+}
+
+fn op() -> impl Strategy<Value = Op> {
+	prop_oneof![
+		8 => (presented(), any::<u32>()).prop_map(|(d, v)| Op::Set(d, v)),
+		4 => presented().prop_map(Op::Remove),
+		1 => Just(Op::Clear),
+	]
+}
+
+/// Find the value matching `reference` in the model, preferring exact matches
+/// over wildcard ones
+fn model_get(model: &BTreeMap<Domain, u32>, reference: &Domain) -> Option<u32> {
+	let exact = model
+		.iter()
+		.find(|(k, _)| !k.is_wildcard() && k.labels() == reference.labels());
+	let wildcard = model.iter().find(|(k, _)| {
+		k.is_wildcard() && k.labels() == &reference.labels()[..reference.labels().len() - 1]
+	});
+
+	exact.or(wildcard).map(|(_, v)| *v)
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	value.hash(&mut hasher);
+	hasher.finish()
+}
+
+proptest! {
+	#[test]
+	fn operations_match_model(
+		ops in prop::collection::vec(op(), 0..64),
+		queries in prop::collection::vec(reference(), 0..16),
+	) {
+		let mut map = DomainMap::new();
+		let mut model = BTreeMap::new();
+
+		for op in ops {
+			match op {
+				Op::Set(domain, value) => {
+					prop_assert_eq!(map.set(domain.clone(), value), model.insert(domain, value));
+				}
+				Op::Remove(domain) => {
+					prop_assert_eq!(map.remove(&domain), model.remove(&domain));
+				}
+				Op::Clear => {
+					map.clear();
+					model.clear();
+				}
+			}
+
+			map.debug_validate();
+			prop_assert_eq!(map.len(), model.len());
+			prop_assert_eq!(map.is_empty(), model.is_empty());
+		}
+
+		for (domain, value) in &model {
+			prop_assert_eq!(map.get_eq(domain), Some(value));
+		}
+
+		for query in queries {
+			let expected = model_get(&model, &query);
+			prop_assert_eq!(map.get(&query).copied(), expected);
+			prop_assert_eq!(map.get_mut(&query).map(|v| *v), expected);
+		}
+	}
+
+	#[test]
+	fn get_agrees_with_matches(reference in reference(), presented in presented()) {
+		let mut map = DomainMap::new();
+		map.set(presented.clone(), ());
+
+		prop_assert_eq!(map.get(&reference).is_some(), reference.matches(&presented) == Some(true));
+		prop_assert_eq!(map.get_eq(&reference).is_some(), reference == presented);
+	}
+
+	#[test]
+	fn specific_domain_overrides_wildcard(
+		labels in prop::collection::vec(label(), 1..4),
+		extra in label(),
+		wildcard_first in any::<bool>(),
+	) {
+		let parent = labels.join(".");
+		let specific = Domain::presented(&format!("{extra}.{parent}")).unwrap();
+		let wildcard = Domain::presented(&format!("*.{parent}")).unwrap();
+		let other = if extra == "a" { "b" } else { "a" };
+
+		let mut map = DomainMap::new();
+		if wildcard_first {
+			map.set(wildcard, "wildcard");
+			map.set(specific, "specific");
+		} else {
+			map.set(specific, "specific");
+			map.set(wildcard, "wildcard");
+		}
+
+		map.debug_validate();
+
+		let specific_ref = Domain::reference(&format!("{extra}.{parent}")).unwrap();
+		let other_ref = Domain::reference(&format!("{other}.{parent}")).unwrap();
+		let parent_ref = Domain::reference(&parent).unwrap();
+
+		prop_assert_eq!(map.get(&specific_ref), Some(&"specific"));
+		prop_assert_eq!(map.get(&other_ref), Some(&"wildcard"));
+		prop_assert_eq!(map.get(&parent_ref), None);
+	}
+
+	#[test]
+	fn eq_and_hash_agree(
+		entries in prop::collection::vec((presented(), 0..4_u32), 0..16),
+		other_entries in prop::collection::vec((presented(), 0..4_u32), 0..16),
+	) {
+		let map = entries.into_iter().collect::<DomainMap<_>>();
+		let reversed = map.clone().into_iter().collect::<Vec<_>>().into_iter().rev().collect::<DomainMap<_>>();
+		let other = other_entries.into_iter().collect::<DomainMap<_>>();
+
+		map.debug_validate();
+		reversed.debug_validate();
+		other.debug_validate();
+
+		prop_assert_eq!(&map, &reversed);
+		prop_assert_eq!(hash(&map), hash(&reversed));
+
+		if map == other {
+			prop_assert_eq!(hash(&map), hash(&other));
+		}
+
+		let as_strings = |m: &DomainMap<u32>| {
+			let mut v = m.iter().map(|(k, v)| (k.to_string(), *v)).collect::<Vec<(String, u32)>>();
+			v.sort();
+			v
+		};
+		prop_assert_eq!(map == other, as_strings(&map) == as_strings(&other));
+	}
+}
@@ -10,6 +10,8 @@
 //!
 //! - `serde`: Enable `serde` serialization and deserialization for `DomainMap`
 //!   and `Domain`
+//! - `wasm`: Enable JavaScript bindings (using `wasm-bindgen`) for validating
+//!   domain names in the browser, see the [`wasm`] module
 //!
 //! # Example usage
 //!
@@ -153,10 +155,14 @@ extern crate alloc;
 mod domain;
 mod map;
 
+#[cfg(test)]
+mod invariants;
 #[cfg(feature = "serde")]
 mod serde;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use domain::{Domain, Label, ParseError};
-pub use map::DomainMap;
+pub use map::{DomainMap, OccupiedError, OverwritePolicy};
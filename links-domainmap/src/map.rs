@@ -2,7 +2,8 @@
 
 use alloc::vec::{IntoIter as VecIter, Vec};
 use core::{
-	fmt::Debug,
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
 	hash::{Hash, Hasher},
 	mem,
 	slice::{Iter as SliceIter, IterMut as SliceIterMut},
@@ -154,6 +155,148 @@ impl<T> DomainMap<T> {
 		None
 	}
 
+	/// Set the value for the given domain only if the domain is not already in
+	/// the map, returning an [`OccupiedError`] containing the domain and the
+	/// rejected value otherwise
+	///
+	/// # Errors
+	/// This function returns an [`OccupiedError`] if there is already a value
+	/// for the domain in the map. The map is not modified in that case.
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// # use links_domainmap::{DomainMap, Domain, ParseError};
+	/// # fn main() -> Result<(), ParseError> {
+	/// let mut domainmap = DomainMap::<u32>::new();
+	///
+	/// assert!(domainmap
+	/// 	.try_set(Domain::presented("example.com")?, 1)
+	/// 	.is_ok());
+	///
+	/// let error = domainmap
+	/// 	.try_set(Domain::presented("example.com")?, 2)
+	/// 	.unwrap_err();
+	/// assert_eq!(error.value, 2);
+	///
+	/// assert_eq!(domainmap.get(&Domain::reference("example.com")?), Some(&1));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn try_set(&mut self, domain: Domain, value: T) -> Result<(), OccupiedError<T>> {
+		if self.data.iter().any(|(k, _)| *k == domain) {
+			return Err(OccupiedError { domain, value });
+		}
+
+		self.data.push((domain, value));
+		Ok(())
+	}
+
+	/// Set the value for the given domain, handling domains already in the map
+	/// according to `policy`
+	///
+	/// If the domain was not already in the map, this always adds a new entry
+	/// and returns `Ok(None)`. Otherwise, with [`OverwritePolicy::Replace`] the
+	/// old value is replaced and returned, with [`OverwritePolicy::KeepFirst`]
+	/// the new value is discarded and returned, and with
+	/// [`OverwritePolicy::Error`] an [`OccupiedError`] is returned.
+	///
+	/// # Errors
+	/// This function returns an [`OccupiedError`] if there is already a value
+	/// for the domain in the map and `policy` is [`OverwritePolicy::Error`]
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// # use links_domainmap::{DomainMap, Domain, OverwritePolicy, ParseError};
+	/// # fn main() -> Result<(), ParseError> {
+	/// let mut domainmap = DomainMap::<u32>::new();
+	/// let domain = Domain::presented("example.com")?;
+	///
+	/// let set = |map: &mut DomainMap<u32>, value, policy| {
+	/// 	map.set_with_policy(domain.clone(), value, policy)
+	/// };
+	///
+	/// assert_eq!(set(&mut domainmap, 1, OverwritePolicy::Error), Ok(None));
+	/// assert_eq!(
+	/// 	set(&mut domainmap, 2, OverwritePolicy::KeepFirst),
+	/// 	Ok(Some(2))
+	/// );
+	/// assert_eq!(
+	/// 	set(&mut domainmap, 3, OverwritePolicy::Replace),
+	/// 	Ok(Some(1))
+	/// );
+	/// assert!(set(&mut domainmap, 4, OverwritePolicy::Error).is_err());
+	///
+	/// assert_eq!(domainmap.get(&Domain::reference("example.com")?), Some(&3));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn set_with_policy(
+		&mut self,
+		domain: Domain,
+		value: T,
+		policy: OverwritePolicy,
+	) -> Result<Option<T>, OccupiedError<T>> {
+		match policy {
+			OverwritePolicy::Replace => Ok(self.set(domain, value)),
+			OverwritePolicy::Error => self.try_set(domain, value).map(|()| None),
+			OverwritePolicy::KeepFirst => match self.try_set(domain, value) {
+				Ok(()) => Ok(None),
+				Err(OccupiedError { value, .. }) => Ok(Some(value)),
+			},
+		}
+	}
+
+	/// Set the values for all domains in `iter` in order, handling domains
+	/// already in the map (or repeated in `iter`) according to `policy`
+	///
+	/// This is intended for bulk loading of values, e.g. from configuration,
+	/// where duplicate entries should not silently replace each other.
+	///
+	/// # Errors
+	/// This function returns the first [`OccupiedError`] encountered if
+	/// `policy` is [`OverwritePolicy::Error`]. Entries before the duplicate
+	/// are still added to the map, while entries after it are not.
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// # use links_domainmap::{DomainMap, Domain, OverwritePolicy, ParseError};
+	/// # fn main() -> Result<(), ParseError> {
+	/// let entries = [
+	/// 	(Domain::presented("example.com")?, 1),
+	/// 	(Domain::presented("example.net")?, 2),
+	/// 	(Domain::presented("example.com")?, 3),
+	/// ];
+	///
+	/// let mut domainmap = DomainMap::<u32>::new();
+	/// let error = domainmap
+	/// 	.extend_with_policy(entries.clone(), OverwritePolicy::Error)
+	/// 	.unwrap_err();
+	/// assert_eq!(error.domain, Domain::presented("example.com")?);
+	/// assert_eq!(domainmap.len(), 2);
+	///
+	/// let mut domainmap = DomainMap::<u32>::new();
+	/// assert!(domainmap
+	/// 	.extend_with_policy(entries, OverwritePolicy::KeepFirst)
+	/// 	.is_ok());
+	/// assert_eq!(domainmap.get(&Domain::reference("example.com")?), Some(&1));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn extend_with_policy<I: IntoIterator<Item = (Domain, T)>>(
+		&mut self,
+		iter: I,
+		policy: OverwritePolicy,
+	) -> Result<(), OccupiedError<T>> {
+		for (domain, value) in iter {
+			self.set_with_policy(domain, value, policy)?;
+		}
+
+		Ok(())
+	}
+
 	/// Get the value matching the [reference identifier] domain
 	///
 	/// If there is a value for a wildcard domain matching the given domain, and
@@ -484,6 +627,66 @@ impl<T> DomainMap<T> {
 	pub fn iter_mut(&mut self) -> IterMut<'_, T> {
 		<&mut Self as IntoIterator>::into_iter(self)
 	}
+
+	/// Check the internal invariants of this [`DomainMap`]
+	///
+	/// This is meant to be used in tests (including tests of code using this
+	/// crate) to catch bugs in this crate early. It is slow (quadratic in the
+	/// number of entries), and should not be used outside of tests. The
+	/// checked invariants are:
+	/// - every key has at least one label
+	/// - every label of every key is non-empty and at most 63 bytes long
+	/// - every key (including the wildcard label, if any) is at most 253 bytes
+	///   long
+	/// - no two keys are equal (using `==`)
+	///
+	/// # Panics
+	///
+	/// Panics if any of the invariants are violated
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// # use links_domainmap::{DomainMap, Domain, ParseError};
+	/// # fn main() -> Result<(), ParseError> {
+	/// let mut domainmap = DomainMap::<u32>::new();
+	///
+	/// domainmap.set(Domain::presented("example.com")?, 1);
+	/// domainmap.set(Domain::presented("*.example.com")?, 2);
+	/// domainmap.set(Domain::presented("example.com")?, 3);
+	///
+	/// domainmap.debug_validate();
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn debug_validate(&self) {
+		for (i, (domain, _)) in self.data.iter().enumerate() {
+			assert!(
+				!domain.labels().is_empty(),
+				"key #{i} in the domain map has no labels"
+			);
+
+			for label in domain.labels() {
+				assert!(
+					(1..=63).contains(&label.as_str().len()),
+					"key {domain} has a label with an invalid length"
+				);
+			}
+
+			let len = domain
+				.labels()
+				.iter()
+				.map(|l| l.as_str().len() + 1)
+				.sum::<usize>()
+				- 1 + if domain.is_wildcard() { 2 } else { 0 };
+			assert!(len <= 253, "key {domain} is too long ({len} bytes)");
+
+			assert!(
+				self.data[..i].iter().all(|(other, _)| other != domain),
+				"key {domain} is in the domain map more than once"
+			);
+		}
+	}
 }
 
 impl<T> Default for DomainMap<T> {
@@ -606,9 +809,43 @@ impl<'a, T: 'a> Iterator for IterMut<'a, T> {
 	}
 }
 
+/// What to do when setting a value for a domain which is already in a
+/// [`DomainMap`], see [`DomainMap::set_with_policy`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OverwritePolicy {
+	/// Return an [`OccupiedError`], leaving the existing value in the map
+	Error,
+	/// Replace the existing value with the new one, like [`DomainMap::set`]
+	#[default]
+	Replace,
+	/// Keep the existing value, discarding the new one
+	KeepFirst,
+}
+
+/// The error returned when trying to set a value for a domain which is already
+/// in a [`DomainMap`], containing the domain and the rejected value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccupiedError<T> {
+	/// The domain which is already in the map
+	pub domain: Domain,
+	/// The value which was not added to the map
+	pub value: T,
+}
+
+impl<T> Display for OccupiedError<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.write_fmt(format_args!(
+			"the domain {} is already in the map",
+			self.domain
+		))
+	}
+}
+
+impl<T: Debug> Error for OccupiedError<T> {}
+
 #[cfg(test)]
 mod tests {
-	use alloc::format;
+	use alloc::{format, string::ToString, vec};
 
 	use super::*;
 
@@ -759,6 +996,116 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn domainmap_try_set() {
+		let mut map = DomainMap::<u32>::new();
+
+		assert_eq!(
+			map.try_set(Domain::presented("example.com").unwrap(), 1),
+			Ok(())
+		);
+		assert_eq!(
+			map.try_set(Domain::presented("*.example.com").unwrap(), 10),
+			Ok(())
+		);
+
+		let error = map
+			.try_set(Domain::presented("example.com").unwrap(), 2)
+			.unwrap_err();
+		assert_eq!(error, OccupiedError {
+			domain: Domain::presented("example.com").unwrap(),
+			value: 2
+		});
+		assert_eq!(
+			error.to_string(),
+			"the domain example.com is already in the map"
+		);
+
+		assert_eq!(
+			map.get(&Domain::reference("example.com").unwrap()),
+			Some(&1)
+		);
+		assert_eq!(map.len(), 2);
+		map.debug_validate();
+	}
+
+	#[test]
+	fn domainmap_set_with_policy() {
+		let mut map = DomainMap::<u32>::new();
+		let domain = Domain::presented("*.example.com").unwrap();
+		let reference = Domain::reference("foo.example.com").unwrap();
+
+		for policy in [
+			OverwritePolicy::Error,
+			OverwritePolicy::Replace,
+			OverwritePolicy::KeepFirst,
+		] {
+			map.clear();
+			assert_eq!(map.set_with_policy(domain.clone(), 1, policy), Ok(None));
+			assert_eq!(map.get(&reference), Some(&1));
+		}
+
+		assert_eq!(
+			map.set_with_policy(domain.clone(), 2, OverwritePolicy::KeepFirst),
+			Ok(Some(2))
+		);
+		assert_eq!(map.get(&reference), Some(&1));
+
+		assert_eq!(
+			map.set_with_policy(domain.clone(), 3, OverwritePolicy::Replace),
+			Ok(Some(1))
+		);
+		assert_eq!(map.get(&reference), Some(&3));
+
+		assert_eq!(
+			map.set_with_policy(domain.clone(), 4, OverwritePolicy::Error),
+			Err(OccupiedError { domain, value: 4 })
+		);
+		assert_eq!(map.get(&reference), Some(&3));
+
+		assert_eq!(map.len(), 1);
+		map.debug_validate();
+	}
+
+	#[test]
+	fn domainmap_extend_with_policy() {
+		let entries = vec![
+			(Domain::presented("example.com").unwrap(), 1),
+			(Domain::presented("example.net").unwrap(), 2),
+			(Domain::presented("example.com").unwrap(), 3),
+			(Domain::presented("example.org").unwrap(), 4),
+		];
+		let reference = Domain::reference("example.com").unwrap();
+
+		let mut map = DomainMap::new();
+		assert_eq!(
+			map.extend_with_policy(entries.clone(), OverwritePolicy::Error),
+			Err(OccupiedError {
+				domain: Domain::presented("example.com").unwrap(),
+				value: 3
+			})
+		);
+		assert_eq!(map.get(&reference), Some(&1));
+		assert_eq!(map.len(), 2);
+
+		let mut map = DomainMap::new();
+		assert_eq!(
+			map.extend_with_policy(entries.clone(), OverwritePolicy::KeepFirst),
+			Ok(())
+		);
+		assert_eq!(map.get(&reference), Some(&1));
+		assert_eq!(map.len(), 3);
+
+		let mut map = DomainMap::new();
+		assert_eq!(
+			map.extend_with_policy(entries, OverwritePolicy::Replace),
+			Ok(())
+		);
+		assert_eq!(map.get(&reference), Some(&3));
+		assert_eq!(map.len(), 3);
+		map.debug_validate();
+	}
+
 	#[test]
 	fn domainmap_remove() {
 		let mut map = DomainMap::<u32>::new();
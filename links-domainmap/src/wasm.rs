@@ -0,0 +1,88 @@
+//! JavaScript bindings for validating domain names
+//!
+//! When compiled to `wasm32-unknown-unknown` with the `wasm` feature enabled
+//! (e.g. using `wasm-pack`), these functions can be called from JavaScript, so
+//! that web front-ends can check domain names using exactly the same rules as
+//! the links server. Errors are thrown as JavaScript `Error`s with a
+//! human-readable message.
+
+use alloc::string::{String, ToString};
+
+use wasm_bindgen::prelude::*;
+
+use crate::Domain;
+
+/// Check whether a domain name is valid as a configured domain (a "[presented
+/// identifier]", which may be a wildcard), returning its normalized form
+///
+/// # Errors
+/// This returns (throws in JavaScript) an error if the domain name is invalid,
+/// see [`Domain::presented`] for details.
+///
+/// [presented identifier]: https://www.rfc-editor.org/rfc/rfc6125#page-11
+#[wasm_bindgen(js_name = validateDomain)]
+pub fn validate_domain(domain: &str) -> Result<String, JsError> {
+	Ok(Domain::presented(domain)?.to_string())
+}
+
+/// Check whether a domain name is valid as a host name being looked up (a
+/// "[reference identifier]", which may not be a wildcard), returning its
+/// normalized form
+///
+/// # Errors
+/// This returns (throws in JavaScript) an error if the domain name is invalid,
+/// see [`Domain::reference`] for details.
+///
+/// [reference identifier]: https://www.rfc-editor.org/rfc/rfc6125#page-12
+#[wasm_bindgen(js_name = validateHostname)]
+pub fn validate_hostname(hostname: &str) -> Result<String, JsError> {
+	Ok(Domain::reference(hostname)?.to_string())
+}
+
+/// Check whether a domain name is valid as a configured domain, see
+/// [`validate_domain`]
+#[must_use]
+#[wasm_bindgen(js_name = isValidDomain)]
+pub fn is_valid_domain(domain: &str) -> bool {
+	Domain::presented(domain).is_ok()
+}
+
+/// Check whether a host name matches a configured (possibly wildcard) domain
+///
+/// Returns `false` if either of the domain names is invalid.
+#[must_use]
+#[wasm_bindgen(js_name = domainMatches)]
+pub fn domain_matches(hostname: &str, domain: &str) -> bool {
+	let (Ok(hostname), Ok(domain)) = (Domain::reference(hostname), Domain::presented(domain))
+	else {
+		return false;
+	};
+
+	hostname.matches(&domain).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bindings() {
+		assert_eq!(validate_domain("*.EXAMPLE.com.").unwrap(), "*.example.com");
+		assert_eq!(
+			validate_domain("êxämpłé.ćóm").unwrap(),
+			"xn--xmp-qla1ab43d.xn--m-uga3d"
+		);
+		assert_eq!(
+			validate_hostname("www.Example.com").unwrap(),
+			"www.example.com"
+		);
+		assert!(is_valid_domain("*.example.com"));
+		assert!(!is_valid_domain("*.*.example.com"));
+		assert!(!is_valid_domain("-example.com"));
+
+		assert!(domain_matches("www.example.com", "*.example.com"));
+		assert!(domain_matches("example.com", "example.com"));
+		assert!(!domain_matches("a.b.example.com", "*.example.com"));
+		assert!(!domain_matches("*.example.com", "*.example.com"));
+	}
+}
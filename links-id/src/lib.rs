@@ -63,6 +63,20 @@ pub const REVERSE_CHARSET: [u8; 69] = [
 /// `REVERSE_CHARSET_BASE_38_OFFSET` from its value (`ascii - this`).
 pub const REVERSE_CHARSET_BASE_38_OFFSET: usize = 54;
 
+/// Lookup table from an ASCII byte to its base 38 value, with `u8::MAX` for
+/// bytes which are not in the base 38 character set.
+const BASE_38_DECODE: [u8; 128] = {
+	let mut table = [u8::MAX; 128];
+	let mut i = 0;
+
+	while i < BASE_38_CHARSET.len() {
+		table[BASE_38_CHARSET[i] as usize] = i as u8;
+		i += 1;
+	}
+
+	table
+};
+
 /// The 40 bit ID used to identify links in links.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(try_from = "&str", into = "String")]
@@ -100,6 +114,57 @@ impl Id {
 		RE.is_match(id) && id <= MAX.as_str()
 	}
 
+	/// Parse the usual string representation of an Id from its ASCII bytes.
+	/// Returns `None` if the input is not a valid Id, i.e. exactly when
+	/// [`Id::is_valid`] would return `false`.
+	///
+	/// Unlike [`Id::is_valid`] and the `TryFrom<&str>` implementation, this
+	/// does not use regular expressions or allocate, so it is suitable for
+	/// performance-sensitive code like parsing request paths.
+	#[must_use]
+	pub const fn from_ascii(bytes: &[u8]) -> Option<Self> {
+		if bytes.len() != Self::CHARS || !bytes[0].is_ascii_digit() {
+			return None;
+		}
+
+		let mut num = (bytes[0] - b'0') as u64;
+		let mut i = 1;
+
+		while i < Self::CHARS {
+			if bytes[i] >= 128 || BASE_38_DECODE[bytes[i] as usize] == u8::MAX {
+				return None;
+			}
+
+			num = num * 38 + BASE_38_DECODE[bytes[i] as usize] as u64;
+			i += 1;
+		}
+
+		if num > Self::MAX {
+			return None;
+		}
+
+		let num = num.to_be_bytes();
+		Some(Self([num[3], num[4], num[5], num[6], num[7]]))
+	}
+
+	/// Get the usual string representation of this Id as ASCII bytes, without
+	/// allocating.
+	#[must_use]
+	pub fn to_ascii(self) -> [u8; Self::CHARS] {
+		let mut buf = [0u8; Self::CHARS];
+		let num = self.to_u64();
+
+		buf[0] = BASE_10_CHARSET[((num / 38u64.pow(Self::CHARS as u32 - 1)) % 10) as usize] as u8;
+
+		for (i, char) in buf.iter_mut().enumerate().skip(1) {
+			let index = (num / 38u64.pow((Self::CHARS - 1 - i) as u32)) % 38;
+
+			*char = BASE_38_CHARSET[index as usize] as u8;
+		}
+
+		buf
+	}
+
 	/// Create a new random Id.
 	#[must_use]
 	pub fn new() -> Self {
@@ -131,19 +196,10 @@ impl Debug for Id {
 
 impl Display for Id {
 	fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), FmtError> {
-		let mut buf = ['\0'; Self::CHARS];
-		let num = self.to_u64();
-
-		buf[0] = BASE_10_CHARSET[((num / 38u64.pow(Self::CHARS as u32 - 1)) % 10) as usize];
+		let buf = self.to_ascii();
 
-		for (i, char) in buf.iter_mut().enumerate().skip(1) {
-			let index = (num / 38u64.pow((Self::CHARS - 1 - i) as u32)) % 38;
-
-			*char = BASE_38_CHARSET[index as usize];
-		}
-
-		let buf: String = buf.iter().collect();
-		formatter.write_str(&buf)
+		// Panic: all characters in the Id charsets are ASCII, which is valid UTF-8
+		formatter.write_str(std::str::from_utf8(&buf).unwrap())
 	}
 }
 
@@ -299,6 +355,46 @@ mod tests {
 		assert!(!Id::is_valid("an invalid id")); // not even close
 	}
 
+	#[test]
+	fn from_ascii() {
+		for id in [
+			"1wqLjdjd",
+			"06789BCD",
+			"06666666",
+			"9dDbKpJP",
+			"𝟵𝚍𝐃𝐛𝓚𝐩𝐉𝑷",
+			"9dDbKpJQ",
+			"00000000",
+			"xHJ6CH79",
+			"06789BC",
+			"06789BCDD",
+			"9pqrtwxz",
+			"",
+			"an invalid id",
+			"1HJ6CH7\u{80}",
+		] {
+			assert_eq!(Id::from_ascii(id.as_bytes()), Id::try_from(id).ok(), "{id}");
+			assert_eq!(Id::from_ascii(id.as_bytes()).is_some(), Id::is_valid(id));
+		}
+
+		for _ in 0..1000 {
+			let id = Id::new();
+			assert_eq!(Id::from_ascii(id.to_string().as_bytes()), Some(id));
+		}
+
+		assert_eq!(Id::from_ascii(b"06666666"), Some(Id([0; 5])));
+		assert_eq!(Id::from_ascii(b"9dDbKpJP"), Some(Id([0xff; 5])));
+	}
+
+	#[test]
+	fn to_ascii() {
+		assert_eq!(&Id([0x21, 0x22, 0x23, 0x24, 0x25]).to_ascii(), b"1HJ6CH79");
+		assert_eq!(&Id([0x00, 0x22, 0x44, 0x66, 0x88]).to_ascii(), b"06FHjHkx");
+
+		let id = Id::new();
+		assert_eq!(id.to_ascii(), id.to_string().as_bytes());
+	}
+
 	#[test]
 	fn new() {
 		assert_ne!(Id::new(), Id::new());
@@ -9,6 +9,13 @@
 //! [`Link`]s are normalized (in the URI sense) `http`/`https` URLs used as
 //! redirect destinations by links.
 //!
+//! # Cargo features
+//!
+//! - `fred`: Enable conversion from Redis values using `fred` (not available
+//!   when compiling for `wasm32` targets)
+//! - `wasm`: Enable JavaScript bindings (using `wasm-bindgen`) for validating
+//!   vanity paths and links in the browser, see the [`wasm`] module
+//!
 //! [NFKC]: https://www.unicode.org/reports/tr15/#Norm_Forms
 
 use std::{
@@ -17,7 +24,7 @@ use std::{
 	str::FromStr,
 };
 
-#[cfg(feature = "fred")]
+#[cfg(all(feature = "fred", not(target_arch = "wasm32")))]
 use fred::{
 	error::{RedisError, RedisErrorKind},
 	types::{FromRedis, RedisValue},
@@ -26,6 +33,9 @@ use serde::{Deserialize, Serialize};
 use unicode_normalization::UnicodeNormalization;
 use uriparse::{Scheme, URIReference};
 
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
 /// A normalized string used for vanity paths.
 ///
 /// Allows for storing and comparing vanity paths in a normalized,
@@ -54,6 +64,12 @@ impl Normalized {
 	pub fn into_string(self) -> String {
 		self.0
 	}
+
+	/// Returns a reference to the string this `Normalized` wraps.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
 }
 
 impl Display for Normalized {
@@ -70,7 +86,7 @@ impl FromStr for Normalized {
 	}
 }
 
-#[cfg(feature = "fred")]
+#[cfg(all(feature = "fred", not(target_arch = "wasm32")))]
 impl FromRedis for Normalized {
 	fn from_value(value: RedisValue) -> Result<Self, RedisError> {
 		value.into_string().map_or_else(
@@ -191,6 +207,12 @@ impl Link {
 	pub fn into_string(self) -> String {
 		self.0
 	}
+
+	/// Returns a reference to the string this `Link` wraps.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
 }
 
 impl Display for Link {
@@ -207,7 +229,7 @@ impl FromStr for Link {
 	}
 }
 
-#[cfg(feature = "fred")]
+#[cfg(all(feature = "fred", not(target_arch = "wasm32")))]
 impl FromRedis for Link {
 	fn from_value(value: RedisValue) -> Result<Self, RedisError> {
 		match value {
@@ -351,7 +373,7 @@ mod tests {
 	}
 
 	#[test]
-	#[cfg(feature = "fred")]
+	#[cfg(all(feature = "fred", not(target_arch = "wasm32")))]
 	fn normalized_from_redis() {
 		assert_eq!(
 			Normalized::from_value(RedisValue::from_static_str("BiG bIrD"))
@@ -528,7 +550,7 @@ mod tests {
 	}
 
 	#[test]
-	#[cfg(feature = "fred")]
+	#[cfg(all(feature = "fred", not(target_arch = "wasm32")))]
 	fn link_from_redis() {
 		assert_eq!(
 			Link::from_value(RedisValue::from_static_str(
@@ -0,0 +1,136 @@
+//! Benchmarking of the redirector's request handling
+
+use std::{collections::HashMap, hint::black_box, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hyper::Request;
+use links::{
+	config::{ExpiredStatus, Hsts, PathNormalization, RedirectStatus, Redirector, ReferrerPolicy},
+	options::Cors,
+	ratelimit::RedirectRateLimits,
+	redirector::redirector,
+	stats::{ExtraStatisticInfo, StatisticCategories},
+	store::{BackendType, Store},
+};
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use tokio::runtime::Runtime;
+
+/// Sample IDs, including invalid ones (which are treated as vanity paths)
+const IDS: [&str; 6] = [
+	"1wqLjdjd",
+	"06789BCD",
+	"9dDbKpJP",
+	"9dDbKpJQ",
+	"example",
+	"an invalid id",
+];
+
+/// Get a redirector config with all default headers enabled, but without
+/// statistics (which are collected in the background)
+fn config() -> Arc<Redirector> {
+	Arc::new(Redirector {
+		hsts: Hsts::default(),
+		hsts_header: Hsts::default().header_value(),
+		send_alt_svc: true,
+		send_server: true,
+		send_csp: true,
+		referrer_policy: ReferrerPolicy::default(),
+		extra_headers: Arc::new([]),
+		cors: Arc::new(Cors::default()),
+		path_passthrough: false,
+		path_normalization: PathNormalization::default(),
+		expired_status: ExpiredStatus::default(),
+		preview_suffix: None,
+		robots_txt: Arc::from(""),
+		reserved_paths: Arc::from([]),
+		favicon: None,
+		default_destination: None,
+		search_fallback: None,
+		interstitial_delay: 3,
+		interstitial_template: None,
+		access_log: None,
+		geoip: None,
+		blocklist: None,
+		loop_detection: None,
+		rate_limits: RedirectRateLimits::default(),
+		statistics: StatisticCategories::NONE,
+		https_redirect: false,
+		redirect_status: RedirectStatus::default(),
+		namespace: None,
+	})
+}
+
+/// Get a memory store with one redirect (`/1wqLjdjd`) and one vanity path
+/// (`/example`)
+fn store(rt: &Runtime) -> Store {
+	rt.block_on(async {
+		let store = Store::new(BackendType::Memory, &HashMap::new())
+			.await
+			.unwrap();
+		let id = Id::try_from("1wqLjdjd").unwrap();
+
+		store
+			.set_redirect(id, Link::new("https://example.com/").unwrap())
+			.await
+			.unwrap();
+		store
+			.set_vanity(Normalized::new("example"), id)
+			.await
+			.unwrap();
+
+		store
+	})
+}
+
+pub fn id_parsing(c: &mut Criterion) {
+	let mut group = c.benchmark_group("Id parsing");
+	group.throughput(Throughput::Elements(IDS.len() as u64));
+
+	group.bench_function("Id::is_valid + Id::try_from", |b| {
+		b.iter(|| {
+			for id in IDS {
+				let id = black_box(id);
+				let _ = black_box(Id::is_valid(id).then(|| Id::try_from(id)));
+			}
+		})
+	});
+
+	group.bench_function("Id::from_ascii", |b| {
+		b.iter(|| {
+			for id in IDS {
+				let _ = black_box(Id::from_ascii(black_box(id).as_bytes()));
+			}
+		})
+	});
+
+	group.finish();
+}
+
+pub fn redirect(c: &mut Criterion) {
+	let rt = Runtime::new().unwrap();
+	let store = store(&rt);
+	let config = config();
+
+	let mut group = c.benchmark_group("redirector");
+
+	for path in ["/1wqLjdjd", "/example", "/06789BCD", "/not-found"] {
+		group.bench_with_input(BenchmarkId::from_parameter(path), path, |b, path| {
+			b.to_async(&rt).iter(|| {
+				let req = Request::get(path).body(()).unwrap();
+
+				redirector(
+					req,
+					store.clone(),
+					Arc::clone(&config),
+					ExtraStatisticInfo::default(),
+				)
+			});
+		});
+	}
+
+	group.finish();
+}
+
+criterion_group!(benches, id_parsing, redirect);
+criterion_main!(benches);
@@ -19,23 +19,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 		.build_server(true)
 		.compile_well_known_types(true)
 		.compile_protos(&["../proto/links.proto"], &["../proto"])?;
+	wrap_generated("links")?;
 
-	// Disable pedantic clippy lints in the generated file (if anyone has a
-	// more elegant solution to this, please open an issue)
-	let out_dir = env::var_os("OUT_DIR").unwrap();
-	let proto_path = Path::new(&out_dir).join("links.rs");
-	let proto = fs::read_to_string(&proto_path)?;
-	fs::write(
-		&proto_path,
-		"#[allow(clippy::pedantic, clippy::nursery, missing_docs, clippy::redundant_async_block, \
-		 reason = \"generated code\")]\npub mod rpc {\n"
-			.to_string()
-			+ &proto + "}\n",
-	)?;
+	// Compile the etcd API subset used by the etcd store backend
+	tonic_build::configure()
+		.build_client(true)
+		.build_server(false)
+		.compile_protos(&["../proto/etcd.proto"], &["../proto"])?;
+	wrap_generated("etcdserverpb")?;
 
 	// Include and minify html pages
 	minify("not-found", PathBuf::from("../misc/not-found.html"));
+	minify("expired", PathBuf::from("../misc/expired.html"));
 	minify("redirect", PathBuf::from("../misc/redirect.html"));
+	minify("preview", PathBuf::from("../misc/preview.html"));
+	minify("interstitial", PathBuf::from("../misc/interstitial.html"));
 	minify("bad-request", PathBuf::from("../misc/bad-request.html"));
 	minify(
 		"https-redirect",
@@ -45,17 +43,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// Generate hashes for the CSP header
 	hash_tags("style", [
 		"not-found",
+		"expired",
 		"redirect",
+		"preview",
+		"interstitial",
 		"bad-request",
 		"https-redirect",
 	]);
 
 	println!("cargo::rerun-if-changed=../proto/links.proto");
+	println!("cargo::rerun-if-changed=../proto/etcd.proto");
 	println!("cargo::rerun-if-changed=../proto/*");
 
 	Ok(())
 }
 
+/// Wrap the generated protobuf code of the `package` in a `rpc` module, to
+/// disable pedantic clippy lints in the generated file (if anyone has a more
+/// elegant solution to this, please open an issue)
+fn wrap_generated(package: &str) -> Result<(), Box<dyn std::error::Error>> {
+	let out_dir = env::var_os("OUT_DIR").unwrap();
+	let proto_path = Path::new(&out_dir).join(format!("{package}.rs"));
+	let proto = fs::read_to_string(&proto_path)?;
+	fs::write(
+		&proto_path,
+		"#[allow(clippy::pedantic, clippy::nursery, missing_docs, clippy::redundant_async_block, \
+		 clippy::enum_variant_names, reason = \"generated code\")]\npub mod rpc {\n"
+			.to_string()
+			+ &proto + "}\n",
+	)?;
+
+	Ok(())
+}
+
 /// Minify the html file in `path`. The resulting file will be output into the
 /// `OUT_DIR` directory with the name `name.html`
 fn minify(name: &str, path: PathBuf) {
@@ -0,0 +1,304 @@
+//! Structured access log of redirects
+//!
+//! Separately from the tracing logs, the redirector can write an access log
+//! with one JSON object per line for every request it processes, configured
+//! using the `access_log` configuration option. Each line contains the time of
+//! the request (in RFC 3339 format), its method, host, and path, the resolved
+//! link ID and vanity path (if any), the response status code, the time taken
+//! to process the request (in nanoseconds), and depending on the `client_ip`
+//! setting, the client's IP address (either in `full`, `anonymized` to its
+//! /24 or /48 network, or not at all, which is the default).
+//!
+//! The log is written to the file at `path`, or to stdout if `path` is `-`.
+//! Log files can be rotated (`minutely`, `hourly`, or `daily`), in which case
+//! the date and time are appended to the file name, and only the `max_files`
+//! most recent files are kept (unless that is `0`). Lines are written in the
+//! background, and are dropped if they can't be written fast enough.
+//!
+//! # Example
+//! ```json
+//! {"time":"2025-01-01T00:00:00.123456789Z","method":"GET","host":"example.com","path":"/example","id":"9dDbKpJP","vanity":"example","status":302,"time_ns":52341}
+//! ```
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	io::{self, Write},
+	net::IpAddr,
+	path::Path,
+	sync::Arc,
+};
+
+use links_id::Id;
+use links_normalized::Normalized;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::{error, info};
+use tracing_appender::{
+	non_blocking::{NonBlocking, WorkerGuard},
+	rolling::{RollingFileAppender, Rotation},
+};
+
+/// The most recently opened access log, which is reused until its settings
+/// change
+static OPENED: Mutex<Option<Arc<AccessLogger>>> = Mutex::new(None);
+
+/// Settings for the access log, see the [module-level documentation][self]
+/// for details.
+///
+/// The access log is disabled if `path` is empty (the default).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessLog {
+	/// The path of the log file, `-` for stdout, or empty to disable the
+	/// access log
+	pub path: String,
+	/// How often the log file is rotated
+	pub rotation: LogRotation,
+	/// The maximum number of rotated log files kept, or `0` to keep all of them
+	pub max_files: usize,
+	/// Whether and how the client's IP address is logged
+	pub client_ip: ClientIpLogging,
+}
+
+/// How often the access log file is rotated. In configuration, this is
+/// represented in lowercase, e.g. `daily`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+	/// The log file is never rotated
+	#[default]
+	Never,
+	/// A new log file is started every minute
+	Minutely,
+	/// A new log file is started every hour
+	Hourly,
+	/// A new log file is started every day
+	Daily,
+}
+
+impl From<LogRotation> for Rotation {
+	fn from(rotation: LogRotation) -> Self {
+		match rotation {
+			LogRotation::Never => Self::NEVER,
+			LogRotation::Minutely => Self::MINUTELY,
+			LogRotation::Hourly => Self::HOURLY,
+			LogRotation::Daily => Self::DAILY,
+		}
+	}
+}
+
+/// Whether and how the client's IP address is included in the access log. In
+/// configuration, this is represented in lowercase, e.g. `anonymized`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientIpLogging {
+	/// The client's IP address is not logged
+	#[default]
+	None,
+	/// Only the client's network is logged, i.e. the IP address with all but
+	/// the first 24 (IPv4) or 48 (IPv6) bits set to zero
+	Anonymized,
+	/// The client's full IP address is logged
+	Full,
+}
+
+impl ClientIpLogging {
+	/// Get the client IP address `ip` as it should be logged, if at all
+	#[must_use]
+	pub fn apply(self, ip: IpAddr) -> Option<IpAddr> {
+		match (self, ip) {
+			(Self::None, _) => None,
+			(Self::Full, ip) => Some(ip),
+			(Self::Anonymized, IpAddr::V4(ip)) => {
+				Some(IpAddr::from(u32::from(ip) & 0xffff_ff00_u32))
+			}
+			(Self::Anonymized, IpAddr::V6(ip)) => Some(IpAddr::from(
+				u128::from(ip) & 0xffff_ffff_ffff_0000_0000_0000_0000_0000_u128,
+			)),
+		}
+	}
+}
+
+/// A single line of the access log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessLogEntry<'a> {
+	/// The time at which the request was received, in RFC 3339 format
+	pub time: String,
+	/// The request method
+	pub method: &'a str,
+	/// The host of the request, if known
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub host: Option<&'a str>,
+	/// The request path
+	pub path: &'a str,
+	/// The ID of the link the request resolved to, if any
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub id: Option<Id>,
+	/// The vanity path the request resolved to, if any
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub vanity: Option<&'a Normalized>,
+	/// The status code of the response
+	pub status: u16,
+	/// The time taken to process the request in nanoseconds
+	pub time_ns: u64,
+	/// The client's IP address, if it is known and should be logged
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub client_ip: Option<IpAddr>,
+}
+
+impl AccessLogEntry<'_> {
+	/// Get the current time in the format used in access log entries
+	#[must_use]
+	pub fn now() -> String {
+		OffsetDateTime::now_utc()
+			.format(&Rfc3339)
+			.unwrap_or_default()
+	}
+}
+
+/// An open access log, which lines can be written to
+pub struct AccessLogger {
+	/// The settings this access log was opened with
+	settings: AccessLog,
+	/// The background writer of log lines
+	writer: NonBlocking,
+	/// The guard flushing the background writer when this log is dropped
+	_guard: WorkerGuard,
+}
+
+impl AccessLogger {
+	/// Open the access log according to the `settings`, or reuse the
+	/// previously opened one if its settings haven't changed since. Returns
+	/// `None` if the access log is disabled or can not be opened.
+	#[must_use]
+	pub fn open(settings: &AccessLog) -> Option<Arc<Self>> {
+		let mut opened = OPENED.lock();
+
+		if settings.path.is_empty() {
+			// Close (and flush) the previously opened log, if any
+			*opened = None;
+			return None;
+		}
+
+		if let Some(logger) = opened.as_ref().filter(|log| &log.settings == settings) {
+			return Some(Arc::clone(logger));
+		}
+
+		let (writer, guard) = if settings.path == "-" {
+			tracing_appender::non_blocking(io::stdout())
+		} else {
+			let path = Path::new(&settings.path);
+			let file_name = path
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+				.unwrap_or_default();
+			let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+			let mut builder = RollingFileAppender::builder()
+				.rotation(settings.rotation.into())
+				.filename_prefix(file_name);
+
+			if settings.max_files > 0 {
+				builder = builder.max_log_files(settings.max_files);
+			}
+
+			match builder.build(directory) {
+				Ok(appender) => tracing_appender::non_blocking(appender),
+				Err(err) => {
+					error!(%err, path = %settings.path, "Could not open the access log, it is disabled");
+					return None;
+				}
+			}
+		};
+
+		info!(path = %settings.path, "Opened the access log");
+
+		let logger = Arc::new(Self {
+			settings: settings.clone(),
+			writer,
+			_guard: guard,
+		});
+
+		*opened = Some(Arc::clone(&logger));
+		Some(logger)
+	}
+
+	/// Write the `entry` to the access log, with its client IP address
+	/// processed according to the configured `client_ip` setting
+	pub fn log(&self, mut entry: AccessLogEntry<'_>) {
+		entry.client_ip = entry
+			.client_ip
+			.and_then(|ip| self.settings.client_ip.apply(ip));
+
+		let Ok(mut line) = serde_json::to_vec(&entry) else {
+			return;
+		};
+
+		line.push(b'\n');
+		let _ = self.writer.clone().write_all(&line);
+	}
+}
+
+impl Debug for AccessLogger {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("AccessLogger")
+			.field("settings", &self.settings)
+			.finish_non_exhaustive()
+	}
+}
+
+impl PartialEq for AccessLogger {
+	fn eq(&self, other: &Self) -> bool {
+		self.settings == other.settings
+	}
+}
+
+impl Eq for AccessLogger {}
+
+#[cfg(test)]
+mod tests {
+	use std::net::{Ipv4Addr, Ipv6Addr};
+
+	use super::*;
+
+	#[test]
+	fn client_ips() {
+		let v4 = IpAddr::from(Ipv4Addr::new(192, 0, 2, 123));
+		let v6 = IpAddr::from(Ipv6Addr::new(0x2001, 0xdb8, 1, 2, 3, 4, 5, 6));
+
+		assert_eq!(ClientIpLogging::None.apply(v4), None);
+		assert_eq!(ClientIpLogging::Full.apply(v6), Some(v6));
+		assert_eq!(
+			ClientIpLogging::Anonymized.apply(v4),
+			Some(IpAddr::from(Ipv4Addr::new(192, 0, 2, 0)))
+		);
+		assert_eq!(
+			ClientIpLogging::Anonymized.apply(v6),
+			Some(IpAddr::from(Ipv6Addr::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 0)))
+		);
+	}
+
+	#[test]
+	fn entries() {
+		let vanity = Normalized::new("example");
+		let entry = AccessLogEntry {
+			time: "2025-01-01T00:00:00Z".to_string(),
+			method: "GET",
+			host: Some("example.com"),
+			path: "/example",
+			id: Some(Id::from([0x73, 0x65, 0x72, 0x64, 0x65])),
+			vanity: Some(&vanity),
+			status: 302,
+			time_ns: 1234,
+			client_ip: None,
+		};
+
+		assert_eq!(
+			serde_json::to_string(&entry).unwrap(),
+			r#"{"time":"2025-01-01T00:00:00Z","method":"GET","host":"example.com","path":"/example","id":"4Ld9TJrd","vanity":"example","status":302,"time_ns":1234}"#
+		);
+
+		assert!(AccessLogger::open(&AccessLog::default()).is_none());
+	}
+}
@@ -1,26 +1,86 @@
 //! This module contains the gRPC-based low-level links API, responsible for
 //! allowing outside services access to the links store.
 
+use std::{
+	net::IpAddr,
+	num::NonZeroU32,
+	pin::Pin,
+	sync::{Arc, LazyLock},
+	time::Duration,
+};
+
+use futures_util::{stream, Stream, StreamExt};
 use links_id::Id;
 use links_normalized::{Link, Normalized};
 use rpc::links_server::Links;
 pub use rpc::{
-	links_client::LinksClient, links_server::LinksServer, GetRedirectRequest, GetRedirectResponse,
-	GetStatisticsRequest, GetVanityRequest, GetVanityResponse, RemRedirectRequest,
-	RemRedirectResponse, RemStatisticsRequest, RemVanityRequest, RemVanityResponse,
-	SetRedirectRequest, SetRedirectResponse, SetVanityRequest, SetVanityResponse,
+	links_client::LinksClient, links_server::LinksServer, AddListenerRequest, AddListenerResponse,
+	CertificateLookups, DomainCertificateLookups, GetCertificateLookupsRequest,
+	GetCertificateLookupsResponse, GetOptionsRequest, GetOptionsResponse, GetRedirectRequest,
+	GetRedirectResponse, GetStatisticsRequest, GetStatisticsSummaryRequest,
+	GetStatisticsSummaryResponse, GetVanityRequest, GetVanityResponse, ListListenersRequest,
+	ListListenersResponse, ListRedirectsRequest, ListRedirectsResponse, ListVanitiesRequest,
+	ListVanitiesResponse, ListenerInfo, PurgeRedirectsRequest, PurgeRedirectsResponse, Redirect,
+	RedirectChange, ReloadCertificatesRequest, ReloadCertificatesResponse, ReloadConfigRequest,
+	ReloadConfigResponse, RemOptionsRequest, RemOptionsResponse, RemRedirectRequest,
+	RemRedirectResponse, RemRedirectsRequest, RemRedirectsResponse, RemStatisticsRequest,
+	RemVanityRequest, RemVanityResponse, RemoveListenerRequest, RemoveListenerResponse,
+	RestoreRedirectRequest, RestoreRedirectResponse, RotateTokenRequest, RotateTokenResponse,
+	SearchVanitiesRequest, SearchVanitiesResponse, SetOptionsRequest, SetOptionsResponse,
+	SetRedirectEnabledRequest, SetRedirectEnabledResponse, SetRedirectRequest, SetRedirectResponse,
+	SetRedirectsRequest, SetRedirectsResponse, SetVanitiesRequest, SetVanitiesResponse,
+	SetVanityRequest, SetVanityResponse, StatisticTotal, StatisticWithValue, Vanity,
+	WatchRedirectsRequest, WatchStatisticsRequest,
 };
 use rpc_wrapper::rpc;
-use tokio::time::Instant;
+use time::OffsetDateTime;
+use tokio::{
+	task::spawn_blocking,
+	time::{sleep, Instant},
+	try_join,
+};
+use tonic::{
+	metadata::{MetadataMap, MetadataValue},
+	server::NamedService,
+};
 pub use tonic::{Code, Request, Response, Status};
-use tracing::{info, instrument, trace};
+pub use tonic_health::pb::health_server::HealthServer;
+use tonic_health::pb::{
+	health_check_response::ServingStatus, health_server::Health, HealthCheckRequest,
+	HealthCheckResponse,
+};
+use tracing::{debug, info, instrument, trace, warn};
 
 use crate::{
-	config::Config,
-	stats::StatisticDescription,
+	certs::{CertificateResolver, LookupCounts},
+	config::{ApiToken, Config, IntoPartialError, ListenAddress, Partial, TokenRole},
+	options::LinkOptions,
+	ratelimit::{QuotaExceeded, RateLimiter},
+	server::{Listeners, Protocol},
+	stats::{StatisticDescription, StatisticGrouping, StatisticTime, StatisticValue},
 	store::{Current, Store},
+	util::A_YEAR,
+	webhook::{self, LinkChange},
 };
 
+/// The number of items in a page of list RPC results if not requested
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// The maximum number of items in a page of list RPC results
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// The maximum number of entries in a batch RPC request
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// How often the store's health is checked while a health check is watched
+const HEALTH_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The rate limiter for RPC requests from each peer IP address
+static PEER_RATE_LIMITER: LazyLock<RateLimiter<IpAddr>> = LazyLock::new(RateLimiter::new);
+
+/// The rate limiter for RPC requests using each API token (by identifier)
+static TOKEN_RATE_LIMITER: LazyLock<RateLimiter<String>> = LazyLock::new(RateLimiter::new);
+
 /// A wrapper around the generated tonic code. Contains the `rpc` module with
 /// all of the actual functionality. This is necessary to allow
 /// `clippy::pedantic` on the generated code.
@@ -28,47 +88,138 @@ mod rpc_wrapper {
 	tonic::include_proto!("links");
 }
 
+/// A verified TLS client certificate presented on an RPC connection, added to
+/// the extensions of all requests on that connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+	/// The lowercase hex SHA-256 fingerprint of the (end-entity) certificate
+	pub fingerprint: String,
+}
+
+/// The identifier of the API token an RPC request was authenticated with, added
+/// to the request's extensions by the [auth checker][get_auth_checker]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TokenId(String);
+
 /// Get a function that checks authentication/authorization of an incoming grpc
 /// API call.
 ///
 /// The incoming request is checked for the `auth` metadata value, which should
-/// be a shared secret string value, that is simply compared to the one
-/// configured. **It is critical that this value is kept secret and never
-/// exposed publicly!**
+/// be one of the configured API tokens' shared secret string values, which are
+/// simply compared to it. The identifier of the matching token is logged, and
+/// its [role][TokenRole] is added to the request's extensions, to be checked
+/// by each RPC depending on whether it only reads from the store, writes to
+/// it, or administers the server. RPCs which write to the store also check the
+/// token's [quota][crate::ratelimit::Quota].
+/// **It is critical that these values are kept secret and never exposed
+/// publicly!**
+///
+/// Requests without a token are accepted if they were made over a connection
+/// with a verified [client certificate][ClientCertificate] and the
+/// `rpc_client_cert_role` is configured, in which case they get that role.
+///
+/// Requests are also [rate limited][crate::ratelimit] per peer IP address
+/// (before authentication) and per API token, as configured in
+/// `rpc_rate_limits`. The number of tracked peers is limited, and requests
+/// from new peers are rate limited while there are too many of them (see
+/// [`RateLimiter`]).
 ///
 /// # Errors
-/// Returns the `UNAUTHENTICATED` status code if the token is not provided or
-/// is invalid.
+/// Returns the `UNAUTHENTICATED` status code if the token is not provided (and
+/// the request can't be authenticated by its client certificate instead) or is
+/// invalid, and the `RESOURCE_EXHAUSTED` status code if a rate limit is
+/// exceeded.
 pub fn get_auth_checker(
 	config: &'static Config,
 ) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone {
-	move |req: Request<()>| -> Result<Request<()>, Status> {
+	move |mut req: Request<()>| -> Result<Request<()>, Status> {
+		let limits = config.rpc_rate_limits();
+
+		if let Some(peer) = req.remote_addr() {
+			if !PEER_RATE_LIMITER.check(peer.ip(), limits.peer) {
+				debug!(%peer, "peer rate limit exceeded");
+				return Err(Status::new(
+					Code::ResourceExhausted,
+					"rate limit exceeded, try again later",
+				));
+			}
+		}
+
 		let token = if let Some(token) = req.metadata().get("auth") {
 			token.as_encoded_bytes()
+		} else if let (Some(cert), Some(role)) = (
+			req.extensions().get::<ClientCertificate>(),
+			config.rpc_client_cert_role(),
+		) {
+			info!(fingerprint = %cert.fingerprint, %role, "client certificate is valid");
+			req.extensions_mut().insert(role);
+			return Ok(req);
 		} else {
 			trace!("no auth token to check");
 			return Err(Status::new(Code::Unauthenticated, "no auth token provided"));
 		};
 
-		let secret = config.token();
+		trace!("checking auth token {token:?}");
 
-		trace!("checking auth token {token:?}, secret is {secret:?}");
-
-		if secret.as_bytes() == token {
-			trace!("auth token is valid");
-			Ok(req)
-		} else {
+		let Some((token_id, role)) = config.check_token(token) else {
 			trace!("auth token is not valid");
-			Err(Status::new(Code::Unauthenticated, "auth token is invalid"))
+			return Err(Status::new(Code::Unauthenticated, "auth token is invalid"));
+		};
+
+		info!(%token_id, %role, "auth token is valid");
+
+		if !TOKEN_RATE_LIMITER.check(token_id.clone(), limits.token) {
+			debug!("token rate limit exceeded");
+			return Err(Status::new(
+				Code::ResourceExhausted,
+				"rate limit exceeded, try again later",
+			));
 		}
+
+		req.extensions_mut().insert(role);
+		req.extensions_mut().insert(TokenId(token_id));
+		Ok(req)
+	}
+}
+
+/// Check that the request `req` was authenticated with a token with at least
+/// the `role` (as recorded by the [auth checker][get_auth_checker]).
+///
+/// # Errors
+/// Returns the `PERMISSION_DENIED` status code if the token's role is not
+/// sufficient, or if the request's token was not checked at all.
+#[expect(
+	clippy::result_large_err,
+	reason = "this is used in RPC handlers, which return `Status` anyway"
+)]
+fn check_role<T>(req: &Request<T>, role: TokenRole) -> Result<(), Status> {
+	match req.extensions().get::<TokenRole>() {
+		Some(&token_role) if token_role >= role => Ok(()),
+		Some(_) => Err(Status::new(
+			Code::PermissionDenied,
+			format!("this operation requires a token with the {role} role"),
+		)),
+		None => Err(Status::new(
+			Code::PermissionDenied,
+			"the request's auth token was not checked",
+		)),
 	}
 }
 
-/// The grpc API implementation. Contains a reference to the store on which all
-/// operations are performed. Implements all RPC calls from `links.proto`.
+/// The grpc API implementation. Implements all RPC calls from `links.proto`.
+///
+/// Contains a reference to the store on which all operations are performed,
+/// optionally to the server's listeners, which can then be added and removed,
+/// and optionally to the server's config, whose webhooks are notified of
+/// changes to links, whose API tokens can be rotated and have their quotas
+/// enforced, and which can be reloaded, and optionally to the server's TLS
+/// certificate resolver, whose certificates can then be reloaded.
 #[derive(Debug)]
 pub struct Api {
 	store: &'static Current,
+	config: Option<&'static Config>,
+	listeners: Option<&'static Listeners>,
+	cert_resolver: Option<Arc<CertificateResolver>>,
 }
 
 impl Api {
@@ -76,7 +227,52 @@ impl Api {
 	/// provided, and provide access to that store via gRPC.
 	#[instrument(level = "info", skip_all, fields(store = store.backend_name()))]
 	pub fn new(store: &'static Current) -> Self {
-		Self { store }
+		Self {
+			store,
+			config: None,
+			listeners: None,
+			cert_resolver: None,
+		}
+	}
+
+	/// Allow this API instance to add and remove `listeners`, optionally
+	/// persisting those changes to the `config`'s file. Without this, the
+	/// listener RPCs return the `UNIMPLEMENTED` status code.
+	#[must_use]
+	pub const fn with_listeners(
+		mut self,
+		config: &'static Config,
+		listeners: &'static Listeners,
+	) -> Self {
+		self.config = Some(config);
+		self.listeners = Some(listeners);
+		self
+	}
+
+	/// Notify the [webhooks][crate::webhook] configured in `config` of changes
+	/// to redirects and vanity paths made via this API instance, allow
+	/// rotating its API tokens and reloading it, and enforce the tokens'
+	/// quotas. This is also done if `config` is provided to
+	/// [`Api::with_listeners`].
+	#[must_use]
+	pub const fn with_webhooks(mut self, config: &'static Config) -> Self {
+		self.config = Some(config);
+		self
+	}
+
+	/// Allow this API instance to reload the certificates of the
+	/// `cert_resolver` from the certificate sources in the `config`, and to
+	/// report its certificate lookups. Without this, the certificate reloading
+	/// and lookup RPCs return the `UNIMPLEMENTED` status code.
+	#[must_use]
+	pub fn with_certificates(
+		mut self,
+		config: &'static Config,
+		cert_resolver: Arc<CertificateResolver>,
+	) -> Self {
+		self.config = Some(config);
+		self.cert_resolver = Some(cert_resolver);
+		self
 	}
 
 	/// Get a reference to this API's store.
@@ -84,28 +280,315 @@ impl Api {
 	pub fn store(&self) -> Store {
 		self.store.get()
 	}
+
+	/// Get the store to be used for the request `req`, which is the store for
+	/// the namespace in the request's `namespace` metadata value if there is
+	/// one, or this API's store otherwise
+	async fn namespaced_store<T: Sync>(&self, req: &Request<T>) -> Result<Store, Status> {
+		let store = self.store();
+
+		let Some(namespace) = req.metadata().get("namespace") else {
+			return Ok(store);
+		};
+
+		let Ok(namespace) = namespace.to_str() else {
+			return Err(Status::new(Code::InvalidArgument, "namespace is invalid"));
+		};
+
+		store
+			.with_namespace(namespace)
+			.await
+			.map_err(|err| Status::new(Code::InvalidArgument, err.to_string()))
+	}
+
+	/// Check whether the `link` points to a domain blocked in this API's
+	/// config. Links are never blocked if this API has no config.
+	fn is_blocked(&self, link: &Link) -> bool {
+		self.config
+			.is_some_and(|config| config.blocked_domains().is_blocked(link))
+	}
+
+	/// Check whether the redirect from `id` to `link` would cause a [redirect
+	/// loop][crate::loops] in the `store` according to this API's config.
+	/// Redirects never cause loops if this API has no config.
+	///
+	/// # Errors
+	/// Returns the `INTERNAL` status code if a store operation fails.
+	async fn is_loop(&self, store: &Store, id: Id, link: &Link) -> Result<bool, Status> {
+		let Some(config) = self.config else {
+			return Ok(false);
+		};
+
+		config
+			.loop_detection()
+			.is_loop(store, id, link)
+			.await
+			.map_err(|_| Status::new(Code::Internal, "store operation failed"))
+	}
+
+	/// Check whether the `vanity` path is reserved in this API's config, so
+	/// that it can't be used. Vanity paths are never reserved if this API has
+	/// no config.
+	fn is_reserved(&self, vanity: &Normalized) -> bool {
+		self.config
+			.is_some_and(|config| config.redirector().is_reserved(vanity.as_str()))
+	}
+
+	/// Count `amount` changes made by the request `req` against the
+	/// [quota][crate::ratelimit::Quota] of the API token it was authenticated
+	/// with. Quotas are only checked if this API has a config.
+	///
+	/// # Errors
+	/// Returns the `RESOURCE_EXHAUSTED` status code (with the `quota-period`,
+	/// `quota-limit`, and `quota-reset` metadata values) if the quota is
+	/// exceeded, and the `UNAVAILABLE` status code if the quota's usage could
+	/// not be recorded in the store.
+	async fn check_quota<T: Sync>(&self, req: &Request<T>, amount: usize) -> Result<(), Status> {
+		let (Some(config), Some(TokenId(token_id))) =
+			(self.config, req.extensions().get::<TokenId>())
+		else {
+			return Ok(());
+		};
+
+		let quota = config.token_quota(token_id);
+		if !quota.is_enabled() {
+			return Ok(());
+		}
+
+		let amount = u64::try_from(amount).unwrap_or(u64::MAX);
+		match quota.check(&self.store(), token_id, amount).await {
+			Ok(None) => Ok(()),
+			Ok(Some(exceeded)) => {
+				debug!(%token_id, %exceeded, "token quota exceeded");
+				Err(quota_exceeded(&exceeded))
+			}
+			Err(err) => {
+				warn!(?err, %token_id, "token quota usage could not be recorded");
+				Err(Status::new(
+					Code::Unavailable,
+					"quota usage could not be recorded",
+				))
+			}
+		}
+	}
+
+	/// Send the `changes` made in the `store` to the configured webhooks, if
+	/// this API has a config
+	fn notify(&self, store: &Store, changes: impl IntoIterator<Item = LinkChange>) {
+		if let Some(config) = self.config {
+			webhook::notify(config, store.namespace(), changes);
+		}
+	}
+
+	/// Get this API's config and listeners, and check that the `address` is
+	/// valid and the change can be persisted (if requested). The returned
+	/// address has its port set, even if it was omitted in `address`.
+	#[expect(
+		clippy::result_large_err,
+		reason = "`Status` is what all RPC calls return"
+	)]
+	fn listener_change(
+		&self,
+		address: &str,
+		persist: bool,
+	) -> Result<(&'static Config, &'static Listeners, ListenAddress), Status> {
+		let (Some(config), Some(listeners)) = (self.config, self.listeners) else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"listeners can not be changed on this server",
+			));
+		};
+
+		let Ok(addr) = address.parse::<ListenAddress>() else {
+			return Err(Status::new(Code::InvalidArgument, "address is invalid"));
+		};
+		let addr = addr.with_default_port();
+
+		if persist && config.file().is_none() {
+			return Err(Status::new(
+				Code::FailedPrecondition,
+				"the server has no configuration file to persist the change to",
+			));
+		}
+
+		Ok((config, listeners, addr))
+	}
+}
+
+/// Check whether the listener on `addr` could be serving the request `req`.
+/// The socket path of Unix domain socket connections isn't known, so all Unix
+/// domain socket gRPC listeners are considered to be serving requests received
+/// through one.
+fn is_serving<T>(req: &Request<T>, addr: &ListenAddress) -> bool {
+	#[cfg(unix)]
+	if req
+		.extensions()
+		.get::<tonic::transport::server::UdsConnectInfo>()
+		.is_some()
+	{
+		return addr.protocol == Protocol::GrpcUnix;
+	}
+
+	req.local_addr().is_some_and(|local| {
+		matches!(addr.protocol, Protocol::Grpc | Protocol::Grpcs)
+			&& addr.port == Some(local.port())
+			&& addr
+				.address
+				.map_or(true, |ip| ip.to_canonical() == local.ip().to_canonical())
+	})
+}
+
+/// Get the `RESOURCE_EXHAUSTED` status for the `exceeded` quota, with metadata
+/// describing the quota's period, its limit, and when it resets (as a unix
+/// timestamp)
+fn quota_exceeded(exceeded: &QuotaExceeded) -> Status {
+	let mut metadata = MetadataMap::new();
+	metadata.insert(
+		"quota-period",
+		MetadataValue::from_static(exceeded.period.into()),
+	);
+	metadata.insert("quota-limit", MetadataValue::from(exceeded.limit));
+	metadata.insert(
+		"quota-reset",
+		MetadataValue::from(exceeded.resets_at.unix_timestamp()),
+	);
+
+	Status::with_metadata(Code::ResourceExhausted, exceeded.to_string(), metadata)
+}
+
+/// Get the page size for list and search RPCs from the requested `limit`,
+/// which defaults to [`DEFAULT_PAGE_SIZE`] and is capped at [`MAX_PAGE_SIZE`]
+#[expect(
+	clippy::result_large_err,
+	reason = "`Status` is what all RPC calls return"
+)]
+fn page_size(limit: Option<u32>) -> Result<usize, Status> {
+	match limit {
+		None => Ok(DEFAULT_PAGE_SIZE),
+		Some(0) => Err(Status::new(Code::InvalidArgument, "limit must not be 0")),
+		Some(limit) => Ok(usize::try_from(limit).map_or(MAX_PAGE_SIZE, |l| l.min(MAX_PAGE_SIZE))),
+	}
+}
+
+/// Get the grouping of statistics for the statistics summary RPC from the
+/// requested fields in `group_by` and the `time_bucket` size in seconds
+#[expect(
+	clippy::result_large_err,
+	reason = "`Status` is what all RPC calls return"
+)]
+fn statistic_grouping(
+	group_by: &[String],
+	time_bucket: Option<u64>,
+) -> Result<StatisticGrouping, Status> {
+	let mut grouping = StatisticGrouping::default();
+	let mut by_time = false;
+
+	for field in group_by {
+		match field.as_str() {
+			"link" => grouping.link = true,
+			"type" => grouping.stat_type = true,
+			"data" => grouping.data = true,
+			"time" => by_time = true,
+			_ => {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					format!("can not group statistics by \"{field}\""),
+				))
+			}
+		}
+	}
+
+	let resolution = StatisticTime::RESOLUTION_SECS.unsigned_abs();
+	grouping.time = match (by_time, time_bucket) {
+		(false, None) => None,
+		(false, Some(_)) => {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"the time bucket size requires grouping by time",
+			))
+		}
+		(true, None) => Some(NonZeroU32::MIN),
+		(true, Some(secs)) if secs % resolution == 0 => Some(
+			u32::try_from(secs / resolution)
+				.ok()
+				.and_then(NonZeroU32::new)
+				.ok_or_else(|| Status::new(Code::InvalidArgument, "invalid time bucket size"))?,
+		),
+		(true, Some(_)) => {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				format!("the time bucket size must be a multiple of {resolution} seconds"),
+			))
+		}
+	};
+
+	Ok(grouping)
+}
+
+/// Add `addr` to (if `add` is `true`) or remove it from (otherwise) the
+/// listeners in `config`'s file. If the file doesn't configure any listeners,
+/// the currently configured ones are changed and written to the file instead.
+///
+/// # IO
+/// This function performs synchronous file IO, and should not be used in an
+/// asynchronous context.
+fn persist_listener(
+	config: &Config,
+	addr: ListenAddress,
+	add: bool,
+) -> Result<(), IntoPartialError> {
+	let Some(file) = config.file() else {
+		return Ok(());
+	};
+
+	let mut listeners = Partial::from_file(file)?
+		.listeners
+		.unwrap_or_else(|| config.listeners());
+
+	listeners.retain(|l| *l != addr);
+	if add {
+		listeners.push(addr);
+	}
+
+	Partial::write_listeners_to_file(file, &listeners)
 }
 
 #[tonic::async_trait]
 impl Links for Api {
+	type WatchRedirectsStream =
+		Pin<Box<dyn Stream<Item = Result<rpc::RedirectChange, Status>> + Send + 'static>>;
+	type WatchStatisticsStream =
+		Pin<Box<dyn Stream<Item = Result<rpc::StatisticWithValue, Status>> + Send + 'static>>;
+
 	#[instrument(level = "info", name = "rpc_get_redirect", skip_all, fields(store = %self.store.backend_name()))]
 	async fn get_redirect(
 		&self,
 		req: Request<rpc::GetRedirectRequest>,
 	) -> Result<Response<rpc::GetRedirectResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
 
 		let Ok(id) = Id::try_from(req.into_inner().id) else {
 			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
 		};
 
-		let Ok(link) = store.get_redirect(id).await else {
+		let Ok((link, vanities, options)) = try_join!(
+			store.get_redirect(id),
+			store.get_vanities_of(id),
+			store.get_options(id)
+		) else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
 		let res = Ok(Response::new(rpc::GetRedirectResponse {
 			link: link.map(Link::into_string),
+			vanities: vanities.into_iter().map(Normalized::into_string).collect(),
+			enabled: options
+				.as_ref()
+				.and_then(|options| options.enabled)
+				.unwrap_or(true),
+			flagged: options.and_then(|options| options.flagged),
 		}));
 
 		let time = time.elapsed();
@@ -125,9 +608,15 @@ impl Links for Api {
 		req: Request<rpc::SetRedirectRequest>,
 	) -> Result<Response<rpc::SetRedirectResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
-		let rpc::SetRedirectRequest { id, link } = req.into_inner();
+		let rpc::SetRedirectRequest {
+			id,
+			link,
+			expires_at,
+		} = req.into_inner();
 
 		let Ok(id) = Id::try_from(id) else {
 			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
@@ -137,12 +626,128 @@ impl Links for Api {
 			return Err(Status::new(Code::InvalidArgument, "link is invalid"));
 		};
 
-		let Ok(link) = store.set_redirect(id, link).await else {
+		if self.is_blocked(&link) {
+			return Err(Status::new(Code::InvalidArgument, "link domain is blocked"));
+		}
+
+		if self.is_loop(&store, id, &link).await? {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"link would cause a redirect loop",
+			));
+		}
+
+		let Ok(expires_at) = expires_at
+			.map(OffsetDateTime::from_unix_timestamp)
+			.transpose()
+		else {
+			return Err(Status::new(Code::InvalidArgument, "expiry is invalid"));
+		};
+
+		let previous = if let Some(expires_at) = expires_at {
+			if expires_at <= OffsetDateTime::now_utc() {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					"expiry must be in the future",
+				));
+			}
+
+			store
+				.set_redirect_with_expiry(id, link.clone(), expires_at)
+				.await
+		} else {
+			store.set_redirect(id, link.clone()).await
+		};
+
+		let Ok(previous) = previous else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
+		self.notify(&store, [LinkChange::redirect_set(
+			id,
+			&link,
+			previous.clone(),
+		)]);
+
 		let res = Ok(Response::new(rpc::SetRedirectResponse {
-			link: link.map(Link::into_string),
+			link: previous.map(Link::into_string),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_set_redirects", skip_all, fields(store = %self.store.backend_name()))]
+	async fn set_redirects(
+		&self,
+		req: Request<rpc::SetRedirectsRequest>,
+	) -> Result<Response<rpc::SetRedirectsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, req.get_ref().redirects.len())
+			.await?;
+
+		let redirects = req.into_inner().redirects;
+
+		if redirects.len() > MAX_BATCH_SIZE {
+			return Err(Status::new(Code::InvalidArgument, "too many redirects"));
+		}
+
+		let Some(redirects) = redirects
+			.into_iter()
+			.map(|rpc::Redirect { id, link }| {
+				Some((Id::try_from(id).ok()?, Link::new(&link).ok()?))
+			})
+			.collect::<Option<Vec<_>>>()
+		else {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"one of the redirects is invalid",
+			));
+		};
+
+		if redirects.iter().any(|(_, link)| self.is_blocked(link)) {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"one of the redirects has a blocked link domain",
+			));
+		}
+
+		for (id, link) in &redirects {
+			if self.is_loop(&store, *id, link).await? {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					"one of the redirects would cause a redirect loop",
+				));
+			}
+		}
+
+		let Ok(links) = store.set_redirects(&redirects).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		self.notify(
+			&store,
+			redirects.iter().zip(&links).map(|((id, link), previous)| {
+				LinkChange::redirect_set(*id, link, previous.clone())
+			}),
+		);
+
+		let res = Ok(Response::new(rpc::SetRedirectsResponse {
+			redirects: links
+				.into_iter()
+				.map(|link| rpc::SetRedirectResponse {
+					link: link.map(Link::into_string),
+				})
+				.collect(),
 		}));
 
 		let time = time.elapsed();
@@ -162,16 +767,20 @@ impl Links for Api {
 		req: Request<rpc::RemRedirectRequest>,
 	) -> Result<Response<rpc::RemRedirectResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
 		let Ok(id) = Id::try_from(req.into_inner().id) else {
 			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
 		};
 
-		let Ok(link) = store.rem_redirect(id).await else {
+		let Ok(link) = store.rem_redirect_soft(id).await else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
+		self.notify(&store, LinkChange::redirect_removed(id, link.clone()));
+
 		let res = Ok(Response::new(rpc::RemRedirectResponse {
 			link: link.map(Link::into_string),
 		}));
@@ -187,22 +796,51 @@ impl Links for Api {
 		res
 	}
 
-	#[instrument(level = "info", name = "rpc_get_vanity", skip_all, fields(store = %self.store.backend_name()))]
-	async fn get_vanity(
+	#[instrument(level = "info", name = "rpc_rem_redirects", skip_all, fields(store = %self.store.backend_name()))]
+	async fn rem_redirects(
 		&self,
-		req: Request<rpc::GetVanityRequest>,
-	) -> Result<Response<rpc::GetVanityResponse>, Status> {
+		req: Request<rpc::RemRedirectsRequest>,
+	) -> Result<Response<rpc::RemRedirectsResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, req.get_ref().ids.len()).await?;
 
-		let vanity = Normalized::new(&req.into_inner().vanity);
+		let ids = req.into_inner().ids;
 
-		let Ok(id) = store.get_vanity(vanity).await else {
+		if ids.len() > MAX_BATCH_SIZE {
+			return Err(Status::new(Code::InvalidArgument, "too many ids"));
+		}
+
+		let Ok(ids) = ids
+			.into_iter()
+			.map(Id::try_from)
+			.collect::<Result<Vec<_>, _>>()
+		else {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"one of the ids is invalid",
+			));
+		};
+
+		let Ok(links) = store.rem_redirects_soft(&ids).await else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
-		let res = Ok(Response::new(rpc::GetVanityResponse {
-			id: id.map(|id| id.to_string()),
+		self.notify(
+			&store,
+			ids.iter()
+				.zip(&links)
+				.filter_map(|(id, link)| LinkChange::redirect_removed(*id, link.clone())),
+		);
+
+		let res = Ok(Response::new(rpc::RemRedirectsResponse {
+			redirects: links
+				.into_iter()
+				.map(|link| rpc::RemRedirectResponse {
+					link: link.map(Link::into_string),
+				})
+				.collect(),
 		}));
 
 		let time = time.elapsed();
@@ -216,28 +854,43 @@ impl Links for Api {
 		res
 	}
 
-	#[instrument(level = "info", name = "rpc_set_vanity", skip_all, fields(store = %self.store.backend_name()))]
-	async fn set_vanity(
+	#[instrument(level = "info", name = "rpc_restore_redirect", skip_all, fields(store = %self.store.backend_name()))]
+	async fn restore_redirect(
 		&self,
-		req: Request<rpc::SetVanityRequest>,
-	) -> Result<Response<rpc::SetVanityResponse>, Status> {
+		req: Request<rpc::RestoreRedirectRequest>,
+	) -> Result<Response<rpc::RestoreRedirectResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
-
-		let rpc::SetVanityRequest { vanity, id } = req.into_inner();
-
-		let vanity = Normalized::new(&vanity);
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
-		let Ok(id) = Id::try_from(id) else {
+		let Ok(id) = Id::try_from(req.into_inner().id) else {
 			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
 		};
 
-		let Ok(id) = store.set_vanity(vanity, id).await else {
+		match store.get_redirect(id).await {
+			Ok(Some(_)) => {
+				return Err(Status::new(
+					Code::AlreadyExists,
+					"a redirect with this id already exists",
+				))
+			}
+			Ok(None) => (),
+			Err(_) => return Err(Status::new(Code::Internal, "store operation failed")),
+		}
+
+		let Ok(link) = store.restore_redirect(id).await else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
-		let res = Ok(Response::new(rpc::SetVanityResponse {
-			id: id.map(|id| id.to_string()),
+		self.notify(
+			&store,
+			link.as_ref()
+				.map(|link| LinkChange::redirect_set(id, link, None)),
+		);
+
+		let res = Ok(Response::new(rpc::RestoreRedirectResponse {
+			link: link.map(Link::into_string),
 		}));
 
 		let time = time.elapsed();
@@ -251,22 +904,22 @@ impl Links for Api {
 		res
 	}
 
-	#[instrument(level = "info", name = "rpc_rem_vanity", skip_all, fields(store = %self.store.backend_name()))]
-	async fn rem_vanity(
+	#[instrument(level = "info", name = "rpc_purge_redirects", skip_all, fields(store = %self.store.backend_name()))]
+	async fn purge_redirects(
 		&self,
-		req: Request<rpc::RemVanityRequest>,
-	) -> Result<Response<rpc::RemVanityResponse>, Status> {
+		req: Request<rpc::PurgeRedirectsRequest>,
+	) -> Result<Response<rpc::PurgeRedirectsResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::Admin)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
-		let vanity = Normalized::new(&req.into_inner().vanity);
-
-		let Ok(id) = store.rem_vanity(vanity).await else {
+		let Ok(purged) = store.purge_redirects().await else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
-		let res = Ok(Response::new(rpc::RemVanityResponse {
-			id: id.map(|id| id.to_string()),
+		let res = Ok(Response::new(rpc::PurgeRedirectsResponse {
+			purged: purged as u64,
 		}));
 
 		let time = time.elapsed();
@@ -280,56 +933,29 @@ impl Links for Api {
 		res
 	}
 
-	#[instrument(level = "info", name = "rpc_get_statistics", skip_all, fields(store = %self.store.backend_name()))]
-	async fn get_statistics(
+	#[instrument(level = "info", name = "rpc_set_redirect_enabled", skip_all, fields(store = %self.store.backend_name()))]
+	async fn set_redirect_enabled(
 		&self,
-		req: Request<rpc::GetStatisticsRequest>,
-	) -> Result<Response<rpc::GetStatisticsResponse>, Status> {
+		req: Request<rpc::SetRedirectEnabledRequest>,
+	) -> Result<Response<rpc::SetRedirectEnabledResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
-		let rpc::GetStatisticsRequest {
-			link,
-			r#type: stat_type,
-			data,
-			time: stat_time,
-		} = req.into_inner();
+		let rpc::SetRedirectEnabledRequest { id, enabled } = req.into_inner();
 
-		let stat_desc = match (
-			link.map(TryInto::try_into).transpose(),
-			stat_type.map(|s| s.as_str().try_into()).transpose(),
-			data.map(TryInto::try_into).transpose(),
-			stat_time.map(|t| t.as_str().try_into()).transpose(),
-		) {
-			(Ok(link), Ok(stat_type), Ok(data), Ok(time)) => StatisticDescription {
-				link,
-				stat_type,
-				data,
-				time,
-			},
-			_ => {
-				return Err(Status::new(
-					Code::InvalidArgument,
-					"one of the supplied arguments is invalid",
-				))
-			}
+		let Ok(id) = Id::try_from(id) else {
+			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
 		};
 
-		let Ok(stats) = store.get_statistics(stat_desc).await else {
+		let Ok(previous) = store.set_enabled(id, enabled).await else {
 			return Err(Status::new(Code::Internal, "store operation failed"));
 		};
 
-		let statistics = stats
-			.map(|(s, v)| rpc::StatisticWithValue {
-				link: s.link.to_string(),
-				r#type: s.stat_type.to_string(),
-				data: s.data.to_string(),
-				time: s.time.to_string(),
-				value: v.get(),
-			})
-			.collect();
-
-		let res = Ok(Response::new(rpc::GetStatisticsResponse { statistics }));
+		let res = Ok(Response::new(rpc::SetRedirectEnabledResponse {
+			enabled: previous,
+		}));
 
 		let time = time.elapsed();
 		info!(
@@ -342,13 +968,638 @@ impl Links for Api {
 		res
 	}
 
-	#[instrument(level = "info", name = "rpc_rem_statistics", skip_all, fields(store = %self.store.backend_name()))]
-	async fn rem_statistics(
+	#[instrument(level = "info", name = "rpc_list_redirects", skip_all, fields(store = %self.store.backend_name()))]
+	async fn list_redirects(
 		&self,
-		req: Request<rpc::RemStatisticsRequest>,
-	) -> Result<Response<rpc::RemStatisticsResponse>, Status> {
+		req: Request<rpc::ListRedirectsRequest>,
+	) -> Result<Response<rpc::ListRedirectsResponse>, Status> {
 		let time = Instant::now();
-		let store = self.store();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::ListRedirectsRequest { cursor, limit } = req.into_inner();
+
+		let Ok(cursor) = cursor.map(Id::try_from).transpose() else {
+			return Err(Status::new(Code::InvalidArgument, "cursor is invalid"));
+		};
+
+		let Ok(page) = store.list_redirects(cursor, page_size(limit)?).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let res = Ok(Response::new(rpc::ListRedirectsResponse {
+			redirects: page
+				.items
+				.into_iter()
+				.map(|(id, link)| rpc::Redirect {
+					id: id.to_string(),
+					link: link.into_string(),
+				})
+				.collect(),
+			next: page.next.map(|id| id.to_string()),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_watch_redirects", skip_all, fields(store = %self.store.backend_name()))]
+	async fn watch_redirects(
+		&self,
+		req: Request<rpc::WatchRedirectsRequest>,
+	) -> Result<Response<Self::WatchRedirectsStream>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		#[expect(
+			clippy::result_large_err,
+			reason = "the item type is required by tonic's streaming responses"
+		)]
+		let changes = store.watch_redirects().map(|change| {
+			Ok(rpc::RedirectChange {
+				id: change.id.to_string(),
+				old_link: change.old.map(Link::into_string),
+				new_link: change.new.map(Link::into_string),
+			})
+		});
+
+		let res: Result<Response<Self::WatchRedirectsStream>, Status> =
+			Ok(Response::new(Box::pin(changes)));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_get_vanity", skip_all, fields(store = %self.store.backend_name()))]
+	async fn get_vanity(
+		&self,
+		req: Request<rpc::GetVanityRequest>,
+	) -> Result<Response<rpc::GetVanityResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let vanity = Normalized::new(&req.into_inner().vanity);
+
+		let Ok(id) = store.get_vanity(vanity).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let res = Ok(Response::new(rpc::GetVanityResponse {
+			id: id.map(|id| id.to_string()),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_set_vanity", skip_all, fields(store = %self.store.backend_name()))]
+	async fn set_vanity(
+		&self,
+		req: Request<rpc::SetVanityRequest>,
+	) -> Result<Response<rpc::SetVanityResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
+
+		let rpc::SetVanityRequest { vanity, id } = req.into_inner();
+
+		let vanity = Normalized::new(&vanity);
+
+		let Ok(id) = Id::try_from(id) else {
+			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
+		};
+
+		if self.is_reserved(&vanity) {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"vanity path is reserved",
+			));
+		}
+
+		let Ok(previous) = store.set_vanity(vanity.clone(), id).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		self.notify(&store, [LinkChange::vanity_set(&vanity, id, previous)]);
+
+		let res = Ok(Response::new(rpc::SetVanityResponse {
+			id: previous.map(|id| id.to_string()),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_set_vanities", skip_all, fields(store = %self.store.backend_name()))]
+	async fn set_vanities(
+		&self,
+		req: Request<rpc::SetVanitiesRequest>,
+	) -> Result<Response<rpc::SetVanitiesResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, req.get_ref().vanities.len()).await?;
+
+		let vanities = req.into_inner().vanities;
+
+		if vanities.len() > MAX_BATCH_SIZE {
+			return Err(Status::new(Code::InvalidArgument, "too many vanity paths"));
+		}
+
+		let Ok(vanities) = vanities
+			.into_iter()
+			.map(|rpc::Vanity { vanity, id }| {
+				Id::try_from(id).map(|id| (Normalized::new(&vanity), id))
+			})
+			.collect::<Result<Vec<_>, _>>()
+		else {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"one of the ids is invalid",
+			));
+		};
+
+		if vanities.iter().any(|(vanity, _)| self.is_reserved(vanity)) {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"one of the vanity paths is reserved",
+			));
+		}
+
+		let Ok(ids) = store.set_vanities(&vanities).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		self.notify(
+			&store,
+			vanities
+				.iter()
+				.zip(&ids)
+				.map(|((vanity, id), previous)| LinkChange::vanity_set(vanity, *id, *previous)),
+		);
+
+		let res = Ok(Response::new(rpc::SetVanitiesResponse {
+			vanities: ids
+				.into_iter()
+				.map(|id| rpc::SetVanityResponse {
+					id: id.map(|id| id.to_string()),
+				})
+				.collect(),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_rem_vanity", skip_all, fields(store = %self.store.backend_name()))]
+	async fn rem_vanity(
+		&self,
+		req: Request<rpc::RemVanityRequest>,
+	) -> Result<Response<rpc::RemVanityResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
+
+		let vanity = Normalized::new(&req.into_inner().vanity);
+
+		let Ok(id) = store.rem_vanity(vanity.clone()).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		self.notify(&store, LinkChange::vanity_removed(&vanity, id));
+
+		let res = Ok(Response::new(rpc::RemVanityResponse {
+			id: id.map(|id| id.to_string()),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_list_vanities", skip_all, fields(store = %self.store.backend_name()))]
+	async fn list_vanities(
+		&self,
+		req: Request<rpc::ListVanitiesRequest>,
+	) -> Result<Response<rpc::ListVanitiesResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::ListVanitiesRequest { cursor, limit } = req.into_inner();
+		let cursor = cursor.map(|cursor| Normalized::new(&cursor));
+
+		let Ok(page) = store.list_vanities(cursor, page_size(limit)?).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let res = Ok(Response::new(rpc::ListVanitiesResponse {
+			vanities: page
+				.items
+				.into_iter()
+				.map(|(vanity, id)| rpc::Vanity {
+					vanity: vanity.into_string(),
+					id: id.to_string(),
+				})
+				.collect(),
+			next: page.next.map(Normalized::into_string),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_search_vanities", skip_all, fields(store = %self.store.backend_name()))]
+	async fn search_vanities(
+		&self,
+		req: Request<rpc::SearchVanitiesRequest>,
+	) -> Result<Response<rpc::SearchVanitiesResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::SearchVanitiesRequest {
+			query,
+			prefix,
+			limit,
+		} = req.into_inner();
+		let query = Normalized::new(&query);
+
+		let Ok(vanities) = store
+			.search_vanities(&query, prefix, page_size(limit)?)
+			.await
+		else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let res = Ok(Response::new(rpc::SearchVanitiesResponse {
+			vanities: vanities
+				.into_iter()
+				.map(|(vanity, id)| rpc::Vanity {
+					vanity: vanity.into_string(),
+					id: id.to_string(),
+				})
+				.collect(),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_get_options", skip_all, fields(store = %self.store.backend_name()))]
+	async fn get_options(
+		&self,
+		req: Request<rpc::GetOptionsRequest>,
+	) -> Result<Response<rpc::GetOptionsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let Ok(id) = Id::try_from(req.into_inner().id) else {
+			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
+		};
+
+		let Ok(options) = store.get_options(id).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let Ok(options) = options.as_ref().map(serde_json::to_string).transpose() else {
+			return Err(Status::new(
+				Code::Internal,
+				"options could not be serialized",
+			));
+		};
+
+		let res = Ok(Response::new(rpc::GetOptionsResponse { options }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_set_options", skip_all, fields(store = %self.store.backend_name()))]
+	async fn set_options(
+		&self,
+		req: Request<rpc::SetOptionsRequest>,
+	) -> Result<Response<rpc::SetOptionsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
+
+		let rpc::SetOptionsRequest { id, options } = req.into_inner();
+
+		let Ok(id) = Id::try_from(id) else {
+			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
+		};
+
+		let Ok(options) = serde_json::from_str::<LinkOptions>(&options) else {
+			return Err(Status::new(Code::InvalidArgument, "options are invalid"));
+		};
+
+		if options.max_clicks.is_some() && !store.supports_usage() {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"max_clicks is not supported by the store backend",
+			));
+		}
+
+		let Ok(options) = store.set_options(id, options).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let Ok(options) = options.as_ref().map(serde_json::to_string).transpose() else {
+			return Err(Status::new(
+				Code::Internal,
+				"options could not be serialized",
+			));
+		};
+
+		let res = Ok(Response::new(rpc::SetOptionsResponse { options }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_rem_options", skip_all, fields(store = %self.store.backend_name()))]
+	async fn rem_options(
+		&self,
+		req: Request<rpc::RemOptionsRequest>,
+	) -> Result<Response<rpc::RemOptionsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
+
+		let Ok(id) = Id::try_from(req.into_inner().id) else {
+			return Err(Status::new(Code::InvalidArgument, "id is invalid"));
+		};
+
+		let Ok(options) = store.rem_options(id).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let Ok(options) = options.as_ref().map(serde_json::to_string).transpose() else {
+			return Err(Status::new(
+				Code::Internal,
+				"options could not be serialized",
+			));
+		};
+
+		let res = Ok(Response::new(rpc::RemOptionsResponse { options }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_get_statistics", skip_all, fields(store = %self.store.backend_name()))]
+	async fn get_statistics(
+		&self,
+		req: Request<rpc::GetStatisticsRequest>,
+	) -> Result<Response<rpc::GetStatisticsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::GetStatisticsRequest {
+			link,
+			r#type: stat_type,
+			data,
+			time: stat_time,
+			cursor,
+			limit,
+		} = req.into_inner();
+
+		let stat_desc = match (
+			link.map(TryInto::try_into).transpose(),
+			stat_type.map(|s| s.as_str().try_into()).transpose(),
+			data.map(TryInto::try_into).transpose(),
+			stat_time.map(|t| t.as_str().try_into()).transpose(),
+		) {
+			(Ok(link), Ok(stat_type), Ok(data), Ok(time)) => StatisticDescription {
+				link,
+				stat_type,
+				data,
+				time,
+			},
+			_ => {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					"one of the supplied arguments is invalid",
+				))
+			}
+		};
+
+		let (stats, next) = if cursor.is_some() || limit.is_some() {
+			let Ok(cursor) = cursor.as_deref().map(serde_json::from_str).transpose() else {
+				return Err(Status::new(Code::InvalidArgument, "cursor is invalid"));
+			};
+
+			let Ok(page) = store
+				.get_statistics_page(stat_desc, cursor, page_size(limit)?)
+				.await
+			else {
+				return Err(Status::new(Code::Internal, "store operation failed"));
+			};
+
+			let Ok(next) = page.next.as_ref().map(serde_json::to_string).transpose() else {
+				return Err(Status::new(Code::Internal, "cursor could not be created"));
+			};
+
+			(page.items, next)
+		} else {
+			let Ok(stats) = store.get_statistics(stat_desc).await else {
+				return Err(Status::new(Code::Internal, "store operation failed"));
+			};
+
+			(stats.collect(), None)
+		};
+
+		let statistics = stats
+			.into_iter()
+			.map(|(s, v)| rpc::StatisticWithValue {
+				link: s.link.to_string(),
+				r#type: s.stat_type.to_string(),
+				data: s.data.to_string(),
+				time: s.time.to_string(),
+				value: v.get(),
+			})
+			.collect();
+
+		let res = Ok(Response::new(rpc::GetStatisticsResponse {
+			statistics,
+			next,
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_get_statistics_summary", skip_all, fields(store = %self.store.backend_name()))]
+	async fn get_statistics_summary(
+		&self,
+		req: Request<rpc::GetStatisticsSummaryRequest>,
+	) -> Result<Response<rpc::GetStatisticsSummaryResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::GetStatisticsSummaryRequest {
+			link,
+			r#type: stat_type,
+			data,
+			time: stat_time,
+			group_by,
+			time_bucket,
+		} = req.into_inner();
+
+		let stat_desc = match (
+			link.map(TryInto::try_into).transpose(),
+			stat_type.map(|s| s.as_str().try_into()).transpose(),
+			data.map(TryInto::try_into).transpose(),
+			stat_time.map(|t| t.as_str().try_into()).transpose(),
+		) {
+			(Ok(link), Ok(stat_type), Ok(data), Ok(time)) => StatisticDescription {
+				link,
+				stat_type,
+				data,
+				time,
+			},
+			_ => {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					"one of the supplied arguments is invalid",
+				))
+			}
+		};
+
+		let grouping = statistic_grouping(&group_by, time_bucket)?;
+
+		let Ok(stats) = store.get_statistics(stat_desc).await else {
+			return Err(Status::new(Code::Internal, "store operation failed"));
+		};
+
+		let totals = grouping
+			.summarize(stats)
+			.into_iter()
+			.map(|total| rpc::StatisticTotal {
+				link: total.link.map(|l| l.to_string()),
+				r#type: total.stat_type.map(|t| t.to_string()),
+				data: total.data.map(|d| d.to_string()),
+				time: total.time.map(|t| t.to_string()),
+				total: total.total,
+				count: total.count,
+			})
+			.collect();
+
+		let res = Ok(Response::new(rpc::GetStatisticsSummaryResponse { totals }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_rem_statistics", skip_all, fields(store = %self.store.backend_name()))]
+	async fn rem_statistics(
+		&self,
+		req: Request<rpc::RemStatisticsRequest>,
+	) -> Result<Response<rpc::RemStatisticsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Write)?;
+		let store = self.namespaced_store(&req).await?;
+		self.check_quota(&req, 1).await?;
 
 		let rpc::RemStatisticsRequest {
 			link,
@@ -403,4 +1654,484 @@ impl Links for Api {
 
 		res
 	}
+
+	#[instrument(level = "info", name = "rpc_watch_statistics", skip_all, fields(store = %self.store.backend_name()))]
+	async fn watch_statistics(
+		&self,
+		req: Request<rpc::WatchStatisticsRequest>,
+	) -> Result<Response<Self::WatchStatisticsStream>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::ReadOnly)?;
+		let store = self.namespaced_store(&req).await?;
+
+		let rpc::WatchStatisticsRequest {
+			link,
+			r#type: stat_type,
+			data,
+			time: stat_time,
+		} = req.into_inner();
+
+		let stat_desc = match (
+			link.map(TryInto::try_into).transpose(),
+			stat_type.map(|s| s.as_str().try_into()).transpose(),
+			data.map(TryInto::try_into).transpose(),
+			stat_time.map(|t| t.as_str().try_into()).transpose(),
+		) {
+			(Ok(link), Ok(stat_type), Ok(data), Ok(time)) => StatisticDescription {
+				link,
+				stat_type,
+				data,
+				time,
+			},
+			_ => {
+				return Err(Status::new(
+					Code::InvalidArgument,
+					"one of the supplied arguments is invalid",
+				))
+			}
+		};
+
+		#[expect(
+			clippy::result_large_err,
+			reason = "the item type is required by tonic's streaming responses"
+		)]
+		let statistics = store.watch_statistics(stat_desc).map(|(s, v)| {
+			Ok(rpc::StatisticWithValue {
+				link: s.link.to_string(),
+				r#type: s.stat_type.to_string(),
+				data: s.data.to_string(),
+				time: s.time.to_string(),
+				value: v.map_or(0, StatisticValue::get),
+			})
+		});
+
+		let res: Result<Response<Self::WatchStatisticsStream>, Status> =
+			Ok(Response::new(Box::pin(statistics)));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_add_listener", skip_all)]
+	async fn add_listener(
+		&self,
+		req: Request<rpc::AddListenerRequest>,
+	) -> Result<Response<rpc::AddListenerResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let rpc::AddListenerRequest { address, persist } = req.into_inner();
+		let (config, listeners, addr) = self.listener_change(&address, persist)?;
+
+		let added = match listeners.add(addr.clone()).await {
+			Ok(added) => added,
+			Err(err) => {
+				return Err(Status::new(
+					Code::FailedPrecondition,
+					format!("could not listen on {addr}: {err}"),
+				))
+			}
+		};
+
+		if persist {
+			let persisted = spawn_blocking(move || persist_listener(config, addr, true)).await;
+			if !matches!(persisted, Ok(Ok(()))) {
+				return Err(Status::new(
+					Code::Internal,
+					"the listener was added, but the configuration file could not be updated",
+				));
+			}
+		}
+
+		let res = Ok(Response::new(rpc::AddListenerResponse { added }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_remove_listener", skip_all)]
+	async fn remove_listener(
+		&self,
+		req: Request<rpc::RemoveListenerRequest>,
+	) -> Result<Response<rpc::RemoveListenerResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let persist = req.get_ref().persist;
+		let (config, listeners, addr) = self.listener_change(&req.get_ref().address, persist)?;
+
+		if is_serving(&req, &addr) {
+			return Err(Status::new(
+				Code::FailedPrecondition,
+				"the listener serving this request can not be removed",
+			));
+		}
+
+		// Dropping a listener blocks until it is closed
+		let Ok((removed, addr)) = spawn_blocking(move || (listeners.remove(&addr), addr)).await
+		else {
+			return Err(Status::new(
+				Code::Internal,
+				"the listener could not be removed",
+			));
+		};
+
+		if persist {
+			let persisted = spawn_blocking(move || persist_listener(config, addr, false)).await;
+			if !matches!(persisted, Ok(Ok(()))) {
+				return Err(Status::new(
+					Code::Internal,
+					"the listener was removed, but the configuration file could not be updated",
+				));
+			}
+		}
+
+		let res = Ok(Response::new(rpc::RemoveListenerResponse { removed }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_list_listeners", skip_all)]
+	async fn list_listeners(
+		&self,
+		req: Request<rpc::ListListenersRequest>,
+	) -> Result<Response<rpc::ListListenersResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let Some(listeners) = self.listeners else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"listeners can not be listed on this server",
+			));
+		};
+
+		let mut listeners = listeners
+			.list()
+			.into_iter()
+			.map(|info| rpc::ListenerInfo {
+				address: info.address.to_string(),
+				protocol: info.address.protocol.to_string(),
+				ip: info.address.address.map(|ip| ip.to_string()),
+				port: info.address.port.map_or(0, u32::from),
+				path: info.address.path.map(|p| p.display().to_string()),
+				accepted: info.accepted,
+				errors: info.errors,
+			})
+			.collect::<Vec<_>>();
+		listeners.sort_unstable_by(|a, b| a.address.cmp(&b.address));
+
+		let res = Ok(Response::new(rpc::ListListenersResponse { listeners }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_reload_certificates", skip_all)]
+	async fn reload_certificates(
+		&self,
+		req: Request<rpc::ReloadCertificatesRequest>,
+	) -> Result<Response<rpc::ReloadCertificatesResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let (Some(config), Some(cert_resolver)) = (self.config, self.cert_resolver.clone()) else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"certificates can not be reloaded on this server",
+			));
+		};
+
+		let Ok(reload) = spawn_blocking(move || {
+			cert_resolver.reload(config.default_certificate(), &config.certificates())
+		})
+		.await
+		else {
+			return Err(Status::new(
+				Code::Internal,
+				"the certificates could not be reloaded",
+			));
+		};
+
+		info!(
+			reloaded = reload.reloaded,
+			errors = reload.errors.len(),
+			"tls certificates reloaded"
+		);
+
+		let res = Ok(Response::new(rpc::ReloadCertificatesResponse {
+			reloaded: u32::try_from(reload.reloaded).unwrap_or(u32::MAX),
+			errors: reload.errors,
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_get_certificate_lookups", skip_all)]
+	async fn get_certificate_lookups(
+		&self,
+		req: Request<rpc::GetCertificateLookupsRequest>,
+	) -> Result<Response<rpc::GetCertificateLookupsResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let Some(cert_resolver) = &self.cert_resolver else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"certificate lookups are not available on this server",
+			));
+		};
+
+		let lookups = cert_resolver.lookups();
+		let counts = |counts: LookupCounts| rpc::CertificateLookups {
+			exact: counts.exact,
+			wildcard: counts.wildcard,
+			default: counts.default,
+			unresolved: counts.unresolved,
+		};
+
+		let res = Ok(Response::new(rpc::GetCertificateLookupsResponse {
+			domains: lookups
+				.domains
+				.into_iter()
+				.map(|(domain, lookups)| rpc::DomainCertificateLookups {
+					domain,
+					lookups: Some(counts(lookups)),
+				})
+				.collect(),
+			without_sni: Some(counts(lookups.without_sni)),
+			untracked: Some(counts(lookups.untracked)),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_rotate_token", skip_all)]
+	async fn rotate_token(
+		&self,
+		req: Request<rpc::RotateTokenRequest>,
+	) -> Result<Response<rpc::RotateTokenResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let Some(config) = self.config else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"tokens can not be rotated on this server",
+			));
+		};
+
+		let rpc::RotateTokenRequest {
+			id,
+			token,
+			grace_period,
+		} = req.into_inner();
+
+		if grace_period > u64::from(A_YEAR) {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"grace period must be at most a year",
+			));
+		}
+
+		let token = token.unwrap_or_else(ApiToken::random_token);
+		if token.is_empty() {
+			return Err(Status::new(
+				Code::InvalidArgument,
+				"token must not be empty",
+			));
+		}
+
+		let Some(role) = config.rotate_token(&id, token.clone(), Duration::from_secs(grace_period))
+		else {
+			return Err(Status::new(Code::NotFound, "token does not exist"));
+		};
+
+		info!(token_id = %id, %role, grace_period, "api token rotated");
+
+		let res = Ok(Response::new(rpc::RotateTokenResponse { token }));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+
+	#[instrument(level = "info", name = "rpc_reload_config", skip_all, fields(store = %self.store.backend_name()))]
+	async fn reload_config(
+		&self,
+		req: Request<rpc::ReloadConfigRequest>,
+	) -> Result<Response<rpc::ReloadConfigResponse>, Status> {
+		let time = Instant::now();
+		check_role(&req, TokenRole::Admin)?;
+
+		let Some(config) = self.config else {
+			return Err(Status::new(
+				Code::Unimplemented,
+				"the configuration can not be reloaded on this server",
+			));
+		};
+
+		let Some(changed) = config.reload().await else {
+			return Err(Status::new(Code::Internal, "configuration reload failed"));
+		};
+
+		info!(?changed, "configuration reloaded");
+
+		let res = Ok(Response::new(rpc::ReloadConfigResponse {
+			changed: changed.into_iter().map(ToString::to_string).collect(),
+		}));
+
+		let time = time.elapsed();
+		info!(
+			time_ns = %time.as_nanos(),
+			success = %res.is_ok(),
+			"rpc processed in {:.6} seconds",
+			time.as_secs_f64()
+		);
+
+		res
+	}
+}
+
+/// The standard gRPC health checking service (`grpc.health.v1.Health`).
+///
+/// This reports whether this server can serve RPCs based on the health of its
+/// store, so that load balancers and orchestrators (e.g. Kubernetes probes)
+/// can use native gRPC health checks. Both the overall server (the empty
+/// service name) and the links service (`links.Links`) are `SERVING` if the
+/// store is healthy, and `NOT_SERVING` otherwise. Unlike the links service,
+/// this service does not require authentication.
+#[derive(Debug)]
+pub struct HealthApi {
+	store: &'static Current,
+}
+
+impl HealthApi {
+	/// Create a new health checking service instance, reporting the health of
+	/// the `store`
+	pub const fn new(store: &'static Current) -> Self {
+		Self { store }
+	}
+
+	/// Get the serving status of the `service`, or `None` if it is unknown
+	async fn status(&self, service: &str) -> Option<ServingStatus> {
+		if !service.is_empty() && service != <LinksServer<Api> as NamedService>::NAME {
+			return None;
+		}
+
+		let health = self.store.get().health().await;
+		if health.healthy {
+			Some(ServingStatus::Serving)
+		} else {
+			debug!(error = ?health.error, "store is not healthy");
+			Some(ServingStatus::NotServing)
+		}
+	}
+}
+
+#[tonic::async_trait]
+impl Health for HealthApi {
+	type WatchStream = Pin<Box<dyn Stream<Item = Result<HealthCheckResponse, Status>> + Send>>;
+
+	#[instrument(level = "debug", name = "rpc_health_check", skip_all)]
+	async fn check(
+		&self,
+		req: Request<HealthCheckRequest>,
+	) -> Result<Response<HealthCheckResponse>, Status> {
+		let Some(status) = self.status(&req.into_inner().service).await else {
+			return Err(Status::new(Code::NotFound, "unknown service"));
+		};
+
+		Ok(Response::new(HealthCheckResponse {
+			status: status.into(),
+		}))
+	}
+
+	#[instrument(level = "debug", name = "rpc_health_watch", skip_all)]
+	async fn watch(
+		&self,
+		req: Request<HealthCheckRequest>,
+	) -> Result<Response<Self::WatchStream>, Status> {
+		let service = req.into_inner().service;
+		let store = self.store;
+
+		// The current status is always sent first, then only changes to it
+		let statuses = stream::unfold(None, move |last| {
+			let api = Self::new(store);
+			let service = service.clone();
+
+			async move {
+				loop {
+					if last.is_some() {
+						sleep(HEALTH_WATCH_INTERVAL).await;
+					}
+
+					let status = api
+						.status(&service)
+						.await
+						.unwrap_or(ServingStatus::ServiceUnknown);
+
+					if last != Some(status) {
+						let res = Ok(HealthCheckResponse {
+							status: status.into(),
+						});
+						return Some((res, Some(status)));
+					}
+				}
+			}
+		});
+
+		Ok(Response::new(Box::pin(statuses)))
+	}
 }
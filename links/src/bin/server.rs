@@ -23,9 +23,9 @@
 //! backends are supported.
 
 use std::{
+	path::PathBuf,
 	sync::{
 		atomic::{AtomicUsize, Ordering},
-		mpsc::{self, RecvTimeoutError},
 		Arc,
 	},
 	thread,
@@ -33,15 +33,19 @@ use std::{
 };
 
 use anyhow::anyhow;
-use crossbeam_channel::unbounded;
+use crossbeam_channel::{select, unbounded};
 use links::{
 	certs::CertificateResolver,
-	config::{CertConfigUpdate, CertificateWatcher, Config, DefaultCertificateSource, LogLevel},
+	config::{
+		CertConfigUpdate, CertificateWatcher, Config, DefaultCertificateSource, LogLevel, Partial,
+	},
+	reputation,
 	server::{
-		store_setup, Listener, PlainHttpAcceptor, PlainRpcAcceptor, Protocol, TlsHttpAcceptor,
-		TlsRpcAcceptor,
+		store_setup, Acceptors, Listeners, PlainHttpAcceptor, PlainHttpApiAcceptor,
+		PlainRpcAcceptor, SessionTicketer, TlsHttpAcceptor, TlsRpcAcceptor,
 	},
-	store::Current,
+	stats::anomaly,
+	store::{migrate, Current, Store},
 	util::{stringify_map, SERVER_HELP, SERVER_NAME},
 };
 use notify::{EventKind, RecursiveMode, Watcher};
@@ -150,35 +154,74 @@ fn main() -> Result<(), anyhow::Error> {
 
 	// Initialize the store
 	let store = rt.block_on(store_setup(config, args.contains("--example-redirect")))?;
+
+	// Migrate all data to another store and exit instead of starting the
+	// server, if requested
+	if let Some(path) = args.opt_value_from_str::<_, PathBuf>("--migrate-to")? {
+		let policy = args
+			.opt_value_from_str("--migrate-policy")?
+			.unwrap_or_default();
+		let dry_run = args.contains("--dry-run");
+		let destination = Partial::from_file(&path)?;
+		let destination = rt.block_on(Store::new(
+			destination.store.unwrap_or_default(),
+			&destination.store_config.unwrap_or_default(),
+		))?;
+
+		info!(from = store.backend_name(), to = destination.backend_name(), %policy, dry_run, "Migrating store data");
+
+		let summary = rt.block_on(migrate(&store, &destination, policy, dry_run, |read| {
+			info!(read, "Migration in progress");
+		}))?;
+
+		info!(
+			migrated = summary.imported,
+			skipped = summary.skipped,
+			dry_run,
+			"Migration complete"
+		);
+
+		return Ok(());
+	}
 	let current_store = Current::new_static(store);
 
 	// Initialize all acceptors
-	let plain_http_acceptor = PlainHttpAcceptor::new(config, current_store);
-	let tls_http_acceptor = TlsHttpAcceptor::new(config, current_store, cert_resolver.clone());
-	let plain_rpc_acceptor = PlainRpcAcceptor::new(config, current_store);
-	let tls_rpc_acceptor = TlsRpcAcceptor::new(config, current_store, cert_resolver.clone());
+	let listeners = Listeners::new_static();
+	let ticketer = SessionTicketer::new(config);
+	let acceptors = Acceptors {
+		plain_http: PlainHttpAcceptor::new(config, current_store, cert_resolver.clone()),
+		tls_http: TlsHttpAcceptor::new(
+			config,
+			current_store,
+			cert_resolver.clone(),
+			Arc::clone(&ticketer),
+		),
+		plain_rpc: PlainRpcAcceptor::new(config, current_store, listeners, cert_resolver.clone()),
+		tls_rpc: TlsRpcAcceptor::new(
+			config,
+			current_store,
+			listeners,
+			cert_resolver.clone(),
+			ticketer,
+		),
+		plain_http_api: PlainHttpApiAcceptor::new(config, current_store),
+	};
+	listeners
+		.set_acceptors(acceptors)
+		.expect("listener acceptors are only set once");
 
 	// Set up listeners
-	let mut listeners = Vec::new();
-
 	for addr in config.listeners() {
-		listeners.push(match addr.protocol {
-			Protocol::Http => {
-				rt.block_on(Listener::new(addr.address, addr.port, plain_http_acceptor))?
-			}
-			Protocol::Https => {
-				rt.block_on(Listener::new(addr.address, addr.port, tls_http_acceptor))?
-			}
-			Protocol::Grpc => {
-				rt.block_on(Listener::new(addr.address, addr.port, plain_rpc_acceptor))?
-			}
-			Protocol::Grpcs => {
-				rt.block_on(Listener::new(addr.address, addr.port, tls_rpc_acceptor))?
-			}
-		})
+		rt.block_on(listeners.add(addr))?;
 	}
 
-	let (watcher_tx, watcher_rx) = mpsc::channel();
+	// Start analyzing redirect traffic in the background (if enabled)
+	rt.spawn(anomaly::run(config));
+
+	// Check the reputation of link destinations in the background (if enabled)
+	rt.spawn(reputation::run(config, current_store));
+
+	let (watcher_tx, watcher_rx) = unbounded();
 	let mut file_watcher = notify::recommended_watcher(move |res| match res {
 		Ok(event) => {
 			if let Err(err) = watcher_tx.send(event) {
@@ -208,28 +251,44 @@ fn main() -> Result<(), anyhow::Error> {
 	);
 
 	// The `links-config` thread is responsible for updating the server's
-	// configuration when it is changed
+	// configuration when it is changed or a reload is requested via the API
 	let cert_watcher_updates_tx = cert_watcher.get_config_sender();
+	let reload_rx = config
+		.take_reload_requests()
+		.expect("config reload requests are only taken once");
 	let config_thread = thread::Builder::new()
 		.name("links-config".to_string())
 		.spawn(move || loop {
-			match watcher_rx.recv_timeout(if last_file_event.is_none() {
+			let timeout = if last_file_event.is_none() {
 				watcher_timeout
 			} else {
 				watcher_debounce.min(watcher_timeout) / 4
-			}) {
-				Ok(event) if matches!(event.kind, EventKind::Access(_)) => {
-					debug!(?event, "Ignoring file event from watcher");
-				}
-				Ok(event) => {
-					debug!(?event, "Received file event from watcher");
-					last_file_event = Some(Instant::now());
-				}
-				Err(RecvTimeoutError::Disconnected) => error!("File watching error"),
-				Err(RecvTimeoutError::Timeout) => (),
-			}
+			};
+
+			let reload_request = select! {
+				recv(watcher_rx) -> event => {
+					match event {
+						Ok(event) if matches!(event.kind, EventKind::Access(_)) => {
+							debug!(?event, "Ignoring file event from watcher");
+						}
+						Ok(event) => {
+							debug!(?event, "Received file event from watcher");
+							last_file_event = Some(Instant::now());
+						}
+						Err(_) => error!("File watching error"),
+					}
+
+					None
+				},
+				recv(reload_rx) -> request => request.ok(),
+				default(timeout) => None,
+			};
 
-			if last_file_event.is_none() || last_file_event.unwrap().elapsed() < watcher_debounce {
+			if reload_request.is_some() {
+				debug!("Reloading configuration as requested");
+			} else if last_file_event.is_none()
+				|| last_file_event.unwrap().elapsed() < watcher_debounce
+			{
 				continue;
 			}
 
@@ -241,7 +300,7 @@ fn main() -> Result<(), anyhow::Error> {
 			let old_certs = config.certificates();
 			let old_store = (config.store(), config.store_config());
 			let old_listeners = config.listeners();
-			config.update();
+			let changes = config.update();
 			let new_default_cert = config.default_certificate();
 			let new_certs = config.certificates();
 			let new_store = (config.store(), config.store_config());
@@ -330,71 +389,28 @@ fn main() -> Result<(), anyhow::Error> {
 				debug!("Store config not changed, continuing with existing store");
 			}
 
-			// Update listeners per the new config
-			listeners.retain(|l| new_listeners.contains(&l.listen_address()));
-
-			for addr in new_listeners {
-				if !old_listeners.contains(&addr) {
-					listeners.push(match addr.protocol {
-						Protocol::Http => {
-							match rt.block_on(Listener::new(
-								addr.address,
-								addr.port,
-								plain_http_acceptor,
-							)) {
-								Ok(listener) => listener,
-								Err(err) => {
-									error!("Error creating new listener on \"{addr}\": {err}");
-									continue;
-								}
-							}
-						}
-						Protocol::Https => match rt.block_on(Listener::new(
-							addr.address,
-							addr.port,
-							tls_http_acceptor,
-						)) {
-							Ok(listener) => listener,
-							Err(err) => {
-								error!("Error creating new listener on \"{addr}\": {err}");
-								continue;
-							}
-						},
-						Protocol::Grpc => match rt.block_on(Listener::new(
-							addr.address,
-							addr.port,
-							plain_rpc_acceptor,
-						)) {
-							Ok(listener) => listener,
-							Err(err) => {
-								error!("Error creating new listener on \"{addr}\": {err}");
-								continue;
-							}
-						},
-						Protocol::Grpcs => match rt.block_on(Listener::new(
-							addr.address,
-							addr.port,
-							tls_rpc_acceptor,
-						)) {
-							Ok(listener) => listener,
-							Err(err) => {
-								error!("Error creating new listener on \"{addr}\": {err}");
-								continue;
-							}
-						},
-					})
+			// Update listeners per the new config, keeping any listeners added or
+			// removed via the RPC API if the configuration doesn't change them
+			for addr in old_listeners.iter().filter(|a| !new_listeners.contains(a)) {
+				listeners.remove(addr);
+			}
+
+			for addr in new_listeners.iter().filter(|a| !old_listeners.contains(a)) {
+				if let Err(err) = rt.block_on(listeners.add(addr.clone())) {
+					error!("Error creating new listener on \"{addr}\": {err}");
 				}
 			}
 
 			debug!(
 				"Updated listeners, currently active: {:?}",
-				listeners
-					.iter()
-					.map(|l| l.listen_address())
-					.collect::<Vec<_>>()
+				listeners.addresses()
 			);
 
-			info!(?config, "Configuration reloaded");
+			info!(?config, ?changes, "Configuration reloaded");
+
+			if let Some(request) = reload_request {
+				request.respond(changes);
+			}
 		})
 		.expect("error spawning configuration-reloading thread");
 
@@ -419,17 +435,8 @@ fn main() -> Result<(), anyhow::Error> {
 			for source in sources {
 				debug!(?source, "Updating certificate source");
 
-				let certkey = match source.get_certkey().map(Arc::new) {
-					Ok(certkey) => certkey,
-					Err(error) => {
-						error!(%error, "Couldn't get TLS certificate / key");
-						continue;
-					}
-				};
-
-				for domain in source.domains {
-					debug!("Updating certificate for {domain}");
-					resolver.set(domain, Arc::clone(&certkey));
+				if let Err(error) = resolver.load_source(&source) {
+					error!(%error, "Couldn't get TLS certificate / key");
 				}
 			}
 
@@ -464,28 +471,18 @@ fn main() -> Result<(), anyhow::Error> {
 						info!("Default certificate removed");
 					}
 				}
-				CertConfigUpdate::SourceAdded(source) => match source.get_certkey().map(Arc::new) {
-					Ok(certkey) => {
-						for domain in &source.domains {
-							debug!("Setting certificate for {domain}");
-							cert_resolver.set(domain.clone(), Arc::clone(&certkey));
-						}
-
-						info!(?source, "Certificate updated");
-					}
-					Err(err) => {
-						error!(%err, ?source, "Error updating certificate");
-					}
+				CertConfigUpdate::SourceAdded(source) => match cert_resolver.load_source(&source) {
+					Ok(()) => info!(?source, "Certificate updated"),
+					Err(err) => error!(%err, ?source, "Error updating certificate"),
 				},
 				CertConfigUpdate::SourceRemoved(source) => {
-					for domain in &source.domains {
-						debug!("Removing certificate for {domain}");
-						cert_resolver.remove(domain);
-					}
-
+					cert_resolver.remove_source(&source);
 					info!(?source, "Certificate removed");
 				}
 			}
+
+			// Keep the precedence of sources with the same priority in configuration order
+			cert_resolver.order_sources(&config.certificates());
 		})
 		.expect("error spawning certificate-updating thread");
 
@@ -0,0 +1,116 @@
+//! Destination domain blocklist
+//!
+//! The `blocked_domains` configuration option lists domains which links must
+//! never point to, e.g. known phishing or malware hosts. Redirects to these
+//! domains can not be created or changed via the APIs, and if the
+//! `recheck_blocked_domains` option is enabled, link destinations (including
+//! those chosen using link options) are also checked when redirecting, so that
+//! links created before a domain was blocked are no longer followed.
+//!
+//! Entries are either exact domains (e.g. `example.com`), which only block
+//! that domain, or wildcards (e.g. `*.example.com`), which block all of its
+//! subdomains (e.g. `www.example.com` or `a.b.example.com`), but not the domain
+//! itself. Links to IP addresses are never blocked.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use hyper::Uri;
+use links_domainmap::{Domain, DomainMap};
+use links_normalized::Link;
+
+/// A list of blocked link destination domains
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Blocklist {
+	/// The blocked domains, including wildcards
+	domains: DomainMap<()>,
+}
+
+impl Blocklist {
+	/// Create a new blocklist of the `domains`
+	#[must_use]
+	pub fn new(domains: impl IntoIterator<Item = Domain>) -> Self {
+		Self {
+			domains: domains.into_iter().map(|domain| (domain, ())).collect(),
+		}
+	}
+
+	/// Whether this blocklist is empty, so that no links are blocked
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.domains.is_empty()
+	}
+
+	/// Check whether the host of the `link` is blocked
+	#[must_use]
+	pub fn is_blocked(&self, link: &Link) -> bool {
+		if self.is_empty() {
+			return false;
+		}
+
+		link.as_str()
+			.parse::<Uri>()
+			.ok()
+			.and_then(|uri| uri.host().map(|host| self.is_blocked_host(host)))
+			.unwrap_or(false)
+	}
+
+	/// Check whether the `host` (a domain name or IP address) is blocked
+	fn is_blocked_host(&self, host: &str) -> bool {
+		let host = host.strip_suffix('.').unwrap_or(host);
+
+		let Ok(domain) = Domain::reference(host) else {
+			return false;
+		};
+
+		// This also matches wildcards one level above the host
+		if self.domains.get(&domain).is_some() {
+			return true;
+		}
+
+		// Wildcards block subdomains at any depth, so all higher-level
+		// wildcards need to be checked as well
+		host.match_indices('.')
+			.skip(1)
+			.filter_map(|(i, _)| Domain::presented(&format!("*{}", &host[i..])).ok())
+			.any(|wildcard| self.domains.get_eq(&wildcard).is_some())
+	}
+}
+
+impl Debug for Blocklist {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_list()
+			.entries(self.domains.iter().map(|(domain, ())| domain.to_string()))
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn blocked() {
+		let blocklist = Blocklist::new([
+			Domain::presented("example.com").unwrap(),
+			Domain::presented("*.example.net").unwrap(),
+		]);
+
+		let blocked = |link: &str| blocklist.is_blocked(&Link::new(link).unwrap());
+
+		assert!(blocked("https://example.com/"));
+		assert!(blocked("http://EXAMPLE.com:8080/path?query"));
+		assert!(blocked("https://example.com./"));
+		assert!(!blocked("https://www.example.com/"));
+		assert!(!blocked("https://example.org/"));
+
+		assert!(!blocked("https://example.net/"));
+		assert!(blocked("https://www.example.net/"));
+		assert!(blocked("https://a.b.example.net/"));
+		assert!(!blocked("https://notexample.net/"));
+
+		assert!(!blocked("https://127.0.0.1/"));
+		assert!(!blocked("https://[::1]/"));
+
+		assert!(!Blocklist::default().is_blocked(&Link::new("https://example.com/").unwrap()));
+	}
+}
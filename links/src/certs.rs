@@ -1,18 +1,29 @@
 //! Links server certificate handling.
 
 use std::{
+	collections::BTreeMap,
 	fmt::{Debug, Formatter, Result as FmtResult},
-	sync::{Arc, RwLock},
+	fs, mem,
+	path::Path,
+	sync::{Arc, Mutex, RwLock},
 };
 
 use links_domainmap::{Domain, DomainMap};
 use tokio_rustls::rustls::{
-	server::{ClientHello, ResolvesServerCert},
+	pki_types::CertificateDer,
+	server::{danger::ClientCertVerifier, ClientHello, ResolvesServerCert, WebPkiClientVerifier},
 	sign::CertifiedKey,
+	RootCertStore,
 };
-use tracing::debug;
+use tracing::{debug, error};
 
-use crate::util::Unpoison;
+use crate::{
+	config::{
+		CertificateAcquisitionError, CertificateSource, CertificateSourceType,
+		DefaultCertificateSource, TlsPolicy,
+	},
+	util::Unpoison,
+};
 
 /// A per-domain [`ResolvesServerCert`] implementor with fallback.
 ///
@@ -20,12 +31,41 @@ use crate::util::Unpoison;
 /// The default certificate for unknown or unrecognized domain names can be
 /// specified using `default`.
 ///
+/// If multiple certificate sources cover the same domain, the certificate from
+/// the source with the highest `priority` is used. Of sources with the same
+/// priority, the one loaded later (or, after [`order_sources`], listed later)
+/// takes precedence. The certificates of each source are kept, so removing a
+/// source falls back to the next source covering its domains (if any).
+///
+/// Every certificate lookup made during a TLS handshake is counted per
+/// requested server name, by whether it was resolved using a certificate for
+/// exactly that domain, a wildcard certificate, or the default certificate (or
+/// not at all), see [`lookups`].
+///
+/// [`order_sources`]: CertificateResolver::order_sources
+/// [`lookups`]: CertificateResolver::lookups
 /// [`ResolvesServerCert`]: https://docs.rs/rustls/latest/rustls/server/trait.ResolvesServerCert.html
 pub struct CertificateResolver {
 	/// The map containing all certificates
 	certs: RwLock<DomainMap<Arc<CertifiedKey>>>,
 	/// Default certificate/key for unknown and unrecognized domain names
 	default: RwLock<Option<Arc<CertifiedKey>>>,
+	/// The TLS policies of domains whose sources don't use the default policy
+	policies: RwLock<DomainMap<TlsPolicy>>,
+	/// The certificates last loaded from each source, in order of precedence
+	/// (for sources with the same priority)
+	sources: RwLock<Vec<LoadedSource>>,
+	/// The counts of certificate lookups made during TLS handshakes
+	lookups: Mutex<CertificateLookups>,
+}
+
+/// The certificates loaded from a certificate source, see
+/// [`CertificateResolver::load_source`]
+struct LoadedSource {
+	/// The certificate source
+	source: CertificateSource,
+	/// The certificate for each domain covered by the source
+	certs: Vec<(Domain, Arc<CertifiedKey>)>,
 }
 
 impl CertificateResolver {
@@ -35,6 +75,9 @@ impl CertificateResolver {
 		Self {
 			certs: RwLock::new(DomainMap::new()),
 			default: RwLock::new(None),
+			policies: RwLock::new(DomainMap::new()),
+			sources: RwLock::new(Vec::new()),
+			lookups: Mutex::new(CertificateLookups::new()),
 		}
 	}
 
@@ -46,16 +89,56 @@ impl CertificateResolver {
 	/// Get the matching `CertifiedKey` for the given reference identifier
 	/// domain name
 	pub fn get(&self, domain: Option<&Domain>) -> Option<Arc<CertifiedKey>> {
-		domain.map_or_else(
-			|| self.get_default(),
-			|domain| {
-				self.certs
-					.read()
-					.unpoison()
-					.get(domain)
-					.map_or_else(|| self.get_default(), |certkey| Some(Arc::clone(certkey)))
+		self.find(domain).0
+	}
+
+	/// Get the matching `CertifiedKey` for the given reference identifier
+	/// domain name, along with how it was found
+	fn find(&self, domain: Option<&Domain>) -> (Option<Arc<CertifiedKey>>, Lookup) {
+		let found = domain.and_then(|domain| {
+			let certs = self.certs.read().unpoison();
+			certs
+				.get_eq(domain)
+				.map(|certkey| (Arc::clone(certkey), Lookup::Exact))
+				.or_else(|| {
+					certs
+						.get(domain)
+						.map(|certkey| (Arc::clone(certkey), Lookup::Wildcard))
+				})
+		});
+
+		match found {
+			Some((certkey, lookup)) => (Some(certkey), lookup),
+			None => match self.get_default() {
+				Some(certkey) => (Some(certkey), Lookup::Default),
+				None => (None, Lookup::Unresolved),
 			},
-		)
+		}
+	}
+
+	/// Get the matching `CertifiedKey` for the given reference identifier
+	/// domain name like [`get`][Self::get], counting the lookup in
+	/// [`lookups`][Self::lookups]
+	fn lookup(&self, domain: Option<&Domain>) -> Option<Arc<CertifiedKey>> {
+		let (certkey, lookup) = self.find(domain);
+		self.lookups.lock().unpoison().count(domain, lookup);
+		certkey
+	}
+
+	/// Get the counts of certificate lookups made during TLS handshakes since
+	/// this resolver was created. Server names for which clients only get the
+	/// default certificate (or none at all) may indicate DNS records pointing
+	/// at this server which it is not configured for.
+	pub fn lookups(&self) -> CertificateLookups {
+		self.lookups.lock().unpoison().clone()
+	}
+
+	/// Get the [`TlsPolicy`] for connections to the given reference identifier
+	/// domain name, which is the default policy for unknown domains
+	pub fn policy(&self, domain: Option<&Domain>) -> TlsPolicy {
+		domain
+			.and_then(|domain| self.policies.read().unpoison().get(domain).cloned())
+			.unwrap_or_default()
 	}
 
 	/// Set the cert-key pair for the given domain. All future calls to `get` or
@@ -64,6 +147,13 @@ impl CertificateResolver {
 		self.certs.write().unpoison().set(domain, certkey);
 	}
 
+	/// Check whether any certificates (including the default one) are
+	/// available to be resolved
+	#[must_use]
+	pub fn has_certificates(&self) -> bool {
+		self.default.read().unpoison().is_some() || !self.certs.read().unpoison().is_empty()
+	}
+
 	/// Set the default cert-key pair for unknown or unrecognized domains. All
 	/// future calls to `get_default` or `resolve` without a domain name or a
 	/// domain name not found in any other certificate sources will return this
@@ -78,6 +168,311 @@ impl CertificateResolver {
 	pub fn remove(&self, domain: &Domain) {
 		self.certs.write().unpoison().remove(domain);
 	}
+
+	/// Load the certificates and keys from `source` and use them (and the
+	/// source's TLS policy) for their domains, unless another source takes
+	/// precedence. Certificates previously loaded from this source for domains
+	/// it no longer covers (e.g. because a certificate was removed from a
+	/// `directory` source) are removed. If the source can not be read, the
+	/// previous certificates are kept.
+	///
+	/// # IO
+	/// Depending on the type of the source, blocking IO may be performed. This
+	/// function should not be called in async contexts.
+	///
+	/// # Errors
+	/// This function returns an error if the source could not be read, see
+	/// [`CertificateAcquisitionError`] for more details
+	pub fn load_source(
+		&self,
+		source: &CertificateSource,
+	) -> Result<(), CertificateAcquisitionError> {
+		let loaded = LoadedSource {
+			source: source.clone(),
+			certs: source
+				.get_certkeys()?
+				.into_iter()
+				.flat_map(|(domains, certkey)| {
+					let certkey = Arc::new(certkey);
+					domains
+						.into_iter()
+						.map(move |domain| (domain, Arc::clone(&certkey)))
+				})
+				.collect(),
+		};
+
+		let mut sources = self.sources.write().unpoison();
+		let mut domains = loaded
+			.certs
+			.iter()
+			.map(|(domain, _)| domain.clone())
+			.collect::<Vec<_>>();
+
+		// A reloaded source keeps its position, so that its precedence doesn't change
+		if let Some(old) = sources.iter_mut().find(|s| s.source == *source) {
+			let old = mem::replace(old, loaded);
+			domains.extend(old.certs.into_iter().map(|(domain, _)| domain));
+		} else {
+			sources.push(loaded);
+		}
+
+		self.update_domains(&sources, domains);
+
+		Ok(())
+	}
+
+	/// Remove all certificates loaded from `source`, using certificates from
+	/// other sources for its domains instead (if there are any)
+	pub fn remove_source(&self, source: &CertificateSource) {
+		let mut sources = self.sources.write().unpoison();
+
+		let domains = sources
+			.iter()
+			.position(|s| s.source == *source)
+			.map_or_else(
+				|| source.domains.clone(),
+				|i| {
+					sources
+						.remove(i)
+						.certs
+						.into_iter()
+						.map(|(domain, _)| domain)
+						.collect()
+				},
+			);
+
+		self.update_domains(&sources, domains);
+	}
+
+	/// Order the loaded certificate sources like in `order` (usually the
+	/// configured `certificates`), so that of sources with the same priority,
+	/// the one listed later takes precedence. Sources not in `order` are
+	/// placed after all others.
+	pub fn order_sources(&self, order: &[CertificateSource]) {
+		let mut sources = self.sources.write().unpoison();
+
+		sources.sort_by_key(|s| {
+			order
+				.iter()
+				.position(|o| *o == s.source)
+				.unwrap_or(usize::MAX)
+		});
+
+		let domains = sources
+			.iter()
+			.flat_map(|s| s.certs.iter().map(|(domain, _)| domain.clone()))
+			.collect::<Vec<_>>();
+
+		self.update_domains(&sources, domains);
+	}
+
+	/// Use the certificate (and TLS policy) of the source taking precedence
+	/// for each of the `domains`, or remove the domain's certificate if no
+	/// source covers it anymore
+	fn update_domains(&self, sources: &[LoadedSource], mut domains: Vec<Domain>) {
+		let mut certs = self.certs.write().unpoison();
+		let mut policies = self.policies.write().unpoison();
+
+		domains.sort();
+		domains.dedup();
+
+		for domain in &domains {
+			let winner = sources
+				.iter()
+				.enumerate()
+				.filter_map(|(i, s)| {
+					let (_, certkey) = s.certs.iter().find(|(d, _)| d == domain)?;
+					Some(((s.source.priority, i), certkey, &s.source.tls))
+				})
+				.max_by_key(|&(precedence, ..)| precedence);
+
+			if let Some((_, certkey, policy)) = winner {
+				debug!("Setting certificate for {domain}");
+				certs.set(domain.clone(), Arc::clone(certkey));
+
+				if policy.is_default() {
+					policies.remove(domain);
+				} else {
+					policies.set(domain.clone(), policy.clone());
+				}
+			} else {
+				debug!("Removing certificate for {domain}");
+				certs.remove(domain);
+				policies.remove(domain);
+			}
+		}
+	}
+
+	/// Re-read the certificates and keys from the `default` source and all
+	/// other `sources`, replacing the ones currently used (and ordering them
+	/// like `sources`, see [`CertificateResolver::order_sources`]). Sources
+	/// which can not be read keep their previous certificates, and their
+	/// errors are logged and returned.
+	///
+	/// # IO
+	/// This function performs blocking IO, and should not be called in async
+	/// contexts.
+	pub fn reload(
+		&self,
+		default: DefaultCertificateSource,
+		sources: &[CertificateSource],
+	) -> CertificateReload {
+		let mut reload = CertificateReload::default();
+
+		if let Some(default) = default.into_cs() {
+			match default.get_certkey() {
+				Ok(certkey) => {
+					self.set_default(Some(Arc::new(certkey)));
+					reload.reloaded += 1;
+				}
+				Err(err) => {
+					error!(%err, "Couldn't reload default TLS certificate / key");
+					reload.errors.push(format!("default certificate: {err}"));
+				}
+			}
+		}
+
+		for source in sources {
+			match self.load_source(source) {
+				Ok(()) => reload.reloaded += 1,
+				Err(err) => {
+					let description = match &source.source {
+						CertificateSourceType::Directory { path } => {
+							format!("certificates in {}", path.display())
+						}
+						CertificateSourceType::Files { cert: file, .. }
+						| CertificateSourceType::Pkcs12 { file, .. }
+							if source.domains.is_empty() =>
+						{
+							format!("certificate in {}", file.display())
+						}
+						_ => {
+							let domains = source
+								.domains
+								.iter()
+								.map(ToString::to_string)
+								.collect::<Vec<_>>()
+								.join(", ");
+
+							format!("certificate for [{domains}]")
+						}
+					};
+
+					error!(%err, ?source, "Couldn't reload TLS certificate / key");
+					reload.errors.push(format!("{description}: {err}"));
+				}
+			}
+		}
+
+		self.order_sources(sources);
+
+		reload
+	}
+}
+
+/// The result of [reloading][CertificateResolver::reload] certificates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateReload {
+	/// The number of certificate sources which were successfully reloaded
+	pub reloaded: usize,
+	/// Descriptions of the errors of sources which could not be reloaded
+	pub errors: Vec<String>,
+}
+
+/// The maximum number of different server names whose certificate lookups are
+/// counted individually, to limit the memory used when clients send arbitrary
+/// server names
+const MAX_LOOKUP_DOMAINS: usize = 1024;
+
+/// How the certificate for a TLS handshake was resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lookup {
+	/// Using a certificate for exactly the requested domain
+	Exact,
+	/// Using a wildcard certificate matching the requested domain
+	Wildcard,
+	/// Using the default certificate
+	Default,
+	/// No certificate was available
+	Unresolved,
+}
+
+/// The counts of certificate lookups made during TLS handshakes, see
+/// [`CertificateResolver::lookups`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateLookups {
+	/// The lookups for each server name requested by clients
+	pub domains: BTreeMap<String, LookupCounts>,
+	/// The lookups of clients which didn't send a valid server name
+	pub without_sni: LookupCounts,
+	/// The lookups for server names which are not counted individually,
+	/// because too many different server names were already requested
+	pub untracked: LookupCounts,
+}
+
+impl CertificateLookups {
+	/// Create new empty `CertificateLookups`
+	const fn new() -> Self {
+		Self {
+			domains: BTreeMap::new(),
+			without_sni: LookupCounts::new(),
+			untracked: LookupCounts::new(),
+		}
+	}
+
+	/// Count a certificate lookup for the requested `domain`
+	fn count(&mut self, domain: Option<&Domain>, lookup: Lookup) {
+		let Some(domain) = domain else {
+			self.without_sni.count(lookup);
+			return;
+		};
+
+		let domain = domain.to_string();
+		if let Some(counts) = self.domains.get_mut(&domain) {
+			counts.count(lookup);
+		} else if self.domains.len() < MAX_LOOKUP_DOMAINS {
+			self.domains.entry(domain).or_default().count(lookup);
+		} else {
+			self.untracked.count(lookup);
+		}
+	}
+}
+
+/// The number of certificate lookups, by how the certificate was resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupCounts {
+	/// Lookups using a certificate for exactly the requested domain
+	pub exact: u64,
+	/// Lookups using a wildcard certificate
+	pub wildcard: u64,
+	/// Lookups which fell back to the default certificate
+	pub default: u64,
+	/// Lookups for which no certificate was available
+	pub unresolved: u64,
+}
+
+impl LookupCounts {
+	/// Create new `LookupCounts` with all counts at zero
+	const fn new() -> Self {
+		Self {
+			exact: 0,
+			wildcard: 0,
+			default: 0,
+			unresolved: 0,
+		}
+	}
+
+	/// Count one certificate lookup
+	fn count(&mut self, lookup: Lookup) {
+		let count = match lookup {
+			Lookup::Exact => &mut self.exact,
+			Lookup::Wildcard => &mut self.wildcard,
+			Lookup::Default => &mut self.default,
+			Lookup::Unresolved => &mut self.unresolved,
+		};
+
+		*count = count.saturating_add(1);
+	}
 }
 
 impl Default for CertificateResolver {
@@ -88,7 +483,7 @@ impl Default for CertificateResolver {
 
 impl ResolvesServerCert for CertificateResolver {
 	fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
-		let cert = self.get(
+		let cert = self.lookup(
 			client_hello
 				.server_name()
 				.map(Domain::reference)
@@ -114,3 +509,597 @@ impl Debug for CertificateResolver {
 			.finish()
 	}
 }
+
+/// Create a [`ClientCertVerifier`] accepting only client certificates issued by
+/// one of the CA certificates in the PEM file at `ca`.
+///
+/// # IO
+/// This function performs blocking IO, and should not be called in async
+/// contexts.
+///
+/// # Errors
+/// This function returns an error if the file can not be read, or if it
+/// doesn't contain any valid CA certificates.
+///
+/// [`ClientCertVerifier`]: https://docs.rs/rustls/latest/rustls/server/danger/trait.ClientCertVerifier.html
+pub fn client_cert_verifier(
+	ca: &Path,
+) -> Result<Arc<dyn ClientCertVerifier>, CertificateAcquisitionError> {
+	let certs = fs::read(ca)?;
+
+	let mut roots = RootCertStore::empty();
+	for cert in rustls_pemfile::certs(&mut &certs[..]) {
+		roots
+			.add(CertificateDer::from(cert?.to_vec()))
+			.map_err(CertificateAcquisitionError::InvalidCert)?;
+	}
+
+	if roots.is_empty() {
+		return Err(CertificateAcquisitionError::MissingCert);
+	}
+
+	WebPkiClientVerifier::builder(Arc::new(roots))
+		.build()
+		.map_err(CertificateAcquisitionError::InvalidCa)
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio_rustls::rustls::pki_types::UnixTime;
+
+	use super::*;
+	use crate::config::{CertificateFormat, TlsVersion};
+
+	fn read_cert(path: &str) -> CertificateDer<'static> {
+		let cert = fs::read(path).unwrap();
+		let cert = rustls_pemfile::certs(&mut &cert[..])
+			.next()
+			.unwrap()
+			.unwrap();
+		cert
+	}
+
+	#[test]
+	fn client_cert_verifier() {
+		let verifier = super::client_cert_verifier(Path::new("tests/client-ca.pem")).unwrap();
+
+		assert!(verifier.client_auth_mandatory());
+		assert!(verifier
+			.verify_client_cert(&read_cert("tests/client-cert.pem"), &[], UnixTime::now())
+			.is_ok());
+		assert!(verifier
+			.verify_client_cert(&read_cert("tests/other-cert.pem"), &[], UnixTime::now())
+			.is_err());
+
+		assert!(matches!(
+			super::client_cert_verifier(Path::new("tests/key.pem")),
+			Err(CertificateAcquisitionError::MissingCert)
+		));
+		assert!(matches!(
+			super::client_cert_verifier(Path::new("tests/nonexistent.pem")),
+			Err(CertificateAcquisitionError::FileIo(_))
+		));
+	}
+
+	#[test]
+	fn resolver_reload() {
+		let source = |domain: &str, cert: &str, key: &str| CertificateSource {
+			domains: vec![Domain::presented(domain).unwrap()],
+			source: CertificateSourceType::Files {
+				cert: cert.into(),
+				key: key.into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+
+		let resolver = CertificateResolver::new();
+		let default = DefaultCertificateSource::Some {
+			domains: Vec::new(),
+			source: CertificateSourceType::Files {
+				cert: "tests/cert.pem".into(),
+				key: "tests/key.pem".into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+		};
+		let sources = [
+			source("localhost", "tests/cert.pem", "tests/key.pem"),
+			source("example.net", "tests/nonexistent.pem", "tests/key.pem"),
+		];
+
+		let reload = resolver.reload(default, &sources);
+
+		assert_eq!(reload.reloaded, 2);
+		assert_eq!(reload.errors.len(), 1);
+		assert!(reload.errors[0].contains("example.net"));
+		assert!(resolver.get(None).is_some());
+		assert!(resolver
+			.get(Some(&Domain::reference("localhost").unwrap()))
+			.is_some());
+
+		let reload = resolver.reload(DefaultCertificateSource::None, &[]);
+
+		assert_eq!(reload, CertificateReload::default());
+		assert!(resolver.get(None).is_some());
+	}
+
+	#[test]
+	fn resolver_san_domains() {
+		let source = CertificateSource {
+			domains: Vec::new(),
+			source: CertificateSourceType::Files {
+				cert: "tests/cert.pem".into(),
+				key: "tests/key.pem".into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+		let localhost = Domain::reference("localhost").unwrap();
+		let resolver = CertificateResolver::new();
+
+		let certkeys = source.get_certkeys().unwrap();
+		assert_eq!(certkeys.len(), 1);
+		assert_eq!(certkeys[0].0, [Domain::presented("localhost").unwrap()]);
+
+		resolver.load_source(&source).unwrap();
+		assert!(resolver.get(Some(&localhost)).is_some());
+		assert!(resolver
+			.get(Some(&Domain::reference("example.com").unwrap()))
+			.is_none());
+
+		resolver.remove_source(&source);
+		assert!(resolver.get(Some(&localhost)).is_none());
+	}
+
+	#[test]
+	fn der_files_source() {
+		let source = |cert: &str, key: &str, format| CertificateSource {
+			domains: vec![Domain::presented("localhost").unwrap()],
+			source: CertificateSourceType::Files {
+				cert: cert.into(),
+				key: key.into(),
+				format,
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+
+		for (cert, key, format) in [
+			("tests/cert.der", "tests/key.der", CertificateFormat::Auto),
+			("tests/cert.der", "tests/key.der", CertificateFormat::Der),
+			("tests/cert.der", "tests/key.pem", CertificateFormat::Auto),
+			("tests/cert.pem", "tests/key.der", CertificateFormat::Auto),
+		] {
+			let certkey = source(cert, key, format).get_certkey().unwrap();
+			assert_eq!(certkey.cert[0], read_cert("tests/cert.pem"));
+		}
+
+		assert!(matches!(
+			source("tests/cert.der", "tests/key.pem", CertificateFormat::Pem).get_certkey(),
+			Err(CertificateAcquisitionError::MissingCert)
+		));
+		assert!(matches!(
+			source("tests/cert.der", "tests/cert.der", CertificateFormat::Der).get_certkey(),
+			Err(CertificateAcquisitionError::InvalidKey(_))
+		));
+		assert!(matches!(
+			source(
+				"tests/cert.der",
+				"tests/other-key.pem",
+				CertificateFormat::Auto
+			)
+			.get_certkey(),
+			Err(CertificateAcquisitionError::KeyMismatch(_))
+		));
+	}
+
+	#[test]
+	fn encrypted_key_files_source() {
+		let dir = std::env::temp_dir().join("links_test_certs-encrypted_key_files_source");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("passphrase.txt"), "links-test\n").unwrap();
+		fs::write(dir.join("wrong.txt"), "wrong").unwrap();
+		std::env::set_var("LINKS_TEST_KEY_PASSPHRASE", "links-test");
+
+		let source =
+			|passphrase_file: Option<&Path>, passphrase_env: Option<&str>| CertificateSource {
+				domains: vec![Domain::presented("localhost").unwrap()],
+				source: CertificateSourceType::Files {
+					cert: "tests/cert.pem".into(),
+					key: "tests/key-encrypted.pem".into(),
+					format: CertificateFormat::default(),
+					passphrase_file: passphrase_file.map(Path::to_path_buf),
+					passphrase_env: passphrase_env.map(ToString::to_string),
+				},
+				tls: TlsPolicy::default(),
+				priority: 0,
+			};
+
+		for source in [
+			source(Some(&dir.join("passphrase.txt")), None),
+			source(None, Some("LINKS_TEST_KEY_PASSPHRASE")),
+			source(
+				Some(&dir.join("passphrase.txt")),
+				Some("LINKS_TEST_NONEXISTENT"),
+			),
+		] {
+			let certkey = source.get_certkey().unwrap();
+			assert_eq!(certkey.cert[0], read_cert("tests/cert.pem"));
+		}
+
+		assert!(matches!(
+			source(None, None).get_certkey(),
+			Err(CertificateAcquisitionError::MissingPassphrase)
+		));
+		assert!(matches!(
+			source(None, Some("LINKS_TEST_NONEXISTENT")).get_certkey(),
+			Err(CertificateAcquisitionError::MissingPassphrase)
+		));
+		assert!(matches!(
+			source(Some(&dir.join("wrong.txt")), None).get_certkey(),
+			Err(CertificateAcquisitionError::InvalidEncryptedKey(_))
+		));
+		assert!(matches!(
+			source(Some(&dir.join("nonexistent.txt")), None).get_certkey(),
+			Err(CertificateAcquisitionError::FileIo(_))
+		));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn chain_validation() {
+		let dir = std::env::temp_dir().join("links_test_certs-chain_validation");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+
+		let source = |chain: &[&str], domain: &str| {
+			let cert = dir.join(format!("{}.pem", chain.join("+").replace('/', "_")));
+			let pem = chain
+				.iter()
+				.map(|path| fs::read_to_string(path).unwrap())
+				.collect::<String>();
+			fs::write(&cert, pem).unwrap();
+
+			CertificateSource {
+				domains: vec![Domain::presented(domain).unwrap()],
+				source: CertificateSourceType::Files {
+					cert,
+					key: "tests/key.pem".into(),
+					format: CertificateFormat::default(),
+					passphrase_file: None,
+					passphrase_env: None,
+				},
+				tls: TlsPolicy::default(),
+				priority: 0,
+			}
+		};
+
+		assert!(source(&["tests/cert.pem"], "localhost")
+			.get_certkey()
+			.is_ok());
+		assert!(matches!(
+			source(&["tests/cert.pem"], "example.com").get_certkey(),
+			Err(CertificateAcquisitionError::DomainMismatch(domain)) if domain.as_str() == "example.com"
+		));
+		assert!(matches!(
+			source(
+				&["tests/client-cert.pem", "tests/client-ca.pem"],
+				"localhost"
+			)
+			.get_certkey(),
+			Err(CertificateAcquisitionError::KeyMismatch(_))
+		));
+		assert!(matches!(
+			source(
+				&["tests/client-ca.pem", "tests/client-cert.pem"],
+				"localhost"
+			)
+			.get_certkey(),
+			Err(CertificateAcquisitionError::ChainOrder(1))
+		));
+		assert!(matches!(
+			source(
+				&["tests/client-cert.pem", "tests/other-cert.pem"],
+				"localhost"
+			)
+			.get_certkey(),
+			Err(CertificateAcquisitionError::MissingIntermediate(1))
+		));
+		assert!(matches!(
+			source(
+				&[
+					"tests/client-cert.pem",
+					"tests/client-ca.pem",
+					"tests/cert.pem"
+				],
+				"localhost"
+			)
+			.get_certkey(),
+			Err(CertificateAcquisitionError::ChainOrder(2))
+		));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn pkcs12_source() {
+		let source = |password: Option<&str>, password_file: Option<&str>| CertificateSource {
+			domains: vec![Domain::presented("localhost").unwrap()],
+			source: CertificateSourceType::Pkcs12 {
+				file: "tests/cert.p12".into(),
+				password: password.map(ToString::to_string),
+				password_file: password_file.map(Into::into),
+			},
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+
+		let password_file = std::env::temp_dir().join("links_test_certs-pkcs12_source");
+		fs::write(&password_file, "links-test\n").unwrap();
+
+		let certkey = source(Some("links-test"), None).get_certkey().unwrap();
+		assert_eq!(certkey.cert[0], read_cert("tests/cert.pem"));
+
+		let certkey = source(None, password_file.to_str()).get_certkey().unwrap();
+		assert_eq!(certkey.cert[0], read_cert("tests/cert.pem"));
+
+		assert!(matches!(
+			source(Some("wrong"), password_file.to_str()).get_certkey(),
+			Err(CertificateAcquisitionError::InvalidPkcs12(_))
+		));
+		assert!(matches!(
+			source(None, None).get_certkey(),
+			Err(CertificateAcquisitionError::InvalidPkcs12(_))
+		));
+		assert!(!format!("{:?}", source(Some("links-test"), None)).contains("links-test"));
+
+		fs::remove_file(&password_file).unwrap();
+	}
+
+	#[test]
+	fn resolver_directory_source() {
+		let dir = std::env::temp_dir().join("links_test_certs-resolver_directory_source");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(dir.join("example")).unwrap();
+		fs::create_dir_all(dir.join(".hidden")).unwrap();
+
+		let source = CertificateSource {
+			domains: Vec::new(),
+			source: CertificateSourceType::Directory { path: dir.clone() },
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+		let localhost = Domain::reference("localhost").unwrap();
+		let resolver = CertificateResolver::new();
+
+		resolver.load_source(&source).unwrap();
+		assert!(resolver.get(Some(&localhost)).is_none());
+		assert!(matches!(
+			source.get_certkey(),
+			Err(CertificateAcquisitionError::MissingCert)
+		));
+
+		fs::copy("tests/cert.pem", dir.join("example/fullchain.pem")).unwrap();
+		fs::copy("tests/key.pem", dir.join("example/privkey.pem")).unwrap();
+		fs::copy("tests/other-cert.pem", dir.join(".hidden/tls.crt")).unwrap();
+		fs::copy("tests/other-key.pem", dir.join(".hidden/tls.key")).unwrap();
+
+		resolver.load_source(&source).unwrap();
+		assert_eq!(
+			resolver.get(Some(&localhost)).unwrap().cert[0],
+			read_cert("tests/cert.pem")
+		);
+
+		fs::remove_file(dir.join("example/privkey.pem")).unwrap();
+		fs::copy("tests/other-cert.pem", dir.join("tls.crt")).unwrap();
+		fs::copy("tests/other-key.pem", dir.join("tls.key")).unwrap();
+
+		resolver.load_source(&source).unwrap();
+		assert_eq!(
+			resolver.get(Some(&localhost)).unwrap().cert[0],
+			read_cert("tests/other-cert.pem")
+		);
+		assert_eq!(
+			source.get_certkey().unwrap().cert[0],
+			read_cert("tests/other-cert.pem")
+		);
+
+		fs::remove_file(dir.join("tls.key")).unwrap();
+
+		resolver.load_source(&source).unwrap();
+		assert!(resolver.get(Some(&localhost)).is_none());
+
+		fs::copy("tests/other-key.pem", dir.join("tls.key")).unwrap();
+
+		resolver.load_source(&source).unwrap();
+		assert!(resolver.get(Some(&localhost)).is_some());
+
+		resolver.remove_source(&source);
+		assert!(resolver.get(Some(&localhost)).is_none());
+
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert!(matches!(
+			resolver.load_source(&source),
+			Err(CertificateAcquisitionError::FileIo(_))
+		));
+	}
+
+	#[test]
+	fn resolver_tls_policy() {
+		let policy = TlsPolicy {
+			min_version: Some(TlsVersion::Tls13),
+			max_version: None,
+			cipher_suites: vec!["TLS13_AES_256_GCM_SHA384".to_string()],
+			alpn: Some(vec!["http/1.1".to_string()]),
+		};
+		let source = |tls: TlsPolicy| CertificateSource {
+			domains: vec![Domain::presented("localhost").unwrap()],
+			source: CertificateSourceType::Files {
+				cert: "tests/cert.pem".into(),
+				key: "tests/key.pem".into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls,
+			priority: 0,
+		};
+		let domain = Domain::reference("localhost").unwrap();
+		let other = Domain::reference("example.net").unwrap();
+		let resolver = CertificateResolver::new();
+
+		resolver.load_source(&source(policy.clone())).unwrap();
+		assert_eq!(resolver.policy(Some(&domain)), policy);
+		assert_eq!(resolver.policy(Some(&other)), TlsPolicy::default());
+		assert_eq!(resolver.policy(None), TlsPolicy::default());
+
+		resolver.remove_source(&source(policy));
+		assert_eq!(resolver.policy(Some(&domain)), TlsPolicy::default());
+
+		resolver.load_source(&source(TlsPolicy::default())).unwrap();
+		assert_eq!(resolver.policy(Some(&domain)), TlsPolicy::default());
+		assert!(resolver.get(Some(&domain)).is_some());
+	}
+
+	#[test]
+	fn resolver_precedence() {
+		let source = |cert: &str, key: &str, priority| CertificateSource {
+			domains: vec![Domain::presented("localhost").unwrap()],
+			source: CertificateSourceType::Files {
+				cert: cert.into(),
+				key: key.into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls: TlsPolicy::default(),
+			priority,
+		};
+		let first = source("tests/cert.pem", "tests/key.pem", 0);
+		let second = source("tests/other-cert.pem", "tests/other-key.pem", 0);
+		let localhost = Domain::reference("localhost").unwrap();
+		let resolver = CertificateResolver::new();
+		let cert = || resolver.get(Some(&localhost)).unwrap().cert[0].clone();
+
+		resolver.load_source(&first).unwrap();
+		resolver.load_source(&second).unwrap();
+		assert_eq!(cert(), read_cert("tests/other-cert.pem"));
+
+		resolver.load_source(&first).unwrap();
+		assert_eq!(cert(), read_cert("tests/other-cert.pem"));
+
+		resolver.order_sources(&[second.clone(), first.clone()]);
+		assert_eq!(cert(), read_cert("tests/cert.pem"));
+
+		resolver.remove_source(&first);
+		assert_eq!(cert(), read_cert("tests/other-cert.pem"));
+
+		let low = source("tests/cert.pem", "tests/key.pem", -1);
+		resolver.load_source(&low).unwrap();
+		assert_eq!(cert(), read_cert("tests/other-cert.pem"));
+
+		resolver.remove_source(&second);
+		assert_eq!(cert(), read_cert("tests/cert.pem"));
+
+		resolver.remove_source(&low);
+		assert!(resolver.get(Some(&localhost)).is_none());
+	}
+
+	#[test]
+	fn resolver_lookups() {
+		let source = CertificateSource {
+			domains: vec![Domain::presented("localhost").unwrap()],
+			source: CertificateSourceType::Files {
+				cert: "tests/cert.pem".into(),
+				key: "tests/key.pem".into(),
+				format: CertificateFormat::default(),
+				passphrase_file: None,
+				passphrase_env: None,
+			},
+			tls: TlsPolicy::default(),
+			priority: 0,
+		};
+		let localhost = Domain::reference("localhost").unwrap();
+		let wildcard = Domain::reference("www.example.com").unwrap();
+		let unknown = Domain::reference("example.net").unwrap();
+		let resolver = CertificateResolver::new();
+
+		resolver.load_source(&source).unwrap();
+		let certkey = resolver.get(Some(&localhost)).unwrap();
+		resolver.set(Domain::presented("*.example.com").unwrap(), certkey.clone());
+		assert_eq!(resolver.lookups(), CertificateLookups::default());
+
+		assert!(resolver.lookup(Some(&localhost)).is_some());
+		assert!(resolver.lookup(Some(&wildcard)).is_some());
+		assert!(resolver.lookup(Some(&unknown)).is_none());
+		assert!(resolver.lookup(None).is_none());
+
+		resolver.set_default(Some(certkey));
+		assert!(resolver.lookup(Some(&unknown)).is_some());
+		assert!(resolver.lookup(Some(&localhost)).is_some());
+		assert!(resolver.lookup(None).is_some());
+
+		let lookups = resolver.lookups();
+		assert_eq!(
+			lookups.domains,
+			BTreeMap::from([
+				("example.net".to_string(), LookupCounts {
+					default: 1,
+					unresolved: 1,
+					..LookupCounts::default()
+				}),
+				("localhost".to_string(), LookupCounts {
+					exact: 2,
+					..LookupCounts::default()
+				}),
+				("www.example.com".to_string(), LookupCounts {
+					wildcard: 1,
+					..LookupCounts::default()
+				}),
+			])
+		);
+		assert_eq!(lookups.without_sni, LookupCounts {
+			default: 1,
+			unresolved: 1,
+			..LookupCounts::default()
+		});
+		assert_eq!(lookups.untracked, LookupCounts::default());
+
+		assert!(resolver.get(Some(&localhost)).is_some());
+		assert_eq!(resolver.lookups(), lookups);
+	}
+
+	#[test]
+	fn lookups_limit() {
+		let mut lookups = CertificateLookups::new();
+
+		for i in 0..MAX_LOOKUP_DOMAINS {
+			let domain = Domain::reference(&format!("{i}.example.com")).unwrap();
+			lookups.count(Some(&domain), Lookup::Default);
+		}
+
+		let domain = Domain::reference("example.com").unwrap();
+		lookups.count(Some(&domain), Lookup::Unresolved);
+		let domain = Domain::reference("0.example.com").unwrap();
+		lookups.count(Some(&domain), Lookup::Exact);
+
+		assert_eq!(lookups.domains.len(), MAX_LOOKUP_DOMAINS);
+		assert!(!lookups.domains.contains_key("example.com"));
+		assert_eq!(lookups.domains["0.example.com"].exact, 1);
+		assert_eq!(lookups.domains["0.example.com"].default, 1);
+		assert_eq!(lookups.untracked.unresolved, 1);
+	}
+}
@@ -1,21 +1,50 @@
 //! Global redirector server configuration.
 
 use std::{
+	borrow::Cow,
 	collections::HashMap,
-	fmt::{Display, Formatter, Result as FmtResult},
-	net::{IpAddr, Ipv6Addr},
-	path::PathBuf,
+	fmt::{Debug, Display, Formatter, Result as FmtResult},
+	fs,
+	net::{IpAddr, Ipv6Addr, SocketAddr},
+	path::{Path, PathBuf},
 	sync::Arc,
+	time::{Duration, Instant},
 };
 
-use parking_lot::RwLock;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use hyper::{
+	header::{HeaderName, HeaderValue},
+	Method, StatusCode,
+};
+use links_domainmap::{Domain, DomainMap, OccupiedError};
+use links_normalized::{Link, Normalized};
+use parking_lot::{Mutex, RwLock};
+use percent_encoding::percent_decode_str;
 use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use strum::{Display as EnumDisplay, EnumString};
+use tokio::{sync::oneshot, task::spawn_blocking};
 use tracing::{debug, instrument, warn};
 
-use super::{CertificateSource, DefaultCertificateSource, ListenAddress, LogLevel};
+use super::{
+	CertificateSource, DefaultCertificateSource, ListenAddress, LogLevel, TlsListenerPolicy,
+	TlsPolicy,
+};
 use crate::{
-	config::partial::Partial, server::Protocol, stats::StatisticCategories, store::BackendType,
+	access_log::{AccessLog, AccessLogger},
+	blocklist::Blocklist,
+	config::partial::Partial,
+	geo::GeoIpDatabase,
+	http_api::ApiCors,
+	loops::LoopDetection,
+	options::Cors,
+	ratelimit::{Quota, RedirectRateLimits, RpcRateLimits},
+	reputation::ReputationChecks,
+	server::Protocol,
+	stats::{anomaly::AnomalyDetection, StatisticCategories},
+	store::BackendType,
 	util::A_YEAR,
+	webhook::Webhook,
 };
 
 /// Global configuration for the links redirector server. This is the more
@@ -24,6 +53,9 @@ use crate::{
 #[derive(Debug)]
 pub struct Config {
 	inner: RwLock<ConfigInner>,
+	redirectors: RwLock<Arc<Redirectors>>,
+	rotated_tokens: RwLock<HashMap<String, RotatedToken>>,
+	reloads: ReloadChannel,
 	file: Option<PathBuf>,
 }
 
@@ -41,9 +73,13 @@ impl Config {
 	#[must_use]
 	pub fn new(file: Option<PathBuf>) -> Self {
 		let config = ConfigInner::default();
+		let redirectors = Redirectors::from_inner(&config);
 
 		let config = Self {
 			inner: RwLock::new(config),
+			redirectors: RwLock::new(Arc::new(redirectors)),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
 			file,
 		};
 		config.update();
@@ -74,13 +110,14 @@ impl Config {
 	/// command-line arguments. This function starts with defaults for each
 	/// option, then updates those from environment variables, then from the
 	/// config file, then from command-line arguments, and finally overwrites
-	/// this `Config`'s options with those newly-parsed ones.
+	/// this `Config`'s options with those newly-parsed ones. Returns the names
+	/// of the options which were changed by this update.
 	///
 	/// # IO
 	/// This function performs synchronous file IO, and should therefore not be
 	/// used inside of an asynchronous context.
 	#[instrument(level = "info", fields(%self))]
-	pub fn update(&self) {
+	pub fn update(&self) -> Vec<&'static str> {
 		let mut config = ConfigInner::default();
 
 		config.update_from_partial(&Partial::from_env_vars());
@@ -96,19 +133,98 @@ impl Config {
 
 		debug!(new_config = ?config, "Configuration reloaded");
 
-		*self.inner.write() = config;
+		let redirectors = Redirectors::from_inner(&config);
+		let mut inner = self.inner.write();
+		let changes = inner.changes(&config);
+		*inner = config;
+		drop(inner);
+		*self.redirectors.write() = Arc::new(redirectors);
+
+		changes
+	}
+
+	/// Reload this config immediately, returning the names of the options
+	/// which were changed, or `None` if the reload could not be completed.
+	///
+	/// If the [reload requests][Self::take_reload_requests] of this config are
+	/// handled (e.g. by the links server, which also applies the changes to
+	/// its listeners, store, and certificates), a [`ReloadRequest`] is sent to
+	/// be handled there. Otherwise, this config is just
+	/// [updated][Self::update].
+	pub async fn reload(&'static self) -> Option<Vec<&'static str>> {
+		if self.reloads.rx.lock().is_some() {
+			return spawn_blocking(|| self.update()).await.ok();
+		}
+
+		let (tx, rx) = oneshot::channel();
+		self.reloads.tx.send(ReloadRequest(tx)).ok()?;
+		rx.await.ok()
+	}
+
+	/// Take the receiver for requests to reload this config made via
+	/// [`Config::reload`], which then have to be handled by the caller. This
+	/// can only be done once, so `None` is returned if the receiver has
+	/// already been taken.
+	pub fn take_reload_requests(&self) -> Option<Receiver<ReloadRequest>> {
+		self.reloads.rx.lock().take()
+	}
+
+	/// Get the redirector configuration generated from the options defined in
+	/// this global links config. This is only regenerated when the config is
+	/// updated, so getting it for every request is cheap.
+	#[must_use]
+	pub fn redirector(&self) -> Arc<Redirector> {
+		Arc::clone(&self.redirectors.read().default)
+	}
+
+	/// Get the redirector configuration for requests to `host` (a host name,
+	/// optionally with a port), which includes the overrides of any
+	/// [`RedirectorPolicy`] configured for that host. Like
+	/// [`Config::redirector`], this is only regenerated when the config is
+	/// updated.
+	#[must_use]
+	pub fn redirector_for(&self, host: Option<&str>) -> Arc<Redirector> {
+		let redirectors = Arc::clone(&self.redirectors.read());
+
+		if redirectors.domains.is_empty() {
+			return Arc::clone(&redirectors.default);
+		}
+
+		// IPv6 addresses aren't domain names, so splitting them here is fine
+		host.map(|host| host.split_once(':').map_or(host, |(host, _)| host))
+			.and_then(|host| Domain::reference(host).ok())
+			.and_then(|domain| redirectors.domains.get(&domain))
+			.map_or_else(|| Arc::clone(&redirectors.default), Arc::clone)
+	}
+
+	/// Generate an HTTP connection configuration from the options defined in
+	/// this global links config
+	#[must_use]
+	pub fn http_connection(&self) -> HttpConnection {
+		let inner = self.inner.read();
+		let secs = |secs| (secs != 0).then(|| Duration::from_secs(secs));
+
+		HttpConnection {
+			header_read_timeout: secs(inner.http_header_read_timeout),
+			request_timeout: secs(inner.http_request_timeout),
+			idle_timeout: secs(inner.http_idle_timeout),
+		}
 	}
 
-	/// Generate a redirector configuration from the options defined in this
-	/// global links config.
+	/// Generate an RPC connection configuration from the options defined in
+	/// this global links config. Whether zstd compression is enabled depends on
+	/// the listener, so it is disabled here.
 	#[must_use]
-	pub fn redirector(&self) -> Redirector {
-		Redirector {
-			hsts: self.hsts(),
-			send_alt_svc: self.send_alt_svc(),
-			send_server: self.send_server(),
-			send_csp: self.send_csp(),
-			statistics: self.statistics(),
+	pub fn rpc_connection(&self) -> RpcConnection {
+		let inner = self.inner.read();
+		let secs = |secs| (secs != 0).then(|| Duration::from_secs(secs));
+
+		RpcConnection {
+			keepalive_interval: secs(inner.rpc_keepalive_interval),
+			keepalive_timeout: Duration::from_secs(inner.rpc_keepalive_timeout),
+			idle_timeout: secs(inner.rpc_idle_timeout),
+			max_connection_age: secs(inner.rpc_max_connection_age),
+			zstd: false,
 		}
 	}
 
@@ -118,10 +234,96 @@ impl Config {
 		self.inner.read().log_level
 	}
 
-	/// Get the RPC API token
+	/// Get all API tokens, with the current values of
+	/// [rotated][Self::rotate_token] ones
+	#[must_use]
+	pub fn tokens(&self) -> Vec<ApiToken> {
+		let rotated = self.rotated_tokens.read();
+
+		self.inner
+			.read()
+			.tokens
+			.iter()
+			.map(|api_token| ApiToken {
+				token: rotated
+					.get(&api_token.id)
+					.map_or_else(|| api_token.token.clone(), |r| r.token.clone()),
+				..api_token.clone()
+			})
+			.collect()
+	}
+
+	/// Check the `token` against all configured API tokens (or the values of
+	/// [rotated][Self::rotate_token] ones, including previous values still in
+	/// their grace period), returning the identifier and role of the matching
+	/// one, or `None` if it doesn't match any
+	#[must_use]
+	pub fn check_token(&self, token: &[u8]) -> Option<(String, TokenRole)> {
+		let now = Instant::now();
+		let rotated = self.rotated_tokens.read();
+
+		self.inner
+			.read()
+			.tokens
+			.iter()
+			.find(|api_token| {
+				rotated.get(&api_token.id).map_or_else(
+					|| api_token.token.as_bytes() == token,
+					|r| r.matches(token, now),
+				)
+			})
+			.map(|api_token| (api_token.id.clone(), api_token.role))
+	}
+
+	/// Get the [quota][Quota] of the API token with the identifier `id`, which
+	/// is disabled if no such token is configured
 	#[must_use]
-	pub fn token(&self) -> Arc<str> {
-		Arc::clone(&self.inner.read().token)
+	pub fn token_quota(&self, id: &str) -> Quota {
+		self.inner
+			.read()
+			.tokens
+			.iter()
+			.find(|api_token| api_token.id == id)
+			.map(|api_token| api_token.quota)
+			.unwrap_or_default()
+	}
+
+	/// Rotate the API token with the identifier `id`, replacing its secret
+	/// value with `token`. The previous value remains valid for the
+	/// `grace_period` (which may be zero), after which only the new value is
+	/// accepted. Returns the token's role, or `None` if no token with the `id`
+	/// is configured.
+	///
+	/// Rotated values are only kept in memory, and take precedence over the
+	/// token's value in the configuration (even after it is reloaded) until
+	/// the server is restarted. Only the last previous value is kept, so
+	/// rotating a token again ends the previous grace period early.
+	pub fn rotate_token(
+		&self,
+		id: &str,
+		token: String,
+		grace_period: Duration,
+	) -> Option<TokenRole> {
+		let mut rotated = self.rotated_tokens.write();
+
+		let (previous, role) = self
+			.inner
+			.read()
+			.tokens
+			.iter()
+			.find(|api_token| api_token.id == id)
+			.map(|api_token| (api_token.token.clone(), api_token.role))?;
+
+		let previous = rotated.get(id).map_or(previous, |r| r.token.clone());
+		let previous = Instant::now()
+			.checked_add(grace_period)
+			.filter(|_| !grace_period.is_zero())
+			.map(|expiry| (previous, expiry));
+
+		rotated.insert(id.to_string(), RotatedToken { token, previous });
+		drop(rotated);
+
+		Some(role)
 	}
 
 	/// Get the list of listener addresses
@@ -130,6 +332,13 @@ impl Config {
 		self.inner.read().listeners.clone()
 	}
 
+	/// Get the addresses of listeners on which connections start with a PROXY
+	/// protocol header
+	#[must_use]
+	pub fn proxy_protocol_listeners(&self) -> Vec<ListenAddress> {
+		self.inner.read().proxy_protocol_listeners.clone()
+	}
+
 	/// Get the types of statistics to collect
 	#[must_use]
 	pub fn statistics(&self) -> StatisticCategories {
@@ -148,6 +357,44 @@ impl Config {
 		self.inner.read().certificates.clone()
 	}
 
+	/// Get the global TLS policy
+	#[must_use]
+	pub fn tls_policy(&self) -> TlsPolicy {
+		self.inner.read().tls_policy.clone()
+	}
+
+	/// Get the TLS policies of specific listeners
+	#[must_use]
+	pub fn tls_listener_policies(&self) -> Vec<TlsListenerPolicy> {
+		self.inner.read().tls_listener_policies.clone()
+	}
+
+	/// Get the TLS policy for connections to `local_addr` on a `protocol`
+	/// listener, which is the first matching listener policy (if any), with
+	/// unspecified settings taken from the global TLS policy
+	#[must_use]
+	pub fn listener_tls_policy(&self, protocol: Protocol, local_addr: SocketAddr) -> TlsPolicy {
+		let inner = self.inner.read();
+
+		inner
+			.tls_listener_policies
+			.iter()
+			.find(|policy| {
+				policy
+					.listeners
+					.iter()
+					.any(|l| l.protocol == protocol && l.socket_addr() == Some(local_addr))
+			})
+			.map_or_else(TlsPolicy::default, |policy| policy.tls.clone())
+			.or(&inner.tls_policy)
+	}
+
+	/// Get the `tls_ticket_rotation` configuration option (in seconds)
+	#[must_use]
+	pub fn tls_ticket_rotation(&self) -> u64 {
+		self.inner.read().tls_ticket_rotation
+	}
+
 	/// Get the `hsts` configuration option
 	#[must_use]
 	pub fn hsts(&self) -> Hsts {
@@ -160,6 +407,12 @@ impl Config {
 		self.inner.read().https_redirect
 	}
 
+	/// Get the `redirector_policies` configuration option
+	#[must_use]
+	pub fn redirector_policies(&self) -> Vec<RedirectorPolicy> {
+		self.inner.read().redirector_policies.clone()
+	}
+
 	/// Get the `send_alt_svc` configuration option
 	#[must_use]
 	pub fn send_alt_svc(&self) -> bool {
@@ -178,6 +431,225 @@ impl Config {
 		self.inner.read().send_csp
 	}
 
+	/// Get the `referrer_policy` configuration option
+	#[must_use]
+	pub fn referrer_policy(&self) -> ReferrerPolicy {
+		self.inner.read().referrer_policy
+	}
+
+	/// Get the `extra_headers` configuration option
+	#[must_use]
+	pub fn extra_headers(&self) -> HashMap<String, String> {
+		self.inner.read().extra_headers.clone()
+	}
+
+	/// Get the global CORS configuration
+	#[must_use]
+	pub fn cors(&self) -> Arc<Cors> {
+		Arc::clone(&self.inner.read().cors)
+	}
+
+	/// Get the `path_passthrough` configuration option
+	#[must_use]
+	pub fn path_passthrough(&self) -> bool {
+		self.inner.read().path_passthrough
+	}
+
+	/// Get the `path_normalization` configuration option
+	#[must_use]
+	pub fn path_normalization(&self) -> PathNormalization {
+		self.inner.read().path_normalization
+	}
+
+	/// Get the `expired_status` configuration option
+	#[must_use]
+	pub fn expired_status(&self) -> ExpiredStatus {
+		self.inner.read().expired_status
+	}
+
+	/// Get the `preview_suffix` configuration option
+	#[must_use]
+	pub fn preview_suffix(&self) -> String {
+		self.inner.read().preview_suffix.clone()
+	}
+
+	/// Get the `robots_txt` configuration option
+	#[must_use]
+	pub fn robots_txt(&self) -> Arc<str> {
+		Arc::clone(&self.inner.read().robots_txt)
+	}
+
+	/// Get the `reserved_paths` configuration option
+	#[must_use]
+	pub fn reserved_paths(&self) -> Vec<String> {
+		self.inner.read().reserved_paths.clone()
+	}
+
+	/// Get the `favicon` configuration option
+	#[must_use]
+	pub fn favicon(&self) -> Option<Link> {
+		self.inner.read().favicon.clone()
+	}
+
+	/// Get the `default_destination` configuration option
+	#[must_use]
+	pub fn default_destination(&self) -> Option<Link> {
+		self.inner.read().default_destination.clone()
+	}
+
+	/// Get the `search_fallback` configuration option
+	#[must_use]
+	pub fn search_fallback(&self) -> Option<String> {
+		self.inner.read().search_fallback.clone()
+	}
+
+	/// Get the `interstitial_delay` configuration option
+	#[must_use]
+	pub fn interstitial_delay(&self) -> u64 {
+		self.inner.read().interstitial_delay
+	}
+
+	/// Get the `interstitial_template` configuration option
+	#[must_use]
+	pub fn interstitial_template(&self) -> Option<PathBuf> {
+		self.inner.read().interstitial_template.clone()
+	}
+
+	/// Get the `metrics` configuration option
+	#[must_use]
+	pub fn metrics(&self) -> bool {
+		self.inner.read().metrics
+	}
+
+	/// Get the `access_log` configuration option
+	#[must_use]
+	pub fn access_log(&self) -> AccessLog {
+		self.inner.read().access_log.clone()
+	}
+
+	/// Get the `geoip_database` configuration option
+	#[must_use]
+	pub fn geoip_database(&self) -> Option<PathBuf> {
+		self.inner.read().geoip_database.clone()
+	}
+
+	/// Get the `blocked_domains` configuration option
+	#[must_use]
+	pub fn blocked_domains(&self) -> Arc<Blocklist> {
+		Arc::clone(&self.inner.read().blocked_domains)
+	}
+
+	/// Get the `recheck_blocked_domains` configuration option
+	#[must_use]
+	pub fn recheck_blocked_domains(&self) -> bool {
+		self.inner.read().recheck_blocked_domains
+	}
+
+	/// Get the `loop_detection` configuration option
+	#[must_use]
+	pub fn loop_detection(&self) -> Arc<LoopDetection> {
+		Arc::clone(&self.inner.read().loop_detection)
+	}
+
+	/// Get the `redirect_rate_limits` configuration option
+	#[must_use]
+	pub fn redirect_rate_limits(&self) -> RedirectRateLimits {
+		self.inner.read().redirect_rate_limits
+	}
+
+	/// Get the cross-origin resource sharing settings for the HTTP API
+	#[must_use]
+	pub fn http_api_cors(&self) -> Arc<ApiCors> {
+		Arc::clone(&self.inner.read().http_api_cors)
+	}
+
+	/// Get the anomaly detection configuration
+	#[must_use]
+	pub fn anomaly_detection(&self) -> AnomalyDetection {
+		self.inner.read().anomaly_detection
+	}
+
+	/// Get the `reputation_checks` configuration option
+	#[must_use]
+	pub fn reputation_checks(&self) -> ReputationChecks {
+		self.inner.read().reputation_checks.clone()
+	}
+
+	/// Get the `http_header_read_timeout` configuration option (in seconds)
+	#[must_use]
+	pub fn http_header_read_timeout(&self) -> u64 {
+		self.inner.read().http_header_read_timeout
+	}
+
+	/// Get the `http_request_timeout` configuration option (in seconds)
+	#[must_use]
+	pub fn http_request_timeout(&self) -> u64 {
+		self.inner.read().http_request_timeout
+	}
+
+	/// Get the `http_idle_timeout` configuration option (in seconds)
+	#[must_use]
+	pub fn http_idle_timeout(&self) -> u64 {
+		self.inner.read().http_idle_timeout
+	}
+
+	/// Get the `rpc_keepalive_interval` configuration option (in seconds)
+	#[must_use]
+	pub fn rpc_keepalive_interval(&self) -> u64 {
+		self.inner.read().rpc_keepalive_interval
+	}
+
+	/// Get the `rpc_keepalive_timeout` configuration option (in seconds)
+	#[must_use]
+	pub fn rpc_keepalive_timeout(&self) -> u64 {
+		self.inner.read().rpc_keepalive_timeout
+	}
+
+	/// Get the `rpc_idle_timeout` configuration option (in seconds)
+	#[must_use]
+	pub fn rpc_idle_timeout(&self) -> u64 {
+		self.inner.read().rpc_idle_timeout
+	}
+
+	/// Get the `rpc_max_connection_age` configuration option (in seconds)
+	#[must_use]
+	pub fn rpc_max_connection_age(&self) -> u64 {
+		self.inner.read().rpc_max_connection_age
+	}
+
+	/// Get the path of the CA certificates file used to verify RPC client
+	/// certificates, if client certificates are required
+	#[must_use]
+	pub fn rpc_client_ca(&self) -> Option<PathBuf> {
+		self.inner.read().rpc_client_ca.clone()
+	}
+
+	/// Get the role given to RPC clients with a verified client certificate
+	/// but without an API token, if they are allowed
+	#[must_use]
+	pub fn rpc_client_cert_role(&self) -> Option<TokenRole> {
+		self.inner.read().rpc_client_cert_role
+	}
+
+	/// Get the RPC API rate limits
+	#[must_use]
+	pub fn rpc_rate_limits(&self) -> RpcRateLimits {
+		self.inner.read().rpc_rate_limits
+	}
+
+	/// Get the addresses of RPC listeners on which responses may be compressed
+	/// using zstd
+	#[must_use]
+	pub fn rpc_zstd_listeners(&self) -> Vec<ListenAddress> {
+		self.inner.read().rpc_zstd_listeners.clone()
+	}
+
+	/// Get all webhooks notified of changes to links
+	#[must_use]
+	pub fn webhooks(&self) -> Vec<Webhook> {
+		self.inner.read().webhooks.clone()
+	}
+
 	/// Get the store type
 	#[must_use]
 	pub fn store(&self) -> BackendType {
@@ -201,23 +673,66 @@ impl Display for Config {
 	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
 		fmt.debug_struct("Config")
 			.field("log_level", &(self.log_level()).to_string())
+			.field("tokens", &self.tokens())
+			.field("listeners", &serde_json::to_string(&self.listeners()))
 			.field(
-				"token",
-				&(self.token())
-					.chars()
-					.take(3)
-					.chain("...".chars())
-					.collect::<String>(),
+				"proxy_protocol_listeners",
+				&serde_json::to_string(&self.proxy_protocol_listeners()),
 			)
-			.field("listeners", &serde_json::to_string(&self.listeners()))
 			.field("statistics", &serde_json::to_string(&self.statistics()))
 			.field("default_certificate", &self.default_certificate())
 			.field("certificates", &self.certificates())
+			.field("tls_policy", &serde_json::to_string(&self.tls_policy()))
+			.field(
+				"tls_listener_policies",
+				&serde_json::to_string(&self.tls_listener_policies()),
+			)
+			.field("tls_ticket_rotation", &self.tls_ticket_rotation())
 			.field("hsts", &self.hsts())
 			.field("https_redirect", &self.https_redirect())
+			.field("redirector_policies", &self.redirector_policies())
 			.field("send_alt_svc", &self.send_alt_svc())
 			.field("send_server", &self.send_server())
 			.field("send_csp", &self.send_csp())
+			.field("referrer_policy", &self.referrer_policy().to_string())
+			.field("extra_headers", &self.extra_headers())
+			.field("cors", &self.cors())
+			.field("path_passthrough", &self.path_passthrough())
+			.field("path_normalization", &self.path_normalization())
+			.field("expired_status", &self.expired_status())
+			.field("preview_suffix", &self.preview_suffix())
+			.field("robots_txt", &self.robots_txt())
+			.field("reserved_paths", &self.reserved_paths())
+			.field("favicon", &self.favicon())
+			.field("default_destination", &self.default_destination())
+			.field("search_fallback", &self.search_fallback())
+			.field("interstitial_delay", &self.interstitial_delay())
+			.field("interstitial_template", &self.interstitial_template())
+			.field("metrics", &self.metrics())
+			.field("access_log", &self.access_log())
+			.field("geoip_database", &self.geoip_database())
+			.field("blocked_domains", &self.blocked_domains())
+			.field("recheck_blocked_domains", &self.recheck_blocked_domains())
+			.field("loop_detection", &self.loop_detection())
+			.field("redirect_rate_limits", &self.redirect_rate_limits())
+			.field("http_api_cors", &self.http_api_cors())
+			.field("anomaly_detection", &self.anomaly_detection())
+			.field("reputation_checks", &self.reputation_checks())
+			.field("http_header_read_timeout", &self.http_header_read_timeout())
+			.field("http_request_timeout", &self.http_request_timeout())
+			.field("http_idle_timeout", &self.http_idle_timeout())
+			.field("rpc_keepalive_interval", &self.rpc_keepalive_interval())
+			.field("rpc_keepalive_timeout", &self.rpc_keepalive_timeout())
+			.field("rpc_idle_timeout", &self.rpc_idle_timeout())
+			.field("rpc_max_connection_age", &self.rpc_max_connection_age())
+			.field("rpc_client_ca", &self.rpc_client_ca())
+			.field("rpc_client_cert_role", &self.rpc_client_cert_role())
+			.field("rpc_rate_limits", &self.rpc_rate_limits())
+			.field(
+				"rpc_zstd_listeners",
+				&serde_json::to_string(&self.rpc_zstd_listeners()),
+			)
+			.field("webhooks", &self.webhooks())
 			.field("store", &self.store())
 			.field("store_config", &self.store_config())
 			.field("file", &self.file())
@@ -233,21 +748,32 @@ struct ConfigInner {
 	/// may expose secret information, so are not recommended for production
 	/// deployments.
 	pub log_level: LogLevel,
-	/// API token, used for authentication of gRPC clients
-	pub token: Arc<str>,
+	/// API tokens, used for authentication of API clients
+	pub tokens: Vec<ApiToken>,
 	/// Addresses on which the links redirector server will listen on
 	pub listeners: Vec<ListenAddress>,
+	/// Listeners on which connections start with a PROXY protocol header
+	pub proxy_protocol_listeners: Vec<ListenAddress>,
 	/// Which types of statistics should be collected
 	pub statistics: StatisticCategories,
 	/// Default TLS certificate source
 	pub default_certificate: DefaultCertificateSource,
 	/// TLS certificate sources
 	pub certificates: Vec<CertificateSource>,
+	/// Global TLS policy
+	pub tls_policy: TlsPolicy,
+	/// TLS policies of specific listeners
+	pub tls_listener_policies: Vec<TlsListenerPolicy>,
+	/// Interval between TLS session ticket key rotations in seconds (`0` to
+	/// disable session tickets)
+	pub tls_ticket_rotation: u64,
 	/// HTTP Strict Transport Security setting on redirect
 	pub hsts: Hsts,
 	/// Redirect incoming HTTP requests to HTTPS first, before the actual
 	/// external redirect
 	pub https_redirect: bool,
+	/// Per-domain overrides of redirect behavior
+	pub redirector_policies: Vec<RedirectorPolicy>,
 	/// Send the `Alt-Svc` header advertising `h2` (HTTP/2.0 with TLS) support
 	/// on port 443
 	pub send_alt_svc: bool,
@@ -255,6 +781,85 @@ struct ConfigInner {
 	pub send_server: bool,
 	/// Send the `Content-Security-Policy` header
 	pub send_csp: bool,
+	/// The value of the `Referrer-Policy` header of redirect responses
+	pub referrer_policy: ReferrerPolicy,
+	/// Additional headers (by name) sent in all redirector responses
+	pub extra_headers: HashMap<String, String>,
+	/// Global cross-origin resource sharing settings
+	pub cors: Arc<Cors>,
+	/// Append the rest of the request path after a link's ID or vanity path
+	/// to its destination
+	pub path_passthrough: bool,
+	/// How request paths are normalized before their link is looked up
+	pub path_normalization: PathNormalization,
+	/// The response to requests for expired links
+	pub expired_status: ExpiredStatus,
+	/// The suffix of requests for link previews (empty to disable)
+	pub preview_suffix: String,
+	/// The contents of `/robots.txt`
+	pub robots_txt: Arc<str>,
+	/// Path prefixes which are never looked up as links, and can't be used as
+	/// vanity paths
+	pub reserved_paths: Vec<String>,
+	/// Where to redirect requests for `/favicon.ico` (`None` to serve the
+	/// built-in icon)
+	pub favicon: Option<Link>,
+	/// Where to redirect requests which don't match any link (`None` to
+	/// respond with `404 Not Found`)
+	pub default_destination: Option<Link>,
+	/// The URL template (with a `{path}` placeholder) that requests for
+	/// unknown paths are redirected to, e.g. a search page
+	pub search_fallback: Option<String>,
+	/// The number of seconds before the interstitial page redirects
+	pub interstitial_delay: u64,
+	/// Path to the template of the interstitial page (`None` to use the
+	/// built-in page)
+	pub interstitial_template: Option<PathBuf>,
+	/// Serve metrics from `/metrics` on redirector listeners
+	pub metrics: bool,
+	/// The structured access log of redirects
+	pub access_log: AccessLog,
+	/// Path to a MaxMind GeoIP2 or GeoLite2 database for geo-targeting
+	pub geoip_database: Option<PathBuf>,
+	/// Domains which links must not point to
+	pub blocked_domains: Arc<Blocklist>,
+	/// Also check link destinations against `blocked_domains` when
+	/// redirecting
+	pub recheck_blocked_domains: bool,
+	/// Detection of redirect loops through this server's own domains
+	pub loop_detection: Arc<LoopDetection>,
+	/// Rate limits of redirector requests
+	pub redirect_rate_limits: RedirectRateLimits,
+	/// Cross-origin resource sharing settings for the HTTP API
+	pub http_api_cors: Arc<ApiCors>,
+	/// Detection of anomalous redirect traffic
+	pub anomaly_detection: AnomalyDetection,
+	/// Periodic reputation checks of link destinations
+	pub reputation_checks: ReputationChecks,
+	/// Timeout for reading HTTP request headers in seconds (`0` to disable)
+	pub http_header_read_timeout: u64,
+	/// Timeout for processing HTTP requests in seconds (`0` to disable)
+	pub http_request_timeout: u64,
+	/// Idle timeout of HTTP connections in seconds (`0` to disable)
+	pub http_idle_timeout: u64,
+	/// Interval between RPC keepalive pings in seconds (`0` to disable)
+	pub rpc_keepalive_interval: u64,
+	/// Timeout for RPC keepalive ping acknowledgements in seconds
+	pub rpc_keepalive_timeout: u64,
+	/// Idle timeout of RPC connections in seconds (`0` to disable)
+	pub rpc_idle_timeout: u64,
+	/// Maximum age of RPC connections in seconds (`0` to disable)
+	pub rpc_max_connection_age: u64,
+	/// CA certificates file for verifying RPC client certificates
+	pub rpc_client_ca: Option<PathBuf>,
+	/// Role of RPC clients authenticated only by their client certificate
+	pub rpc_client_cert_role: Option<TokenRole>,
+	/// Rate limits of RPC API requests
+	pub rpc_rate_limits: RpcRateLimits,
+	/// RPC listeners on which zstd compression of responses is enabled
+	pub rpc_zstd_listeners: Vec<ListenAddress>,
+	/// Webhooks notified of changes to links
+	pub webhooks: Vec<Webhook>,
 	/// The store backend type
 	pub store: BackendType,
 	/// The store backend configuration
@@ -270,14 +875,20 @@ impl ConfigInner {
 			self.log_level = log_level;
 		}
 
-		if let Some(ref token) = partial.token {
-			self.token = Arc::from(token.as_str());
+		if let Some(ref tokens) = partial.tokens {
+			warn_tokens(tokens);
+			self.tokens.clone_from(tokens);
 		}
 
 		if let Some(ref listeners) = partial.listeners {
 			self.listeners.clone_from(listeners);
 		}
 
+		if let Some(ref proxy_protocol_listeners) = partial.proxy_protocol_listeners {
+			self.proxy_protocol_listeners
+				.clone_from(proxy_protocol_listeners);
+		}
+
 		if let Some(statistics) = partial.statistics {
 			self.statistics = statistics;
 		}
@@ -287,9 +898,22 @@ impl ConfigInner {
 		}
 
 		if let Some(ref certificates) = partial.certificates {
+			warn_duplicate_certificates(certificates);
 			self.certificates.clone_from(certificates);
 		}
 
+		if let Some(ref tls_policy) = partial.tls_policy {
+			self.tls_policy = tls_policy.clone();
+		}
+
+		if let Some(ref tls_listener_policies) = partial.tls_listener_policies {
+			self.tls_listener_policies.clone_from(tls_listener_policies);
+		}
+
+		if let Some(tls_ticket_rotation) = partial.tls_ticket_rotation {
+			self.tls_ticket_rotation = tls_ticket_rotation;
+		}
+
 		if let Some(hsts) = partial.hsts() {
 			self.hsts = hsts;
 		}
@@ -298,6 +922,10 @@ impl ConfigInner {
 			self.https_redirect = https_redirect;
 		}
 
+		if let Some(ref redirector_policies) = partial.redirector_policies {
+			self.redirector_policies.clone_from(redirector_policies);
+		}
+
 		if let Some(send_alt_svc) = partial.send_alt_svc {
 			self.send_alt_svc = send_alt_svc;
 		}
@@ -310,86 +938,1071 @@ impl ConfigInner {
 			self.send_csp = send_csp;
 		}
 
-		if let Some(store) = partial.store {
-			self.store = store;
+		if let Some(referrer_policy) = partial.referrer_policy {
+			self.referrer_policy = referrer_policy;
 		}
 
-		if let Some(ref store_config) = partial.store_config {
-			self.store_config
-				.extend(store_config.iter().map(|(k, v)| (k.clone(), v.clone())));
+		if let Some(ref extra_headers) = partial.extra_headers {
+			self.extra_headers.clone_from(extra_headers);
 		}
-	}
-}
 
-impl Default for ConfigInner {
-	fn default() -> Self {
-		Self {
-			log_level: LogLevel::default(),
-			token: rand::thread_rng()
-				.sample_iter(&Alphanumeric)
-				.take(32)
-				.map(char::from)
-				.collect::<String>()
-				.into(),
-			listeners: vec![
-				ListenAddress {
-					protocol: Protocol::Http,
-					address: None,
-					port: None,
-				},
-				ListenAddress {
-					protocol: Protocol::Https,
-					address: None,
-					port: None,
-				},
-				ListenAddress {
-					protocol: Protocol::Grpc,
-					address: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
-					port: None,
-				},
-				ListenAddress {
-					protocol: Protocol::Grpcs,
-					address: None,
-					port: None,
-				},
-			],
-			statistics: StatisticCategories::default(),
-			https_redirect: false,
-			default_certificate: DefaultCertificateSource::None,
-			certificates: Vec::default(),
-			hsts: Hsts::default(),
-			send_alt_svc: false,
-			send_server: true,
-			send_csp: true,
-			store: BackendType::default(),
-			store_config: HashMap::with_capacity(0),
+		if let Some(ref cors) = partial.cors {
+			self.cors = Arc::new(cors.clone());
 		}
-	}
-}
 
-/// Configuration of a redirector. Can be generated from a [`Config`]. This is
-/// separate from the actual `Config`, because it shouldn't/can't change during
-/// the course of processing a redirect.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Redirector {
-	/// HTTP Strict Transport Security configuration
-	pub hsts: Hsts,
-	/// Send the `Alt-Svc` header advertising `h2` (HTTP/2.0 with TLS) support
-	/// on port 443
-	pub send_alt_svc: bool,
-	/// Send the `Server` header
-	pub send_server: bool,
-	/// Send the `Content-Security-Policy` header
-	pub send_csp: bool,
-	/// The categories of statistics to collect
-	pub statistics: StatisticCategories,
-}
+		if let Some(path_passthrough) = partial.path_passthrough {
+			self.path_passthrough = path_passthrough;
+		}
 
-/// HTTP Strict Transport Security configuration settings and `max-age` in
-/// seconds for the links redirector.
-///
-/// The `max-age` indicates for how long the server's HSTS setting should be
-/// saved by browsers, with 2 years (63072000 seconds) being recommended. For
+		if let Some(path_normalization) = partial.path_normalization {
+			self.path_normalization = path_normalization;
+		}
+
+		if let Some(expired_status) = partial.expired_status {
+			self.expired_status = expired_status;
+		}
+
+		if let Some(ref preview_suffix) = partial.preview_suffix {
+			self.preview_suffix.clone_from(preview_suffix);
+		}
+
+		if let Some(ref robots_txt) = partial.robots_txt {
+			self.robots_txt = Arc::from(robots_txt.as_str());
+		}
+
+		if let Some(ref reserved_paths) = partial.reserved_paths {
+			self.reserved_paths.clone_from(reserved_paths);
+		}
+
+		if let Some(ref favicon) = partial.favicon {
+			self.favicon = Some(favicon.clone());
+		}
+
+		if let Some(ref default_destination) = partial.default_destination {
+			self.default_destination = Some(default_destination.clone());
+		}
+
+		if let Some(ref search_fallback) = partial.search_fallback {
+			self.search_fallback = Some(search_fallback.clone()).filter(|url| !url.is_empty());
+		}
+
+		if let Some(interstitial_delay) = partial.interstitial_delay {
+			self.interstitial_delay = interstitial_delay;
+		}
+
+		if let Some(ref interstitial_template) = partial.interstitial_template {
+			self.interstitial_template = Some(interstitial_template.clone());
+		}
+
+		if let Some(metrics) = partial.metrics {
+			self.metrics = metrics;
+		}
+
+		if let Some(ref access_log) = partial.access_log {
+			self.access_log.clone_from(access_log);
+		}
+
+		if let Some(ref geoip_database) = partial.geoip_database {
+			self.geoip_database = Some(geoip_database.clone());
+		}
+
+		if let Some(ref blocked_domains) = partial.blocked_domains {
+			self.blocked_domains = Arc::new(Blocklist::new(blocked_domains.iter().cloned()));
+		}
+
+		if let Some(recheck_blocked_domains) = partial.recheck_blocked_domains {
+			self.recheck_blocked_domains = recheck_blocked_domains;
+		}
+
+		if let Some(ref loop_detection) = partial.loop_detection {
+			self.loop_detection = Arc::new(loop_detection.clone());
+		}
+
+		if let Some(redirect_rate_limits) = partial.redirect_rate_limits {
+			self.redirect_rate_limits = redirect_rate_limits;
+		}
+
+		if let Some(ref http_api_cors) = partial.http_api_cors {
+			self.http_api_cors = Arc::new(http_api_cors.clone());
+		}
+
+		if let Some(anomaly_detection) = partial.anomaly_detection {
+			self.anomaly_detection = anomaly_detection;
+		}
+
+		if let Some(ref reputation_checks) = partial.reputation_checks {
+			self.reputation_checks.clone_from(reputation_checks);
+		}
+
+		if let Some(http_header_read_timeout) = partial.http_header_read_timeout {
+			self.http_header_read_timeout = http_header_read_timeout;
+		}
+
+		if let Some(http_request_timeout) = partial.http_request_timeout {
+			self.http_request_timeout = http_request_timeout;
+		}
+
+		if let Some(http_idle_timeout) = partial.http_idle_timeout {
+			self.http_idle_timeout = http_idle_timeout;
+		}
+
+		if let Some(rpc_keepalive_interval) = partial.rpc_keepalive_interval {
+			self.rpc_keepalive_interval = rpc_keepalive_interval;
+		}
+
+		if let Some(rpc_keepalive_timeout) = partial.rpc_keepalive_timeout {
+			self.rpc_keepalive_timeout = rpc_keepalive_timeout;
+		}
+
+		if let Some(rpc_idle_timeout) = partial.rpc_idle_timeout {
+			self.rpc_idle_timeout = rpc_idle_timeout;
+		}
+
+		if let Some(rpc_max_connection_age) = partial.rpc_max_connection_age {
+			self.rpc_max_connection_age = rpc_max_connection_age;
+		}
+
+		if let Some(ref rpc_client_ca) = partial.rpc_client_ca {
+			self.rpc_client_ca = Some(rpc_client_ca.clone());
+		}
+
+		if let Some(rpc_client_cert_role) = partial.rpc_client_cert_role {
+			self.rpc_client_cert_role = Some(rpc_client_cert_role);
+		}
+
+		if let Some(rpc_rate_limits) = partial.rpc_rate_limits {
+			self.rpc_rate_limits = rpc_rate_limits;
+		}
+
+		if let Some(ref rpc_zstd_listeners) = partial.rpc_zstd_listeners {
+			self.rpc_zstd_listeners.clone_from(rpc_zstd_listeners);
+		}
+
+		if let Some(ref webhooks) = partial.webhooks {
+			self.webhooks.clone_from(webhooks);
+		}
+
+		if let Some(store) = partial.store {
+			self.store = store;
+		}
+
+		if let Some(ref store_config) = partial.store_config {
+			self.store_config
+				.extend(store_config.iter().map(|(k, v)| (k.clone(), v.clone())));
+		}
+	}
+
+	/// Get the names of all options which are different in the `other`
+	/// config. All fields are destructured, so that none can be forgotten.
+	fn changes(&self, other: &Self) -> Vec<&'static str> {
+		macro_rules! changes {
+			($($field:ident),* $(,)?) => {{
+				let Self { $($field),* } = self;
+				let mut changes = Vec::new();
+				$(if *$field != other.$field {
+					changes.push(stringify!($field));
+				})*
+				changes
+			}};
+		}
+
+		changes!(
+			log_level,
+			tokens,
+			listeners,
+			proxy_protocol_listeners,
+			statistics,
+			default_certificate,
+			certificates,
+			tls_policy,
+			tls_listener_policies,
+			tls_ticket_rotation,
+			hsts,
+			https_redirect,
+			redirector_policies,
+			send_alt_svc,
+			send_server,
+			send_csp,
+			referrer_policy,
+			extra_headers,
+			cors,
+			path_passthrough,
+			path_normalization,
+			expired_status,
+			preview_suffix,
+			robots_txt,
+			reserved_paths,
+			favicon,
+			default_destination,
+			search_fallback,
+			interstitial_delay,
+			interstitial_template,
+			metrics,
+			access_log,
+			geoip_database,
+			blocked_domains,
+			recheck_blocked_domains,
+			loop_detection,
+			redirect_rate_limits,
+			http_api_cors,
+			anomaly_detection,
+			reputation_checks,
+			http_header_read_timeout,
+			http_request_timeout,
+			http_idle_timeout,
+			rpc_keepalive_interval,
+			rpc_keepalive_timeout,
+			rpc_idle_timeout,
+			rpc_max_connection_age,
+			rpc_client_ca,
+			rpc_client_cert_role,
+			rpc_rate_limits,
+			rpc_zstd_listeners,
+			webhooks,
+			store,
+			store_config,
+		)
+	}
+}
+
+/// Log a warning if no API tokens are configured (so that the APIs are
+/// unusable), or if an identifier is used by more than one token (so that logs
+/// can't tell those tokens apart)
+fn warn_tokens(tokens: &[ApiToken]) {
+	if tokens.is_empty() {
+		warn!("No API tokens are configured, all API requests will be rejected");
+	}
+
+	for (i, token) in tokens.iter().enumerate() {
+		if tokens[..i].iter().any(|other| other.id == token.id) {
+			warn!(
+				"The API token identifier {} is used more than once, requests using those tokens \
+				 can not be told apart",
+				token.id
+			);
+		}
+	}
+}
+
+/// Log a warning for every domain configured more than once in certificate
+/// sources. The last source configured for a domain is used for it, so any
+/// earlier ones are silently ignored for that domain.
+fn warn_duplicate_certificates(certificates: &[CertificateSource]) {
+	let mut seen = DomainMap::with_capacity(certificates.len());
+
+	for (i, source) in certificates.iter().enumerate() {
+		for domain in &source.domains {
+			if let Err(OccupiedError { domain, .. }) = seen.try_set(domain.clone(), i) {
+				warn!(
+					"The domain {domain} is configured more than once in certificate sources, \
+					 only the last one will be used"
+				);
+			}
+		}
+	}
+}
+
+/// Parse the `extra_headers` configuration option into header names and
+/// values, sorted by name. Invalid headers are skipped with a warning.
+fn header_list(headers: &HashMap<String, String>) -> Arc<[(HeaderName, HeaderValue)]> {
+	let mut list = headers
+		.iter()
+		.filter_map(|(name, value)| {
+			let header = HeaderName::try_from(name)
+				.ok()
+				.zip(HeaderValue::try_from(value).ok());
+
+			if header.is_none() {
+				warn!("The extra header {name:?} is invalid and will not be sent");
+			}
+
+			header
+		})
+		.collect::<Vec<_>>();
+
+	list.sort_unstable_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+	list.into()
+}
+
+/// Load the interstitial page template at `path`. Returns `None` (so that the
+/// built-in page is used instead) with a warning if it can not be read.
+fn load_template(path: &Path) -> Option<Arc<str>> {
+	match fs::read_to_string(path) {
+		Ok(template) => Some(template.into()),
+		Err(err) => {
+			warn!(%err, ?path, "Could not read the interstitial template, using the built-in page");
+			None
+		}
+	}
+}
+
+impl Default for ConfigInner {
+	fn default() -> Self {
+		Self {
+			log_level: LogLevel::default(),
+			tokens: vec![ApiToken {
+				id: "default".to_string(),
+				token: ApiToken::random_token(),
+				role: TokenRole::Admin,
+				quota: Quota::default(),
+			}],
+			listeners: vec![
+				ListenAddress {
+					protocol: Protocol::Http,
+					address: None,
+					port: None,
+					path: None,
+				},
+				ListenAddress {
+					protocol: Protocol::Https,
+					address: None,
+					port: None,
+					path: None,
+				},
+				ListenAddress {
+					protocol: Protocol::Grpc,
+					address: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+					port: None,
+					path: None,
+				},
+				ListenAddress {
+					protocol: Protocol::Grpcs,
+					address: None,
+					port: None,
+					path: None,
+				},
+			],
+			proxy_protocol_listeners: Vec::new(),
+			statistics: StatisticCategories::default(),
+			https_redirect: false,
+			redirector_policies: Vec::new(),
+			default_certificate: DefaultCertificateSource::None,
+			certificates: Vec::default(),
+			tls_policy: TlsPolicy::default(),
+			tls_listener_policies: Vec::new(),
+			tls_ticket_rotation: 21600,
+			hsts: Hsts::default(),
+			send_alt_svc: false,
+			send_server: true,
+			send_csp: true,
+			referrer_policy: ReferrerPolicy::default(),
+			extra_headers: HashMap::with_capacity(0),
+			cors: Arc::default(),
+			path_passthrough: false,
+			path_normalization: PathNormalization::default(),
+			expired_status: ExpiredStatus::default(),
+			preview_suffix: String::new(),
+			robots_txt: Arc::from("User-agent: *\nAllow: /\n"),
+			reserved_paths: ["/.well-known", "/api", "/healthz", "/metrics", "/readyz"]
+				.map(String::from)
+				.to_vec(),
+			favicon: None,
+			default_destination: None,
+			search_fallback: None,
+			interstitial_delay: 3,
+			interstitial_template: None,
+			metrics: false,
+			access_log: AccessLog::default(),
+			geoip_database: None,
+			blocked_domains: Arc::new(Blocklist::default()),
+			recheck_blocked_domains: false,
+			loop_detection: Arc::default(),
+			redirect_rate_limits: RedirectRateLimits::default(),
+			http_api_cors: Arc::default(),
+			anomaly_detection: AnomalyDetection::default(),
+			reputation_checks: ReputationChecks::default(),
+			http_header_read_timeout: 30,
+			http_request_timeout: 0,
+			http_idle_timeout: 0,
+			rpc_keepalive_interval: 0,
+			rpc_keepalive_timeout: 20,
+			rpc_idle_timeout: 0,
+			rpc_max_connection_age: 0,
+			rpc_client_ca: None,
+			rpc_client_cert_role: None,
+			rpc_rate_limits: RpcRateLimits::default(),
+			rpc_zstd_listeners: Vec::new(),
+			webhooks: Vec::new(),
+			store: BackendType::default(),
+			store_config: HashMap::with_capacity(0),
+		}
+	}
+}
+
+/// Configuration of a redirector. Can be generated from a [`Config`]. This is
+/// separate from the actual `Config`, because it shouldn't/can't change during
+/// the course of processing a redirect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[expect(
+	clippy::struct_excessive_bools,
+	reason = "these are independent options, not states"
+)]
+pub struct Redirector {
+	/// HTTP Strict Transport Security configuration
+	pub hsts: Hsts,
+	/// The value of the `Strict-Transport-Security` header according to
+	/// `hsts`, or `None` if HSTS is disabled
+	pub hsts_header: Option<HeaderValue>,
+	/// Send the `Alt-Svc` header advertising `h2` (HTTP/2.0 with TLS) support
+	/// on port 443
+	pub send_alt_svc: bool,
+	/// Send the `Server` header
+	pub send_server: bool,
+	/// Send the `Content-Security-Policy` header
+	pub send_csp: bool,
+	/// The value of the `Referrer-Policy` header of redirect responses
+	pub referrer_policy: ReferrerPolicy,
+	/// Additional headers sent in all responses, after the default ones
+	pub extra_headers: Arc<[(HeaderName, HeaderValue)]>,
+	/// Cross-origin resource sharing settings, used for links without their
+	/// own CORS options
+	pub cors: Arc<Cors>,
+	/// Append the rest of the request path after a link's ID or vanity path
+	/// to its destination, used for links without their own
+	/// `path_passthrough` option
+	pub path_passthrough: bool,
+	/// How request paths are normalized before their link is looked up
+	pub path_normalization: PathNormalization,
+	/// The response to requests for expired links
+	pub expired_status: ExpiredStatus,
+	/// The suffix of request paths (or queries) for previews of links instead
+	/// of redirects, or `None` if link previews are disabled
+	pub preview_suffix: Option<String>,
+	/// The contents of `/robots.txt`
+	pub robots_txt: Arc<str>,
+	/// Normalized path prefixes (without the leading slash) which are never
+	/// looked up as links
+	pub reserved_paths: Arc<[Normalized]>,
+	/// Where to redirect requests for `/favicon.ico`, or `None` to serve the
+	/// built-in icon
+	pub favicon: Option<Link>,
+	/// Where to redirect requests which don't match any link, or `None` to
+	/// respond with `404 Not Found`
+	pub default_destination: Option<Link>,
+	/// The URL template (with a `{path}` placeholder) that requests for
+	/// unknown paths are redirected to, or `None` to use the default
+	/// destination
+	pub search_fallback: Option<String>,
+	/// The number of seconds before the interstitial page redirects
+	pub interstitial_delay: u64,
+	/// The template of the interstitial page, or `None` to use the built-in
+	/// page
+	pub interstitial_template: Option<Arc<str>>,
+	/// The access log which processed requests are written to, if any
+	pub access_log: Option<Arc<AccessLogger>>,
+	/// The GeoIP database used for geo-targeted link destinations, if any
+	pub geoip: Option<Arc<GeoIpDatabase>>,
+	/// The blocklist which link destinations are checked against when
+	/// redirecting, or `None` if they aren't checked
+	pub blocklist: Option<Arc<Blocklist>>,
+	/// The loop detection settings which links are checked against when
+	/// redirecting, or `None` if they aren't checked
+	pub loop_detection: Option<Arc<LoopDetection>>,
+	/// Rate limits of redirector requests per client
+	pub rate_limits: RedirectRateLimits,
+	/// The categories of statistics to collect
+	pub statistics: StatisticCategories,
+	/// Redirect plain HTTP requests to HTTPS before the external redirect
+	pub https_redirect: bool,
+	/// The status code of external redirects
+	pub redirect_status: RedirectStatus,
+	/// The store namespace in which links are looked up, or `None` to use the
+	/// store without a namespace
+	pub namespace: Option<String>,
+}
+
+impl Redirector {
+	/// Generate a redirector configuration from the options in `config`
+	fn from_inner(config: &ConfigInner) -> Self {
+		Self {
+			hsts: config.hsts,
+			hsts_header: config.hsts.header_value(),
+			send_alt_svc: config.send_alt_svc,
+			send_server: config.send_server,
+			send_csp: config.send_csp,
+			referrer_policy: config.referrer_policy,
+			extra_headers: header_list(&config.extra_headers),
+			cors: Arc::clone(&config.cors),
+			path_passthrough: config.path_passthrough,
+			path_normalization: config.path_normalization,
+			expired_status: config.expired_status,
+			preview_suffix: Some(config.preview_suffix.clone()).filter(|suffix| !suffix.is_empty()),
+			robots_txt: Arc::clone(&config.robots_txt),
+			reserved_paths: config
+				.reserved_paths
+				.iter()
+				.map(|path| Normalized::new(path.trim_matches('/')))
+				.filter(|path| !path.as_str().is_empty())
+				.collect(),
+			favicon: config.favicon.clone(),
+			default_destination: config.default_destination.clone(),
+			search_fallback: config.search_fallback.clone(),
+			interstitial_delay: config.interstitial_delay,
+			interstitial_template: config
+				.interstitial_template
+				.as_deref()
+				.and_then(load_template),
+			access_log: AccessLogger::open(&config.access_log),
+			geoip: config
+				.geoip_database
+				.as_deref()
+				.and_then(GeoIpDatabase::load),
+			blocklist: Some(Arc::clone(&config.blocked_domains))
+				.filter(|blocklist| config.recheck_blocked_domains && !blocklist.is_empty()),
+			loop_detection: Some(Arc::clone(&config.loop_detection))
+				.filter(|detection| detection.recheck && detection.is_enabled()),
+			rate_limits: config.redirect_rate_limits,
+			statistics: config.statistics,
+			https_redirect: config.https_redirect,
+			redirect_status: RedirectStatus::default(),
+			namespace: None,
+		}
+	}
+
+	/// Check whether the request `path` (without the leading slash) is one of
+	/// the reserved paths or inside of one, e.g. `api/v1` for the reserved
+	/// path `/api`. Paths are normalized like vanity paths before checking.
+	#[must_use]
+	pub fn is_reserved(&self, path: &str) -> bool {
+		if self.reserved_paths.is_empty() {
+			return false;
+		}
+
+		let path = Normalized::new(path);
+
+		self.reserved_paths.iter().any(|reserved| {
+			path.as_str()
+				.strip_prefix(reserved.as_str())
+				.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+		})
+	}
+
+	/// Get a copy of this redirector configuration with the overrides from
+	/// `policy` applied
+	fn with_policy(&self, policy: &RedirectorPolicy) -> Self {
+		Self {
+			statistics: policy.statistics.unwrap_or(self.statistics),
+			https_redirect: policy.https_redirect.unwrap_or(self.https_redirect),
+			redirect_status: policy.redirect_status.unwrap_or(self.redirect_status),
+			namespace: policy.namespace.clone().or_else(|| self.namespace.clone()),
+			default_destination: policy
+				.default_destination
+				.clone()
+				.or_else(|| self.default_destination.clone()),
+			..self.clone()
+		}
+	}
+}
+
+/// All redirector configurations, the default one and those for domains with
+/// a [`RedirectorPolicy`]
+#[derive(Debug)]
+struct Redirectors {
+	/// The redirector configuration used for domains without a policy
+	default: Arc<Redirector>,
+	/// Redirector configurations with policy overrides applied, by domain
+	domains: DomainMap<Arc<Redirector>>,
+}
+
+impl Redirectors {
+	/// Generate all redirector configurations from the options in `config`.
+	/// If a domain is configured in multiple policies, only the last one is
+	/// used for it.
+	fn from_inner(config: &ConfigInner) -> Self {
+		let default = Redirector::from_inner(config);
+		let mut domains = DomainMap::with_capacity(config.redirector_policies.len());
+
+		for policy in &config.redirector_policies {
+			let redirector = Arc::new(default.with_policy(policy));
+
+			for domain in &policy.domains {
+				if let Err(OccupiedError { domain, value }) =
+					domains.try_set(domain.clone(), Arc::clone(&redirector))
+				{
+					warn!(
+						"The domain {domain} is configured in more than one redirector policy, \
+						 only the last one will be used"
+					);
+					domains.set(domain, value);
+				}
+			}
+		}
+
+		Self {
+			default: Arc::new(default),
+			domains,
+		}
+	}
+}
+
+/// An API token, used to authenticate requests to the gRPC and HTTP APIs.
+///
+/// Each token has an identifier, which is logged whenever the token is used,
+/// so that separate tokens can be given to e.g. CI, dashboards, and people,
+/// and their use can be told apart (and each can be revoked separately). The
+/// token's [role][TokenRole] limits which operations it can be used for, and
+/// its optional [quota][Quota] limits how many changes it can make per day and
+/// per month.
+///
+/// # Example
+/// ```toml
+/// { id = "ci", token = "random secret api token value", role = "write", quota = { daily = 10000 } }
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+	/// The identifier of this token, which is not secret
+	pub id: String,
+	/// The secret token value, which should be long and random
+	pub token: String,
+	/// What this token is allowed to do
+	#[serde(default)]
+	pub role: TokenRole,
+	/// How many requests modifying the store this token can make per day and
+	/// per month
+	#[serde(default)]
+	pub quota: Quota,
+}
+
+/// The role of an [`ApiToken`], determining which API operations it is
+/// allowed to perform.
+///
+/// Each role can do everything the roles before it can. In configuration,
+/// this is represented in kebab-case, e.g. `read-only`.
+#[derive(
+	Copy,
+	Clone,
+	Debug,
+	Default,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Serialize,
+	Deserialize,
+	EnumString,
+	EnumDisplay,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum TokenRole {
+	/// Get and list redirects, vanity paths, options, and statistics, and
+	/// watch redirects and statistics
+	ReadOnly,
+	/// Also set, remove, and restore redirects, vanity paths, and options,
+	/// and remove statistics
+	Write,
+	/// Also purge soft-deleted redirects, add, remove, and list listeners,
+	/// rotate API tokens, and reload the configuration and TLS certificates
+	#[default]
+	Admin,
+}
+
+impl ApiToken {
+	/// Generate a new random secret token value
+	#[must_use]
+	pub fn random_token() -> String {
+		rand::thread_rng()
+			.sample_iter(&Alphanumeric)
+			.take(32)
+			.map(char::from)
+			.collect()
+	}
+}
+
+impl Debug for ApiToken {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("ApiToken")
+			.field("id", &self.id)
+			.field("role", &self.role)
+			.field("quota", &self.quota)
+			.field(
+				"token",
+				&self
+					.token
+					.chars()
+					.take(3)
+					.chain("...".chars())
+					.collect::<String>(),
+			)
+			.finish()
+	}
+}
+
+/// A request to reload the configuration, made via [`Config::reload`] and
+/// received from [`Config::take_reload_requests`]
+///
+/// The handler of this request should [update][Config::update] the config,
+/// apply the changes as needed, and then [respond][ReloadRequest::respond]
+/// with the names of the changed options.
+#[derive(Debug)]
+pub struct ReloadRequest(oneshot::Sender<Vec<&'static str>>);
+
+impl ReloadRequest {
+	/// Respond to this request with the names of the options which were
+	/// changed by the reload
+	pub fn respond(self, changes: Vec<&'static str>) {
+		// The requester not waiting for the response anymore is not a problem
+		let _ = self.0.send(changes);
+	}
+}
+
+/// The channel through which [`ReloadRequest`]s are sent to their handler
+#[derive(Debug)]
+struct ReloadChannel {
+	tx: Sender<ReloadRequest>,
+	/// The receiver, until it is taken by the handler
+	rx: Mutex<Option<Receiver<ReloadRequest>>>,
+}
+
+impl Default for ReloadChannel {
+	fn default() -> Self {
+		let (tx, rx) = unbounded();
+
+		Self {
+			tx,
+			rx: Mutex::new(Some(rx)),
+		}
+	}
+}
+
+/// The value of an [`ApiToken`] which was rotated at runtime, see
+/// [`Config::rotate_token`]
+struct RotatedToken {
+	/// The current secret token value
+	token: String,
+	/// The previous secret token value, and when it stops being valid
+	previous: Option<(String, Instant)>,
+}
+
+impl RotatedToken {
+	/// Check whether the `token` is this token's current value, or its
+	/// previous value which is still valid at `now`
+	fn matches(&self, token: &[u8], now: Instant) -> bool {
+		self.token.as_bytes() == token
+			|| self
+				.previous
+				.as_ref()
+				.is_some_and(|(previous, expiry)| previous.as_bytes() == token && now < *expiry)
+	}
+}
+
+impl Debug for RotatedToken {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("RotatedToken")
+			.field("token", &"...")
+			.field(
+				"previous",
+				&self.previous.as_ref().map(|(_, expiry)| expiry),
+			)
+			.finish()
+	}
+}
+
+/// Overrides of redirect behavior for specific domains served by the same
+/// redirector server.
+///
+/// The policy used for a request is selected by its `Host` (or HTTP/2
+/// `:authority`), with exact domains taking precedence over wildcards (e.g.
+/// `*.example.com`). Options which are not set fall back to the global
+/// configuration, so a policy only needs to specify what it changes.
+///
+/// A policy can also select a store `namespace`, in which links (including
+/// vanity paths) are looked up for its domains, so that one redirector server
+/// can serve several brands, with e.g. `go.example.com/foo` and
+/// `links.example.org/foo` leading to different destinations. Requests which
+/// don't match any link can also be redirected to a per-domain
+/// `default_destination`, e.g. the brand's homepage.
+///
+/// # Example
+/// ```toml
+/// { domains = ["example.com", "*.example.com"], https_redirect = true, redirect_status = 301, namespace = "example" }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedirectorPolicy {
+	/// The domains this policy applies to
+	pub domains: Vec<Domain>,
+	/// Redirect plain HTTP requests to HTTPS before the external redirect,
+	/// overriding the global `https_redirect` option
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub https_redirect: Option<bool>,
+	/// The status code of external redirects
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redirect_status: Option<RedirectStatus>,
+	/// The categories of statistics to collect, overriding the global
+	/// `statistics` option
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub statistics: Option<StatisticCategories>,
+	/// The store namespace in which links for these domains are looked up,
+	/// instead of the store without a namespace
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub namespace: Option<String>,
+	/// Where to redirect requests which don't match any link, overriding the
+	/// global `default_destination` option
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub default_destination: Option<Link>,
+}
+
+/// The status code used for external redirects.
+///
+/// This is the status code for `GET` requests. Other requests are redirected
+/// with the equivalent status code which preserves the request method, e.g.
+/// `307 Temporary Redirect` instead of `302 Found`. In configuration, this is
+/// represented by its numeric value, e.g. `301`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum RedirectStatus {
+	/// `301 Moved Permanently` (`308 Permanent Redirect` for methods other
+	/// than `GET`)
+	MovedPermanently,
+	/// `302 Found` (`307 Temporary Redirect` for methods other than `GET`)
+	#[default]
+	Found,
+	/// `303 See Other` (for all methods)
+	SeeOther,
+	/// `307 Temporary Redirect` (for all methods)
+	TemporaryRedirect,
+	/// `308 Permanent Redirect` (for all methods)
+	PermanentRedirect,
+}
+
+impl RedirectStatus {
+	/// Get the status code used to redirect a request with the `method`
+	#[must_use]
+	pub fn status_code(self, method: &Method) -> StatusCode {
+		match (self, method == Method::GET) {
+			(Self::MovedPermanently, true) => StatusCode::MOVED_PERMANENTLY,
+			(Self::Found, true) => StatusCode::FOUND,
+			(Self::SeeOther, _) => StatusCode::SEE_OTHER,
+			(Self::TemporaryRedirect, _) | (Self::Found, false) => StatusCode::TEMPORARY_REDIRECT,
+			(Self::PermanentRedirect, _) | (Self::MovedPermanently, false) => {
+				StatusCode::PERMANENT_REDIRECT
+			}
+		}
+	}
+}
+
+impl TryFrom<u16> for RedirectStatus {
+	type Error = String;
+
+	fn try_from(code: u16) -> Result<Self, Self::Error> {
+		match code {
+			301 => Ok(Self::MovedPermanently),
+			302 => Ok(Self::Found),
+			303 => Ok(Self::SeeOther),
+			307 => Ok(Self::TemporaryRedirect),
+			308 => Ok(Self::PermanentRedirect),
+			_ => Err(format!("{code} is not a supported redirect status code")),
+		}
+	}
+}
+
+impl From<RedirectStatus> for u16 {
+	fn from(status: RedirectStatus) -> Self {
+		match status {
+			RedirectStatus::MovedPermanently => 301,
+			RedirectStatus::Found => 302,
+			RedirectStatus::SeeOther => 303,
+			RedirectStatus::TemporaryRedirect => 307,
+			RedirectStatus::PermanentRedirect => 308,
+		}
+	}
+}
+
+/// The response to requests for links which have expired (according to their
+/// [options][crate::options]). In configuration, this is represented by the
+/// numeric value of the status code, e.g. `410`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum ExpiredStatus {
+	/// `404 Not Found`, responding just like to requests for links which don't
+	/// exist
+	NotFound,
+	/// `410 Gone`, with a page saying that the link has expired
+	#[default]
+	Gone,
+}
+
+impl ExpiredStatus {
+	/// Get the status code of responses to requests for expired links
+	#[must_use]
+	pub const fn status_code(self) -> StatusCode {
+		match self {
+			Self::NotFound => StatusCode::NOT_FOUND,
+			Self::Gone => StatusCode::GONE,
+		}
+	}
+}
+
+impl TryFrom<u16> for ExpiredStatus {
+	type Error = String;
+
+	fn try_from(code: u16) -> Result<Self, Self::Error> {
+		match code {
+			404 => Ok(Self::NotFound),
+			410 => Ok(Self::Gone),
+			_ => Err(format!("{code} is not a supported expired status code")),
+		}
+	}
+}
+
+impl From<ExpiredStatus> for u16 {
+	fn from(status: ExpiredStatus) -> Self {
+		status.status_code().as_u16()
+	}
+}
+
+/// How request paths are normalized by the redirector before their link is
+/// looked up. By default, paths are looked up as-is (apart from the usual
+/// [normalization][links_normalized::Normalized] of vanity paths), so e.g.
+/// `/foo/` and `/foo` are different links, and `/foo%2Fbar` is not the same as
+/// `/foo/bar`.
+///
+/// # Example
+/// ```toml
+/// { trim_trailing_slash = true, percent_decode = true, segments = "first" }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathNormalization {
+	/// Ignore trailing slashes, so that e.g. `/foo/` is treated like `/foo`
+	pub trim_trailing_slash: bool,
+	/// Decode percent-encoded characters (including `%2F` to `/`) before the
+	/// path is looked up
+	pub percent_decode: bool,
+	/// How paths with multiple segments (e.g. `/foo/bar`) are looked up
+	pub segments: PathSegments,
+}
+
+impl PathNormalization {
+	/// Normalize the request `path` (without the leading slash) according to
+	/// these settings, only allocating if the path is changed
+	#[must_use]
+	pub fn normalize(self, path: &str) -> Cow<'_, str> {
+		let path = if self.percent_decode {
+			percent_decode_str(path).decode_utf8_lossy()
+		} else {
+			Cow::Borrowed(path)
+		};
+
+		if self.trim_trailing_slash && path.ends_with('/') {
+			Cow::Owned(path.trim_end_matches('/').to_string())
+		} else {
+			path
+		}
+	}
+}
+
+/// How request paths with multiple segments (e.g. `/foo/bar`) are looked up
+/// by the redirector. In configuration, this is represented in lowercase, e.g.
+/// `first`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathSegments {
+	/// The whole path is looked up as one ID or vanity path, and only if that
+	/// doesn't match a link, its first segment is checked for path
+	/// pass-through
+	#[default]
+	Whole,
+	/// Only the first segment of the path is looked up, with the rest of the
+	/// path either passed through to the link's destination (if path
+	/// pass-through is enabled for it) or ignored
+	First,
+}
+
+/// The [`Referrer-Policy`] of redirect responses, which determines what
+/// information about the link (if any) is sent to its destination in the
+/// `Referer` header. In configuration, this is represented by the policy's
+/// name, e.g. `strict-origin-when-cross-origin`.
+///
+/// [`Referrer-Policy`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Referrer-Policy
+#[derive(
+	Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString, EnumDisplay,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum ReferrerPolicy {
+	/// Never send the `Referer` header
+	NoReferrer,
+	/// Send the full link, except from HTTPS to HTTP destinations
+	NoReferrerWhenDowngrade,
+	/// Only send the origin of the link (e.g. `https://example.com/`)
+	Origin,
+	/// Send the full link to same-origin destinations, and only its origin to
+	/// others
+	OriginWhenCrossOrigin,
+	/// Send the full link to same-origin destinations, and nothing to others
+	SameOrigin,
+	/// Only send the origin of the link, except from HTTPS to HTTP
+	/// destinations
+	StrictOrigin,
+	/// Send the full link to same-origin destinations, and only its origin to
+	/// others, except from HTTPS to HTTP destinations
+	StrictOriginWhenCrossOrigin,
+	/// Always send the full link
+	#[default]
+	UnsafeUrl,
+}
+
+impl ReferrerPolicy {
+	/// Get the value of the `Referrer-Policy` header for this policy
+	#[must_use]
+	pub const fn header_value(self) -> HeaderValue {
+		HeaderValue::from_static(match self {
+			Self::NoReferrer => "no-referrer",
+			Self::NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+			Self::Origin => "origin",
+			Self::OriginWhenCrossOrigin => "origin-when-cross-origin",
+			Self::SameOrigin => "same-origin",
+			Self::StrictOrigin => "strict-origin",
+			Self::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+			Self::UnsafeUrl => "unsafe-url",
+		})
+	}
+}
+
+/// Configuration of HTTP connections (of the redirector and the HTTP API). Can
+/// be generated from a [`Config`]. This is read once when a connection is
+/// accepted, and applies to that connection for its entire lifetime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HttpConnection {
+	/// How long a client may take to send the headers of an HTTP/1 request
+	/// before the connection is closed, or `None` for no limit
+	pub header_read_timeout: Option<Duration>,
+	/// How long a request may take to be processed before it is answered with
+	/// `408 Request Timeout`, or `None` for no limit
+	pub request_timeout: Option<Duration>,
+	/// How long a connection may go without any new requests before it is
+	/// gracefully closed, or `None` for no limit
+	pub idle_timeout: Option<Duration>,
+}
+
+/// Configuration of RPC API connections. Can be generated from a [`Config`].
+/// This is read once when a connection is accepted, and applies to that
+/// connection for its entire lifetime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RpcConnection {
+	/// Interval between HTTP/2 keepalive pings, or `None` to not send any
+	pub keepalive_interval: Option<Duration>,
+	/// How long to wait for a keepalive ping to be acknowledged before closing
+	/// the connection
+	pub keepalive_timeout: Duration,
+	/// How long a connection may go without any new requests before it is
+	/// gracefully closed, or `None` for no limit
+	pub idle_timeout: Option<Duration>,
+	/// How long a connection may stay open before it is gracefully closed, or
+	/// `None` for no limit
+	pub max_connection_age: Option<Duration>,
+	/// Whether responses may be compressed using zstd (if the client accepts
+	/// it), instead of only gzip
+	pub zstd: bool,
+}
+
+/// HTTP Strict Transport Security configuration settings and `max-age` in
+/// seconds for the links redirector.
+///
+/// The `max-age` indicates for how long the server's HSTS setting should be
+/// saved by browsers, with 2 years (63072000 seconds) being recommended. For
 /// preloading to work, `max-age` must be at least 1 year (31536000 seconds).
 /// Setting `max-age` to 0 will clear a browser's HSTS setting for the
 /// redirection website on next request, allowing it to perform HTTP (without
@@ -444,6 +2057,23 @@ pub enum Hsts {
 	Preload(u32),
 }
 
+impl Hsts {
+	/// Get the value of the `Strict-Transport-Security` header for this
+	/// setting, or `None` if the header should not be sent
+	#[must_use]
+	pub fn header_value(self) -> Option<HeaderValue> {
+		let value = match self {
+			Self::Disable => return None,
+			Self::Enable(max_age) => format!("max-age={max_age}"),
+			Self::IncludeSubDomains(max_age) => format!("max-age={max_age}; includeSubDomains"),
+			Self::Preload(max_age) => format!("max-age={max_age}; includeSubDomains; preload"),
+		};
+
+		// The formatted value is always a valid header value
+		HeaderValue::try_from(value).ok()
+	}
+}
+
 impl Default for Hsts {
 	fn default() -> Self {
 		Self::Enable(2 * A_YEAR)
@@ -453,7 +2083,7 @@ impl Default for Hsts {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::stats::StatisticType;
+	use crate::{config::TlsVersion, stats::StatisticType};
 
 	#[test]
 	fn config_inner_update_from_partial_all() {
@@ -465,7 +2095,7 @@ mod tests {
 
 		assert_eq!(inner, ConfigInner {
 			// This would otherwise be randomly generated and fail the test
-			token: Arc::clone(&inner.token),
+			tokens: inner.tokens.clone(),
 			..Default::default()
 		});
 
@@ -473,11 +2103,165 @@ mod tests {
 
 		assert_ne!(inner, ConfigInner {
 			// This would otherwise be randomly generated and fail the test
-			token: Arc::clone(&inner.token),
+			tokens: inner.tokens.clone(),
 			..Default::default()
 		});
 	}
 
+	#[test]
+	fn config_changes() {
+		let inner = ConfigInner::default();
+		let mut other = ConfigInner::default();
+		assert!(inner.changes(&other).is_empty());
+
+		other.send_server = !other.send_server;
+		other.rpc_idle_timeout += 1;
+		assert_eq!(inner.changes(&other), ["send_server", "rpc_idle_timeout"]);
+		assert_eq!(other.changes(&inner), ["send_server", "rpc_idle_timeout"]);
+	}
+
+	#[test]
+	fn config_rpc_connection() {
+		let inner = ConfigInner::default();
+		let config = Config {
+			redirectors: RwLock::new(Arc::new(Redirectors::from_inner(&inner))),
+			inner: RwLock::new(inner),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
+			file: None,
+		};
+
+		assert_eq!(config.http_connection(), HttpConnection {
+			header_read_timeout: Some(Duration::from_secs(30)),
+			request_timeout: None,
+			idle_timeout: None,
+		});
+
+		assert_eq!(config.rpc_connection(), RpcConnection {
+			keepalive_interval: None,
+			keepalive_timeout: Duration::from_secs(20),
+			idle_timeout: None,
+			max_connection_age: None,
+			zstd: false,
+		});
+
+		config.inner.write().update_from_partial(&Partial {
+			rpc_keepalive_interval: Some(30),
+			rpc_idle_timeout: Some(300),
+			http_header_read_timeout: Some(0),
+			http_idle_timeout: Some(60),
+			..Default::default()
+		});
+
+		assert_eq!(config.http_connection(), HttpConnection {
+			header_read_timeout: None,
+			request_timeout: None,
+			idle_timeout: Some(Duration::from_secs(60)),
+		});
+
+		assert_eq!(config.rpc_connection(), RpcConnection {
+			keepalive_interval: Some(Duration::from_secs(30)),
+			keepalive_timeout: Duration::from_secs(20),
+			idle_timeout: Some(Duration::from_secs(300)),
+			max_connection_age: None,
+			zstd: false,
+		});
+	}
+
+	#[test]
+	fn hsts_header_value() {
+		assert_eq!(Hsts::Disable.header_value(), None);
+		assert_eq!(
+			Hsts::Enable(300).header_value(),
+			Some(HeaderValue::from_static("max-age=300"))
+		);
+		assert_eq!(
+			Hsts::IncludeSubDomains(300).header_value(),
+			Some(HeaderValue::from_static("max-age=300; includeSubDomains"))
+		);
+		assert_eq!(
+			Hsts::Preload(300).header_value(),
+			Some(HeaderValue::from_static(
+				"max-age=300; includeSubDomains; preload"
+			))
+		);
+
+		let inner = ConfigInner {
+			hsts: Hsts::Preload(A_YEAR),
+			..Default::default()
+		};
+		let redirector = Redirector::from_inner(&inner);
+		assert_eq!(redirector.hsts, inner.hsts);
+		assert_eq!(redirector.hsts_header, inner.hsts.header_value());
+	}
+
+	#[test]
+	fn redirector_headers() {
+		assert_eq!(
+			ReferrerPolicy::default().header_value(),
+			HeaderValue::from_static("unsafe-url")
+		);
+		assert_eq!(
+			"strict-origin-when-cross-origin".parse::<ReferrerPolicy>(),
+			Ok(ReferrerPolicy::StrictOriginWhenCrossOrigin)
+		);
+		assert_eq!(
+			ReferrerPolicy::NoReferrerWhenDowngrade.header_value(),
+			HeaderValue::from_static("no-referrer-when-downgrade")
+		);
+
+		let inner = ConfigInner {
+			referrer_policy: ReferrerPolicy::SameOrigin,
+			extra_headers: HashMap::from([
+				("X-Frame-Options".to_string(), "DENY".to_string()),
+				(
+					"Permissions-Policy".to_string(),
+					"interest-cohort=()".to_string(),
+				),
+				("Invalid Header".to_string(), "value".to_string()),
+				("X-Invalid-Value".to_string(), "line\nbreak".to_string()),
+			]),
+			..Default::default()
+		};
+		let redirector = Redirector::from_inner(&inner);
+		assert_eq!(redirector.referrer_policy, ReferrerPolicy::SameOrigin);
+		assert_eq!(&*redirector.extra_headers, &[
+			(
+				HeaderName::from_static("permissions-policy"),
+				HeaderValue::from_static("interest-cohort=()")
+			),
+			(
+				HeaderName::from_static("x-frame-options"),
+				HeaderValue::from_static("DENY")
+			),
+		]);
+	}
+
+	#[test]
+	fn redirector_reserved_paths() {
+		let redirector = Redirector::from_inner(&ConfigInner::default());
+		assert!(redirector.is_reserved("api"));
+		assert!(redirector.is_reserved("API/v1/redirects"));
+		assert!(redirector.is_reserved(".well-known/security.txt"));
+		assert!(!redirector.is_reserved("apis"));
+		assert!(!redirector.is_reserved("example"));
+		assert!(!redirector.is_reserved(""));
+
+		let redirector = Redirector::from_inner(&ConfigInner {
+			reserved_paths: vec!["/internal/".to_string(), "/".to_string()],
+			..Default::default()
+		});
+		assert!(redirector.is_reserved("internal/status"));
+		assert!(!redirector.is_reserved("api"));
+		assert!(!redirector.is_reserved("example"));
+
+		let redirector = Redirector::from_inner(&ConfigInner {
+			reserved_paths: Vec::new(),
+			..Default::default()
+		});
+		assert!(!redirector.is_reserved("api"));
+	}
+
 	#[test]
 	fn config_inner_update_from_partial_overwrite_listeners() {
 		let mut inner = ConfigInner::default();
@@ -486,6 +2270,7 @@ mod tests {
 				protocol: Protocol::Http,
 				address: Some("::1".parse().unwrap()),
 				port: None,
+				path: None,
 			}]),
 			..Default::default()
 		};
@@ -523,4 +2308,277 @@ mod tests {
 
 		assert!(!inner.statistics.specifies(StatisticType::Request));
 	}
+
+	#[test]
+	fn config_redirector_for() {
+		let mut inner = ConfigInner::default();
+		inner.update_from_partial(&Partial {
+			https_redirect: Some(false),
+			redirector_policies: Some(vec![
+				RedirectorPolicy {
+					domains: vec![Domain::presented("*.example.com").unwrap()],
+					https_redirect: Some(true),
+					..Default::default()
+				},
+				RedirectorPolicy {
+					domains: vec![Domain::presented("www.example.com").unwrap()],
+					redirect_status: Some(RedirectStatus::MovedPermanently),
+					statistics: Some(StatisticCategories::NONE),
+					namespace: Some("www".to_string()),
+					..Default::default()
+				},
+			]),
+			..Default::default()
+		});
+
+		let config = Config {
+			redirectors: RwLock::new(Arc::new(Redirectors::from_inner(&inner))),
+			inner: RwLock::new(inner),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
+			file: None,
+		};
+
+		let default = config.redirector();
+		assert!(!default.https_redirect);
+		assert_eq!(default.redirect_status, RedirectStatus::Found);
+
+		for host in [
+			None,
+			Some("example.com"),
+			Some("[::1]:80"),
+			Some("not a domain"),
+		] {
+			assert_eq!(config.redirector_for(host), default, "{host:?}");
+		}
+
+		let wildcard = config.redirector_for(Some("links.example.com:8080"));
+		assert!(wildcard.https_redirect);
+		assert_eq!(wildcard.redirect_status, RedirectStatus::Found);
+		assert_eq!(wildcard.statistics, default.statistics);
+		assert_eq!(wildcard.namespace, None);
+
+		let www = config.redirector_for(Some("WWW.example.com"));
+		assert!(!www.https_redirect);
+		assert_eq!(www.redirect_status, RedirectStatus::MovedPermanently);
+		assert_eq!(www.statistics, StatisticCategories::NONE);
+		assert_eq!(www.namespace.as_deref(), Some("www"));
+	}
+
+	#[test]
+	fn config_listener_tls_policy() {
+		let mut inner = ConfigInner::default();
+		inner.update_from_partial(&Partial {
+			tls_policy: Some(TlsPolicy {
+				min_version: Some(TlsVersion::Tls12),
+				alpn: Some(vec!["http/1.1".to_string()]),
+				..Default::default()
+			}),
+			tls_listener_policies: Some(vec![TlsListenerPolicy {
+				listeners: vec!["https:127.0.0.1:8443".parse().unwrap()],
+				tls: TlsPolicy {
+					min_version: Some(TlsVersion::Tls13),
+					..Default::default()
+				},
+			}]),
+			..Default::default()
+		});
+
+		let config = Config {
+			redirectors: RwLock::new(Arc::new(Redirectors::from_inner(&inner))),
+			inner: RwLock::new(inner),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
+			file: None,
+		};
+
+		let global = config.tls_policy();
+		assert_eq!(
+			config.listener_tls_policy(Protocol::Https, "[::]:443".parse().unwrap()),
+			global
+		);
+		assert_eq!(
+			config.listener_tls_policy(Protocol::Grpcs, "127.0.0.1:8443".parse().unwrap()),
+			global
+		);
+
+		let listener =
+			config.listener_tls_policy(Protocol::Https, "127.0.0.1:8443".parse().unwrap());
+		assert_eq!(listener.min_version, Some(TlsVersion::Tls13));
+		assert_eq!(listener.alpn, global.alpn);
+	}
+
+	#[test]
+	fn config_check_token() {
+		let mut inner = ConfigInner::default();
+		inner.update_from_partial(&Partial {
+			tokens: Some(vec![
+				ApiToken {
+					id: "ci".to_string(),
+					token: "abc123".to_string(),
+					role: TokenRole::Write,
+					quota: Quota {
+						daily: 100,
+						monthly: 0,
+					},
+				},
+				ApiToken {
+					id: "dashboard".to_string(),
+					token: "def456".to_string(),
+					role: TokenRole::ReadOnly,
+					quota: Quota::default(),
+				},
+			]),
+			..Default::default()
+		});
+
+		let config = Config {
+			redirectors: RwLock::new(Arc::new(Redirectors::from_inner(&inner))),
+			inner: RwLock::new(inner),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
+			file: None,
+		};
+
+		assert_eq!(
+			config.check_token(b"abc123"),
+			Some(("ci".to_string(), TokenRole::Write))
+		);
+		assert_eq!(
+			config.check_token(b"def456"),
+			Some(("dashboard".to_string(), TokenRole::ReadOnly))
+		);
+		assert_eq!(config.check_token(b"abc"), None);
+		assert_eq!(config.check_token(b""), None);
+		assert!(!format!("{:?}", config.tokens()).contains("abc123"));
+		assert_eq!(config.token_quota("ci").daily, 100);
+		assert!(!config.token_quota("dashboard").is_enabled());
+		assert!(!config.token_quota("unknown").is_enabled());
+	}
+
+	#[test]
+	fn config_rotate_token() {
+		let mut inner = ConfigInner::default();
+		inner.update_from_partial(&Partial {
+			tokens: Some(vec![ApiToken {
+				id: "ci".to_string(),
+				token: "abc123".to_string(),
+				role: TokenRole::Write,
+				quota: Quota::default(),
+			}]),
+			..Default::default()
+		});
+
+		let config = Config {
+			redirectors: RwLock::new(Arc::new(Redirectors::from_inner(&inner))),
+			inner: RwLock::new(inner),
+			rotated_tokens: RwLock::new(HashMap::new()),
+			reloads: ReloadChannel::default(),
+			file: None,
+		};
+
+		let ci = Some(("ci".to_string(), TokenRole::Write));
+
+		assert_eq!(
+			config.rotate_token("missing", "xyz".to_string(), Duration::ZERO),
+			None
+		);
+
+		assert_eq!(
+			config.rotate_token("ci", "def456".to_string(), Duration::from_secs(60)),
+			Some(TokenRole::Write)
+		);
+		assert_eq!(config.check_token(b"def456"), ci);
+		assert_eq!(config.check_token(b"abc123"), ci);
+		assert_eq!(config.tokens()[0].token, "def456");
+
+		assert_eq!(
+			config.rotate_token("ci", "ghi789".to_string(), Duration::ZERO),
+			Some(TokenRole::Write)
+		);
+		assert_eq!(config.check_token(b"ghi789"), ci);
+		assert_eq!(config.check_token(b"def456"), None);
+		assert_eq!(config.check_token(b"abc123"), None);
+		assert!(!format!("{config:?}").contains("ghi789"));
+	}
+
+	#[test]
+	fn token_role() {
+		assert!(TokenRole::ReadOnly < TokenRole::Write);
+		assert!(TokenRole::Write < TokenRole::Admin);
+		assert_eq!(TokenRole::default(), TokenRole::Admin);
+
+		for role in [TokenRole::ReadOnly, TokenRole::Write, TokenRole::Admin] {
+			assert_eq!(
+				serde_json::from_value::<TokenRole>(serde_json::to_value(role).unwrap()).unwrap(),
+				role
+			);
+			assert_eq!(serde_json::to_value(role).unwrap(), role.to_string());
+			assert_eq!(role.to_string().parse::<TokenRole>().unwrap(), role);
+		}
+
+		assert_eq!(
+			serde_json::from_str::<ApiToken>(r#"{"id": "a", "token": "b"}"#)
+				.unwrap()
+				.role,
+			TokenRole::Admin
+		);
+	}
+
+	#[test]
+	fn redirect_status() {
+		let statuses = [301, 302, 303, 307, 308].map(|s| RedirectStatus::try_from(s).unwrap());
+
+		assert_eq!(statuses.map(|s| s.status_code(&Method::GET).as_u16()), [
+			301, 302, 303, 307, 308
+		]);
+		assert_eq!(statuses.map(|s| s.status_code(&Method::POST).as_u16()), [
+			308, 307, 303, 307, 308
+		]);
+		assert_eq!(statuses.map(u16::from), [301, 302, 303, 307, 308]);
+
+		assert!(RedirectStatus::try_from(200).is_err());
+		assert!(RedirectStatus::try_from(304).is_err());
+		assert_eq!(
+			serde_json::from_str::<RedirectStatus>("308").unwrap(),
+			RedirectStatus::PermanentRedirect
+		);
+	}
+
+	#[test]
+	fn expired_status() {
+		assert_eq!(ExpiredStatus::default(), ExpiredStatus::Gone);
+		assert_eq!(
+			ExpiredStatus::try_from(404).unwrap().status_code(),
+			StatusCode::NOT_FOUND
+		);
+		assert_eq!(u16::from(ExpiredStatus::Gone), 410);
+
+		assert!(ExpiredStatus::try_from(200).is_err());
+		assert!(ExpiredStatus::try_from(302).is_err());
+		assert_eq!(
+			serde_json::from_str::<ExpiredStatus>("404").unwrap(),
+			ExpiredStatus::NotFound
+		);
+	}
+
+	#[test]
+	fn path_normalization() {
+		let default = PathNormalization::default();
+		assert_eq!(default.normalize("foo/"), "foo/");
+		assert_eq!(default.normalize("foo%2Fbar"), "foo%2Fbar");
+		assert!(matches!(default.normalize("foo"), Cow::Borrowed(_)));
+
+		let normalization: PathNormalization = serde_json::from_str(
+			r#"{"trim_trailing_slash": true, "percent_decode": true, "segments": "first"}"#,
+		)
+		.unwrap();
+		assert_eq!(normalization.segments, PathSegments::First);
+		assert_eq!(normalization.normalize("foo/"), "foo");
+		assert_eq!(normalization.normalize("foo//"), "foo");
+		assert_eq!(normalization.normalize("foo%2Fbar%2f"), "foo/bar");
+		assert_eq!(normalization.normalize("caf%C3%A9"), "café");
+		assert_eq!(normalization.normalize(""), "");
+		assert!(matches!(normalization.normalize("foo"), Cow::Borrowed(_)));
+	}
 }
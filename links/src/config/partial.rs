@@ -1,22 +1,41 @@
 //! Links server configuration as seen by the user
 
 use std::{
-	collections::HashMap, env, ffi::OsStr, fs, io::Error as IoError, path::Path, str::FromStr,
+	collections::HashMap,
+	env,
+	ffi::OsStr,
+	fs,
+	io::Error as IoError,
+	path::{Path, PathBuf},
+	str::FromStr,
 };
 
 use basic_toml::Error as TomlError;
+use links_domainmap::Domain;
+use links_normalized::Link;
 use pico_args::Arguments;
 use serde::{Deserialize, Serialize};
 use serde_json::Error as JsonError;
-use serde_yaml::Error as YamlError;
+use serde_yaml::{Error as YamlError, Mapping, Value};
 use strum::{Display as EnumDisplay, EnumString};
 use thiserror::Error;
 use tracing::{instrument, warn};
 
 use crate::{
-	config::{global::Hsts, CertificateSource, DefaultCertificateSource, ListenAddress, LogLevel},
-	stats::StatisticCategories,
+	access_log::AccessLog,
+	config::{
+		global::Hsts, ApiToken, CertificateSource, DefaultCertificateSource, ExpiredStatus,
+		ListenAddress, LogLevel, PathNormalization, RedirectorPolicy, ReferrerPolicy,
+		TlsListenerPolicy, TlsPolicy, TokenRole,
+	},
+	http_api::ApiCors,
+	loops::LoopDetection,
+	options::Cors,
+	ratelimit::{RedirectRateLimits, RpcRateLimits},
+	reputation::ReputationChecks,
+	stats::{anomaly::AnomalyDetection, StatisticCategories},
 	store::BackendType,
+	webhook::Webhook,
 };
 
 /// The error returned by fallible conversions into a [`Partial`]
@@ -99,16 +118,27 @@ pub struct Partial {
 	/// may expose secret information, so are not recommended for production
 	/// deployments.
 	pub log_level: Option<LogLevel>,
-	/// API token, used for authentication of gRPC clients
-	pub token: Option<String>,
+	/// API tokens, used for authentication of API clients
+	pub tokens: Option<Vec<ApiToken>>,
 	/// Listener addresses, see [`ListenAddress`] for details
 	pub listeners: Option<Vec<ListenAddress>>,
+	/// Addresses of listeners on which connections start with a PROXY
+	/// protocol header
+	pub proxy_protocol_listeners: Option<Vec<ListenAddress>>,
 	/// What types of statistics should be collected
 	pub statistics: Option<StatisticCategories>,
 	/// Default TLS certificate and key source
 	pub default_certificate: Option<DefaultCertificateSource>,
 	/// TLS certificate and key sources
 	pub certificates: Option<Vec<CertificateSource>>,
+	/// Global TLS policy, used for settings not specified by listeners'
+	/// or domains' policies
+	pub tls_policy: Option<TlsPolicy>,
+	/// TLS policies of specific listeners
+	pub tls_listener_policies: Option<Vec<TlsListenerPolicy>>,
+	/// Interval (in seconds) between rotations of the TLS session ticket
+	/// encryption keys, `0` to disable session tickets
+	pub tls_ticket_rotation: Option<u64>,
 	/// HTTP Strict Transport Security setting on redirect
 	pub hsts: Option<PartialHsts>,
 	/// HTTP Strict Transport Security `max_age` header attribute (retention
@@ -116,6 +146,9 @@ pub struct Partial {
 	pub hsts_max_age: Option<u32>,
 	/// Redirect from HTTP to HTTPS before the external redirect
 	pub https_redirect: Option<bool>,
+	/// Per-domain overrides of redirect behavior, selected by the request's
+	/// host
+	pub redirector_policies: Option<Vec<RedirectorPolicy>>,
 	/// Send the `Alt-Svc` header advertising `h2` (HTTP/2.0 with TLS) support
 	/// on port 443
 	pub send_alt_svc: Option<bool>,
@@ -123,6 +156,102 @@ pub struct Partial {
 	pub send_server: Option<bool>,
 	/// Send the `Content-Security-Policy` header
 	pub send_csp: Option<bool>,
+	/// The `Referrer-Policy` of redirect responses
+	pub referrer_policy: Option<ReferrerPolicy>,
+	/// Additional headers (by name) sent in all redirector responses, e.g.
+	/// `Permissions-Policy`
+	pub extra_headers: Option<HashMap<String, String>>,
+	/// Cross-origin resource sharing settings for redirect responses, which
+	/// can be overridden per-link
+	pub cors: Option<Cors>,
+	/// Append the rest of the request path after a link's ID or vanity path
+	/// to its destination, which can be overridden per-link
+	pub path_passthrough: Option<bool>,
+	/// How request paths are normalized before their link is looked up
+	pub path_normalization: Option<PathNormalization>,
+	/// The response to requests for links which have expired
+	pub expired_status: Option<ExpiredStatus>,
+	/// The suffix of request paths (or queries) for previews of links instead
+	/// of redirects, empty to disable link previews
+	pub preview_suffix: Option<String>,
+	/// The contents of `/robots.txt`
+	pub robots_txt: Option<String>,
+	/// Path prefixes which are never looked up as links, and can't be used as
+	/// vanity paths
+	pub reserved_paths: Option<Vec<String>>,
+	/// The URL that requests for `/favicon.ico` are redirected to, instead of
+	/// serving the built-in icon
+	pub favicon: Option<Link>,
+	/// The URL that requests which don't match any link are redirected to,
+	/// instead of responding with `404 Not Found`
+	pub default_destination: Option<Link>,
+	/// The URL template that requests for unknown paths are redirected to,
+	/// with `{path}` replaced by the requested path
+	pub search_fallback: Option<String>,
+	/// The number of seconds before the interstitial page redirects
+	pub interstitial_delay: Option<u64>,
+	/// Path to a template of the interstitial page, used instead of the
+	/// built-in one
+	pub interstitial_template: Option<PathBuf>,
+	/// Whether to serve metrics in the Prometheus text format from `/metrics`
+	/// on redirector listeners
+	pub metrics: Option<bool>,
+	/// The structured access log of redirects
+	pub access_log: Option<AccessLog>,
+	/// Path to a MaxMind GeoIP2 or GeoLite2 database used for geo-targeted
+	/// link destinations
+	pub geoip_database: Option<PathBuf>,
+	/// Domains which links must not point to, including wildcards
+	pub blocked_domains: Option<Vec<Domain>>,
+	/// Whether to also check link destinations against the blocked domains
+	/// when redirecting
+	pub recheck_blocked_domains: Option<bool>,
+	/// Detection of redirect loops through this server's own domains
+	pub loop_detection: Option<LoopDetection>,
+	/// Rate limits of redirector requests per client IP address
+	pub redirect_rate_limits: Option<RedirectRateLimits>,
+	/// Cross-origin resource sharing settings for the HTTP API
+	pub http_api_cors: Option<ApiCors>,
+	/// Detection of anomalous redirect traffic, disabled if the interval is
+	/// `0`
+	pub anomaly_detection: Option<AnomalyDetection>,
+	/// Periodic reputation checks of link destinations, disabled if the
+	/// interval is `0`
+	pub reputation_checks: Option<ReputationChecks>,
+	/// Time (in seconds) a client may take to send the headers of an HTTP/1
+	/// request, `0` to disable
+	pub http_header_read_timeout: Option<u64>,
+	/// Time (in seconds) an HTTP request may take to be processed, `0` to
+	/// disable
+	pub http_request_timeout: Option<u64>,
+	/// Time (in seconds) after the last request on an HTTP connection after
+	/// which that connection is gracefully closed, `0` to disable
+	pub http_idle_timeout: Option<u64>,
+	/// Interval (in seconds) between HTTP/2 keepalive pings sent on RPC
+	/// connections, `0` to disable keepalive pings
+	pub rpc_keepalive_interval: Option<u64>,
+	/// Time (in seconds) to wait for an acknowledgement of an RPC keepalive
+	/// ping before closing the connection
+	pub rpc_keepalive_timeout: Option<u64>,
+	/// Time (in seconds) after the last request on an RPC connection after
+	/// which that connection is gracefully closed, `0` to disable
+	pub rpc_idle_timeout: Option<u64>,
+	/// Maximum age (in seconds) of an RPC connection, after which it is
+	/// gracefully closed, `0` to disable
+	pub rpc_max_connection_age: Option<u64>,
+	/// Path to a PEM file with the CA certificates used to verify the client
+	/// certificates required by `grpcs` listeners
+	pub rpc_client_ca: Option<PathBuf>,
+	/// The role given to RPC clients authenticated only by a verified client
+	/// certificate, without an API token
+	pub rpc_client_cert_role: Option<TokenRole>,
+	/// Rate limits of RPC API requests per API token and per peer
+	pub rpc_rate_limits: Option<RpcRateLimits>,
+	/// Addresses of RPC listeners on which responses may be compressed using
+	/// zstd
+	pub rpc_zstd_listeners: Option<Vec<ListenAddress>>,
+	/// Webhooks notified of changes to links made via the APIs
+	pub webhooks: Option<Vec<Webhook>>,
 	/// The store backend type
 	pub store: Option<BackendType>,
 	/// The store backend configuration. All of these options are
@@ -182,6 +311,63 @@ impl Partial {
 		parse(&fs::read_to_string(path)?)
 	}
 
+	/// Overwrite the `listeners` option in a configuration file, keeping all
+	/// other options as they are. The format of the file is determined from
+	/// its extension, just like in [`Partial::from_file`]. Comments and
+	/// formatting are not preserved, as the whole file is rewritten.
+	///
+	/// # IO
+	/// This function performs synchronous file IO, and should not be used in an
+	/// asynchronous context.
+	///
+	/// # Errors
+	/// Returns an error when reading, parsing, serializing, or writing the file
+	/// fails.
+	#[instrument(level = "debug", err)]
+	pub fn write_listeners_to_file(
+		path: &Path,
+		listeners: &[ListenAddress],
+	) -> Result<(), IntoPartialError> {
+		// `serde_yaml`'s `Value` is used for all formats because it keeps the
+		// order of options in the file
+		let mut file: Value = match path.extension().map(OsStr::to_str) {
+			Some(Some("toml")) => basic_toml::from_str(&fs::read_to_string(path)?)?,
+			Some(Some("yaml" | "yml")) => serde_yaml::from_str(&fs::read_to_string(path)?)?,
+			Some(Some("json")) => serde_json::from_str(&fs::read_to_string(path)?)?,
+			_ => return Err(IntoPartialError::UnknownExtension),
+		};
+
+		let listeners = serde_yaml::to_value(listeners)?;
+		match file {
+			Value::Mapping(ref mut options) => {
+				options.insert("listeners".into(), listeners);
+			}
+			_ => file = Value::Mapping(Mapping::from_iter([("listeners".into(), listeners)])),
+		}
+
+		let contents = match path.extension().map(OsStr::to_str) {
+			Some(Some("toml")) => {
+				// Tables have to come after all other values in toml
+				if let Value::Mapping(ref mut options) = file {
+					let (tables, values): (Vec<_>, Vec<_>) =
+						std::mem::take(options).into_iter().partition(|(_, v)| {
+							v.is_mapping()
+								|| v.as_sequence().is_some_and(|s| {
+									!s.is_empty() && s.iter().all(Value::is_mapping)
+								})
+						});
+					options.extend(values.into_iter().chain(tables));
+				}
+
+				basic_toml::to_string(&file)?
+			}
+			Some(Some("json")) => serde_json::to_string_pretty(&file)?,
+			_ => serde_yaml::to_string(&file)?,
+		};
+
+		Ok(fs::write(path, contents)?)
+	}
+
 	/// Parse command-line arguments into a [`Partial`]. Listeners and store
 	/// configuration are parsed from json strings.
 	#[must_use]
@@ -190,17 +376,86 @@ impl Partial {
 		let mut args = Arguments::from_env();
 		Self {
 			log_level: args.opt_value_from_str("--log-level").unwrap_or(None),
-			token: args.opt_value_from_str("--token").unwrap_or(None),
+			tokens: deserialize_arg(&mut args, "--tokens"),
 			listeners: deserialize_arg(&mut args, "--listeners"),
+			proxy_protocol_listeners: deserialize_arg(&mut args, "--proxy-protocol-listeners"),
 			statistics: deserialize_arg(&mut args, "--statistics"),
 			default_certificate: deserialize_arg(&mut args, "--default-certificate"),
 			certificates: deserialize_arg(&mut args, "--certificates"),
+			tls_policy: deserialize_arg(&mut args, "--tls-policy"),
+			tls_listener_policies: deserialize_arg(&mut args, "--tls-listener-policies"),
+			tls_ticket_rotation: args
+				.opt_value_from_str("--tls-ticket-rotation")
+				.unwrap_or(None),
 			hsts: args.opt_value_from_str("--hsts").unwrap_or(None),
 			hsts_max_age: args.opt_value_from_str("--hsts-max-age").unwrap_or(None),
 			https_redirect: args.opt_value_from_str("--https-redirect").unwrap_or(None),
+			redirector_policies: deserialize_arg(&mut args, "--redirector-policies"),
 			send_alt_svc: args.opt_value_from_str("--send-alt-svc").unwrap_or(None),
 			send_server: args.opt_value_from_str("--send-server").unwrap_or(None),
 			send_csp: args.opt_value_from_str("--send-csp").unwrap_or(None),
+			referrer_policy: args.opt_value_from_str("--referrer-policy").unwrap_or(None),
+			extra_headers: deserialize_arg(&mut args, "--extra-headers"),
+			cors: deserialize_arg(&mut args, "--cors"),
+			path_passthrough: args
+				.opt_value_from_str("--path-passthrough")
+				.unwrap_or(None),
+			path_normalization: deserialize_arg(&mut args, "--path-normalization"),
+			expired_status: deserialize_arg(&mut args, "--expired-status"),
+			preview_suffix: args.opt_value_from_str("--preview-suffix").unwrap_or(None),
+			robots_txt: args.opt_value_from_str("--robots-txt").unwrap_or(None),
+			reserved_paths: deserialize_arg(&mut args, "--reserved-paths"),
+			favicon: args.opt_value_from_str("--favicon").unwrap_or(None),
+			default_destination: args
+				.opt_value_from_str("--default-destination")
+				.unwrap_or(None),
+			search_fallback: args.opt_value_from_str("--search-fallback").unwrap_or(None),
+			interstitial_delay: args
+				.opt_value_from_str("--interstitial-delay")
+				.unwrap_or(None),
+			interstitial_template: args
+				.opt_value_from_str("--interstitial-template")
+				.unwrap_or(None),
+			metrics: args.opt_value_from_str("--metrics").unwrap_or(None),
+			access_log: deserialize_arg(&mut args, "--access-log"),
+			geoip_database: args.opt_value_from_str("--geoip-database").unwrap_or(None),
+			blocked_domains: deserialize_arg(&mut args, "--blocked-domains"),
+			recheck_blocked_domains: args
+				.opt_value_from_str("--recheck-blocked-domains")
+				.unwrap_or(None),
+			loop_detection: deserialize_arg(&mut args, "--loop-detection"),
+			redirect_rate_limits: deserialize_arg(&mut args, "--redirect-rate-limits"),
+			http_api_cors: deserialize_arg(&mut args, "--http-api-cors"),
+			anomaly_detection: deserialize_arg(&mut args, "--anomaly-detection"),
+			reputation_checks: deserialize_arg(&mut args, "--reputation-checks"),
+			http_header_read_timeout: args
+				.opt_value_from_str("--http-header-read-timeout")
+				.unwrap_or(None),
+			http_request_timeout: args
+				.opt_value_from_str("--http-request-timeout")
+				.unwrap_or(None),
+			http_idle_timeout: args
+				.opt_value_from_str("--http-idle-timeout")
+				.unwrap_or(None),
+			rpc_keepalive_interval: args
+				.opt_value_from_str("--rpc-keepalive-interval")
+				.unwrap_or(None),
+			rpc_keepalive_timeout: args
+				.opt_value_from_str("--rpc-keepalive-timeout")
+				.unwrap_or(None),
+			rpc_idle_timeout: args
+				.opt_value_from_str("--rpc-idle-timeout")
+				.unwrap_or(None),
+			rpc_max_connection_age: args
+				.opt_value_from_str("--rpc-max-connection-age")
+				.unwrap_or(None),
+			rpc_client_ca: args.opt_value_from_str("--rpc-client-ca").unwrap_or(None),
+			rpc_client_cert_role: args
+				.opt_value_from_str("--rpc-client-cert-role")
+				.unwrap_or(None),
+			rpc_rate_limits: deserialize_arg(&mut args, "--rpc-rate-limits"),
+			rpc_zstd_listeners: deserialize_arg(&mut args, "--rpc-zstd-listeners"),
+			webhooks: deserialize_arg(&mut args, "--webhooks"),
 			store: args.opt_value_from_str("--store").unwrap_or(None),
 			store_config: deserialize_arg(&mut args, "--store-config"),
 		}
@@ -213,17 +468,58 @@ impl Partial {
 	pub fn from_env_vars() -> Self {
 		Self {
 			log_level: parse_env_var("LINKS_LOG_LEVEL"),
-			token: parse_env_var("LINKS_TOKEN"),
+			tokens: deserialize_env_var("LINKS_TOKENS"),
 			listeners: deserialize_env_var("LINKS_LISTENERS"),
+			proxy_protocol_listeners: deserialize_env_var("LINKS_PROXY_PROTOCOL_LISTENERS"),
 			statistics: deserialize_env_var("LINKS_STATISTICS"),
 			default_certificate: deserialize_env_var("LINKS_DEFAULT_CERTIFICATE"),
 			certificates: deserialize_env_var("LINKS_CERTIFICATES"),
+			tls_policy: deserialize_env_var("LINKS_TLS_POLICY"),
+			tls_listener_policies: deserialize_env_var("LINKS_TLS_LISTENER_POLICIES"),
+			tls_ticket_rotation: parse_env_var("LINKS_TLS_TICKET_ROTATION"),
 			hsts: parse_env_var("LINKS_HSTS"),
 			hsts_max_age: parse_env_var("LINKS_HSTS_MAX_AGE"),
 			https_redirect: parse_env_var("LINKS_HTTPS_REDIRECT"),
+			redirector_policies: deserialize_env_var("LINKS_REDIRECTOR_POLICIES"),
 			send_alt_svc: parse_env_var("LINKS_SEND_ALT_SVC"),
 			send_server: parse_env_var("LINKS_SEND_SERVER"),
 			send_csp: parse_env_var("LINKS_SEND_CSP"),
+			referrer_policy: parse_env_var("LINKS_REFERRER_POLICY"),
+			extra_headers: deserialize_env_var("LINKS_EXTRA_HEADERS"),
+			cors: deserialize_env_var("LINKS_CORS"),
+			path_passthrough: parse_env_var("LINKS_PATH_PASSTHROUGH"),
+			path_normalization: deserialize_env_var("LINKS_PATH_NORMALIZATION"),
+			expired_status: deserialize_env_var("LINKS_EXPIRED_STATUS"),
+			preview_suffix: parse_env_var("LINKS_PREVIEW_SUFFIX"),
+			robots_txt: parse_env_var("LINKS_ROBOTS_TXT"),
+			reserved_paths: deserialize_env_var("LINKS_RESERVED_PATHS"),
+			favicon: parse_env_var("LINKS_FAVICON"),
+			default_destination: parse_env_var("LINKS_DEFAULT_DESTINATION"),
+			search_fallback: parse_env_var("LINKS_SEARCH_FALLBACK"),
+			interstitial_delay: parse_env_var("LINKS_INTERSTITIAL_DELAY"),
+			interstitial_template: parse_env_var("LINKS_INTERSTITIAL_TEMPLATE"),
+			metrics: parse_env_var("LINKS_METRICS"),
+			access_log: deserialize_env_var("LINKS_ACCESS_LOG"),
+			geoip_database: parse_env_var("LINKS_GEOIP_DATABASE"),
+			blocked_domains: deserialize_env_var("LINKS_BLOCKED_DOMAINS"),
+			recheck_blocked_domains: parse_env_var("LINKS_RECHECK_BLOCKED_DOMAINS"),
+			loop_detection: deserialize_env_var("LINKS_LOOP_DETECTION"),
+			redirect_rate_limits: deserialize_env_var("LINKS_REDIRECT_RATE_LIMITS"),
+			http_api_cors: deserialize_env_var("LINKS_HTTP_API_CORS"),
+			anomaly_detection: deserialize_env_var("LINKS_ANOMALY_DETECTION"),
+			reputation_checks: deserialize_env_var("LINKS_REPUTATION_CHECKS"),
+			http_header_read_timeout: parse_env_var("LINKS_HTTP_HEADER_READ_TIMEOUT"),
+			http_request_timeout: parse_env_var("LINKS_HTTP_REQUEST_TIMEOUT"),
+			http_idle_timeout: parse_env_var("LINKS_HTTP_IDLE_TIMEOUT"),
+			rpc_keepalive_interval: parse_env_var("LINKS_RPC_KEEPALIVE_INTERVAL"),
+			rpc_keepalive_timeout: parse_env_var("LINKS_RPC_KEEPALIVE_TIMEOUT"),
+			rpc_idle_timeout: parse_env_var("LINKS_RPC_IDLE_TIMEOUT"),
+			rpc_max_connection_age: parse_env_var("LINKS_RPC_MAX_CONNECTION_AGE"),
+			rpc_client_ca: parse_env_var("LINKS_RPC_CLIENT_CA"),
+			rpc_client_cert_role: parse_env_var("LINKS_RPC_CLIENT_CERT_ROLE"),
+			rpc_rate_limits: deserialize_env_var("LINKS_RPC_RATE_LIMITS"),
+			rpc_zstd_listeners: deserialize_env_var("LINKS_RPC_ZSTD_LISTENERS"),
+			webhooks: deserialize_env_var("LINKS_WEBHOOKS"),
 			store: parse_env_var("LINKS_STORE"),
 			store_config: deserialize_env_var("LINKS_STORE_CONFIG"),
 		}
@@ -0,0 +1,181 @@
+//! Geo-targeted link destinations
+//!
+//! Links can have alternative destinations for visitors from specific
+//! countries or continents (see the `geo_destinations` [link
+//! option][crate::options]), keyed by ISO 3166-1 alpha-2 country codes (e.g.
+//! `US`) or continent codes (`AF`, `AN`, `AS`, `EU`, `NA`, `OC`, or `SA`).
+//! Visitors are located by their IP address using a [MaxMind] GeoIP2 or
+//! GeoLite2 country (or city) database, configured via the `geoip_database`
+//! configuration option. Lookups are done entirely locally, so no information
+//! about visitors ever leaves the server.
+//!
+//! If a visitor's country has a destination, they are redirected there,
+//! otherwise to their continent's destination (if any). Visitors whose
+//! location is unknown (e.g. because no database is configured) or matches no
+//! geo-targeted destination are redirected to the link's default destination.
+//!
+//! [MaxMind]: https://dev.maxmind.com/geoip/docs/databases
+
+use std::{
+	collections::BTreeMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	fs,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::SystemTime,
+};
+
+use links_normalized::Link;
+use maxminddb::{geoip2, Reader};
+use parking_lot::Mutex;
+use tracing::{error, info, trace};
+
+/// The most recently loaded database, which is reused until its file changes
+static LOADED: Mutex<Option<Arc<GeoIpDatabase>>> = Mutex::new(None);
+
+/// A MaxMind GeoIP2 or GeoLite2 database, loaded into memory
+pub struct GeoIpDatabase {
+	/// The path of the database file
+	path: PathBuf,
+	/// The modification time of the database file when it was loaded
+	modified: Option<SystemTime>,
+	/// The database itself
+	reader: Reader<Vec<u8>>,
+}
+
+impl GeoIpDatabase {
+	/// Load the database at `path`, or reuse the previously loaded one if its
+	/// file hasn't changed since. Returns `None` if the database can not be
+	/// loaded, in which case geo-targeting is effectively disabled.
+	#[must_use]
+	pub fn load(path: &Path) -> Option<Arc<Self>> {
+		let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+		let mut loaded = LOADED.lock();
+
+		if let Some(database) = loaded
+			.as_ref()
+			.filter(|db| db.path == path && db.modified == modified)
+		{
+			return Some(Arc::clone(database));
+		}
+
+		match Reader::open_readfile(path) {
+			Ok(reader) => {
+				info!(?path, "Loaded GeoIP database");
+
+				let database = Arc::new(Self {
+					path: path.to_path_buf(),
+					modified,
+					reader,
+				});
+
+				*loaded = Some(Arc::clone(&database));
+				Some(database)
+			}
+			Err(err) => {
+				error!(%err, ?path, "Could not load GeoIP database, geo-targeting is disabled");
+				None
+			}
+		}
+	}
+
+	/// Get the destination for a visitor from `ip` out of the geo-targeted
+	/// `destinations`, or `None` if there is no destination for their location
+	#[must_use]
+	pub fn destination<'a>(
+		&self,
+		ip: IpAddr,
+		destinations: &'a BTreeMap<String, Link>,
+	) -> Option<&'a Link> {
+		let location = match self.reader.lookup::<geoip2::Country>(ip) {
+			Ok(location) => location,
+			Err(err) => {
+				trace!(%err, %ip, "IP address not found in GeoIP database");
+				return None;
+			}
+		};
+
+		find_destination(
+			destinations,
+			location.country.and_then(|country| country.iso_code),
+			location.continent.and_then(|continent| continent.code),
+		)
+	}
+}
+
+impl Debug for GeoIpDatabase {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("GeoIpDatabase")
+			.field("path", &self.path)
+			.field("modified", &self.modified)
+			.finish_non_exhaustive()
+	}
+}
+
+impl PartialEq for GeoIpDatabase {
+	fn eq(&self, other: &Self) -> bool {
+		self.path == other.path && self.modified == other.modified
+	}
+}
+
+impl Eq for GeoIpDatabase {}
+
+/// Find the destination for the `country` (or otherwise the `continent`) in
+/// `destinations`, whose keys are matched case-insensitively
+fn find_destination<'a>(
+	destinations: &'a BTreeMap<String, Link>,
+	country: Option<&str>,
+	continent: Option<&str>,
+) -> Option<&'a Link> {
+	let find = |code: &str| {
+		destinations
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(code))
+			.map(|(_, link)| link)
+	};
+
+	country.and_then(find).or_else(|| continent.and_then(find))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn destinations() {
+		let us = Link::new("https://example.com/us").unwrap();
+		let eu = Link::new("https://example.com/eu").unwrap();
+		let si = Link::new("https://example.com/si").unwrap();
+		let destinations = BTreeMap::from([
+			("US".to_string(), us.clone()),
+			("eu".to_string(), eu.clone()),
+			("SI".to_string(), si.clone()),
+		]);
+
+		assert_eq!(
+			find_destination(&destinations, Some("US"), Some("NA")),
+			Some(&us)
+		);
+		assert_eq!(
+			find_destination(&destinations, Some("SI"), Some("EU")),
+			Some(&si)
+		);
+		assert_eq!(
+			find_destination(&destinations, Some("DE"), Some("EU")),
+			Some(&eu)
+		);
+		assert_eq!(
+			find_destination(&destinations, Some("CA"), Some("NA")),
+			None
+		);
+		assert_eq!(find_destination(&destinations, None, Some("EU")), Some(&eu));
+		assert_eq!(find_destination(&destinations, None, None), None);
+		assert_eq!(find_destination(&BTreeMap::new(), Some("US"), None), None);
+	}
+
+	#[test]
+	fn missing_database() {
+		assert!(GeoIpDatabase::load(Path::new("./does-not-exist.mmdb")).is_none());
+	}
+}
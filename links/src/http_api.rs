@@ -0,0 +1,1383 @@
+//! The HTTP+JSON API, an alternative to the [gRPC API][crate::api] for clients
+//! which can't use gRPC. It provides the same store operations, mapped to HTTP
+//! methods on resources under `/api/v1/`:
+//!
+//! - `GET`, `PUT`, and `DELETE` `/api/v1/redirects/[ID]` to get, set, and
+//!   remove redirects, with the request and response bodies containing the
+//!   redirect's link (e.g. `{"link": "https://example.com/"}`)
+//! - `POST /api/v1/redirects/[ID]/restore` to restore a soft-deleted redirect
+//! - `POST /api/v1/redirects/[ID]/enable` and `.../disable` to enable and
+//!   disable a redirect, with the response body containing whether it was
+//!   enabled before (e.g. `{"enabled": true}`)
+//! - `POST /api/v1/purge` to permanently remove all soft-deleted redirects
+//! - `GET`, `PUT`, and `DELETE` `/api/v1/vanities/[vanity path]` to get, set,
+//!   and remove vanity paths, with the request and response bodies containing
+//!   the vanity path's ID (e.g. `{"id": "9dDbKpJP"}`)
+//! - `GET`, `PUT`, and `DELETE` `/api/v1/options/[ID]` to get, set, and remove
+//!   link options, with the request body containing the options, and the
+//!   response body containing them in `options`
+//! - `GET` and `DELETE` `/api/v1/stats` to get and remove statistics, filtered
+//!   by the `link`, `type`, `data`, and `time` query parameters
+//!
+//! Responses to `PUT` and `DELETE` requests contain the previous value, if
+//! any. Changes to redirects and vanity paths are sent to the configured
+//! [webhooks][crate::webhook]. Errors are returned with an appropriate status
+//! code and a JSON body with the error message in `error`.
+//!
+//! An [OpenAPI 3] document describing all of these endpoints (e.g. for
+//! generating client SDKs) is served at `/api/openapi.json`, without
+//! authentication.
+//!
+//! All other requests must be authenticated using one of the same tokens as
+//! the gRPC API, in the `Authorization` header (`Authorization: Bearer
+//! [TOKEN]`). A store namespace can be selected using the `Links-Namespace`
+//! header, like with the `namespace` gRPC metadata value. Like in the gRPC API,
+//! `GET` requests need a token with at least the `read-only` role, purging
+//! soft-deleted redirects needs the `admin` role, and everything else needs the
+//! `write` role. Requests which need the `write` or `admin` role also count
+//! towards the token's [quota][crate::ratelimit::Quota], and are rejected with
+//! `429 Too Many Requests` if it is exceeded.
+//!
+//! Browser-based clients (e.g. admin dashboards) on other websites can call the
+//! API directly if their origin is allowed by the `http_api_cors` configuration
+//! option (see [`ApiCors`]). CORS preflight requests are answered without
+//! authentication.
+//!
+//! [OpenAPI 3]: https://spec.openapis.org/oas/v3.1.0
+
+use std::sync::LazyLock;
+
+use http_body_util::{BodyExt, Limited};
+use hyper::{
+	body::Body,
+	header::{
+		HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+		ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD,
+		AUTHORIZATION, CONTENT_TYPE, ORIGIN, SERVER, VARY, WWW_AUTHENTICATE,
+	},
+	Method, Request, Response, StatusCode,
+};
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use percent_encoding::percent_decode_str;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{time::Instant, try_join};
+use tracing::{debug, info, instrument, warn};
+use utoipa::{
+	openapi::{
+		path::{ParameterBuilder, ParameterIn},
+		security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+		Required,
+	},
+	Modify, OpenApi, PartialSchema, ToSchema,
+};
+
+use crate::{
+	config::{Config, TokenRole},
+	options::{self, LinkOptions},
+	stats::{Statistic, StatisticDescription, StatisticValue},
+	store::Store,
+	util::SERVER_NAME,
+	webhook::{self, LinkChange},
+};
+
+/// The path prefix of all API resources
+const PATH_PREFIX: &str = "/api/v1/";
+
+/// The path of the API's OpenAPI document
+const OPENAPI_PATH: &str = "/api/openapi.json";
+
+/// The maximum size of request bodies (in bytes)
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// The `Content-Type` of all API responses
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// The name of the header used to select a store namespace
+const NAMESPACE_HEADER: &str = "links-namespace";
+
+/// The OpenAPI document describing this API, generated from [`ApiDoc`]
+static OPENAPI: LazyLock<Value> = LazyLock::new(|| {
+	serde_json::to_value(ApiDoc::openapi()).expect("the OpenAPI document is serializable")
+});
+
+/// Cross-origin resource sharing (CORS) settings for the HTTP API, which allow
+/// browser-based clients (e.g. admin dashboards) on other websites to call the
+/// API directly.
+///
+/// CORS is disabled (no `Access-Control-*` headers are sent) if
+/// `allowed_origins` is empty, which is the default.
+///
+/// # Example
+/// ```toml
+/// { allowed_origins = ["https://admin.example.com"], max_age = 600 }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiCors {
+	/// Origins (e.g. `https://example.com`) allowed to call the API, or `*` to
+	/// allow any origin
+	pub allowed_origins: Vec<String>,
+	/// HTTP methods allowed in cross-origin requests, all methods used by the
+	/// API if empty
+	pub allowed_methods: Vec<String>,
+	/// Request headers allowed in cross-origin requests, `Authorization`,
+	/// `Content-Type`, and `Links-Namespace` if empty
+	pub allowed_headers: Vec<String>,
+	/// How long (in seconds) browsers may cache the response to a preflight
+	/// request
+	pub max_age: u64,
+}
+
+impl Default for ApiCors {
+	fn default() -> Self {
+		Self {
+			allowed_origins: Vec::new(),
+			allowed_methods: Vec::new(),
+			allowed_headers: Vec::new(),
+			max_age: 3600,
+		}
+	}
+}
+
+impl ApiCors {
+	/// The request headers allowed if none are explicitly configured
+	const DEFAULT_HEADERS: [&'static str; 3] = ["Authorization", "Content-Type", "Links-Namespace"];
+	/// The methods allowed if none are explicitly configured
+	const DEFAULT_METHODS: [&'static str; 4] = ["GET", "PUT", "POST", "DELETE"];
+
+	/// Check whether CORS is enabled by these settings
+	#[must_use]
+	pub fn is_enabled(&self) -> bool {
+		!self.allowed_origins.is_empty()
+	}
+
+	/// Get the value of the `Access-Control-Allow-Origin` header for a request
+	/// from `origin`, or `None` if that origin is not allowed
+	#[must_use]
+	pub fn allow_origin<'a>(&self, origin: &'a str) -> Option<&'a str> {
+		options::allow_origin(&self.allowed_origins, origin)
+	}
+
+	/// Check whether cross-origin requests with the `method` are allowed
+	#[must_use]
+	pub fn allows_method(&self, method: &str) -> bool {
+		if self.allowed_methods.is_empty() {
+			Self::DEFAULT_METHODS.contains(&method)
+		} else {
+			self.allowed_methods.iter().any(|m| m == "*" || m == method)
+		}
+	}
+
+	/// Get the value of the `Access-Control-Allow-Methods` header
+	#[must_use]
+	pub fn allow_methods(&self) -> String {
+		if self.allowed_methods.is_empty() {
+			Self::DEFAULT_METHODS.join(", ")
+		} else {
+			self.allowed_methods.join(", ")
+		}
+	}
+
+	/// Get the value of the `Access-Control-Allow-Headers` header
+	#[must_use]
+	pub fn allow_headers(&self) -> String {
+		if self.allowed_headers.is_empty() {
+			Self::DEFAULT_HEADERS.join(", ")
+		} else {
+			self.allowed_headers.join(", ")
+		}
+	}
+}
+
+/// An API error, with the HTTP status code and message sent to the client
+#[derive(Debug)]
+struct Error {
+	status: StatusCode,
+	message: String,
+}
+
+impl Error {
+	/// Create a new error with the `status` code and `message`
+	fn new(status: StatusCode, message: impl Into<String>) -> Self {
+		Self {
+			status,
+			message: message.into(),
+		}
+	}
+
+	/// An error for a failed store operation
+	fn store() -> Self {
+		Self::new(StatusCode::INTERNAL_SERVER_ERROR, "store operation failed")
+	}
+
+	/// An error for an unsupported method on an existing resource
+	fn method_not_allowed() -> Self {
+		Self::new(StatusCode::METHOD_NOT_ALLOWED, "method not allowed")
+	}
+}
+
+/// The request and response body of redirect resources
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct RedirectBody {
+	/// The redirect's (new or previous) destination link
+	link: Option<String>,
+}
+
+/// The response body of getting a redirect
+#[derive(Debug, Serialize, ToSchema)]
+struct RedirectInfo {
+	/// The redirect's ID
+	id: String,
+	/// The redirect's destination link
+	link: String,
+	/// All vanity paths of the redirect, sorted
+	vanities: Vec<String>,
+	/// Why the redirect's destination was flagged by a reputation check, if it
+	/// was, in which case the redirect is disabled
+	flagged: Option<String>,
+	/// Whether the redirect is enabled (i.e. not explicitly disabled)
+	enabled: bool,
+}
+
+/// The request and response body of vanity path resources
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct VanityBody {
+	/// The ID the vanity path (now or previously) points to
+	id: Option<String>,
+}
+
+/// The response body of link options resources
+#[derive(Debug, Serialize, ToSchema)]
+struct OptionsBody {
+	/// The link's (current or previous) options
+	options: Option<LinkOptions>,
+}
+
+/// The response body of statistics resources
+#[derive(Debug, Serialize, ToSchema)]
+struct StatisticsBody {
+	/// All matching statistics with their values
+	statistics: Vec<StatisticWithValue>,
+}
+
+/// The response body of enabling or disabling a redirect
+#[derive(Debug, Serialize, ToSchema)]
+struct EnabledBody {
+	/// Whether the redirect was enabled before
+	enabled: bool,
+}
+
+/// The response body of purging soft-deleted redirects
+#[derive(Debug, Serialize, ToSchema)]
+struct PurgedBody {
+	/// How many soft-deleted redirects were purged
+	purged: usize,
+}
+
+/// The response body of all errors
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorBody {
+	/// The error message
+	error: String,
+}
+
+/// A statistic with its value, like `StatisticWithValue` in the gRPC API
+#[derive(Debug, Serialize, ToSchema)]
+struct StatisticWithValue {
+	link: String,
+	r#type: String,
+	data: String,
+	time: String,
+	value: u64,
+}
+
+impl From<(Statistic, StatisticValue)> for StatisticWithValue {
+	fn from((stat, value): (Statistic, StatisticValue)) -> Self {
+		Self {
+			link: stat.link.to_string(),
+			r#type: stat.stat_type.to_string(),
+			data: stat.data.to_string(),
+			time: stat.time.to_string(),
+			value: value.get(),
+		}
+	}
+}
+
+/// Process an HTTP API request `req`, performing the requested operation on
+/// the `store` if the request is authenticated with one of the tokens from the
+/// `config`. See the [module-level documentation][self] for details.
+///
+/// # Errors
+/// This function returns an error if the response could not be built. Errors
+/// caused by the request are returned to the client as error responses
+/// instead.
+#[instrument(level = "info", name = "http_api", skip_all, fields(http.method = %req.method(), http.path = ?req.uri().path(), store = %store.backend_name()))]
+pub async fn http_api<B>(
+	req: Request<B>,
+	store: Store,
+	config: &'static Config,
+) -> Result<Response<String>, anyhow::Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	let time = Instant::now();
+
+	let cors = config.http_api_cors();
+	let origin = req
+		.headers()
+		.get(ORIGIN)
+		.and_then(|origin| origin.to_str().ok())
+		.and_then(|origin| cors.allow_origin(origin))
+		.map(ToString::to_string);
+
+	// Respond to CORS preflight requests directly, without authentication
+	if cors.is_enabled() && req.method() == Method::OPTIONS {
+		if let Some(method) = req.headers().get(ACCESS_CONTROL_REQUEST_METHOD) {
+			let mut builder = Response::builder()
+				.status(StatusCode::NO_CONTENT)
+				.header(VARY, HeaderValue::from_static("Origin"));
+
+			if let Some(ref origin) = origin {
+				builder = builder.header(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+
+				if method.to_str().is_ok_and(|m| cors.allows_method(m)) {
+					builder = builder
+						.header(ACCESS_CONTROL_ALLOW_METHODS, cors.allow_methods())
+						.header(ACCESS_CONTROL_ALLOW_HEADERS, cors.allow_headers())
+						.header(ACCESS_CONTROL_MAX_AGE, cors.max_age);
+				}
+			}
+
+			if config.redirector().send_server {
+				builder = builder.header(SERVER, HeaderValue::from_static(SERVER_NAME));
+			}
+
+			let res = builder.body(String::new())?;
+
+			let time = time.elapsed();
+			info!(
+				time_ns = %time.as_nanos(),
+				status_code = %res.status().as_u16(),
+				"api preflight request processed in {:.6} seconds",
+				time.as_secs_f64()
+			);
+
+			return Ok(res);
+		}
+	}
+
+	let res = match process(req, store, config).await {
+		Ok((status, body)) => (status, body),
+		Err(Error { status, message }) => (status, json!(ErrorBody { error: message })),
+	};
+
+	let mut builder = Response::builder()
+		.status(res.0)
+		.header(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+
+	if res.0 == StatusCode::UNAUTHORIZED {
+		builder = builder.header(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+	}
+
+	if cors.is_enabled() {
+		builder = builder.header(VARY, HeaderValue::from_static("Origin"));
+	}
+
+	if let Some(origin) = origin {
+		builder = builder.header(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+	}
+
+	if config.redirector().send_server {
+		builder = builder.header(SERVER, HeaderValue::from_static(SERVER_NAME));
+	}
+
+	let res = builder.body(res.1.to_string())?;
+
+	let time = time.elapsed();
+	info!(
+		time_ns = %time.as_nanos(),
+		status_code = %res.status().as_u16(),
+		"api request processed in {:.6} seconds",
+		time.as_secs_f64()
+	);
+
+	Ok(res)
+}
+
+/// Authenticate and route the request `req`, returning the response's status
+/// code and body
+async fn process<B>(
+	req: Request<B>,
+	store: Store,
+	config: &Config,
+) -> Result<(StatusCode, Value), Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	if req.uri().path() == OPENAPI_PATH {
+		if req.method() != Method::GET {
+			return Err(Error::method_not_allowed());
+		}
+
+		return Ok((StatusCode::OK, OPENAPI.clone()));
+	}
+
+	let token = req
+		.headers()
+		.get(AUTHORIZATION)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| v.strip_prefix("Bearer "))
+		.ok_or_else(|| Error::new(StatusCode::UNAUTHORIZED, "no auth token provided"))?;
+
+	let Some((token_id, role)) = config.check_token(token.as_bytes()) else {
+		return Err(Error::new(
+			StatusCode::UNAUTHORIZED,
+			"auth token is invalid",
+		));
+	};
+
+	info!(%token_id, %role, "auth token is valid");
+
+	let path = req
+		.uri()
+		.path()
+		.strip_prefix(PATH_PREFIX)
+		.ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "not found"))?
+		.split('/')
+		.map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+		.collect::<Vec<_>>();
+	let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+	let query = req.uri().query().unwrap_or_default().to_string();
+	let method = req.method().clone();
+
+	let required_role = match (&method, path.as_slice()) {
+		(_, ["purge"]) => TokenRole::Admin,
+		(&Method::GET, _) => TokenRole::ReadOnly,
+		_ => TokenRole::Write,
+	};
+
+	if role < required_role {
+		return Err(Error::new(
+			StatusCode::FORBIDDEN,
+			format!("this operation requires a token with the {required_role} role"),
+		));
+	}
+
+	if required_role >= TokenRole::Write {
+		check_quota(&store, &token_id, config).await?;
+	}
+
+	let store = match req.headers().get(NAMESPACE_HEADER) {
+		None => store,
+		Some(namespace) => {
+			let namespace = namespace
+				.to_str()
+				.map_err(|_| Error::new(StatusCode::BAD_REQUEST, "namespace is invalid"))?;
+
+			store
+				.with_namespace(namespace)
+				.await
+				.map_err(|err| Error::new(StatusCode::BAD_REQUEST, err.to_string()))?
+		}
+	};
+
+	match path.as_slice() {
+		["redirects", id] => redirect(&method, parse_id(id)?, req, &store, config).await,
+		["redirects", id, "restore"] if method == Method::POST => {
+			restore(parse_id(id)?, &store, config).await
+		}
+		["redirects", id, action @ ("enable" | "disable")] if method == Method::POST => {
+			let enabled = store
+				.set_enabled(parse_id(id)?, *action == "enable")
+				.await
+				.map_err(|_| Error::store())?;
+			Ok((StatusCode::OK, json!(EnabledBody { enabled })))
+		}
+		["purge"] if method == Method::POST => {
+			let purged = store.purge_redirects().await.map_err(|_| Error::store())?;
+			Ok((StatusCode::OK, json!(PurgedBody { purged })))
+		}
+		["vanities", path] => vanity(&method, Normalized::new(path), req, &store, config).await,
+		["options", id] => options(&method, parse_id(id)?, req, &store).await,
+		["stats"] => statistics(&method, parse_stats_query(&query)?, &store).await,
+		["redirects", _, "restore" | "enable" | "disable"] | ["purge"] => {
+			Err(Error::method_not_allowed())
+		}
+		_ => Err(Error::new(StatusCode::NOT_FOUND, "not found")),
+	}
+}
+
+/// Count a request made using the API token with the identifier `token_id`
+/// against that token's quota in `config`, recording its usage in the `store`
+async fn check_quota(store: &Store, token_id: &str, config: &Config) -> Result<(), Error> {
+	let quota = config.token_quota(token_id);
+	if !quota.is_enabled() {
+		return Ok(());
+	}
+
+	match quota.check(store, token_id, 1).await {
+		Ok(None) => Ok(()),
+		Ok(Some(exceeded)) => {
+			debug!(%token_id, %exceeded, "token quota exceeded");
+			Err(Error::new(
+				StatusCode::TOO_MANY_REQUESTS,
+				exceeded.to_string(),
+			))
+		}
+		Err(err) => {
+			warn!(?err, %token_id, "token quota usage could not be recorded");
+			Err(Error::new(
+				StatusCode::SERVICE_UNAVAILABLE,
+				"quota usage could not be recorded",
+			))
+		}
+	}
+}
+
+/// Get, set, or remove the redirect with the `id`, notifying webhooks in
+/// `config` of changes
+async fn redirect<B>(
+	method: &Method,
+	id: Id,
+	req: Request<B>,
+	store: &Store,
+	config: &Config,
+) -> Result<(StatusCode, Value), Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	let old = match *method {
+		Method::GET => {
+			let (link, vanities, options) = try_join!(
+				store.get_redirect(id),
+				store.get_vanities_of(id),
+				store.get_options(id)
+			)
+			.map_err(|_| Error::store())?;
+			let link =
+				link.ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "redirect not found"))?;
+
+			return Ok((
+				StatusCode::OK,
+				json!(RedirectInfo {
+					id: id.to_string(),
+					link: link.into_string(),
+					vanities: vanities.into_iter().map(Normalized::into_string).collect(),
+					enabled: options
+						.as_ref()
+						.and_then(|options| options.enabled)
+						.unwrap_or(true),
+					flagged: options.and_then(|options| options.flagged),
+				}),
+			));
+		}
+		Method::PUT => {
+			let RedirectBody { link } = read_body(req).await?;
+			let link = link
+				.and_then(|link| Link::new(&link).ok())
+				.ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "link is invalid"))?;
+
+			if config.blocked_domains().is_blocked(&link) {
+				return Err(Error::new(
+					StatusCode::BAD_REQUEST,
+					"link domain is blocked",
+				));
+			}
+
+			if config
+				.loop_detection()
+				.is_loop(store, id, &link)
+				.await
+				.map_err(|_| Error::store())?
+			{
+				return Err(Error::new(
+					StatusCode::BAD_REQUEST,
+					"link would cause a redirect loop",
+				));
+			}
+
+			let old = store
+				.set_redirect(id, link.clone())
+				.await
+				.map_err(|_| Error::store())?;
+
+			webhook::notify(config, store.namespace(), [LinkChange::redirect_set(
+				id,
+				&link,
+				old.clone(),
+			)]);
+
+			old
+		}
+		Method::DELETE => {
+			let old = store
+				.rem_redirect_soft(id)
+				.await
+				.map_err(|_| Error::store())?;
+
+			webhook::notify(
+				config,
+				store.namespace(),
+				LinkChange::redirect_removed(id, old.clone()),
+			);
+
+			old
+		}
+		_ => return Err(Error::method_not_allowed()),
+	};
+
+	Ok((
+		StatusCode::OK,
+		json!(RedirectBody {
+			link: old.map(Link::into_string),
+		}),
+	))
+}
+
+/// Restore the soft-deleted redirect with the `id`, notifying webhooks in
+/// `config`
+async fn restore(id: Id, store: &Store, config: &Config) -> Result<(StatusCode, Value), Error> {
+	if store
+		.get_redirect(id)
+		.await
+		.map_err(|_| Error::store())?
+		.is_some()
+	{
+		return Err(Error::new(
+			StatusCode::CONFLICT,
+			"a redirect with this id already exists",
+		));
+	}
+
+	let link = store
+		.restore_redirect(id)
+		.await
+		.map_err(|_| Error::store())?;
+
+	webhook::notify(
+		config,
+		store.namespace(),
+		link.as_ref()
+			.map(|link| LinkChange::redirect_set(id, link, None)),
+	);
+
+	Ok((
+		StatusCode::OK,
+		json!(RedirectBody {
+			link: link.map(Link::into_string),
+		}),
+	))
+}
+
+/// Get, set, or remove the `vanity` path, notifying webhooks in `config` of
+/// changes
+async fn vanity<B>(
+	method: &Method,
+	vanity: Normalized,
+	req: Request<B>,
+	store: &Store,
+	config: &Config,
+) -> Result<(StatusCode, Value), Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	let id = match *method {
+		Method::GET => Some(
+			store
+				.get_vanity(vanity)
+				.await
+				.map_err(|_| Error::store())?
+				.ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "vanity path not found"))?,
+		),
+		Method::PUT => {
+			let VanityBody { id } = read_body(req).await?;
+			let id = parse_id(&id.unwrap_or_default())?;
+
+			if config.redirector().is_reserved(vanity.as_str()) {
+				return Err(Error::new(
+					StatusCode::BAD_REQUEST,
+					"vanity path is reserved",
+				));
+			}
+
+			let old = store
+				.set_vanity(vanity.clone(), id)
+				.await
+				.map_err(|_| Error::store())?;
+
+			webhook::notify(config, store.namespace(), [LinkChange::vanity_set(
+				&vanity, id, old,
+			)]);
+
+			old
+		}
+		Method::DELETE => {
+			let old = store
+				.rem_vanity(vanity.clone())
+				.await
+				.map_err(|_| Error::store())?;
+
+			webhook::notify(
+				config,
+				store.namespace(),
+				LinkChange::vanity_removed(&vanity, old),
+			);
+
+			old
+		}
+		_ => return Err(Error::method_not_allowed()),
+	};
+
+	Ok((
+		StatusCode::OK,
+		json!(VanityBody {
+			id: id.map(|id| id.to_string()),
+		}),
+	))
+}
+
+/// Get, set, or remove the options of the link with the `id`
+async fn options<B>(
+	method: &Method,
+	id: Id,
+	req: Request<B>,
+	store: &Store,
+) -> Result<(StatusCode, Value), Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	let options = match *method {
+		Method::GET => Some(
+			store
+				.get_options(id)
+				.await
+				.map_err(|_| Error::store())?
+				.ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "options not found"))?,
+		),
+		Method::PUT => {
+			let options: LinkOptions = read_body(req).await?;
+
+			if options.max_clicks.is_some() && !store.supports_usage() {
+				return Err(Error::new(
+					StatusCode::BAD_REQUEST,
+					"max_clicks is not supported by the store backend",
+				));
+			}
+
+			store
+				.set_options(id, options)
+				.await
+				.map_err(|_| Error::store())?
+		}
+		Method::DELETE => store.rem_options(id).await.map_err(|_| Error::store())?,
+		_ => return Err(Error::method_not_allowed()),
+	};
+
+	Ok((StatusCode::OK, json!(OptionsBody { options })))
+}
+
+/// Get or remove the statistics matching the `description`
+async fn statistics(
+	method: &Method,
+	description: StatisticDescription,
+	store: &Store,
+) -> Result<(StatusCode, Value), Error> {
+	let statistics = match *method {
+		Method::GET => store
+			.get_statistics(description)
+			.await
+			.map_err(|_| Error::store())?
+			.map(StatisticWithValue::from)
+			.collect::<Vec<_>>(),
+		Method::DELETE => store
+			.rem_statistics(description)
+			.await
+			.map_err(|_| Error::store())?
+			.map(StatisticWithValue::from)
+			.collect::<Vec<_>>(),
+		_ => return Err(Error::method_not_allowed()),
+	};
+
+	Ok((StatusCode::OK, json!(StatisticsBody { statistics })))
+}
+
+/// Parse an ID from a path segment or request body
+fn parse_id(id: &str) -> Result<Id, Error> {
+	Id::try_from(id).map_err(|_| Error::new(StatusCode::BAD_REQUEST, "id is invalid"))
+}
+
+/// Read the body of `req` as JSON
+async fn read_body<B, T>(req: Request<B>) -> Result<T, Error>
+where
+	B: Body + Send,
+	B::Data: Send,
+	B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+	T: DeserializeOwned,
+{
+	let body = Limited::new(req.into_body(), MAX_BODY_SIZE)
+		.collect()
+		.await
+		.map_err(|_| Error::new(StatusCode::BAD_REQUEST, "request body is too large"))?
+		.to_bytes();
+
+	serde_json::from_slice(&body).map_err(|err| {
+		Error::new(
+			StatusCode::BAD_REQUEST,
+			format!("request body is invalid: {err}"),
+		)
+	})
+}
+
+/// Parse the statistics filter from the `query` string
+fn parse_stats_query(query: &str) -> Result<StatisticDescription, Error> {
+	let invalid = || {
+		Error::new(
+			StatusCode::BAD_REQUEST,
+			"one of the supplied arguments is invalid",
+		)
+	};
+	let mut description = StatisticDescription::default();
+
+	for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+		match &*key {
+			"link" => description.link = Some(value.into_owned().into()),
+			"type" => {
+				description.stat_type = Some(value.as_ref().try_into().map_err(|_| invalid())?);
+			}
+			"data" => description.data = Some(value.into_owned().into()),
+			"time" => description.time = Some(value.as_ref().try_into().map_err(|_| invalid())?),
+			_ => return Err(invalid()),
+		}
+	}
+
+	Ok(description)
+}
+
+/// The OpenAPI document of this API, served at `/api/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+	paths(
+		operations::get_redirect,
+		operations::set_redirect,
+		operations::rem_redirect,
+		operations::restore_redirect,
+		operations::enable_redirect,
+		operations::disable_redirect,
+		operations::purge_redirects,
+		operations::get_vanity,
+		operations::set_vanity,
+		operations::rem_vanity,
+		operations::get_options,
+		operations::set_options,
+		operations::rem_options,
+		operations::get_statistics,
+		operations::rem_statistics,
+	),
+	modifiers(&Authentication),
+	security(("token" = [])),
+	tags(
+		(name = "redirects", description = "Redirects from IDs to links"),
+		(name = "vanities", description = "Vanity paths pointing to IDs"),
+		(name = "options", description = "Per-link options"),
+		(name = "statistics", description = "Collected statistics"),
+	)
+)]
+struct ApiDoc;
+
+/// Adds the token authentication scheme and the namespace header to the
+/// OpenAPI document
+struct Authentication;
+
+impl Modify for Authentication {
+	fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+		if let Some(components) = openapi.components.as_mut() {
+			components.add_security_scheme(
+				"token",
+				SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+			);
+		}
+
+		let namespace = ParameterBuilder::new()
+			.name(NAMESPACE_HEADER)
+			.parameter_in(ParameterIn::Header)
+			.required(Required::False)
+			.description(Some("The store namespace to use"))
+			.schema(Some(String::schema()))
+			.build();
+
+		for item in openapi.paths.paths.values_mut() {
+			for operation in [
+				&mut item.get,
+				&mut item.put,
+				&mut item.post,
+				&mut item.delete,
+			]
+			.into_iter()
+			.flatten()
+			{
+				operation
+					.parameters
+					.get_or_insert_with(Vec::new)
+					.push(namespace.clone());
+			}
+		}
+	}
+}
+
+/// Descriptions of all API operations, which only exist to generate the
+/// OpenAPI document (the actual operations are performed by [`process`])
+#[expect(
+	dead_code,
+	reason = "these functions are only used for their `utoipa::path` attributes"
+)]
+mod operations {
+	use super::{
+		EnabledBody, ErrorBody, OptionsBody, PurgedBody, RedirectBody, RedirectInfo,
+		StatisticsBody, VanityBody,
+	};
+	use crate::options::LinkOptions;
+
+	/// Get a redirect, along with its vanity paths
+	#[utoipa::path(
+		get, path = "/api/v1/redirects/{id}", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		responses(
+			(status = 200, description = "The redirect", body = RedirectInfo),
+			(status = "4XX", description = "The request is invalid, or the redirect doesn't exist", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn get_redirect() {}
+
+	/// Set a redirect, returning its previous link
+	#[utoipa::path(
+		put, path = "/api/v1/redirects/{id}", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		request_body = RedirectBody,
+		responses(
+			(status = 200, description = "The redirect's previous link", body = RedirectBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn set_redirect() {}
+
+	/// Remove a redirect, returning its previous link
+	#[utoipa::path(
+		delete, path = "/api/v1/redirects/{id}", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		responses(
+			(status = 200, description = "The redirect's previous link", body = RedirectBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn rem_redirect() {}
+
+	/// Restore a soft-deleted redirect, returning its restored link
+	#[utoipa::path(
+		post, path = "/api/v1/redirects/{id}/restore", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		responses(
+			(status = 200, description = "The restored link", body = RedirectBody),
+			(status = "4XX", description = "The request is invalid, or the redirect exists", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn restore_redirect() {}
+
+	/// Enable a redirect, returning whether it was enabled before
+	#[utoipa::path(
+		post, path = "/api/v1/redirects/{id}/enable", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		responses(
+			(status = 200, description = "Whether the redirect was enabled before", body = EnabledBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn enable_redirect() {}
+
+	/// Disable a redirect without removing it, returning whether it was
+	/// enabled before
+	#[utoipa::path(
+		post, path = "/api/v1/redirects/{id}/disable", tag = "redirects",
+		params(("id" = String, Path, description = "The redirect's ID")),
+		responses(
+			(status = 200, description = "Whether the redirect was enabled before", body = EnabledBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn disable_redirect() {}
+
+	/// Permanently remove all soft-deleted redirects
+	#[utoipa::path(
+		post, path = "/api/v1/purge", tag = "redirects",
+		responses(
+			(status = 200, description = "The number of purged redirects", body = PurgedBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn purge_redirects() {}
+
+	/// Get the ID of a vanity path
+	#[utoipa::path(
+		get, path = "/api/v1/vanities/{vanity}", tag = "vanities",
+		params(("vanity" = String, Path, description = "The vanity path")),
+		responses(
+			(status = 200, description = "The vanity path's ID", body = VanityBody),
+			(status = "4XX", description = "The request is invalid, or the vanity path doesn't exist", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn get_vanity() {}
+
+	/// Set a vanity path, returning its previous ID
+	#[utoipa::path(
+		put, path = "/api/v1/vanities/{vanity}", tag = "vanities",
+		params(("vanity" = String, Path, description = "The vanity path")),
+		request_body = VanityBody,
+		responses(
+			(status = 200, description = "The vanity path's previous ID", body = VanityBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn set_vanity() {}
+
+	/// Remove a vanity path, returning its previous ID
+	#[utoipa::path(
+		delete, path = "/api/v1/vanities/{vanity}", tag = "vanities",
+		params(("vanity" = String, Path, description = "The vanity path")),
+		responses(
+			(status = 200, description = "The vanity path's previous ID", body = VanityBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn rem_vanity() {}
+
+	/// Get a link's options
+	#[utoipa::path(
+		get, path = "/api/v1/options/{id}", tag = "options",
+		params(("id" = String, Path, description = "The link's ID")),
+		responses(
+			(status = 200, description = "The link's options", body = OptionsBody),
+			(status = "4XX", description = "The request is invalid, or the link has no options", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn get_options() {}
+
+	/// Set a link's options, returning its previous options
+	#[utoipa::path(
+		put, path = "/api/v1/options/{id}", tag = "options",
+		params(("id" = String, Path, description = "The link's ID")),
+		request_body = LinkOptions,
+		responses(
+			(status = 200, description = "The link's previous options", body = OptionsBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn set_options() {}
+
+	/// Remove a link's options, returning its previous options
+	#[utoipa::path(
+		delete, path = "/api/v1/options/{id}", tag = "options",
+		params(("id" = String, Path, description = "The link's ID")),
+		responses(
+			(status = 200, description = "The link's previous options", body = OptionsBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn rem_options() {}
+
+	/// Get statistics matching the filter
+	#[utoipa::path(
+		get, path = "/api/v1/stats", tag = "statistics",
+		params(
+			("link" = Option<String>, Query, description = "The link (ID or vanity path) of the statistics"),
+			("type" = Option<String>, Query, description = "The type of the statistics"),
+			("data" = Option<String>, Query, description = "The data of the statistics"),
+			("time" = Option<String>, Query, description = "The time of the statistics"),
+		),
+		responses(
+			(status = 200, description = "The matching statistics", body = StatisticsBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn get_statistics() {}
+
+	/// Remove statistics matching the filter, returning their previous values
+	#[utoipa::path(
+		delete, path = "/api/v1/stats", tag = "statistics",
+		params(
+			("link" = Option<String>, Query, description = "The link (ID or vanity path) of the statistics"),
+			("type" = Option<String>, Query, description = "The type of the statistics"),
+			("data" = Option<String>, Query, description = "The data of the statistics"),
+			("time" = Option<String>, Query, description = "The time of the statistics"),
+		),
+		responses(
+			(status = 200, description = "The removed statistics", body = StatisticsBody),
+			(status = "4XX", description = "The request is invalid", body = ErrorBody),
+			(status = "5XX", description = "The store operation failed", body = ErrorBody),
+		)
+	)]
+	const fn rem_statistics() {}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use http_body_util::Full;
+	use hyper::body::Bytes;
+
+	use super::*;
+
+	async fn call(
+		config: &'static Config,
+		store: &Store,
+		method: Method,
+		path: &str,
+		body: &str,
+	) -> (StatusCode, Value) {
+		let req = Request::builder()
+			.method(method)
+			.uri(path)
+			.header(
+				AUTHORIZATION,
+				format!("Bearer {}", config.tokens()[0].token),
+			)
+			.body(Full::new(Bytes::from(body.to_string())))
+			.unwrap();
+
+		let res = http_api(req, store.clone(), config).await.unwrap();
+		let status = res.status();
+
+		(status, serde_json::from_str(res.body()).unwrap())
+	}
+
+	#[tokio::test]
+	async fn redirects() {
+		let config = Box::leak(Box::new(Config::new(None)));
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let (status, _) = call(
+			config,
+			&store,
+			Method::GET,
+			"/api/v1/redirects/0fDbKpJP",
+			"",
+		)
+		.await;
+		assert_eq!(status, StatusCode::NOT_FOUND);
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::PUT,
+			"/api/v1/redirects/0fDbKpJP",
+			r#"{"link": "https://example.com/api"}"#,
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "link": null }));
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::PUT,
+			"/api/v1/vanities/api%20test",
+			r#"{"id": "0fDbKpJP"}"#,
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "id": null }));
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::GET,
+			"/api/v1/redirects/0fDbKpJP",
+			"",
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(
+			body,
+			json!({
+				"id": "0fDbKpJP",
+				"link": "https://example.com/api",
+				"vanities": ["apitest"],
+			})
+		);
+
+		let (status, body) =
+			call(config, &store, Method::GET, "/api/v1/vanities/apitest", "").await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "id": "0fDbKpJP" }));
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::DELETE,
+			"/api/v1/redirects/0fDbKpJP",
+			"",
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "link": "https://example.com/api" }));
+	}
+
+	#[tokio::test]
+	async fn errors() {
+		let config = Box::leak(Box::new(Config::new(None)));
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let req = Request::builder()
+			.uri("/api/v1/redirects/0fDbKpJP")
+			.header(AUTHORIZATION, "Bearer wrong")
+			.body(Full::new(Bytes::new()))
+			.unwrap();
+		let res = http_api(req, store.clone(), config).await.unwrap();
+		assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+		assert!(res.headers().contains_key(WWW_AUTHENTICATE));
+
+		let cases = [
+			(
+				Method::GET,
+				"/api/v1/redirects/invalid",
+				"",
+				StatusCode::BAD_REQUEST,
+			),
+			(
+				Method::PUT,
+				"/api/v1/redirects/0fDbKpJP",
+				"{}",
+				StatusCode::BAD_REQUEST,
+			),
+			(
+				Method::PUT,
+				"/api/v1/redirects/0fDbKpJP",
+				"nope",
+				StatusCode::BAD_REQUEST,
+			),
+			(
+				Method::POST,
+				"/api/v1/redirects/0fDbKpJP",
+				"",
+				StatusCode::METHOD_NOT_ALLOWED,
+			),
+			(
+				Method::GET,
+				"/api/v1/purge",
+				"",
+				StatusCode::METHOD_NOT_ALLOWED,
+			),
+			(
+				Method::GET,
+				"/api/v1/stats?type=nope",
+				"",
+				StatusCode::BAD_REQUEST,
+			),
+			(Method::GET, "/api/v1/unknown", "", StatusCode::NOT_FOUND),
+			(Method::GET, "/unknown", "", StatusCode::NOT_FOUND),
+		];
+
+		for (method, path, body, expected) in cases {
+			let (status, body) = call(config, &store, method, path, body).await;
+			assert_eq!(status, expected, "{path}");
+			assert!(body["error"].is_string());
+		}
+	}
+
+	#[tokio::test]
+	async fn options_and_stats() {
+		let config = Box::leak(Box::new(Config::new(None)));
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::PUT,
+			"/api/v1/options/0fDbKpJP",
+			r#"{"cors": {"allowed_origins": ["*"]}}"#,
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "options": null }));
+
+		let (status, body) =
+			call(config, &store, Method::GET, "/api/v1/options/0fDbKpJP", "").await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["options"]["cors"]["allowed_origins"], json!(["*"]));
+
+		let (status, body) = call(
+			config,
+			&store,
+			Method::GET,
+			"/api/v1/stats?link=0fDbKpJP",
+			"",
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "statistics": [] }));
+
+		let (status, body) = call(config, &store, Method::POST, "/api/v1/purge", "").await;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body, json!({ "purged": 0 }));
+	}
+
+	#[tokio::test]
+	async fn openapi() {
+		let config = Box::leak(Box::new(Config::new(None)));
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let req = Request::builder()
+			.method(Method::GET)
+			.uri(OPENAPI_PATH)
+			.body(Full::new(Bytes::new()))
+			.unwrap();
+		let res = http_api(req, store.clone(), config).await.unwrap();
+		assert_eq!(res.status(), StatusCode::OK);
+
+		let body: Value = serde_json::from_str(res.body()).unwrap();
+		assert!(body["openapi"].as_str().unwrap().starts_with("3."));
+		assert!(body["paths"]["/api/v1/redirects/{id}"]["put"].is_object());
+		assert!(body["paths"]["/api/v1/stats"]["delete"].is_object());
+		assert!(body["components"]["schemas"]["LinkOptions"].is_object());
+		assert_eq!(
+			body["components"]["securitySchemes"]["token"]["scheme"],
+			"bearer"
+		);
+
+		let (status, _) = call(config, &store, Method::POST, OPENAPI_PATH, "").await;
+		assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+	}
+}
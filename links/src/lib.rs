@@ -19,11 +19,23 @@
 	reason = "false-positives in `#[derive(Serialize)]`-generated code"
 )]
 
+pub mod access_log;
 pub mod api;
+pub mod blocklist;
 pub mod certs;
 pub mod config;
+pub mod geo;
+pub mod http_api;
+pub mod loops;
+pub mod metrics;
+pub mod options;
+pub mod proxy;
+pub mod ratelimit;
 pub mod redirector;
+pub mod reputation;
 pub mod server;
 pub mod stats;
 pub mod store;
+pub mod trace;
 pub mod util;
+pub mod webhook;
@@ -0,0 +1,220 @@
+//! Redirect loop detection
+//!
+//! Links can point at other links on the same server (e.g. an alias of a
+//! vanity path), which can accidentally create chains of redirects, or even
+//! infinite loops (e.g. a link pointing at itself). If the `loop_detection`
+//! configuration option lists the `domains` this server is reachable on,
+//! destinations on those domains are recognized as links on this server.
+//!
+//! When a redirect is set via the APIs, the links it points at are followed
+//! through the store (as long as they are on this server), and the redirect is
+//! rejected if this leads back to a link already in the chain, or if more than
+//! `max_chain` links in a row would point at this server. By default,
+//! `max_chain` is `0`, so links can't point at this server at all. If
+//! `recheck` is enabled, the same check is done when redirecting, and links
+//! which start a loop or a too long chain (e.g. because a vanity path was
+//! changed afterwards) are treated as if they didn't exist.
+//!
+//! Entries of `domains` are either exact domains (e.g. `go.example.com`), or
+//! wildcards (e.g. `*.example.com`), which match one level of subdomains, just
+//! like in TLS certificates. Destinations are looked up by their whole path,
+//! as an ID or vanity path, in the same store namespace as the link itself.
+
+use std::{
+	borrow::Cow,
+	fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+use anyhow::Result;
+use hyper::Uri;
+use links_domainmap::Domain;
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+use crate::store::Store;
+
+/// Settings for redirect loop detection, see the [module-level
+/// documentation][self] for details.
+///
+/// Loop detection is disabled if `domains` is empty (the default).
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoopDetection {
+	/// The domains this server is reachable on, including wildcards
+	pub domains: Vec<Domain>,
+	/// The maximum number of links in a row which may point at this server
+	pub max_chain: usize,
+	/// Also check links for loops when redirecting
+	pub recheck: bool,
+}
+
+impl LoopDetection {
+	/// Whether loop detection is enabled, i.e. any domains are configured
+	#[must_use]
+	pub fn is_enabled(&self) -> bool {
+		!self.domains.is_empty()
+	}
+
+	/// Check whether the redirect from `id` to `link` starts a redirect loop
+	/// or a too long chain of links on this server, following the chain
+	/// through the `store`. This is never the case if loop detection is
+	/// disabled.
+	///
+	/// # Errors
+	/// Returns an error if a store operation fails.
+	pub async fn is_loop(&self, store: &Store, id: Id, link: &Link) -> Result<bool> {
+		let mut seen = vec![id];
+		let mut next = Cow::Borrowed(link);
+
+		while let Some(path) = self.own_path(&next) {
+			if seen.len() > self.max_chain {
+				trace!(chain = seen.len(), "redirect chain is too long");
+				return Ok(true);
+			}
+
+			let target = match Id::from_ascii(path.as_bytes()) {
+				Some(id) => Some(id),
+				None => store.get_vanity(Normalized::new(&path)).await?,
+			};
+
+			let Some(target) = target else {
+				return Ok(false);
+			};
+
+			if seen.contains(&target) {
+				trace!(%target, "redirect chain leads back to an earlier link");
+				return Ok(true);
+			}
+
+			seen.push(target);
+
+			match store.get_redirect(target).await? {
+				Some(link) => next = Cow::Owned(link),
+				None => return Ok(false),
+			}
+		}
+
+		Ok(false)
+	}
+
+	/// Get the path (without the leading slash) of the `link` if it points at
+	/// one of this server's domains, or `None` if it doesn't
+	fn own_path(&self, link: &Link) -> Option<String> {
+		if !self.is_enabled() {
+			return None;
+		}
+
+		let uri = link.as_str().parse::<Uri>().ok()?;
+		let host = uri.host()?;
+		let host = Domain::reference(host.strip_suffix('.').unwrap_or(host)).ok()?;
+
+		self.domains
+			.iter()
+			.any(|domain| host.matches(domain) == Some(true))
+			.then(|| uri.path().trim_start_matches('/').to_string())
+	}
+}
+
+impl Debug for LoopDetection {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("LoopDetection")
+			.field(
+				"domains",
+				&self
+					.domains
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>(),
+			)
+			.field("max_chain", &self.max_chain)
+			.field("recheck", &self.recheck)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+
+	fn loop_detection(max_chain: usize) -> LoopDetection {
+		LoopDetection {
+			domains: vec![
+				Domain::presented("go.example.com").unwrap(),
+				Domain::presented("*.example.net").unwrap(),
+			],
+			max_chain,
+			recheck: false,
+		}
+	}
+
+	#[test]
+	fn own_paths() {
+		let detection = loop_detection(0);
+		let path = |link: &str| detection.own_path(&Link::new(link).unwrap());
+
+		assert_eq!(
+			path("https://go.example.com/example"),
+			Some("example".to_string())
+		);
+		assert_eq!(
+			path("http://GO.example.com./a/b?query"),
+			Some("a/b".to_string())
+		);
+		assert_eq!(path("https://go.example.net/"), Some(String::new()));
+		assert_eq!(path("https://example.com/example"), None);
+		assert_eq!(path("https://example.net/example"), None);
+		assert_eq!(path("https://a.b.example.net/example"), None);
+		assert_eq!(path("https://[::1]/example"), None);
+
+		assert_eq!(
+			LoopDetection::default().own_path(&Link::new("https://go.example.com/").unwrap()),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn loops() {
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let a = Id::from([0x11, 0x21, 0x31, 0x41, 0x51]);
+		let b = Id::from([0x12, 0x22, 0x32, 0x42, 0x52]);
+		let c = Id::from([0x13, 0x23, 0x33, 0x43, 0x53]);
+
+		let external = Link::new("https://example.com/").unwrap();
+		let to_a = Link::new(&format!("https://go.example.com/{a}")).unwrap();
+		let to_b = Link::new("https://go.example.com/b").unwrap();
+		let to_none = Link::new("https://go.example.com/none").unwrap();
+
+		store.set_redirect(b, external.clone()).await.unwrap();
+		store.set_vanity("b".into(), b).await.unwrap();
+
+		let detection = loop_detection(0);
+		assert!(!detection.is_loop(&store, a, &external).await.unwrap());
+		assert!(detection.is_loop(&store, a, &to_b).await.unwrap());
+		assert!(!LoopDetection::default()
+			.is_loop(&store, a, &to_a)
+			.await
+			.unwrap());
+
+		let detection = loop_detection(1);
+		assert!(!detection.is_loop(&store, a, &to_b).await.unwrap());
+		assert!(detection.is_loop(&store, a, &to_a).await.unwrap());
+		assert!(!detection.is_loop(&store, a, &to_none).await.unwrap());
+
+		store.set_redirect(b, to_a).await.unwrap();
+		store.set_redirect(c, to_b.clone()).await.unwrap();
+
+		let detection = loop_detection(usize::MAX);
+		assert!(detection.is_loop(&store, a, &to_b).await.unwrap());
+		assert!(!detection.is_loop(&store, c, &to_b).await.unwrap());
+
+		let detection = loop_detection(1);
+		assert!(detection.is_loop(&store, c, &to_b).await.unwrap());
+	}
+}
@@ -0,0 +1,271 @@
+//! Prometheus metrics of the links redirector server.
+//!
+//! Metrics are collected in process-wide counters, and can be scraped in the
+//! [Prometheus text format] from the reserved `/metrics` path of redirector
+//! (HTTP and HTTPS) listeners, if the `metrics` configuration option is
+//! enabled. The following metrics are collected:
+//! - `links_http_requests_total` - Counter of HTTP requests processed by
+//!   redirector listeners, by response `status` code
+//! - `links_http_request_duration_seconds` - Histogram of the time taken to
+//!   process HTTP requests
+//! - `links_store_operations_total` - Counter of store operations, by `kind`
+//!   (`read` or `write`) and `result` (`ok` or `error`)
+//! - `links_store_operation_duration_seconds` - Histogram of the time taken by
+//!   store operations (including retries), by `kind`
+//! - `links_tls_handshakes_total` - Counter of TLS handshakes on HTTPS
+//!   listeners, by `result` (`success` or `failure`)
+//! - `links_tls_handshake_duration_seconds` - Histogram of the time taken by
+//!   TLS handshakes
+//!
+//! [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+
+use std::{
+	fmt::Write,
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+use hyper::StatusCode;
+
+/// The `Content-Type` of the Prometheus text format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The upper bounds (in seconds) of the buckets of all histograms
+const BUCKETS: [f64; 12] = [
+	0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+];
+
+/// The lowest HTTP status code counted separately
+const MIN_STATUS: u16 = 100;
+
+/// HTTP requests by status code, starting at [`MIN_STATUS`]
+static HTTP_REQUESTS: [AtomicU64; 500] = [const { AtomicU64::new(0) }; 500];
+
+/// HTTP request processing time
+static HTTP_REQUEST_DURATION: Histogram = Histogram::new();
+
+/// Store operations by kind and result
+static STORE_OPERATIONS: [[AtomicU64; 2]; 2] = [const { [const { AtomicU64::new(0) }; 2] }; 2];
+
+/// Store operation time by kind
+static STORE_OPERATION_DURATION: [Histogram; 2] = [const { Histogram::new() }; 2];
+
+/// TLS handshakes by result
+static TLS_HANDSHAKES: [AtomicU64; 2] = [const { AtomicU64::new(0) }; 2];
+
+/// TLS handshake time
+static TLS_HANDSHAKE_DURATION: Histogram = Histogram::new();
+
+/// The kind of a store operation, see [`record_store_operation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOperation {
+	/// An operation only reading from the store (possibly from a replica)
+	Read,
+	/// An operation modifying the store
+	Write,
+}
+
+impl StoreOperation {
+	/// All kinds of store operations, in the order of their indices
+	const ALL: [Self; 2] = [Self::Read, Self::Write];
+
+	/// The value of the `kind` label of this kind of operation
+	const fn label(self) -> &'static str {
+		match self {
+			Self::Read => "read",
+			Self::Write => "write",
+		}
+	}
+}
+
+/// A histogram of durations with the bucket bounds in [`BUCKETS`]
+struct Histogram {
+	/// The number of observations in each bucket (not cumulative)
+	buckets: [AtomicU64; BUCKETS.len()],
+	/// The total number of observations
+	count: AtomicU64,
+	/// The sum of all observations in nanoseconds
+	sum_ns: AtomicU64,
+}
+
+impl Histogram {
+	/// Create a new empty histogram
+	const fn new() -> Self {
+		Self {
+			buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+			count: AtomicU64::new(0),
+			sum_ns: AtomicU64::new(0),
+		}
+	}
+
+	/// Record an observation of the `duration`
+	fn observe(&self, duration: Duration) {
+		let secs = duration.as_secs_f64();
+
+		if let Some(bucket) = BUCKETS.iter().position(|&bound| secs <= bound) {
+			self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+		}
+
+		self.count.fetch_add(1, Ordering::Relaxed);
+		self.sum_ns.fetch_add(
+			u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX),
+			Ordering::Relaxed,
+		);
+	}
+
+	/// Write the samples of this histogram named `name` with the `labels`
+	/// (formatted like `a="b",c="d"`, possibly empty) to `out`
+	fn render(&self, out: &mut String, name: &str, labels: &str) {
+		let (bucket_labels, labels) = if labels.is_empty() {
+			(String::new(), String::new())
+		} else {
+			(format!("{labels},"), format!("{{{labels}}}"))
+		};
+
+		let mut cumulative = 0;
+		for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+			cumulative += bucket.load(Ordering::Relaxed);
+			let _ = writeln!(
+				out,
+				"{name}_bucket{{{bucket_labels}le=\"{bound}\"}} {cumulative}"
+			);
+		}
+
+		let count = self.count.load(Ordering::Relaxed);
+		let _ = writeln!(out, "{name}_bucket{{{bucket_labels}le=\"+Inf\"}} {count}");
+
+		let sum = Duration::from_nanos(self.sum_ns.load(Ordering::Relaxed)).as_secs_f64();
+		let _ = writeln!(out, "{name}_sum{labels} {sum}");
+		let _ = writeln!(out, "{name}_count{labels} {count}");
+	}
+}
+
+/// Record an HTTP request processed by a redirector listener, which got a
+/// response with the `status` code after the `duration`
+pub fn record_http_request(status: StatusCode, duration: Duration) {
+	if let Some(counter) = HTTP_REQUESTS.get(usize::from(status.as_u16() - MIN_STATUS)) {
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+
+	HTTP_REQUEST_DURATION.observe(duration);
+}
+
+/// Record a store operation of the `kind`, which took the `duration` and
+/// either succeeded or failed (according to `ok`)
+pub fn record_store_operation(kind: StoreOperation, ok: bool, duration: Duration) {
+	STORE_OPERATIONS[kind as usize][usize::from(!ok)].fetch_add(1, Ordering::Relaxed);
+	STORE_OPERATION_DURATION[kind as usize].observe(duration);
+}
+
+/// Record a TLS handshake, which took the `duration` and either succeeded or
+/// failed (according to `ok`)
+pub fn record_tls_handshake(ok: bool, duration: Duration) {
+	TLS_HANDSHAKES[usize::from(!ok)].fetch_add(1, Ordering::Relaxed);
+	TLS_HANDSHAKE_DURATION.observe(duration);
+}
+
+/// Render all metrics in the Prometheus text format
+#[must_use]
+pub fn render() -> String {
+	let mut out = String::new();
+
+	out.push_str(
+		"# HELP links_http_requests_total HTTP requests processed by redirector listeners.\n",
+	);
+	out.push_str("# TYPE links_http_requests_total counter\n");
+	for (status, counter) in (MIN_STATUS..).zip(&HTTP_REQUESTS) {
+		let count = counter.load(Ordering::Relaxed);
+		if count > 0 {
+			let _ = writeln!(
+				out,
+				"links_http_requests_total{{status=\"{status}\"}} {count}"
+			);
+		}
+	}
+
+	out.push_str(
+		"# HELP links_http_request_duration_seconds Time taken to process HTTP requests.\n",
+	);
+	out.push_str("# TYPE links_http_request_duration_seconds histogram\n");
+	HTTP_REQUEST_DURATION.render(&mut out, "links_http_request_duration_seconds", "");
+
+	out.push_str("# HELP links_store_operations_total Store operations.\n");
+	out.push_str("# TYPE links_store_operations_total counter\n");
+	for kind in StoreOperation::ALL {
+		for (result, counter) in ["ok", "error"].iter().zip(&STORE_OPERATIONS[kind as usize]) {
+			let _ = writeln!(
+				out,
+				"links_store_operations_total{{kind=\"{}\",result=\"{result}\"}} {}",
+				kind.label(),
+				counter.load(Ordering::Relaxed)
+			);
+		}
+	}
+
+	out.push_str("# HELP links_store_operation_duration_seconds Time taken by store operations.\n");
+	out.push_str("# TYPE links_store_operation_duration_seconds histogram\n");
+	for kind in StoreOperation::ALL {
+		STORE_OPERATION_DURATION[kind as usize].render(
+			&mut out,
+			"links_store_operation_duration_seconds",
+			&format!("kind=\"{}\"", kind.label()),
+		);
+	}
+
+	out.push_str("# HELP links_tls_handshakes_total TLS handshakes on HTTPS listeners.\n");
+	out.push_str("# TYPE links_tls_handshakes_total counter\n");
+	for (result, counter) in ["success", "failure"].iter().zip(&TLS_HANDSHAKES) {
+		let _ = writeln!(
+			out,
+			"links_tls_handshakes_total{{result=\"{result}\"}} {}",
+			counter.load(Ordering::Relaxed)
+		);
+	}
+
+	out.push_str("# HELP links_tls_handshake_duration_seconds Time taken by TLS handshakes.\n");
+	out.push_str("# TYPE links_tls_handshake_duration_seconds histogram\n");
+	TLS_HANDSHAKE_DURATION.render(&mut out, "links_tls_handshake_duration_seconds", "");
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn histogram() {
+		let histogram = Histogram::new();
+		histogram.observe(Duration::from_micros(100));
+		histogram.observe(Duration::from_millis(3));
+		histogram.observe(Duration::from_secs(10));
+
+		let mut out = String::new();
+		histogram.render(&mut out, "test", "kind=\"read\"");
+
+		assert!(out.contains("test_bucket{kind=\"read\",le=\"0.0005\"} 1\n"));
+		assert!(out.contains("test_bucket{kind=\"read\",le=\"0.005\"} 2\n"));
+		assert!(out.contains("test_bucket{kind=\"read\",le=\"5\"} 2\n"));
+		assert!(out.contains("test_bucket{kind=\"read\",le=\"+Inf\"} 3\n"));
+		assert!(out.contains("test_sum{kind=\"read\"} 10.003"));
+		assert!(out.contains("test_count{kind=\"read\"} 3\n"));
+
+		let mut out = String::new();
+		Histogram::new().render(&mut out, "empty", "");
+		assert!(out.contains("empty_bucket{le=\"+Inf\"} 0\n"));
+		assert!(out.contains("empty_count 0\n"));
+	}
+
+	#[test]
+	fn rendering() {
+		record_http_request(StatusCode::IM_A_TEAPOT, Duration::from_millis(1));
+		record_store_operation(StoreOperation::Write, false, Duration::from_millis(1));
+		record_tls_handshake(true, Duration::from_millis(1));
+
+		let out = render();
+		assert!(out.contains("links_http_requests_total{status=\"418\"} "));
+		assert!(out.contains("links_store_operations_total{kind=\"write\",result=\"error\"} "));
+		assert!(out.contains("links_tls_handshakes_total{result=\"success\"} "));
+		assert!(out.contains("# TYPE links_http_request_duration_seconds histogram\n"));
+	}
+}
@@ -0,0 +1,596 @@
+//! Per-link options, which change the way that the redirector handles requests
+//! for a specific link.
+//!
+//! Options are stored per-[ID][links_id::Id] in the store (see
+//! [`Store::get_options`][crate::store::Store::get_options]), and are
+//! represented as a JSON object when stored or sent via the RPC API. All
+//! options are optional, and missing options fall back to the global server
+//! configuration, so e.g. `{}` is a valid (if useless) set of link options.
+//! Options are independent of the redirect itself, so removing a redirect
+//! doesn't remove its options (just like it doesn't remove its vanity paths).
+//!
+//! A link's `expires_at` option (a unix timestamp in seconds) makes the
+//! redirector respond with the configured `expired_status` instead of
+//! redirecting once that time has passed, and the expired redirect is then
+//! lazily removed from the store (keeping a tombstone if soft deletion is
+//! enabled). Because the options are kept, the link keeps responding as
+//! expired (not as missing) afterwards, until its options are changed.
+//!
+//! Similarly, a link's `max_clicks` option limits how many times it is
+//! redirected (e.g. for one-time invitation links), after which it is
+//! treated just like an expired link. Redirects are counted atomically in the
+//! store, so this requires a store backend supporting usage counters. Setting
+//! it is rejected by the APIs otherwise, and links whose clicks can't be
+//! counted are redirected without a limit.
+//!
+//! A link's `geo_destinations` option maps country or continent codes to
+//! alternative destinations for visitors from there, see [`crate::geo`] for
+//! details. Visitors from elsewhere are redirected to the link's default
+//! destination.
+//!
+//! Likewise, a link's `device_destinations` option maps the platforms
+//! `android`, `ios`, `windows`, `macos`, `linux`, and `chromeos`, as well as
+//! `mobile` and `desktop` devices in general, to alternative destinations
+//! (e.g. app store pages). Platform-specific destinations take precedence over
+//! `mobile` and `desktop` ones, and device-targeted destinations take
+//! precedence over geo-targeted ones. Devices are recognized using the
+//! `Sec-CH-UA-Platform` and `Sec-CH-UA-Mobile` client hints, falling back to
+//! the `User-Agent` header if those aren't available.
+//!
+//! A link's `split` option specifies weighted destinations (variants) for
+//! A/B testing, one of which is chosen for each redirect instead of the link's
+//! default destination (but not over any device- or geo-targeted one). The
+//! chance of a variant being chosen is proportional to its `weight`. If
+//! `sticky` is enabled, the variant is chosen based on a hash of the client's
+//! IP address and user agent instead of randomly, so that repeated requests
+//! from the same client are usually redirected to the same variant. The
+//! variant's `name` (or its index in the list if it doesn't have one) is
+//! recorded in the [`Variant`][crate::stats::StatisticType::Variant]
+//! statistic.
+//!
+//! A link's `query_params` option adds fixed query parameters (e.g. UTM
+//! campaign parameters like `utm_source=links`) to its destination when
+//! redirecting, after the destination has been chosen and any path has been
+//! passed through. Parameters which are already in the destination's query are
+//! not added again, so the destination's own values take precedence.
+//!
+//! A link's `flagged` option is set by [reputation checks][crate::reputation]
+//! when its destination is found to be unsafe (e.g. malware or phishing), and
+//! the redirector treats flagged links as if they didn't exist. Flagged links
+//! are re-enabled by setting their options without `flagged`, e.g. after the
+//! destination has been cleaned up (if it is still unsafe, it will be flagged
+//! again by the next check).
+//!
+//! A link's `enabled` option can be set to `false` to pause it, which makes
+//! the redirector treat it as if it didn't exist, while keeping its redirect,
+//! vanity paths, other options, and statistics, so that it can be re-enabled
+//! later (e.g. using the `SetRedirectEnabled` RPC).
+//!
+//! A link's `interstitial` option makes the redirector respond with an
+//! interstitial page (saying that the visitor is being redirected to the
+//! link's destination in a few seconds) instead of redirecting immediately,
+//! e.g. for compliance-sensitive destinations. The page redirects using a
+//! meta refresh after the configured `interstitial_delay`, and can be replaced
+//! using the `interstitial_template` configuration option.
+//!
+//! # Example
+//! ```json
+//! {
+//! 	"cors": {
+//! 		"allowed_origins": ["https://example.com"],
+//! 		"allowed_methods": ["GET", "HEAD"]
+//! 	},
+//! 	"path_passthrough": true,
+//! 	"expires_at": 1767225600,
+//! 	"max_clicks": 100,
+//! 	"geo_destinations": {
+//! 		"US": "https://example.com/us",
+//! 		"EU": "https://example.com/eu"
+//! 	},
+//! 	"device_destinations": {
+//! 		"ios": "https://apps.apple.com/app/id000000000",
+//! 		"android": "https://play.google.com/store/apps/details?id=com.example"
+//! 	},
+//! 	"split": {
+//! 		"variants": [
+//! 			{ "name": "a", "link": "https://example.com/a", "weight": 3 },
+//! 			{ "name": "b", "link": "https://example.com/b", "weight": 1 }
+//! 		],
+//! 		"sticky": true
+//! 	},
+//! 	"query_params": {
+//! 		"utm_source": "links",
+//! 		"utm_campaign": "launch"
+//! 	},
+//! 	"interstitial": true
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use links_normalized::Link;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+
+use crate::stats::ClientHints;
+
+/// Options for a single link, overriding the global configuration for that
+/// link. See the [module-level documentation][self] for details.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct LinkOptions {
+	/// CORS settings for this link, overriding the global `cors` configuration
+	/// option
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub cors: Option<Cors>,
+	/// Whether the rest of the request path after this link's ID or vanity
+	/// path is appended to its destination, overriding the global
+	/// `path_passthrough` configuration option
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub path_passthrough: Option<bool>,
+	/// The time (as a unix timestamp in seconds) after which this link is
+	/// expired, and is no longer redirected
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expires_at: Option<i64>,
+	/// The maximum number of times this link is redirected, after which it is
+	/// treated as expired
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_clicks: Option<u64>,
+	/// Alternative destinations of this link for visitors from specific
+	/// countries or continents, keyed by their (case-insensitive) code
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[schema(value_type = Option<BTreeMap<String, String>>)]
+	pub geo_destinations: Option<BTreeMap<String, Link>>,
+	/// Alternative destinations of this link for visitors using specific
+	/// platforms or mobile or desktop devices, keyed by their
+	/// (case-insensitive) name
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[schema(value_type = Option<BTreeMap<String, String>>)]
+	pub device_destinations: Option<BTreeMap<String, Link>>,
+	/// Weighted destinations of this link, one of which is chosen for each
+	/// redirect instead of its default destination
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub split: Option<SplitDestinations>,
+	/// Query parameters added to this link's destination when redirecting
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub query_params: Option<BTreeMap<String, String>>,
+	/// Why this link's destination was flagged by a [reputation
+	/// check][crate::reputation] (e.g. `MALWARE`), if it was, in which case
+	/// the link is disabled
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub flagged: Option<String>,
+	/// Whether this link is enabled (the default), disabled links are treated
+	/// as if they didn't exist, but keep their data and statistics
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub enabled: Option<bool>,
+	/// Whether visitors are shown an interstitial page (with a countdown)
+	/// before being redirected to this link's destination
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub interstitial: Option<bool>,
+}
+
+impl LinkOptions {
+	/// Check whether these options don't override anything
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.cors.is_none()
+			&& self.path_passthrough.is_none()
+			&& self.expires_at.is_none()
+			&& self.max_clicks.is_none()
+			&& self.geo_destinations.is_none()
+			&& self.device_destinations.is_none()
+			&& self.split.is_none()
+			&& self.query_params.is_none()
+			&& self.flagged.is_none()
+			&& self.enabled.is_none()
+			&& self.interstitial.is_none()
+	}
+
+	/// Check whether the link with these options is disabled, either
+	/// explicitly or because its destination was flagged
+	#[must_use]
+	pub const fn is_disabled(&self) -> bool {
+		matches!(self.enabled, Some(false)) || self.flagged.is_some()
+	}
+
+	/// Check whether the link with these options has expired
+	#[must_use]
+	pub fn is_expired(&self) -> bool {
+		self.expires_at
+			.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc().unix_timestamp())
+	}
+
+	/// Get the device-targeted destination of the link with these options for
+	/// a visitor with the client `hints`, if there is one for their device
+	#[must_use]
+	pub fn device_destination(&self, hints: &ClientHints<'_>) -> Option<&Link> {
+		let destinations = self.device_destinations.as_ref()?;
+		let find = |name: &str| {
+			destinations
+				.iter()
+				.find(|(key, _)| key.eq_ignore_ascii_case(name))
+				.map(|(_, link)| link)
+		};
+
+		let device = hints
+			.is_mobile()
+			.map(|mobile| if mobile { "mobile" } else { "desktop" });
+
+		hints
+			.platform()
+			.and_then(|platform| find(platform.as_str()))
+			.or_else(|| device.and_then(find))
+	}
+}
+
+/// Weighted destinations of a link for A/B testing. See the [module-level
+/// documentation][self] for details.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SplitDestinations {
+	/// The destinations to choose from
+	pub variants: Vec<Variant>,
+	/// Whether to choose the variant based on a hash of the client's
+	/// attributes instead of randomly
+	pub sticky: bool,
+}
+
+impl SplitDestinations {
+	/// Choose a variant, based on the `client_hash` if these destinations are
+	/// sticky and randomly otherwise. Returns the variant's index and the
+	/// variant itself, or `None` if no variant has a non-zero weight.
+	#[must_use]
+	pub fn choose(&self, client_hash: impl FnOnce() -> u64) -> Option<(usize, &Variant)> {
+		let total = self
+			.variants
+			.iter()
+			.map(|v| u64::from(v.weight))
+			.sum::<u64>();
+
+		if total == 0 {
+			return None;
+		}
+
+		let point = if self.sticky {
+			client_hash()
+		} else {
+			rand::thread_rng().gen()
+		};
+
+		self.variant_at(point % total)
+	}
+
+	/// Get the variant at `point` (less than the total weight of all variants)
+	/// along with its index, where each variant covers a range of points as
+	/// wide as its weight
+	fn variant_at(&self, mut point: u64) -> Option<(usize, &Variant)> {
+		for (i, variant) in self.variants.iter().enumerate() {
+			if point < u64::from(variant.weight) {
+				return Some((i, variant));
+			}
+
+			point -= u64::from(variant.weight);
+		}
+
+		None
+	}
+}
+
+/// One of a link's [`SplitDestinations`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Variant {
+	/// The name of this variant, recorded in statistics instead of its index
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	/// The destination of this variant
+	#[schema(value_type = String)]
+	pub link: Link,
+	/// The relative weight of this variant, `1` by default
+	#[serde(default = "Variant::default_weight")]
+	pub weight: u32,
+}
+
+impl Variant {
+	/// The weight of variants without an explicitly specified weight
+	const fn default_weight() -> u32 {
+		1
+	}
+}
+
+/// Cross-origin resource sharing (CORS) settings for redirect responses, which
+/// allow browser JavaScript running on other websites to fetch links.
+///
+/// CORS is disabled (no `Access-Control-*` headers are sent) if
+/// `allowed_origins` is empty, which is the default.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct Cors {
+	/// Origins (e.g. `https://example.com`) allowed to fetch links, or `*` to
+	/// allow any origin
+	pub allowed_origins: Vec<String>,
+	/// HTTP methods allowed in cross-origin requests, `GET` and `HEAD` if empty
+	pub allowed_methods: Vec<String>,
+}
+
+impl Cors {
+	/// The methods allowed if none are explicitly configured
+	const DEFAULT_METHODS: [&'static str; 2] = ["GET", "HEAD"];
+
+	/// Check whether CORS is enabled by these settings
+	#[must_use]
+	pub fn is_enabled(&self) -> bool {
+		!self.allowed_origins.is_empty()
+	}
+
+	/// Get the value of the `Access-Control-Allow-Origin` header for a request
+	/// from `origin`, or `None` if that origin is not allowed
+	#[must_use]
+	pub fn allow_origin<'a>(&self, origin: &'a str) -> Option<&'a str> {
+		allow_origin(&self.allowed_origins, origin)
+	}
+
+	/// Check whether cross-origin requests with the `method` are allowed
+	#[must_use]
+	pub fn allows_method(&self, method: &str) -> bool {
+		if self.allowed_methods.is_empty() {
+			Self::DEFAULT_METHODS.contains(&method)
+		} else {
+			self.allowed_methods.iter().any(|m| m == "*" || m == method)
+		}
+	}
+
+	/// Get the value of the `Access-Control-Allow-Methods` header
+	#[must_use]
+	pub fn allow_methods(&self) -> String {
+		if self.allowed_methods.is_empty() {
+			Self::DEFAULT_METHODS.join(", ")
+		} else {
+			self.allowed_methods.join(", ")
+		}
+	}
+}
+
+/// Get the value of the `Access-Control-Allow-Origin` header for a request
+/// from `origin`, or `None` if that origin is not one of the `allowed` ones (or
+/// `*`)
+pub(crate) fn allow_origin<'a>(allowed: &[String], origin: &'a str) -> Option<&'a str> {
+	if allowed.iter().any(|o| o == "*") {
+		Some("*")
+	} else if allowed
+		.iter()
+		.any(|o| o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+	{
+		Some(origin)
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cors_origins() {
+		let disabled = Cors::default();
+		assert!(!disabled.is_enabled());
+		assert_eq!(disabled.allow_origin("https://example.com"), None);
+
+		let any = Cors {
+			allowed_origins: vec!["*".to_string()],
+			..Default::default()
+		};
+		assert!(any.is_enabled());
+		assert_eq!(any.allow_origin("https://example.com"), Some("*"));
+
+		let some = Cors {
+			allowed_origins: vec![
+				"https://example.com/".to_string(),
+				"http://localhost:8080".to_string(),
+			],
+			..Default::default()
+		};
+		assert_eq!(
+			some.allow_origin("https://example.com"),
+			Some("https://example.com")
+		);
+		assert_eq!(
+			some.allow_origin("http://localhost:8080"),
+			Some("http://localhost:8080")
+		);
+		assert_eq!(some.allow_origin("https://example.net"), None);
+		assert_eq!(some.allow_origin("http://localhost"), None);
+	}
+
+	#[test]
+	fn cors_methods() {
+		let default = Cors::default();
+		assert!(default.allows_method("GET"));
+		assert!(default.allows_method("HEAD"));
+		assert!(!default.allows_method("POST"));
+		assert_eq!(default.allow_methods(), "GET, HEAD");
+
+		let custom = Cors {
+			allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+			..Default::default()
+		};
+		assert!(custom.allows_method("POST"));
+		assert!(!custom.allows_method("HEAD"));
+		assert_eq!(custom.allow_methods(), "GET, POST");
+	}
+
+	#[test]
+	fn options_json() {
+		assert_eq!(
+			serde_json::from_str::<LinkOptions>("{}").unwrap(),
+			LinkOptions::default()
+		);
+		assert!(LinkOptions::default().is_empty());
+		assert_eq!(
+			serde_json::to_string(&LinkOptions::default()).unwrap(),
+			"{}"
+		);
+
+		let options: LinkOptions =
+			serde_json::from_str(r#"{"cors": {"allowed_origins": ["*"]}}"#).unwrap();
+		assert_eq!(
+			options.cors,
+			Some(Cors {
+				allowed_origins: vec!["*".to_string()],
+				allowed_methods: Vec::new(),
+			})
+		);
+		assert_eq!(options.path_passthrough, None);
+		assert!(!options.is_empty());
+
+		let options: LinkOptions = serde_json::from_str(r#"{"path_passthrough": false}"#).unwrap();
+		assert_eq!(options.cors, None);
+		assert_eq!(options.path_passthrough, Some(false));
+		assert!(!options.is_empty());
+		assert_eq!(
+			serde_json::to_string(&options).unwrap(),
+			r#"{"path_passthrough":false}"#
+		);
+	}
+
+	#[test]
+	fn options_expiry() {
+		let now = OffsetDateTime::now_utc().unix_timestamp();
+		assert!(!LinkOptions::default().is_expired());
+
+		let options: LinkOptions =
+			serde_json::from_str(&format!(r#"{{"expires_at": {}}}"#, now + 3600)).unwrap();
+		assert_eq!(options.expires_at, Some(now + 3600));
+		assert!(!options.is_empty());
+		assert!(!options.is_expired());
+
+		let options = LinkOptions {
+			expires_at: Some(now - 1),
+			..Default::default()
+		};
+		assert!(options.is_expired());
+
+		let options: LinkOptions = serde_json::from_str(r#"{"max_clicks": 1}"#).unwrap();
+		assert_eq!(options.max_clicks, Some(1));
+		assert!(!options.is_empty());
+		assert!(!options.is_expired());
+	}
+
+	#[test]
+	fn options_disabled() {
+		assert!(!LinkOptions::default().is_disabled());
+
+		let options: LinkOptions = serde_json::from_str(r#"{"enabled": false}"#).unwrap();
+		assert!(!options.is_empty());
+		assert!(options.is_disabled());
+
+		let options: LinkOptions = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+		assert!(!options.is_disabled());
+
+		let options: LinkOptions =
+			serde_json::from_str(r#"{"enabled": true, "flagged": "MALWARE"}"#).unwrap();
+		assert!(options.is_disabled());
+	}
+
+	#[test]
+	fn options_geo_destinations() {
+		let options: LinkOptions =
+			serde_json::from_str(r#"{"geo_destinations": {"US": "https://example.com/us"}}"#)
+				.unwrap();
+		assert_eq!(
+			options.geo_destinations,
+			Some(BTreeMap::from([(
+				"US".to_string(),
+				Link::new("https://example.com/us").unwrap()
+			)]))
+		);
+		assert!(!options.is_empty());
+		assert_eq!(
+			serde_json::to_string(&options).unwrap(),
+			r#"{"geo_destinations":{"US":"https://example.com/us"}}"#
+		);
+
+		assert!(serde_json::from_str::<LinkOptions>(
+			r#"{"geo_destinations": {"US": "not a link"}}"#
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn options_device_destinations() {
+		let options: LinkOptions = serde_json::from_str(
+			r#"{"device_destinations": {"iOS": "https://example.com/ios", "mobile": "https://example.com/mobile"}}"#,
+		)
+		.unwrap();
+		assert!(!options.is_empty());
+
+		let iphone = ClientHints {
+			user_agent: Some("Mozilla/5.0 (iPhone; CPU iPhone OS 18_0 like Mac OS X) Mobile"),
+			..Default::default()
+		};
+		let android = ClientHints {
+			sec_ch_ua_mobile: Some("?1"),
+			sec_ch_ua_platform: Some(r#""Android""#),
+			..Default::default()
+		};
+		let desktop = ClientHints {
+			sec_ch_ua_mobile: Some("?0"),
+			sec_ch_ua_platform: Some(r#""Windows""#),
+			..Default::default()
+		};
+
+		assert_eq!(
+			options.device_destination(&iphone).map(Link::as_str),
+			Some("https://example.com/ios")
+		);
+		assert_eq!(
+			options.device_destination(&android).map(Link::as_str),
+			Some("https://example.com/mobile")
+		);
+		assert_eq!(options.device_destination(&desktop), None);
+		assert_eq!(options.device_destination(&ClientHints::default()), None);
+		assert_eq!(LinkOptions::default().device_destination(&android), None);
+	}
+
+	#[test]
+	fn options_split() {
+		let options: LinkOptions = serde_json::from_str(
+			r#"{"split": {"variants": [{"name": "a", "link": "https://example.com/a", "weight": 3}, {"link": "https://example.com/b"}], "sticky": true}}"#,
+		)
+		.unwrap();
+		assert!(!options.is_empty());
+
+		let split = options.split.unwrap();
+		assert!(split.sticky);
+		assert_eq!(split.variants[0].name.as_deref(), Some("a"));
+		assert_eq!(split.variants[1].name, None);
+		assert_eq!(split.variants[1].weight, 1);
+
+		let chosen = |hash| split.choose(|| hash).map(|(i, _)| i);
+		assert_eq!(chosen(0), Some(0));
+		assert_eq!(chosen(2), Some(0));
+		assert_eq!(chosen(3), Some(1));
+		assert_eq!(chosen(4), Some(0));
+		assert_eq!(chosen(7), Some(1));
+
+		let random = SplitDestinations {
+			sticky: false,
+			..split.clone()
+		};
+		for _ in 0..100 {
+			assert!(random.choose(|| unreachable!()).is_some());
+		}
+
+		let empty = SplitDestinations {
+			variants: vec![Variant {
+				weight: 0,
+				..split.variants[0].clone()
+			}],
+			sticky: false,
+		};
+		assert_eq!(empty.choose(|| 0), None);
+		assert_eq!(SplitDestinations::default().choose(|| 0), None);
+	}
+}
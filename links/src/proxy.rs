@@ -0,0 +1,244 @@
+//! PROXY protocol support
+//!
+//! When links runs behind a layer 4 (TCP) load balancer, the peer address of
+//! incoming connections is the load balancer's, not the client's. Load
+//! balancers like HAProxy can send the client's address at the start of each
+//! connection using the [PROXY protocol], in either its human-readable version
+//! 1 or its binary version 2 format. On the listeners listed in the
+//! `proxy_protocol_listeners` configuration option, this header (in either
+//! version) is read before the connection is passed to the acceptor, so that
+//! the client's address is used for statistics, rate limiting, and logging.
+//!
+//! Connections to those listeners must start with a valid header, and are
+//! closed if they don't, or if the header isn't received within
+//! [`HEADER_TIMEOUT`]. Headers without a client address (e.g. for the load
+//! balancer's own health checks, which use the `LOCAL` command in version 2 or
+//! the `UNKNOWN` protocol in version 1) are accepted, in which case the peer
+//! address is used.
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+
+use std::{
+	net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+	str,
+	time::Duration,
+};
+
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, Error as IoError, ErrorKind},
+	time::timeout,
+};
+
+/// The maximum time allowed for receiving the PROXY protocol header after a
+/// connection is accepted
+pub const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The signature at the start of version 2 headers
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The maximum length of a version 1 header, including the final CRLF
+const V1_MAX_LEN: usize = 107;
+
+/// Read the PROXY protocol header (version 1 or 2) at the start of the
+/// `stream`, without reading any of the data after it. Returns the client's
+/// address from the header, or `None` if the header doesn't include one.
+///
+/// # Errors
+/// Returns an error if the header is invalid, if it isn't received within
+/// [`HEADER_TIMEOUT`], or if reading from the `stream` fails.
+pub async fn read_header(
+	stream: &mut (impl AsyncRead + Unpin + Send),
+) -> Result<Option<SocketAddr>, IoError> {
+	timeout(HEADER_TIMEOUT, read_any_header(stream))
+		.await
+		.map_err(|_| IoError::new(ErrorKind::TimedOut, "PROXY protocol header timed out"))?
+}
+
+/// Read a version 1 or 2 header, see [`read_header`]
+async fn read_any_header(
+	stream: &mut (impl AsyncRead + Unpin + Send),
+) -> Result<Option<SocketAddr>, IoError> {
+	// Both versions' headers are at least this long
+	let mut start = [0; 12];
+	stream.read_exact(&mut start).await?;
+
+	if start == V2_SIGNATURE {
+		let mut fixed = [0; 4];
+		stream.read_exact(&mut fixed).await?;
+
+		let mut addresses = vec![0; usize::from(u16::from_be_bytes([fixed[2], fixed[3]]))];
+		stream.read_exact(&mut addresses).await?;
+
+		return parse_v2(fixed[0], fixed[1], &addresses);
+	}
+
+	if !start.starts_with(b"PROXY ") {
+		return Err(invalid("missing PROXY protocol header"));
+	}
+
+	// Version 1 headers are read one byte at a time, so that nothing after the
+	// header is consumed
+	let mut line = start.to_vec();
+	while !line.ends_with(b"\r\n") {
+		if line.len() >= V1_MAX_LEN {
+			return Err(invalid("PROXY protocol header is too long"));
+		}
+
+		line.push(stream.read_u8().await?);
+	}
+
+	parse_v1(&line)
+}
+
+/// Parse a version 1 header `line`, including the final CRLF
+fn parse_v1(line: &[u8]) -> Result<Option<SocketAddr>, IoError> {
+	let line = str::from_utf8(line)
+		.ok()
+		.and_then(|line| line.strip_suffix("\r\n"))
+		.ok_or_else(|| invalid("invalid PROXY protocol version 1 header"))?;
+
+	let parts = line.split(' ').collect::<Vec<_>>();
+	let (protocol, source, source_port) = match parts[..] {
+		["PROXY", "UNKNOWN", ..] => return Ok(None),
+		["PROXY", protocol, source, _, source_port, _] => (protocol, source, source_port),
+		_ => return Err(invalid("invalid PROXY protocol version 1 header")),
+	};
+
+	let ip = match (protocol, source.parse()) {
+		("TCP4", Ok(ip @ IpAddr::V4(_))) | ("TCP6", Ok(ip @ IpAddr::V6(_))) => ip,
+		_ => return Err(invalid("invalid PROXY protocol version 1 source address")),
+	};
+
+	let port = source_port
+		.parse()
+		.map_err(|_| invalid("invalid PROXY protocol version 1 source port"))?;
+
+	Ok(Some(SocketAddr::new(ip, port)))
+}
+
+/// Parse a version 2 header from its version and command byte, its address
+/// family and protocol byte, and its `addresses` block
+fn parse_v2(
+	version_command: u8,
+	family: u8,
+	addresses: &[u8],
+) -> Result<Option<SocketAddr>, IoError> {
+	if version_command >> 4 != 2 {
+		return Err(invalid("unsupported PROXY protocol version"));
+	}
+
+	match version_command & 0x0f {
+		// `LOCAL`, e.g. health checks by the load balancer itself
+		0x0 => return Ok(None),
+		// `PROXY`
+		0x1 => (),
+		_ => return Err(invalid("unsupported PROXY protocol command")),
+	}
+
+	match family >> 4 {
+		// `AF_INET`, with 4-byte addresses followed by the ports
+		0x1 if addresses.len() >= 12 => {
+			let mut ip = [0; 4];
+			ip.copy_from_slice(&addresses[..4]);
+			let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+
+			Ok(Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port)))
+		}
+		// `AF_INET6`, with 16-byte addresses followed by the ports
+		0x2 if addresses.len() >= 36 => {
+			let mut ip = [0; 16];
+			ip.copy_from_slice(&addresses[..16]);
+			let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+
+			Ok(Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port)))
+		}
+		// `AF_UNSPEC` and `AF_UNIX`, without an IP address
+		0x0 | 0x3 => Ok(None),
+		_ => Err(invalid("invalid PROXY protocol version 2 addresses")),
+	}
+}
+
+/// Create an error for an invalid PROXY protocol header
+fn invalid(message: &'static str) -> IoError {
+	IoError::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn v1() {
+		assert_eq!(
+			parse_v1(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n").unwrap(),
+			Some("192.0.2.1:56324".parse().unwrap())
+		);
+		assert_eq!(
+			parse_v1(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n").unwrap(),
+			Some("[2001:db8::1]:56324".parse().unwrap())
+		);
+		assert_eq!(parse_v1(b"PROXY UNKNOWN\r\n").unwrap(), None);
+		assert_eq!(
+			parse_v1(b"PROXY UNKNOWN 192.0.2.1 198.51.100.1 56324 443\r\n").unwrap(),
+			None
+		);
+
+		assert!(parse_v1(b"PROXY TCP4 2001:db8::1 2001:db8::2 56324 443\r\n").is_err());
+		assert!(parse_v1(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n").is_err());
+		assert!(parse_v1(b"PROXY TCP4 192.0.2.1 198.51.100.1 65536 443\r\n").is_err());
+		assert!(parse_v1(b"PROXY UDP4 192.0.2.1 198.51.100.1 56324 443\r\n").is_err());
+	}
+
+	#[test]
+	fn v2() {
+		let v4 = [192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb];
+		assert_eq!(
+			parse_v2(0x21, 0x11, &v4).unwrap(),
+			Some("192.0.2.1:56324".parse().unwrap())
+		);
+
+		let mut v6 = [0; 36];
+		v6[..2].copy_from_slice(&[0x20, 0x01]);
+		v6[15] = 1;
+		v6[32..].copy_from_slice(&[0xdc, 0x04, 0x01, 0xbb]);
+		assert_eq!(
+			parse_v2(0x21, 0x21, &v6).unwrap(),
+			Some("[2001::1]:56324".parse().unwrap())
+		);
+
+		assert_eq!(parse_v2(0x20, 0x00, &[]).unwrap(), None);
+		assert_eq!(parse_v2(0x21, 0x00, &[]).unwrap(), None);
+
+		assert!(parse_v2(0x11, 0x11, &v4).is_err());
+		assert!(parse_v2(0x22, 0x11, &v4).is_err());
+		assert!(parse_v2(0x21, 0x21, &v4).is_err());
+	}
+
+	#[tokio::test]
+	async fn read_headers() {
+		let mut stream = &b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n"[..];
+		assert_eq!(
+			read_header(&mut stream).await.unwrap(),
+			Some("192.0.2.1:56324".parse().unwrap())
+		);
+		assert_eq!(stream, b"GET / HTTP/1.1\r\n");
+
+		let mut header = V2_SIGNATURE.to_vec();
+		header.extend_from_slice(&[0x21, 0x11, 0x00, 0x0c]);
+		header.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb]);
+		header.extend_from_slice(b"GET / HTTP/1.1\r\n");
+		let mut stream = &header[..];
+		assert_eq!(
+			read_header(&mut stream).await.unwrap(),
+			Some("192.0.2.1:56324".parse().unwrap())
+		);
+		assert_eq!(stream, b"GET / HTTP/1.1\r\n");
+
+		let mut stream = &b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"[..];
+		assert!(read_header(&mut stream).await.is_err());
+
+		let long = [b"PROXY TCP4 ".as_slice(), &[b'1'; 200]].concat();
+		let mut stream = &long[..];
+		assert!(read_header(&mut stream).await.is_err());
+	}
+}
@@ -0,0 +1,581 @@
+//! Rate limiting of API and redirector requests.
+//!
+//! Requests are limited using a [token bucket] for each key (e.g. an API
+//! token's identifier or a client's IP address). Each bucket holds up to
+//! `burst` requests, and is refilled at a steady rate of `per_minute` requests
+//! per minute. A request is allowed if its bucket isn't empty, so short bursts
+//! of requests are allowed, but sustained traffic is limited to the configured
+//! rate. Redirector requests can be limited per client IP address (see
+//! [`RedirectRateLimits`]), protecting the store from scraping and enumeration
+//! of links.
+//!
+//! API tokens can also have a [`Quota`] of requests which modify the store
+//! per day and per month. Unlike rate limits, which are kept in memory, quota
+//! usage is tracked in the store, so that it is shared by all links instances
+//! using the same store.
+//!
+//! [token bucket]: https://en.wikipedia.org/wiki/Token_bucket
+
+use std::{
+	collections::HashMap,
+	fmt::{Display, Error as FmtError, Formatter, Result as FmtResult},
+	hash::Hash,
+	net::IpAddr,
+	time::Instant,
+};
+
+use anyhow::{anyhow, Result};
+use hyper::HeaderMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use strum::{Display as EnumDisplay, IntoStaticStr};
+use time::{format_description::well_known::Rfc3339, Date, Month, OffsetDateTime};
+
+use crate::store::Store;
+
+/// The number of new keys in a [`RateLimiter`] after which full buckets (which
+/// are equivalent to missing ones) are removed
+const PRUNE_INTERVAL: usize = 10_000;
+
+/// The maximum number of buckets in a [`RateLimiter`]
+const MAX_BUCKETS: usize = 100_000;
+
+/// A rate limit, allowing bursts of up to `burst` requests, and `per_minute`
+/// requests per minute on average.
+///
+/// The rate limit is disabled if `per_minute` is `0`, which is the default.
+///
+/// # Example
+/// ```toml
+/// { per_minute = 600, burst = 60 }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+	/// The average number of allowed requests per minute, or `0` for no limit
+	pub per_minute: u32,
+	/// The maximum number of requests allowed in a burst (at least `1`)
+	pub burst: u32,
+}
+
+impl Default for RateLimit {
+	fn default() -> Self {
+		Self {
+			per_minute: 0,
+			burst: 10,
+		}
+	}
+}
+
+impl RateLimit {
+	/// Check whether this rate limit is enabled
+	#[must_use]
+	pub const fn is_enabled(&self) -> bool {
+		self.per_minute != 0
+	}
+
+	/// Get the number of seconds after which a request is allowed again once
+	/// this rate limit is exceeded (rounded up)
+	#[must_use]
+	pub const fn retry_after(self) -> u32 {
+		if self.per_minute == 0 {
+			return 0;
+		}
+
+		60_u32.div_ceil(self.per_minute)
+	}
+
+	/// Get the bucket capacity of this rate limit
+	fn capacity(self) -> f64 {
+		f64::from(self.burst.max(1))
+	}
+
+	/// Get the number of requests this rate limit allows per second
+	fn per_second(self) -> f64 {
+		f64::from(self.per_minute) / 60.0
+	}
+}
+
+/// Rate limits of RPC API requests, per API token and per peer IP address.
+///
+/// Requests exceeding either limit are rejected with the `RESOURCE_EXHAUSTED`
+/// status code. Both limits are disabled by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RpcRateLimits {
+	/// The rate limit for each API token (by its identifier)
+	pub token: RateLimit,
+	/// The rate limit for each peer IP address, which is checked before
+	/// authentication
+	pub peer: RateLimit,
+}
+
+/// Rate limits of redirector requests, per client IP address.
+///
+/// Requests exceeding the limit are rejected with the `429 Too Many Requests`
+/// status code before any links are looked up. The client's IP address is
+/// the peer's address, unless `use_forwarded_for` is enabled, in which case
+/// the last address in the `X-Forwarded-For` header (as added by a reverse
+/// proxy in front of links) is used if there is one. This should only be
+/// enabled if all requests go through such a proxy, as the header could
+/// otherwise be set to anything by clients. The limit is disabled by default.
+///
+/// # Example
+/// ```toml
+/// { client = { per_minute = 600, burst = 60 }, use_forwarded_for = false }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedirectRateLimits {
+	/// The rate limit for each client IP address
+	pub client: RateLimit,
+	/// Get the client IP address from the `X-Forwarded-For` header
+	pub use_forwarded_for: bool,
+}
+
+impl RedirectRateLimits {
+	/// Get the IP address of the client making a request with the `headers`
+	/// from the `peer` IP address, according to these settings
+	#[must_use]
+	pub fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+		if !self.use_forwarded_for {
+			return peer;
+		}
+
+		headers
+			.get_all("x-forwarded-for")
+			.iter()
+			.last()
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.rsplit(',').next())
+			.and_then(|ip| ip.trim().parse().ok())
+			.or(peer)
+	}
+}
+
+/// A quota of API requests which modify the store (e.g. setting or removing
+/// redirects), limiting how many of them an API token can make per calendar
+/// day and per calendar month (in UTC).
+///
+/// Requests modifying multiple items at once (e.g. setting a batch of
+/// redirects) count once per item. Requests exceeding the quota are rejected
+/// with the `RESOURCE_EXHAUSTED` status code in the gRPC API, or the `429 Too
+/// Many Requests` status code in the HTTP API, but still count towards the
+/// quota. Quota usage is tracked in the store, so the store backend must
+/// support [usage counters][crate::store::Store::supports_usage], otherwise
+/// links refuses to start with quotas configured.
+///
+/// Both limits are disabled (`0`) by default.
+///
+/// # Example
+/// ```toml
+/// { daily = 10000, monthly = 200000 }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quota {
+	/// The number of allowed requests per day, or `0` for no limit
+	pub daily: u64,
+	/// The number of allowed requests per month, or `0` for no limit
+	pub monthly: u64,
+}
+
+impl Quota {
+	/// Check whether this quota is enabled
+	#[must_use]
+	pub const fn is_enabled(&self) -> bool {
+		self.daily != 0 || self.monthly != 0
+	}
+
+	/// Record `amount` requests made using the API token with the identifier
+	/// `token_id` in the `store`, and check whether they are allowed by this
+	/// quota. Returns `None` if they are (or if the quota is disabled), or the
+	/// exceeded limit if they aren't.
+	///
+	/// # Errors
+	/// Returns an error if the usage could not be recorded in the store, e.g.
+	/// because the store backend doesn't support it.
+	pub async fn check(
+		self,
+		store: &Store,
+		token_id: &str,
+		amount: u64,
+	) -> Result<Option<QuotaExceeded>> {
+		let now = OffsetDateTime::now_utc();
+
+		for (period, limit) in [
+			(QuotaPeriod::Daily, self.daily),
+			(QuotaPeriod::Monthly, self.monthly),
+		] {
+			if limit == 0 {
+				continue;
+			}
+
+			let resets_at = period.end(now)?;
+			let key = format!("{token_id}:{period}:{}", period.name(now));
+			let used = store.incr_usage(&key, amount, resets_at).await?;
+
+			if used > limit {
+				return Ok(Some(QuotaExceeded {
+					period,
+					limit,
+					resets_at,
+				}));
+			}
+		}
+
+		Ok(None)
+	}
+}
+
+/// The period of one of the limits of a [`Quota`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, EnumDisplay, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum QuotaPeriod {
+	/// A calendar day (in UTC)
+	Daily,
+	/// A calendar month (in UTC)
+	Monthly,
+}
+
+impl QuotaPeriod {
+	/// Get the name of the period containing `now`, e.g. `2024-05-17` for a
+	/// day or `2024-05` for a month
+	fn name(self, now: OffsetDateTime) -> String {
+		match self {
+			Self::Daily => now.date().to_string(),
+			Self::Monthly => format!("{}-{:02}", now.year(), u8::from(now.month())),
+		}
+	}
+
+	/// Get the end of the period containing `now`, i.e. the start of the next
+	/// one
+	fn end(self, now: OffsetDateTime) -> Result<OffsetDateTime> {
+		let next = match self {
+			Self::Daily => now.date().next_day(),
+			Self::Monthly if now.month() == Month::December => {
+				Date::from_calendar_date(now.year() + 1, Month::January, 1).ok()
+			}
+			Self::Monthly => Date::from_calendar_date(now.year(), now.month().next(), 1).ok(),
+		};
+
+		next.map(|date| date.midnight().assume_utc())
+			.ok_or_else(|| anyhow!("the date is out of range"))
+	}
+}
+
+/// An exceeded limit of a [`Quota`], returned by [`Quota::check`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded {
+	/// The period of the exceeded limit
+	pub period: QuotaPeriod,
+	/// The number of requests allowed per `period`
+	pub limit: u64,
+	/// When the current period ends, resetting the quota's usage
+	pub resets_at: OffsetDateTime,
+}
+
+impl Display for QuotaExceeded {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		write!(
+			fmt,
+			"{} quota of {} requests exceeded, try again after {}",
+			self.period,
+			self.limit,
+			self.resets_at.format(&Rfc3339).map_err(|_| FmtError)?
+		)
+	}
+}
+
+/// A token bucket
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+	/// The number of requests currently available
+	available: f64,
+	/// When `available` was last updated
+	updated: Instant,
+}
+
+/// A set of token buckets, one for each key, used to limit the rate of
+/// requests for each key individually
+///
+/// Full buckets are removed after every [`PRUNE_INTERVAL`] new keys, so that
+/// the time this takes is spread out over many requests, instead of making
+/// every request slow while there are many buckets. There are at most
+/// [`MAX_BUCKETS`] buckets, and requests for new keys are denied while there
+/// is no room for theirs, so that e.g. requests from very many different IP
+/// addresses can't use up all memory.
+#[derive(Debug, Default)]
+pub struct RateLimiter<K> {
+	buckets: Mutex<Buckets<K>>,
+}
+
+/// The buckets of a [`RateLimiter`]
+#[derive(Debug)]
+struct Buckets<K> {
+	map: HashMap<K, Bucket>,
+	/// The number of new keys since full buckets were last removed
+	new_keys: usize,
+}
+
+impl<K> Default for Buckets<K> {
+	fn default() -> Self {
+		Self {
+			map: HashMap::new(),
+			new_keys: 0,
+		}
+	}
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+	/// Create a new rate limiter with no buckets
+	#[must_use]
+	pub fn new() -> Self {
+		Self {
+			buckets: Mutex::default(),
+		}
+	}
+
+	/// Check whether a request for the `key` is allowed by the `limit`, taking
+	/// it out of the key's bucket if it is. Always returns `true` if the limit
+	/// is disabled.
+	pub fn check(&self, key: K, limit: RateLimit) -> bool {
+		self.check_at(key, limit, Instant::now())
+	}
+
+	/// Check whether a request for the `key` at the time `now` is allowed by
+	/// the `limit`, see [`RateLimiter::check`]
+	fn check_at(&self, key: K, limit: RateLimit, now: Instant) -> bool {
+		if !limit.is_enabled() {
+			return true;
+		}
+
+		let mut buckets = self.buckets.lock();
+		let Buckets { map, new_keys } = &mut *buckets;
+
+		if !map.contains_key(&key) {
+			*new_keys += 1;
+
+			if *new_keys >= PRUNE_INTERVAL {
+				*new_keys = 0;
+				map.retain(|_, bucket| Self::refill(*bucket, limit, now) < limit.capacity());
+			}
+
+			if map.len() >= MAX_BUCKETS {
+				return false;
+			}
+		}
+
+		let bucket = map.entry(key).or_insert_with(|| Bucket {
+			available: limit.capacity(),
+			updated: now,
+		});
+
+		bucket.available = Self::refill(*bucket, limit, now);
+		bucket.updated = now;
+
+		let allowed = bucket.available >= 1.0;
+		if allowed {
+			bucket.available -= 1.0;
+		}
+		drop(buckets);
+
+		allowed
+	}
+
+	/// Get the number of requests available in the `bucket` at `now`
+	fn refill(bucket: Bucket, limit: RateLimit, now: Instant) -> f64 {
+		let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+		elapsed
+			.mul_add(limit.per_second(), bucket.available)
+			.min(limit.capacity())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn rate_limiter() {
+		let limiter = RateLimiter::new();
+		let limit = RateLimit {
+			per_minute: 60,
+			burst: 3,
+		};
+		let start = Instant::now();
+
+		for _ in 0..3 {
+			assert!(limiter.check_at("a", limit, start));
+		}
+		assert!(!limiter.check_at("a", limit, start));
+		assert!(limiter.check_at("b", limit, start));
+
+		let later = start + Duration::from_millis(1500);
+		assert!(limiter.check_at("a", limit, later));
+		assert!(!limiter.check_at("a", limit, later));
+
+		let much_later = start + Duration::from_secs(60);
+		for _ in 0..3 {
+			assert!(limiter.check_at("a", limit, much_later));
+		}
+		assert!(!limiter.check_at("a", limit, much_later));
+	}
+
+	#[test]
+	fn rate_limiter_pruning() {
+		let limiter = RateLimiter::new();
+		let limit = RateLimit {
+			per_minute: 60,
+			burst: 1,
+		};
+		let start = Instant::now();
+		let len = || limiter.buckets.lock().map.len();
+
+		for key in 0..2 * PRUNE_INTERVAL {
+			assert!(limiter.check_at(key, limit, start));
+		}
+		assert_eq!(len(), 2 * PRUNE_INTERVAL);
+
+		// All buckets are full again, but existing keys don't scan all of them
+		let later = start + Duration::from_secs(60);
+		for _ in 0..1000 {
+			limiter.check_at(0, limit, later);
+		}
+		assert_eq!(len(), 2 * PRUNE_INTERVAL);
+
+		// They are only removed after enough new keys
+		for key in 2 * PRUNE_INTERVAL..3 * PRUNE_INTERVAL - 1 {
+			assert!(limiter.check_at(key, limit, later));
+		}
+		assert_eq!(len(), 3 * PRUNE_INTERVAL - 1);
+		assert!(limiter.check_at(3 * PRUNE_INTERVAL, limit, later));
+		assert_eq!(len(), PRUNE_INTERVAL + 1);
+	}
+
+	#[test]
+	fn rate_limiter_max_buckets() {
+		let limiter = RateLimiter::new();
+		let limit = RateLimit {
+			per_minute: 60,
+			burst: 1,
+		};
+		let start = Instant::now();
+
+		for key in 0..MAX_BUCKETS {
+			assert!(limiter.check_at(key, limit, start));
+		}
+
+		// There is no room for new keys, but existing ones still work
+		assert!(!limiter.check_at(MAX_BUCKETS, limit, start));
+		let later = start + Duration::from_secs(1);
+		assert!(limiter.check_at(0, limit, later));
+		assert_eq!(limiter.buckets.lock().map.len(), MAX_BUCKETS);
+
+		// Once full buckets are removed, there is room again
+		let much_later = start + Duration::from_secs(60);
+		for key in MAX_BUCKETS..MAX_BUCKETS + PRUNE_INTERVAL {
+			limiter.check_at(key, limit, much_later);
+		}
+		assert!(limiter.check_at(MAX_BUCKETS, limit, much_later));
+	}
+
+	#[test]
+	fn redirect_client_ip() {
+		let peer = Some(IpAddr::from([192, 0, 2, 1]));
+		let mut headers = HeaderMap::new();
+		headers.append("X-Forwarded-For", "203.0.113.7".parse().unwrap());
+		headers.append(
+			"X-Forwarded-For",
+			"198.51.100.1, 2001:db8::1".parse().unwrap(),
+		);
+
+		let limits = RedirectRateLimits::default();
+		assert_eq!(limits.client_ip(&headers, peer), peer);
+
+		let limits = RedirectRateLimits {
+			use_forwarded_for: true,
+			..Default::default()
+		};
+		assert_eq!(
+			limits.client_ip(&headers, peer),
+			Some("2001:db8::1".parse().unwrap())
+		);
+		assert_eq!(limits.client_ip(&HeaderMap::new(), peer), peer);
+
+		assert_eq!(RateLimit::default().retry_after(), 0);
+		assert_eq!(
+			RateLimit {
+				per_minute: 7,
+				burst: 1
+			}
+			.retry_after(),
+			9
+		);
+	}
+
+	#[test]
+	fn quota_period() {
+		let now = time::macros::datetime!(2024-12-31 13:37:00 UTC);
+
+		assert_eq!(QuotaPeriod::Daily.name(now), "2024-12-31");
+		assert_eq!(QuotaPeriod::Monthly.name(now), "2024-12");
+		assert_eq!(
+			QuotaPeriod::Daily.end(now).unwrap(),
+			time::macros::datetime!(2025-01-01 00:00:00 UTC)
+		);
+		assert_eq!(
+			QuotaPeriod::Monthly.end(now).unwrap(),
+			time::macros::datetime!(2025-01-01 00:00:00 UTC)
+		);
+
+		let now = time::macros::datetime!(2024-02-10 00:00:00 UTC);
+		assert_eq!(
+			QuotaPeriod::Monthly.end(now).unwrap(),
+			time::macros::datetime!(2024-03-01 00:00:00 UTC)
+		);
+	}
+
+	#[tokio::test]
+	async fn quota() {
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+		let quota = Quota {
+			daily: 3,
+			monthly: 0,
+		};
+
+		assert_eq!(quota.check(&store, "a", 2).await.unwrap(), None);
+		assert_eq!(quota.check(&store, "a", 1).await.unwrap(), None);
+		assert_eq!(quota.check(&store, "b", 3).await.unwrap(), None);
+
+		let exceeded = quota.check(&store, "a", 1).await.unwrap().unwrap();
+		assert_eq!(exceeded.period, QuotaPeriod::Daily);
+		assert_eq!(exceeded.limit, 3);
+		assert!(exceeded.resets_at > OffsetDateTime::now_utc());
+
+		for _ in 0..10 {
+			assert_eq!(Quota::default().check(&store, "a", 1).await.unwrap(), None);
+		}
+	}
+
+	#[test]
+	fn rate_limiter_disabled() {
+		let limiter = RateLimiter::new();
+		let now = Instant::now();
+
+		for _ in 0..1000 {
+			assert!(limiter.check_at("a", RateLimit::default(), now));
+		}
+
+		let no_burst = RateLimit {
+			per_minute: 1,
+			burst: 0,
+		};
+		assert!(limiter.check_at("a", no_burst, now));
+		assert!(!limiter.check_at("a", no_burst, now));
+	}
+}
@@ -1,131 +1,724 @@
 //! The main part of links. This module contains code relating to actually
 //! redirecting requests.
 
-use std::fmt::Debug;
+use std::{
+	collections::BTreeMap,
+	fmt::{Debug, Display},
+	net::IpAddr,
+	sync::{Arc, LazyLock},
+};
 
 use hyper::{
-	header::HeaderValue, http::uri::PathAndQuery, Method, Request, Response, StatusCode, Uri,
+	header::{
+		HeaderName, HeaderValue, ALT_SVC, CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE,
+		LOCATION, REFERRER_POLICY, RETRY_AFTER, SERVER, STRICT_TRANSPORT_SECURITY,
+	},
+	http::{response::Builder, uri::PathAndQuery},
+	Method, Request, Response, StatusCode, Uri,
 };
 use links_id::Id;
-use links_normalized::Normalized;
-use tokio::time::Instant;
-use tracing::{debug, field::Empty, instrument, trace};
+use links_normalized::{Link, Normalized};
+use ring::digest::{Context, SHA256};
+use tokio::{spawn, time::Instant, try_join};
+use tracing::{
+	debug,
+	field::{self, Empty},
+	instrument, trace, warn,
+};
 
 use crate::{
-	config::{Hsts, Redirector as Config},
-	stats::{ExtraStatisticInfo, Statistic},
+	access_log::AccessLogEntry,
+	config::{ExpiredStatus, PathSegments, Redirector as Config},
+	options::LinkOptions,
+	ratelimit::RateLimiter,
+	stats::{anomaly, ClientHints, ExtraStatisticInfo, Statistic},
 	store::Store,
+	trace::TraceContext,
 	util::{csp_hashes, include_html, SERVER_NAME},
 };
 
+/// The `Content-Type` of all HTML responses
+const HTML_CONTENT_TYPE: &str = "text/html; charset=UTF-8";
+
+/// The `Content-Type` of `/robots.txt` responses
+const TEXT_CONTENT_TYPE: &str = "text/plain; charset=UTF-8";
+
+/// The `Content-Type` of the built-in icon
+const SVG_CONTENT_TYPE: &str = "image/svg+xml";
+
+/// The value of the `Alt-Svc` header, advertising HTTP/2 support on port 443
+const ALT_SVC_H2: &str = "h2=\":443\"; ma=31536000";
+
+/// Paths which are answered directly by the redirector, without looking them
+/// up as links
+const WELL_KNOWN_PATHS: [&str; 2] = ["/robots.txt", "/favicon.ico"];
+
+/// The rate limiter of redirector requests per client IP address
+static CLIENT_RATE_LIMITER: LazyLock<RateLimiter<IpAddr>> = LazyLock::new(RateLimiter::new);
+
+/// The built-in icon served for `/favicon.ico`, unless another one is
+/// configured
+const FAVICON: &str = include_str!("../../misc/icon.svg");
+
+/// Render the page `template` (the redirect, preview, or interstitial page)
+/// for `link`, allocating only once
+fn link_page(template: &str, link: &str) -> String {
+	const PLACEHOLDER: &str = "{{LINK_URL}}";
+
+	let mut page = String::with_capacity(template.len() + 2 * link.len());
+
+	for (i, part) in template.split(PLACEHOLDER).enumerate() {
+		if i != 0 {
+			page.push_str(link);
+		}
+
+		page.push_str(part);
+	}
+
+	page
+}
+
+/// Find the ID of the link at `id_or_vanity` (a request path without the
+/// leading slash), along with the normalized vanity path if it isn't an ID.
+/// IDs are parsed straight from the path without allocating, only vanity paths
+/// need to be normalized and looked up.
+async fn find_link(
+	store: &Store,
+	id_or_vanity: &str,
+) -> Result<(Option<Id>, Option<Normalized>), anyhow::Error> {
+	if let Some(id) = Id::from_ascii(id_or_vanity.as_bytes()) {
+		trace!("path is an ID");
+		Ok((Some(id), None))
+	} else {
+		let vanity = Normalized::new(id_or_vanity);
+		trace!("path is a vanity path, normalized to \"{}\"", &vanity);
+		Ok((store.get_vanity(vanity.clone()).await?, Some(vanity)))
+	}
+}
+
+/// Get the destination and options of the link with the `id`, if any
+async fn get_link(
+	store: &Store,
+	id: Option<Id>,
+) -> Result<(Option<Link>, Option<LinkOptions>), anyhow::Error> {
+	if let Some(id) = id {
+		Ok(try_join!(store.get_redirect(id), store.get_options(id))?)
+	} else {
+		Ok((None, None))
+	}
+}
+
+/// Choose the destination of a link for a request from its `default`
+/// destination and its `options`. This is one of the link's device-targeted
+/// destinations if any matches the client's device (according to its
+/// `hints`), or one of its geo-targeted destinations if any matches the
+/// location of the client (see [`crate::geo`]), or one of its weighted split
+/// destinations (recording the chosen variant in `stat_info`), or otherwise
+/// the default one.
+fn choose_destination(
+	default: Link,
+	options: Option<&LinkOptions>,
+	config: &Config,
+	stat_info: &mut ExtraStatisticInfo,
+	hints: &ClientHints<'_>,
+) -> Link {
+	if let Some(destination) = options.and_then(|options| options.device_destination(hints)) {
+		trace!(%destination, "using device-targeted destination");
+		return destination.clone();
+	}
+
+	let geo_destinations = options.and_then(|options| options.geo_destinations.as_ref());
+
+	if let (Some(destinations), Some(geoip), Some(ip)) =
+		(geo_destinations, &config.geoip, stat_info.client_ip)
+	{
+		if let Some(destination) = geoip.destination(ip, destinations) {
+			trace!(%destination, "using geo-targeted destination");
+			return destination.clone();
+		}
+	}
+
+	let split = options.and_then(|options| options.split.as_ref());
+
+	if let Some((i, variant)) =
+		split.and_then(|split| split.choose(|| client_hash(&default, stat_info.client_ip, hints)))
+	{
+		trace!(destination = %variant.link, i, "using split destination variant");
+		stat_info.variant = Some(
+			variant
+				.name
+				.as_deref()
+				.map_or_else(|| i.to_string().into(), Arc::from),
+		);
+		return variant.link.clone();
+	}
+
+	default
+}
+
+/// Hash the attributes of a client (its `ip` address and user agent `hints`)
+/// for sticky split destinations. The `link`'s default destination is
+/// included, so that the same client is independently assigned to variants of
+/// different links.
+fn client_hash(link: &Link, ip: Option<IpAddr>, hints: &ClientHints<'_>) -> u64 {
+	let mut hash = Context::new(&SHA256);
+	hash.update(link.as_str().as_bytes());
+
+	match ip {
+		Some(IpAddr::V4(ip)) => hash.update(&ip.octets()),
+		Some(IpAddr::V6(ip)) => hash.update(&ip.octets()),
+		None => (),
+	}
+
+	hash.update(hints.brands().unwrap_or_default().as_bytes());
+
+	let mut bytes = [0; 8];
+	bytes.copy_from_slice(&hash.finish().as_ref()[..8]);
+	u64::from_le_bytes(bytes)
+}
+
+/// Append the rest of the request `path` to the path of the destination
+/// `link`, keeping its query and fragment (if any) at the end. Returns `None`
+/// if the resulting URL is not a valid link.
+fn append_path(link: &Link, path: &str) -> Option<Link> {
+	if path.is_empty() {
+		return Some(link.clone());
+	}
+
+	let link = link.as_str();
+	let (base, suffix) = link.split_at(link.find(['?', '#']).unwrap_or(link.len()));
+	Link::new(&format!("{}/{path}{suffix}", base.trim_end_matches('/'))).ok()
+}
+
+/// Fill in the search fallback URL `template`, replacing `{path}` with the
+/// URL-encoded request `path`. Returns `None` if the resulting URL is not a
+/// valid link.
+fn search_link(template: &str, path: &str) -> Option<Link> {
+	let path = form_urlencoded::byte_serialize(path.as_bytes()).collect::<String>();
+	Link::new(&template.replace("{path}", &path)).ok()
+}
+
+/// Add the query `params` to the destination `link`, keeping its fragment (if
+/// any) at the end. Parameters whose name is already in the link's query are
+/// skipped. Returns `None` if the resulting URL is not a valid link.
+fn append_query(link: &Link, params: &BTreeMap<String, String>) -> Option<Link> {
+	let url = link.as_str();
+	let (base, fragment) = url.split_at(url.find('#').unwrap_or(url.len()));
+	let existing = base.split_once('?').map_or("", |(_, query)| query);
+
+	let mut query = form_urlencoded::Serializer::new(String::new());
+	for (name, value) in params {
+		if !form_urlencoded::parse(existing.as_bytes()).any(|(n, _)| n == name.as_str()) {
+			query.append_pair(name, value);
+		}
+	}
+
+	let query = query.finish();
+	if query.is_empty() {
+		return Some(link.clone());
+	}
+
+	let separator = if base.ends_with(['?', '&']) {
+		""
+	} else if base.contains('?') {
+		"&"
+	} else {
+		"?"
+	};
+
+	Link::new(&format!("{base}{separator}{query}{fragment}")).ok()
+}
+
+/// Respond to a request for one of the [`WELL_KNOWN_PATHS`] according to the
+/// `config`. The response is built from `res`, which may already have some
+/// headers set.
+fn well_known(
+	path: &str,
+	config: &Config,
+	res: Builder,
+) -> Result<Response<String>, anyhow::Error> {
+	let res = if path == "/robots.txt" {
+		res.status(StatusCode::OK)
+			.header(CONTENT_TYPE, HeaderValue::from_static(TEXT_CONTENT_TYPE))
+			.body(config.robots_txt.to_string())?
+	} else if let Some(ref favicon) = config.favicon {
+		res.status(StatusCode::FOUND)
+			.header(LOCATION, favicon.as_str())
+			.body(String::new())?
+	} else {
+		res.status(StatusCode::OK)
+			.header(CONTENT_TYPE, HeaderValue::from_static(SVG_CONTENT_TYPE))
+			.header(
+				CACHE_CONTROL,
+				HeaderValue::from_static("public, max-age=86400"),
+			)
+			.body(FAVICON.to_string())?
+	};
+
+	Ok(res)
+}
+
 /// Redirects the `req`uest to the appropriate target URL (if one is found in
-/// the `store`) or returns a `404 Not Found` response. When redirecting, the
-/// status code is `302 Found` when the method is GET, and `307 Temporary
-/// Redirect` otherwise. Additionally, `stat_info` can be used to pass extra
-/// [`Statistic`]s to be collected in addition to the ones inside of this
-/// function.
-#[instrument(level = "debug", name = "redirect-external", skip_all, fields(http.version = ?req.version(), http.host = %req.uri().host().unwrap_or_else(|| req.headers().get("host").map_or_else(|| "[unknown]", |h| h.to_str().unwrap_or("[unknown]"))), http.path = ?req.uri().path(), http.method = %req.method(), store = %store.backend_name(), time_ns = Empty, link = Empty, id = Empty, vanity = Empty, status_code = Empty))]
+/// the `store`, or otherwise to the configured search fallback or default
+/// destination, if any) or returns a `404 Not Found` response.
+///
+/// When redirecting, the status code is determined by the configured
+/// [`RedirectStatus`], by default `302 Found` when the method is GET, and `307
+/// Temporary Redirect` otherwise. Additionally, `stat_info` can be used to pass
+/// extra [`Statistic`]s to be collected in addition to the ones inside of this
+/// function. The IDs from the request's [W3C trace context][crate::trace] are
+/// recorded on this function's span. Requests for `/robots.txt` and
+/// `/favicon.ico` are answered directly, without any store lookups or
+/// statistics.
+///
+/// # Lookup
+/// The request path is first normalized according to the configured
+/// [`PathNormalization`]. If the configuration has a store `namespace` (e.g.
+/// from a [`RedirectorPolicy`]), links are looked up in that namespace of the
+/// store. If no link matches the whole path, but path pass-through is enabled
+/// for the link matching its first segment (globally or in its
+/// [options][crate::options]), the rest of the path is appended to that link's
+/// destination.
+///
+/// # Options
+/// Links with device-targeted or [geo-targeted][crate::geo] destinations are
+/// redirected according to the client's device or location, and links with
+/// weighted split destinations to one of their variants. A link's
+/// `query_params` option is applied to its destination last. Links with the
+/// `interstitial` option are answered with an interstitial page, which
+/// redirects to their destination after the configured delay.
+///
+/// # Fallbacks
+/// Links whose `expires_at` option has passed or whose `max_clicks` are used up
+/// are not redirected, instead the response depends on the configured
+/// [`ExpiredStatus`], and their redirect is removed from the store in the
+/// background. If link previews are enabled and the request ends with the
+/// configured suffix, a page showing the link's destination is returned
+/// instead of redirecting.
+///
+/// [`RedirectStatus`]: crate::config::RedirectStatus
+/// [`RedirectorPolicy`]: crate::config::RedirectorPolicy
+/// [`PathNormalization`]: crate::config::PathNormalization
+#[instrument(level = "debug", name = "redirect-external", skip_all, fields(http.version = ?req.version(), http.host = %req.uri().host().unwrap_or_else(|| req.headers().get("host").map_or_else(|| "[unknown]", |h| h.to_str().unwrap_or("[unknown]"))), http.path = ?req.uri().path(), http.method = %req.method(), store = %store.backend_name(), time_ns = Empty, link = Empty, id = Empty, vanity = Empty, status_code = Empty, trace_id = Empty, parent_id = Empty))]
 pub async fn redirector<B: Debug + Send + 'static>(
 	req: Request<B>,
 	store: Store,
-	config: Config,
-	stat_info: ExtraStatisticInfo,
+	config: Arc<Config>,
+	mut stat_info: ExtraStatisticInfo,
 ) -> Result<Response<String>, anyhow::Error> {
 	let redirect_start = Instant::now();
 	trace!(?req);
 
-	let path = req.uri().path();
+	// Attach the caller's distributed trace context (if any) to this span
+	if let Some(context) = TraceContext::from_headers(req.headers()) {
+		let span = tracing::Span::current();
+		span.record("trace_id", context.trace_id_hex());
+		span.record("parent_id", context.parent_id_hex());
+		trace!(?context, "request is part of a distributed trace");
+	}
+
+	// Requests for link previews end with the configured suffix, which may
+	// also be (part of) the query
+	let preview_target = config.preview_suffix.as_deref().and_then(|suffix| {
+		req.uri()
+			.path_and_query()
+			.and_then(|p_and_q| p_and_q.as_str().strip_suffix(suffix))
+	});
+	let preview = preview_target.is_some();
+	let path = preview_target.map_or_else(
+		|| req.uri().path(),
+		|target| target.split_once('?').map_or(target, |(path, _)| path),
+	);
+
 	let mut res = Response::builder();
 
-	// Set default response headers
-	res = res.header("Referrer-Policy", "unsafe-url");
+	// Set default response headers, using static or preformatted values
+	res = res.header(REFERRER_POLICY, config.referrer_policy.header_value());
 	if config.send_server {
-		res = res.header("Server", SERVER_NAME);
+		res = res.header(SERVER, HeaderValue::from_static(SERVER_NAME));
 	}
 
 	if config.send_alt_svc {
-		res = res.header("Alt-Svc", "h2=\":443\"; ma=31536000");
+		res = res.header(ALT_SVC, HeaderValue::from_static(ALT_SVC_H2));
+	}
+
+	if let Some(ref hsts) = config.hsts_header {
+		res = res.header(STRICT_TRANSPORT_SECURITY, hsts.clone());
+	}
+
+	for (name, value) in &*config.extra_headers {
+		res = res.header(name.clone(), value.clone());
 	}
 
-	res = match config.hsts {
-		Hsts::Disable => res,
-		Hsts::Enable(max_age) => {
-			res.header("Strict-Transport-Security", &format!("max-age={max_age}"))
+	// Crawlers and browsers request these paths on their own, so looking them
+	// up in the store would only waste time and clutter the statistics
+	if WELL_KNOWN_PATHS.contains(&req.uri().path()) {
+		let res = well_known(req.uri().path(), &config, res)?;
+
+		trace!(?res);
+		let span = tracing::Span::current();
+		span.record("time_ns", redirect_start.elapsed().as_nanos());
+		span.record("status_code", res.status().as_u16());
+		debug!("Well-known path request processed");
+
+		return Ok(res);
+	}
+
+	// Rate limited requests are rejected before touching the store, so that
+	// scraping or enumerating links can't overload it
+	let limits = config.rate_limits;
+	if limits.client.is_enabled() {
+		let client_ip = limits.client_ip(req.headers(), stat_info.client_ip);
+
+		if let Some(ip) = client_ip.filter(|&ip| !CLIENT_RATE_LIMITER.check(ip, limits.client)) {
+			let res = res
+				.status(StatusCode::TOO_MANY_REQUESTS)
+				.header(RETRY_AFTER, limits.client.retry_after())
+				.header(CONTENT_TYPE, HeaderValue::from_static(TEXT_CONTENT_TYPE))
+				.body("Too many requests, try again later\n".to_string())?;
+
+			trace!(?res);
+			let span = tracing::Span::current();
+			span.record("time_ns", redirect_start.elapsed().as_nanos());
+			span.record("status_code", res.status().as_u16());
+			debug!(%ip, "Redirect request rate limited");
+
+			return Ok(res);
 		}
-		Hsts::IncludeSubDomains(max_age) => res.header(
-			"Strict-Transport-Security",
-			&format!("max-age={max_age}; includeSubDomains"),
-		),
-		Hsts::Preload(max_age) => res.header(
-			"Strict-Transport-Security",
-			&format!("max-age={max_age}; includeSubDomains; preload"),
-		),
+	}
+
+	// Domains can have their own store namespace (e.g. for separate brands),
+	// in which case all links are looked up (and counted) there
+	let store = match config.namespace {
+		Some(ref namespace) => store.with_namespace(namespace).await?,
+		None => store,
 	};
 
-	let id_or_vanity = path.trim_start_matches('/');
+	let id_or_vanity = config
+		.path_normalization
+		.normalize(path.trim_start_matches('/'));
+	let (lookup, rest) = match config.path_normalization.segments {
+		PathSegments::Whole => (&*id_or_vanity, None),
+		PathSegments::First => id_or_vanity
+			.split_once('/')
+			.map_or((&*id_or_vanity, None), |(first, rest)| (first, Some(rest))),
+	};
 
-	let (id, vanity) = if Id::is_valid(id_or_vanity) {
-		trace!("path is an ID");
-		(Some(Id::try_from(id_or_vanity)?), None)
-	} else {
-		let vanity = Normalized::new(id_or_vanity);
-		trace!("path is a vanity path, normalized to \"{}\"", &vanity);
-		(store.get_vanity(vanity.clone()).await?, Some(vanity))
+	let passthrough = |options: Option<&LinkOptions>| {
+		options
+			.and_then(|options| options.path_passthrough)
+			.unwrap_or(config.path_passthrough)
 	};
 
-	let link = if let Some(id) = id {
-		store.get_redirect(id).await?
+	// Reserved paths (e.g. of internal endpoints) are never looked up as links
+	let reserved = config.is_reserved(&id_or_vanity);
+	let (mut id, mut vanity) = if reserved {
+		trace!("path is reserved");
+		(None, None)
 	} else {
-		None
+		find_link(&store, lookup).await?
 	};
+	let (mut link, mut options) = get_link(&store, id).await?;
+	let hints = ClientHints::from_headers(req.headers());
+	link = link
+		.map(|link| choose_destination(link, options.as_ref(), &config, &mut stat_info, &hints));
+
+	if let Some(rest) = rest {
+		// Only the first path segment was looked up, so the rest of the path is
+		// either passed through to the destination or ignored
+		if passthrough(options.as_ref()) {
+			trace!("passing \"{rest}\" through to the link at \"{lookup}\"");
+			link = link.and_then(|link| append_path(&link, rest));
+		}
+	} else if let (false, None, Some((first, rest))) =
+		(reserved, &link, id_or_vanity.split_once('/'))
+	{
+		// Paths matching a link exactly take precedence, only otherwise is the
+		// link matching the first path segment checked for path pass-through
+		let (prefix_id, prefix_vanity) = find_link(&store, first).await?;
+		let (prefix_link, prefix_options) = get_link(&store, prefix_id).await?;
+
+		if let Some(prefix_link) = prefix_link.filter(|_| passthrough(prefix_options.as_ref())) {
+			trace!("passing \"{rest}\" through to the link at \"{first}\"");
+			let prefix_link = choose_destination(
+				prefix_link,
+				prefix_options.as_ref(),
+				&config,
+				&mut stat_info,
+				&hints,
+			);
+			link = append_path(&prefix_link, rest);
+			id = prefix_id;
+			vanity = prefix_vanity;
+			options = prefix_options;
+		}
+	}
 
-	let res = if let Some(link) = link.clone() {
-		let link = link.into_string();
+	// Fixed query parameters are added once the destination is fully resolved,
+	// keeping the destination as-is if that doesn't result in a valid link
+	if let Some(params) = options
+		.as_ref()
+		.and_then(|options| options.query_params.as_ref())
+	{
+		link = link.map(|link| append_query(&link, params).unwrap_or(link));
+	}
 
-		res = res.header("Location", &link);
-		res = res.header("Link-Id", &id.unwrap().to_string());
+	// Links to blocked domains (e.g. created before the domain was blocked)
+	// are treated as if they didn't exist
+	if let Some(ref blocklist) = config.blocklist {
+		if link.as_ref().is_some_and(|link| blocklist.is_blocked(link)) {
+			debug!("link destination is blocked");
+			link = None;
+		}
+	}
+
+	// Links starting a redirect loop or a too long chain of links on this
+	// server (e.g. after a vanity path was changed) are treated the same way
+	if let (Some(ref loop_detection), Some(id), Some(ref destination)) =
+		(&config.loop_detection, id, &link)
+	{
+		if loop_detection.is_loop(&store, id, destination).await? {
+			debug!("link destination causes a redirect loop");
+			link = None;
+		}
+	}
+
+	// Disabled links (including ones with destinations flagged by reputation
+	// checks) are treated as if they didn't exist
+	if link.is_some() && options.as_ref().is_some_and(LinkOptions::is_disabled) {
+		debug!("link is disabled");
+		link = None;
+	}
+
+	// Per-link CORS settings override the global ones
+	let cors = options
+		.as_ref()
+		.and_then(|options| options.cors.as_ref())
+		.unwrap_or(&config.cors);
+
+	if cors.is_enabled() {
+		res = res.header("Vary", "Origin");
+
+		if let Some(origin) = req
+			.headers()
+			.get("Origin")
+			.and_then(|origin| origin.to_str().ok())
+			.and_then(|origin| cors.allow_origin(origin))
+		{
+			res = res.header("Access-Control-Allow-Origin", origin);
+			res = res.header("Access-Control-Expose-Headers", "Link-Id, Location");
+		}
+
+		// Respond to CORS preflight requests directly, without redirecting
+		if req.method() == Method::OPTIONS {
+			if let Some(method) = req.headers().get("Access-Control-Request-Method") {
+				if method.to_str().is_ok_and(|m| cors.allows_method(m)) {
+					res = res.header("Access-Control-Allow-Methods", cors.allow_methods());
+					res = res.header("Access-Control-Max-Age", "86400");
+				}
+
+				let res = res.status(StatusCode::NO_CONTENT).body(String::new())?;
+
+				trace!(?res);
+				let span = tracing::Span::current();
+				span.record("time_ns", redirect_start.elapsed().as_nanos());
+				span.record("status_code", res.status().as_u16());
+				debug!("CORS preflight request processed");
+
+				return Ok(res);
+			}
+		}
+	}
+
+	let mut expired = options.as_ref().is_some_and(LinkOptions::is_expired);
+
+	// Links with a maximum number of clicks expire once they are used up. Only
+	// redirects are counted (not previews), but links whose redirect was
+	// already removed are still reported as expired after that. If the clicks
+	// can't be counted (e.g. because the store backend doesn't support it),
+	// the link is redirected anyway.
+	if let (false, Some(id), Some(max_clicks)) = (
+		expired,
+		id,
+		options.as_ref().and_then(|options| options.max_clicks),
+	) {
+		let counted = link.is_some() && !preview;
+		match store.incr_clicks(id, u64::from(counted)).await {
+			Ok(clicks) if counted => expired = clicks > max_clicks,
+			Ok(clicks) => expired = clicks >= max_clicks,
+			Err(err) => warn!(?err, %id, "link clicks could not be counted"),
+		}
+	}
+
+	// Expired links are not redirected, and their redirects are lazily removed
+	if expired {
+		if let (Some(id), Some(_)) = (id, link.take()) {
+			trace!("link has expired, removing its redirect");
+			let store = store.clone();
+			spawn(async move {
+				if let Err(err) = store.rem_redirect_soft(id).await {
+					debug!(?err, %id, "expired redirect removal failed");
+				}
+			});
+		}
+	}
+
+	let interstitial = options
+		.as_ref()
+		.and_then(|options| options.interstitial)
+		.unwrap_or(false);
+
+	// Unknown paths can be looked up elsewhere (e.g. in an intranet search),
+	// while everything else which doesn't match a link uses the default
+	let fallback = config
+		.search_fallback
+		.as_deref()
+		.filter(|_| id.is_none() && !reserved && !id_or_vanity.is_empty())
+		.and_then(|template| search_link(template, &id_or_vanity))
+		.or_else(|| config.default_destination.clone());
+
+	let res = if let (Some(link), Some(id), true) = (&link, id, preview) {
+		res = res.header(
+			HeaderName::from_static("link-id"),
+			HeaderValue::from_bytes(&id.to_ascii())?,
+		);
 
 		if config.send_csp {
 			res = res.header(
-				"Content-Security-Policy",
-				concat!(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
+					"default-src 'none'; style-src ",
+					csp_hashes!("preview", "style"),
+					"; sandbox allow-top-navigation"
+				)),
+			);
+		}
+
+		res = res.status(StatusCode::OK);
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
+		res.body(link_page(include_html!("preview"), link.as_str()))?
+	} else if let (Some(link), Some(id), true) = (&link, id, interstitial) {
+		anomaly::record(id);
+
+		res = res.header(
+			HeaderName::from_static("link-id"),
+			HeaderValue::from_bytes(&id.to_ascii())?,
+		);
+
+		// Custom templates may need anything, so only the built-in page gets a
+		// CSP, which is not sandboxed so that its meta refresh works
+		if config.send_csp && config.interstitial_template.is_none() {
+			res = res.header(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
+					"default-src 'none'; style-src ",
+					csp_hashes!("interstitial", "style")
+				)),
+			);
+		}
+
+		let template = config
+			.interstitial_template
+			.as_deref()
+			.unwrap_or(include_html!("interstitial"))
+			.replace("{{DELAY}}", &config.interstitial_delay.to_string());
+
+		res = res.status(StatusCode::OK);
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
+		res.body(link_page(&template, link.as_str()))?
+	} else if let (Some(link), Some(id)) = (&link, id) {
+		anomaly::record(id);
+
+		res = res.header(LOCATION, link.as_str());
+		res = res.header(
+			HeaderName::from_static("link-id"),
+			HeaderValue::from_bytes(&id.to_ascii())?,
+		);
+
+		if config.send_csp {
+			res = res.header(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
 					"default-src 'none'; style-src ",
 					csp_hashes!("redirect", "style"),
 					"; sandbox allow-top-navigation"
-				),
+				)),
 			);
 		}
 
-		if req.method() == Method::GET {
-			res = res.status(StatusCode::FOUND);
-		} else {
-			res = res.status(StatusCode::TEMPORARY_REDIRECT);
+		res = res.status(config.redirect_status.status_code(req.method()));
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
+		res.body(link_page(include_html!("redirect"), link.as_str()))?
+	} else if expired && config.expired_status == ExpiredStatus::Gone {
+		res = res.status(config.expired_status.status_code());
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
+
+		if config.send_csp {
+			res = res.header(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
+					"default-src 'none'; style-src ",
+					csp_hashes!("expired", "style"),
+					"; sandbox allow-top-navigation"
+				)),
+			);
 		}
 
-		res = res.header("Content-Type", "text/html; charset=UTF-8");
-		res.body(
-			include_html!("redirect")
-				.to_string()
-				.replace("{{LINK_URL}}", &link),
-		)?
+		res.body(include_html!("expired").to_string())?
+	} else if let Some(ref destination) = fallback {
+		trace!(%destination, "no link found, using the fallback destination");
+		res = res.header(LOCATION, destination.as_str());
+
+		if config.send_csp {
+			res = res.header(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
+					"default-src 'none'; style-src ",
+					csp_hashes!("redirect", "style"),
+					"; sandbox allow-top-navigation"
+				)),
+			);
+		}
+
+		res = res.status(config.redirect_status.status_code(req.method()));
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
+		res.body(link_page(include_html!("redirect"), destination.as_str()))?
 	} else {
 		res = res.status(StatusCode::NOT_FOUND);
-		res = res.header("Content-Type", "text/html; charset=UTF-8");
+		res = res.header(CONTENT_TYPE, HeaderValue::from_static(HTML_CONTENT_TYPE));
 
 		if config.send_csp {
 			res = res.header(
-				"Content-Security-Policy",
-				concat!(
+				CONTENT_SECURITY_POLICY,
+				HeaderValue::from_static(concat!(
 					"default-src 'none'; style-src ",
 					csp_hashes!("not-found", "style"),
 					"; sandbox allow-top-navigation"
-				),
+				)),
 			);
 		}
 
 		res.body(include_html!("not-found").to_string())?
 	};
 
+	if let Some(ref access_log) = config.access_log {
+		access_log.log(AccessLogEntry {
+			time: AccessLogEntry::now(),
+			method: req.method().as_str(),
+			host: req.uri().host().or_else(|| {
+				req.headers()
+					.get("host")
+					.and_then(|host| host.to_str().ok())
+			}),
+			path: req.uri().path(),
+			id,
+			vanity: vanity.as_ref(),
+			status: res.status().as_u16(),
+			time_ns: u64::try_from(redirect_start.elapsed().as_nanos()).unwrap_or(u64::MAX),
+			client_ip: stat_info.client_ip,
+		});
+	}
+
 	let id = id.map(Into::into);
 	let vanity = vanity.map(Into::into);
 
@@ -155,17 +748,14 @@ pub async fn redirector<B: Debug + Send + 'static>(
 	trace!(?res);
 	let span = tracing::Span::current();
 	span.record("time_ns", redirect_time.as_nanos());
-	span.record(
-		"link",
-		link.map_or_else(|| "[none]".to_string(), |link| link.to_string()),
-	);
+	span.record("link", link.as_ref().map_or("[none]", Link::as_str));
 	span.record(
 		"id",
-		id.map_or_else(|| "[none]".to_string(), |id| id.to_string()),
+		field::display(id.as_ref().map_or(&"[none]" as &dyn Display, |id| id)),
 	);
 	span.record(
 		"vanity",
-		vanity.map_or_else(|| "[none]".to_string(), |vanity| vanity.to_string()),
+		field::display(vanity.as_ref().map_or(&"[none]" as &dyn Display, |v| v)),
 	);
 	span.record("status_code", res.status().as_u16());
 
@@ -182,7 +772,7 @@ pub async fn redirector<B: Debug + Send + 'static>(
 #[instrument(level = "debug", name = "redirect-https", skip_all, fields(http.version = ?req.version(), http.host = %req.uri().host().unwrap_or_else(|| req.headers().get("host").map_or_else(|| "[unknown]", |h| h.to_str().unwrap_or("[unknown]"))), http.path = ?req.uri().path(), http.method = %req.method(), time_ns = Empty, link = Empty, status_code = Empty))]
 pub async fn https_redirector<B: Debug + Send + 'static>(
 	req: Request<B>,
-	config: Config,
+	config: Arc<Config>,
 ) -> Result<Response<String>, anyhow::Error> {
 	let redirect_start = Instant::now();
 	trace!(?req);
@@ -196,6 +786,9 @@ pub async fn https_redirector<B: Debug + Send + 'static>(
 	if config.send_alt_svc {
 		res = res.header("Alt-Svc", "h2=\":443\"; ma=31536000");
 	}
+	for (name, value) in &*config.extra_headers {
+		res = res.header(name.clone(), value.clone());
+	}
 
 	let p_and_q = req.uri().path_and_query().map_or("/", PathAndQuery::as_str);
 	let (res, link) = if let Some(Ok(host)) = req.headers().get("host").map(HeaderValue::to_str) {
@@ -262,3 +855,99 @@ pub async fn https_redirector<B: Debug + Send + 'static>(
 
 	Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn append_paths() {
+		let link = Link::new("https://docs.example.com").unwrap();
+		assert_eq!(
+			append_path(&link, "installation").unwrap().as_str(),
+			"https://docs.example.com/installation"
+		);
+		assert_eq!(append_path(&link, "").unwrap(), link);
+
+		let link = Link::new("https://example.com/docs/").unwrap();
+		assert_eq!(
+			append_path(&link, "guide/setup").unwrap().as_str(),
+			"https://example.com/docs/guide/setup"
+		);
+
+		let link = Link::new("https://example.com/docs?lang=en#top").unwrap();
+		assert_eq!(
+			append_path(&link, "faq").unwrap().as_str(),
+			"https://example.com/docs/faq?lang=en#top"
+		);
+	}
+
+	#[test]
+	fn search_links() {
+		let template = "https://intranet.example.com/search?q={path}";
+		assert_eq!(
+			search_link(template, "example").unwrap().as_str(),
+			"https://intranet.example.com/search?q=example"
+		);
+		assert_eq!(
+			search_link(template, "team/on call&more").unwrap().as_str(),
+			"https://intranet.example.com/search?q=team%2Fon+call%26more"
+		);
+		assert_eq!(
+			search_link("https://example.com/", "example")
+				.unwrap()
+				.as_str(),
+			"https://example.com/"
+		);
+		assert!(search_link("not a url {path}", "example").is_none());
+	}
+
+	#[test]
+	fn append_queries() {
+		let params = BTreeMap::from([
+			("utm_source".to_string(), "links".to_string()),
+			("utm_campaign".to_string(), "spring sale".to_string()),
+		]);
+
+		let link = Link::new("https://example.com/").unwrap();
+		assert_eq!(
+			append_query(&link, &params).unwrap().as_str(),
+			"https://example.com/?utm_campaign=spring+sale&utm_source=links"
+		);
+
+		let link = Link::new("https://example.com/page?utm_source=other&a=1#top").unwrap();
+		assert_eq!(
+			append_query(&link, &params).unwrap().as_str(),
+			"https://example.com/page?utm_source=other&a=1&utm_campaign=spring+sale#top"
+		);
+
+		let link = Link::new("https://example.com/?").unwrap();
+		assert_eq!(
+			append_query(&link, &params).unwrap().as_str(),
+			"https://example.com/?utm_campaign=spring+sale&utm_source=links"
+		);
+
+		let link = Link::new("https://example.com/?utm_source=a&utm_campaign=b").unwrap();
+		assert_eq!(append_query(&link, &params).unwrap(), link);
+		assert_eq!(append_query(&link, &BTreeMap::new()).unwrap(), link);
+	}
+
+	#[test]
+	fn client_hashes() {
+		let a = Link::new("https://example.com/a").unwrap();
+		let b = Link::new("https://example.com/b").unwrap();
+		let ip = Some(IpAddr::from([192, 0, 2, 1]));
+		let hints = ClientHints {
+			user_agent: Some("Mozilla/5.0"),
+			..Default::default()
+		};
+
+		assert_eq!(client_hash(&a, ip, &hints), client_hash(&a, ip, &hints));
+		assert_ne!(client_hash(&a, ip, &hints), client_hash(&b, ip, &hints));
+		assert_ne!(client_hash(&a, ip, &hints), client_hash(&a, None, &hints));
+		assert_ne!(
+			client_hash(&a, ip, &hints),
+			client_hash(&a, ip, &ClientHints::default())
+		);
+	}
+}
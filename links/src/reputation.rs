@@ -0,0 +1,494 @@
+//! Reputation checks of link destinations
+//!
+//! When enabled (via the `reputation_checks` configuration option), the
+//! destinations of all redirects in the store are periodically checked by a
+//! [`DestinationCheck`], currently using the [Google Safe Browsing] Lookup API.
+//! Links whose destinations are flagged (e.g. because the destination website
+//! was compromised and now hosts malware) get their `flagged` [link
+//! option][crate::options] set to the reason they were flagged for, which
+//! disables them, so that they don't keep sending visitors to an unsafe
+//! website. A warning is also logged for every newly flagged link.
+//!
+//! Flagged links are returned as such by the APIs (e.g. in the `flagged` field
+//! of `GetRedirect` responses), and can be re-enabled by setting their options
+//! without `flagged`. Links are never un-flagged automatically, but if a
+//! re-enabled link's destination is still unsafe, it will be flagged again by
+//! the next check.
+//!
+//! Checks run in the background, independently of redirects, so they never
+//! slow down redirects, and only the destination URLs (never any information
+//! about visitors) are sent to the reputation service. Only redirects in the
+//! default store namespace are checked.
+//!
+//! [Google Safe Browsing]: https://developers.google.com/safe-browsing/v4/lookup-api
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::LazyLock,
+	time::Duration,
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use links_normalized::Link;
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+use crate::{
+	config::Config,
+	store::{Current, Store},
+	util::{SERVER_NAME, VERSION},
+};
+
+/// How often to check whether reputation checks were enabled while they are
+/// disabled
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The number of links checked at once, which every [`DestinationCheck`] must
+/// support
+const PAGE_SIZE: usize = 500;
+
+/// The timeout of each reputation service request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The Google Safe Browsing Lookup API endpoint
+const SAFE_BROWSING_ENDPOINT: &str = "https://safebrowsing.googleapis.com/v4/threatMatches:find";
+
+/// The HTTP client used for all reputation service requests
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+	Client::builder()
+		.user_agent(SERVER_NAME)
+		.timeout(REQUEST_TIMEOUT)
+		.build()
+		.expect("the reputation check HTTP client configuration is valid")
+});
+
+/// A check of the reputation of link destinations, e.g. using an external
+/// URL reputation service
+#[async_trait]
+pub trait DestinationCheck: Debug + Send + Sync {
+	/// Check the `links` (up to 500 at once), returning why each of them is
+	/// flagged (in the same order), or `None` for links which aren't flagged
+	///
+	/// # Errors
+	/// Returns an error if the check can not be completed, e.g. because the
+	/// reputation service is unreachable
+	async fn check(&self, links: &[Link]) -> Result<Vec<Option<String>>>;
+}
+
+/// Settings for reputation checks of link destinations.
+///
+/// Reputation checks are disabled if `interval` is `0` (the default), or if
+/// no reputation service is configured (i.e. `safe_browsing_key` is empty).
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReputationChecks {
+	/// How often the destinations of all links are checked (in seconds), or
+	/// `0` to disable reputation checks
+	pub interval: u64,
+	/// The API key used for the Google Safe Browsing Lookup API
+	pub safe_browsing_key: String,
+}
+
+impl ReputationChecks {
+	/// Get the destination check configured by these settings, or `None` if
+	/// reputation checks are disabled
+	#[must_use]
+	pub fn checker(&self) -> Option<Box<dyn DestinationCheck>> {
+		if self.interval == 0 || self.safe_browsing_key.is_empty() {
+			return None;
+		}
+
+		Some(Box::new(SafeBrowsing::new(self.safe_browsing_key.clone())))
+	}
+}
+
+impl Debug for ReputationChecks {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("ReputationChecks")
+			.field("interval", &self.interval)
+			.field(
+				"safe_browsing_key",
+				&Some("[redacted]").filter(|_| !self.safe_browsing_key.is_empty()),
+			)
+			.finish()
+	}
+}
+
+/// Continuously check the destinations of all links in the `store` according
+/// to the `config`, flagging the links with unsafe destinations. This never
+/// returns, and should be spawned as a background task.
+pub async fn run(config: &'static Config, store: &'static Current) -> ! {
+	loop {
+		let settings = config.reputation_checks();
+
+		let Some(checker) = settings.checker() else {
+			sleep(DISABLED_POLL_INTERVAL).await;
+			continue;
+		};
+
+		match check_all(&store.get(), &*checker).await {
+			Ok(flagged) => debug!(flagged, "Link destination reputation check completed"),
+			Err(err) => warn!(%err, "Link destination reputation check failed"),
+		}
+
+		sleep(Duration::from_secs(settings.interval)).await;
+	}
+}
+
+/// Check the destinations of all links in the `store` using the `checker`,
+/// setting the `flagged` option of links whose destinations are flagged.
+/// Returns the number of flagged links.
+///
+/// # Errors
+/// Returns an error if the check or a store operation fails
+pub async fn check_all(store: &Store, checker: &dyn DestinationCheck) -> Result<usize> {
+	let mut cursor = None;
+	let mut flagged = 0;
+
+	loop {
+		let page = store.list_redirects(cursor, PAGE_SIZE).await?;
+		let links = page
+			.items
+			.iter()
+			.map(|(_, link)| link.clone())
+			.collect::<Vec<_>>();
+
+		let reasons = checker.check(&links).await?;
+		if reasons.len() != links.len() {
+			bail!("the destination check returned the wrong number of results");
+		}
+
+		for ((id, link), reason) in page.items.iter().zip(reasons) {
+			let Some(reason) = reason else {
+				continue;
+			};
+
+			flagged += 1;
+			let mut options = store.get_options(*id).await?.unwrap_or_default();
+			if options.flagged.as_ref() == Some(&reason) {
+				continue;
+			}
+
+			warn!(
+				%id,
+				%link,
+				%reason,
+				"The destination of link {id} was flagged as {reason}, disabling the link"
+			);
+
+			options.flagged = Some(reason);
+			store.set_options(*id, options).await?;
+		}
+
+		match page.next {
+			Some(next) => cursor = Some(next),
+			None => return Ok(flagged),
+		}
+	}
+}
+
+/// A destination check using the [Google Safe Browsing] Lookup API (v4). Links
+/// are flagged with the type of threat they were found to be, e.g. `MALWARE`
+/// or `SOCIAL_ENGINEERING`.
+///
+/// [Google Safe Browsing]: https://developers.google.com/safe-browsing/v4/lookup-api
+pub struct SafeBrowsing {
+	/// The API key
+	key: String,
+}
+
+impl SafeBrowsing {
+	/// The types of threats which links are checked for
+	const THREAT_TYPES: [&'static str; 4] = [
+		"MALWARE",
+		"SOCIAL_ENGINEERING",
+		"UNWANTED_SOFTWARE",
+		"POTENTIALLY_HARMFUL_APPLICATION",
+	];
+
+	/// Create a new Safe Browsing destination check using the API `key`
+	#[must_use]
+	pub const fn new(key: String) -> Self {
+		Self { key }
+	}
+}
+
+impl Debug for SafeBrowsing {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("SafeBrowsing").finish_non_exhaustive()
+	}
+}
+
+#[async_trait]
+impl DestinationCheck for SafeBrowsing {
+	async fn check(&self, links: &[Link]) -> Result<Vec<Option<String>>> {
+		if links.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let body = serde_json::to_string(&ThreatMatchesRequest {
+			client: ClientInfo {
+				client_id: "links",
+				client_version: VERSION,
+			},
+			threat_info: ThreatInfo {
+				threat_types: &Self::THREAT_TYPES,
+				platform_types: &["ANY_PLATFORM"],
+				threat_entry_types: &["URL"],
+				threat_entries: links
+					.iter()
+					.map(|link| ThreatEntry { url: link.as_str() })
+					.collect(),
+			},
+		})?;
+
+		let url = format!(
+			"{SAFE_BROWSING_ENDPOINT}?{}",
+			form_urlencoded::Serializer::new(String::new())
+				.append_pair("key", &self.key)
+				.finish()
+		);
+
+		let res = CLIENT
+			.post(url)
+			.header(CONTENT_TYPE, "application/json")
+			.body(body)
+			.send()
+			.await?
+			.error_for_status()?;
+
+		let res = serde_json::from_slice::<ThreatMatchesResponse>(&res.bytes().await?)?;
+		Ok(threat_types(links, res))
+	}
+}
+
+/// Get the threat type of each of the `links` from the Safe Browsing `res`
+fn threat_types(links: &[Link], res: ThreatMatchesResponse) -> Vec<Option<String>> {
+	let mut types = vec![None; links.len()];
+
+	for threat_match in res.matches {
+		for (link, threat_type) in links.iter().zip(&mut types) {
+			if link.as_str() == threat_match.threat.url && threat_type.is_none() {
+				*threat_type = Some(threat_match.threat_type.clone());
+			}
+		}
+	}
+
+	types
+}
+
+/// The body of a Safe Browsing `threatMatches:find` request
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatchesRequest<'a> {
+	/// Information about the client making the request
+	client: ClientInfo,
+	/// The links to check and the threats to check them for
+	threat_info: ThreatInfo<'a>,
+}
+
+/// Information about the Safe Browsing API client
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientInfo {
+	/// The name of the client
+	client_id: &'static str,
+	/// The version of the client
+	client_version: &'static str,
+}
+
+/// The links to check and the threats to check them for
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatInfo<'a> {
+	/// The types of threats to check for
+	threat_types: &'static [&'static str],
+	/// The platforms to check for threats on
+	platform_types: &'static [&'static str],
+	/// The types of the entries to check
+	threat_entry_types: &'static [&'static str],
+	/// The entries (links) to check
+	threat_entries: Vec<ThreatEntry<'a>>,
+}
+
+/// A link checked for threats
+#[derive(Debug, Serialize)]
+struct ThreatEntry<'a> {
+	/// The URL of the link
+	url: &'a str,
+}
+
+/// The response body of a Safe Browsing `threatMatches:find` request, which
+/// is empty if no threats were found
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ThreatMatchesResponse {
+	/// The threats found
+	matches: Vec<ThreatMatch>,
+}
+
+/// A threat found by Safe Browsing
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatch {
+	/// The type of the threat, e.g. `MALWARE`
+	threat_type: String,
+	/// The link this threat was found for
+	threat: ThreatUrl,
+}
+
+/// The link a threat was found for
+#[derive(Debug, Deserialize)]
+struct ThreatUrl {
+	/// The URL of the link
+	url: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use links_id::Id;
+
+	use super::*;
+	use crate::{options::LinkOptions, store::BackendType};
+
+	/// A destination check flagging all links containing "malware"
+	#[derive(Debug)]
+	struct MalwareCheck;
+
+	#[async_trait]
+	impl DestinationCheck for MalwareCheck {
+		async fn check(&self, links: &[Link]) -> Result<Vec<Option<String>>> {
+			Ok(links
+				.iter()
+				.map(|link| {
+					Some("MALWARE".to_string()).filter(|_| link.as_str().contains("malware"))
+				})
+				.collect())
+		}
+	}
+
+	#[tokio::test]
+	async fn check_all_links() {
+		let store = Store::new(BackendType::Memory, &HashMap::new())
+			.await
+			.unwrap();
+		let safe = Id::try_from("9dDbKpJP").unwrap();
+		let unsafe_ = Id::try_from("0fDbKpJP").unwrap();
+
+		store
+			.set_redirect(safe, Link::new("https://example.com/").unwrap())
+			.await
+			.unwrap();
+		store
+			.set_redirect(unsafe_, Link::new("https://example.com/malware").unwrap())
+			.await
+			.unwrap();
+		store
+			.set_options(unsafe_, LinkOptions {
+				max_clicks: Some(10),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+
+		assert_eq!(check_all(&store, &MalwareCheck).await.unwrap(), 1);
+		assert_eq!(store.get_options(safe).await.unwrap(), None);
+		assert_eq!(
+			store.get_options(unsafe_).await.unwrap(),
+			Some(LinkOptions {
+				max_clicks: Some(10),
+				flagged: Some("MALWARE".to_string()),
+				..Default::default()
+			})
+		);
+	}
+
+	#[test]
+	fn safe_browsing_request() {
+		let request = ThreatMatchesRequest {
+			client: ClientInfo {
+				client_id: "links",
+				client_version: "1.0.0",
+			},
+			threat_info: ThreatInfo {
+				threat_types: &["MALWARE"],
+				platform_types: &["ANY_PLATFORM"],
+				threat_entry_types: &["URL"],
+				threat_entries: vec![ThreatEntry {
+					url: "https://example.com/",
+				}],
+			},
+		};
+
+		assert_eq!(
+			serde_json::to_string(&request).unwrap(),
+			r#"{"client":{"clientId":"links","clientVersion":"1.0.0"},"threatInfo":{"threatTypes":["MALWARE"],"platformTypes":["ANY_PLATFORM"],"threatEntryTypes":["URL"],"threatEntries":[{"url":"https://example.com/"}]}}"#
+		);
+	}
+
+	#[test]
+	fn safe_browsing_response() {
+		let links = [
+			Link::new("https://example.com/").unwrap(),
+			Link::new("https://example.com/malware").unwrap(),
+			Link::new("https://example.com/phishing").unwrap(),
+		];
+
+		let res = serde_json::from_str::<ThreatMatchesResponse>(
+			r#"{"matches": [
+				{
+					"threatType": "MALWARE",
+					"platformType": "ANY_PLATFORM",
+					"threatEntryType": "URL",
+					"threat": {"url": "https://example.com/malware"},
+					"cacheDuration": "300s"
+				},
+				{
+					"threatType": "SOCIAL_ENGINEERING",
+					"platformType": "ANY_PLATFORM",
+					"threatEntryType": "URL",
+					"threat": {"url": "https://example.com/phishing"},
+					"cacheDuration": "300s"
+				}
+			]}"#,
+		)
+		.unwrap();
+
+		assert_eq!(threat_types(&links, res), [
+			None,
+			Some("MALWARE".to_string()),
+			Some("SOCIAL_ENGINEERING".to_string())
+		]);
+
+		let res = serde_json::from_str::<ThreatMatchesResponse>("{}").unwrap();
+		assert_eq!(threat_types(&links, res), [None, None, None]);
+	}
+
+	#[test]
+	fn checker() {
+		assert!(ReputationChecks::default().checker().is_none());
+		assert!(ReputationChecks {
+			interval: 3600,
+			safe_browsing_key: String::new(),
+		}
+		.checker()
+		.is_none());
+		assert!(ReputationChecks {
+			interval: 0,
+			safe_browsing_key: "key".to_string(),
+		}
+		.checker()
+		.is_none());
+		assert!(ReputationChecks {
+			interval: 3600,
+			safe_browsing_key: "key".to_string(),
+		}
+		.checker()
+		.is_some());
+	}
+}
@@ -24,49 +24,88 @@
 //! Handlers are responsible for all application logic. A handler is an async
 //! function called from an acceptor. There is one predefined handler for each
 //! kind of request: currently one external HTTP redirector, one HTTP to HTTPS
-//! redirector, and one RPC handler.
+//! redirector, one RPC handler, and one HTTP API handler.
 
 use std::{
+	collections::HashMap,
+	error::Error as StdError,
 	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
 	net::{IpAddr, Ipv6Addr, SocketAddr},
 	os::raw::c_int,
-	sync::Arc,
+	path::{Path, PathBuf},
+	pin::pin,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc, OnceLock,
+	},
 	thread,
+	time::Duration,
 };
 
-use hyper::{rt, server::conn::http2, service::service_fn, Request};
+use hyper::{
+	body::Incoming,
+	header::{HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, HOST},
+	rt,
+	server::conn::http2,
+	service::{service_fn, Service},
+	Request, Response, StatusCode,
+};
 use hyper_util::{
-	rt::{TokioExecutor, TokioIo},
+	rt::{TokioExecutor, TokioIo, TokioTimer},
 	server::conn::auto::Builder,
 	service::TowerToHyperService,
 };
+use links_domainmap::Domain as DomainName;
 use links_id::Id;
 use links_normalized::{Link, Normalized};
-use parking_lot::Mutex;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::{Mutex, RwLock};
+use ring::digest::{digest, SHA256};
 use socket2::{Domain, Protocol as SocketProtocol, Socket, Type};
 use strum::{Display as EnumDisplay, EnumString};
+#[cfg(unix)]
+use tokio::net::{unix::SocketAddr as UnixSocketAddr, UnixListener, UnixStream};
 use tokio::{
-	io::{AsyncRead, AsyncWrite, Error as IoError},
+	io::{AsyncRead, AsyncWrite, Error as IoError, ErrorKind},
 	net::{TcpListener, TcpStream},
-	spawn,
+	select, spawn,
+	sync::Mutex as AsyncMutex,
 	task::JoinHandle,
+	time::{sleep_until, timeout, Instant},
+};
+use tokio_rustls::{
+	rustls::{
+		crypto::ring::Ticketer,
+		server::{danger::ClientCertVerifier, Acceptor as ClientHelloAcceptor, ProducesTickets},
+		Error as RustlsError, ServerConfig,
+	},
+	server::TlsStream,
+	LazyConfigAcceptor,
 };
-use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+#[cfg(unix)]
+use tonic::transport::server::Connected;
 use tonic::{
 	codegen::{CompressionEncoding, InterceptedService},
 	service::Routes,
-	transport::Server as RpcServer,
+	transport::{server::TcpConnectInfo, Server as RpcServer},
 };
 use tower::util::ServiceExt;
 use tracing::{debug, error, trace, warn};
 
 use crate::{
-	api::{self, Api, LinksServer},
-	certs::CertificateResolver,
-	config::{Config, ListenAddress},
+	api::{self, Api, ClientCertificate, HealthApi, HealthServer, LinksServer},
+	certs::{self, CertificateResolver},
+	config::{
+		CertificateAcquisitionError, Config, HttpConnection, ListenAddress, RpcConnection,
+		TlsPolicy,
+	},
+	http_api::http_api,
+	metrics, proxy,
 	redirector::{https_redirector, redirector},
 	stats::ExtraStatisticInfo,
 	store::{Current, Store},
+	trace::to_hex,
 };
 
 /// Number of incoming connections that can be kept in the TCP socket backlog of
@@ -76,23 +115,214 @@ use crate::{
 /// [winsock docs]: https://learn.microsoft.com/en-us/windows/win32/api/winsock2/nf-winsock2-listen
 const LISTENER_TCP_BACKLOG_SIZE: c_int = 1024;
 
+/// The name of the header listing the compression encodings accepted by an RPC
+/// client
+const GRPC_ACCEPT_ENCODING: &str = "grpc-accept-encoding";
+
+/// The ALPN protocols offered by TLS acceptors, unless the [`TlsPolicy`] of a
+/// connection specifies others
+const DEFAULT_ALPN_PROTOCOLS: [&str; 2] = ["h2", "http/1.1"];
+
+/// Get the host that `req` was sent to, from its URI (e.g. HTTP/2's
+/// `:authority`) or its `Host` header
+fn request_host<B>(req: &Request<B>) -> Option<&str> {
+	req.uri()
+		.host()
+		.or_else(|| req.headers().get(HOST)?.to_str().ok())
+}
+
+/// Respond to requests for the reserved paths of redirector listeners, or
+/// return `None` for all other requests:
+/// - `/healthz` (liveness) is always `200 OK` while the server is running
+/// - `/readyz` (readiness) is `200 OK` if the `store` is healthy and, if any
+///   TLS listeners are configured, the `cert_resolver` has certificates to use,
+///   and `503 Service Unavailable` otherwise
+/// - `/metrics` returns all [metrics] in the Prometheus text format, but only
+///   if enabled via the `metrics` configuration option
+///
+/// # Errors
+/// This returns an error if the response can not be built
+pub async fn reserved_path<B>(
+	req: &Request<B>,
+	store: &Store,
+	config: &Config,
+	cert_resolver: &CertificateResolver,
+) -> Result<Option<Response<String>>, anyhow::Error> {
+	let (status, body) = match req.uri().path() {
+		"/metrics" if config.metrics() => {
+			return Ok(Some(
+				Response::builder()
+					.status(StatusCode::OK)
+					.header(CONTENT_TYPE, metrics::CONTENT_TYPE)
+					.header(CACHE_CONTROL, "no-store")
+					.body(metrics::render())?,
+			));
+		}
+		"/healthz" => (StatusCode::OK, "ok"),
+		"/readyz" => {
+			let tls = config
+				.listeners()
+				.iter()
+				.any(|l| matches!(l.protocol, Protocol::Https | Protocol::Grpcs));
+
+			if !store.health().await.healthy {
+				(StatusCode::SERVICE_UNAVAILABLE, "store unavailable")
+			} else if tls && !cert_resolver.has_certificates() {
+				(StatusCode::SERVICE_UNAVAILABLE, "no certificates")
+			} else {
+				(StatusCode::OK, "ok")
+			}
+		}
+		_ => return Ok(None),
+	};
+
+	trace!(%status, body, "health check request processed");
+
+	Ok(Some(
+		Response::builder()
+			.status(status)
+			.header(CONTENT_TYPE, "text/plain; charset=UTF-8")
+			.header(CACHE_CONTROL, "no-store")
+			.body(body.to_string())?,
+	))
+}
+
+/// Process an HTTP request using the `handler`, recording its [metrics]
+async fn record_request(
+	handler: impl Future<Output = Result<Response<String>, anyhow::Error>> + Send,
+) -> Result<Response<String>, anyhow::Error> {
+	let start = Instant::now();
+	let res = handler.await;
+
+	if let Ok(res) = &res {
+		metrics::record_http_request(res.status(), start.elapsed());
+	}
+
+	res
+}
+
+/// Serve HTTP requests on the connection in `stream` using the `service`.
+///
+/// The header read timeout, request timeout, and idle timeout are set up
+/// according to `settings`. Requests that exceed the request timeout are
+/// answered with `408 Request Timeout`. Connections that don't send a complete
+/// request within the header read timeout of being opened, or that exceed their
+/// idle timeout, are shut down gracefully, allowing in-progress requests to
+/// complete. Errors caused by these timeouts are only logged, not returned.
+async fn serve_http<S>(
+	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
+	settings: HttpConnection,
+	service: S,
+) -> Result<(), Box<dyn StdError + Send + Sync>>
+where
+	S: Service<Request<Incoming>, Response = Response<String>, Error = anyhow::Error>
+		+ Send
+		+ 'static,
+	S::Future: Send + 'static,
+{
+	let opened = Instant::now();
+	let last_request = Arc::new(Mutex::new(None));
+
+	let service = {
+		let last_request = Arc::clone(&last_request);
+		service_fn(move |req: Request<Incoming>| {
+			*last_request.lock() = Some(Instant::now());
+			let res = service.call(req);
+
+			async move {
+				let Some(request_timeout) = settings.request_timeout else {
+					return res.await;
+				};
+
+				if let Ok(res) = timeout(request_timeout, res).await {
+					res
+				} else {
+					debug!("HTTP request timed out");
+					Ok(Response::builder()
+						.status(StatusCode::REQUEST_TIMEOUT)
+						.header(CONTENT_TYPE, "text/plain; charset=UTF-8")
+						.body("Request timed out\n".to_string())?)
+				}
+			}
+		})
+	};
+
+	let mut builder = Builder::new(TokioExecutor::new());
+	builder
+		.http1()
+		.timer(TokioTimer::new())
+		.header_read_timeout(settings.header_read_timeout);
+	builder.http2().timer(TokioTimer::new());
+
+	let mut conn = pin!(builder.serve_connection(stream, service));
+
+	let mut timed_out = false;
+
+	let res = loop {
+		let last = *last_request.lock();
+		let deadline = match last {
+			Some(last) => settings.idle_timeout.map(|timeout| last + timeout),
+			None => [settings.header_read_timeout, settings.idle_timeout]
+				.into_iter()
+				.flatten()
+				.min()
+				.map(|timeout| opened + timeout),
+		};
+
+		let timer = sleep_until(deadline.unwrap_or_else(Instant::now));
+
+		select! {
+			res = conn.as_mut() => break res,
+			() = timer, if deadline.is_some() && !timed_out => {
+				// A request may have been received since the deadline was calculated
+				if *last_request.lock() == last {
+					debug!(requested = last.is_some(), "Gracefully closing timed out HTTP connection");
+					conn.as_mut().graceful_shutdown();
+					timed_out = true;
+				}
+			}
+		}
+	};
+
+	match res {
+		Err(err) if timed_out => {
+			debug!(?err, "HTTP connection timed out");
+			Ok(())
+		}
+		res => res,
+	}
+}
+
 /// A handler that does external HTTP redirects using information from the
 /// provided store. Extra information for statistics can be passed via
-/// `stat_info`.
+/// `stat_info`. Requests for the [reserved paths][reserved_path] are answered
+/// directly.
+///
+/// The redirector configuration is selected for each request based on its
+/// host (see [`Config::redirector_for`]).
 pub async fn http_handler(
 	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
 	store: Store,
 	config: &'static Config,
+	cert_resolver: Arc<CertificateResolver>,
 	stat_info: ExtraStatisticInfo,
 ) {
 	let redirector_service = service_fn(move |req: Request<_>| {
-		redirector(req, store.clone(), config.redirector(), stat_info.clone())
+		let redirector_config = config.redirector_for(request_host(&req));
+		let store = store.clone();
+		let cert_resolver = Arc::clone(&cert_resolver);
+		let stat_info = stat_info.clone();
+
+		record_request(async move {
+			if let Some(res) = reserved_path(&req, &store, config, &cert_resolver).await? {
+				return Ok(res);
+			}
+
+			redirector(req, store, redirector_config, stat_info).await
+		})
 	});
 
-	if let Err(err) = Builder::new(TokioExecutor::new())
-		.serve_connection(stream, redirector_service)
-		.await
-	{
+	if let Err(err) = serve_http(stream, config.http_connection(), redirector_service).await {
 		error!(?err, "Error while handling HTTP connection");
 	}
 }
@@ -107,54 +337,218 @@ pub async fn http_to_https_handler(
 	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
 	config: &'static Config,
 ) {
-	let redirector_service =
-		service_fn(move |req: Request<_>| https_redirector(req, config.redirector()));
+	let redirector_service = service_fn(move |req: Request<_>| {
+		let redirector_config = config.redirector_for(request_host(&req));
+		record_request(https_redirector(req, redirector_config))
+	});
+
+	if let Err(err) = serve_http(stream, config.http_connection(), redirector_service).await {
+		error!(?err, "Error while handling HTTP connection");
+	}
+}
+
+/// A handler for plaintext HTTP requests, which either redirects them to HTTPS
+/// or does external redirects.
+///
+/// Which one is done depends on the `https_redirect` setting of the redirector
+/// configuration for each request's host, like in [`http_to_https_handler`]
+/// and [`http_handler`] respectively. Requests for the [reserved
+/// paths][reserved_path] are always answered directly. Extra information for
+/// statistics can be passed via `stat_info`.
+pub async fn plain_http_handler(
+	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
+	store: Store,
+	config: &'static Config,
+	cert_resolver: Arc<CertificateResolver>,
+	stat_info: ExtraStatisticInfo,
+) {
+	let redirector_service = service_fn(move |req: Request<_>| {
+		let redirector_config = config.redirector_for(request_host(&req));
+		let store = store.clone();
+		let cert_resolver = Arc::clone(&cert_resolver);
+		let stat_info = stat_info.clone();
+
+		record_request(async move {
+			if let Some(res) = reserved_path(&req, &store, config, &cert_resolver).await? {
+				Ok(res)
+			} else if redirector_config.https_redirect {
+				https_redirector(req, redirector_config).await
+			} else {
+				redirector(req, store, redirector_config, stat_info).await
+			}
+		})
+	});
 
-	if let Err(err) = Builder::new(TokioExecutor::new())
-		.serve_connection(stream, redirector_service)
-		.await
-	{
+	if let Err(err) = serve_http(stream, config.http_connection(), redirector_service).await {
 		error!(?err, "Error while handling HTTP connection");
 	}
 }
 
+/// Handler processing HTTP API requests (see [`http_api`] for details)
+pub async fn http_api_handler(
+	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
+	store: Store,
+	config: &'static Config,
+) {
+	let api_service = service_fn(move |req: Request<_>| http_api(req, store.clone(), config));
+
+	if let Err(err) = serve_http(stream, config.http_connection(), api_service).await {
+		error!(?err, "Error while handling HTTP API connection");
+	}
+}
+
 /// Handler processing RPC API calls.
+///
+/// HTTP/2 keepalive pings, the idle timeout, and the maximum connection age are
+/// set up according to `settings`. Connections that exceed their idle timeout
+/// or maximum age are shut down gracefully, allowing in-progress calls to
+/// complete. The connection's addresses (in `connect_info`) and verified TLS
+/// `client_cert` (if any) are made available to the API in each request's
+/// extensions.
 pub async fn rpc_handler(
 	stream: impl rt::Read + rt::Write + Send + Unpin + 'static,
 	service: Routes,
+	settings: RpcConnection,
+	connect_info: impl Clone + Send + Sync + 'static,
+	client_cert: Option<ClientCertificate>,
 ) {
-	if let Err(rpc_err) = http2::Builder::new(TokioExecutor::new())
-		.serve_connection(
-			stream,
-			TowerToHyperService::new(
-				service.map_request(|req: Request<_>| req.map(tonic::body::boxed)),
-			),
-		)
-		.await
-	{
+	let opened = Instant::now();
+	let last_request = Arc::new(Mutex::new(opened));
+
+	let service = {
+		let last_request = Arc::clone(&last_request);
+		service.map_request(move |mut req: Request<_>| {
+			*last_request.lock() = Instant::now();
+			if !settings.zstd {
+				remove_accepted_encoding(req.headers_mut(), "zstd");
+			}
+			req.extensions_mut().insert(connect_info.clone());
+			if let Some(ref client_cert) = client_cert {
+				req.extensions_mut().insert(client_cert.clone());
+			}
+			req.map(tonic::body::boxed)
+		})
+	};
+
+	let mut conn = pin!(http2::Builder::new(TokioExecutor::new())
+		.timer(TokioTimer::new())
+		.keep_alive_interval(settings.keepalive_interval)
+		.keep_alive_timeout(settings.keepalive_timeout)
+		.serve_connection(stream, TowerToHyperService::new(service)));
+
+	let mut shutting_down = false;
+
+	let res = loop {
+		let deadline = [
+			settings.max_connection_age.map(|age| opened + age),
+			settings
+				.idle_timeout
+				.map(|timeout| *last_request.lock() + timeout),
+		]
+		.into_iter()
+		.flatten()
+		.min();
+
+		let timer = sleep_until(deadline.unwrap_or_else(Instant::now));
+
+		select! {
+			res = conn.as_mut() => break res,
+			() = timer, if deadline.is_some() && !shutting_down => {
+				let now = Instant::now();
+				let too_old = settings
+					.max_connection_age
+					.is_some_and(|age| now >= opened + age);
+				let idle = settings
+					.idle_timeout
+					.is_some_and(|timeout| now >= *last_request.lock() + timeout);
+
+				if too_old || idle {
+					debug!(too_old, idle, "Gracefully closing gRPC connection");
+					conn.as_mut().graceful_shutdown();
+					shutting_down = true;
+				}
+			}
+		}
+	};
+
+	if let Err(rpc_err) = res {
 		error!(?rpc_err, "Error while handling gRPC connection");
 	}
 }
 
+/// Check whether zstd compression is enabled for RPC connections to
+/// `local_addr` on a `protocol` listener
+fn zstd_enabled(config: &Config, protocol: Protocol, local_addr: SocketAddr) -> bool {
+	config
+		.rpc_zstd_listeners()
+		.iter()
+		.any(|l| l.protocol == protocol && l.socket_addr() == Some(local_addr))
+}
+
+/// Check whether connections to `local_addr` on a `protocol` listener start
+/// with a [PROXY protocol header][crate::proxy]
+fn proxy_protocol_enabled(config: &Config, protocol: Protocol, local_addr: SocketAddr) -> bool {
+	config
+		.proxy_protocol_listeners()
+		.iter()
+		.any(|l| l.protocol == protocol && l.socket_addr() == Some(local_addr))
+}
+
+/// Remove `encoding` from the encodings accepted by an RPC client in its
+/// `grpc-accept-encoding` header, so that responses are never compressed using
+/// that encoding
+fn remove_accepted_encoding(headers: &mut HeaderMap, encoding: &str) {
+	let Some(accepted) = headers
+		.get(GRPC_ACCEPT_ENCODING)
+		.and_then(|v| v.to_str().ok())
+	else {
+		return;
+	};
+
+	let accepted = accepted
+		.split(',')
+		.map(str::trim)
+		.filter(|e| !e.eq_ignore_ascii_case(encoding))
+		.collect::<Vec<_>>()
+		.join(",");
+
+	if let Ok(accepted) = HeaderValue::from_str(&accepted) {
+		headers.insert(GRPC_ACCEPT_ENCODING, accepted);
+	}
+}
+
 /// A trait for defining links server acceptors.
 ///
+/// Acceptors accept connections in streams of type `S`, coming from and to
+/// addresses of type `A` (a [`SocketAddr`] for TCP connections).
+///
 /// For more info about acceptors in general, please see the [module-level
 /// documentation][mod].
 ///
 /// [mod]: crate::server
 #[async_trait::async_trait]
-pub trait Acceptor<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>:
-	Send + Sync + 'static
+pub trait Acceptor<
+	S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+	A: Send + 'static = SocketAddr,
+>: Send + Sync + 'static
 {
 	/// Accept an incoming connection in `stream` from `remote_addr` to
 	/// `local_addr`. This function should [spawn a task][spawn] to handle the
 	/// request using this acceptor's associated handler.
 	///
 	/// [spawn]: tokio::task
-	async fn accept(&self, stream: S, local_addr: SocketAddr, remote_addr: SocketAddr);
+	async fn accept(&self, stream: S, local_addr: A, remote_addr: A);
 
 	/// Get the [`Protocol`] that this acceptor processes
 	fn protocol(&self) -> Protocol;
+
+	/// Check whether connections to `local_addr` start with a [PROXY protocol
+	/// header][crate::proxy], which the listener then reads before passing
+	/// the connection (from the client address in the header) to this
+	/// acceptor. By default, this is never the case.
+	fn proxy_protocol(&self, _local_addr: &A) -> bool {
+		false
+	}
 }
 
 /// An acceptor for plaintext (unencrypted) HTTP requests. Supports HTTP/1.0,
@@ -164,19 +558,26 @@ pub trait Acceptor<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>:
 pub struct PlainHttpAcceptor {
 	config: &'static Config,
 	current_store: &'static Current,
+	cert_resolver: Arc<CertificateResolver>,
 }
 
 impl PlainHttpAcceptor {
-	/// Create a new [`PlainHttpAcceptor`] with the provided [`Config`] and
-	/// [`Current`]
+	/// Create a new [`PlainHttpAcceptor`] with the provided [`Config`],
+	/// [`Current`], and reference-counted (via [`Arc`])
+	/// [`CertificateResolver`] (used only for readiness checks)
 	///
 	/// # Memory
 	/// This function leaks memory, and should therefore not be called an
 	/// unbounded number of times
-	pub fn new(config: &'static Config, current_store: &'static Current) -> &'static Self {
+	pub fn new(
+		config: &'static Config,
+		current_store: &'static Current,
+		cert_resolver: Arc<CertificateResolver>,
+	) -> &'static Self {
 		Box::leak(Box::new(Self {
 			config,
 			current_store,
+			cert_resolver,
 		}))
 	}
 }
@@ -186,41 +587,328 @@ impl Acceptor<TcpStream> for PlainHttpAcceptor {
 	async fn accept(&self, stream: TcpStream, local_addr: SocketAddr, remote_addr: SocketAddr) {
 		let config = self.config;
 		let current_store = self.current_store;
+		let cert_resolver = Arc::clone(&self.cert_resolver);
 
 		spawn(async move {
 			trace!("New plain connection from {remote_addr} on {local_addr}");
 
-			if config.https_redirect() {
-				http_to_https_handler(TokioIo::new(stream), config).await;
-			} else {
-				http_handler(
-					TokioIo::new(stream),
-					current_store.get(),
-					config,
-					ExtraStatisticInfo::default(),
-				)
-				.await;
-			}
+			let extra_info = ExtraStatisticInfo {
+				client_ip: Some(remote_addr.ip()),
+				..Default::default()
+			};
+
+			plain_http_handler(
+				TokioIo::new(stream),
+				current_store.get(),
+				config,
+				cert_resolver,
+				extra_info,
+			)
+			.await;
 		});
 	}
 
 	fn protocol(&self) -> Protocol {
 		Protocol::Http
 	}
+
+	fn proxy_protocol(&self, local_addr: &SocketAddr) -> bool {
+		proxy_protocol_enabled(self.config, Protocol::Http, *local_addr)
+	}
+}
+
+#[cfg(unix)]
+#[async_trait::async_trait]
+impl Acceptor<UnixStream, Arc<UnixSocketAddr>> for PlainHttpAcceptor {
+	async fn accept(
+		&self,
+		stream: UnixStream,
+		local_addr: Arc<UnixSocketAddr>,
+		remote_addr: Arc<UnixSocketAddr>,
+	) {
+		let config = self.config;
+		let current_store = self.current_store;
+		let cert_resolver = Arc::clone(&self.cert_resolver);
+
+		spawn(async move {
+			trace!("New plain connection from {remote_addr:?} on {local_addr:?}");
+
+			// Unix domain socket peers don't have an IP address, the client's
+			// one can only come from a reverse proxy (e.g. `X-Forwarded-For`)
+			plain_http_handler(
+				TokioIo::new(stream),
+				current_store.get(),
+				config,
+				cert_resolver,
+				ExtraStatisticInfo::default(),
+			)
+			.await;
+		});
+	}
+
+	fn protocol(&self) -> Protocol {
+		Protocol::HttpUnix
+	}
+}
+
+/// The TLS server configurations used by a TLS acceptor, one for each
+/// [`TlsPolicy`] used by its connections (combining the policies of the domains
+/// in its [`CertificateResolver`] with those of its listeners), built when
+/// first needed
+struct TlsConfigs {
+	cert_resolver: Arc<CertificateResolver>,
+	ticketer: Arc<SessionTicketer>,
+	client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+	configs: Mutex<HashMap<TlsPolicy, Option<Arc<ServerConfig>>>>,
+}
+
+impl TlsConfigs {
+	/// Create new [`TlsConfigs`] using certificates from the `cert_resolver`,
+	/// session tickets from the `ticketer`, and requiring client certificates
+	/// accepted by the `client_verifier` (if any)
+	fn new(
+		cert_resolver: Arc<CertificateResolver>,
+		ticketer: Arc<SessionTicketer>,
+		client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+	) -> Self {
+		Self {
+			cert_resolver,
+			ticketer,
+			client_verifier,
+			configs: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Build a [`ServerConfig`] applying the TLS `policy`
+	fn build(&self, policy: &TlsPolicy) -> Result<ServerConfig, RustlsError> {
+		let builder = ServerConfig::builder_with_provider(Arc::new(policy.crypto_provider()?))
+			.with_protocol_versions(policy.protocol_versions())?;
+		let builder = match self.client_verifier {
+			None => builder.with_no_client_auth(),
+			Some(ref verifier) => builder.with_client_cert_verifier(Arc::clone(verifier)),
+		};
+
+		let mut server_config = builder.with_cert_resolver(self.cert_resolver.clone());
+		server_config.ticketer = self.ticketer.clone();
+		server_config.alpn_protocols = policy
+			.alpn
+			.clone()
+			.unwrap_or_else(|| DEFAULT_ALPN_PROTOCOLS.map(String::from).to_vec())
+			.into_iter()
+			.map(String::into_bytes)
+			.collect();
+
+		Ok(server_config)
+	}
+
+	/// Get the [`ServerConfig`] for connections to `server_name` (the SNI sent
+	/// by the client, if any) on a listener with the `listener_policy`, or
+	/// `None` if the combined TLS policy is invalid
+	fn get(
+		&self,
+		server_name: Option<&str>,
+		listener_policy: &TlsPolicy,
+	) -> Option<Arc<ServerConfig>> {
+		let domain = server_name.map(DomainName::reference).and_then(Result::ok);
+		let policy = self
+			.cert_resolver
+			.policy(domain.as_ref())
+			.or(listener_policy);
+
+		self.configs
+			.lock()
+			.entry(policy)
+			.or_insert_with_key(|policy| match self.build(policy) {
+				Ok(server_config) => Some(Arc::new(server_config)),
+				Err(err) => {
+					error!(%err, ?policy, "Invalid TLS policy, rejecting TLS connections using it");
+					None
+				}
+			})
+			.clone()
+	}
+
+	/// Accept a TLS connection in `stream`, using the server configuration for
+	/// the domain requested by the client (falling back to the settings of the
+	/// `listener_policy`), and recording handshake [metrics]
+	async fn accept(
+		&self,
+		stream: TcpStream,
+		listener_policy: &TlsPolicy,
+	) -> Result<TlsStream<TcpStream>, IoError> {
+		let start = Instant::now();
+		let res = self.handshake(stream, listener_policy).await;
+		metrics::record_tls_handshake(res.is_ok(), start.elapsed());
+		res
+	}
+
+	/// Perform the TLS handshake for [`accept`][Self::accept]
+	async fn handshake(
+		&self,
+		stream: TcpStream,
+		listener_policy: &TlsPolicy,
+	) -> Result<TlsStream<TcpStream>, IoError> {
+		let start = LazyConfigAcceptor::new(ClientHelloAcceptor::default(), stream).await?;
+		let server_name = start.client_hello().server_name().map(ToString::to_string);
+
+		let Some(server_config) = self.get(server_name.as_deref(), listener_policy) else {
+			return Err(IoError::other(format!(
+				"invalid TLS policy for {server_name:?}"
+			)));
+		};
+
+		start.into_stream(server_config).await
+	}
+}
+
+/// The maximum interval between rotations of the session ticket encryption
+/// keys in seconds. Each key is a rustls [`Ticketer`], which also rotates its
+/// own keys this often.
+const MAX_TICKET_ROTATION: u64 = 6 * 60 * 60;
+
+/// The producer of TLS session tickets, shared by all TLS acceptors, so that
+/// sessions can be resumed on any TLS listener.
+///
+/// The ticket encryption key is replaced every `tls_ticket_rotation` seconds
+/// (which is read from the [`Config`] on every use, so changes apply
+/// immediately). Tickets encrypted with the previous key are accepted until
+/// the next rotation, after which that key is dropped, limiting how long a
+/// leaked key could be used to decrypt recorded sessions. If the interval is
+/// `0`, no session tickets are issued.
+pub struct SessionTicketer {
+	config: &'static Config,
+	keys: RwLock<TicketKeys>,
+}
+
+/// The session ticket encryption keys of a [`SessionTicketer`]
+struct TicketKeys {
+	/// The key used to encrypt new tickets, if one was generated
+	current: Option<Arc<dyn ProducesTickets>>,
+	/// The key replaced by `current`, whose tickets are still accepted
+	previous: Option<Arc<dyn ProducesTickets>>,
+	/// When `current` was generated
+	rotated: Instant,
+}
+
+impl SessionTicketer {
+	/// Create a new [`SessionTicketer`], rotating its keys on the interval
+	/// configured in the [`Config`]
+	#[must_use]
+	pub fn new(config: &'static Config) -> Arc<Self> {
+		Arc::new(Self {
+			config,
+			keys: RwLock::new(TicketKeys {
+				current: None,
+				previous: None,
+				rotated: Instant::now(),
+			}),
+		})
+	}
+
+	/// Get the configured key rotation interval, or `None` if session tickets
+	/// are disabled
+	fn interval(&self) -> Option<Duration> {
+		match self.config.tls_ticket_rotation() {
+			0 => None,
+			secs => Some(Duration::from_secs(secs.min(MAX_TICKET_ROTATION))),
+		}
+	}
+
+	/// Get the current and previous keys, rotating them first if the current
+	/// key is older than the rotation interval. Returns `None` if session
+	/// tickets are disabled or no key could be generated.
+	fn keys(&self) -> Option<(Arc<dyn ProducesTickets>, Option<Arc<dyn ProducesTickets>>)> {
+		let interval = self.interval()?;
+
+		let keys = self.keys.read();
+		if let Some(ref current) = keys.current {
+			if keys.rotated.elapsed() < interval {
+				return Some((Arc::clone(current), keys.previous.clone()));
+			}
+		}
+		drop(keys);
+
+		let mut keys = self.keys.write();
+		let elapsed = keys.rotated.elapsed();
+		if keys.current.is_none() || elapsed >= interval {
+			debug!("Rotating session ticket keys");
+
+			// Tickets of a key which should have been rotated out already are
+			// not accepted anymore
+			if let Err(err) = Self::rotate(&mut keys, elapsed >= interval * 2) {
+				error!(%err, "Could not generate a new session ticket key");
+				return None;
+			}
+		}
+
+		keys.current
+			.clone()
+			.map(|current| (current, keys.previous.clone()))
+	}
+
+	/// Replace the current key in `keys` with a newly generated one, keeping
+	/// the replaced key as the previous key unless it is `expired`
+	fn rotate(keys: &mut TicketKeys, expired: bool) -> Result<(), RustlsError> {
+		let current = Ticketer::new()?;
+		let previous = keys.current.take().filter(|_| !expired);
+
+		*keys = TicketKeys {
+			current: Some(current),
+			previous,
+			rotated: Instant::now(),
+		};
+
+		Ok(())
+	}
+}
+
+impl ProducesTickets for SessionTicketer {
+	fn enabled(&self) -> bool {
+		self.interval().is_some()
+	}
+
+	fn lifetime(&self) -> u32 {
+		self.interval().map_or(0, |interval| {
+			u32::try_from(interval.as_secs()).unwrap_or(u32::MAX)
+		})
+	}
+
+	fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+		self.keys()?.0.encrypt(plain)
+	}
+
+	fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+		let (current, previous) = self.keys()?;
+		current
+			.decrypt(cipher)
+			.or_else(|| previous?.decrypt(cipher))
+	}
+}
+
+impl Debug for SessionTicketer {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("SessionTicketer")
+			.field("interval", &self.interval())
+			.finish_non_exhaustive()
+	}
 }
 
 /// An acceptor for TLS-encrypted HTTPS requests. Supports HTTP/1.0, HTTP/1.1,
 /// and HTTP/2.
+///
+/// The TLS configuration (protocol versions, cipher suites, and ALPN
+/// protocols) is selected for each connection based on the [`TlsPolicy`] of
+/// the requested domain, then that of the listener, then the global one.
 pub struct TlsHttpAcceptor {
 	config: &'static Config,
 	current_store: &'static Current,
-	tls_acceptor: TlsAcceptor,
+	tls_configs: Arc<TlsConfigs>,
 }
 
 impl TlsHttpAcceptor {
 	/// Create a new [`TlsHttpAcceptor`] with the provided [`Config`],
-	/// [`Current`], and a reference-counted (via [`Arc`])
-	/// [`CertificateResolver`]
+	/// [`Current`], and reference-counted (via [`Arc`])
+	/// [`CertificateResolver`] and [`SessionTicketer`]
 	///
 	/// # Memory
 	/// This function leaks memory, and should therefore not be called an
@@ -229,19 +917,14 @@ impl TlsHttpAcceptor {
 		config: &'static Config,
 		current_store: &'static Current,
 		cert_resolver: Arc<CertificateResolver>,
+		ticketer: Arc<SessionTicketer>,
 	) -> &'static Self {
-		let mut server_config = ServerConfig::builder()
-			.with_no_client_auth()
-			.with_cert_resolver(cert_resolver);
-		server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
-
-		let server_config = Arc::new(server_config);
-		let tls_acceptor = TlsAcceptor::from(server_config);
+		let tls_configs = Arc::new(TlsConfigs::new(cert_resolver, ticketer, None));
 
 		Box::leak(Box::new(Self {
 			config,
 			current_store,
-			tls_acceptor,
+			tls_configs,
 		}))
 	}
 }
@@ -251,24 +934,28 @@ impl Acceptor<TcpStream> for TlsHttpAcceptor {
 	async fn accept(&self, stream: TcpStream, local_addr: SocketAddr, remote_addr: SocketAddr) {
 		let config = self.config;
 		let current_store = self.current_store;
-		let tls_acceptor = self.tls_acceptor.clone();
+		let tls_configs = Arc::clone(&self.tls_configs);
+		let cert_resolver = Arc::clone(&self.tls_configs.cert_resolver);
+		let tls_policy = config.listener_tls_policy(Protocol::Https, local_addr);
 
 		spawn(async move {
 			trace!("New TLS connection from {remote_addr} on {local_addr}");
 
-			match tls_acceptor.accept(stream).await {
+			match tls_configs.accept(stream, &tls_policy).await {
 				Ok(stream) => {
 					let tls_conn = stream.get_ref().1;
 					let extra_info = ExtraStatisticInfo {
 						tls_sni: tls_conn.server_name().map(Arc::from),
 						tls_version: tls_conn.protocol_version(),
 						tls_cipher_suite: tls_conn.negotiated_cipher_suite(),
+						client_ip: Some(remote_addr.ip()),
 					};
 
 					http_handler(
 						TokioIo::new(stream),
 						current_store.get(),
 						config,
+						cert_resolver,
 						extra_info,
 					)
 					.await;
@@ -281,47 +968,112 @@ impl Acceptor<TcpStream> for TlsHttpAcceptor {
 	fn protocol(&self) -> Protocol {
 		Protocol::Https
 	}
+
+	fn proxy_protocol(&self, local_addr: &SocketAddr) -> bool {
+		proxy_protocol_enabled(self.config, Protocol::Https, *local_addr)
+	}
 }
 
 impl Debug for TlsHttpAcceptor {
 	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
 		#[derive(Debug)]
-		struct TlsAcceptor {}
+		struct TlsConfigs {}
 
 		fmt.debug_struct("TlsHttpAcceptor")
 			.field("config", self.config)
 			.field("current_store", self.current_store)
-			.field("tls_acceptor", &TlsAcceptor {})
+			.field("tls_configs", &TlsConfigs {})
 			.finish()
 	}
 }
 
+/// An acceptor for plaintext (unencrypted) HTTP API requests. Supports
+/// HTTP/1.0, HTTP/1.1, and unencrypted HTTP/2.
+#[derive(Debug)]
+pub struct PlainHttpApiAcceptor {
+	config: &'static Config,
+	current_store: &'static Current,
+}
+
+impl PlainHttpApiAcceptor {
+	/// Create a new [`PlainHttpApiAcceptor`] with the provided [`Config`] and
+	/// [`Current`]
+	///
+	/// # Memory
+	/// This function leaks memory, and should therefore not be called an
+	/// unbounded number of times
+	pub fn new(config: &'static Config, current_store: &'static Current) -> &'static Self {
+		Box::leak(Box::new(Self {
+			config,
+			current_store,
+		}))
+	}
+}
+
+#[async_trait::async_trait]
+impl Acceptor<TcpStream> for PlainHttpApiAcceptor {
+	async fn accept(&self, stream: TcpStream, local_addr: SocketAddr, remote_addr: SocketAddr) {
+		let config = self.config;
+		let current_store = self.current_store;
+
+		spawn(async move {
+			trace!("New plain connection from {remote_addr} on {local_addr}");
+
+			http_api_handler(TokioIo::new(stream), current_store.get(), config).await;
+		});
+	}
+
+	fn protocol(&self) -> Protocol {
+		Protocol::HttpApi
+	}
+
+	fn proxy_protocol(&self, local_addr: &SocketAddr) -> bool {
+		proxy_protocol_enabled(self.config, Protocol::HttpApi, *local_addr)
+	}
+}
+
 /// An acceptor for plaintext (unencrypted) RPC calls. Supports `gRPC` over
 /// unencrypted HTTP/2.
 #[derive(Debug)]
 pub struct PlainRpcAcceptor {
+	config: &'static Config,
 	service: Mutex<Routes>,
 }
 
 impl PlainRpcAcceptor {
-	/// Create a new [`PlainRpcAcceptor`] with the provided [`Config`] and
-	/// [`Current`]
+	/// Create a new [`PlainRpcAcceptor`] with the provided [`Config`],
+	/// [`Current`], [`Listeners`] (which can be changed via the RPC API), and
+	/// a reference-counted (via [`Arc`]) [`CertificateResolver`] (whose
+	/// certificates can be reloaded via the RPC API)
 	///
 	/// # Memory
 	/// This function leaks memory, and should therefore not be called an
 	/// unbounded number of times
-	pub fn new(config: &'static Config, current_store: &'static Current) -> &'static Self {
+	pub fn new(
+		config: &'static Config,
+		current_store: &'static Current,
+		listeners: &'static Listeners,
+		cert_resolver: Arc<CertificateResolver>,
+	) -> &'static Self {
+		let api = Api::new(current_store)
+			.with_listeners(config, listeners)
+			.with_certificates(config, cert_resolver);
+
 		let service = RpcServer::builder()
 			.add_service(InterceptedService::new(
-				LinksServer::new(Api::new(current_store))
+				LinksServer::new(api)
 					.send_compressed(CompressionEncoding::Gzip)
-					.accept_compressed(CompressionEncoding::Gzip),
+					.send_compressed(CompressionEncoding::Zstd)
+					.accept_compressed(CompressionEncoding::Gzip)
+					.accept_compressed(CompressionEncoding::Zstd),
 				api::get_auth_checker(config),
 			))
+			.add_service(HealthServer::new(HealthApi::new(current_store)))
 			.into_service()
 			.prepare();
 
 		Box::leak(Box::new(Self {
+			config,
 			service: Mutex::new(service),
 		}))
 	}
@@ -331,30 +1083,90 @@ impl PlainRpcAcceptor {
 impl Acceptor<TcpStream> for PlainRpcAcceptor {
 	async fn accept(&self, stream: TcpStream, local_addr: SocketAddr, remote_addr: SocketAddr) {
 		let service = self.service.lock().clone();
+		let settings = RpcConnection {
+			zstd: zstd_enabled(self.config, Protocol::Grpc, local_addr),
+			..self.config.rpc_connection()
+		};
 
 		spawn(async move {
 			trace!("New plain connection from {remote_addr} on {local_addr}");
 
-			rpc_handler(TokioIo::new(stream), service).await;
+			let connect_info = TcpConnectInfo {
+				local_addr: Some(local_addr),
+				remote_addr: Some(remote_addr),
+			};
+
+			rpc_handler(TokioIo::new(stream), service, settings, connect_info, None).await;
 		});
 	}
 
 	fn protocol(&self) -> Protocol {
 		Protocol::Grpc
 	}
+
+	fn proxy_protocol(&self, local_addr: &SocketAddr) -> bool {
+		proxy_protocol_enabled(self.config, Protocol::Grpc, *local_addr)
+	}
+}
+
+#[cfg(unix)]
+#[async_trait::async_trait]
+impl Acceptor<UnixStream, Arc<UnixSocketAddr>> for PlainRpcAcceptor {
+	async fn accept(
+		&self,
+		stream: UnixStream,
+		local_addr: Arc<UnixSocketAddr>,
+		remote_addr: Arc<UnixSocketAddr>,
+	) {
+		let service = self.service.lock().clone();
+		let settings = RpcConnection {
+			zstd: self.config.rpc_zstd_listeners().iter().any(|l| {
+				l.protocol == Protocol::GrpcUnix && l.path.as_deref() == local_addr.as_pathname()
+			}),
+			..self.config.rpc_connection()
+		};
+
+		spawn(async move {
+			trace!("New plain connection from {remote_addr:?} on {local_addr:?}");
+
+			let connect_info = stream.connect_info();
+
+			rpc_handler(TokioIo::new(stream), service, settings, connect_info, None).await;
+		});
+	}
+
+	fn protocol(&self) -> Protocol {
+		Protocol::GrpcUnix
+	}
 }
 
 /// An acceptor for TLS-encrypted RPC calls. Supports `gRPC` over
 /// HTTP/2 with HTTPS.
+///
+/// If the `rpc_client_ca` is configured, clients must present a certificate
+/// issued by one of its CAs. The TLS configuration is rebuilt when that option
+/// changes, and if the CA certificates can't be loaded, all connections are
+/// rejected until it changes again. The CA certificates file is also watched,
+/// and reloaded when it is modified (e.g. to add a new CA before rotating
+/// client certificates), keeping the previous CA certificates if the modified
+/// file can't be loaded. Like for [`TlsHttpAcceptor`], the rest of the TLS
+/// configuration is selected based on the requested domain's, the listener's,
+/// and the global [`TlsPolicy`].
 pub struct TlsRpcAcceptor {
+	config: &'static Config,
 	service: Arc<Mutex<Routes>>,
-	tls_acceptor: TlsAcceptor,
+	cert_resolver: Arc<CertificateResolver>,
+	ticketer: Arc<SessionTicketer>,
+	tls_configs: Mutex<(Option<PathBuf>, Option<Arc<TlsConfigs>>)>,
+	client_ca_watcher: Mutex<Option<RecommendedWatcher>>,
+	client_ca_changed: Arc<AtomicBool>,
 }
 
 impl TlsRpcAcceptor {
 	/// Create a new [`TlsRpcAcceptor`] with the provided [`Config`],
-	/// [`Current`], and a reference-counted (via [`Arc`])
-	/// [`CertificateResolver`]
+	/// [`Current`], [`Listeners`] (which can be changed via the RPC API), and
+	/// reference-counted (via [`Arc`]) [`CertificateResolver`] and
+	/// [`SessionTicketer`]
 	///
 	/// # Memory
 	/// This function leaks memory, and should therefore not be called an
@@ -362,44 +1174,190 @@ impl TlsRpcAcceptor {
 	pub fn new(
 		config: &'static Config,
 		current_store: &'static Current,
+		listeners: &'static Listeners,
 		cert_resolver: Arc<CertificateResolver>,
+		ticketer: Arc<SessionTicketer>,
 	) -> &'static Self {
-		let mut server_config = ServerConfig::builder()
-			.with_no_client_auth()
-			.with_cert_resolver(cert_resolver);
-		server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
-
-		let server_config = Arc::new(server_config);
-		let tls_acceptor = TlsAcceptor::from(server_config);
+		let client_ca = config.rpc_client_ca();
+		let client_ca_changed = Arc::new(AtomicBool::new(false));
+		let client_ca_watcher = client_ca
+			.as_deref()
+			.and_then(|client_ca| Self::watch_client_ca(client_ca, &client_ca_changed));
+		let tls_configs = Self::tls_configs_for(&cert_resolver, &ticketer, client_ca.as_deref())
+			.inspect_err(|err| {
+				error!(
+					?err,
+					"Could not load RPC client CA certificates, rejecting TLS RPC connections"
+				);
+			})
+			.ok();
+
+		let api = Api::new(current_store)
+			.with_listeners(config, listeners)
+			.with_certificates(config, Arc::clone(&cert_resolver));
 
 		let service = RpcServer::builder()
 			.add_service(InterceptedService::new(
-				LinksServer::new(Api::new(current_store))
+				LinksServer::new(api)
 					.send_compressed(CompressionEncoding::Gzip)
-					.accept_compressed(CompressionEncoding::Gzip),
+					.send_compressed(CompressionEncoding::Zstd)
+					.accept_compressed(CompressionEncoding::Gzip)
+					.accept_compressed(CompressionEncoding::Zstd),
 				api::get_auth_checker(config),
 			))
+			.add_service(HealthServer::new(HealthApi::new(current_store)))
 			.into_service()
 			.prepare();
 
 		Box::leak(Box::new(Self {
+			config,
 			service: Arc::new(Mutex::new(service)),
-			tls_acceptor,
+			cert_resolver,
+			ticketer,
+			tls_configs: Mutex::new((client_ca, tls_configs)),
+			client_ca_watcher: Mutex::new(client_ca_watcher),
+			client_ca_changed,
 		}))
 	}
+
+	/// Create [`TlsConfigs`] using certificates from the `cert_resolver` and
+	/// session tickets from the `ticketer`, requiring client certificates
+	/// issued by the CAs in `client_ca` (if any)
+	///
+	/// # Errors
+	/// This function returns an error if the CA certificates can not be loaded
+	fn tls_configs_for(
+		cert_resolver: &Arc<CertificateResolver>,
+		ticketer: &Arc<SessionTicketer>,
+		client_ca: Option<&Path>,
+	) -> Result<Arc<TlsConfigs>, CertificateAcquisitionError> {
+		let client_verifier = client_ca.map(certs::client_cert_verifier).transpose()?;
+
+		Ok(Arc::new(TlsConfigs::new(
+			Arc::clone(cert_resolver),
+			Arc::clone(ticketer),
+			client_verifier,
+		)))
+	}
+
+	/// Watch the CA certificates file at `client_ca` for modifications, setting
+	/// `changed` when it is modified. Returns `None` if the file can not be
+	/// watched.
+	fn watch_client_ca(client_ca: &Path, changed: &Arc<AtomicBool>) -> Option<RecommendedWatcher> {
+		let changed = Arc::clone(changed);
+		let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+			Ok(event) if matches!(event.kind, EventKind::Access(_)) => {
+				trace!(?event, "Ignoring RPC client CA file event from watcher");
+			}
+			Ok(event) => {
+				debug!(?event, "Received RPC client CA file event from watcher");
+				changed.store(true, Ordering::Release);
+			}
+			Err(err) => error!(%err, "RPC client CA file watching error"),
+		});
+
+		match watcher.and_then(|mut watcher| {
+			watcher
+				.watch(client_ca, RecursiveMode::NonRecursive)
+				.map(|()| watcher)
+		}) {
+			Ok(watcher) => Some(watcher),
+			Err(err) => {
+				error!(
+					%err,
+					?client_ca,
+					"Could not watch the RPC client CA certificates, changes will not be applied"
+				);
+				None
+			}
+		}
+	}
+
+	/// Get the current [`TlsConfigs`], rebuilding them first if the configured
+	/// `rpc_client_ca` or the file it points to changed
+	fn tls_configs(&self) -> Option<Arc<TlsConfigs>> {
+		let client_ca = self.config.rpc_client_ca();
+		let mut current = self.tls_configs.lock();
+
+		if current.0 != client_ca {
+			debug!(?client_ca, "Updating RPC client CA certificates");
+
+			// The new file is watched before it is loaded, so that no changes
+			// are missed
+			self.client_ca_changed.store(false, Ordering::Release);
+			*self.client_ca_watcher.lock() = client_ca
+				.as_deref()
+				.and_then(|client_ca| Self::watch_client_ca(client_ca, &self.client_ca_changed));
+
+			let tls_configs =
+				Self::tls_configs_for(&self.cert_resolver, &self.ticketer, client_ca.as_deref())
+					.inspect_err(|err| {
+						error!(
+							?err,
+							"Could not load RPC client CA certificates, rejecting TLS RPC \
+							 connections"
+						);
+					})
+					.ok();
+			*current = (client_ca, tls_configs);
+		} else if self.client_ca_changed.swap(false, Ordering::AcqRel) {
+			debug!(?client_ca, "Reloading modified RPC client CA certificates");
+
+			match Self::tls_configs_for(&self.cert_resolver, &self.ticketer, client_ca.as_deref()) {
+				Ok(tls_configs) => current.1 = Some(tls_configs),
+				Err(err) => error!(
+					?err,
+					"Could not reload RPC client CA certificates, keeping the previous ones"
+				),
+			}
+		}
+
+		current.1.clone()
+	}
 }
 
 #[async_trait::async_trait]
 impl Acceptor<TcpStream> for TlsRpcAcceptor {
 	async fn accept(&self, stream: TcpStream, local_addr: SocketAddr, remote_addr: SocketAddr) {
-		let tls_acceptor = self.tls_acceptor.clone();
+		let Some(tls_configs) = self.tls_configs() else {
+			debug!("Rejecting TLS connection from {remote_addr} on {local_addr}");
+			return;
+		};
 		let service = self.service.lock().clone();
+		let settings = RpcConnection {
+			zstd: zstd_enabled(self.config, Protocol::Grpcs, local_addr),
+			..self.config.rpc_connection()
+		};
+		let tls_policy = self.config.listener_tls_policy(Protocol::Grpcs, local_addr);
 
 		spawn(async move {
 			trace!("New TLS connection from {remote_addr} on {local_addr}");
 
-			match tls_acceptor.accept(stream).await {
-				Ok(stream) => rpc_handler(TokioIo::new(stream), service).await,
+			match tls_configs.accept(stream, &tls_policy).await {
+				Ok(stream) => {
+					let client_cert = stream
+						.get_ref()
+						.1
+						.peer_certificates()
+						.and_then(<[_]>::first)
+						.map(|cert| ClientCertificate {
+							fingerprint: to_hex(digest(&SHA256, cert).as_ref()),
+						});
+
+					let connect_info = TcpConnectInfo {
+						local_addr: Some(local_addr),
+						remote_addr: Some(remote_addr),
+					};
+
+					rpc_handler(
+						TokioIo::new(stream),
+						service,
+						settings,
+						connect_info,
+						client_cert,
+					)
+					.await;
+				}
 				Err(err) => warn!("Error accepting incoming TLS connection: {err:?}"),
 			}
 		});
@@ -408,16 +1366,25 @@ impl Acceptor<TcpStream> for TlsRpcAcceptor {
 	fn protocol(&self) -> Protocol {
 		Protocol::Grpcs
 	}
+
+	fn proxy_protocol(&self, local_addr: &SocketAddr) -> bool {
+		proxy_protocol_enabled(self.config, Protocol::Grpcs, *local_addr)
+	}
 }
 
 impl Debug for TlsRpcAcceptor {
 	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
 		#[derive(Debug)]
-		struct TlsAcceptor {}
+		struct TlsConfigs {}
 
 		fmt.debug_struct("TlsRpcAcceptor")
+			.field("config", self.config)
 			.field("service", &self.service)
-			.field("tls_acceptor", &TlsAcceptor {})
+			.field("cert_resolver", &self.cert_resolver)
+			.field("ticketer", &self.ticketer)
+			.field("tls_configs", &TlsConfigs {})
+			.field("client_ca_watcher", &self.client_ca_watcher)
+			.field("client_ca_changed", &self.client_ca_changed)
 			.finish()
 	}
 }
@@ -434,6 +1401,18 @@ pub enum Protocol {
 	Grpc,
 	/// gRPC over HTTP/2 (h2) over TCP with TLS
 	Grpcs,
+	/// The HTTP+JSON API over HTTP/1.0, HTTP/1.1, and HTTP/2 (h2c) over TCP
+	/// (unencrypted)
+	HttpApi,
+	/// gRPC over HTTP/2 (h2c) over a Unix domain socket (unencrypted). Only
+	/// supported on Unix-like platforms.
+	#[strum(serialize = "grpc+unix")]
+	GrpcUnix,
+	/// HTTP/1.0, HTTP/1.1, and HTTP/2 (h2c) over a Unix domain socket
+	/// (unencrypted), e.g. behind a local reverse proxy. Only supported on
+	/// Unix-like platforms.
+	#[strum(serialize = "http+unix")]
+	HttpUnix,
 }
 
 impl Protocol {
@@ -443,10 +1422,13 @@ impl Protocol {
 	pub const GRPC_DEFAULT_PORT: u16 = 50051;
 	/// Default port for the `https` protocol
 	pub const HTTPS_DEFAULT_PORT: u16 = 443;
+	/// Default port for the `http_api` protocol
+	pub const HTTP_API_DEFAULT_PORT: u16 = 8080;
 	/// Default port for the `http` protocol
 	pub const HTTP_DEFAULT_PORT: u16 = 80;
 
-	/// Get the default port for this [`Protocol`]
+	/// Get the default port for this [`Protocol`]. Unix domain socket
+	/// protocols don't use ports, so their default port is `0`.
 	#[must_use]
 	pub const fn default_port(self) -> u16 {
 		match self {
@@ -454,8 +1436,17 @@ impl Protocol {
 			Self::Https => Self::HTTPS_DEFAULT_PORT,
 			Self::Grpc => Self::GRPC_DEFAULT_PORT,
 			Self::Grpcs => Self::GRPCS_DEFAULT_PORT,
+			Self::HttpApi => Self::HTTP_API_DEFAULT_PORT,
+			Self::GrpcUnix | Self::HttpUnix => 0,
 		}
 	}
+
+	/// Check whether this [`Protocol`] uses Unix domain sockets (identified by
+	/// a path) instead of TCP (identified by an address and port)
+	#[must_use]
+	pub const fn is_unix(self) -> bool {
+		matches!(self, Self::GrpcUnix | Self::HttpUnix)
+	}
 }
 
 /// A links redirector listener.
@@ -476,9 +1467,34 @@ pub struct Listener {
 	/// The protocol of the acceptor/handler this listener uses to process
 	/// requests
 	pub proto: Protocol,
+	/// The path of this listener's Unix domain socket, if it uses one instead
+	/// of TCP (in which case `addr` and `port` are meaningless)
+	pub path: Option<PathBuf>,
+	counters: Arc<ListenerCounters>,
 	handle: JoinHandle<()>,
 }
 
+/// The counters of connections accepted by a [`Listener`]
+#[derive(Debug, Default)]
+struct ListenerCounters {
+	/// The number of successfully accepted connections
+	accepted: AtomicU64,
+	/// The number of errors while accepting connections
+	errors: AtomicU64,
+}
+
+/// Information about an active [`Listener`], see [`Listeners::list`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerInfo {
+	/// The address of the listener, with its port filled in (if it uses one)
+	pub address: ListenAddress,
+	/// The number of connections the listener has accepted
+	pub accepted: u64,
+	/// The number of errors the listener has encountered while accepting
+	/// connections
+	pub errors: u64,
+}
+
 impl Listener {
 	/// Create a new [`Listener`] on the specified address, which will use the
 	/// specified acceptor to accept incoming connections. If no address is
@@ -548,13 +1564,44 @@ impl Listener {
 		socket.listen(LISTENER_TCP_BACKLOG_SIZE)?;
 		let listener = TcpListener::from_std(socket.into())?;
 
+		let counters = Arc::new(ListenerCounters::default());
+		let task_counters = Arc::clone(&counters);
 		let handle = spawn(async move {
 			loop {
 				match listener.accept().await {
-					Ok((stream, remote_addr)) => {
-						acceptor.accept(stream, socket_addr, remote_addr).await;
+					Ok((mut stream, remote_addr)) => {
+						task_counters.accepted.fetch_add(1, Ordering::Relaxed);
+
+						if !acceptor.proxy_protocol(&socket_addr) {
+							acceptor.accept(stream, socket_addr, remote_addr).await;
+							continue;
+						}
+
+						// The header is read in its own task, so that slow clients
+						// can't hold up accepting other connections
+						let counters = Arc::clone(&task_counters);
+						spawn(async move {
+							match proxy::read_header(&mut stream).await {
+								Ok(client_addr) => {
+									let client_addr = client_addr.unwrap_or(remote_addr);
+									trace!(
+										"PROXY protocol connection from {remote_addr} for \
+										 {client_addr}"
+									);
+									acceptor.accept(stream, socket_addr, client_addr).await;
+								}
+								Err(err) => {
+									counters.errors.fetch_add(1, Ordering::Relaxed);
+									debug!(
+										"Invalid PROXY protocol header from {remote_addr} on \
+										 {socket_addr}: {err}"
+									);
+								}
+							}
+						});
 					}
 					Err(err) => {
+						task_counters.errors.fetch_add(1, Ordering::Relaxed);
 						warn!("Error accepting TCP connection on {socket_addr}: {err:?}");
 						continue;
 					}
@@ -566,23 +1613,119 @@ impl Listener {
 			protocol: proto,
 			address: addr,
 			port: Some(port),
+			path: None,
 		});
 
 		Ok(Self {
 			addr,
 			port,
 			proto,
+			path: None,
+			counters,
 			handle,
 		})
 	}
 
+	/// Create a new [`Listener`] on the Unix domain socket at `path`, which
+	/// will use the specified acceptor to accept incoming connections.
+	///
+	/// If a socket file already exists at `path` (e.g. left over after links
+	/// was killed), it is removed first. Other kinds of files are never
+	/// removed, and result in an error instead. The socket file is removed
+	/// when the listener is dropped.
+	///
+	/// # Drop
+	/// Dropping a Unix domain socket listener blocks, just like for TCP
+	/// listeners, see [`Listener::new`] for details.
+	///
+	/// # Errors
+	/// This function returns an error if it can not set up the listening
+	/// socket.
+	#[cfg(unix)]
+	pub fn new_unix(
+		path: PathBuf,
+		acceptor: &'static impl Acceptor<UnixStream, Arc<UnixSocketAddr>>,
+	) -> Result<Self, IoError> {
+		use std::os::unix::fs::FileTypeExt;
+
+		let proto = acceptor.protocol();
+
+		match std::fs::symlink_metadata(&path) {
+			Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(&path)?,
+			Ok(_) => {
+				return Err(IoError::new(
+					ErrorKind::AlreadyExists,
+					format!("{} exists and is not a socket", path.display()),
+				))
+			}
+			Err(err) if err.kind() == ErrorKind::NotFound => (),
+			Err(err) => return Err(err),
+		}
+
+		let listener = UnixListener::bind(&path)?;
+		let local_addr = Arc::new(listener.local_addr()?);
+		let display_path = path.display().to_string();
+
+		let counters = Arc::new(ListenerCounters::default());
+		let task_counters = Arc::clone(&counters);
+		let handle = spawn(async move {
+			loop {
+				match listener.accept().await {
+					Ok((stream, remote_addr)) => {
+						task_counters.accepted.fetch_add(1, Ordering::Relaxed);
+						acceptor
+							.accept(stream, Arc::clone(&local_addr), Arc::new(remote_addr))
+							.await;
+					}
+					Err(err) => {
+						task_counters.errors.fetch_add(1, Ordering::Relaxed);
+						warn!("Error accepting Unix socket connection on {display_path}: {err:?}");
+					}
+				}
+			}
+		});
+
+		let listener = Self {
+			addr: None,
+			port: 0,
+			proto,
+			path: Some(path),
+			counters,
+			handle,
+		};
+
+		debug!("Opened new listener on {}", listener.listen_address());
+
+		Ok(listener)
+	}
+
 	/// Get the [`ListenAddress`] of this listener
 	#[must_use]
-	pub const fn listen_address(&self) -> ListenAddress {
+	pub fn listen_address(&self) -> ListenAddress {
+		if self.path.is_some() {
+			return ListenAddress {
+				protocol: self.proto,
+				address: None,
+				port: None,
+				path: self.path.clone(),
+			};
+		}
+
 		ListenAddress {
 			protocol: self.proto,
 			address: self.addr,
 			port: Some(self.port),
+			path: None,
+		}
+	}
+
+	/// Get information about this listener, including its current counters
+	#[must_use]
+	pub fn info(&self) -> ListenerInfo {
+		ListenerInfo {
+			address: self.listen_address(),
+			accepted: self.counters.accepted.load(Ordering::Relaxed),
+			errors: self.counters.errors.load(Ordering::Relaxed),
 		}
 	}
 }
@@ -603,20 +1746,210 @@ impl Drop for Listener {
 			thread::yield_now();
 		}
 
+		if let Some(ref path) = self.path {
+			if let Err(err) = std::fs::remove_file(path) {
+				warn!("Could not remove socket file {}: {err}", path.display());
+			}
+		}
+
 		debug!("Closed listener on {}", self.listen_address());
 	}
 }
 
+/// The acceptors used by [`Listeners`] for each [`Protocol`]
+#[derive(Debug, Clone, Copy)]
+pub struct Acceptors {
+	/// The acceptor for [`Protocol::Http`] and [`Protocol::HttpUnix`] listeners
+	pub plain_http: &'static PlainHttpAcceptor,
+	/// The acceptor for [`Protocol::Https`] listeners
+	pub tls_http: &'static TlsHttpAcceptor,
+	/// The acceptor for [`Protocol::Grpc`] and [`Protocol::GrpcUnix`] listeners
+	pub plain_rpc: &'static PlainRpcAcceptor,
+	/// The acceptor for [`Protocol::Grpcs`] listeners
+	pub tls_rpc: &'static TlsRpcAcceptor,
+	/// The acceptor for [`Protocol::HttpApi`] listeners
+	pub plain_http_api: &'static PlainHttpApiAcceptor,
+}
+
+impl Acceptors {
+	/// Create a new [`Listener`] on `addr`, using the acceptor for that
+	/// address's protocol
+	///
+	/// # Errors
+	/// This function returns an error if it can not set up the listening
+	/// socket.
+	pub async fn listen(&self, addr: ListenAddress) -> Result<Listener, IoError> {
+		match addr.protocol {
+			Protocol::Http => Listener::new(addr.address, addr.port, self.plain_http).await,
+			Protocol::Https => Listener::new(addr.address, addr.port, self.tls_http).await,
+			Protocol::Grpc => Listener::new(addr.address, addr.port, self.plain_rpc).await,
+			Protocol::Grpcs => Listener::new(addr.address, addr.port, self.tls_rpc).await,
+			Protocol::HttpApi => Listener::new(addr.address, addr.port, self.plain_http_api).await,
+			Protocol::GrpcUnix | Protocol::HttpUnix => self.listen_unix(addr),
+		}
+	}
+
+	/// Create a new [`Listener`] on the Unix domain socket at the path of
+	/// `addr`, using the acceptor for that address's protocol
+	#[cfg(unix)]
+	fn listen_unix(&self, addr: ListenAddress) -> Result<Listener, IoError> {
+		let path = addr.path.ok_or_else(|| {
+			IoError::new(ErrorKind::InvalidInput, "missing Unix domain socket path")
+		})?;
+
+		if addr.protocol == Protocol::HttpUnix {
+			Listener::new_unix(path, self.plain_http)
+		} else {
+			Listener::new_unix(path, self.plain_rpc)
+		}
+	}
+
+	/// Unix domain socket listeners aren't supported on this platform
+	#[cfg(not(unix))]
+	fn listen_unix(&self, _: ListenAddress) -> Result<Listener, IoError> {
+		Err(IoError::new(
+			ErrorKind::Unsupported,
+			"Unix domain socket listeners are not supported on this platform",
+		))
+	}
+}
+
+/// The set of all active [`Listener`]s of a links redirector server.
+///
+/// Listeners are added and removed when the configuration is reloaded, and
+/// can also be added and removed via the RPC API while the server is running.
+/// Because RPC acceptors need a reference to this set, while this set needs
+/// the acceptors to create listeners, the acceptors are set separately (via
+/// [`Listeners::set_acceptors`]) after this set is created.
+#[derive(Debug, Default)]
+pub struct Listeners {
+	acceptors: OnceLock<Acceptors>,
+	active: Mutex<Vec<Listener>>,
+	/// Held while a listener is added, so that only one listener per address
+	/// is ever created (the `active` lock can't be held while one is created)
+	adding: AsyncMutex<()>,
+}
+
+impl Listeners {
+	/// Create a new empty set of listeners without any acceptors
+	///
+	/// # Memory
+	/// This function leaks memory, and should therefore not be called an
+	/// unbounded number of times
+	#[must_use]
+	pub fn new_static() -> &'static Self {
+		Box::leak(Box::default())
+	}
+
+	/// Set the acceptors used to create new listeners. This can only be done
+	/// once, so if acceptors have already been set, the provided ones are
+	/// returned back.
+	///
+	/// # Errors
+	/// Returns the `acceptors` if acceptors have already been set.
+	pub fn set_acceptors(&self, acceptors: Acceptors) -> Result<(), Acceptors> {
+		self.acceptors.set(acceptors)
+	}
+
+	/// Start listening on `addr`, returning `true` if a new listener was
+	/// created, and `false` if a listener on that address already exists
+	///
+	/// # Errors
+	/// This function returns an error if the acceptors haven't been set yet or
+	/// if the listening socket can not be set up (e.g. because the port is
+	/// already in use by another program).
+	pub async fn add(&self, addr: ListenAddress) -> Result<bool, IoError> {
+		let Some(acceptors) = self.acceptors.get() else {
+			return Err(IoError::other("listener acceptors are not set up"));
+		};
+
+		let _adding = self.adding.lock().await;
+
+		if self.contains(&addr) {
+			return Ok(false);
+		}
+
+		let listener = acceptors.listen(addr).await?;
+		self.active.lock().push(listener);
+
+		Ok(true)
+	}
+
+	/// Stop listening on `addr`, returning `true` if a listener on that address
+	/// was removed, and `false` if no such listener exists
+	///
+	/// # Blocking
+	/// This function drops the removed [`Listener`], so it blocks the current
+	/// thread until that listener is closed. See [`Listener::new`] for details.
+	pub fn remove(&self, addr: &ListenAddress) -> bool {
+		let removed = {
+			let mut active = self.active.lock();
+			active
+				.iter()
+				.position(|l| l.listen_address() == *addr)
+				.map(|i| active.swap_remove(i))
+		};
+
+		removed.is_some()
+	}
+
+	/// Check whether a listener on `addr` is currently active
+	#[must_use]
+	pub fn contains(&self, addr: &ListenAddress) -> bool {
+		self.active
+			.lock()
+			.iter()
+			.any(|l| l.listen_address() == *addr)
+	}
+
+	/// Get the addresses of all currently active listeners
+	#[must_use]
+	pub fn addresses(&self) -> Vec<ListenAddress> {
+		self.active
+			.lock()
+			.iter()
+			.map(Listener::listen_address)
+			.collect()
+	}
+
+	/// Get information about all currently active listeners, including how
+	/// many connections each of them has accepted
+	#[must_use]
+	pub fn list(&self) -> Vec<ListenerInfo> {
+		self.active.lock().iter().map(Listener::info).collect()
+	}
+}
+
 /// Set up the links store, optionally setting an example redirect
 /// (`example` -> `9dDbKpJP` -> `https://example.com/`).
 ///
+/// A warning is logged if the store is not healthy, but that is not considered
+/// an error, so that links can start while the store is temporarily
+/// unavailable.
+///
 /// # Errors
 /// This function returns an error if construction of the [`Store`] (using
-/// `Store::new`) fails or if the example redirect can not be set when
-/// requested.
+/// `Store::new`) fails, if API token quotas are configured but the store
+/// backend doesn't support usage counters, or if the example redirect can not
+/// be set when requested.
 pub async fn store_setup(config: &Config, example_redirect: bool) -> Result<Store, anyhow::Error> {
 	let store = Store::new(config.store(), &config.store_config()).await?;
 
+	if !store.supports_usage() && config.tokens().iter().any(|t| t.quota.is_enabled()) {
+		anyhow::bail!(
+			"API token quotas are configured, but the {} store backend doesn't support usage \
+			 counters",
+			store.backend_name()
+		);
+	}
+
+	let health = store.health().await;
+	if health.healthy {
+		debug!(latency = ?health.latency, "Store is healthy");
+	} else {
+		warn!(latency = ?health.latency, error = ?health.error, "Store is not healthy");
+	}
+
 	if example_redirect {
 		store
 			.set_redirect(Id::try_from(Id::MAX)?, Link::new("https://example.com/")?)
@@ -683,4 +2016,66 @@ mod tests {
 			None
 		);
 	}
+	#[test]
+	fn fn_remove_accepted_encoding() {
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			GRPC_ACCEPT_ENCODING,
+			HeaderValue::from_static("zstd, gzip,identity"),
+		);
+
+		remove_accepted_encoding(&mut headers, "zstd");
+		assert_eq!(headers[GRPC_ACCEPT_ENCODING], "gzip,identity");
+
+		remove_accepted_encoding(&mut headers, "zstd");
+		assert_eq!(headers[GRPC_ACCEPT_ENCODING], "gzip,identity");
+
+		let mut headers = HeaderMap::new();
+		remove_accepted_encoding(&mut headers, "zstd");
+		assert!(headers.is_empty());
+	}
+
+	#[test]
+	fn session_ticketer() {
+		let ticketer = SessionTicketer::new(Config::new_static(None));
+		assert!(ticketer.enabled());
+		assert_eq!(ticketer.lifetime(), 21600);
+
+		let first = ticketer.encrypt(b"first").unwrap();
+		assert_eq!(ticketer.decrypt(&first).unwrap(), b"first");
+		assert!(ticketer.decrypt(b"not a ticket").is_none());
+
+		SessionTicketer::rotate(&mut ticketer.keys.write(), false).unwrap();
+		let second = ticketer.encrypt(b"second").unwrap();
+		assert_eq!(ticketer.decrypt(&first).unwrap(), b"first");
+		assert_eq!(ticketer.decrypt(&second).unwrap(), b"second");
+
+		SessionTicketer::rotate(&mut ticketer.keys.write(), false).unwrap();
+		assert!(ticketer.decrypt(&first).is_none());
+		assert_eq!(ticketer.decrypt(&second).unwrap(), b"second");
+
+		SessionTicketer::rotate(&mut ticketer.keys.write(), true).unwrap();
+		assert!(ticketer.decrypt(&second).is_none());
+	}
+
+	#[test]
+	fn client_ca_watcher() {
+		let dir = std::env::temp_dir().join("links_test_server-client_ca_watcher");
+		std::fs::create_dir_all(&dir).unwrap();
+		let client_ca = dir.join("client-ca.pem");
+		std::fs::copy("tests/client-ca.pem", &client_ca).unwrap();
+
+		let changed = Arc::new(AtomicBool::new(false));
+		let _watcher = TlsRpcAcceptor::watch_client_ca(&client_ca, &changed).unwrap();
+		assert!(!changed.load(Ordering::Acquire));
+
+		std::fs::copy("tests/client-ca.pem", &client_ca).unwrap();
+		let start = Instant::now();
+		while !changed.load(Ordering::Acquire) && start.elapsed() < Duration::from_secs(5) {
+			thread::sleep(Duration::from_millis(10));
+		}
+		assert!(changed.load(Ordering::Acquire));
+
+		assert!(TlsRpcAcceptor::watch_client_ca(&dir.join("missing.pem"), &changed).is_none());
+	}
 }
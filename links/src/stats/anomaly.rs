@@ -0,0 +1,322 @@
+//! Detection of anomalous redirect traffic
+//!
+//! When enabled (via the `anomaly_detection` configuration option), the number
+//! of redirects for every link is counted over fixed intervals. After each
+//! interval, these counts are compared to an exponentially weighted moving
+//! average (EWMA) of the link's previous traffic, and a warning is logged for
+//! every link with a sudden, unusually large spike in traffic, which may
+//! indicate abuse of that link (or it just going viral).
+//!
+//! Only traffic to existing links is counted, and links are identified by
+//! their ID, so requests using any of a link's vanity paths count towards the
+//! same link. Counting is done in memory on each redirector server separately,
+//! and is independent of statistics collection.
+
+use std::{
+	collections::HashMap,
+	mem,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		LazyLock,
+	},
+	time::Duration,
+};
+
+use links_id::Id;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+/// Whether redirects are currently being counted
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The number of redirects per link in the current interval
+static COUNTS: LazyLock<Mutex<HashMap<Id, u64>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How often to check whether anomaly detection was enabled while it is
+/// disabled
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Record a redirect for the link with the `id`, if anomaly detection is
+/// enabled
+pub fn record(id: Id) {
+	if ENABLED.load(Ordering::Relaxed) {
+		*COUNTS.lock().entry(id).or_default() += 1;
+	}
+}
+
+/// Continuously analyze redirect traffic according to the `config`, logging a
+/// warning for each detected anomaly. This never returns, and should be
+/// spawned as a background task.
+pub async fn run(config: &'static Config) -> ! {
+	let mut detector = AnomalyDetector::default();
+
+	loop {
+		let settings = config.anomaly_detection();
+
+		if !settings.is_enabled() {
+			if ENABLED.swap(false, Ordering::Relaxed) {
+				debug!("Anomaly detection disabled");
+				COUNTS.lock().clear();
+				detector = AnomalyDetector::default();
+			}
+
+			tokio::time::sleep(DISABLED_POLL_INTERVAL).await;
+			continue;
+		}
+
+		if !ENABLED.swap(true, Ordering::Relaxed) {
+			debug!(?settings, "Anomaly detection enabled");
+		}
+
+		tokio::time::sleep(Duration::from_secs(settings.interval)).await;
+
+		let counts = mem::take(&mut *COUNTS.lock());
+		for anomaly in detector.update(&counts, &settings) {
+			warn!(
+				id = %anomaly.id,
+				requests = anomaly.requests,
+				expected = anomaly.expected,
+				"Anomalous traffic detected for link {}: {} requests in the last {} seconds, \
+				 usually about {:.0}",
+				anomaly.id,
+				anomaly.requests,
+				settings.interval,
+				anomaly.expected
+			);
+		}
+	}
+}
+
+/// Settings for the detection of anomalous redirect traffic.
+///
+/// Anomaly detection is disabled if `interval` is `0`, which is the default.
+/// A link's traffic is considered anomalous if, in one interval, it receives
+/// at least `min_requests` requests and more than `sensitivity` standard
+/// deviations above its moving average.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnomalyDetection {
+	/// The length of the intervals over which requests are counted (in
+	/// seconds), or `0` to disable anomaly detection
+	pub interval: u64,
+	/// The weight of the latest interval in the moving average (as a
+	/// percentage, `1` to `100`), higher values adapt to changes in traffic
+	/// more quickly
+	pub smoothing: u8,
+	/// How many standard deviations above the moving average the number of
+	/// requests must be to be considered anomalous, lower values are more
+	/// sensitive
+	pub sensitivity: u32,
+	/// The minimum number of requests in an interval to be considered
+	/// anomalous, to ignore spikes in links with very little traffic
+	pub min_requests: u64,
+	/// The number of intervals a link must have been seen in before its
+	/// traffic can be considered anomalous
+	pub warmup: u32,
+}
+
+impl AnomalyDetection {
+	/// Check whether anomaly detection is enabled by these settings
+	#[must_use]
+	pub const fn is_enabled(&self) -> bool {
+		self.interval != 0
+	}
+
+	/// Get the EWMA smoothing factor (between `0.01` and `1.0`)
+	fn alpha(&self) -> f64 {
+		f64::from(self.smoothing.clamp(1, 100)) / 100.0
+	}
+}
+
+impl Default for AnomalyDetection {
+	fn default() -> Self {
+		Self {
+			interval: 0,
+			smoothing: 10,
+			sensitivity: 4,
+			min_requests: 100,
+			warmup: 10,
+		}
+	}
+}
+
+/// A detected traffic anomaly for a link
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Anomaly {
+	/// The ID of the link
+	pub id: Id,
+	/// The number of requests in the latest interval
+	pub requests: u64,
+	/// The moving average of requests per interval before the latest one
+	pub expected: f64,
+}
+
+/// The exponentially weighted moving average and variance of a link's
+/// requests per interval
+#[derive(Copy, Clone, Debug, Default)]
+struct Ewma {
+	mean: f64,
+	variance: f64,
+	samples: u32,
+}
+
+impl Ewma {
+	/// Add a new `value` to this moving average and variance
+	fn update(&mut self, value: f64, alpha: f64) {
+		if self.samples == 0 {
+			self.mean = value;
+		} else {
+			let diff = value - self.mean;
+			let incr = alpha * diff;
+			self.mean += incr;
+			self.variance = (1.0 - alpha) * diff.mul_add(incr, self.variance);
+		}
+
+		self.samples = self.samples.saturating_add(1);
+	}
+}
+
+/// Per-link traffic models used to detect anomalies
+#[derive(Debug, Default)]
+pub struct AnomalyDetector {
+	models: HashMap<Id, Ewma>,
+}
+
+impl AnomalyDetector {
+	/// Moving averages below this (with no new requests) are forgotten
+	const MIN_MEAN: f64 = 0.01;
+
+	/// Update the traffic models with the request `counts` of the latest
+	/// interval, returning all detected anomalies. Links missing from `counts`
+	/// are treated as having had no requests in that interval.
+	#[expect(
+		clippy::cast_precision_loss,
+		reason = "request counts are far below 2^52"
+	)]
+	pub fn update(
+		&mut self,
+		counts: &HashMap<Id, u64>,
+		settings: &AnomalyDetection,
+	) -> Vec<Anomaly> {
+		let alpha = settings.alpha();
+		let mut anomalies = Vec::new();
+
+		for &id in counts.keys() {
+			self.models.entry(id).or_default();
+		}
+
+		self.models.retain(|&id, model| {
+			let requests = counts.get(&id).copied().unwrap_or(0);
+			let value = requests as f64;
+
+			if model.samples >= settings.warmup
+				&& requests >= settings.min_requests
+				&& value
+					> f64::from(settings.sensitivity)
+						.mul_add(model.variance.sqrt().max(1.0), model.mean)
+			{
+				anomalies.push(Anomaly {
+					id,
+					requests,
+					expected: model.mean,
+				});
+			}
+
+			model.update(value, alpha);
+
+			requests != 0 || model.mean >= Self::MIN_MEAN
+		});
+
+		anomalies
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn counts(id: Id, requests: u64) -> HashMap<Id, u64> {
+		HashMap::from([(id, requests)])
+	}
+
+	#[test]
+	fn detect_spike() {
+		let settings = AnomalyDetection {
+			interval: 60,
+			min_requests: 50,
+			warmup: 3,
+			..Default::default()
+		};
+		let id = Id::new();
+		let mut detector = AnomalyDetector::default();
+
+		// Steady traffic (even with some noise) is not anomalous
+		for requests in [20, 25, 18, 22, 21, 24, 19, 23] {
+			assert!(detector.update(&counts(id, requests), &settings).is_empty());
+		}
+
+		// A large spike is
+		let anomalies = detector.update(&counts(id, 500), &settings);
+		assert_eq!(anomalies.len(), 1);
+		assert_eq!(anomalies[0].id, id);
+		assert_eq!(anomalies[0].requests, 500);
+		assert!(anomalies[0].expected > 18.0 && anomalies[0].expected < 25.0);
+	}
+
+	#[test]
+	fn ignore_small_and_new() {
+		let settings = AnomalyDetection {
+			interval: 60,
+			min_requests: 100,
+			warmup: 3,
+			..Default::default()
+		};
+		let id = Id::new();
+		let mut detector = AnomalyDetector::default();
+
+		// New links are not anomalous, even with lots of traffic
+		assert!(detector.update(&counts(id, 1000), &settings).is_empty());
+
+		let other = Id::new();
+		for _ in 0..5 {
+			assert!(detector.update(&counts(other, 1), &settings).is_empty());
+		}
+
+		// Spikes below `min_requests` are not anomalous
+		assert!(detector.update(&counts(other, 80), &settings).is_empty());
+	}
+
+	#[test]
+	fn forget_idle() {
+		let settings = AnomalyDetection {
+			interval: 60,
+			smoothing: 50,
+			..Default::default()
+		};
+		let id = Id::new();
+		let mut detector = AnomalyDetector::default();
+
+		detector.update(&counts(id, 1), &settings);
+		assert!(detector.models.contains_key(&id));
+
+		for _ in 0..10 {
+			detector.update(&HashMap::new(), &settings);
+		}
+		assert!(!detector.models.contains_key(&id));
+	}
+
+	#[test]
+	fn settings() {
+		assert!(!AnomalyDetection::default().is_enabled());
+		assert_eq!(
+			serde_json::from_str::<AnomalyDetection>(r#"{"interval": 30}"#).unwrap(),
+			AnomalyDetection {
+				interval: 30,
+				..Default::default()
+			}
+		);
+	}
+}
@@ -0,0 +1,243 @@
+//! Clocks used to timestamp statistics
+//!
+//! [`StatisticTime::now`] (and therefore [`Statistic::new`]) gets the current
+//! time from a global [`Clock`], which can be replaced with [`set_clock`]. By
+//! default, this is a [`MonotonicClock`], which follows the system's monotonic
+//! clock after being anchored to the wall clock, so that small wall clock
+//! adjustments (e.g. by NTP) don't move statistics back and forth between
+//! neighbouring time buckets. A [`ManualClock`] can be used instead to make
+//! statistics deterministic, e.g. in tests.
+//!
+//! [`StatisticTime::now`]: crate::stats::StatisticTime::now
+//! [`Statistic::new`]: crate::stats::Statistic::new
+
+use std::{
+	fmt::Debug,
+	sync::{
+		atomic::{AtomicI64, Ordering},
+		Arc, LazyLock,
+	},
+	time::Instant,
+};
+
+use parking_lot::{Mutex, RwLock};
+use time::{Duration, OffsetDateTime};
+use tracing::debug;
+
+/// The global clock used for statistic timestamps
+static CLOCK: LazyLock<RwLock<Arc<dyn Clock>>> =
+	LazyLock::new(|| RwLock::new(Arc::new(MonotonicClock::default())));
+
+/// Get the current time from the global statistics clock
+#[must_use]
+pub fn now() -> OffsetDateTime {
+	CLOCK.read().now()
+}
+
+/// Replace the global statistics clock, returning the previous one
+pub fn set_clock(clock: impl Clock + 'static) -> Arc<dyn Clock> {
+	std::mem::replace(&mut *CLOCK.write(), Arc::new(clock))
+}
+
+/// A source of the current (UTC) date and time
+pub trait Clock: Debug + Send + Sync {
+	/// Get the current date and time
+	fn now(&self) -> OffsetDateTime;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+	fn now(&self) -> OffsetDateTime {
+		(**self).now()
+	}
+}
+
+/// The system's wall clock, exactly as reported by the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> OffsetDateTime {
+		OffsetDateTime::now_utc()
+	}
+}
+
+/// A clock which only changes when explicitly told to
+#[derive(Debug)]
+pub struct ManualClock {
+	time: Mutex<OffsetDateTime>,
+}
+
+impl ManualClock {
+	/// Create a new [`ManualClock`] stopped at `time`
+	#[must_use]
+	pub const fn new(time: OffsetDateTime) -> Self {
+		Self {
+			time: Mutex::new(time),
+		}
+	}
+
+	/// Set this clock's time
+	pub fn set(&self, time: OffsetDateTime) {
+		*self.time.lock() = time;
+	}
+
+	/// Move this clock's time by `duration` (which may be negative)
+	pub fn advance(&self, duration: Duration) {
+		*self.time.lock() += duration;
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> OffsetDateTime {
+		*self.time.lock()
+	}
+}
+
+/// A clock which follows the monotonic clock from an anchor taken from a wall
+/// clock (by default the [`SystemClock`])
+///
+/// Differences between the wall clock and the anchored monotonic time smaller
+/// than the tolerance (e.g. caused by NTP adjustments) are ignored, and this
+/// clock never goes backwards because of them. Larger differences (e.g. after
+/// a manual clock change or a system suspend) are treated as a correction of
+/// the wall clock, after which this clock is re-anchored to the new time.
+///
+/// The anchor is kept in an atomic integer, so getting the time never blocks.
+/// If multiple threads re-anchor this clock at the same time, later times win
+/// when the wall clock jumped forward, and going backwards only happens if
+/// the anchor wasn't changed concurrently.
+#[derive(Debug)]
+pub struct MonotonicClock<C: Clock = SystemClock> {
+	wall: C,
+	tolerance: Duration,
+	/// The instant from which the monotonic time is measured
+	base: Instant,
+	/// The wall clock time at `base` (as a unix timestamp in nanoseconds)
+	anchor: AtomicI64,
+}
+
+impl<C: Clock> MonotonicClock<C> {
+	/// The default maximum tolerated difference between the wall clock and the
+	/// anchored monotonic time
+	pub const DEFAULT_TOLERANCE: Duration = Duration::minutes(1);
+
+	/// Create a new [`MonotonicClock`] anchored to the `wall` clock, tolerating
+	/// differences of up to `tolerance` between the two
+	pub fn new(wall: C, tolerance: Duration) -> Self {
+		let (base, anchor) = (Instant::now(), wall.now());
+
+		Self {
+			wall,
+			tolerance: tolerance.abs(),
+			base,
+			anchor: AtomicI64::new(unix_nanos(anchor)),
+		}
+	}
+}
+
+impl Default for MonotonicClock {
+	fn default() -> Self {
+		Self::new(SystemClock, Self::DEFAULT_TOLERANCE)
+	}
+}
+
+impl<C: Clock> Clock for MonotonicClock<C> {
+	fn now(&self) -> OffsetDateTime {
+		let anchor = self.anchor.load(Ordering::Acquire);
+		let elapsed = i64::try_from(self.base.elapsed().as_nanos()).unwrap_or(i64::MAX);
+		let wall = self.wall.now();
+		let monotonic = anchor.saturating_add(elapsed);
+
+		let difference = wall.unix_timestamp_nanos() - i128::from(monotonic);
+		if difference.abs() <= self.tolerance.whole_nanoseconds() {
+			if let Ok(monotonic) = OffsetDateTime::from_unix_timestamp_nanos(monotonic.into()) {
+				return monotonic;
+			}
+		}
+
+		debug!(%wall, monotonic_ns = monotonic, "Wall clock jump detected, re-anchoring statistics clock");
+		let new_anchor = unix_nanos(wall).saturating_sub(elapsed);
+		if new_anchor > anchor {
+			self.anchor.fetch_max(new_anchor, Ordering::AcqRel);
+		} else {
+			let _ = self.anchor.compare_exchange(
+				anchor,
+				new_anchor,
+				Ordering::AcqRel,
+				Ordering::Acquire,
+			);
+		}
+
+		wall
+	}
+}
+
+/// Get the `time` as a unix timestamp in nanoseconds, saturating at the bounds
+/// of `i64` (about the years 1677 and 2262)
+fn unix_nanos(time: OffsetDateTime) -> i64 {
+	let nanos = time
+		.unix_timestamp_nanos()
+		.clamp(i64::MIN.into(), i64::MAX.into());
+
+	i64::try_from(nanos).expect("the timestamp was clamped to the range of i64")
+}
+
+#[cfg(test)]
+mod tests {
+	use time::macros::datetime;
+
+	use super::*;
+	use crate::stats::{Statistic, StatisticTime, StatisticType};
+
+	#[test]
+	fn manual_clock() {
+		let clock = ManualClock::new(datetime!(2022-10-08 16:29:59 UTC));
+		assert_eq!(clock.now(), datetime!(2022-10-08 16:29:59 UTC));
+
+		clock.advance(Duration::seconds(2));
+		assert_eq!(clock.now(), datetime!(2022-10-08 16:30:01 UTC));
+
+		clock.set(datetime!(2000-01-01 00:00:00 UTC));
+		assert_eq!(clock.now(), datetime!(2000-01-01 00:00:00 UTC));
+	}
+
+	#[test]
+	fn monotonic_clock() {
+		let wall = Arc::new(ManualClock::new(datetime!(2022-10-08 16:29:59.5 UTC)));
+		let clock = MonotonicClock::new(Arc::clone(&wall), Duration::minutes(1));
+
+		let start = clock.now();
+		assert!(start >= datetime!(2022-10-08 16:29:59.5 UTC));
+		assert!(start < datetime!(2022-10-08 16:30:00 UTC));
+
+		// Small wall clock adjustments are ignored
+		wall.advance(Duration::seconds(30));
+		let now = clock.now();
+		assert!(now >= start && now < datetime!(2022-10-08 16:30:00 UTC));
+
+		wall.advance(Duration::seconds(-59));
+		let now = clock.now();
+		assert!(now >= start && now < datetime!(2022-10-08 16:30:00 UTC));
+
+		// Large jumps re-anchor the clock
+		wall.set(datetime!(2022-10-08 18:00:00 UTC));
+		let now = clock.now();
+		assert_eq!(now, datetime!(2022-10-08 18:00:00 UTC));
+
+		wall.set(datetime!(2022-10-08 17:00:00 UTC));
+		let now = clock.now();
+		assert_eq!(now, datetime!(2022-10-08 17:00:00 UTC));
+	}
+
+	#[test]
+	fn statistic_time() {
+		let clock = ManualClock::new(datetime!(2022-10-08 16:44:59.999 UTC));
+		let stat = Statistic::with_clock("example", StatisticType::Request, "", &clock);
+		assert_eq!(stat.time.to_string(), "2022-10-08T16:30:00Z");
+
+		clock.advance(Duration::milliseconds(1));
+		let stat = Statistic::with_clock("example", StatisticType::Request, "", &clock);
+		assert_eq!(stat.time.to_string(), "2022-10-08T16:45:00Z");
+		assert_eq!(stat.time, StatisticTime::from(clock.now()));
+	}
+}
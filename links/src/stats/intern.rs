@@ -0,0 +1,167 @@
+//! Interning of frequently used statistic data
+//!
+//! Many statistics share the same data (e.g. the same few user agents, hosts,
+//! or TLS cipher suites show up in the majority of requests). Instead of
+//! allocating a new string for each collected statistic, these values are
+//! deduplicated through a shared pool of reference-counted strings.
+
+use std::{
+	collections::HashSet,
+	hash::{BuildHasher, RandomState},
+	sync::{Arc, LazyLock},
+};
+
+use parking_lot::RwLock;
+
+/// The global pool used for interning [`StatisticData`] values
+///
+/// [`StatisticData`]: crate::stats::StatisticData
+pub static POOL: LazyLock<Interner> = LazyLock::new(|| Interner::new(Interner::DEFAULT_CAPACITY));
+
+/// A bounded pool of interned strings
+///
+/// The pool is split into shards (each with its own lock and an equal part of
+/// the capacity), which strings are assigned to by their hash. Once a shard is
+/// full, strings not referenced anywhere outside of it are evicted, so that
+/// frequently used values stay interned, while rarely seen ones are dropped.
+/// Finding those takes time proportional to the shard's size, so it is only
+/// done after as many strings weren't interned because the shard was full as
+/// the shard can hold. Strings which aren't interned are still returned, but
+/// are not added to the pool.
+#[derive(Debug)]
+pub struct Interner {
+	shards: Box<[RwLock<Shard>]>,
+	shard_capacity: usize,
+	hasher: RandomState,
+}
+
+/// A part of an [`Interner`]'s pool
+#[derive(Debug, Default)]
+struct Shard {
+	strings: HashSet<Arc<str>>,
+	/// How many strings weren't interned because this shard was full, since
+	/// unused strings were last evicted from it
+	misses: usize,
+}
+
+impl Interner {
+	/// The default maximum number of strings in an [`Interner`]
+	pub const DEFAULT_CAPACITY: usize = 4096;
+	/// The maximum length (in bytes) of strings that get interned, longer
+	/// strings are just allocated normally
+	pub const MAX_LEN: usize = 512;
+	/// The number of shards of an [`Interner`]
+	const SHARDS: usize = 16;
+
+	/// Create a new empty [`Interner`] holding at most (approximately)
+	/// `capacity` strings
+	pub fn new(capacity: usize) -> Self {
+		Self::with_shards(capacity, Self::SHARDS)
+	}
+
+	/// Create a new empty [`Interner`] holding at most (approximately)
+	/// `capacity` strings, split into `shards` shards
+	fn with_shards(capacity: usize, shards: usize) -> Self {
+		Self {
+			shards: (0..shards).map(|_| RwLock::default()).collect(),
+			shard_capacity: capacity.div_ceil(shards),
+			hasher: RandomState::new(),
+		}
+	}
+
+	/// Get the interned version of `s`, adding it to the pool if possible
+	pub fn intern(&self, s: &str) -> Arc<str> {
+		if s.len() > Self::MAX_LEN {
+			return Arc::from(s);
+		}
+
+		let i = usize::try_from(self.hasher.hash_one(s) % self.shards.len() as u64)
+			.expect("the shard index is less than the number of shards");
+		let shard = &self.shards[i];
+
+		if let Some(interned) = shard.read().strings.get(s) {
+			return Arc::clone(interned);
+		}
+
+		let mut shard = shard.write();
+
+		// Another thread could have interned this string in the meantime
+		if let Some(interned) = shard.strings.get(s) {
+			return Arc::clone(interned);
+		}
+
+		if shard.strings.len() >= self.shard_capacity {
+			shard.misses += 1;
+
+			if shard.misses >= self.shard_capacity {
+				shard.misses = 0;
+				shard.strings.retain(|s| Arc::strong_count(s) > 1);
+			}
+		}
+
+		let interned = Arc::<str>::from(s);
+
+		if shard.strings.len() < self.shard_capacity {
+			shard.strings.insert(Arc::clone(&interned));
+		}
+
+		interned
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn intern() {
+		let interner = Interner::with_shards(2, 1);
+		let len = || interner.shards[0].read().strings.len();
+
+		let a = interner.intern("a");
+		let b = interner.intern("b");
+		assert!(Arc::ptr_eq(&a, &interner.intern("a")));
+		assert!(Arc::ptr_eq(&b, &interner.intern("b")));
+		assert_eq!(len(), 2);
+
+		// The pool is full and everything is in use, so this isn't interned
+		let c = interner.intern("c");
+		assert!(!Arc::ptr_eq(&c, &interner.intern("c")));
+		assert_eq!(len(), 2);
+
+		// Unused strings get evicted to make room for new ones, but only after
+		// as many misses as the pool can hold
+		drop(b);
+		let c = interner.intern("c");
+		assert!(!Arc::ptr_eq(&c, &interner.intern("c")));
+		let c = interner.intern("c");
+		assert!(Arc::ptr_eq(&c, &interner.intern("c")));
+		assert!(Arc::ptr_eq(&a, &interner.intern("a")));
+		assert_eq!(len(), 2);
+
+		let long = "x".repeat(Interner::MAX_LEN + 1);
+		assert_eq!(&*interner.intern(&long), long);
+		assert_eq!(len(), 2);
+	}
+
+	#[test]
+	fn shards() {
+		let interner = Interner::new(Interner::DEFAULT_CAPACITY);
+		let strings = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+		let interned = strings
+			.iter()
+			.map(|s| interner.intern(s))
+			.collect::<Vec<_>>();
+
+		for (s, interned) in strings.iter().zip(&interned) {
+			assert!(Arc::ptr_eq(interned, &interner.intern(s)));
+		}
+
+		let len = interner
+			.shards
+			.iter()
+			.map(|shard| shard.read().strings.len())
+			.sum::<usize>();
+		assert_eq!(len, 100);
+	}
+}
@@ -2,10 +2,12 @@
 
 use std::{
 	fmt::{Display, Formatter, Result as FmtResult},
+	num::NonZeroU32,
 	str::FromStr,
+	sync::Arc,
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use strum::{Display as EnumDisplay, EnumString};
 use time::{
 	format_description::well_known::{
@@ -18,26 +20,56 @@ use time::{
 
 #[cfg(doc)]
 use crate::stats::Statistic;
+use crate::stats::{clock, intern::POOL};
 
 /// The data for a statistic
 ///
 /// This struct holds the data associated with a statistic, that along with the
 /// statistic's type and link comprises one full [`Statistic`]
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(transparent)]
+///
+/// The data is interned, so that frequently seen values (like common user
+/// agents or hosts) share a single allocation instead of being copied for
+/// every collected statistic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
 pub struct StatisticData {
-	data: String,
+	data: Arc<str>,
+}
+
+impl StatisticData {
+	/// Get this statistic data as a string slice
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.data
+	}
+}
+
+impl Default for StatisticData {
+	fn default() -> Self {
+		Self::from("")
+	}
 }
 
 impl From<&str> for StatisticData {
 	fn from(s: &str) -> Self {
-		Self { data: s.into() }
+		Self {
+			data: POOL.intern(s),
+		}
 	}
 }
 
 impl From<String> for StatisticData {
 	fn from(s: String) -> Self {
-		Self { data: s }
+		Self::from(s.as_str())
+	}
+}
+
+impl Serialize for StatisticData {
+	fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		ser.serialize_str(&self.data)
 	}
 }
 
@@ -61,7 +93,7 @@ impl Display for StatisticData {
 	clippy::unsafe_derive_deserialize,
 	reason = "false positive in the EPOCH constant"
 )]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(try_from = "&str", into = "String")]
 pub struct StatisticTime {
 	intervals: u32,
@@ -73,14 +105,21 @@ impl StatisticTime {
 	/// The resolution of a [`StatisticTime`] (15 minutes) in seconds
 	pub const RESOLUTION_SECS: i64 = 15 * 60;
 
-	/// Get the [`StatisticTime`] for now (the current time)
+	/// Get the [`StatisticTime`] for now (the current time), according to the
+	/// [global statistics clock][crate::stats::clock]
 	#[must_use]
 	pub fn now() -> Self {
-		let intervals =
-			(OffsetDateTime::now_utc() - Self::EPOCH).whole_seconds() / Self::RESOLUTION_SECS;
+		Self::from(clock::now())
+	}
 
+	/// Get the start of the time bucket containing this time, where each
+	/// bucket is `intervals` resolution intervals long (e.g. `4` for one hour
+	/// or `96` for one day). Buckets are aligned to [`EPOCH`][Self::EPOCH], so
+	/// for example daily buckets start at midnight UTC.
+	#[must_use]
+	pub const fn bucket(self, intervals: NonZeroU32) -> Self {
 		Self {
-			intervals: intervals.try_into().unwrap_or(u32::MAX),
+			intervals: self.intervals - self.intervals % intervals.get(),
 		}
 	}
 }
@@ -241,6 +280,13 @@ pub enum StatisticType {
 	///
 	/// [header]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Sec-CH-UA-Platform
 	UserAgentPlatform,
+	/// Number of redirects to the specified variant of a link with weighted
+	/// split destinations (see [link options][crate::options])
+	///
+	/// # Data
+	/// The name of the variant that the request was redirected to, or its
+	/// index (e.g. `0` or `1`) if it doesn't have a name
+	Variant,
 }
 
 #[cfg(test)]
@@ -268,6 +314,18 @@ mod tests {
 			"Some Arbitrary Text Data",
 			StatisticData::from("Some Arbitrary Text Data").to_string()
 		);
+
+		assert!(Arc::ptr_eq(
+			&StatisticData::from("Mozilla/5.0").data,
+			&StatisticData::from(String::from("Mozilla/5.0")).data
+		));
+
+		assert!(Arc::ptr_eq(
+			&StatisticData::from("TLS13_AES_256_GCM_SHA384").data,
+			&serde_json::from_str::<StatisticData>(r#""TLS13_AES_256_GCM_SHA384""#)
+				.unwrap()
+				.data
+		));
 	}
 
 	#[test]
@@ -289,6 +347,17 @@ mod tests {
 			datetime!(2022-10-08 16:34:25.159 UTC)
 		);
 
+		let time = StatisticTime::try_from("2022-10-08T16:34:25Z").unwrap();
+		assert_eq!(time.bucket(NonZeroU32::MIN), time);
+		assert_eq!(
+			time.bucket(NonZeroU32::new(4).unwrap()),
+			StatisticTime::try_from("2022-10-08T16:00:00Z").unwrap()
+		);
+		assert_eq!(
+			time.bucket(NonZeroU32::new(96).unwrap()),
+			StatisticTime::try_from("2022-10-08T00:00:00Z").unwrap()
+		);
+
 		assert_eq!(
 			OffsetDateTime::from(StatisticTime::try_from("2022-10-08T16:34:25.159Z").unwrap()),
 			datetime!(2022-10-08 16:30:00.000 UTC)
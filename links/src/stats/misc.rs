@@ -3,11 +3,12 @@
 use std::{
 	convert::Infallible,
 	fmt::{Display, Formatter, Result as FmtResult},
+	net::IpAddr,
 	str::FromStr,
 	sync::Arc,
 };
 
-use hyper::Version;
+use hyper::{HeaderMap, Version};
 use links_id::Id;
 use links_normalized::Normalized;
 use serde::{Deserialize, Serialize};
@@ -25,6 +26,147 @@ pub struct ExtraStatisticInfo {
 	pub tls_version: Option<ProtocolVersion>,
 	/// The negotiated TLS cipher suite, if any
 	pub tls_cipher_suite: Option<SupportedCipherSuite>,
+	/// The IP address of the client, if known. This is only used to choose a
+	/// link's destination (e.g. for [geo-targeting][crate::geo]), and is
+	/// never collected as a statistic.
+	pub client_ip: Option<IpAddr>,
+	/// The name (or index) of the split destination variant that the request
+	/// was redirected to, if any
+	pub variant: Option<Arc<str>>,
+}
+
+/// User agent information from the headers of an HTTP request, used both for
+/// [`StatisticType::UserAgent`], [`StatisticType::UserAgentMobile`], and
+/// [`StatisticType::UserAgentPlatform`] statistics, and for device-targeted
+/// link destinations (see [`crate::options`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientHints<'a> {
+	/// The value of the `Sec-CH-UA` header
+	pub sec_ch_ua: Option<&'a str>,
+	/// The value of the `Sec-CH-UA-Mobile` header
+	pub sec_ch_ua_mobile: Option<&'a str>,
+	/// The value of the `Sec-CH-UA-Platform` header
+	pub sec_ch_ua_platform: Option<&'a str>,
+	/// The value of the `User-Agent` header
+	pub user_agent: Option<&'a str>,
+}
+
+impl<'a> ClientHints<'a> {
+	/// Get the user agent information from the request `headers`, ignoring
+	/// any headers which aren't valid strings
+	#[must_use]
+	pub fn from_headers(headers: &'a HeaderMap) -> Self {
+		let get = |name| headers.get(name).and_then(|val| val.to_str().ok());
+
+		Self {
+			sec_ch_ua: get("sec-ch-ua"),
+			sec_ch_ua_mobile: get("sec-ch-ua-mobile"),
+			sec_ch_ua_platform: get("sec-ch-ua-platform"),
+			user_agent: get("user-agent"),
+		}
+	}
+
+	/// Get the brand information of the user agent, preferring the
+	/// `Sec-CH-UA` client hint to the `User-Agent` header
+	#[must_use]
+	pub fn brands(&self) -> Option<&'a str> {
+		self.sec_ch_ua.or(self.user_agent)
+	}
+
+	/// Check whether the user agent is on a mobile device, using the
+	/// `Sec-CH-UA-Mobile` client hint if available, and otherwise checking for
+	/// `Mobi` in the `User-Agent` header
+	#[must_use]
+	pub fn is_mobile(&self) -> Option<bool> {
+		match self.sec_ch_ua_mobile {
+			Some("?1") => Some(true),
+			Some("?0") => Some(false),
+			_ => self.user_agent.map(|ua| ua.contains("Mobi")),
+		}
+	}
+
+	/// Get the platform of the user agent, using the `Sec-CH-UA-Platform`
+	/// client hint if available, and otherwise guessing from the `User-Agent`
+	/// header (as e.g. Safari doesn't send client hints)
+	#[must_use]
+	pub fn platform(&self) -> Option<Platform> {
+		if let Some(platform) = self.sec_ch_ua_platform {
+			return Platform::from_client_hint(platform);
+		}
+
+		let ua = self.user_agent?;
+		if ["iPhone", "iPad", "iPod"].iter().any(|d| ua.contains(d)) {
+			Some(Platform::Ios)
+		} else if ua.contains("Android") {
+			Some(Platform::Android)
+		} else if ua.contains("CrOS") {
+			Some(Platform::ChromeOs)
+		} else if ua.contains("Windows") {
+			Some(Platform::Windows)
+		} else if ua.contains("Macintosh") {
+			Some(Platform::MacOs)
+		} else if ua.contains("Linux") {
+			Some(Platform::Linux)
+		} else {
+			None
+		}
+	}
+}
+
+/// The operating system platform of a user agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+	/// Android
+	Android,
+	/// iOS or iPadOS
+	Ios,
+	/// Windows
+	Windows,
+	/// macOS
+	MacOs,
+	/// Linux (other than Android or ChromeOS)
+	Linux,
+	/// ChromeOS
+	ChromeOs,
+}
+
+impl Platform {
+	/// Get this platform's name, as used for device-targeted link destinations
+	#[must_use]
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Android => "android",
+			Self::Ios => "ios",
+			Self::Windows => "windows",
+			Self::MacOs => "macos",
+			Self::Linux => "linux",
+			Self::ChromeOs => "chromeos",
+		}
+	}
+
+	/// Parse the value of a `Sec-CH-UA-Platform` header (e.g. `"macOS"`),
+	/// returning `None` for unknown platforms
+	fn from_client_hint(hint: &str) -> Option<Self> {
+		let hint = hint.trim_matches('"');
+
+		[
+			("Android", Self::Android),
+			("iOS", Self::Ios),
+			("Windows", Self::Windows),
+			("macOS", Self::MacOs),
+			("Linux", Self::Linux),
+			("Chrome OS", Self::ChromeOs),
+			("Chromium OS", Self::ChromeOs),
+		]
+		.into_iter()
+		.find_map(|(name, platform)| hint.eq_ignore_ascii_case(name).then_some(platform))
+	}
+}
+
+impl Display for Platform {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.write_str(self.as_str())
+	}
 }
 
 /// A links ID or vanity path
@@ -85,7 +227,7 @@ impl From<&IdOrVanity> for IdOrVanity {
 	reason = "using bitflags to save 3 bytes is a bit excessive for this"
 )]
 pub struct StatisticCategories {
-	/// Collect [`StatisticType::Request`]
+	/// Collect [`StatisticType::Request`] and [`StatisticType::Variant`]
 	pub redirect: bool,
 	/// Collect [`StatisticType::HostRequest`], [`StatisticType::SniRequest`],
 	/// and [`StatisticType::StatusCode`]
@@ -123,7 +265,7 @@ impl StatisticCategories {
 		use StatisticType::*;
 
 		match stat_type {
-			Request => self.redirect,
+			Request | Variant => self.redirect,
 			HostRequest | SniRequest | StatusCode => self.basic,
 			HttpVersion | TlsVersion | TlsCipherSuite => self.protocol,
 			UserAgent | UserAgentMobile | UserAgentPlatform => self.user_agent,
@@ -427,4 +569,51 @@ mod tests {
 			Version::HTTP_10
 		);
 	}
+
+	#[test]
+	fn client_hints() {
+		let mut headers = HeaderMap::new();
+		headers.insert("sec-ch-ua", r#""Chromium";v="130""#.parse().unwrap());
+		headers.insert("sec-ch-ua-mobile", "?1".parse().unwrap());
+		headers.insert("sec-ch-ua-platform", r#""Android""#.parse().unwrap());
+		headers.insert(
+			"user-agent",
+			"Mozilla/5.0 (Linux; Android 10; K) Chrome/130.0.0.0 Mobile Safari/537.36"
+				.parse()
+				.unwrap(),
+		);
+
+		let hints = ClientHints::from_headers(&headers);
+		assert_eq!(hints.brands(), Some(r#""Chromium";v="130""#));
+		assert_eq!(hints.is_mobile(), Some(true));
+		assert_eq!(hints.platform(), Some(Platform::Android));
+
+		// Safari doesn't send any client hints
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			"user-agent",
+			"Mozilla/5.0 (iPhone; CPU iPhone OS 18_0 like Mac OS X) Version/18.0 Mobile/15E148 \
+			 Safari/604.1"
+				.parse()
+				.unwrap(),
+		);
+
+		let hints = ClientHints::from_headers(&headers);
+		assert_eq!(hints.brands(), hints.user_agent);
+		assert_eq!(hints.is_mobile(), Some(true));
+		assert_eq!(hints.platform(), Some(Platform::Ios));
+
+		let mut headers = HeaderMap::new();
+		headers.insert("sec-ch-ua-mobile", "?0".parse().unwrap());
+		headers.insert("sec-ch-ua-platform", r#""Chrome OS""#.parse().unwrap());
+
+		let hints = ClientHints::from_headers(&headers);
+		assert_eq!(hints.brands(), None);
+		assert_eq!(hints.is_mobile(), Some(false));
+		assert_eq!(hints.platform(), Some(Platform::ChromeOs));
+
+		let hints = ClientHints::default();
+		assert_eq!(hints.is_mobile(), None);
+		assert_eq!(hints.platform(), None);
+	}
 }
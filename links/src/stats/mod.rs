@@ -25,14 +25,22 @@
 //! collection may fail. None of these situations are considered critical
 //! errors; statistics are not an integral part of links.
 
+pub mod anomaly;
+pub mod clock;
+mod intern;
 mod internals;
 mod misc;
 
-use std::num::NonZeroU64;
+use std::{
+	collections::HashMap,
+	fmt::{Display, Formatter, Result as FmtResult},
+	num::{NonZeroU32, NonZeroU64},
+};
 
 use hyper::{http::HeaderValue, Request, StatusCode};
 use serde::{Deserialize, Serialize};
 
+use self::clock::Clock;
 pub use self::{internals::*, misc::*};
 
 /// A links statistic
@@ -56,7 +64,7 @@ pub struct Statistic {
 
 impl Statistic {
 	/// Create a new [`Statistic`] from the provided information and the current
-	/// time
+	/// time according to the [global statistics clock][clock]
 	pub fn new(
 		link: impl Into<IdOrVanity>,
 		stat_type: StatisticType,
@@ -70,6 +78,22 @@ impl Statistic {
 		}
 	}
 
+	/// Create a new [`Statistic`] from the provided information and the
+	/// current time according to `clock`
+	pub fn with_clock(
+		link: impl Into<IdOrVanity>,
+		stat_type: StatisticType,
+		data: impl Into<StatisticData>,
+		clock: &dyn Clock,
+	) -> Self {
+		Self {
+			link: link.into(),
+			stat_type,
+			data: data.into(),
+			time: clock.now().into(),
+		}
+	}
+
 	/// Get all statistics from the provided [`ExtraStatisticInfo`] and other
 	/// miscellaneous data. Only statistics specified by `categories` are
 	/// returned.
@@ -77,6 +101,7 @@ impl Statistic {
 	/// The returned value is an iterator over statistics with some or all of
 	/// the following types:
 	/// - [`StatisticType::Request`]
+	/// - [`StatisticType::Variant`]
 	/// - [`StatisticType::StatusCode`]
 	/// - [`StatisticType::SniRequest`]
 	/// - [`StatisticType::TlsVersion`]
@@ -100,6 +125,12 @@ impl Statistic {
 					));
 				}
 
+				if categories.specifies(StatisticType::Variant) {
+					if let Some(variant) = stat_info.variant {
+						stats.push(Self::new(link, StatisticType::Variant, variant.to_string()));
+					}
+				}
+
 				if categories.specifies(StatisticType::StatusCode) {
 					stats.push(Self::new(
 						link,
@@ -174,26 +205,22 @@ impl Statistic {
 					}
 				}
 
-				if categories.user_agent {
-					if let Some(Ok(val)) = headers.get("sec-ch-ua").map(HeaderValue::to_str) {
-						stats.push(Self::new(link, StatisticType::UserAgent, val));
-					} else if let Some(Ok(val)) = headers.get("user-agent").map(HeaderValue::to_str)
-					{
+				let hints = ClientHints::from_headers(headers);
+
+				if categories.specifies(StatisticType::UserAgent) {
+					if let Some(val) = hints.brands() {
 						stats.push(Self::new(link, StatisticType::UserAgent, val));
 					}
 				}
 
 				if categories.specifies(StatisticType::UserAgentMobile) {
-					if let Some(Ok(val)) = headers.get("sec-ch-ua-mobile").map(HeaderValue::to_str)
-					{
+					if let Some(val) = hints.sec_ch_ua_mobile {
 						stats.push(Self::new(link, StatisticType::UserAgentMobile, val));
 					}
 				}
 
 				if categories.specifies(StatisticType::UserAgentPlatform) {
-					if let Some(Ok(val)) =
-						headers.get("sec-ch-ua-platform").map(HeaderValue::to_str)
-					{
+					if let Some(val) = hints.sec_ch_ua_platform {
 						stats.push(Self::new(link, StatisticType::UserAgentPlatform, val));
 					}
 				}
@@ -204,6 +231,18 @@ impl Statistic {
 	}
 }
 
+impl Display for Statistic {
+	/// Format this statistic as `link:type:time:data`, e.g.
+	/// `example:user_agent_platform:2022-10-02T14:30:00Z:Windows`
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		write!(
+			fmt,
+			"{}:{}:{}:{}",
+			self.link, self.stat_type, self.time, self.data
+		)
+	}
+}
+
 /// A description of one or more [`Statistic`]s, where some fields may be
 /// omitted so that they act as a wildcard
 ///
@@ -238,6 +277,96 @@ impl StatisticDescription {
 	}
 }
 
+/// The fields by which [`Statistic`]s are grouped when summarizing them
+///
+/// All statistics which have the same values in the grouped fields are part of
+/// the same group, and their values are summed up into one [`StatisticTotal`].
+/// If no fields are grouped, all statistics are summed up into a single total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StatisticGrouping {
+	/// Group statistics by their link
+	pub link: bool,
+	/// Group statistics by their type
+	pub stat_type: bool,
+	/// Group statistics by their data
+	pub data: bool,
+	/// Group statistics by their time, in buckets of this many 15 minute
+	/// intervals (see [`StatisticTime::bucket`])
+	pub time: Option<NonZeroU32>,
+}
+
+impl StatisticGrouping {
+	/// Sum up the values of the provided statistics per group, returning one
+	/// [`StatisticTotal`] for each group, sorted by the grouped fields
+	#[must_use]
+	pub fn summarize(
+		self,
+		statistics: impl IntoIterator<Item = (Statistic, StatisticValue)>,
+	) -> Vec<StatisticTotal> {
+		let mut groups = HashMap::new();
+
+		for (stat, value) in statistics {
+			let key = (
+				self.link.then_some(stat.link),
+				self.stat_type.then_some(stat.stat_type),
+				self.data.then_some(stat.data),
+				self.time.map(|intervals| stat.time.bucket(intervals)),
+			);
+
+			let (total, count) = groups.entry(key).or_insert((0u64, 0u64));
+			*total = total.saturating_add(value.get());
+			*count += 1;
+		}
+
+		let mut totals = groups
+			.into_iter()
+			.map(
+				|((link, stat_type, data, time), (total, count))| StatisticTotal {
+					link,
+					stat_type,
+					data,
+					time,
+					total,
+					count,
+				},
+			)
+			.collect::<Vec<_>>();
+
+		totals.sort_by_cached_key(|total| {
+			(
+				total.link.as_ref().map(ToString::to_string),
+				total.stat_type.as_ref().map(ToString::to_string),
+				total.data.as_ref().map(ToString::to_string),
+				total.time,
+			)
+		});
+
+		totals
+	}
+}
+
+/// The total of the values of a group of [`Statistic`]s, see
+/// [`StatisticGrouping`]
+///
+/// Only the fields by which the statistics were grouped are `Some(...)`, all
+/// others are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StatisticTotal {
+	/// The ID or vanity path of the link of the statistics in this group
+	pub link: Option<IdOrVanity>,
+	/// The type of the statistics in this group
+	#[serde(rename = "type")]
+	pub stat_type: Option<StatisticType>,
+	/// The data of the statistics in this group
+	pub data: Option<StatisticData>,
+	/// The start of the time bucket of the statistics in this group
+	pub time: Option<StatisticTime>,
+	/// The sum of the values of all statistics in this group
+	pub total: u64,
+	/// The number of statistics in this group
+	pub count: u64,
+}
+
 /// The value of a links statistic
 ///
 /// A [`StatisticValue`] represents the number of requests matching a particular
@@ -340,6 +469,8 @@ mod tests {
 				tls_sni: Some("example.com".into()),
 				tls_version: Some(ProtocolVersion::TLSv1_3),
 				tls_cipher_suite: Some(ALL_CIPHER_SUITES[0]),
+				client_ip: None,
+				variant: Some("b".into()),
 			},
 			StatusCode::TEMPORARY_REDIRECT,
 			StatisticCategories::ALL,
@@ -348,6 +479,7 @@ mod tests {
 		.collect::<Vec<_>>();
 
 		assert!(stats.contains(&StatisticType::Request));
+		assert!(stats.contains(&StatisticType::Variant));
 		assert!(stats.contains(&StatisticType::StatusCode));
 		assert!(stats.contains(&StatisticType::SniRequest));
 		assert!(stats.contains(&StatisticType::TlsVersion));
@@ -554,4 +686,80 @@ mod tests {
 		let stat_val = stat_val.increment();
 		assert_eq!(stat_val.get(), u64::MAX);
 	}
+
+	#[test]
+	fn statistic_grouping() {
+		let stat = |link: &str, stat_type, data: &str, time: &str| Statistic {
+			link: Normalized::new(link).into(),
+			stat_type,
+			data: data.into(),
+			time: time.parse().unwrap(),
+		};
+
+		let stats = [
+			(
+				stat("a", StatisticType::Request, "", "2022-10-08T16:00:00Z"),
+				StatisticValue::new(3).unwrap(),
+			),
+			(
+				stat("a", StatisticType::Request, "", "2022-10-08T16:45:00Z"),
+				StatisticValue::new(2).unwrap(),
+			),
+			(
+				stat(
+					"a",
+					StatisticType::StatusCode,
+					"308",
+					"2022-10-08T17:00:00Z",
+				),
+				StatisticValue::new(5).unwrap(),
+			),
+			(
+				stat("b", StatisticType::Request, "", "2022-10-08T16:15:00Z"),
+				StatisticValue::new(1).unwrap(),
+			),
+		];
+
+		let totals = StatisticGrouping::default().summarize(stats.clone());
+		assert_eq!(totals, [StatisticTotal {
+			link: None,
+			stat_type: None,
+			data: None,
+			time: None,
+			total: 11,
+			count: 4,
+		}]);
+
+		let totals = StatisticGrouping {
+			link: true,
+			..Default::default()
+		}
+		.summarize(stats.clone());
+		assert_eq!(totals.len(), 2);
+		assert_eq!(totals[0].link, Some(Normalized::new("a").into()));
+		assert_eq!((totals[0].total, totals[0].count), (10, 3));
+		assert_eq!(totals[1].link, Some(Normalized::new("b").into()));
+		assert_eq!((totals[1].total, totals[1].count), (1, 1));
+
+		let totals = StatisticGrouping {
+			stat_type: true,
+			time: NonZeroU32::new(4),
+			..Default::default()
+		}
+		.summarize(stats);
+		assert_eq!(totals.len(), 2);
+		assert_eq!(totals[0].stat_type, Some(StatisticType::Request));
+		assert_eq!(
+			totals[0].time,
+			Some("2022-10-08T16:00:00Z".parse().unwrap())
+		);
+		assert_eq!((totals[0].total, totals[0].count), (6, 3));
+		assert_eq!(totals[1].stat_type, Some(StatisticType::StatusCode));
+		assert_eq!(
+			totals[1].time,
+			Some("2022-10-08T17:00:00Z".parse().unwrap())
+		);
+		assert_eq!((totals[1].total, totals[1].count), (5, 1));
+		assert!(totals.iter().all(|t| t.link.is_none() && t.data.is_none()));
+	}
 }
@@ -7,19 +7,302 @@
 //! different storage backends, and to make developing them fast. For details
 //! about configuring each store backend, see that backend's documentation.
 
-use core::fmt::Debug;
-use std::collections::HashMap;
+use core::{
+	fmt::{Debug, Display},
+	future::Future,
+	pin::Pin,
+	time::Duration,
+};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use futures_util::{future::try_join_all, stream, Stream, TryStreamExt};
 use links_id::Id;
 use links_normalized::{Link, Normalized};
+use serde::{Deserialize, Serialize};
+use strum::{Display as EnumDisplay, EnumString, IntoStaticStr};
+use time::OffsetDateTime;
+use tokio::time::timeout;
 
 use crate::{
+	options::LinkOptions,
 	stats::{Statistic, StatisticDescription, StatisticValue},
 	store::BackendType,
 };
 
+/// How long a store backend's health check may take before the backend is
+/// considered unhealthy
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A single item of data exported from a store backend, see
+/// [`StoreBackend::export_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportItem {
+	/// A redirect from an ID to a link
+	Redirect {
+		/// The ID being redirected from
+		id: Id,
+		/// The link being redirected to
+		link: Link,
+	},
+	/// A vanity path pointing to an ID
+	Vanity {
+		/// The vanity path
+		vanity: Normalized,
+		/// The ID the vanity path points to
+		id: Id,
+	},
+	/// The options of a link
+	Options {
+		/// The ID of the link
+		id: Id,
+		/// The link's options
+		options: LinkOptions,
+	},
+	/// A statistic and its value
+	Statistic {
+		/// The statistic
+		statistic: Statistic,
+		/// The statistic's value
+		value: StatisticValue,
+	},
+}
+
+/// A stream of all data in a store backend, returned by
+/// [`StoreBackend::export_all`]
+pub type ExportStream<'a> = Pin<Box<dyn Stream<Item = Result<ExportItem>> + Send + 'a>>;
+
+/// A page of redirects, vanity paths, or statistics, see
+/// [`StoreBackend::list_redirects`], [`StoreBackend::list_vanities`], and
+/// [`StoreBackend::get_statistics_page`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<K, V> {
+	/// The items on this page, sorted by their keys' string representation
+	/// (or in a backend-specific order for statistics)
+	pub items: Vec<(K, V)>,
+	/// The cursor to get the next page with, or `None` if this is the last
+	/// page
+	pub next: Option<K>,
+}
+
+impl<K: Display + Clone, V> Page<K, V> {
+	/// Create a page of up to `limit` items from all `items` (in any order),
+	/// containing the ones sorted after the `cursor` (if any)
+	pub(crate) fn from_unsorted(
+		items: impl IntoIterator<Item = (K, V)>,
+		cursor: Option<&K>,
+		limit: usize,
+	) -> Self {
+		let cursor = cursor.map(ToString::to_string);
+		let mut items = items
+			.into_iter()
+			.map(|(key, value)| (key.to_string(), key, value))
+			.filter(|(key, ..)| cursor.as_ref().map_or(true, |cursor| key > cursor))
+			.collect::<Vec<_>>();
+		items.sort_unstable_by(|(a, ..), (b, ..)| a.cmp(b));
+
+		Self::from_sorted(items.into_iter().map(|(_, key, value)| (key, value)), limit)
+	}
+
+	/// Create a page of up to `limit` items from the `items`, which must
+	/// already be sorted and only contain items after the cursor. Only up to
+	/// `limit + 1` items are consumed, to check whether there is a next page.
+	pub(crate) fn from_sorted(items: impl IntoIterator<Item = (K, V)>, limit: usize) -> Self {
+		let mut items = items
+			.into_iter()
+			.take(limit.saturating_add(1))
+			.collect::<Vec<_>>();
+		let more = items.len() > limit;
+		items.truncate(limit);
+
+		Self {
+			next: items.last().filter(|_| more).map(|(key, _)| key.clone()),
+			items,
+		}
+	}
+}
+
+/// The health of a store backend, see [`StoreBackend::health`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+	/// Whether the store backend is reachable and working
+	pub healthy: bool,
+	/// How long the health check took
+	pub latency: Duration,
+	/// Why the store backend is unhealthy, if it is
+	pub error: Option<String>,
+}
+
+impl Health {
+	/// Check the health of a store backend by running the `check`, which is
+	/// considered failed if it returns an error or takes longer than
+	/// [`HEALTH_CHECK_TIMEOUT`]
+	pub(crate) async fn check(check: impl Future<Output = Result<()>> + Send) -> Self {
+		let start = Instant::now();
+		let res = timeout(HEALTH_CHECK_TIMEOUT, check).await;
+		let latency = start.elapsed();
+
+		let error = match res {
+			Ok(Ok(())) => None,
+			Ok(Err(err)) => Some(err.to_string()),
+			Err(_) => Some("health check timed out".to_string()),
+		};
+
+		Self {
+			healthy: error.is_none(),
+			latency,
+			error,
+		}
+	}
+}
+
+/// An error of a store operation which is not caused by the store backend
+/// failing, so retrying the operation wouldn't help. These errors are neither
+/// retried nor counted by the circuit breaker, see [`Retry`][super::Retry].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum NonTransient {
+	/// The operation is not supported by this store backend
+	#[error("{0} not supported by this store backend")]
+	Unsupported(&'static str),
+	/// A redirect with this ID already exists (e.g. when restoring a removed
+	/// redirect)
+	#[error("a redirect with this ID already exists")]
+	RedirectExists,
+}
+
+impl NonTransient {
+	/// Check whether the `err` (or any error in its chain) is non-transient
+	#[must_use]
+	pub fn is_cause_of(err: &anyhow::Error) -> bool {
+		err.chain().any(|err| err.is::<Self>())
+	}
+}
+
+/// What to do when importing a redirect, vanity path, or link options which
+/// already exist in the store with a different value, see
+/// [`StoreBackend::import`]
+#[derive(
+	Copy,
+	Clone,
+	Debug,
+	Default,
+	PartialEq,
+	Eq,
+	Serialize,
+	Deserialize,
+	EnumString,
+	EnumDisplay,
+	IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ImportPolicy {
+	/// Keep the existing value
+	#[default]
+	Skip,
+	/// Replace the existing value with the imported one
+	Overwrite,
+	/// Stop importing and return an error
+	Error,
+}
+
+impl ImportPolicy {
+	/// Check whether the `new` value should be written over the `existing` one,
+	/// returning an error for conflicts if this is [`ImportPolicy::Error`].
+	/// Values identical to the existing ones are never written.
+	fn should_write<T: PartialEq>(
+		self,
+		existing: Option<&T>,
+		new: &T,
+		name: impl FnOnce() -> String,
+	) -> Result<bool> {
+		match (existing, self) {
+			(Some(existing), _) if existing == new => Ok(false),
+			(None, _) | (Some(_), Self::Overwrite) => Ok(true),
+			(Some(_), Self::Skip) => Ok(false),
+			(Some(_), Self::Error) => bail!("{} already exists with a different value", name()),
+		}
+	}
+}
+
+/// The number of items imported and skipped by [`StoreBackend::import`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+	/// The number of items written to the store
+	pub imported: usize,
+	/// The number of items not written, because they already existed, or
+	/// because they are not supported by the store backend
+	pub skipped: usize,
+}
+
+/// Import the `item` into the `store` (if `write` is set, otherwise only check
+/// what would be done), with conflicts resolved according to the `policy`.
+/// Returns whether the item was (or would be) written. Statistics are never
+/// written.
+pub(crate) async fn import_item<S: StoreBackend + ?Sized>(
+	store: &S,
+	item: ExportItem,
+	policy: ImportPolicy,
+	write: bool,
+) -> Result<bool> {
+	let overwrite = policy == ImportPolicy::Overwrite;
+
+	match item {
+		ExportItem::Redirect { id, link } => {
+			let existing = if overwrite {
+				None
+			} else {
+				store.get_redirect(id).await?
+			};
+
+			Ok(
+				policy.should_write(existing.as_ref(), &link, || format!("redirect {id}"))?
+					&& (!write || store.set_redirect(id, link).await.map(|_| true)?),
+			)
+		}
+		ExportItem::Vanity { vanity, id } => {
+			let existing = if overwrite {
+				None
+			} else {
+				store.get_vanity(vanity.clone()).await?
+			};
+
+			Ok(
+				policy.should_write(existing.as_ref(), &id, || format!("vanity {vanity}"))?
+					&& (!write || store.set_vanity(vanity, id).await.map(|_| true)?),
+			)
+		}
+		ExportItem::Options { id, options } => {
+			let existing = if overwrite {
+				None
+			} else {
+				store.get_options(id).await?
+			};
+
+			Ok(
+				policy.should_write(existing.as_ref(), &options, || format!("options of {id}"))?
+					&& (!write || store.set_options(id, options).await.map(|_| true)?),
+			)
+		}
+		ExportItem::Statistic { .. } => Ok(false),
+	}
+}
+
+/// Create an [`ExportStream`] from a stream of pages of exported items, for
+/// backends which fetch their data in batches
+pub(crate) fn export_pages<'a>(
+	pages: impl Stream<Item = Result<Vec<ExportItem>>> + Send + 'a,
+) -> ExportStream<'a> {
+	Box::pin(
+		pages
+			.map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+			.try_flatten(),
+	)
+}
+
 /// The redirect, vanity path, and statistics store trait used by links.
 #[async_trait]
 pub trait StoreBackend: Debug + Send + Sync {
@@ -51,6 +334,22 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// considered an error.
 	async fn get_redirect(&self, from: Id) -> Result<Option<Link>>;
 
+	/// Get multiple redirects at once. Returns the full `to` links
+	/// corresponding to each of the `from` IDs, in the same order, with `None`
+	/// for IDs without a redirect.
+	///
+	/// By default, this function gets each redirect concurrently using
+	/// [`get_redirect`][StoreBackend::get_redirect]. Backends which can get
+	/// multiple values in a single round-trip should override this.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if a link exists or not, or what it is. A link not existing is not
+	/// considered an error.
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		try_join_all(from.iter().map(|&id| self.get_redirect(id))).await
+	}
+
 	/// Set a redirect. `from` is the ID of the link, while `to` is the full
 	/// destination link. If a mapping with this ID already exists, it must be
 	/// changed to the new one, returning the old one.
@@ -62,6 +361,49 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// insofar as that is possible to determine from the backend.
 	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>>;
 
+	/// Set multiple redirects at once. Works like
+	/// [`set_redirect`][StoreBackend::set_redirect] for each of the
+	/// `redirects` in order, returning the old links in the same order.
+	///
+	/// By default, this function sets each redirect one after another using
+	/// [`set_redirect`][StoreBackend::set_redirect]. Backends which can set
+	/// multiple values in a single round-trip should override this.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all new values were definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, some of the values may have been set already,
+	/// unless the backend sets all of them atomically.
+	async fn set_redirects(&self, redirects: &[(Id, Link)]) -> Result<Vec<Option<Link>>> {
+		let mut old = Vec::with_capacity(redirects.len());
+		for (from, to) in redirects {
+			old.push(self.set_redirect(*from, to.clone()).await?);
+		}
+
+		Ok(old)
+	}
+
+	/// Set a redirect that expires at `expires_at`. Works like
+	/// [`set_redirect`][StoreBackend::set_redirect], but after `expires_at`,
+	/// the redirect is treated as if it didn't exist (and is removed from the
+	/// store, either immediately if the backend supports expiry natively, or
+	/// lazily otherwise). Setting the redirect again without an expiry removes
+	/// the expiry.
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if redirect expiry is not supported by this
+	/// backend, or if something fails when it should have worked.
+	async fn set_redirect_with_expiry(
+		&self,
+		_from: Id,
+		_to: Link,
+		_expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		bail!(NonTransient::Unsupported("redirect expiry is"))
+	}
+
 	/// Remove a redirect. `from` is the ID of the links link to be removed.
 	/// Returns the old value of the mapping or `None` if there was no such
 	/// mapping.
@@ -73,6 +415,108 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// modified, insofar as that is possible to determine from the backend.
 	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>>;
 
+	/// Remove multiple redirects at once. Works like
+	/// [`rem_redirect`][StoreBackend::rem_redirect] for each of the IDs in
+	/// `from` in order, returning the old links in the same order.
+	///
+	/// By default, this function removes each redirect one after another using
+	/// [`rem_redirect`][StoreBackend::rem_redirect]. Backends which can remove
+	/// multiple values in a single round-trip should override this.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all values were definitely removed / processed /
+	/// saved, and will be unavailable on next request.
+	/// If an `Err` is returned, some of the values may have been removed
+	/// already, unless the backend removes all of them atomically.
+	async fn rem_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let mut old = Vec::with_capacity(from.len());
+		for &id in from {
+			old.push(self.rem_redirect(id).await?);
+		}
+
+		Ok(old)
+	}
+
+	/// Remove a redirect, but keep a tombstone of it until `keep_until`, so
+	/// that it can be restored using
+	/// [`restore_redirect`][StoreBackend::restore_redirect] until then.
+	/// Returns the old value of the mapping or `None` if there was no such
+	/// mapping (in which case no tombstone is created). Removing a redirect
+	/// again replaces its tombstone.
+	///
+	/// By default this function returns an error
+	///
+	/// # Storage Guarantees
+	/// The same as for [`rem_redirect`][StoreBackend::rem_redirect].
+	///
+	/// # Error
+	/// An error is returned if soft deletion is not supported by this backend,
+	/// or if something fails when it should have worked.
+	async fn rem_redirect_soft(
+		&self,
+		_from: Id,
+		_keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		bail!(NonTransient::Unsupported("soft deletion is"))
+	}
+
+	/// Restore a redirect removed using
+	/// [`rem_redirect_soft`][StoreBackend::rem_redirect_soft] from its
+	/// tombstone, removing the tombstone. Returns the restored link, or `None`
+	/// if there is no (unexpired) tombstone for the ID. Restored redirects
+	/// never expire, even if they did before they were removed.
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if soft deletion is not supported by this backend,
+	/// if a redirect with the ID already exists, or if something fails when it
+	/// should have worked.
+	async fn restore_redirect(&self, _from: Id) -> Result<Option<Link>> {
+		bail!(NonTransient::Unsupported("soft deletion is"))
+	}
+
+	/// Permanently remove all tombstones of soft-deleted redirects, so that
+	/// they can no longer be restored. Returns the number of removed
+	/// tombstones, which may or may not include already expired ones.
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if soft deletion is not supported by this backend,
+	/// or if something fails when it should have worked.
+	async fn purge_redirects(&self) -> Result<usize> {
+		bail!(NonTransient::Unsupported("soft deletion is"))
+	}
+
+	/// Find all redirects to the `link`. Returns the IDs of all (non-expired)
+	/// redirects whose `to` link is exactly `link`, sorted by ID.
+	///
+	/// By default, this function searches all redirects using
+	/// [`export_all`][StoreBackend::export_all], which is slow for large
+	/// stores. Backends which can maintain a secondary index of redirects by
+	/// their links should override this.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails. No redirects to
+	/// the `link` existing is not considered an error.
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		let mut ids = self
+			.export_all()
+			.await?
+			.try_filter_map(|item| async move {
+				Ok(match item {
+					ExportItem::Redirect { id, link: to } if to == *link => Some(id),
+					_ => None,
+				})
+			})
+			.try_collect::<Vec<_>>()
+			.await?;
+		ids.sort_unstable();
+
+		Ok(ids)
+	}
+
 	/// Get a vanity path's ID. Returns the ID of the `to` link corresponding
 	/// to the `from` vanity path. An ID not existing is not an error, if no
 	/// matching ID is found, `None` is returned.
@@ -83,6 +527,22 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// considered an error.
 	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>>;
 
+	/// Get the IDs of multiple vanity paths at once. Returns the IDs
+	/// corresponding to each of the `from` vanity paths, in the same order,
+	/// with `None` for vanity paths which don't exist.
+	///
+	/// By default, this function gets each vanity path concurrently using
+	/// [`get_vanity`][StoreBackend::get_vanity]. Backends which can get
+	/// multiple values in a single round-trip should override this.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if a link exists or not, or what it is. A link not existing is not
+	/// considered an error.
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		try_join_all(from.iter().map(|vanity| self.get_vanity(vanity.clone()))).await
+	}
+
 	/// Set a vanity path for an ID. `from` is the vanity path of the links ID,
 	/// while `to` is the ID itself. If a vanity link with this path already
 	/// exists, it must be changed to the new one, returning the old one.
@@ -94,6 +554,28 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// insofar as that is possible to determine from the backend.
 	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>>;
 
+	/// Set multiple vanity paths at once. Works like
+	/// [`set_vanity`][StoreBackend::set_vanity] for each of the `vanities` in
+	/// order, returning the old IDs in the same order.
+	///
+	/// By default, this function sets each vanity path one after another using
+	/// [`set_vanity`][StoreBackend::set_vanity]. Backends which can set
+	/// multiple values in a single round-trip should override this.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all new values were definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, some of the values may have been set already,
+	/// unless the backend sets all of them atomically.
+	async fn set_vanities(&self, vanities: &[(Normalized, Id)]) -> Result<Vec<Option<Id>>> {
+		let mut old = Vec::with_capacity(vanities.len());
+		for (from, to) in vanities {
+			old.push(self.set_vanity(from.clone(), *to).await?);
+		}
+
+		Ok(old)
+	}
+
 	/// Remove a vanity path. `from` is the vanity path to be removed. Returns
 	/// the old value of the mapping or `None` if there was no such mapping.
 	///
@@ -104,6 +586,65 @@ pub trait StoreBackend: Debug + Send + Sync {
 	/// modified, insofar as that is possible to determine from the backend.
 	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>>;
 
+	/// Get all vanity paths of an ID. Returns all vanity paths corresponding to
+	/// the `id`, sorted by their string representation.
+	///
+	/// By default, this function searches all vanity paths using
+	/// [`export_all`][StoreBackend::export_all], which is slow for large
+	/// stores. Backends which can maintain a secondary index of vanity paths by
+	/// their IDs should override this.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails. The ID not having
+	/// any vanity paths is not considered an error.
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		let mut vanities = self
+			.export_all()
+			.await?
+			.try_filter_map(|item| async move {
+				Ok(match item {
+					ExportItem::Vanity { vanity, id: to } if to == id => Some(vanity),
+					_ => None,
+				})
+			})
+			.try_collect::<Vec<_>>()
+			.await?;
+		vanities.sort_unstable();
+
+		Ok(vanities)
+	}
+
+	/// Get a link's options. Returns the [`LinkOptions`] of the link with the
+	/// ID `id`. Options not existing is not an error, if no options are found,
+	/// `None` is returned.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if options exist or not, or what they are. Options not existing is not
+	/// considered an error.
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>>;
+
+	/// Set a link's options. `id` is the ID of the link, while `options` are
+	/// its new options. If the link already has options, they must be replaced
+	/// with the new ones, returning the old ones.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, the new value was definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, the value must not have been set / modified,
+	/// insofar as that is possible to determine from the backend.
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>>;
+
+	/// Remove a link's options. `id` is the ID of the link whose options are
+	/// to be removed. Returns the old options or `None` if there were none.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, the new value was definitely removed /
+	/// processed / saved, and will be unavailable on next request.
+	/// If an `Err` is returned, the value must not have been removed /
+	/// modified, insofar as that is possible to determine from the backend.
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>>;
+
 	/// Get statistics' values by their description. Returns all matching
 	/// [`Statistic`]s and their values for the provided
 	/// [`StatisticDescription`]. Statistics not having been collected is not an
@@ -123,6 +664,32 @@ pub trait StoreBackend: Debug + Send + Sync {
 		Ok(Vec::new())
 	}
 
+	/// Get up to `limit` statistics' values by their description, starting
+	/// after the `cursor` (the `next` cursor of the previous page, or `None`
+	/// for the first page). The order of the statistics depends on the
+	/// backend, but is the same for all pages. Changes made while paging may or
+	/// may not be seen on later pages.
+	///
+	/// By default, this function gets all matching statistics using
+	/// [`get_statistics`][StoreBackend::get_statistics] for every page, and
+	/// sorts them by their string representation, which is slow for large
+	/// stores.
+	///
+	/// # Error
+	/// An error is only returned if something fails when it should have worked.
+	/// A statistic not existing or the store not supporting statistics is not
+	/// considered an error.
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		let stats = self.get_statistics(description).await?;
+
+		Ok(Page::from_unsorted(stats, cursor.as_ref(), limit))
+	}
+
 	/// Increment a statistic's count. The provided [`Statistic`]'s value is
 	/// incremented by 1. Returns the new value of the statistic after the
 	/// increment, or `None` if the statistic wasn't recorded or its new value
@@ -156,4 +723,177 @@ pub trait StoreBackend: Debug + Send + Sync {
 	) -> Result<Vec<(Statistic, StatisticValue)>> {
 		Ok(Vec::new())
 	}
+
+	/// Increment a usage counter, used to track
+	/// [quotas][crate::ratelimit::Quota] of API tokens and the
+	/// [clicks][crate::options::LinkOptions::max_clicks] of links. The counter
+	/// with the `key` is incremented by `amount`, and its new value is
+	/// returned. Counters which don't exist yet start at `0`. The counter is
+	/// no longer needed after `expires_at`, and may be removed at
+	/// any time after that.
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if usage counters are not supported by this
+	/// backend, or if something fails when it should have worked.
+	async fn incr_usage(
+		&self,
+		_key: &str,
+		_amount: u64,
+		_expires_at: OffsetDateTime,
+	) -> Result<u64> {
+		bail!(NonTransient::Unsupported("usage counters are"))
+	}
+
+	/// Check whether this store backend supports
+	/// [usage counters][StoreBackend::incr_usage]. Backends which implement
+	/// `incr_usage` must override this to return `true`.
+	///
+	/// By default this function returns `false`
+	fn supports_usage(&self) -> bool {
+		false
+	}
+
+	/// Check whether this store backend is reachable and working, and how long
+	/// it takes to respond. This never returns an error, failures are reported
+	/// as an unhealthy [`Health`] instead.
+	///
+	/// By default, this function reads a redirect which (most likely) doesn't
+	/// exist. Backends which cache reads or have a cheaper way to check their
+	/// connection should override this.
+	async fn health(&self) -> Health {
+		Health::check(async {
+			self.get_redirect(Id::from([0; 5])).await?;
+			Ok(())
+		})
+		.await
+	}
+
+	/// Get a store backend for the `namespace`, which uses the same underlying
+	/// storage (e.g. database connection) as this one, but has completely
+	/// separate redirects, vanity paths, options, and statistics. This is
+	/// only called on backends which aren't namespaced themselves, with a
+	/// `namespace` already validated by
+	/// [`Store::with_namespace`][crate::store::Store::with_namespace].
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if namespaces are not supported by this backend,
+	/// or if the namespace's storage could not be set up.
+	async fn namespaced(&self, _namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		bail!(NonTransient::Unsupported("namespaces are"))
+	}
+
+	/// Export all data in this store. Returns a stream of all redirects, vanity
+	/// paths, link options, and statistics, in no particular order. The data
+	/// is not guaranteed to be a consistent snapshot, changes made while the
+	/// stream is being consumed may or may not be included. Expired redirects
+	/// are not exported, but the expiry times of other redirects are not
+	/// included, so they are exported as if they never expire.
+	///
+	/// By default this function returns an error
+	///
+	/// # Error
+	/// An error is returned if exporting is not supported by this backend, or
+	/// if something fails when it should have worked. Errors may be returned
+	/// both by this function and by the returned stream.
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		bail!(NonTransient::Unsupported("exporting is"))
+	}
+
+	/// List up to `limit` redirects, sorted by the string representation of
+	/// their IDs, starting after the `cursor` (the `next` cursor of the
+	/// previous page, or `None` for the first page). Expired redirects are not
+	/// listed. Changes made while listing may or may not be seen on later
+	/// pages.
+	///
+	/// By default, this function lists all redirects using
+	/// [`export_all`][StoreBackend::export_all] for every page, which is slow
+	/// for large stores.
+	///
+	/// # Error
+	/// An error is returned if listing is not supported by this backend, or if
+	/// something fails when it should have worked.
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		let redirects = self
+			.export_all()
+			.await?
+			.try_filter_map(|item| async move {
+				Ok(match item {
+					ExportItem::Redirect { id, link } => Some((id, link)),
+					_ => None,
+				})
+			})
+			.try_collect::<Vec<_>>()
+			.await?;
+
+		Ok(Page::from_unsorted(redirects, cursor.as_ref(), limit))
+	}
+
+	/// List up to `limit` vanity paths, sorted by their string
+	/// representation, starting after the `cursor` (the `next` cursor of the
+	/// previous page, or `None` for the first page). Changes made while
+	/// listing may or may not be seen on later pages.
+	///
+	/// By default, this function lists all vanity paths using
+	/// [`export_all`][StoreBackend::export_all] for every page, which is slow
+	/// for large stores.
+	///
+	/// # Error
+	/// An error is returned if listing is not supported by this backend, or if
+	/// something fails when it should have worked.
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		let vanities = self
+			.export_all()
+			.await?
+			.try_filter_map(|item| async move {
+				Ok(match item {
+					ExportItem::Vanity { vanity, id } => Some((vanity, id)),
+					_ => None,
+				})
+			})
+			.try_collect::<Vec<_>>()
+			.await?;
+
+		Ok(Page::from_unsorted(vanities, cursor.as_ref(), limit))
+	}
+
+	/// Import data into this store. Writes all redirects, vanity paths, and
+	/// link options from the `items` (e.g. from another store's
+	/// [`export_all`][StoreBackend::export_all]) to this store, with
+	/// conflicts with existing data resolved according to the `policy`.
+	/// Returns the number of imported and skipped items.
+	///
+	/// By default, this function imports items one at a time using this
+	/// store's getters and setters, and skips all statistics. The import is
+	/// not atomic, if an error is returned, some items may have already been
+	/// imported.
+	///
+	/// # Error
+	/// An error is returned if an item conflicts with existing data and the
+	/// `policy` is [`ImportPolicy::Error`], if the `items` stream returns an
+	/// error, or if something fails when it should have worked.
+	async fn import(
+		&self,
+		mut items: ExportStream<'_>,
+		policy: ImportPolicy,
+	) -> Result<ImportSummary> {
+		let mut summary = ImportSummary::default();
+
+		while let Some(item) = items.try_next().await? {
+			if import_item(self, item, policy, true).await? {
+				summary.imported += 1;
+			} else {
+				summary.skipped += 1;
+			}
+		}
+
+		Ok(summary)
+	}
 }
@@ -0,0 +1,713 @@
+//! A generic read-through cache for any [`StoreBackend`], keeping recently used
+//! redirects, vanity paths, and link options in memory to avoid round-trips to
+//! the underlying store for frequently used links.
+//!
+//! Cached values (including the absence of a redirect, vanity path, or options)
+//! expire after a configurable time. Writes made through the cached backend
+//! update its cache immediately, but changes made in any other way (e.g. by
+//! other links instances sharing the same store) are only seen after the
+//! cached value expires. Similarly, expiring redirects may still be used for up
+//! to the cache TTL after they expire. Namespaces of the wrapped backend are
+//! not cached.
+
+use std::{
+	collections::HashMap,
+	future::Future,
+	hash::Hash,
+	num::NonZeroUsize,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use lru::LruCache;
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+	options::LinkOptions,
+	stats::{Statistic, StatisticDescription, StatisticValue},
+	store::{
+		backend::{ExportStream, Health, Page},
+		BackendType, StoreBackend,
+	},
+};
+
+/// How long values are cached for if not configured (in seconds)
+const DEFAULT_TTL: u64 = 10;
+
+/// A [`StoreBackend`] wrapping another backend `B`, with an in-memory LRU cache
+/// of redirects, vanity paths, and link options.
+///
+/// # Configuration
+///
+/// The cache is configured together with the wrapped backend, using the
+/// following options in addition to the backend's own:
+/// - `read_cache_size`: The maximum number of cached redirects, vanity paths,
+///   and link options (each separately). **Required**, must not be `0`.
+/// - `read_cache_ttl`: How long values are cached for (in seconds). **Default
+///   `10`**.
+#[derive(Debug)]
+pub struct Cached<B> {
+	inner: B,
+	redirects: TtlCache<Id, Link>,
+	vanity: TtlCache<Normalized, Id>,
+	options: TtlCache<Id, LinkOptions>,
+}
+
+impl<B: StoreBackend> Cached<B> {
+	/// Check whether the `config` enables the read cache, i.e. whether it
+	/// contains a non-zero `read_cache_size`
+	///
+	/// # Errors
+	/// Returns an error if `read_cache_size` is not a valid number
+	pub fn is_enabled(config: &HashMap<String, String>) -> Result<bool> {
+		Ok(config
+			.get("read_cache_size")
+			.map_or(Ok(0), |s| s.parse::<usize>())?
+			!= 0)
+	}
+
+	/// Wrap the `inner` backend in a cache holding up to `size` redirects,
+	/// vanity paths, and link options each, for `ttl`
+	pub fn with_backend(inner: B, size: NonZeroUsize, ttl: Duration) -> Self {
+		Self {
+			inner,
+			redirects: TtlCache::new(size, ttl),
+			vanity: TtlCache::new(size, ttl),
+			options: TtlCache::new(size, ttl),
+		}
+	}
+
+	/// Invalidate the cached redirect and options of the link with the `id`
+	fn invalidate_link(&self, id: &Id) {
+		self.redirects.invalidate(id);
+		self.options.invalidate(id);
+	}
+}
+
+#[async_trait]
+impl<B: StoreBackend> StoreBackend for Cached<B> {
+	fn store_type() -> BackendType
+	where
+		Self: Sized,
+	{
+		B::store_type()
+	}
+
+	fn get_store_type(&self) -> BackendType {
+		self.inner.get_store_type()
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn new(config: &HashMap<String, String>) -> Result<Self> {
+		let size = config
+			.get("read_cache_size")
+			.ok_or_else(|| anyhow!("missing read_cache_size option"))?
+			.parse::<usize>()?;
+		let size =
+			NonZeroUsize::new(size).ok_or_else(|| anyhow!("read_cache_size must not be 0"))?;
+		let ttl = config
+			.get("read_cache_ttl")
+			.map_or(Ok(DEFAULT_TTL), |s| s.parse())?;
+
+		Ok(Self::with_backend(
+			B::new(config).await?,
+			size,
+			Duration::from_secs(ttl),
+		))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
+		if let Some(cached) = self.redirects.get(&from) {
+			return Ok(cached);
+		}
+
+		let version = self.redirects.version();
+		let link = self.inner.get_redirect(from).await?;
+		self.redirects.insert(version, from, link.clone());
+
+		Ok(link)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		self.redirects
+			.get_many(from, |missing| async move {
+				self.inner.get_redirects(&missing).await
+			})
+			.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
+		let res = self.inner.set_redirect(from, to).await;
+		self.redirects.invalidate(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirects(&self, redirects: &[(Id, Link)]) -> Result<Vec<Option<Link>>> {
+		let res = self.inner.set_redirects(redirects).await;
+		for (from, _) in redirects {
+			self.redirects.invalidate(from);
+		}
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let res = self
+			.inner
+			.set_redirect_with_expiry(from, to, expires_at)
+			.await;
+		self.redirects.invalidate(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let res = self.inner.rem_redirect(from).await;
+		self.invalidate_link(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let res = self.inner.rem_redirects(from).await;
+		for id in from {
+			self.invalidate_link(id);
+		}
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let res = self.inner.rem_redirect_soft(from, keep_until).await;
+		self.invalidate_link(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let res = self.inner.restore_redirect(from).await;
+		self.invalidate_link(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn purge_redirects(&self) -> Result<usize> {
+		self.inner.purge_redirects().await
+	}
+
+	/// Not cached, always uses the inner store
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		self.inner.find_by_link(link).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		if let Some(cached) = self.vanity.get(&from) {
+			return Ok(cached);
+		}
+
+		let version = self.vanity.version();
+		let id = self.inner.get_vanity(from.clone()).await?;
+		self.vanity.insert(version, from, id);
+
+		Ok(id)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		self.vanity
+			.get_many(from, |missing| async move {
+				self.inner.get_vanities(&missing).await
+			})
+			.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
+		let res = self.inner.set_vanity(from.clone(), to).await;
+		self.vanity.invalidate(&from);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanities(&self, vanities: &[(Normalized, Id)]) -> Result<Vec<Option<Id>>> {
+		let res = self.inner.set_vanities(vanities).await;
+		for (from, _) in vanities {
+			self.vanity.invalidate(from);
+		}
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		let res = self.inner.rem_vanity(from.clone()).await;
+		self.vanity.invalidate(&from);
+		res
+	}
+
+	/// Not cached, always uses the inner store
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		self.inner.get_vanities_of(id).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		if let Some(cached) = self.options.get(&id) {
+			return Ok(cached);
+		}
+
+		let version = self.options.version();
+		let options = self.inner.get_options(id).await?;
+		self.options.insert(version, id, options.clone());
+
+		Ok(options)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		let res = self.inner.set_options(id, options).await;
+		self.options.invalidate(&id);
+		res
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let res = self.inner.rem_options(id).await;
+		self.options.invalidate(&id);
+		res
+	}
+
+	async fn get_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		self.inner.get_statistics(description).await
+	}
+
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		self.inner
+			.get_statistics_page(description, cursor, limit)
+			.await
+	}
+
+	async fn incr_statistic(&self, statistic: Statistic) -> Result<Option<StatisticValue>> {
+		self.inner.incr_statistic(statistic).await
+	}
+
+	async fn rem_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		self.inner.rem_statistics(description).await
+	}
+
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		self.inner.incr_usage(key, amount, expires_at).await
+	}
+
+	fn supports_usage(&self) -> bool {
+		self.inner.supports_usage()
+	}
+
+	async fn health(&self) -> Health {
+		self.inner.health().await
+	}
+
+	/// Namespaced stores are not cached, and use the wrapped backend directly
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		self.inner.namespaced(namespace).await
+	}
+
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		self.inner.export_all().await
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		self.inner.list_redirects(cursor, limit).await
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		self.inner.list_vanities(cursor, limit).await
+	}
+}
+
+/// An LRU cache with entries expiring after a fixed time
+#[derive(Debug)]
+struct TtlCache<K: Hash + Eq, V> {
+	entries: Mutex<LruCache<K, (Option<V>, Instant)>>,
+	ttl: Duration,
+	/// Incremented on every invalidation, so that values read before an
+	/// invalidation are not cached after it
+	version: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+	/// Create a new cache holding up to `size` entries for `ttl` each
+	fn new(size: NonZeroUsize, ttl: Duration) -> Self {
+		Self {
+			entries: Mutex::new(LruCache::new(size)),
+			ttl,
+			version: AtomicU64::new(0),
+		}
+	}
+
+	/// Get the current version of this cache, to be passed to
+	/// [`TtlCache::insert`]
+	fn version(&self) -> u64 {
+		self.version.load(Ordering::Acquire)
+	}
+
+	/// Get the cached value of the `key`, if it is cached and not expired. The
+	/// inner `None` means that the value is known not to exist.
+	#[expect(
+		clippy::option_option,
+		reason = "the outer option is whether the key is cached, the inner one is its value"
+	)]
+	fn get(&self, key: &K) -> Option<Option<V>> {
+		let mut entries = self.entries.lock();
+
+		match entries.get(key) {
+			Some((value, cached)) if cached.elapsed() < self.ttl => Some(value.clone()),
+			Some(_) => {
+				entries.pop(key);
+				None
+			}
+			None => None,
+		}
+	}
+
+	/// Cache the `value` of the `key`, unless anything was invalidated since
+	/// `version`
+	fn insert(&self, version: u64, key: K, value: Option<V>) {
+		let mut entries = self.entries.lock();
+
+		if self.version() == version {
+			entries.put(key, (value, Instant::now()));
+		}
+	}
+
+	/// Get the values of all `keys`, using `fetch` to get the values of the
+	/// ones which aren't cached (all at once, in the same order) and caching
+	/// them
+	async fn get_many<F: Future<Output = Result<Vec<Option<V>>>> + Send>(
+		&self,
+		keys: &[K],
+		fetch: impl FnOnce(Vec<K>) -> F + Send,
+	) -> Result<Vec<Option<V>>>
+	where
+		K: Clone + Send + Sync,
+		V: Send + Sync,
+	{
+		let version = self.version();
+		let cached = keys.iter().map(|key| self.get(key)).collect::<Vec<_>>();
+		let missing = keys
+			.iter()
+			.zip(&cached)
+			.filter(|(_, cached)| cached.is_none())
+			.map(|(key, _)| key.clone())
+			.collect::<Vec<_>>();
+
+		let fetched = if missing.is_empty() {
+			Vec::new()
+		} else {
+			fetch(missing.clone()).await?
+		};
+
+		if fetched.len() != missing.len() {
+			bail!("the store returned the wrong number of values");
+		}
+
+		let mut fetched = missing.into_iter().zip(fetched).map(|(key, value)| {
+			self.insert(version, key, value.clone());
+			value
+		});
+
+		Ok(cached
+			.into_iter()
+			.map(|cached| cached.unwrap_or_else(|| fetched.next().flatten()))
+			.collect())
+	}
+
+	/// Remove the `key` from the cache
+	fn invalidate(&self, key: &K) {
+		let mut entries = self.entries.lock();
+		self.version.fetch_add(1, Ordering::AcqRel);
+		entries.pop(key);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::store::{tests, Memory};
+
+	async fn get_store() -> Cached<Memory> {
+		Cached::new(&HashMap::from([(
+			"read_cache_size".to_string(),
+			"100".to_string(),
+		)]))
+		.await
+		.unwrap()
+	}
+
+	#[test]
+	fn store_type() {
+		tests::store_type::<Cached<Memory>>();
+	}
+
+	#[tokio::test]
+	async fn get_store_type() {
+		tests::get_store_type::<Cached<Memory>>(&get_store().await);
+	}
+
+	#[tokio::test]
+	async fn get_redirect() {
+		tests::get_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_redirects() {
+		tests::get_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect() {
+		tests::set_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirects() {
+		tests::set_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirect() {
+		tests::rem_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirects() {
+		tests::rem_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn soft_delete() {
+		tests::soft_delete(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect_with_expiry() {
+		tests::set_redirect_with_expiry(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn find_by_link() {
+		tests::find_by_link(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanity() {
+		tests::get_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities() {
+		tests::get_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanity() {
+		tests::set_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanities() {
+		tests::set_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_vanity() {
+		tests::rem_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities_of() {
+		tests::get_vanities_of(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_options() {
+		tests::get_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_options() {
+		tests::set_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_options() {
+		tests::rem_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics() {
+		tests::get_statistics(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics_page() {
+		tests::get_statistics_page(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn incr_statistic() {
+		tests::incr_statistic(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_statistics() {
+		tests::rem_statistics(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn incr_usage() {
+		tests::incr_usage(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn export_all() {
+		tests::export_all(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn import() {
+		tests::import(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_redirects() {
+		tests::list_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_vanities() {
+		tests::list_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn health() {
+		tests::health(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn namespaced() {
+		tests::namespaced(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn caching() {
+		let store = get_store().await;
+		let id = Id::from([0x13, 0x23, 0x33, 0x43, 0x53]);
+		let link = Link::new("https://example.com/cached").unwrap();
+
+		// Missing values are cached
+		assert_eq!(store.get_redirect(id).await.unwrap(), None);
+		store.inner.set_redirect(id, link.clone()).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), None);
+
+		// Writes through the cache invalidate it
+		store.set_redirect(id, link.clone()).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		store.inner.rem_redirect(id).await.unwrap();
+		assert!(store.get_redirect(id).await.unwrap().is_some());
+		store.rem_redirect(id).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn caching_options() {
+		let store = get_store().await;
+		let id = Id::from([0x13, 0x23, 0x33, 0x43, 0x54]);
+		let options = LinkOptions {
+			max_clicks: Some(10),
+			..LinkOptions::default()
+		};
+
+		// Missing options are cached
+		assert_eq!(store.get_options(id).await.unwrap(), None);
+		store.inner.set_options(id, options.clone()).await.unwrap();
+		assert_eq!(store.get_options(id).await.unwrap(), None);
+
+		// Writes through the cache invalidate it
+		store.set_options(id, options.clone()).await.unwrap();
+		assert_eq!(store.get_options(id).await.unwrap(), Some(options.clone()));
+		store.inner.rem_options(id).await.unwrap();
+		assert!(store.get_options(id).await.unwrap().is_some());
+		store.rem_options(id).await.unwrap();
+		assert_eq!(store.get_options(id).await.unwrap(), None);
+
+		// Removing a redirect invalidates its link's options
+		store.inner.set_options(id, options).await.unwrap();
+		assert_eq!(store.get_options(id).await.unwrap(), None);
+		store.rem_redirect(id).await.unwrap();
+		assert!(store.get_options(id).await.unwrap().is_some());
+	}
+
+	#[test]
+	fn ttl_cache() {
+		let cache = TtlCache::new(NonZeroUsize::MIN, Duration::from_secs(60));
+
+		cache.insert(cache.version(), 1, Some("a"));
+		assert_eq!(cache.get(&1), Some(Some("a")));
+
+		// Only one entry fits
+		cache.insert(cache.version(), 2, None);
+		assert_eq!(cache.get(&1), None);
+		assert_eq!(cache.get(&2), Some(None));
+
+		// Values read before an invalidation are not cached
+		let version = cache.version();
+		cache.invalidate(&2);
+		cache.insert(version, 2, Some("b"));
+		assert_eq!(cache.get(&2), None);
+
+		// Expired values are not returned
+		let cache = TtlCache::new(NonZeroUsize::MIN, Duration::ZERO);
+		cache.insert(cache.version(), 1, Some("a"));
+		assert_eq!(cache.get(&1), None);
+	}
+}
@@ -0,0 +1,282 @@
+//! At-rest encryption of data stored by file-based store backends, using
+//! AES-256-GCM with a key read from a file or an environment variable.
+//!
+//! Encrypted values are stored as base64 strings of the nonce followed by the
+//! ciphertext (including the authentication tag). Values which need to be
+//! looked up by their plaintext (e.g. database keys) can be encrypted
+//! deterministically, using a nonce derived from the plaintext, so that the
+//! same plaintext always results in the same ciphertext.
+//!
+//! Other values are authenticated together with where they are stored (e.g.
+//! their database key, as stored), so that a ciphertext moved or copied to a
+//! different key fails to decrypt, instead of silently changing the value of
+//! that key.
+
+use std::{collections::HashMap, env, fmt::Debug, fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+	aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+	hmac,
+	rand::{SecureRandom, SystemRandom},
+};
+
+/// The length of encryption keys, in bytes
+const KEY_LEN: usize = 32;
+
+/// An AES-256-GCM cipher for encrypting store data at rest.
+///
+/// # Configuration
+///
+/// Encryption is configured using one of the following store options:
+/// - `encryption_key_file`: The path of a file containing the base64-encoded
+///   256-bit encryption key.
+/// - `encryption_key_env`: The name of an environment variable containing the
+///   base64-encoded 256-bit encryption key.
+///
+/// A new key can be generated using e.g. `openssl rand -base64 32`.
+pub struct Cipher {
+	key: LessSafeKey,
+	nonce_key: hmac::Key,
+	rng: SystemRandom,
+}
+
+impl Cipher {
+	/// Create a new cipher from the store `config`, if encryption is
+	/// configured.
+	///
+	/// # Errors
+	/// This returns an error if both key options are set, or if the key can
+	/// not be read or is invalid.
+	pub fn from_config(config: &HashMap<String, String>) -> Result<Option<Self>> {
+		let key = match (
+			config.get("encryption_key_file"),
+			config.get("encryption_key_env"),
+		) {
+			(None, None) => return Ok(None),
+			(Some(_), Some(_)) => {
+				bail!("only one of encryption_key_file and encryption_key_env can be set")
+			}
+			(Some(path), None) => fs::read_to_string(Path::new(path))
+				.map_err(|err| anyhow!("couldn't read encryption key file: {err}"))?,
+			(None, Some(var)) => env::var(var)
+				.map_err(|err| anyhow!("couldn't read encryption key from {var}: {err}"))?,
+		};
+
+		let key = STANDARD
+			.decode(key.trim())
+			.map_err(|err| anyhow!("couldn't decode encryption key: {err}"))?;
+
+		Ok(Some(Self::new(&key)?))
+	}
+
+	/// Create a new cipher from the raw 256-bit `key`
+	///
+	/// # Errors
+	/// This returns an error if the key is not exactly 256 bits long.
+	pub fn new(key: &[u8]) -> Result<Self> {
+		if key.len() != KEY_LEN {
+			bail!(
+				"encryption key must be {KEY_LEN} bytes long, not {}",
+				key.len()
+			);
+		}
+
+		// Separate keys are derived for encryption and nonce derivation
+		let master = hmac::Key::new(hmac::HMAC_SHA256, key);
+		let derive = |purpose: &[u8]| hmac::sign(&master, purpose);
+
+		let key = UnboundKey::new(&AES_256_GCM, derive(b"links encryption").as_ref())
+			.map_err(|_| anyhow!("invalid encryption key"))?;
+		let nonce_key = hmac::Key::new(hmac::HMAC_SHA256, derive(b"links nonce").as_ref());
+
+		Ok(Self {
+			key: LessSafeKey::new(key),
+			nonce_key,
+			rng: SystemRandom::new(),
+		})
+	}
+
+	/// Encrypt `plaintext` with a random nonce, authenticating it together
+	/// with the additional data `aad` (e.g. the stored key of the value), which
+	/// must be the same when decrypting it
+	///
+	/// # Errors
+	/// This returns an error if no random nonce could be generated.
+	pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<String> {
+		let mut nonce = [0; NONCE_LEN];
+		self.rng
+			.fill(&mut nonce)
+			.map_err(|_| anyhow!("couldn't generate nonce"))?;
+
+		self.seal(nonce, plaintext, aad)
+	}
+
+	/// Encrypt `plaintext` deterministically, so that the same plaintext
+	/// always results in the same ciphertext. This reveals whether two
+	/// encrypted values are equal, so should only be used where that is needed
+	/// (e.g. for database keys). No additional data is authenticated, so the
+	/// ciphertext is decrypted with an empty `aad`.
+	///
+	/// # Errors
+	/// This returns an error if encryption fails.
+	pub fn encrypt_deterministic(&self, plaintext: &[u8]) -> Result<String> {
+		let mut nonce = [0; NONCE_LEN];
+		nonce.copy_from_slice(&hmac::sign(&self.nonce_key, plaintext).as_ref()[..NONCE_LEN]);
+
+		self.seal(nonce, plaintext, &[])
+	}
+
+	/// Encrypt the `plaintext` with the `nonce`, authenticating the `aad`
+	fn seal(&self, nonce: [u8; NONCE_LEN], plaintext: &[u8], aad: &[u8]) -> Result<String> {
+		let mut data = plaintext.to_vec();
+		self.key
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(aad),
+				&mut data,
+			)
+			.map_err(|_| anyhow!("couldn't encrypt data"))?;
+
+		let mut res = nonce.to_vec();
+		res.append(&mut data);
+		Ok(STANDARD.encode(res))
+	}
+
+	/// Decrypt `ciphertext` encrypted by [`encrypt`][Self::encrypt] with the
+	/// same `aad`, or by [`encrypt_deterministic`][Self::encrypt_deterministic]
+	/// (with an empty `aad`)
+	///
+	/// # Errors
+	/// This returns an error if the ciphertext is malformed, was modified, or
+	/// was encrypted with a different key or additional data.
+	pub fn decrypt(&self, ciphertext: &str, aad: &[u8]) -> Result<Vec<u8>> {
+		let data = STANDARD
+			.decode(ciphertext)
+			.map_err(|_| anyhow!("couldn't decrypt data: invalid encoding"))?;
+
+		if data.len() < NONCE_LEN {
+			bail!("couldn't decrypt data: too short");
+		}
+
+		let (nonce, data) = data.split_at(NONCE_LEN);
+		let nonce = Nonce::try_assume_unique_for_key(nonce)
+			.map_err(|_| anyhow!("couldn't decrypt data: invalid nonce"))?;
+
+		let mut data = data.to_vec();
+		let len = self
+			.key
+			.open_in_place(nonce, Aad::from(aad), &mut data)
+			.map_err(|_| anyhow!("couldn't decrypt data: wrong key or corrupted data"))?
+			.len();
+		data.truncate(len);
+
+		Ok(data)
+	}
+
+	/// Decrypt `ciphertext` as a UTF-8 string, see [`decrypt`][Self::decrypt]
+	///
+	/// # Errors
+	/// This returns an error if decryption fails, or the plaintext is not
+	/// valid UTF-8.
+	pub fn decrypt_str(&self, ciphertext: &str, aad: &[u8]) -> Result<String> {
+		Ok(String::from_utf8(self.decrypt(ciphertext, aad)?)?)
+	}
+}
+
+impl Debug for Cipher {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Cipher").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let cipher = Cipher::new(&[7; KEY_LEN]).unwrap();
+
+		let encrypted = cipher.encrypt(b"https://example.com/secret", b"a").unwrap();
+		assert!(!encrypted.contains("example"));
+		assert_eq!(
+			cipher.decrypt(&encrypted, b"a").unwrap(),
+			b"https://example.com/secret"
+		);
+
+		// Random nonces make every ciphertext different
+		assert_ne!(
+			encrypted,
+			cipher.encrypt(b"https://example.com/secret", b"a").unwrap()
+		);
+
+		// Deterministic encryption gives the same ciphertext every time
+		let a = cipher.encrypt_deterministic(b"statistic").unwrap();
+		assert_eq!(a, cipher.encrypt_deterministic(b"statistic").unwrap());
+		assert_ne!(a, cipher.encrypt_deterministic(b"statistics").unwrap());
+		assert_eq!(cipher.decrypt_str(&a, &[]).unwrap(), "statistic");
+	}
+
+	#[test]
+	fn swapped_ciphertext() {
+		let cipher = Cipher::new(&[7; KEY_LEN]).unwrap();
+
+		let a = cipher.encrypt(b"https://example.com/a", b"a").unwrap();
+		let b = cipher.encrypt(b"https://example.com/b", b"b").unwrap();
+
+		// Ciphertexts only decrypt with the additional data of their own key
+		assert!(cipher.decrypt(&a, b"b").is_err());
+		assert!(cipher.decrypt(&b, b"a").is_err());
+		assert!(cipher.decrypt(&a, &[]).is_err());
+		assert_eq!(cipher.decrypt(&b, b"b").unwrap(), b"https://example.com/b");
+	}
+
+	#[test]
+	fn wrong_key() {
+		let encrypted = Cipher::new(&[1; KEY_LEN])
+			.unwrap()
+			.encrypt(b"data", &[])
+			.unwrap();
+		let other = Cipher::new(&[2; KEY_LEN]).unwrap();
+
+		assert!(other.decrypt(&encrypted, &[]).is_err());
+		assert!(other.decrypt("not base64!", &[]).is_err());
+		assert!(other.decrypt("", &[]).is_err());
+		assert!(Cipher::new(&[1; 16]).is_err());
+	}
+
+	#[test]
+	fn from_config() {
+		assert!(Cipher::from_config(&HashMap::new()).unwrap().is_none());
+
+		let path = env::temp_dir().join("links-encryption-key-test");
+		fs::write(&path, format!("{}\n", STANDARD.encode([3; KEY_LEN]))).unwrap();
+		let config = HashMap::from([(
+			"encryption_key_file".to_string(),
+			path.to_string_lossy().into_owned(),
+		)]);
+		let cipher = Cipher::from_config(&config).unwrap().unwrap();
+		let from_key = Cipher::new(&[3; KEY_LEN]).unwrap();
+		assert_eq!(
+			from_key
+				.decrypt(&cipher.encrypt(b"data", &[]).unwrap(), &[])
+				.unwrap(),
+			b"data"
+		);
+		fs::remove_file(&path).unwrap();
+
+		let config = HashMap::from([(
+			"encryption_key_env".to_string(),
+			"LINKS_TEST_MISSING_ENCRYPTION_KEY".to_string(),
+		)]);
+		assert!(Cipher::from_config(&config).is_err());
+
+		let config = HashMap::from([
+			("encryption_key_file".to_string(), "a".to_string()),
+			("encryption_key_env".to_string(), "b".to_string()),
+		]);
+		assert!(Cipher::from_config(&config).is_err());
+	}
+}
@@ -0,0 +1,1955 @@
+//! An etcd-backed [`StoreBackend`] implementation, storing all data in an etcd
+//! cluster. This store backend is useful when links is deployed alongside
+//! other Kubernetes-native infrastructure, which already uses etcd, and allows
+//! any number of links instances to share the same data.
+//!
+//! This uses the etcd v3 gRPC API, and is tested with and developed against
+//! etcd 3.5.
+//!
+//! In etcd, data is stored with keys in the following format (with the
+//! configured prefix, by default `links/`):
+//! - `links/redirect/[ID]` for redirects (with string values of URLs)
+//! - `links/vanity/[vanity]` for vanity paths (with string values of IDs)
+//! - `links/options/[ID]` for link options (with json string values)
+//! - `links/tombstone/[ID]` for soft-deleted redirects (with string values of
+//!   URLs, attached to a lease which expires when the tombstone does)
+//! - `links/stat/[statistic]` for statistics (with json keys and decimal
+//!   integer values)
+//! - `links/by-link/[URL] [ID]` for an index of redirects by their URLs (with
+//!   empty values), which may also contain entries of IDs that no longer
+//!   redirect to that URL
+//! - `links/vanities-of/[ID] [vanity]` for an index of vanity paths by their
+//!   IDs (with empty values), which may also contain entries of vanity paths
+//!   that no longer correspond to that ID
+//! - `links/by-link-indexed` and `links/vanities-of-indexed` marking that the
+//!   above indexes have been built for data set before they existed
+//!
+//! Data of namespaces is stored the same way, but with the prefix
+//! `links/ns/[namespace]/` instead of `links/`.
+//!
+//! Expiring redirects are attached to an etcd lease with the appropriate TTL
+//! (rounded up to whole seconds), so etcd removes them once they expire.
+//!
+//! # Caching
+//! By default, redirects, vanity paths, and options are cached locally. The
+//! cache is kept up to date using an etcd watch on these keys, so that changes
+//! made by other links instances (or anything else writing to etcd) are seen
+//! almost immediately. If the watch fails, the cache is cleared and not used
+//! until the watch is re-established. Statistics are never cached.
+//!
+//! The cache holds at most `cache_size` entries, evicting the least recently
+//! used ones. Only keys which were read (including ones which don't exist) are
+//! cached, changes of other keys seen by the watch are ignored.
+
+use std::{
+	collections::HashMap,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	future::Future,
+	num::NonZeroUsize,
+	sync::Arc,
+	time::Duration,
+};
+
+use ::time::OffsetDateTime;
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use futures_util::{future::try_join_all, stream};
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use lru::LruCache;
+use parking_lot::{Mutex, RwLock};
+use tokio::{task::JoinHandle, time};
+use tokio_stream::StreamExt;
+use tonic::{
+	metadata::AsciiMetadataValue,
+	transport::{Channel, ClientTlsConfig, Endpoint},
+	Code, Request, Response, Status,
+};
+use tracing::{debug, instrument, warn};
+
+use self::rpc_wrapper::rpc::{
+	auth_client::AuthClient,
+	compare::{CompareResult, CompareTarget, TargetUnion},
+	event::EventType,
+	kv_client::KvClient,
+	lease_client::LeaseClient,
+	request_op::Request as Op,
+	response_op::Response as OpResponse,
+	watch_client::WatchClient,
+	watch_request::RequestUnion,
+	AuthenticateRequest, Compare, DeleteRangeRequest, DeleteRangeResponse, KeyValue,
+	LeaseGrantRequest, LeaseGrantResponse, PutRequest, PutResponse, RangeRequest, RangeResponse,
+	RequestOp, TxnRequest, TxnResponse, WatchCreateRequest, WatchRequest,
+};
+use crate::{
+	options::LinkOptions,
+	stats::{Statistic, StatisticDescription, StatisticValue},
+	store::{
+		backend::{export_pages, ExportItem, ExportStream, Health, NonTransient, Page},
+		BackendType, StoreBackend,
+	},
+};
+
+/// A wrapper around the generated tonic code for the etcd API. Contains the
+/// `rpc` module with all of the actual functionality. This is necessary to
+/// allow `clippy::pedantic` on the generated code.
+mod rpc_wrapper {
+	tonic::include_proto!("etcdserverpb");
+}
+
+/// The prefix used for all keys if none is configured
+const DEFAULT_PREFIX: &str = "links/";
+
+/// The maximum number of cached keys if no `cache_size` is configured
+const DEFAULT_CACHE_SIZE: usize = 100_000;
+
+/// The kinds of keys which are cached (and watched)
+const CACHED_KINDS: [&str; 3] = ["redirect/", "vanity/", "options/"];
+
+/// The maximum number of operations in one etcd transaction (etcd's default
+/// `--max-txn-ops`)
+const MAX_TXN_OPS: usize = 128;
+
+/// The maximum number of attempts to increment a statistic, if it is being
+/// concurrently modified
+const MAX_INCR_ATTEMPTS: usize = 16;
+
+/// How long to wait before re-establishing a failed watch
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long to wait for the initial connection to etcd
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum number of keys requested at a time when exporting
+const EXPORT_PAGE_SIZE: i64 = 1000;
+
+/// An etcd-backed `StoreBackend` implementation. A good option for deployments
+/// already using etcd, e.g. on Kubernetes.
+///
+/// # Configuration
+///
+/// **Store backend name:**
+/// `etcd`
+///
+/// **Configuration:**
+/// - `endpoints`: The URLs of the etcd cluster's members, seperated by commas
+///   (e.g. `http://etcd1:2379,http://etcd2:2379`). Requests are load-balanced
+///   between all members. `https` URLs use TLS with the system's root CAs.
+///   **Required**.
+/// - `username`: The etcd user to authenticate as, when etcd authentication is
+///   enabled. **Default none**.
+/// - `password`: The password of the etcd user. **Default none**.
+/// - `prefix`: The prefix of all keys used by links. Multiple links deployments
+///   can share the same etcd cluster using different prefixes. **Default
+///   `links/`**.
+/// - `cache`: Cache redirects, vanity paths, and options locally, using an etcd
+///   watch to keep the cache up to date. *`true` / `false`*. **Default
+///   `true`**.
+/// - `cache_size`: The maximum number of cached keys. `0` disables caching.
+///   **Default `100000`**.
+pub struct Store {
+	kv: KvClient<Channel>,
+	lease: LeaseClient<Channel>,
+	auth: Option<Arc<Auth>>,
+	prefix: String,
+	cache: Option<Arc<Cache>>,
+	watcher: Option<JoinHandle<()>>,
+}
+
+impl Debug for Store {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Store")
+			.field("prefix", &self.prefix)
+			.field("cache", &self.cache.is_some())
+			.finish_non_exhaustive()
+	}
+}
+
+impl Drop for Store {
+	fn drop(&mut self) {
+		if let Some(watcher) = &self.watcher {
+			watcher.abort();
+		}
+	}
+}
+
+impl Store {
+	/// Get the full key for the `kind` of data (e.g. `"redirect/"`) and `name`
+	fn key(&self, kind: &str, name: &str) -> String {
+		format!("{}{kind}{name}", self.prefix)
+	}
+
+	/// Get the full key of the entry for `name` (e.g. an ID) with the `value`
+	/// (e.g. a URL) in the `index` (e.g. `"by-link"`). Neither URLs nor IDs
+	/// contain spaces, so this can't be ambiguous.
+	fn index_key(&self, index: &str, value: &str, name: &str) -> String {
+		format!("{}{index}/{value} {name}", self.prefix)
+	}
+
+	/// Create a new request containing the `message`, with the current auth
+	/// token (if any)
+	fn request<T>(&self, message: T) -> Request<T> {
+		let mut request = Request::new(message);
+
+		if let Some(auth) = &self.auth {
+			auth.add_token(&mut request);
+		}
+
+		request
+	}
+
+	/// Make an API call using the `message`, re-authenticating and retrying
+	/// once if the auth token is no longer valid
+	async fn call<T: Clone, R, F: Future<Output = Result<Response<R>, Status>>>(
+		&self,
+		message: T,
+		call: impl Fn(Request<T>) -> F,
+	) -> Result<R> {
+		match (call(self.request(message.clone())).await, &self.auth) {
+			(Err(status), Some(auth)) if status.code() == Code::Unauthenticated => {
+				auth.authenticate().await?;
+				Ok(call(self.request(message)).await?.into_inner())
+			}
+			(res, _) => Ok(res?.into_inner()),
+		}
+	}
+
+	/// Call etcd's `Range` RPC
+	async fn range(&self, message: RangeRequest) -> Result<RangeResponse> {
+		self.call(message, |req| {
+			let mut kv = self.kv.clone();
+			async move { kv.range(req).await }
+		})
+		.await
+	}
+
+	/// Call etcd's `Put` RPC
+	async fn put(&self, message: PutRequest) -> Result<PutResponse> {
+		self.call(message, |req| {
+			let mut kv = self.kv.clone();
+			async move { kv.put(req).await }
+		})
+		.await
+	}
+
+	/// Call etcd's `DeleteRange` RPC
+	async fn delete_range(&self, message: DeleteRangeRequest) -> Result<DeleteRangeResponse> {
+		self.call(message, |req| {
+			let mut kv = self.kv.clone();
+			async move { kv.delete_range(req).await }
+		})
+		.await
+	}
+
+	/// Call etcd's `Txn` RPC
+	async fn txn(&self, message: TxnRequest) -> Result<TxnResponse> {
+		self.call(message, |req| {
+			let mut kv = self.kv.clone();
+			async move { kv.txn(req).await }
+		})
+		.await
+	}
+
+	/// Call etcd's `LeaseGrant` RPC
+	async fn lease_grant(&self, message: LeaseGrantRequest) -> Result<LeaseGrantResponse> {
+		self.call(message, |req| {
+			let mut lease = self.lease.clone();
+			async move { lease.lease_grant(req).await }
+		})
+		.await
+	}
+
+	/// Get the value of the `key`, using the cache if possible
+	async fn get(&self, key: String) -> Result<Option<String>> {
+		let generation = self.cache.as_ref().map(|cache| cache.generation());
+
+		if let Some(cached) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+			return Ok(cached);
+		}
+
+		let res = self
+			.range(RangeRequest {
+				key: key.clone().into_bytes(),
+				..Default::default()
+			})
+			.await?;
+
+		let value = res
+			.kvs
+			.into_iter()
+			.next()
+			.map(|kv| String::from_utf8(kv.value))
+			.transpose()?;
+
+		if let (Some(cache), Some(generation), Some(header)) = (&self.cache, generation, res.header)
+		{
+			cache.insert(generation, key, value.clone(), header.revision);
+		}
+
+		Ok(value)
+	}
+
+	/// Get the values of all `keys`, using the cache if possible, and getting
+	/// the rest in as few transactions as possible
+	async fn get_many(&self, keys: Vec<String>) -> Result<Vec<Option<String>>> {
+		let generation = self.cache.as_ref().map(|cache| cache.generation());
+		let mut values = keys
+			.iter()
+			.map(|key| self.cache.as_ref().and_then(|cache| cache.get(key)))
+			.collect::<Vec<_>>();
+		let missing = keys
+			.into_iter()
+			.enumerate()
+			.filter(|(i, _)| values[*i].is_none())
+			.collect::<Vec<_>>();
+
+		for chunk in missing.chunks(MAX_TXN_OPS) {
+			let res = self
+				.txn(TxnRequest {
+					success: chunk
+						.iter()
+						.map(|(_, key)| RequestOp {
+							request: Some(Op::RequestRange(RangeRequest {
+								key: key.clone().into_bytes(),
+								..Default::default()
+							})),
+						})
+						.collect(),
+					..Default::default()
+				})
+				.await?;
+
+			let revision = res.header.map(|header| header.revision);
+			for (res, (i, key)) in res.responses.into_iter().zip(chunk) {
+				let Some(OpResponse::ResponseRange(res)) = res.response else {
+					bail!("etcd returned an unexpected transaction response");
+				};
+
+				let value = res
+					.kvs
+					.into_iter()
+					.next()
+					.map(|kv| String::from_utf8(kv.value))
+					.transpose()?;
+
+				if let (Some(cache), Some(generation), Some(revision)) =
+					(&self.cache, generation, revision)
+				{
+					cache.insert(generation, key.clone(), value.clone(), revision);
+				}
+
+				values[*i] = Some(value);
+			}
+		}
+
+		Ok(values.into_iter().map(Option::flatten).collect())
+	}
+
+	/// Set the `key` to the `value`, returning the old value
+	async fn set(&self, key: String, value: String) -> Result<Option<String>> {
+		self.set_with_lease(key, value, 0).await
+	}
+
+	/// Set the `key` to the `value` attached to the `lease` (or no lease if
+	/// `0`), returning the old value
+	async fn set_with_lease(
+		&self,
+		key: String,
+		value: String,
+		lease: i64,
+	) -> Result<Option<String>> {
+		let res = self
+			.put(PutRequest {
+				key: key.clone().into_bytes(),
+				value: value.clone().into_bytes(),
+				lease,
+				prev_kv: true,
+			})
+			.await?;
+
+		if let (Some(cache), Some(header)) = (&self.cache, res.header) {
+			cache.update(&key, Some(value), header.revision);
+		}
+
+		Ok(res
+			.prev_kv
+			.map(|kv| String::from_utf8(kv.value))
+			.transpose()?)
+	}
+
+	/// Set the redirect from `from` to `to` attached to the `lease` (or no
+	/// lease if `0`), returning the old value. The `by-link` index entry
+	/// (attached to the same lease) is added before the redirect is set, so
+	/// that failures can only leave behind extra entries in the index, never
+	/// missing ones.
+	async fn set_redirect_with_lease(
+		&self,
+		from: Id,
+		to: Link,
+		lease: i64,
+	) -> Result<Option<Link>> {
+		let from = from.to_string();
+
+		self.put(PutRequest {
+			key: self.index_key("by-link", to.as_str(), &from).into_bytes(),
+			lease,
+			..Default::default()
+		})
+		.await?;
+
+		let old = self
+			.set_with_lease(self.key("redirect/", &from), to.to_string(), lease)
+			.await?;
+
+		if let Some(old) = old.as_ref().filter(|&old| old != to.as_str()) {
+			self.delete_range(DeleteRangeRequest {
+				key: self.index_key("by-link", old, &from).into_bytes(),
+				..Default::default()
+			})
+			.await?;
+		}
+
+		Ok(old.map(Link::new_unchecked))
+	}
+
+	/// Get the names of all entries with the `value` in the `index` of the
+	/// `kind` of data (e.g. the IDs of all `"redirect/"`s to a URL in the
+	/// `"by-link"` index), building the index first if it doesn't exist yet
+	async fn indexed(&self, kind: &str, index: &str, value: &str) -> Result<Vec<String>> {
+		self.build_index(kind, index).await?;
+
+		let prefix = self.index_key(index, value, "");
+		let res = self
+			.range(RangeRequest {
+				key: prefix.clone().into_bytes(),
+				range_end: prefix_end(&prefix),
+				keys_only: true,
+				..Default::default()
+			})
+			.await?;
+
+		res.kvs
+			.into_iter()
+			.map(|kv| {
+				let key = String::from_utf8(kv.key)?;
+				let name = key
+					.strip_prefix(&prefix)
+					.ok_or_else(|| anyhow!("etcd returned a key outside of the range"))?;
+
+				Ok(name.to_string())
+			})
+			.collect()
+	}
+
+	/// Build the `index` of the `kind` of data, unless it is already marked as
+	/// built. All existing data is added to the index, then the index is
+	/// marked as built using the `[index]-indexed` key. Data set concurrently
+	/// is added to the index when it is set anyway.
+	async fn build_index(&self, kind: &str, index: &str) -> Result<()> {
+		let marker = self.key(index, "-indexed");
+		let indexed = self
+			.range(RangeRequest {
+				key: marker.clone().into_bytes(),
+				count_only: true,
+				..Default::default()
+			})
+			.await?
+			.count > 0;
+
+		if indexed {
+			return Ok(());
+		}
+
+		let page_size = usize::try_from(EXPORT_PAGE_SIZE)?;
+		let mut cursor = None;
+
+		loop {
+			let mut page = self.list(kind, cursor.as_deref(), page_size).await?;
+			let more = page.len() > page_size;
+			page.truncate(page_size);
+
+			try_join_all(page.iter().map(|(name, value)| {
+				self.put(PutRequest {
+					key: self.index_key(index, value, name).into_bytes(),
+					..Default::default()
+				})
+			}))
+			.await?;
+
+			if !more {
+				break;
+			}
+
+			cursor = page.pop().map(|(name, _)| name);
+		}
+
+		self.put(PutRequest {
+			key: marker.into_bytes(),
+			..Default::default()
+		})
+		.await?;
+
+		Ok(())
+	}
+
+	/// Create a lease expiring at `expires_at` (rounded up to whole seconds),
+	/// returning its ID, or `None` if that time has already passed
+	async fn lease_until(&self, expires_at: OffsetDateTime) -> Result<Option<i64>> {
+		let ttl = (expires_at - OffsetDateTime::now_utc())
+			.as_seconds_f64()
+			.ceil();
+
+		if ttl <= 0.0 {
+			return Ok(None);
+		}
+
+		#[expect(
+			clippy::cast_possible_truncation,
+			reason = "the TTL is a positive whole number, saturating is fine for huge values"
+		)]
+		let lease = self
+			.lease_grant(LeaseGrantRequest {
+				ttl: ttl as i64,
+				id: 0,
+			})
+			.await?;
+
+		if !lease.error.is_empty() {
+			bail!("could not create etcd lease: {}", lease.error);
+		}
+
+		Ok(Some(lease.id))
+	}
+
+	/// Remove the `key`, returning the old value
+	async fn rem(&self, key: String) -> Result<Option<String>> {
+		let res = self
+			.delete_range(DeleteRangeRequest {
+				key: key.clone().into_bytes(),
+				prev_kv: true,
+				..Default::default()
+			})
+			.await?;
+
+		if let (Some(cache), Some(header)) = (&self.cache, res.header) {
+			cache.update(&key, None, header.revision);
+		}
+
+		Ok(res
+			.prev_kvs
+			.into_iter()
+			.next()
+			.map(|kv| String::from_utf8(kv.value))
+			.transpose()?)
+	}
+
+	/// Get all statistics matching the `description`, with their keys and
+	/// values
+	async fn matching_statistics(
+		&self,
+		description: &StatisticDescription,
+	) -> Result<Vec<(String, Statistic, u64)>> {
+		let prefix = self.key("stat/", "");
+		let res = self
+			.range(RangeRequest {
+				key: prefix.clone().into_bytes(),
+				range_end: prefix_end(&prefix),
+				..Default::default()
+			})
+			.await?;
+
+		let mut stats = Vec::new();
+		for kv in res.kvs {
+			let key = String::from_utf8(kv.key)?;
+			let stat = serde_json::from_str::<Statistic>(&key[prefix.len()..])?;
+
+			if description.matches(&stat) {
+				stats.push((key, stat, std::str::from_utf8(&kv.value)?.parse()?));
+			}
+		}
+
+		Ok(stats)
+	}
+
+	/// List the names and values of up to `limit + 1` keys of the `kind` (e.g.
+	/// `"redirect/"`) sorted after the `cursor`
+	async fn list(
+		&self,
+		kind: &str,
+		cursor: Option<&str>,
+		limit: usize,
+	) -> Result<Vec<(String, String)>> {
+		let prefix = self.key(kind, "");
+		let key = cursor.map_or_else(
+			|| prefix.clone().into_bytes(),
+			|cursor| [self.key(kind, cursor).as_bytes(), &[0]].concat(),
+		);
+
+		let res = self
+			.range(RangeRequest {
+				key,
+				range_end: prefix_end(&prefix),
+				limit: i64::try_from(limit.saturating_add(1)).unwrap_or(i64::MAX),
+				..Default::default()
+			})
+			.await?;
+
+		res.kvs
+			.into_iter()
+			.map(|kv| {
+				let key = String::from_utf8(kv.key)?;
+				let name = key
+					.strip_prefix(&prefix)
+					.ok_or_else(|| anyhow!("etcd returned a key outside of the range"))?;
+
+				Ok((name.to_string(), String::from_utf8(kv.value)?))
+			})
+			.collect()
+	}
+
+	/// Convert the `kv` to an exported item, if it holds links data
+	fn export_item(&self, kv: KeyValue) -> Result<Option<ExportItem>> {
+		let key = String::from_utf8(kv.key)?;
+		let Some(key) = key.strip_prefix(&self.prefix) else {
+			return Ok(None);
+		};
+		let value = String::from_utf8(kv.value)?;
+
+		let item = if let Some(id) = key.strip_prefix("redirect/") {
+			ExportItem::Redirect {
+				id: Id::try_from(id)?,
+				link: Link::new_unchecked(value),
+			}
+		} else if let Some(vanity) = key.strip_prefix("vanity/") {
+			ExportItem::Vanity {
+				vanity: Normalized::new(vanity),
+				id: Id::try_from(value)?,
+			}
+		} else if let Some(id) = key.strip_prefix("options/") {
+			ExportItem::Options {
+				id: Id::try_from(id)?,
+				options: serde_json::from_str(&value)?,
+			}
+		} else if let Some(statistic) = key.strip_prefix("stat/") {
+			let Some(value) = StatisticValue::new(value.parse()?) else {
+				return Ok(None);
+			};
+
+			ExportItem::Statistic {
+				statistic: serde_json::from_str(statistic)?,
+				value,
+			}
+		} else {
+			return Ok(None);
+		};
+
+		Ok(Some(item))
+	}
+}
+
+#[async_trait]
+impl StoreBackend for Store {
+	fn store_type() -> BackendType
+	where
+		Self: Sized,
+	{
+		BackendType::Etcd
+	}
+
+	fn get_store_type(&self) -> BackendType {
+		BackendType::Etcd
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn new(config: &HashMap<String, String>) -> Result<Self> {
+		let endpoints = config
+			.get("endpoints")
+			.ok_or_else(|| anyhow!("missing endpoints option"))?
+			.split(',')
+			.map(str::trim)
+			.filter(|url| !url.is_empty())
+			.map(|url| {
+				let endpoint = Endpoint::from_shared(url.to_string())?;
+
+				if url.starts_with("https://") {
+					Ok(endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?)
+				} else {
+					Ok(endpoint)
+				}
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		if endpoints.is_empty() {
+			bail!("no etcd endpoints configured");
+		}
+
+		let channel = Channel::balance_list(endpoints.into_iter());
+
+		let auth = match (config.get("username"), config.get("password")) {
+			(Some(name), Some(password)) => Some(Arc::new(Auth {
+				client: AuthClient::new(channel.clone()),
+				name: name.clone(),
+				password: password.clone(),
+				token: RwLock::new(None),
+			})),
+			(None, None) => None,
+			_ => bail!("both username and password must be configured for etcd authentication"),
+		};
+
+		let prefix = config
+			.get("prefix")
+			.map_or(DEFAULT_PREFIX, String::as_str)
+			.to_string();
+
+		let cache_enabled: bool = config.get("cache").map_or(Ok(true), |s| s.parse())?;
+		let cache_size = config
+			.get("cache_size")
+			.map_or(Ok(DEFAULT_CACHE_SIZE), |s| s.parse())?;
+		let cache = NonZeroUsize::new(cache_size)
+			.filter(|_| cache_enabled)
+			.map(|size| Arc::new(Cache::new(size)));
+
+		let mut store = Self {
+			kv: KvClient::new(channel.clone()),
+			lease: LeaseClient::new(channel.clone()),
+			auth,
+			prefix,
+			cache,
+			watcher: None,
+		};
+
+		// Make sure that etcd is reachable (and the credentials are valid)
+		time::timeout(CONNECT_TIMEOUT, async {
+			if let Some(auth) = &store.auth {
+				auth.authenticate().await?;
+			}
+
+			store
+				.range(RangeRequest {
+					key: store.prefix.clone().into_bytes(),
+					range_end: prefix_end(&store.prefix),
+					count_only: true,
+					..Default::default()
+				})
+				.await
+		})
+		.await
+		.map_err(|_| anyhow!("timed out while connecting to etcd"))??;
+
+		if let Some(cache) = &store.cache {
+			store.watcher = Some(tokio::spawn(watch(
+				WatchClient::new(channel),
+				store.auth.clone(),
+				store.prefix.clone(),
+				Arc::clone(cache),
+			)));
+		}
+
+		Ok(store)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
+		Ok(self
+			.get(self.key("redirect/", &from.to_string()))
+			.await?
+			.map(Link::new_unchecked))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let keys = from
+			.iter()
+			.map(|id| self.key("redirect/", &id.to_string()))
+			.collect();
+
+		Ok(self
+			.get_many(keys)
+			.await?
+			.into_iter()
+			.map(|link| link.map(Link::new_unchecked))
+			.collect())
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
+		self.set_redirect_with_lease(from, to, 0).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		// Already expired redirects are just removed
+		let Some(lease) = self.lease_until(expires_at).await? else {
+			return self.rem_redirect(from).await;
+		};
+
+		self.set_redirect_with_lease(from, to, lease).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		// Already expired tombstones are not created at all
+		let Some(lease) = self.lease_until(keep_until).await? else {
+			return self.rem_redirect(from).await;
+		};
+
+		// The tombstone is created before the redirect is removed, so that the
+		// redirect can't be lost if creating the tombstone fails
+		let Some(link) = self.get_redirect(from).await? else {
+			return Ok(None);
+		};
+
+		let key = self.key("tombstone/", &from.to_string());
+		let put_tombstone = |link: &Link| {
+			self.put(PutRequest {
+				key: key.clone().into_bytes(),
+				value: link.to_string().into_bytes(),
+				lease,
+				..Default::default()
+			})
+		};
+
+		put_tombstone(&link).await?;
+		let old = self.rem_redirect(from).await?;
+
+		match old {
+			Some(ref old) if *old != link => {
+				put_tombstone(old).await?;
+			}
+			Some(_) => (),
+			None => {
+				self.delete_range(DeleteRangeRequest {
+					key: key.into_bytes(),
+					..Default::default()
+				})
+				.await?;
+			}
+		}
+
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let key = self.key("tombstone/", &from);
+
+		let res = self
+			.range(RangeRequest {
+				key: key.clone().into_bytes(),
+				..Default::default()
+			})
+			.await?;
+
+		let Some(kv) = res.kvs.into_iter().next() else {
+			return Ok(None);
+		};
+
+		let link = String::from_utf8(kv.value)?;
+		let redirect = self.key("redirect/", &from);
+
+		// The index entry is added first, like in `set_redirect_with_lease`
+		self.put(PutRequest {
+			key: self.index_key("by-link", &link, &from).into_bytes(),
+			..Default::default()
+		})
+		.await?;
+
+		// Only restore the redirect if it doesn't exist (anymore)
+		let res = self
+			.txn(TxnRequest {
+				compare: vec![Compare {
+					result: CompareResult::Equal.into(),
+					target: CompareTarget::Create.into(),
+					key: redirect.clone().into_bytes(),
+					target_union: Some(TargetUnion::CreateRevision(0)),
+					range_end: Vec::new(),
+				}],
+				success: vec![
+					RequestOp {
+						request: Some(Op::RequestPut(PutRequest {
+							key: redirect.clone().into_bytes(),
+							value: link.clone().into_bytes(),
+							..Default::default()
+						})),
+					},
+					RequestOp {
+						request: Some(Op::RequestDeleteRange(DeleteRangeRequest {
+							key: key.into_bytes(),
+							..Default::default()
+						})),
+					},
+				],
+				failure: Vec::new(),
+			})
+			.await?;
+
+		if !res.succeeded {
+			bail!(NonTransient::RedirectExists);
+		}
+
+		if let (Some(cache), Some(header)) = (&self.cache, res.header) {
+			cache.update(&redirect, Some(link.clone()), header.revision);
+		}
+
+		Ok(Some(Link::new_unchecked(link)))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn purge_redirects(&self) -> Result<usize> {
+		let prefix = self.key("tombstone/", "");
+		let res = self
+			.delete_range(DeleteRangeRequest {
+				key: prefix.clone().into_bytes(),
+				range_end: prefix_end(&prefix),
+				..Default::default()
+			})
+			.await?;
+
+		Ok(usize::try_from(res.deleted)?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let old = self.rem(self.key("redirect/", &from)).await?;
+
+		if let Some(ref old) = old {
+			self.delete_range(DeleteRangeRequest {
+				key: self.index_key("by-link", old, &from).into_bytes(),
+				..Default::default()
+			})
+			.await?;
+		}
+
+		Ok(old.map(Link::new_unchecked))
+	}
+
+	/// Uses the `by-link` index, building it first if it doesn't exist yet.
+	/// Index entries which no longer match are skipped, but not removed,
+	/// because they may belong to a redirect which is being set.
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		let mut ids = self
+			.indexed("redirect/", "by-link", link.as_str())
+			.await?
+			.into_iter()
+			.map(Id::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		ids.sort_unstable();
+
+		let current = self.get_redirects(&ids).await?;
+
+		Ok(ids
+			.into_iter()
+			.zip(current)
+			.filter(|(_, current)| current.as_ref() == Some(link))
+			.map(|(id, _)| id)
+			.collect())
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		Ok(self
+			.get(self.key("vanity/", from.as_str()))
+			.await?
+			.map(Id::try_from)
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		let keys = from
+			.iter()
+			.map(|vanity| self.key("vanity/", vanity.as_str()))
+			.collect();
+
+		self.get_many(keys)
+			.await?
+			.into_iter()
+			.map(|id| Ok(id.map(Id::try_from).transpose()?))
+			.collect()
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
+		let to = to.to_string();
+
+		// The index entry is added first, like in `set_redirect_with_lease`
+		self.put(PutRequest {
+			key: self
+				.index_key("vanities-of", &to, from.as_str())
+				.into_bytes(),
+			..Default::default()
+		})
+		.await?;
+
+		let old = self
+			.set(self.key("vanity/", from.as_str()), to.clone())
+			.await?;
+
+		if let Some(old) = old.as_ref().filter(|&old| *old != to) {
+			self.delete_range(DeleteRangeRequest {
+				key: self
+					.index_key("vanities-of", old, from.as_str())
+					.into_bytes(),
+				..Default::default()
+			})
+			.await?;
+		}
+
+		Ok(old.map(Id::try_from).transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		let old = self.rem(self.key("vanity/", from.as_str())).await?;
+
+		if let Some(ref old) = old {
+			self.delete_range(DeleteRangeRequest {
+				key: self
+					.index_key("vanities-of", old, from.as_str())
+					.into_bytes(),
+				..Default::default()
+			})
+			.await?;
+		}
+
+		Ok(old.map(Id::try_from).transpose()?)
+	}
+
+	/// Uses the `vanities-of` index, like `find_by_link` uses the `by-link`
+	/// index
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		let mut vanities = self
+			.indexed("vanity/", "vanities-of", &id.to_string())
+			.await?
+			.into_iter()
+			.map(|vanity| Normalized::new(&vanity))
+			.collect::<Vec<_>>();
+		vanities.sort_unstable();
+
+		let current = self.get_vanities(&vanities).await?;
+
+		Ok(vanities
+			.into_iter()
+			.zip(current)
+			.filter(|&(_, current)| current == Some(id))
+			.map(|(vanity, _)| vanity)
+			.collect())
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		Ok(self
+			.get(self.key("options/", &id.to_string()))
+			.await?
+			.map(|s| serde_json::from_str(&s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		Ok(self
+			.set(
+				self.key("options/", &id.to_string()),
+				serde_json::to_string(&options)?,
+			)
+			.await?
+			.map(|s| serde_json::from_str(&s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		Ok(self
+			.rem(self.key("options/", &id.to_string()))
+			.await?
+			.map(|s| serde_json::from_str(&s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		Ok(self
+			.matching_statistics(&description)
+			.await?
+			.into_iter()
+			.filter_map(|(_, stat, value)| Some((stat, StatisticValue::new(value)?)))
+			.collect())
+	}
+
+	/// Statistics are sorted by their key, i.e. their JSON representation.
+	/// Keys are read in batches until enough matching statistics are found.
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		let prefix = self.key("stat/", "");
+		let mut key = match cursor {
+			Some(stat) => [
+				self.key("stat/", &serde_json::to_string(&stat)?).as_bytes(),
+				&[0],
+			]
+			.concat(),
+			None => prefix.clone().into_bytes(),
+		};
+		let batch = i64::try_from(limit.saturating_add(1)).unwrap_or(i64::MAX);
+		let mut stats = Vec::new();
+
+		loop {
+			let res = self
+				.range(RangeRequest {
+					key,
+					range_end: prefix_end(&prefix),
+					limit: batch,
+					..Default::default()
+				})
+				.await?;
+
+			for kv in &res.kvs {
+				let name = std::str::from_utf8(&kv.key)?
+					.strip_prefix(&prefix)
+					.ok_or_else(|| anyhow!("etcd returned a key outside of the range"))?;
+				let stat = serde_json::from_str::<Statistic>(name)?;
+
+				if description.matches(&stat) {
+					let value = std::str::from_utf8(&kv.value)?.parse()?;
+					stats.extend(StatisticValue::new(value).map(|value| (stat, value)));
+				}
+			}
+
+			// Continue right after the last key of this batch, unless the page
+			// is already full or there are no more statistics
+			match res.kvs.last() {
+				Some(kv) if res.more && stats.len() <= limit => {
+					key = [kv.key.as_slice(), &[0]].concat();
+				}
+				_ => break,
+			}
+		}
+
+		Ok(Page::from_sorted(stats, limit))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_statistic(&self, statistic: Statistic) -> Result<Option<StatisticValue>> {
+		let key = self.key("stat/", &serde_json::to_string(&statistic)?);
+
+		// Optimistically increment the value, retrying if it was modified
+		// between being read and written
+		for _ in 0..MAX_INCR_ATTEMPTS {
+			let res = self
+				.range(RangeRequest {
+					key: key.clone().into_bytes(),
+					..Default::default()
+				})
+				.await?;
+
+			let (value, revision) = match res.kvs.first() {
+				Some(kv) => (
+					std::str::from_utf8(&kv.value)?.parse::<u64>()?,
+					kv.mod_revision,
+				),
+				None => (0, 0),
+			};
+			let value = value.saturating_add(1);
+
+			let res = self
+				.txn(TxnRequest {
+					compare: vec![Compare {
+						result: CompareResult::Equal.into(),
+						target: CompareTarget::Mod.into(),
+						key: key.clone().into_bytes(),
+						target_union: Some(TargetUnion::ModRevision(revision)),
+						range_end: Vec::new(),
+					}],
+					success: vec![RequestOp {
+						request: Some(Op::RequestPut(PutRequest {
+							key: key.clone().into_bytes(),
+							value: value.to_string().into_bytes(),
+							..Default::default()
+						})),
+					}],
+					failure: Vec::new(),
+				})
+				.await?;
+
+			if res.succeeded {
+				return Ok(StatisticValue::new(value));
+			}
+		}
+
+		bail!("statistic was modified concurrently too many times")
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		let key = self.key("usage/", key);
+		let mut new_lease = None;
+
+		// Optimistically increment the value, retrying if it was modified
+		// between being read and written. New counters are attached to a lease
+		// expiring at `expires_at`, existing ones keep their lease.
+		for _ in 0..MAX_INCR_ATTEMPTS {
+			let res = self
+				.range(RangeRequest {
+					key: key.clone().into_bytes(),
+					..Default::default()
+				})
+				.await?;
+
+			let (value, revision, lease) = match res.kvs.first() {
+				Some(kv) => (
+					std::str::from_utf8(&kv.value)?.parse::<u64>()?,
+					kv.mod_revision,
+					kv.lease,
+				),
+				None => {
+					let lease = match new_lease {
+						Some(lease) => lease,
+						None => self.lease_until(expires_at).await?.unwrap_or(0),
+					};
+					new_lease = Some(lease);
+
+					(0, 0, lease)
+				}
+			};
+			let value = value.saturating_add(amount);
+
+			let res = self
+				.txn(TxnRequest {
+					compare: vec![Compare {
+						result: CompareResult::Equal.into(),
+						target: CompareTarget::Mod.into(),
+						key: key.clone().into_bytes(),
+						target_union: Some(TargetUnion::ModRevision(revision)),
+						range_end: Vec::new(),
+					}],
+					success: vec![RequestOp {
+						request: Some(Op::RequestPut(PutRequest {
+							key: key.clone().into_bytes(),
+							value: value.to_string().into_bytes(),
+							lease,
+							..Default::default()
+						})),
+					}],
+					failure: Vec::new(),
+				})
+				.await?;
+
+			if res.succeeded {
+				return Ok(value);
+			}
+		}
+
+		bail!("usage counter was modified concurrently too many times")
+	}
+
+	fn supports_usage(&self) -> bool {
+		true
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		let matching = self.matching_statistics(&description).await?;
+		let mut removed = Vec::with_capacity(matching.len());
+
+		for chunk in matching.chunks(MAX_TXN_OPS) {
+			let res = self
+				.txn(TxnRequest {
+					success: chunk
+						.iter()
+						.map(|(key, ..)| RequestOp {
+							request: Some(Op::RequestDeleteRange(DeleteRangeRequest {
+								key: key.clone().into_bytes(),
+								prev_kv: true,
+								..Default::default()
+							})),
+						})
+						.collect(),
+					..Default::default()
+				})
+				.await?;
+
+			// The values may have changed since they were listed, so use the
+			// ones actually removed
+			for (res, (_, stat, _)) in res.responses.into_iter().zip(chunk) {
+				let Some(OpResponse::ResponseDeleteRange(res)) = res.response else {
+					continue;
+				};
+
+				for kv in res.prev_kvs {
+					if let Some(value) =
+						StatisticValue::new(std::str::from_utf8(&kv.value)?.parse()?)
+					{
+						removed.push((stat.clone(), value));
+					}
+				}
+			}
+		}
+
+		Ok(removed)
+	}
+
+	/// Checks the connection to etcd with a range request, bypassing the cache
+	async fn health(&self) -> Health {
+		Health::check(async {
+			self.range(RangeRequest {
+				key: self.prefix.clone().into_bytes(),
+				count_only: true,
+				..Default::default()
+			})
+			.await?;
+
+			Ok(())
+		})
+		.await
+	}
+
+	/// Namespaces use keys with the prefix `[prefix]ns/[namespace]/`, and
+	/// don't use the local cache
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		Ok(Arc::new(Self {
+			kv: self.kv.clone(),
+			lease: self.lease.clone(),
+			auth: self.auth.clone(),
+			prefix: format!("{}ns/{namespace}/", self.prefix),
+			cache: None,
+			watcher: None,
+		}))
+	}
+
+	/// Exports all data under the configured prefix in pages, all read at the
+	/// revision of the first page, so the exported data is a consistent
+	/// snapshot (unless that revision is compacted during the export)
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		let end = prefix_end(&self.prefix);
+		let start = Some((self.prefix.clone().into_bytes(), 0));
+
+		let pages = stream::try_unfold(start, move |state| {
+			let end = end.clone();
+
+			async move {
+				let Some((key, revision)) = state else {
+					return Ok(None);
+				};
+
+				let res = self
+					.range(RangeRequest {
+						key,
+						range_end: end,
+						limit: EXPORT_PAGE_SIZE,
+						revision,
+						..Default::default()
+					})
+					.await?;
+
+				let revision = match (revision, &res.header) {
+					(0, Some(header)) => header.revision,
+					_ => revision,
+				};
+
+				// Continue right after the last key of this page
+				let next = res
+					.kvs
+					.last()
+					.filter(|_| res.more)
+					.map(|kv| ([kv.key.as_slice(), &[0]].concat(), revision));
+
+				let items = res
+					.kvs
+					.into_iter()
+					.filter_map(|kv| self.export_item(kv).transpose())
+					.collect::<Result<Vec<_>>>()?;
+
+				Ok(Some((items, next)))
+			}
+		});
+
+		Ok(export_pages(pages))
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		let cursor = cursor.map(|id| id.to_string());
+		let redirects = self
+			.list("redirect/", cursor.as_deref(), limit)
+			.await?
+			.into_iter()
+			.map(|(id, link)| Ok((Id::try_from(id)?, Link::new_unchecked(link))))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Page::from_sorted(redirects, limit))
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		let vanities = self
+			.list("vanity/", cursor.as_ref().map(Normalized::as_str), limit)
+			.await?
+			.into_iter()
+			.map(|(vanity, id)| Ok((Normalized::new(&vanity), Id::try_from(id)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Page::from_sorted(vanities, limit))
+	}
+}
+
+/// Credentials and the current auth token for etcd authentication
+struct Auth {
+	client: AuthClient<Channel>,
+	name: String,
+	password: String,
+	token: RwLock<Option<AsciiMetadataValue>>,
+}
+
+impl Debug for Auth {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Auth")
+			.field("name", &self.name)
+			.field("password", &"[REDACTED]")
+			.finish_non_exhaustive()
+	}
+}
+
+impl Auth {
+	/// Get a new auth token from etcd
+	async fn authenticate(&self) -> Result<()> {
+		let res = self
+			.client
+			.clone()
+			.authenticate(AuthenticateRequest {
+				name: self.name.clone(),
+				password: self.password.clone(),
+			})
+			.await?
+			.into_inner();
+
+		*self.token.write() = Some(res.token.parse()?);
+		debug!("Authenticated to etcd");
+
+		Ok(())
+	}
+
+	/// Add the current auth token to the `request`'s metadata
+	fn add_token<T>(&self, request: &mut Request<T>) {
+		if let Some(token) = &*self.token.read() {
+			request.metadata_mut().insert("token", token.clone());
+		}
+	}
+}
+
+/// A local cache of etcd keys and values, kept up to date by a watch
+#[derive(Debug)]
+struct Cache {
+	state: Mutex<CacheState>,
+}
+
+/// The state of a [`Cache`]
+#[derive(Debug)]
+struct CacheState {
+	/// Whether the watch is currently established, the cache is not used
+	/// otherwise
+	live: bool,
+	/// Incremented every time the cache is reset, to prevent values read
+	/// before that from being cached afterwards
+	generation: u64,
+	/// The latest revision seen by the watch. Values read at older revisions
+	/// aren't cached, because the watch may have already ignored changes made
+	/// to them since.
+	revision: i64,
+	/// Cached values (`None` for missing keys) with the revision they are from
+	entries: LruCache<String, (Option<String>, i64)>,
+}
+
+impl Cache {
+	/// Create a new (not yet live) cache holding up to `size` keys
+	fn new(size: NonZeroUsize) -> Self {
+		Self {
+			state: Mutex::new(CacheState {
+				live: false,
+				generation: 0,
+				revision: 0,
+				entries: LruCache::new(size),
+			}),
+		}
+	}
+
+	/// Get the current cache generation, to be passed to [`Cache::insert`]
+	fn generation(&self) -> u64 {
+		self.state.lock().generation
+	}
+
+	/// Get the cached value of the `key`, if it is cached. The inner `None`
+	/// means that the key is known not to exist.
+	#[expect(
+		clippy::option_option,
+		reason = "the outer option is whether the key is cached, the inner one is its value"
+	)]
+	fn get(&self, key: &str) -> Option<Option<String>> {
+		let mut state = self.state.lock();
+
+		if state.live {
+			state.entries.get(key).map(|(value, _)| value.clone())
+		} else {
+			None
+		}
+	}
+
+	/// Cache the `value` of the `key` read at the `revision`, unless the cache
+	/// was reset since `generation`, the watch has already seen a newer
+	/// revision, or a newer value is already cached
+	fn insert(&self, generation: u64, key: String, value: Option<String>, revision: i64) {
+		let mut state = self.state.lock();
+
+		if !state.live || state.generation != generation || revision < state.revision {
+			return;
+		}
+
+		match state.entries.peek(&key) {
+			Some((_, cached)) if *cached >= revision => (),
+			_ => {
+				state.entries.put(key, (value, revision));
+			}
+		}
+	}
+
+	/// Update the cached value of the `key` after a change at the `revision`,
+	/// if the `key` is cached and the change is newer than the cached value
+	fn update(&self, key: &str, value: Option<String>, revision: i64) {
+		let mut state = self.state.lock();
+
+		if let Some((cached_value, cached)) = state.entries.peek_mut(key) {
+			if *cached < revision {
+				*cached_value = value;
+				*cached = revision;
+			}
+		}
+	}
+
+	/// Record that the watch has seen all changes up to the `revision`
+	fn watched(&self, revision: i64) {
+		let mut state = self.state.lock();
+		state.revision = state.revision.max(revision);
+	}
+
+	/// Clear the cache, and set whether it is `live`
+	fn reset(&self, live: bool) {
+		let mut state = self.state.lock();
+		state.entries.clear();
+		state.generation += 1;
+		state.revision = 0;
+		state.live = live;
+	}
+}
+
+/// Keep the `cache` up to date by watching all cached keys with the `prefix`,
+/// re-establishing the watch whenever it fails. This never returns, and should
+/// be spawned as a background task.
+async fn watch(
+	client: WatchClient<Channel>,
+	auth: Option<Arc<Auth>>,
+	prefix: String,
+	cache: Arc<Cache>,
+) {
+	loop {
+		match watch_once(client.clone(), auth.as_deref(), &prefix, &cache).await {
+			Ok(()) => warn!("etcd watch ended unexpectedly, cache disabled until re-established"),
+			Err(err) => warn!("etcd watch failed, cache disabled until re-established: {err}"),
+		}
+
+		cache.reset(false);
+
+		if let Some(auth) = &auth {
+			if let Err(err) = auth.authenticate().await {
+				warn!("Could not re-authenticate to etcd: {err}");
+			}
+		}
+
+		time::sleep(WATCH_RETRY_DELAY).await;
+	}
+}
+
+/// Establish a watch on all cached keys with the `prefix`, and update the
+/// `cache` with its events until it fails
+async fn watch_once(
+	mut client: WatchClient<Channel>,
+	auth: Option<&Auth>,
+	prefix: &str,
+	cache: &Cache,
+) -> Result<()> {
+	let requests = CACHED_KINDS.map(|kind| {
+		let key = format!("{prefix}{kind}");
+
+		WatchRequest {
+			request_union: Some(RequestUnion::CreateRequest(WatchCreateRequest {
+				range_end: prefix_end(&key),
+				key: key.into_bytes(),
+				..Default::default()
+			})),
+		}
+	});
+
+	// The request stream must stay open, otherwise etcd cancels the watch
+	let mut request = Request::new(tokio_stream::iter(requests).chain(tokio_stream::pending()));
+	if let Some(auth) = auth {
+		auth.add_token(&mut request);
+	}
+
+	let mut responses = client.watch(request).await?.into_inner();
+	let mut created = 0;
+
+	while let Some(res) = responses.message().await? {
+		if res.canceled {
+			bail!("watch canceled by etcd: {}", res.cancel_reason);
+		}
+
+		if res.created {
+			created += 1;
+
+			if created == CACHED_KINDS.len() {
+				debug!("etcd watch established, cache enabled");
+				cache.reset(true);
+			}
+
+			if let Some(header) = res.header {
+				cache.watched(header.revision);
+			}
+
+			continue;
+		}
+
+		for event in res.events {
+			let event_type = event.r#type();
+			let Some(kv) = event.kv else {
+				continue;
+			};
+
+			let value = match event_type {
+				EventType::Put => Some(String::from_utf8(kv.value)?),
+				EventType::Delete => None,
+			};
+
+			cache.update(std::str::from_utf8(&kv.key)?, value, kv.mod_revision);
+		}
+
+		if let Some(header) = res.header {
+			cache.watched(header.revision);
+		}
+	}
+
+	Ok(())
+}
+
+/// Get the end of the range of all keys starting with the `prefix`, i.e. the
+/// `prefix` with its last byte incremented
+fn prefix_end(prefix: &str) -> Vec<u8> {
+	let mut end = prefix.as_bytes().to_vec();
+
+	while let Some(last) = end.pop() {
+		if last < u8::MAX {
+			end.push(last + 1);
+			return end;
+		}
+	}
+
+	// All keys
+	vec![0]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn key_prefix_end() {
+		assert_eq!(prefix_end("links/"), b"links0");
+		assert_eq!(prefix_end("a"), b"b");
+		assert_eq!(prefix_end(""), [0]);
+	}
+
+	#[test]
+	fn cache() {
+		let cache = Cache::new(NonZeroUsize::new(2).unwrap());
+		let key = "links/redirect/test".to_string();
+
+		// Nothing is cached until the watch is established
+		cache.insert(cache.generation(), key.clone(), Some("a".to_string()), 1);
+		assert_eq!(cache.get(&key), None);
+
+		cache.reset(true);
+		let generation = cache.generation();
+		cache.insert(generation, key.clone(), Some("a".to_string()), 1);
+		assert_eq!(cache.get(&key), Some(Some("a".to_string())));
+
+		// Older values don't replace newer ones
+		cache.update(&key, None, 3);
+		cache.insert(generation, key.clone(), Some("b".to_string()), 2);
+		assert_eq!(cache.get(&key), Some(None));
+
+		// Values read before a reset aren't cached after it
+		cache.reset(true);
+		cache.insert(generation, key.clone(), Some("c".to_string()), 4);
+		assert_eq!(cache.get(&key), None);
+
+		// Changes of keys which aren't cached are ignored
+		cache.update(&key, Some("d".to_string()), 5);
+		assert_eq!(cache.get(&key), None);
+
+		// Values read before the latest change seen by the watch aren't cached
+		cache.watched(5);
+		cache.insert(cache.generation(), key.clone(), Some("c".to_string()), 4);
+		assert_eq!(cache.get(&key), None);
+		cache.insert(cache.generation(), key.clone(), Some("d".to_string()), 5);
+		assert_eq!(cache.get(&key), Some(Some("d".to_string())));
+
+		cache.update(&key, Some("e".to_string()), 6);
+		assert_eq!(cache.get(&key), Some(Some("e".to_string())));
+
+		// The least recently used keys are evicted
+		let generation = cache.generation();
+		cache.insert(generation, "links/vanity/a".to_string(), None, 6);
+		cache.insert(generation, "links/vanity/b".to_string(), None, 6);
+		assert_eq!(cache.get(&key), None);
+		assert_eq!(cache.get("links/vanity/b"), Some(None));
+
+		cache.reset(false);
+		assert_eq!(cache.get("links/vanity/b"), None);
+	}
+
+	/// Note:
+	/// These tests require a running etcd 3.5 server without authentication.
+	/// Because of this, they only run if the `test-etcd` feature is enabled.
+	/// To run all tests including these, use `cargo test --features
+	/// test-etcd`. You can run an etcd server with Docker using `docker run -p
+	/// 2379:2379 --rm quay.io/coreos/etcd:v3.5.17 etcd --listen-client-urls
+	/// http://0.0.0.0:2379 --advertise-client-urls http://localhost:2379`. It
+	/// is highly recommended **not** to run these tests on a production etcd
+	/// cluster.
+	#[cfg(feature = "test-etcd")]
+	mod etcd {
+		use std::collections::HashMap;
+
+		use super::super::Store;
+		use crate::store::{tests, StoreBackend as _};
+
+		/// Create a new store using a unique prefix, so that tests don't
+		/// interfere with each other
+		async fn get_store() -> Store {
+			get_store_with_prefix(&format!("links-test-{}/", links_id::Id::new())).await
+		}
+
+		async fn get_store_with_prefix(prefix: &str) -> Store {
+			Store::new(&HashMap::from_iter([
+				("endpoints".to_string(), "http://localhost:2379".to_string()),
+				("prefix".to_string(), prefix.to_string()),
+			]))
+			.await
+			.unwrap()
+		}
+
+		#[test]
+		fn store_type() {
+			tests::store_type::<Store>();
+		}
+
+		#[tokio::test]
+		async fn get_store_type() {
+			tests::get_store_type::<Store>(&get_store().await);
+		}
+
+		#[tokio::test]
+		async fn get_redirect() {
+			tests::get_redirect(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_redirects() {
+			tests::get_redirects(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_redirect() {
+			tests::set_redirect(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_redirects() {
+			tests::set_redirects(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn rem_redirect() {
+			tests::rem_redirect(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn rem_redirects() {
+			tests::rem_redirects(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn soft_delete() {
+			tests::soft_delete(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_redirect_with_expiry() {
+			tests::set_redirect_with_expiry(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn find_by_link() {
+			tests::find_by_link(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_vanity() {
+			tests::get_vanity(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_vanities() {
+			tests::get_vanities(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_vanity() {
+			tests::set_vanity(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_vanities() {
+			tests::set_vanities(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn rem_vanity() {
+			tests::rem_vanity(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_vanities_of() {
+			tests::get_vanities_of(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_options() {
+			tests::get_options(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn set_options() {
+			tests::set_options(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn rem_options() {
+			tests::rem_options(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_statistics() {
+			tests::get_statistics(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn get_statistics_page() {
+			tests::get_statistics_page(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn incr_statistic() {
+			tests::incr_statistic(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn incr_usage() {
+			tests::incr_usage(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn rem_statistics() {
+			tests::rem_statistics(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn export_all() {
+			tests::export_all(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn import() {
+			tests::import(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn list_redirects() {
+			tests::list_redirects(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn list_vanities() {
+			tests::list_vanities(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn health() {
+			tests::health(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn namespaced() {
+			tests::namespaced(&get_store().await).await;
+		}
+
+		#[tokio::test]
+		async fn watch_invalidation() {
+			use links_id::Id;
+			use links_normalized::Link;
+
+			let prefix = format!("links-test-{}/", Id::new());
+			let a = get_store_with_prefix(&prefix).await;
+			let b = get_store_with_prefix(&prefix).await;
+			let id = Id::new();
+
+			// Wait for both watches to be established
+			tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+			assert_eq!(b.get_redirect(id).await.unwrap(), None);
+
+			let link = Link::new("https://example.com/etcd").unwrap();
+			a.set_redirect(id, link.clone()).await.unwrap();
+			tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+			assert_eq!(b.get_redirect(id).await.unwrap(), Some(link));
+
+			a.rem_redirect(id).await.unwrap();
+			drop(a);
+			tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+			assert_eq!(b.get_redirect(id).await.unwrap(), None);
+			drop(b);
+		}
+	}
+}
@@ -1,24 +1,49 @@
 //! A fully in-memory [`StoreBackend`] implementation, storing all data in RAM
-//! with no other backups. This is mostly intended for tests, as it doesn't
-//! depend on any state being persisted between links shutdown and startup, nor
-//! does it depend on any external resources or services.
+//! with no other backups (other than optional periodic snapshots). This is
+//! mostly intended for tests and small deployments, as it doesn't depend on
+//! any external resources or services.
 
-use std::collections::HashMap;
+use std::{
+	collections::{hash_map::Entry, BTreeMap, HashMap},
+	fs,
+	mem::size_of,
+	ops::Deref,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Weak,
+	},
+	time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
+use futures_util::stream;
 use links_id::Id;
 use links_normalized::{Link, Normalized};
-use parking_lot::RwLock;
-use tracing::instrument;
+use lru::LruCache;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+use tracing::{debug, info, instrument, warn};
 
 use crate::{
-	stats::{Statistic, StatisticDescription, StatisticValue},
-	store::{BackendType, StoreBackend},
+	options::LinkOptions,
+	stats::{IdOrVanity, Statistic, StatisticDescription, StatisticValue},
+	store::{
+		backend::{ExportItem, ExportStream, NonTransient, Page},
+		encryption::Cipher,
+		BackendType, StoreBackend,
+	},
 };
 
+/// The additional authenticated data of encrypted snapshots, see [`Cipher`]
+const SNAPSHOT_AAD: &[u8] = b"links memory snapshot";
+
 /// A fully in-memory `StoreBackend` implementation useful for testing. Not
-/// recommended for production, as this lacks any data persistence or backups.
+/// recommended for production, as data is only persisted through periodic
+/// snapshots, if at all.
 ///
 /// # Configuration
 ///
@@ -26,12 +51,440 @@ use crate::{
 /// `memory`
 ///
 /// **Configuration:**
-/// *none*
+/// - `max_redirects`: The maximum number of redirects to store. Setting new
+///   redirects fails once this is reached. **Default unlimited**.
+/// - `max_statistics`: The maximum number of statistics to store. Once this is
+///   reached, the least recently incremented statistics are evicted to make
+///   room for new ones. Setting this to `0` disables statistics entirely.
+///   **Default unlimited**.
+/// - `max_memory`: An approximate limit (in bytes) of the memory used by all
+///   stored data. Once this is reached, the least recently incremented
+///   statistics are evicted to make room for new data, and if there are no
+///   statistics left to evict, setting new redirects, vanity paths, or options
+///   fails. **Default unlimited**.
+/// - `snapshot_path`: The path of a file to periodically save all data to. If
+///   this file exists when the store is created, its data is loaded into the
+///   store. Changes made since the last snapshot are lost when links stops.
+///   **Default none (no snapshots)**.
+/// - `snapshot_interval`: The time (in seconds) between snapshots, if
+///   `snapshot_path` is set. **Default `60`**.
+/// - `encryption_key_file` / `encryption_key_env`: Encrypt the snapshot file
+///   with the key from this file or environment variable, see [`Cipher`] for
+///   details. **Default none (unencrypted)**.
+///
+/// A warning is logged when 90% of any of these limits is reached.
+///
+/// Each namespace has its own data and limits, and is included in snapshots.
+/// API usage counters (used for token quotas) are not included in snapshots.
+#[derive(Debug)]
+pub struct Store(Arc<Data>);
+
+impl Deref for Store {
+	type Target = Data;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+/// The data of a memory [`Store`], shared with its snapshot task
 #[derive(Debug)]
-pub struct Store {
-	redirects: RwLock<HashMap<Id, Link>>,
+pub struct Data {
+	redirects: RwLock<HashMap<Id, (Link, Option<OffsetDateTime>)>>,
 	vanity: RwLock<HashMap<Normalized, Id>>,
-	stats: RwLock<HashMap<Statistic, StatisticValue>>,
+	options: RwLock<HashMap<Id, LinkOptions>>,
+	stats: Mutex<LruCache<Statistic, StatisticValue>>,
+	/// Soft-deleted redirects, with the time until which they are kept
+	tombstones: RwLock<HashMap<Id, (Link, OffsetDateTime)>>,
+	/// API usage counters, with the time after which they are no longer needed
+	usage: Mutex<HashMap<String, (u64, OffsetDateTime)>>,
+	/// Stores of all namespaces used so far
+	namespaces: Mutex<HashMap<String, Store>>,
+	limits: Limits,
+	memory: AtomicUsize,
+}
+
+/// Size limits of the memory store, see [`Store`] for details
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Limits {
+	redirects: Option<usize>,
+	statistics: Option<usize>,
+	memory: Option<usize>,
+}
+
+impl Limits {
+	/// Parse the limits from the store configuration
+	fn from_config(config: &HashMap<String, String>) -> Result<Self> {
+		let parse = |key: &str| {
+			config
+				.get(key)
+				.map(|s| s.parse::<usize>())
+				.transpose()
+				.map_err(|err| anyhow!("couldn't parse {key} value: {err}"))
+		};
+
+		Ok(Self {
+			redirects: parse("max_redirects")?,
+			statistics: parse("max_statistics")?,
+			memory: parse("max_memory")?,
+		})
+	}
+}
+
+/// The default time between snapshots of the memory store
+const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// All data of a memory store, as saved in its snapshot file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+	/// Redirects with their expiry time (in milliseconds since the unix epoch)
+	redirects: Vec<(Id, Link, Option<i64>)>,
+	vanity: Vec<(Normalized, Id)>,
+	options: Vec<(Id, LinkOptions)>,
+	/// Statistics, from the least to the most recently incremented
+	statistics: Vec<(Statistic, StatisticValue)>,
+	/// Tombstones of soft-deleted redirects, with the time until which they
+	/// are kept (in milliseconds since the unix epoch)
+	tombstones: Vec<(Id, Link, i64)>,
+	/// Snapshots of all namespaces
+	#[serde(default)]
+	namespaces: HashMap<String, Snapshot>,
+}
+
+impl Snapshot {
+	/// Read a snapshot from the file at `path`, if it exists, decrypting it
+	/// with the `cipher` if provided
+	fn read(path: &Path, cipher: Option<&Cipher>) -> Result<Option<Self>> {
+		let bytes = match fs::read(path) {
+			Ok(bytes) => bytes,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+			Err(err) => return Err(err.into()),
+		};
+
+		let bytes = match cipher {
+			Some(cipher) => cipher.decrypt(std::str::from_utf8(&bytes)?, SNAPSHOT_AAD)?,
+			None => bytes,
+		};
+
+		Ok(Some(serde_json::from_slice(&bytes)?))
+	}
+
+	/// Atomically write this snapshot to the file at `path`, by writing to a
+	/// temporary file next to it first and then renaming it. The snapshot is
+	/// encrypted with the `cipher` if provided.
+	fn write(&self, path: &Path, cipher: Option<&Cipher>) -> Result<()> {
+		let mut temp = path.as_os_str().to_owned();
+		temp.push(".tmp");
+
+		let json = serde_json::to_vec(self)?;
+		let bytes = match cipher {
+			Some(cipher) => cipher.encrypt(&json, SNAPSHOT_AAD)?.into_bytes(),
+			None => json,
+		};
+
+		fs::write(&temp, bytes)?;
+		fs::rename(&temp, path)?;
+
+		Ok(())
+	}
+}
+
+/// Log a warning if `after` is the first value at or above 90% of `max`
+fn warn_threshold(what: &str, before: usize, after: usize, max: usize) {
+	let threshold = max - max / 10;
+
+	if before < threshold && after >= threshold {
+		warn!("The memory store's {what} are at {after} of the configured limit of {max}");
+	}
+}
+
+/// The approximate memory used by a redirect (with the `expiry`, if any)
+fn redirect_size(link: &Link, expiry: Option<OffsetDateTime>) -> usize {
+	size_of::<(Id, Link)>()
+		+ expiry.map_or(0, |_| size_of::<OffsetDateTime>())
+		+ link.as_str().len()
+}
+
+/// Convert the `time` to milliseconds since the unix epoch
+fn to_millis(time: OffsetDateTime) -> Result<i64> {
+	Ok(i64::try_from(time.unix_timestamp_nanos() / 1_000_000)?)
+}
+
+/// Convert `millis` since the unix epoch to a time
+fn from_millis(millis: i64) -> Result<OffsetDateTime> {
+	Ok(OffsetDateTime::from_unix_timestamp_nanos(
+		i128::from(millis) * 1_000_000,
+	)?)
+}
+
+/// Check whether a redirect with the `expiry` has expired
+fn is_expired(expiry: Option<OffsetDateTime>) -> bool {
+	expiry.is_some_and(|expiry| expiry <= OffsetDateTime::now_utc())
+}
+
+/// The approximate memory used by a vanity path
+fn vanity_size(vanity: &Normalized) -> usize {
+	size_of::<(Normalized, Id)>() + vanity.as_str().len()
+}
+
+/// The approximate memory used by a link's options
+fn options_size(options: &LinkOptions) -> usize {
+	size_of::<(Id, LinkOptions)>() + serde_json::to_string(options).map_or(0, |s| s.len())
+}
+
+/// The approximate memory used by a statistic
+fn statistic_size(statistic: &Statistic) -> usize {
+	let link = match statistic.link {
+		IdOrVanity::Id(_) => 0,
+		IdOrVanity::Vanity(ref vanity) => vanity.as_str().len(),
+	};
+
+	size_of::<(Statistic, StatisticValue)>() + link + statistic.data.as_str().len()
+}
+
+impl Data {
+	/// Record `size` more bytes of memory as being used
+	fn add_memory(&self, size: usize) {
+		let before = self.memory.fetch_add(size, Ordering::Relaxed);
+
+		if let Some(max) = self.limits.memory {
+			warn_threshold("memory use (in bytes)", before, before + size, max);
+		}
+	}
+
+	/// Record `size` bytes of memory as no longer being used
+	fn sub_memory(&self, size: usize) {
+		// This can't underflow, because all removals are matched by additions
+		self.memory.fetch_sub(size, Ordering::Relaxed);
+	}
+
+	/// Make room for `size` more bytes of data by evicting statistics if
+	/// necessary, then record that memory as used. Returns `false` if there is
+	/// not enough space even after evicting all statistics.
+	fn make_room(&self, stats: &mut LruCache<Statistic, StatisticValue>, size: usize) -> bool {
+		if let Some(max) = self.limits.memory {
+			while self.memory.load(Ordering::Relaxed) + size > max {
+				let Some((evicted, _)) = stats.pop_lru() else {
+					return false;
+				};
+
+				debug!(?evicted, "Statistic evicted because of the memory limit");
+				self.sub_memory(statistic_size(&evicted));
+			}
+		}
+
+		self.add_memory(size);
+		true
+	}
+
+	/// Reserve `size` more bytes of memory for new data, returning an error if
+	/// the memory limit doesn't allow that
+	fn reserve(&self, size: usize) -> Result<()> {
+		if self.make_room(&mut self.stats.lock(), size) {
+			Ok(())
+		} else {
+			warn!("The memory store's memory limit has been reached, new data can not be stored");
+			Err(anyhow!("memory store memory limit reached"))
+		}
+	}
+
+	/// Set a redirect from `from` to `to`, expiring at `expires_at` (if any)
+	#[expect(
+		clippy::significant_drop_tightening,
+		reason = "the lock is held to keep the limit check consistent"
+	)]
+	fn insert_redirect(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: Option<OffsetDateTime>,
+	) -> Result<Option<Link>> {
+		let mut redirects = self.redirects.write();
+		let len = redirects.len();
+
+		if let Some(max) = self.limits.redirects {
+			if len >= max && !redirects.contains_key(&from) {
+				warn!("The memory store's redirect limit of {max} has been reached");
+				return Err(anyhow!("memory store redirect limit reached"));
+			}
+		}
+
+		self.reserve(redirect_size(&to, expires_at))?;
+		let old = redirects.insert(from, (to, expires_at));
+
+		if let Some((ref old, expiry)) = old {
+			self.sub_memory(redirect_size(old, expiry));
+		} else if let Some(max) = self.limits.redirects {
+			warn_threshold("redirects", len, len + 1, max);
+		}
+
+		Ok(old
+			.filter(|&(_, expiry)| !is_expired(expiry))
+			.map(|(link, _)| link))
+	}
+
+	/// Get the approximate memory (in bytes) used by all stored data
+	#[cfg(test)]
+	fn memory(&self) -> usize {
+		self.memory.load(Ordering::Relaxed)
+	}
+
+	/// Collect all current (non-expired) data into a snapshot
+	fn snapshot(&self) -> Result<Snapshot> {
+		let redirects = self
+			.redirects
+			.read()
+			.iter()
+			.filter(|(_, &(_, expiry))| !is_expired(expiry))
+			.map(|(&id, (link, expiry))| Ok((id, link.clone(), expiry.map(to_millis).transpose()?)))
+			.collect::<Result<_>>()?;
+		let vanity = self
+			.vanity
+			.read()
+			.iter()
+			.map(|(vanity, &id)| (vanity.clone(), id))
+			.collect();
+		let options = self
+			.options
+			.read()
+			.iter()
+			.map(|(&id, options)| (id, options.clone()))
+			.collect();
+		let statistics = self
+			.stats
+			.lock()
+			.iter()
+			.rev()
+			.map(|(statistic, &value)| (statistic.clone(), value))
+			.collect();
+		let tombstones = self
+			.tombstones
+			.read()
+			.iter()
+			.filter(|(_, &(_, keep_until))| !is_expired(Some(keep_until)))
+			.map(|(&id, (link, keep_until))| Ok((id, link.clone(), to_millis(*keep_until)?)))
+			.collect::<Result<_>>()?;
+
+		let namespaces = self
+			.namespaces
+			.lock()
+			.iter()
+			.map(|(namespace, store)| Ok((namespace.clone(), store.snapshot()?)))
+			.collect::<Result<_>>()?;
+
+		Ok(Snapshot {
+			redirects,
+			vanity,
+			options,
+			statistics,
+			tombstones,
+			namespaces,
+		})
+	}
+}
+
+impl Store {
+	/// Create a new empty store with the `limits`
+	fn empty(limits: Limits) -> Self {
+		Self(Arc::new(Data {
+			redirects: RwLock::new(HashMap::new()),
+			vanity: RwLock::new(HashMap::new()),
+			options: RwLock::new(HashMap::new()),
+			stats: Mutex::new(LruCache::unbounded()),
+			tombstones: RwLock::new(HashMap::new()),
+			usage: Mutex::new(HashMap::new()),
+			namespaces: Mutex::new(HashMap::new()),
+			limits,
+			memory: AtomicUsize::new(0),
+		}))
+	}
+
+	/// Get the store of the `namespace`, creating it if it doesn't exist yet
+	fn namespace(&self, namespace: &str) -> Self {
+		let store = Arc::clone(
+			&self
+				.namespaces
+				.lock()
+				.entry(namespace.to_string())
+				.or_insert_with(|| Self::empty(self.limits))
+				.0,
+		);
+
+		Self(store)
+	}
+
+	/// Load all data from the `snapshot` into this store, subject to its limits
+	async fn restore(&self, snapshot: Snapshot) -> Result<()> {
+		for (id, link, expiry) in snapshot.redirects {
+			let expiry = expiry.map(from_millis).transpose()?;
+
+			if !is_expired(expiry) {
+				self.insert_redirect(id, link, expiry)?;
+			}
+		}
+
+		for (vanity, id) in snapshot.vanity {
+			self.set_vanity(vanity, id).await?;
+		}
+
+		for (id, options) in snapshot.options {
+			self.set_options(id, options).await?;
+		}
+
+		for (statistic, value) in snapshot.statistics {
+			if self.incr_statistic(statistic.clone()).await?.is_some() {
+				if let Some(stored) = self.stats.lock().get_mut(&statistic) {
+					*stored = value;
+				}
+			}
+		}
+
+		for (id, link, keep_until) in snapshot.tombstones {
+			let keep_until = from_millis(keep_until)?;
+			self.reserve(redirect_size(&link, Some(keep_until)))?;
+			self.tombstones.write().insert(id, (link, keep_until));
+		}
+
+		for (namespace, snapshot) in snapshot.namespaces {
+			Box::pin(self.namespace(&namespace).restore(snapshot)).await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Save a snapshot of the `data` to the file at `path` every `interval`, until
+/// the store is dropped
+async fn snapshot_periodically(
+	data: Weak<Data>,
+	path: PathBuf,
+	interval: Duration,
+	cipher: Option<Arc<Cipher>>,
+) {
+	loop {
+		tokio::time::sleep(interval).await;
+
+		let Some(data) = data.upgrade() else {
+			return;
+		};
+
+		let snapshot = match data.snapshot() {
+			Ok(snapshot) => snapshot,
+			Err(err) => {
+				warn!(?err, "Collecting memory store snapshot failed");
+				continue;
+			}
+		};
+		drop(data);
+
+		let path = path.clone();
+		let cipher = cipher.clone();
+		match spawn_blocking(move || snapshot.write(&path, cipher.as_deref())).await {
+			Ok(Ok(())) => debug!("Memory store snapshot saved"),
+			Ok(Err(err)) => warn!(?err, "Saving memory store snapshot failed"),
+			Err(err) => warn!(?err, "Saving memory store snapshot panicked"),
+		}
+	}
 }
 
 #[async_trait]
@@ -48,30 +501,187 @@ impl StoreBackend for Store {
 	}
 
 	#[instrument(level = "trace", ret, err)]
-	async fn new(_config: &HashMap<String, String>) -> Result<Self> {
-		Ok(Self {
-			redirects: RwLock::new(HashMap::new()),
-			vanity: RwLock::new(HashMap::new()),
-			stats: RwLock::new(HashMap::new()),
-		})
+	async fn new(config: &HashMap<String, String>) -> Result<Self> {
+		let store = Self::empty(Limits::from_config(config)?);
+
+		let Some(path) = config.get("snapshot_path").map(PathBuf::from) else {
+			return Ok(store);
+		};
+
+		let interval = config
+			.get("snapshot_interval")
+			.map(|s| s.parse::<u64>())
+			.transpose()
+			.map_err(|err| anyhow!("couldn't parse snapshot_interval value: {err}"))?
+			.map_or(DEFAULT_SNAPSHOT_INTERVAL, Duration::from_secs);
+
+		if interval.is_zero() {
+			return Err(anyhow!("snapshot_interval must not be zero"));
+		}
+
+		let cipher = Cipher::from_config(config)?.map(Arc::new);
+
+		let snapshot = {
+			let path = path.clone();
+			let cipher = cipher.clone();
+			spawn_blocking(move || Snapshot::read(&path, cipher.as_deref())).await?
+		}
+		.map_err(|err| anyhow!("couldn't read memory store snapshot: {err}"))?;
+
+		if let Some(snapshot) = snapshot {
+			store.restore(snapshot).await?;
+			info!(path = %path.display(), "Memory store data loaded from snapshot");
+		}
+
+		tokio::spawn(snapshot_periodically(
+			Arc::downgrade(&store.0),
+			path,
+			interval,
+			cipher,
+		));
+
+		Ok(store)
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
-		let redirects = self.redirects.read();
-		Ok(redirects.get(&from).map(ToOwned::to_owned))
+		let redirect = self.redirects.read().get(&from).cloned();
+
+		match redirect {
+			Some((_, expiry)) if is_expired(expiry) => {
+				// Lazily remove the expired redirect, unless it was replaced
+				let mut redirects = self.redirects.write();
+				if redirects
+					.get(&from)
+					.is_some_and(|&(_, expiry)| is_expired(expiry))
+				{
+					if let Some((old, expiry)) = redirects.remove(&from) {
+						self.sub_memory(redirect_size(&old, expiry));
+					}
+				}
+
+				Ok(None)
+			}
+			redirect => Ok(redirect.map(|(link, _)| link)),
+		}
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
-		let mut redirects = self.redirects.write();
-		Ok(redirects.insert(from, to))
+		self.insert_redirect(from, to, None)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		self.insert_redirect(from, to, Some(expires_at))
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
-		let mut redirects = self.redirects.write();
-		Ok(redirects.remove(&from))
+		let old = self.redirects.write().remove(&from);
+
+		if let Some((ref old, expiry)) = old {
+			self.sub_memory(redirect_size(old, expiry));
+		}
+
+		Ok(old
+			.filter(|&(_, expiry)| !is_expired(expiry))
+			.map(|(link, _)| link))
+	}
+
+	/// Expired tombstones are removed lazily, whenever a redirect is
+	/// soft-deleted
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let Some(link) = self.get_redirect(from).await? else {
+			return Ok(None);
+		};
+
+		let size = redirect_size(&link, Some(keep_until));
+		self.reserve(size)?;
+
+		let Some(old) = self.rem_redirect(from).await? else {
+			// The redirect was removed concurrently
+			self.sub_memory(size);
+			return Ok(None);
+		};
+
+		let mut tombstones = self.tombstones.write();
+		tombstones.retain(|_, (link, keep_until)| {
+			let expired = is_expired(Some(*keep_until));
+			if expired {
+				self.sub_memory(redirect_size(link, Some(*keep_until)));
+			}
+			!expired
+		});
+
+		if let Some((link, keep_until)) = tombstones.insert(from, (old.clone(), keep_until)) {
+			self.sub_memory(redirect_size(&link, Some(keep_until)));
+		}
+		drop(tombstones);
+
+		Ok(Some(old))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		if self.get_redirect(from).await?.is_some() {
+			bail!(NonTransient::RedirectExists);
+		}
+
+		let Some((link, keep_until)) = self.tombstones.write().remove(&from) else {
+			return Ok(None);
+		};
+
+		if is_expired(Some(keep_until)) {
+			self.sub_memory(redirect_size(&link, Some(keep_until)));
+			return Ok(None);
+		}
+
+		if let Err(err) = self.insert_redirect(from, link.clone(), None) {
+			// Keep the tombstone, so that restoring can be retried
+			self.tombstones.write().insert(from, (link, keep_until));
+			return Err(err);
+		}
+
+		self.sub_memory(redirect_size(&link, Some(keep_until)));
+		Ok(Some(link))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn purge_redirects(&self) -> Result<usize> {
+		let tombstones = std::mem::take(&mut *self.tombstones.write());
+
+		for (link, keep_until) in tombstones.values() {
+			self.sub_memory(redirect_size(link, Some(*keep_until)));
+		}
+
+		Ok(tombstones.len())
+	}
+
+	/// Searches all redirects directly, which is fast enough in memory to not
+	/// need a secondary index
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		let mut ids = self
+			.redirects
+			.read()
+			.iter()
+			.filter(|(_, (to, expiry))| to == link && !is_expired(*expiry))
+			.map(|(&id, _)| id)
+			.collect::<Vec<_>>();
+		ids.sort_unstable();
+
+		Ok(ids)
 	}
 
 	#[instrument(level = "trace", ret, err)]
@@ -82,14 +692,70 @@ impl StoreBackend for Store {
 
 	#[instrument(level = "trace", ret, err)]
 	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
-		let mut vanity = self.vanity.write();
-		Ok(vanity.insert(from, to))
+		match self.vanity.write().entry(from) {
+			Entry::Occupied(mut entry) => Ok(Some(entry.insert(to))),
+			Entry::Vacant(entry) => {
+				self.reserve(vanity_size(entry.key()))?;
+				entry.insert(to);
+				Ok(None)
+			}
+		}
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
-		let mut vanity = self.vanity.write();
-		Ok(vanity.remove(&from))
+		let old = self.vanity.write().remove(&from);
+
+		if old.is_some() {
+			self.sub_memory(vanity_size(&from));
+		}
+
+		Ok(old)
+	}
+
+	/// Searches all vanity paths directly, which is fast enough in memory to
+	/// not need a secondary index
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		let mut vanities = self
+			.vanity
+			.read()
+			.iter()
+			.filter(|(_, &to)| to == id)
+			.map(|(vanity, _)| vanity.clone())
+			.collect::<Vec<_>>();
+		vanities.sort_unstable();
+
+		Ok(vanities)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let options = self.options.read();
+		Ok(options.get(&id).map(ToOwned::to_owned))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		self.reserve(options_size(&options))?;
+		let old = self.options.write().insert(id, options);
+
+		if let Some(ref old) = old {
+			self.sub_memory(options_size(old));
+		}
+
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let old = self.options.write().remove(&id);
+
+		if let Some(ref old) = old {
+			self.sub_memory(options_size(old));
+		}
+
+		Ok(old)
 	}
 
 	#[instrument(level = "trace", ret, err)]
@@ -97,7 +763,11 @@ impl StoreBackend for Store {
 		&self,
 		description: StatisticDescription,
 	) -> Result<Vec<(Statistic, StatisticValue)>> {
-		let stats = self.stats.read();
+		// This blocks statistics from being collected while all of them are
+		// scanned. Every increment updates the LRU order (which needs exclusive
+		// access anyway), so a read-write lock would not help with that, and
+		// getting statistics is expected to be much rarer than incrementing them.
+		let stats = self.stats.lock();
 		Ok(stats
 			.iter()
 			.filter(|&(k, _)| description.matches(k))
@@ -105,24 +775,94 @@ impl StoreBackend for Store {
 			.collect())
 	}
 
+	/// Statistics are sorted by their JSON representation. Only the `limit + 1`
+	/// first statistics after the cursor are kept while all statistics are
+	/// scanned, so getting a page doesn't need memory for all of them.
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		let cursor = cursor
+			.map(|stat| serde_json::to_string(&stat))
+			.transpose()?;
+		let mut first: BTreeMap<String, (Statistic, StatisticValue)> = BTreeMap::new();
+
+		let stats = self.stats.lock();
+		for (stat, value) in stats.iter() {
+			if !description.matches(stat) {
+				continue;
+			}
+
+			let key = serde_json::to_string(stat)?;
+			let after_cursor = cursor.as_ref().map_or(true, |cursor| key > *cursor);
+			let on_page =
+				first.len() <= limit || first.last_key_value().is_some_and(|(last, _)| key < *last);
+
+			if after_cursor && on_page {
+				first.insert(key, (stat.clone(), *value));
+
+				if first.len() > limit.saturating_add(1) {
+					first.pop_last();
+				}
+			}
+		}
+		drop(stats);
+
+		Ok(Page::from_sorted(first.into_values(), limit))
+	}
+
 	#[instrument(level = "trace", ret, err)]
 	#[expect(clippy::significant_drop_tightening, reason = "false positive")]
 	async fn incr_statistic(&self, statistic: Statistic) -> Result<Option<StatisticValue>> {
-		let mut stats = self.stats.write();
+		if self.limits.statistics == Some(0) {
+			return Ok(None);
+		}
+
+		let mut stats = self.stats.lock();
 
-		#[expect(
-			clippy::option_if_let_else,
-			reason = "this is more readable than clippy's suggestion"
-		)]
 		if let Some(value) = stats.get_mut(&statistic) {
 			let new_value = value.increment();
 			*value = new_value;
-			Ok(Some(new_value))
-		} else {
-			let new_value = StatisticValue::default();
-			stats.insert(statistic, new_value);
-			Ok(Some(new_value))
+			return Ok(Some(new_value));
+		}
+
+		if let Some(max) = self.limits.statistics {
+			let len = stats.len();
+
+			if len >= max {
+				if let Some((evicted, _)) = stats.pop_lru() {
+					debug!(
+						?evicted,
+						"Statistic evicted because of the statistics limit"
+					);
+					self.sub_memory(statistic_size(&evicted));
+				}
+			}
+
+			if len + 1 == max {
+				warn!(
+					"The memory store's statistics limit of {max} has been reached, the least \
+					 recently used statistics will be evicted"
+				);
+			} else {
+				warn_threshold("statistics", len, len + 1, max);
+			}
 		}
+
+		if !self.make_room(&mut stats, statistic_size(&statistic)) {
+			debug!(
+				?statistic,
+				"Statistic not recorded because of the memory limit"
+			);
+			return Ok(None);
+		}
+
+		let new_value = StatisticValue::default();
+		stats.put(statistic, new_value);
+		Ok(Some(new_value))
 	}
 
 	#[expect(clippy::significant_drop_tightening, reason = "false positive")]
@@ -130,26 +870,130 @@ impl StoreBackend for Store {
 		&self,
 		description: StatisticDescription,
 	) -> Result<Vec<(Statistic, StatisticValue)>> {
-		let mut stats = self.stats.write();
+		let mut stats = self.stats.lock();
+		#[expect(
+			clippy::needless_collect,
+			reason = "the keys are collected to release the borrow of `stats`"
+		)]
 		let matches = stats
-			.keys()
+			.iter()
+			.map(|(k, _)| k)
 			.filter(|&k| description.matches(k))
 			.map(Clone::clone)
 			.collect::<Vec<_>>();
 
 		Ok(matches
-			.iter()
-			.filter_map(|k| stats.remove_entry(k))
+			.into_iter()
+			.filter_map(|k| stats.pop_entry(&k))
+			.inspect(|(k, _)| self.sub_memory(statistic_size(k)))
 			.collect())
 	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		let now = OffsetDateTime::now_utc();
+		let mut usage = self.usage.lock();
+		usage.retain(|_, &mut (_, until)| until > now);
+
+		let counter = usage.entry(key.to_string()).or_insert((0, expires_at));
+		counter.0 = counter.0.saturating_add(amount);
+		let value = counter.0;
+		drop(usage);
+
+		Ok(value)
+	}
+
+	fn supports_usage(&self) -> bool {
+		true
+	}
+
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		Ok(Arc::new(self.namespace(namespace)))
+	}
+
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		#[expect(
+			clippy::needless_collect,
+			reason = "the items are collected to release the locks"
+		)]
+		let mut items = self
+			.redirects
+			.read()
+			.iter()
+			.filter(|(_, &(_, expiry))| !is_expired(expiry))
+			.map(|(&id, (link, _))| ExportItem::Redirect {
+				id,
+				link: link.clone(),
+			})
+			.collect::<Vec<_>>();
+		items.extend(
+			self.vanity
+				.read()
+				.iter()
+				.map(|(vanity, &id)| ExportItem::Vanity {
+					vanity: vanity.clone(),
+					id,
+				}),
+		);
+		items.extend(
+			self.options
+				.read()
+				.iter()
+				.map(|(&id, options)| ExportItem::Options {
+					id,
+					options: options.clone(),
+				}),
+		);
+		items.extend(
+			self.stats
+				.lock()
+				.iter()
+				.map(|(statistic, &value)| ExportItem::Statistic {
+					statistic: statistic.clone(),
+					value,
+				}),
+		);
+
+		Ok(Box::pin(stream::iter(items.into_iter().map(Ok))))
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		Ok(Page::from_unsorted(
+			self.redirects
+				.read()
+				.iter()
+				.filter(|(_, &(_, expiry))| !is_expired(expiry))
+				.map(|(&id, (link, _))| (id, link.clone())),
+			cursor.as_ref(),
+			limit,
+		))
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		Ok(Page::from_unsorted(
+			self.vanity
+				.read()
+				.iter()
+				.map(|(vanity, &id)| (vanity.clone(), id)),
+			cursor.as_ref(),
+			limit,
+		))
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use std::collections::HashMap;
+	use std::{collections::HashMap, env};
 
-	use super::Store;
-	use crate::store::{tests, StoreBackend as _};
+	use super::*;
+	use crate::{
+		stats::{StatisticData, StatisticTime, StatisticType},
+		store::tests,
+	};
 
 	async fn get_store() -> Store {
 		Store::new(&HashMap::from([])).await.unwrap()
@@ -170,36 +1014,101 @@ mod tests {
 		tests::get_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_redirects() {
+		tests::get_redirects(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn set_redirect() {
 		tests::set_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn set_redirects() {
+		tests::set_redirects(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn rem_redirect() {
 		tests::rem_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn rem_redirects() {
+		tests::rem_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn soft_delete() {
+		tests::soft_delete(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect_with_expiry() {
+		tests::set_redirect_with_expiry(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn find_by_link() {
+		tests::find_by_link(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn get_vanity() {
 		tests::get_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_vanities() {
+		tests::get_vanities(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn set_vanity() {
 		tests::set_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn set_vanities() {
+		tests::set_vanities(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn rem_vanity() {
 		tests::rem_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_vanities_of() {
+		tests::get_vanities_of(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_options() {
+		tests::get_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_options() {
+		tests::set_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_options() {
+		tests::rem_options(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn get_statistics() {
 		tests::get_statistics(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_statistics_page() {
+		tests::get_statistics_page(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn incr_statistic() {
 		tests::incr_statistic(&get_store().await).await;
@@ -209,4 +1118,301 @@ mod tests {
 	async fn rem_statistics() {
 		tests::rem_statistics(&get_store().await).await;
 	}
+
+	#[tokio::test]
+	async fn incr_usage() {
+		tests::incr_usage(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn export_all() {
+		tests::export_all(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn import() {
+		tests::import(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_redirects() {
+		tests::list_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_vanities() {
+		tests::list_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn health() {
+		tests::health(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn namespaced() {
+		tests::namespaced(&get_store().await).await;
+	}
+
+	fn statistic(n: u64) -> Statistic {
+		Statistic {
+			link: IdOrVanity::Id(Id::try_from(n).unwrap()),
+			stat_type: StatisticType::Request,
+			data: StatisticData::default(),
+			time: StatisticTime::now(),
+		}
+	}
+
+	#[tokio::test]
+	async fn limits_config() {
+		assert!(Store::new(&HashMap::from([(
+			"max_redirects".to_string(),
+			"many".to_string()
+		)]))
+		.await
+		.is_err());
+
+		let store = Store::new(&HashMap::from([
+			("max_redirects".to_string(), "1".to_string()),
+			("max_statistics".to_string(), "2".to_string()),
+			("max_memory".to_string(), "1000000".to_string()),
+		]))
+		.await
+		.unwrap();
+
+		assert_eq!(store.limits, Limits {
+			redirects: Some(1),
+			statistics: Some(2),
+			memory: Some(1_000_000),
+		});
+	}
+
+	#[tokio::test]
+	async fn max_redirects() {
+		let store = Store::new(&HashMap::from([(
+			"max_redirects".to_string(),
+			"2".to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		let link = Link::new("https://example.com/").unwrap();
+
+		store
+			.set_redirect(Id::try_from(1).unwrap(), link.clone())
+			.await
+			.unwrap();
+		store
+			.set_redirect(Id::try_from(2).unwrap(), link.clone())
+			.await
+			.unwrap();
+		assert!(store
+			.set_redirect(Id::try_from(3).unwrap(), link.clone())
+			.await
+			.is_err());
+
+		// Replacing existing redirects is still possible
+		store
+			.set_redirect(Id::try_from(2).unwrap(), link.clone())
+			.await
+			.unwrap();
+
+		store.rem_redirect(Id::try_from(1).unwrap()).await.unwrap();
+		store
+			.set_redirect(Id::try_from(3).unwrap(), link)
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn max_statistics() {
+		let store = Store::new(&HashMap::from([(
+			"max_statistics".to_string(),
+			"2".to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		store.incr_statistic(statistic(1)).await.unwrap();
+		store.incr_statistic(statistic(2)).await.unwrap();
+		store.incr_statistic(statistic(1)).await.unwrap();
+		store.incr_statistic(statistic(3)).await.unwrap();
+
+		// Statistic 2 was the least recently used, so it was evicted
+		let stats = store
+			.get_statistics(StatisticDescription::default())
+			.await
+			.unwrap();
+		assert_eq!(stats.len(), 2);
+		assert!(stats
+			.iter()
+			.any(|(s, v)| *s == statistic(1) && v.get() == 2));
+		assert!(stats.iter().any(|(s, _)| *s == statistic(3)));
+
+		// Statistics are disabled with a limit of 0
+		let store = Store::new(&HashMap::from([(
+			"max_statistics".to_string(),
+			"0".to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		assert_eq!(store.incr_statistic(statistic(1)).await.unwrap(), None);
+		assert_eq!(
+			store
+				.get_statistics(StatisticDescription::default())
+				.await
+				.unwrap(),
+			Vec::new()
+		);
+		assert_eq!(store.memory(), 0);
+	}
+
+	#[tokio::test]
+	async fn max_memory() {
+		let link = Link::new("https://example.com/").unwrap();
+		let max = redirect_size(&link, None) + 3 * statistic_size(&statistic(0));
+
+		let store = Store::new(&HashMap::from([(
+			"max_memory".to_string(),
+			max.to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		for i in 0..4 {
+			store.incr_statistic(statistic(i)).await.unwrap();
+		}
+
+		assert!(store.memory() <= max);
+
+		// Statistics are evicted to make room for redirects
+		store
+			.set_redirect(Id::try_from(1).unwrap(), link.clone())
+			.await
+			.unwrap();
+		assert!(store.memory() <= max);
+		let stats = store
+			.get_statistics(StatisticDescription::default())
+			.await
+			.unwrap();
+		assert!(!stats.iter().any(|(s, _)| *s == statistic(0)));
+
+		// Nothing left to evict
+		store
+			.set_redirect(Id::try_from(2).unwrap(), link.clone())
+			.await
+			.unwrap();
+		store
+			.set_redirect(Id::try_from(3).unwrap(), link.clone())
+			.await
+			.unwrap();
+		store
+			.set_redirect(Id::try_from(4).unwrap(), link.clone())
+			.await
+			.unwrap();
+		assert!(store
+			.set_redirect(Id::try_from(5).unwrap(), link)
+			.await
+			.is_err());
+		assert!(store.memory() <= max);
+		assert_eq!(
+			store
+				.get_statistics(StatisticDescription::default())
+				.await
+				.unwrap(),
+			Vec::new()
+		);
+
+		store.rem_redirect(Id::try_from(1).unwrap()).await.unwrap();
+		store.rem_redirect(Id::try_from(2).unwrap()).await.unwrap();
+		store.rem_redirect(Id::try_from(3).unwrap()).await.unwrap();
+		store.rem_redirect(Id::try_from(4).unwrap()).await.unwrap();
+		assert_eq!(store.memory(), 0);
+	}
+
+	#[tokio::test]
+	async fn snapshot() {
+		let path = env::temp_dir().join(format!("links-memory-test-{}.json", Id::new()));
+		let config = HashMap::from([
+			(
+				"snapshot_path".to_string(),
+				path.to_string_lossy().to_string(),
+			),
+			("snapshot_interval".to_string(), "1".to_string()),
+		]);
+
+		let id = Id::from([0x23, 0, 0, 0, 1]);
+		let link = Link::new("https://example.com/snapshot").unwrap();
+		let vanity = Normalized::new("snapshot");
+
+		let store = Store::new(&config).await.unwrap();
+		store.set_redirect(id, link.clone()).await.unwrap();
+		store.set_vanity(vanity.clone(), id).await.unwrap();
+		store.incr_statistic(statistic(0x23)).await.unwrap();
+		store.incr_statistic(statistic(0x23)).await.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(1500)).await;
+		drop(store);
+		assert!(path.exists());
+
+		let store = Store::new(&config).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		assert_eq!(store.get_vanity(vanity).await.unwrap(), Some(id));
+		let stats = store
+			.get_statistics(StatisticDescription::default())
+			.await
+			.unwrap();
+		assert_eq!(stats.len(), 1);
+		assert_eq!(stats[0].1.get(), 2);
+		drop(store);
+
+		fs::write(&path, "not a snapshot").unwrap();
+		assert!(Store::new(&config).await.is_err());
+
+		let _ = fs::remove_file(&path);
+	}
+
+	#[tokio::test]
+	async fn encrypted_snapshot() {
+		let path = env::temp_dir().join(format!("links-memory-test-{}.json", Id::new()));
+		let key_path = env::temp_dir().join(format!("links-memory-test-{}.key", Id::new()));
+		fs::write(&key_path, "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+		let config = HashMap::from([
+			(
+				"snapshot_path".to_string(),
+				path.to_string_lossy().to_string(),
+			),
+			("snapshot_interval".to_string(), "1".to_string()),
+			(
+				"encryption_key_file".to_string(),
+				key_path.to_string_lossy().to_string(),
+			),
+		]);
+
+		let id = Id::from([0x23, 0, 0, 0, 2]);
+		let link = Link::new("https://example.com/encrypted").unwrap();
+
+		let store = Store::new(&config).await.unwrap();
+		store.set_redirect(id, link.clone()).await.unwrap();
+
+		tokio::time::sleep(Duration::from_millis(1500)).await;
+		drop(store);
+		assert!(!fs::read_to_string(&path).unwrap().contains("example.com"));
+
+		let store = Store::new(&config).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		drop(store);
+
+		// The snapshot can't be read without the right key
+		let mut unencrypted = config.clone();
+		unencrypted.remove("encryption_key_file");
+		assert!(Store::new(&unencrypted).await.is_err());
+
+		fs::write(&key_path, "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=").unwrap();
+		assert!(Store::new(&config).await.is_err());
+
+		let _ = fs::remove_file(&path);
+		let _ = fs::remove_file(&key_path);
+	}
 }
@@ -0,0 +1,129 @@
+//! Migration of all data from one links store to another, e.g. when switching
+//! store backends. See [`migrate`] for details.
+
+use anyhow::Result;
+use futures_util::TryStreamExt;
+use tracing::instrument;
+
+use crate::store::{
+	backend::{import_item, ImportPolicy, ImportSummary},
+	Store,
+};
+
+/// How many items are migrated between progress reports
+const PROGRESS_INTERVAL: usize = 1000;
+
+/// Copy all redirects, vanity paths, and link options from the `source` store
+/// to the `destination` store, with conflicts with data already in the
+/// destination resolved according to the `policy`. Statistics are not
+/// migrated.
+///
+/// The `progress` function is called with the total number of items read from
+/// the source store so far after every 1000 items, and once more at the end.
+///
+/// If `dry_run` is set, nothing is written to the destination store, but it is
+/// still read from to determine what would be migrated, and conflicts are
+/// still reported as errors with [`ImportPolicy::Error`].
+///
+/// Returns the number of items which were (or would be) migrated or skipped.
+///
+/// # Errors
+/// This function returns an error if exporting from the `source` store or
+/// importing into the `destination` store fails, in which case some items may
+/// have already been migrated.
+#[instrument(level = "info", skip(source, destination, progress), ret, err)]
+pub async fn migrate(
+	source: &Store,
+	destination: &Store,
+	policy: ImportPolicy,
+	dry_run: bool,
+	mut progress: impl FnMut(usize) + Send,
+) -> Result<ImportSummary> {
+	let mut read = 0;
+	let mut items = source.export_all().await?.inspect_ok(|_| {
+		read += 1;
+
+		if read % PROGRESS_INTERVAL == 0 {
+			progress(read);
+		}
+	});
+
+	let summary = if dry_run {
+		let mut summary = ImportSummary::default();
+
+		while let Some(item) = items.try_next().await? {
+			if import_item(&*destination.primary, item, policy, false).await? {
+				summary.imported += 1;
+			} else {
+				summary.skipped += 1;
+			}
+		}
+
+		summary
+	} else {
+		destination.import(&mut items, policy).await?
+	};
+
+	drop(items);
+	progress(read);
+
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use links_id::Id;
+	use links_normalized::{Link, Normalized};
+
+	use super::*;
+	use crate::store::BackendType;
+
+	#[tokio::test]
+	async fn migrate() {
+		let source = Store::new(BackendType::Memory, &HashMap::new())
+			.await
+			.unwrap();
+		let destination = Store::new(BackendType::Memory, &HashMap::new())
+			.await
+			.unwrap();
+
+		let id = Id::from([0x1b, 0x2b, 0x3b, 0x4b, 0x5b]);
+		let link = Link::new("https://example.com/migrate").unwrap();
+		let vanity = Normalized::new("migrate");
+
+		source.set_redirect(id, link.clone()).await.unwrap();
+		source.set_vanity(vanity.clone(), id).await.unwrap();
+
+		let mut reported = 0;
+		let summary = super::migrate(&source, &destination, ImportPolicy::Skip, true, |read| {
+			reported = read;
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(summary.imported, 2);
+		assert_eq!(reported, 2);
+		assert_eq!(destination.get_redirect(id).await.unwrap(), None);
+
+		let summary = super::migrate(&source, &destination, ImportPolicy::Error, false, |_| {})
+			.await
+			.unwrap();
+
+		assert_eq!(summary.imported, 2);
+		assert_eq!(destination.get_redirect(id).await.unwrap(), Some(link));
+		assert_eq!(destination.get_vanity(vanity).await.unwrap(), Some(id));
+
+		source
+			.set_redirect(id, Link::new("https://example.com/changed").unwrap())
+			.await
+			.unwrap();
+
+		assert!(
+			super::migrate(&source, &destination, ImportPolicy::Error, true, |_| {})
+				.await
+				.is_err()
+		);
+	}
+}
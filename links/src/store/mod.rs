@@ -3,26 +3,55 @@
 //! each store backend, see that backend's documentation.
 
 pub mod backend;
+mod cache;
+mod encryption;
+mod etcd;
 mod memory;
+mod migrate;
+mod redb;
 mod redis;
+mod retry;
+mod s3;
+mod tiered;
 
 #[cfg(test)]
 mod tests;
 
-use std::{collections::HashMap, sync::Arc};
-
-use anyhow::Result;
-use backend::StoreBackend;
+use std::{
+	collections::HashMap,
+	future::Future,
+	pin::Pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use backend::{ExportItem, ExportStream, Health, ImportPolicy, ImportSummary, Page, StoreBackend};
+use futures_util::{future::try_join_all, stream, Stream};
 use links_id::Id;
 use links_normalized::{Link, Normalized};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use strum::{Display as EnumDisplay, EnumString, IntoStaticStr};
-use tokio::spawn;
-use tracing::{debug, instrument, trace};
-
-pub use self::{memory::Store as Memory, redis::Store as Redis};
-use crate::stats::{Statistic, StatisticDescription, StatisticValue};
+use time::OffsetDateTime;
+use tokio::{
+	spawn,
+	sync::broadcast::{self, error::RecvError},
+};
+use tracing::{debug, instrument, trace, warn};
+
+pub use self::{
+	cache::Cached, etcd::Store as Etcd, memory::Store as Memory, migrate::migrate,
+	redb::Store as Redb, redis::Store as Redis, retry::Retry, s3::Store as S3, tiered::Tiered,
+};
+use crate::{
+	metrics::{self, StoreOperation},
+	options::LinkOptions,
+	stats::{Statistic, StatisticDescription, StatisticValue},
+};
 
 /// The type of store backend used by the links redirector server. All variants
 /// must have a canonical human-readable string representation using only
@@ -51,6 +80,14 @@ pub enum BackendType {
 	Memory,
 	/// A store backend which stores all data using a Redis 6.2+ server.
 	Redis,
+	/// An embedded store backend, which stores all data in a local database
+	/// file using redb.
+	Redb,
+	/// A store backend which stores all data in an etcd v3 cluster.
+	Etcd,
+	/// A store backend which stores redirects, vanity paths, and options as
+	/// objects in an S3-compatible bucket, without support for statistics.
+	S3,
 }
 
 impl BackendType {
@@ -61,6 +98,21 @@ impl BackendType {
 	}
 }
 
+/// Create a new backend of the type `B` with the `config`, wrapped in a
+/// [`Tiered`] backend if the tiered store is enabled, or otherwise in a
+/// [`Cached`] backend if the read cache is enabled
+async fn backend<B: StoreBackend + 'static>(
+	config: &HashMap<String, String>,
+) -> Result<Arc<dyn StoreBackend>> {
+	if Tiered::<B>::is_enabled(config)? {
+		Ok(Arc::new(Tiered::<B>::new(config).await?))
+	} else if Cached::<B>::is_enabled(config)? {
+		Ok(Arc::new(Cached::<B>::new(config).await?))
+	} else {
+		Ok(Arc::new(B::new(config).await?))
+	}
+}
+
 /// A holder for a [`Store`], which allows the store to be updated on the fly.
 #[derive(Debug)]
 pub struct Current {
@@ -109,11 +161,74 @@ impl Current {
 	}
 }
 
+/// A boxed future returned by [`StoreBackend`] methods
+type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// The maximum length of a namespace name
+const MAX_NAMESPACE_LEN: usize = 64;
+
+/// How many incremented statistics can be buffered for each statistics watcher
+/// before the oldest ones are skipped
+const STATISTICS_WATCH_CAPACITY: usize = 1024;
+
+/// How many redirect changes can be buffered for each redirect watcher before
+/// it is considered to have fallen behind
+const REDIRECTS_WATCH_CAPACITY: usize = 1024;
+
+/// The number of vanity paths listed at a time when searching vanity paths
+const SEARCH_PAGE_SIZE: usize = 1000;
+
+/// The maximum number of vanity paths scanned by one vanity path search
+const SEARCH_SCAN_LIMIT: usize = 100_000;
+
+/// How long click counters of links are kept in the store for (about 10 years)
+const CLICKS_RETENTION: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+
+/// A change to a redirect made through a [`Store`], as yielded by
+/// [`Store::watch_redirects`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectChange {
+	/// The ID of the changed redirect
+	pub id: Id,
+	/// The redirect's link before the change, if it existed
+	pub old: Option<Link>,
+	/// The redirect's link after the change, or `None` if it was removed
+	pub new: Option<Link>,
+}
+
 /// A wrapper around any [`StoreBackend`], providing access to the underlying
 /// store along some with extra things like logging.
+///
+/// # Read replicas
+/// A store can optionally have any number of read replicas. If replicas are
+/// configured, all reads (getting redirects, vanity paths, and statistics) are
+/// distributed between them in a round-robin fashion, while all writes go to
+/// the primary store. If reading from a replica fails, the primary is used
+/// instead.
+///
+/// # Retries
+/// Reads and idempotent writes which fail can be retried with exponential
+/// backoff, and a circuit breaker can stop store operations once they keep
+/// failing, see [`Retry`] for details. Incrementing statistics, soft deletion,
+/// exporting, and importing are never retried.
 #[derive(Debug, Clone)]
 pub struct Store {
-	store: Arc<dyn StoreBackend>,
+	primary: Arc<dyn StoreBackend>,
+	replicas: Arc<[Arc<dyn StoreBackend>]>,
+	next_replica: Arc<AtomicUsize>,
+	/// How long soft-deleted redirects can be restored for
+	tombstone_retention: Duration,
+	retry: Arc<Retry>,
+	/// The namespace of this store, if any
+	namespace: Option<Arc<str>>,
+	/// Stores for namespaces of this store which have already been used
+	namespaces: Arc<Mutex<HashMap<String, Store>>>,
+	/// Incremented statistics (with their new values, if known) are sent here
+	/// for [`watch_statistics`][Self::watch_statistics]
+	statistics_watch: broadcast::Sender<(Statistic, Option<StatisticValue>)>,
+	/// Changes to redirects are sent here for
+	/// [`watch_redirects`][Self::watch_redirects]
+	redirects_watch: broadcast::Sender<RedirectChange>,
 }
 
 impl Store {
@@ -126,24 +241,223 @@ impl Store {
 	/// This may happen if the configuration is invalid or for other
 	/// backend-specific reasons (such as a file not being createable or a
 	/// network connection not being establishable, etc.).
+	///
+	/// For the Redis backend, read replicas can be configured using the
+	/// `replicas` option, a comma-separated list of `host:port` pairs. All
+	/// other options (e.g. authentication and TLS) are shared with the primary.
+	///
+	/// For all backends, redirects and vanity paths can be cached in memory
+	/// using the `read_cache_size` and `read_cache_ttl` options, see [`Cached`]
+	/// for details. With read replicas, each replica has its own cache.
+	/// Alternatively, all of them can be replicated in memory using the
+	/// `tiered` option, so that redirects keep working while the backend is
+	/// unavailable, see [`Tiered`] for details.
+	///
+	/// For backends supporting soft deletion, the `tombstone_retention` option
+	/// sets for how long (in seconds) redirects removed using
+	/// [`rem_redirect_soft`][Self::rem_redirect_soft] can be restored. `0`
+	/// (the default) disables soft deletion.
+	///
+	/// For all backends, failed operations can be retried using the `retries`
+	/// option, and a circuit breaker can be enabled using the
+	/// `breaker_threshold` option, see [`Retry`] for details and other options.
 	#[instrument(level = "debug", ret, err)]
 	pub async fn new(store_type: BackendType, config: &HashMap<String, String>) -> Result<Self> {
-		match store_type {
-			BackendType::Memory => Ok(Self {
-				store: Arc::new(Memory::new(config).await?),
-			}),
-			BackendType::Redis => Ok(Self {
-				store: Arc::new(Redis::new(config).await?),
-			}),
+		let store = match store_type {
+			BackendType::Memory => {
+				Self::with_replicas(backend::<Memory>(config).await?, Vec::new())
+			}
+			BackendType::Redb => Self::with_replicas(backend::<Redb>(config).await?, Vec::new()),
+			BackendType::Etcd => Self::with_replicas(backend::<Etcd>(config).await?, Vec::new()),
+			BackendType::S3 => Self::with_replicas(backend::<S3>(config).await?, Vec::new()),
+			BackendType::Redis => {
+				let mut replicas: Vec<Arc<dyn StoreBackend>> = Vec::new();
+
+				for replica in config
+					.get("replicas")
+					.map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()))
+					.into_iter()
+					.flatten()
+				{
+					let mut replica_config = config.clone();
+					replica_config.remove("replicas");
+					replica_config.insert("cluster".to_string(), "false".to_string());
+					replica_config.insert("connect".to_string(), replica.to_string());
+
+					replicas.push(backend::<Redis>(&replica_config).await?);
+				}
+
+				Self::with_replicas(backend::<Redis>(config).await?, replicas)
+			}
+		};
+
+		let tombstone_retention = config
+			.get("tombstone_retention")
+			.map(|s| s.parse::<u64>())
+			.transpose()
+			.map_err(|err| anyhow!("couldn't parse tombstone_retention value: {err}"))?
+			.map_or(Duration::ZERO, Duration::from_secs);
+
+		Ok(Self {
+			tombstone_retention,
+			retry: Arc::new(Retry::from_config(config)?),
+			..store
+		})
+	}
+
+	/// Create a new `Store` from a primary backend and its read replicas
+	fn with_replicas(primary: Arc<dyn StoreBackend>, replicas: Vec<Arc<dyn StoreBackend>>) -> Self {
+		Self {
+			primary,
+			replicas: replicas.into(),
+			next_replica: Arc::new(AtomicUsize::new(0)),
+			tombstone_retention: Duration::ZERO,
+			retry: Arc::new(Retry::disabled()),
+			namespace: None,
+			namespaces: Arc::new(Mutex::new(HashMap::new())),
+			statistics_watch: broadcast::channel(STATISTICS_WATCH_CAPACITY).0,
+			redirects_watch: broadcast::channel(REDIRECTS_WATCH_CAPACITY).0,
+		}
+	}
+
+	/// Get a store for the `namespace`, with completely separate redirects,
+	/// vanity paths, options, and statistics, but using the same store backend
+	/// (e.g. the same database connection and configuration). This allows
+	/// multiple logical link collections to share one backend. The store
+	/// without a namespace is separate from all namespaces.
+	///
+	/// Namespaces can't be nested, so on a namespaced store this only works
+	/// with its own namespace. Namespace names must be between 1 and 64
+	/// characters long, and only contain ASCII letters, digits, `-`, and `_`.
+	///
+	/// # Errors
+	/// An error is returned if the namespace name is invalid, if the store
+	/// backend doesn't support namespaces, or if the namespace's storage could
+	/// not be set up.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn with_namespace(&self, namespace: &str) -> Result<Self> {
+		if let Some(ref current) = self.namespace {
+			if **current == *namespace {
+				return Ok(self.clone());
+			}
+
+			bail!("namespaces can not be nested");
+		}
+
+		if namespace.is_empty()
+			|| namespace.len() > MAX_NAMESPACE_LEN
+			|| !namespace
+				.bytes()
+				.all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+		{
+			bail!("invalid namespace name");
+		}
+
+		if let Some(store) = self.namespaces.lock().get(namespace) {
+			return Ok(store.clone());
+		}
+
+		let primary = self.primary.namespaced(namespace).await?;
+		let replicas = try_join_all(
+			self.replicas
+				.iter()
+				.map(|replica| replica.namespaced(namespace)),
+		)
+		.await?;
+
+		let store = Self {
+			tombstone_retention: self.tombstone_retention,
+			retry: Arc::clone(&self.retry),
+			namespace: Some(namespace.into()),
+			..Self::with_replicas(primary, replicas)
+		};
+
+		Ok(self
+			.namespaces
+			.lock()
+			.entry(namespace.to_string())
+			.or_insert(store)
+			.clone())
+	}
+
+	/// Get the namespace of this store, or `None` if it isn't namespaced
+	#[must_use]
+	pub fn namespace(&self) -> Option<&str> {
+		self.namespace.as_deref()
+	}
+
+	/// Perform a read operation, using the next replica (if there are any),
+	/// and falling back to the primary if that fails. The operation is retried
+	/// according to the store's retry policy.
+	async fn read<'a, T: Send>(
+		&'a self,
+		op: impl Fn(&'a dyn StoreBackend) -> BackendFuture<'a, T> + Send + Sync,
+	) -> Result<T> {
+		let start = Instant::now();
+		let res = self
+			.retry
+			.run(|| async {
+				if !self.replicas.is_empty() {
+					let i = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+
+					match op(self.replicas[i].as_ref()).await {
+						Ok(res) => return Ok(res),
+						Err(err) => {
+							warn!(?err, "Reading from replica failed, falling back to primary");
+						}
+					}
+				}
+
+				op(self.primary.as_ref()).await
+			})
+			.await;
+
+		metrics::record_store_operation(StoreOperation::Read, res.is_ok(), start.elapsed());
+		res
+	}
+
+	/// Perform an idempotent operation on the primary, retrying it according
+	/// to the store's retry policy
+	async fn write<'a, T>(
+		&'a self,
+		op: impl Fn(&'a dyn StoreBackend) -> BackendFuture<'a, T> + Send + Sync,
+	) -> Result<T> {
+		let start = Instant::now();
+		let res = self.retry.run(|| op(self.primary.as_ref())).await;
+		metrics::record_store_operation(StoreOperation::Write, res.is_ok(), start.elapsed());
+		res
+	}
+
+	/// Send the `changes` to all redirect watchers, if there are any
+	fn redirects_changed(&self, changes: impl IntoIterator<Item = RedirectChange>) {
+		if self.redirects_watch.receiver_count() == 0 {
+			return;
+		}
+
+		for change in changes {
+			// This only fails if all watchers are gone in the meantime
+			let _ = self.redirects_watch.send(change);
 		}
 	}
 
+	/// Send the removal of the redirects with the IDs `from`, which had the
+	/// `old` links (in the same order), to all redirect watchers
+	fn redirects_removed(&self, from: &[Id], old: &[Option<Link>]) {
+		self.redirects_changed(from.iter().zip(old).filter_map(|(&id, old)| {
+			Some(RedirectChange {
+				id,
+				old: Some(old.clone()?),
+				new: None,
+			})
+		}));
+	}
+
 	/// Get the underlying implementation's name. The name (used in e.g. the
 	/// configuration) of the backend store implementing this trait must be a
 	/// human-readable name using only 'a'-'z', '0'-'9', and '_'.
 	#[must_use]
 	pub fn backend_name(&self) -> &'static str {
-		self.store.get_store_type().as_str()
+		self.primary.get_store_type().as_str()
 	}
 
 	/// Get a redirect. Returns the full `to` link corresponding to the `from`
@@ -156,7 +470,21 @@ impl Store {
 	/// considered an error.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
-		self.store.get_redirect(from).await
+		self.read(|store| store.get_redirect(from)).await
+	}
+
+	/// Get multiple redirects at once. Returns the full `to` links
+	/// corresponding to each of the `from` IDs, in the same order, with `None`
+	/// for IDs without a redirect. Where the backend supports it, all
+	/// redirects are fetched in a single round-trip.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if a link exists or not, or what it is. A link not existing is not
+	/// considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		self.read(|store| store.get_redirects(from)).await
 	}
 
 	/// Set a redirect. `from` is the ID of the link, while `to` is the full
@@ -170,7 +498,71 @@ impl Store {
 	/// insofar as that is possible to determine from the backend.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
-		self.store.set_redirect(from, to).await
+		let old = self
+			.write(|store| store.set_redirect(from, to.clone()))
+			.await?;
+
+		self.redirects_changed([RedirectChange {
+			id: from,
+			old: old.clone(),
+			new: Some(to),
+		}]);
+
+		Ok(old)
+	}
+
+	/// Set multiple redirects at once, e.g. when importing many links. Works
+	/// like [`set_redirect`][Self::set_redirect] for each of the `redirects` in
+	/// order, returning the old links in the same order. Where the backend
+	/// supports it, all redirects are set in a single round-trip.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all new values were definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, some of the values may have been set already,
+	/// unless the backend sets all of them atomically.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn set_redirects(&self, redirects: &[(Id, Link)]) -> Result<Vec<Option<Link>>> {
+		let old = self.write(|store| store.set_redirects(redirects)).await?;
+
+		self.redirects_changed(
+			redirects
+				.iter()
+				.zip(&old)
+				.map(|((id, to), old)| RedirectChange {
+					id: *id,
+					old: old.clone(),
+					new: Some(to.clone()),
+				}),
+		);
+
+		Ok(old)
+	}
+
+	/// Set a redirect which expires at `expires_at`, after which it is treated
+	/// as if it didn't exist. Otherwise the same as [`Store::set_redirect`].
+	///
+	/// # Errors
+	/// Returns an error if the backend does not support expiring redirects, or
+	/// under the same conditions as [`Store::set_redirect`].
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let old = self
+			.write(|store| store.set_redirect_with_expiry(from, to.clone(), expires_at))
+			.await?;
+
+		self.redirects_changed([RedirectChange {
+			id: from,
+			old: old.clone(),
+			new: Some(to),
+		}]);
+
+		Ok(old)
 	}
 
 	/// Remove a redirect. `from` is the ID of the links link to be removed.
@@ -184,7 +576,132 @@ impl Store {
 	/// modified, insofar as that is possible to determine from the backend.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
-		self.store.rem_redirect(from).await
+		let old = self.write(|store| store.rem_redirect(from)).await?;
+
+		self.redirects_changed(old.clone().map(|old| RedirectChange {
+			id: from,
+			old: Some(old),
+			new: None,
+		}));
+
+		Ok(old)
+	}
+
+	/// Remove multiple redirects at once. Works like
+	/// [`rem_redirect`][Self::rem_redirect] for each of the IDs in `from` in
+	/// order, returning the old links in the same order. Where the backend
+	/// supports it, all redirects are removed in a single round-trip.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all values were definitely removed / processed /
+	/// saved, and will be unavailable on next request.
+	/// If an `Err` is returned, some of the values may have been removed
+	/// already, unless the backend removes all of them atomically.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn rem_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let old = self.write(|store| store.rem_redirects(from)).await?;
+		self.redirects_removed(from, &old);
+		Ok(old)
+	}
+
+	/// Remove a redirect, keeping a tombstone of it for the configured
+	/// `tombstone_retention`, so that it can be restored using
+	/// [`restore_redirect`][Self::restore_redirect] until then. If soft
+	/// deletion is disabled, this works just like
+	/// [`rem_redirect`][Self::rem_redirect]. Returns the old value of the
+	/// mapping or `None` if there was no such mapping.
+	///
+	/// # Error
+	/// An error is returned if soft deletion is enabled but not supported by
+	/// the store backend, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn rem_redirect_soft(&self, from: Id) -> Result<Option<Link>> {
+		if self.tombstone_retention.is_zero() {
+			return self.rem_redirect(from).await;
+		}
+
+		let keep_until = OffsetDateTime::now_utc() + self.tombstone_retention;
+		let old = self.primary.rem_redirect_soft(from, keep_until).await?;
+
+		self.redirects_changed(old.clone().map(|old| RedirectChange {
+			id: from,
+			old: Some(old),
+			new: None,
+		}));
+
+		Ok(old)
+	}
+
+	/// Remove multiple redirects at once, keeping tombstones of them like
+	/// [`rem_redirect_soft`][Self::rem_redirect_soft]. If soft deletion is
+	/// disabled, this works just like [`rem_redirects`][Self::rem_redirects].
+	/// Returns the old links in the same order as `from`.
+	///
+	/// # Error
+	/// An error is returned if soft deletion is enabled but not supported by
+	/// the store backend, or if something fails when it should have worked.
+	/// Some of the redirects may have been removed already in that case.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn rem_redirects_soft(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		if self.tombstone_retention.is_zero() {
+			return self.rem_redirects(from).await;
+		}
+
+		let keep_until = OffsetDateTime::now_utc() + self.tombstone_retention;
+		let mut old = Vec::with_capacity(from.len());
+		for &id in from {
+			old.push(self.primary.rem_redirect_soft(id, keep_until).await?);
+		}
+
+		self.redirects_removed(from, &old);
+		Ok(old)
+	}
+
+	/// Restore a redirect removed using
+	/// [`rem_redirect_soft`][Self::rem_redirect_soft] within the last
+	/// `tombstone_retention`. Returns the restored link, or `None` if there is
+	/// nothing to restore. Restored redirects never expire.
+	///
+	/// # Error
+	/// An error is returned if soft deletion is not supported by the store
+	/// backend, if a redirect with the ID already exists, or if something
+	/// fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let restored = self.primary.restore_redirect(from).await?;
+
+		self.redirects_changed(restored.clone().map(|new| RedirectChange {
+			id: from,
+			old: None,
+			new: Some(new),
+		}));
+
+		Ok(restored)
+	}
+
+	/// Permanently remove all tombstones of soft-deleted redirects, so that
+	/// they can no longer be restored. Returns the number of removed
+	/// tombstones.
+	///
+	/// # Error
+	/// An error is returned if soft deletion is not supported by the store
+	/// backend, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn purge_redirects(&self) -> Result<usize> {
+		self.primary.purge_redirects().await
+	}
+
+	/// Find all redirects to the `link`, e.g. to remove them when their
+	/// destination is compromised. Returns the IDs of all redirects whose `to`
+	/// link is exactly `link`, sorted by ID. This always uses the primary store
+	/// backend, because some backends build their index on first use.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails. No redirects to
+	/// the `link` existing is not considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		self.write(|store| store.find_by_link(link)).await
 	}
 
 	/// Get a vanity path's ID. Returns the ID of the `to` link corresponding
@@ -197,7 +714,21 @@ impl Store {
 	/// considered an error.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
-		self.store.get_vanity(from).await
+		self.read(|store| store.get_vanity(from.clone())).await
+	}
+
+	/// Get the IDs of multiple vanity paths at once. Returns the IDs
+	/// corresponding to each of the `from` vanity paths, in the same order,
+	/// with `None` for vanity paths which don't exist. Where the backend
+	/// supports it, all IDs are fetched in a single round-trip.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if a link exists or not, or what it is. A link not existing is not
+	/// considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		self.read(|store| store.get_vanities(from)).await
 	}
 
 	/// Set a vanity path for an ID. `from` is the vanity path of the links ID,
@@ -211,7 +742,22 @@ impl Store {
 	/// insofar as that is possible to determine from the backend.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
-		self.store.set_vanity(from, to).await
+		self.write(|store| store.set_vanity(from.clone(), to)).await
+	}
+
+	/// Set multiple vanity paths at once, e.g. when importing many links.
+	/// Works like [`set_vanity`][Self::set_vanity] for each of the `vanities`
+	/// in order, returning the old IDs in the same order. Where the backend
+	/// supports it, all vanity paths are set in a single round-trip.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, all new values were definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, some of the values may have been set already,
+	/// unless the backend sets all of them atomically.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn set_vanities(&self, vanities: &[(Normalized, Id)]) -> Result<Vec<Option<Id>>> {
+		self.write(|store| store.set_vanities(vanities)).await
 	}
 
 	/// Remove a vanity path. `from` is the vanity path to be removed. Returns
@@ -224,7 +770,88 @@ impl Store {
 	/// modified, insofar as that is possible to determine from the backend.
 	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
 	pub async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
-		self.store.rem_vanity(from).await
+		self.write(|store| store.rem_vanity(from.clone())).await
+	}
+
+	/// Get all vanity paths of an ID, e.g. to show or remove them together with
+	/// its redirect. Returns all vanity paths corresponding to the `id`, sorted
+	/// by their string representation. Like
+	/// [`find_by_link`][Self::find_by_link], this always uses the primary store
+	/// backend.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails. The ID not having
+	/// any vanity paths is not considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		self.write(|store| store.get_vanities_of(id)).await
+	}
+
+	/// Get a link's options. Returns the [`LinkOptions`] of the link with the
+	/// ID `id`. Options not existing is not an error, if no options are found,
+	/// `None` is returned.
+	///
+	/// # Error
+	/// An error is only returned if something actually fails; if we don't know
+	/// if options exist or not, or what they are. Options not existing is not
+	/// considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		self.read(|store| store.get_options(id)).await
+	}
+
+	/// Set a link's options. `id` is the ID of the link, while `options` are
+	/// its new options. If the link already has options, they are replaced
+	/// with the new ones, returning the old ones.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, the new value was definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, the value must not have been set / modified,
+	/// insofar as that is possible to determine from the backend.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		self.write(|store| store.set_options(id, options.clone()))
+			.await
+	}
+
+	/// Remove a link's options. `id` is the ID of the link whose options are
+	/// to be removed. Returns the old options or `None` if there were none.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, the new value was definitely removed /
+	/// processed / saved, and will be unavailable on next request.
+	/// If an `Err` is returned, the value must not have been removed /
+	/// modified, insofar as that is possible to determine from the backend.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		self.write(|store| store.rem_options(id)).await
+	}
+
+	/// Enable or disable a link, by setting its `enabled`
+	/// [option][LinkOptions::enabled] (keeping its other options). `id` is the
+	/// ID of the link. Returns whether the link was enabled before, which is
+	/// the case unless it was explicitly disabled.
+	///
+	/// # Storage Guarantees
+	/// If an `Ok` is returned, the new value was definitely set / processed /
+	/// saved, and will be available on next request.
+	/// If an `Err` is returned, the value must not have been set / modified,
+	/// insofar as that is possible to determine from the backend. Concurrent
+	/// changes to the link's other options may be lost.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn set_enabled(&self, id: Id, enabled: bool) -> Result<bool> {
+		let mut options = self.get_options(id).await?.unwrap_or_default();
+		let previous = options.enabled.unwrap_or(true);
+		options.enabled = Some(false).filter(|_| !enabled);
+
+		if options.is_empty() {
+			self.rem_options(id).await?;
+		} else {
+			self.set_options(id, options).await?;
+		}
+
+		Ok(previous)
 	}
 
 	/// Get statistics' values by their description. Returns all matching
@@ -242,7 +869,78 @@ impl Store {
 		&self,
 		description: StatisticDescription,
 	) -> Result<impl Iterator<Item = (Statistic, StatisticValue)>> {
-		Ok(self.store.get_statistics(description).await?.into_iter())
+		Ok(self
+			.read(|store| store.get_statistics(description.clone()))
+			.await?
+			.into_iter())
+	}
+
+	/// Get a page of up to `limit` statistics' values by their description,
+	/// starting after the `cursor`. To get all matching statistics, start with
+	/// a `cursor` of `None`, and then pass the `next` cursor of each page to
+	/// get the next one, until it is `None`. The order of the statistics
+	/// depends on the store backend. Otherwise, this is like
+	/// [`get_statistics`][Self::get_statistics].
+	///
+	/// # Error
+	/// An error is returned if `limit` is 0, or if something fails when it
+	/// should have worked. A statistic not existing or the store not
+	/// supporting statistics is not considered an error.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		if limit == 0 {
+			bail!("the page size limit must not be 0");
+		}
+
+		self.read(|store| store.get_statistics_page(description.clone(), cursor.clone(), limit))
+			.await
+	}
+
+	/// Increment the API usage counter with the `key` by `amount`, returning
+	/// its new value. This is used to track [quotas][crate::ratelimit::Quota]
+	/// of API tokens, and is never retried, because it isn't idempotent.
+	///
+	/// # Error
+	/// An error is returned if usage counters are not supported by the store
+	/// backend, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn incr_usage(
+		&self,
+		key: &str,
+		amount: u64,
+		expires_at: OffsetDateTime,
+	) -> Result<u64> {
+		self.primary.incr_usage(key, amount, expires_at).await
+	}
+
+	/// Increment the click counter of the link with the `id` by `amount`,
+	/// returning its new value. This is used to limit the number of redirects
+	/// of a link (see [`LinkOptions::max_clicks`]), and is backed by the
+	/// backend's [usage counters][Self::incr_usage], so it is atomic and never
+	/// retried.
+	///
+	/// # Error
+	/// An error is returned if usage counters are not supported by the store
+	/// backend, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret, err)]
+	pub async fn incr_clicks(&self, id: Id, amount: u64) -> Result<u64> {
+		let expires_at = OffsetDateTime::now_utc() + CLICKS_RETENTION;
+		self.primary
+			.incr_usage(&format!("clicks:{id}"), amount, expires_at)
+			.await
+	}
+
+	/// Check whether the store backend supports
+	/// [usage counters][Self::incr_usage], which are needed for API token
+	/// quotas and link click limits
+	#[must_use]
+	pub fn supports_usage(&self) -> bool {
+		self.primary.supports_usage()
 	}
 
 	/// Increment multiple statistics' count for the given id and/or vanity
@@ -259,17 +957,83 @@ impl Store {
 		I: IntoIterator<Item = Statistic> + Send + 'static,
 		<I as IntoIterator>::IntoIter: Send,
 	{
-		let store = self.store.clone();
+		let store = self.primary.clone();
+		let watch = self.statistics_watch.clone();
 		spawn(async move {
 			for stat in statistics {
 				match store.incr_statistic(stat.clone()).await {
-					Ok(val) => trace!(?val, ?stat, "statistic incremented"),
+					Ok(val) => {
+						trace!(?val, ?stat, "statistic incremented");
+
+						if watch.receiver_count() > 0 {
+							// This only fails if all watchers are gone in the meantime
+							let _ = watch.send((stat, val));
+						}
+					}
 					Err(err) => debug!(?err, ?stat, "statistic incrementing failed"),
 				}
 			}
 		});
 	}
 
+	/// Watch for statistics matching the `description` being incremented
+	/// through this store (using [`incr_statistics`][Self::incr_statistics]).
+	/// The returned stream yields every incremented statistic along with its
+	/// new value (if the store backend returns it), and ends when this store
+	/// and all its clones are dropped (e.g. when the store is replaced after a
+	/// configuration change).
+	///
+	/// Statistics incremented by other links instances using the same store
+	/// backend are not included. If statistics are incremented faster than the
+	/// stream is consumed, the oldest of them are skipped.
+	pub fn watch_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> impl Stream<Item = (Statistic, Option<StatisticValue>)> + Send + 'static {
+		stream::unfold(
+			(self.statistics_watch.subscribe(), description),
+			|(mut receiver, description)| async move {
+				loop {
+					match receiver.recv().await {
+						Ok((stat, val)) if description.matches(&stat) => {
+							break Some(((stat, val), (receiver, description)))
+						}
+						Ok(_) => (),
+						Err(RecvError::Lagged(skipped)) => {
+							debug!(skipped, "statistics watcher lagged behind");
+						}
+						Err(RecvError::Closed) => break None,
+					}
+				}
+			},
+		)
+	}
+
+	/// Watch for redirects being set, removed, or restored through this store.
+	/// The returned stream yields every [change][RedirectChange] as it
+	/// happens, and ends when this store and all its clones are dropped (e.g.
+	/// when the store is replaced after a configuration change).
+	///
+	/// Changes made by other links instances using the same store backend are
+	/// not included. If redirects are changed faster than the stream is
+	/// consumed, the stream ends early, since changes would be missed
+	/// otherwise.
+	pub fn watch_redirects(&self) -> impl Stream<Item = RedirectChange> + Send + 'static {
+		stream::unfold(
+			self.redirects_watch.subscribe(),
+			|mut receiver| async move {
+				match receiver.recv().await {
+					Ok(change) => Some((change, receiver)),
+					Err(RecvError::Lagged(skipped)) => {
+						warn!(skipped, "redirect watcher lagged behind, ending watch");
+						None
+					}
+					Err(RecvError::Closed) => None,
+				}
+			},
+		)
+	}
+
 	/// Remove statistics by their description. Deletes all
 	/// [statistics][`Statistic`] that match the provided
 	/// [description][`StatisticDescription`] and returns their values before
@@ -286,7 +1050,164 @@ impl Store {
 		&self,
 		description: StatisticDescription,
 	) -> Result<impl Iterator<Item = (Statistic, StatisticValue)>> {
-		Ok(self.store.rem_statistics(description).await?.into_iter())
+		Ok(self
+			.write(|store| store.rem_statistics(description.clone()))
+			.await?
+			.into_iter())
+	}
+
+	/// List up to `limit` redirects, sorted by the string representation of
+	/// their IDs, starting after the `cursor`. To list all redirects, start
+	/// with a `cursor` of `None`, and then pass the `next` cursor of each page
+	/// to get the next one, until it is `None`.
+	///
+	/// # Error
+	/// An error is returned if `limit` is 0, if the store backend doesn't
+	/// support listing, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		if limit == 0 {
+			bail!("the page size limit must not be 0");
+		}
+
+		self.read(|store| store.list_redirects(cursor, limit)).await
+	}
+
+	/// List up to `limit` vanity paths, sorted by their string
+	/// representation, starting after the `cursor`. To list all vanity paths,
+	/// start with a `cursor` of `None`, and then pass the `next` cursor of each
+	/// page to get the next one, until it is `None`.
+	///
+	/// # Error
+	/// An error is returned if `limit` is 0, if the store backend doesn't
+	/// support listing, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		if limit == 0 {
+			bail!("the page size limit must not be 0");
+		}
+
+		self.read(|store| store.list_vanities(cursor.clone(), limit))
+			.await
+	}
+
+	/// Search for up to `limit` vanity paths containing the `query`, or only
+	/// those starting with it if `prefix` is `true`, sorted by their string
+	/// representation.
+	///
+	/// Vanity paths are scanned using [`list_vanities`][Self::list_vanities].
+	/// Prefix searches start listing at the `query` and stop at the first
+	/// vanity path not starting with it, but substring searches may have to
+	/// scan all vanity paths. At most 100 000 vanity paths are scanned, so
+	/// substring searches in larger stores may not find all matches.
+	///
+	/// # Error
+	/// An error is returned if `limit` is 0, if the store backend doesn't
+	/// support listing, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn search_vanities(
+		&self,
+		query: &Normalized,
+		prefix: bool,
+		limit: usize,
+	) -> Result<Vec<(Normalized, Id)>> {
+		if limit == 0 {
+			bail!("the search result limit must not be 0");
+		}
+
+		let mut found = Vec::new();
+
+		// Listing starts after the cursor, so an exact match is checked first
+		let mut cursor = if prefix {
+			if let Some(id) = self.get_vanity(query.clone()).await? {
+				found.push((query.clone(), id));
+			}
+
+			Some(query.clone())
+		} else {
+			None
+		};
+
+		let mut scanned = 0;
+		while found.len() < limit {
+			if scanned >= SEARCH_SCAN_LIMIT {
+				debug!(%query, "Vanity path search stopped after the scan limit");
+				break;
+			}
+
+			let page_size = SEARCH_PAGE_SIZE.min(SEARCH_SCAN_LIMIT - scanned);
+			let page = self.list_vanities(cursor, page_size).await?;
+			scanned += page.items.len();
+
+			for (vanity, id) in page.items {
+				if prefix && !vanity.as_str().starts_with(query.as_str()) {
+					return Ok(found);
+				}
+
+				if vanity.as_str().contains(query.as_str()) {
+					found.push((vanity, id));
+
+					if found.len() == limit {
+						return Ok(found);
+					}
+				}
+			}
+
+			cursor = page.next;
+			if cursor.is_none() {
+				break;
+			}
+		}
+
+		Ok(found)
+	}
+
+	/// Check whether the primary store backend is reachable and working, and
+	/// how long it takes to respond. Read replicas are not checked, because
+	/// reads fall back to the primary if a replica fails.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), ret)]
+	pub async fn health(&self) -> Health {
+		self.primary.health().await
+	}
+
+	/// Export all data in the store. Returns a stream of all redirects, vanity
+	/// paths, link options, and statistics in the primary store backend, e.g.
+	/// for backups or migrating between store backends. The exported data is
+	/// not a consistent snapshot, changes made while the stream is being
+	/// consumed may or may not be included.
+	///
+	/// # Error
+	/// An error is returned if the store backend doesn't support exporting, or
+	/// if something fails when it should have worked. Errors may be returned
+	/// both by this function and by the returned stream.
+	#[instrument(level = "debug", skip(self), fields(name = self.backend_name()), err)]
+	pub async fn export_all(&self) -> Result<ExportStream<'_>> {
+		self.primary.export_all().await
+	}
+
+	/// Import data into the store. Writes all redirects, vanity paths, and link
+	/// options from the `items` (e.g. from another store's
+	/// [`export_all`][Self::export_all]) to the primary store backend, with
+	/// conflicts with existing data resolved according to the `policy`.
+	/// Returns the number of imported and skipped items. Statistics may be
+	/// skipped, depending on the store backend. The import is not atomic, if
+	/// an error is returned, some items may have already been imported.
+	///
+	/// # Error
+	/// An error is returned if an item conflicts with existing data and the
+	/// `policy` is [`ImportPolicy::Error`], if the `items` stream returns an
+	/// error, or if something fails when it should have worked.
+	#[instrument(level = "debug", skip(self, items), fields(name = self.backend_name()), ret, err)]
+	pub async fn import(
+		&self,
+		items: impl Stream<Item = Result<ExportItem>> + Send,
+		policy: ImportPolicy,
+	) -> Result<ImportSummary> {
+		self.primary.import(Box::pin(items), policy).await
 	}
 }
 
@@ -294,7 +1215,371 @@ impl Store {
 mod store_tests {
 	use std::str::FromStr;
 
+	use anyhow::anyhow;
+	use async_trait::async_trait;
+	use futures_util::{pin_mut, StreamExt};
+
 	use super::*;
+	use crate::stats::StatisticType;
+
+	/// A store backend where every operation fails
+	#[derive(Debug)]
+	struct Failing;
+
+	#[async_trait]
+	impl StoreBackend for Failing {
+		fn store_type() -> BackendType {
+			BackendType::Memory
+		}
+
+		fn get_store_type(&self) -> BackendType {
+			BackendType::Memory
+		}
+
+		async fn new(_: &HashMap<String, String>) -> Result<Self> {
+			Ok(Self)
+		}
+
+		async fn get_redirect(&self, _: Id) -> Result<Option<Link>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn set_redirect(&self, _: Id, _: Link) -> Result<Option<Link>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn rem_redirect(&self, _: Id) -> Result<Option<Link>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn get_vanity(&self, _: Normalized) -> Result<Option<Id>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn set_vanity(&self, _: Normalized, _: Id) -> Result<Option<Id>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn rem_vanity(&self, _: Normalized) -> Result<Option<Id>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn get_options(&self, _: Id) -> Result<Option<LinkOptions>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn set_options(&self, _: Id, _: LinkOptions) -> Result<Option<LinkOptions>> {
+			Err(anyhow!("failing store"))
+		}
+
+		async fn rem_options(&self, _: Id) -> Result<Option<LinkOptions>> {
+			Err(anyhow!("failing store"))
+		}
+	}
+
+	#[tokio::test]
+	async fn replicas() {
+		let id = Id::from([1, 1, 2, 3, 5]);
+		let link = Link::new("https://example.com/replica").unwrap();
+		let vanity = Normalized::new("replica");
+
+		let primary = Arc::new(Memory::new(&HashMap::new()).await.unwrap());
+		let replica = Arc::new(Memory::new(&HashMap::new()).await.unwrap());
+		replica.set_redirect(id, link.clone()).await.unwrap();
+		replica.set_vanity(vanity.clone(), id).await.unwrap();
+
+		let store = Store::with_replicas(primary.clone(), vec![replica.clone(), replica]);
+
+		// Reads go to the replicas
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link.clone()));
+		assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), Some(id));
+		assert_eq!(primary.get_redirect(id).await.unwrap(), None);
+
+		// Writes go to the primary
+		store.rem_redirect(id).await.unwrap();
+		store
+			.set_vanity(vanity.clone(), Id::from([0, 0, 0, 0, 1]))
+			.await
+			.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), Some(id));
+		assert_eq!(
+			primary.get_vanity(vanity).await.unwrap(),
+			Some(Id::from([0, 0, 0, 0, 1]))
+		);
+	}
+
+	#[tokio::test]
+	async fn replica_fallback() {
+		let id = Id::from([8, 13, 21, 34, 55]);
+		let link = Link::new("https://example.com/primary").unwrap();
+
+		let primary = Arc::new(Memory::new(&HashMap::new()).await.unwrap());
+		let store = Store::with_replicas(primary, vec![Arc::new(Failing)]);
+
+		store.set_redirect(id, link.clone()).await.unwrap();
+
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		assert_eq!(
+			store.get_vanity(Normalized::new("nope")).await.unwrap(),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn health() {
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+		let health = store.health().await;
+		assert!(health.healthy);
+		assert_eq!(health.error, None);
+
+		let store = Store::with_replicas(Arc::new(Failing), Vec::new());
+		let health = store.health().await;
+		assert!(!health.healthy);
+		assert_eq!(health.error.as_deref(), Some("failing store"));
+	}
+
+	#[tokio::test]
+	async fn retry() {
+		let id = Id::from([3, 5, 7, 11, 13]);
+		let config = HashMap::from([
+			("retries".to_string(), "2".to_string()),
+			("retry_backoff".to_string(), "1".to_string()),
+			("breaker_threshold".to_string(), "1".to_string()),
+		]);
+		let store = Store {
+			retry: Arc::new(Retry::from_config(&config).unwrap()),
+			..Store::with_replicas(Arc::new(Failing), Vec::new())
+		};
+
+		let err = store.get_redirect(id).await.unwrap_err();
+		assert!(!err.to_string().contains("circuit breaker"));
+		let err = store.get_redirect(id).await.unwrap_err();
+		assert!(err.to_string().contains("circuit breaker"));
+	}
+
+	#[tokio::test]
+	async fn tombstone_retention() {
+		let id = Id::from([2, 3, 5, 7, 11]);
+		let link = Link::new("https://example.com/tombstone").unwrap();
+
+		// Soft deletion is disabled by default
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+		store.set_redirect(id, link.clone()).await.unwrap();
+		store.rem_redirect_soft(id).await.unwrap();
+		assert_eq!(store.restore_redirect(id).await.unwrap(), None);
+
+		let config = HashMap::from([("tombstone_retention".to_string(), "60".to_string())]);
+		let store = Store::new("memory".parse().unwrap(), &config)
+			.await
+			.unwrap();
+		store.set_redirect(id, link.clone()).await.unwrap();
+		store.rem_redirect_soft(id).await.unwrap();
+		assert_eq!(store.restore_redirect(id).await.unwrap(), Some(link));
+
+		let config = HashMap::from([("tombstone_retention".to_string(), "soon".to_string())]);
+		assert!(Store::new("memory".parse().unwrap(), &config)
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn watch_redirects() {
+		let id = Id::from([5, 8, 13, 21, 34]);
+		let old = Link::new("https://example.com/old").unwrap();
+		let new = Link::new("https://example.com/new").unwrap();
+
+		let config = HashMap::from([("tombstone_retention".to_string(), "60".to_string())]);
+		let store = Store::new("memory".parse().unwrap(), &config)
+			.await
+			.unwrap();
+		let changes = store.watch_redirects();
+		pin_mut!(changes);
+
+		store.set_redirect(id, old.clone()).await.unwrap();
+		store.set_redirects(&[(id, new.clone())]).await.unwrap();
+		store.rem_redirect(Id::from([0, 0, 0, 0, 0])).await.unwrap();
+		store.rem_redirect_soft(id).await.unwrap();
+		store.restore_redirect(id).await.unwrap();
+		drop(store);
+
+		let change = |old: Option<&Link>, new: Option<&Link>| RedirectChange {
+			id,
+			old: old.cloned(),
+			new: new.cloned(),
+		};
+
+		assert_eq!(changes.collect::<Vec<_>>().await, [
+			change(None, Some(&old)),
+			change(Some(&old), Some(&new)),
+			change(Some(&new), None),
+			change(None, Some(&new)),
+		]);
+	}
+
+	#[tokio::test]
+	async fn search_vanities() {
+		let id = Id::from([1, 2, 3, 5, 8]);
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		for vanity in [
+			"conf",
+			"conf-2023-talk",
+			"conf-2024",
+			"conf-2024-slides",
+			"my-conf",
+		] {
+			store.set_vanity(Normalized::new(vanity), id).await.unwrap();
+		}
+
+		let search = |query: &str, prefix: bool, limit: usize| {
+			let store = store.clone();
+			let query = Normalized::new(query);
+
+			async move {
+				store
+					.search_vanities(&query, prefix, limit)
+					.await
+					.unwrap()
+					.into_iter()
+					.map(|(vanity, _)| vanity.into_string())
+					.collect::<Vec<_>>()
+			}
+		};
+
+		assert_eq!(search("conf-2024", true, 10).await, [
+			"conf-2024",
+			"conf-2024-slides"
+		]);
+		assert_eq!(search("conf", true, 2).await, ["conf", "conf-2023-talk"]);
+		assert_eq!(search("conf", false, 10).await, [
+			"conf",
+			"conf-2023-talk",
+			"conf-2024",
+			"conf-2024-slides",
+			"my-conf"
+		]);
+		assert_eq!(search("-2024-", false, 10).await, ["conf-2024-slides"]);
+		assert_eq!(search("CONF-2023", false, 10).await, ["conf-2023-talk"]);
+		assert!(search("missing", true, 10).await.is_empty());
+		assert!(store
+			.search_vanities(&Normalized::new("conf"), true, 0)
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn search_vanities_scan_limit() {
+		let id = Id::from([1, 3, 5, 7, 9]);
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		for i in 0..SEARCH_SCAN_LIMIT {
+			store
+				.set_vanity(Normalized::new(&format!("scan-{i:06}")), id)
+				.await
+				.unwrap();
+		}
+		store
+			.set_vanity(Normalized::new("zzz-match"), id)
+			.await
+			.unwrap();
+
+		// The match is sorted after the scan limit, so only a prefix search
+		// (which starts scanning at the query) finds it
+		assert!(store
+			.search_vanities(&Normalized::new("match"), false, 10)
+			.await
+			.unwrap()
+			.is_empty());
+		assert_eq!(
+			store
+				.search_vanities(&Normalized::new("zzz"), true, 10)
+				.await
+				.unwrap(),
+			[(Normalized::new("zzz-match"), id)]
+		);
+	}
+
+	#[tokio::test]
+	async fn get_statistics_page() {
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		for data in ["a", "b", "c", "d", "e"] {
+			let stat = Statistic::new(Normalized::new("page"), StatisticType::HostRequest, data);
+			store.primary.incr_statistic(stat).await.unwrap();
+		}
+
+		let description = StatisticDescription {
+			link: Some(Normalized::new("page").into()),
+			..Default::default()
+		};
+
+		let mut listed = Vec::new();
+		let mut cursor = None;
+		loop {
+			let page = store
+				.get_statistics_page(description.clone(), cursor, 2)
+				.await
+				.unwrap();
+			assert!(page.items.len() <= 2);
+			listed.extend(
+				page.items
+					.into_iter()
+					.map(|(stat, _)| stat.data.to_string()),
+			);
+
+			cursor = page.next;
+			if cursor.is_none() {
+				break;
+			}
+		}
+
+		assert_eq!(listed, ["a", "b", "c", "d", "e"]);
+		assert!(store
+			.get_statistics_page(description, None, 0)
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn namespaces() {
+		let id = Id::from([3, 5, 7, 11, 13]);
+		let link = Link::new("https://example.com/namespace").unwrap();
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+		assert_eq!(store.namespace(), None);
+
+		let tenant = store.with_namespace("tenant-1").await.unwrap();
+		assert_eq!(tenant.namespace(), Some("tenant-1"));
+		tenant.set_redirect(id, link.clone()).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), None);
+
+		// Namespaced stores are reused, and can't be nested
+		let again = store.with_namespace("tenant-1").await.unwrap();
+		assert_eq!(again.get_redirect(id).await.unwrap(), Some(link.clone()));
+		let same = tenant.with_namespace("tenant-1").await.unwrap();
+		assert_eq!(same.get_redirect(id).await.unwrap(), Some(link));
+		assert!(tenant.with_namespace("tenant-2").await.is_err());
+
+		assert!(store.with_namespace("").await.is_err());
+		assert!(store.with_namespace("no/slashes").await.is_err());
+		assert!(store.with_namespace(&"a".repeat(65)).await.is_err());
+		assert!(Store::with_replicas(Arc::new(Failing), Vec::new())
+			.with_namespace("tenant-1")
+			.await
+			.is_err());
+	}
 
 	#[tokio::test]
 	async fn current() {
@@ -429,6 +1714,29 @@ mod store_tests {
 		assert_eq!(store.get_redirect(id).await.unwrap(), None);
 	}
 
+	#[tokio::test]
+	async fn set_enabled() {
+		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
+			.await
+			.unwrap();
+
+		let id = Id::from([0x14, 0x24, 0x34, 0x44, 0x54]);
+		let link = Link::new("https://example.com/test").unwrap();
+
+		store.set_redirect(id, link.clone()).await.unwrap();
+
+		assert!(store.set_enabled(id, false).await.unwrap());
+		assert!(!store.set_enabled(id, false).await.unwrap());
+		assert_eq!(
+			store.get_options(id).await.unwrap().unwrap().enabled,
+			Some(false)
+		);
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+
+		assert!(!store.set_enabled(id, true).await.unwrap());
+		assert_eq!(store.get_options(id).await.unwrap(), None);
+	}
+
 	#[tokio::test]
 	async fn get_vanity() {
 		let store = Store::new("memory".parse().unwrap(), &HashMap::new())
@@ -0,0 +1,1478 @@
+//! An embedded [`StoreBackend`] implementation using [redb], storing all data
+//! in a single database file on the local filesystem. This store backend is
+//! useful for persistent single-server deployments, where running a separate
+//! database server (like Redis) is not worth it.
+//!
+//! The database is stored in the configured directory, in a file called
+//! `links.redb`. Only one links instance can use a database at the same time.
+//!
+//! Within the database, data is stored in the following tables:
+//! - `redirects`, mapping IDs to URLs
+//! - `redirect_expiry`, mapping IDs of expiring redirects to their expiry time
+//!   (as a unix timestamp in milliseconds)
+//! - `vanity`, mapping vanity paths to IDs
+//! - `options`, mapping IDs to link options (as json)
+//! - `statistics`, mapping statistics (as json) to their values
+//! - `usage`, mapping API usage counter keys to their values and the time after
+//!   which they are no longer needed (as a unix timestamp in milliseconds)
+//!
+//! Expired redirects are removed lazily, when they are next read.
+//!
+//! Each namespace has its own database, stored in the `namespaces/[namespace]`
+//! subdirectory of the configured directory.
+//!
+//! If encryption is configured, URLs, link options, and statistics are
+//! encrypted before being written to the database (with URLs and statistics
+//! used as keys encrypted deterministically), while IDs and vanity paths are
+//! not. Encrypted values are authenticated together with their table and key,
+//! so they can't be moved to a different one. A check value in the `metadata`
+//! table makes sure that the database is always opened with the same key.
+//!
+//! [redb]: https://www.redb.org/
+
+use std::{
+	collections::HashMap,
+	fs,
+	ops::Bound,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use ::redb::{
+	Database, Durability, MultimapTableDefinition, ReadableTable, ReadableTableMetadata,
+	TableDefinition, TableHandle, WriteTransaction,
+};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use futures_util::stream;
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+use tracing::instrument;
+
+use crate::{
+	options::LinkOptions,
+	stats::{Statistic, StatisticDescription, StatisticValue},
+	store::{
+		backend::{ExportItem, ExportStream, NonTransient, Page},
+		encryption::Cipher,
+		BackendType, StoreBackend,
+	},
+};
+
+/// Redirects, from IDs to URLs
+const REDIRECTS: TableDefinition<&str, &str> = TableDefinition::new("redirects");
+
+/// Redirect expiry times, from IDs to unix timestamps in milliseconds
+const REDIRECT_EXPIRY: TableDefinition<&str, i64> = TableDefinition::new("redirect_expiry");
+
+/// An index of redirects by their links, from URLs to the IDs redirecting
+/// there
+const REDIRECTS_BY_LINK: MultimapTableDefinition<&str, &str> =
+	MultimapTableDefinition::new("redirects_by_link");
+
+/// Tombstones of soft-deleted redirects, from IDs to URLs and the unix
+/// timestamps (in milliseconds) until which they are kept
+const TOMBSTONES: TableDefinition<&str, (&str, i64)> = TableDefinition::new("tombstones");
+
+/// Vanity paths, from normalized vanity paths to IDs
+const VANITY: TableDefinition<&str, &str> = TableDefinition::new("vanity");
+
+/// An index of vanity paths by their IDs, from IDs to normalized vanity paths
+const VANITIES_BY_ID: MultimapTableDefinition<&str, &str> =
+	MultimapTableDefinition::new("vanities_by_id");
+
+/// Link options, from IDs to json-serialized [`LinkOptions`]
+const OPTIONS: TableDefinition<&str, &str> = TableDefinition::new("options");
+
+/// Statistics, from json-serialized [`Statistic`]s to their values
+const STATISTICS: TableDefinition<&str, u64> = TableDefinition::new("statistics");
+
+/// API usage counters, from their keys to their values and the unix
+/// timestamps (in milliseconds) after which they are no longer needed
+const USAGE: TableDefinition<&str, (u64, i64)> = TableDefinition::new("usage");
+
+/// Database metadata, currently only used for the encryption check value
+const METADATA: TableDefinition<&str, &str> = TableDefinition::new("metadata");
+
+/// The key of the encryption check value in the metadata table
+const ENCRYPTION_CHECK: &str = "encryption_check";
+
+/// The plaintext of the encryption check value
+const ENCRYPTION_CHECK_VALUE: &str = "links";
+
+/// The name of the database file inside of the configured directory
+const DATABASE_FILE: &str = "links.redb";
+
+/// An embedded `StoreBackend` implementation using redb. A good option for
+/// single-server deployments without a separate database.
+///
+/// # Configuration
+///
+/// **Store backend name:**
+/// `redb`
+///
+/// **Configuration:**
+/// - `directory`: The directory in which the database file (`links.redb`) is
+///   stored. The directory is created if it doesn't exist yet. **Required**.
+/// - `sync_statistics`: Wait for statistics to be written to disk before
+///   continuing. If this is disabled, the last few statistics may be lost if
+///   the server crashes, but collecting statistics is much faster. Redirects,
+///   vanity paths, and options are always written to disk immediately. *`true`
+///   / `false`*. **Default `false`**.
+/// - `encryption_key_file` / `encryption_key_env`: Encrypt stored URLs, link
+///   options, and statistics with the key from this file or environment
+///   variable, see [`Cipher`] for details. Encryption can not be enabled or
+///   disabled for a database which already contains data. **Default none
+///   (unencrypted)**.
+#[derive(Debug)]
+pub struct Store {
+	db: Arc<Database>,
+	directory: PathBuf,
+	sync_statistics: bool,
+	encryption: Encryption,
+	namespaces: Arc<Mutex<HashMap<String, Arc<Database>>>>,
+}
+
+/// Optional encryption of values written to the database
+#[derive(Debug, Clone, Default)]
+struct Encryption(Option<Arc<Cipher>>);
+
+impl Encryption {
+	/// Encrypt the `value` stored at the `key` of the `table`, if encryption is
+	/// enabled
+	fn encrypt(&self, table: impl TableHandle, key: &str, value: &str) -> Result<String> {
+		self.0.as_ref().map_or_else(
+			|| Ok(value.to_string()),
+			|c| c.encrypt(value.as_bytes(), aad(&table, key).as_bytes()),
+		)
+	}
+
+	/// Deterministically encrypt the `key`, if encryption is enabled, so that
+	/// it can be looked up
+	fn encrypt_key(&self, key: &str) -> Result<String> {
+		self.0.as_ref().map_or_else(
+			|| Ok(key.to_string()),
+			|c| c.encrypt_deterministic(key.as_bytes()),
+		)
+	}
+
+	/// Decrypt the `value` stored at the `key` of the `table`, if encryption is
+	/// enabled
+	fn decrypt(&self, table: impl TableHandle, key: &str, value: &str) -> Result<String> {
+		self.0.as_ref().map_or_else(
+			|| Ok(value.to_string()),
+			|c| c.decrypt_str(value, aad(&table, key).as_bytes()),
+		)
+	}
+
+	/// Decrypt the deterministically encrypted `key`, if encryption is enabled
+	fn decrypt_key(&self, key: &str) -> Result<String> {
+		self.0
+			.as_ref()
+			.map_or_else(|| Ok(key.to_string()), |c| c.decrypt_str(key, &[]))
+	}
+
+	/// Decrypt the `link` stored at the `key` of the `table`, if encryption is
+	/// enabled
+	fn decrypt_link(&self, table: impl TableHandle, key: &str, link: &str) -> Result<Link> {
+		Ok(Link::new_unchecked(self.decrypt(table, key, link)?))
+	}
+}
+
+/// The additional authenticated data of an encrypted value stored at the `key`
+/// of the `table`
+fn aad(table: &impl TableHandle, key: &str) -> String {
+	format!("{}/{key}", table.name())
+}
+
+impl Store {
+	/// Run the blocking database operation `op` on a thread where blocking is
+	/// acceptable
+	async fn blocking<T: Send + 'static>(
+		&self,
+		op: impl FnOnce(&Database) -> Result<T> + Send + 'static,
+	) -> Result<T> {
+		let db = Arc::clone(&self.db);
+		spawn_blocking(move || op(&db)).await?
+	}
+
+	/// Get the value of `key` in the string `table`
+	/// Open (or create) the database in the `directory`, making sure that it
+	/// is set up correctly and used with the right encryption key. This
+	/// blocks, so should be run using `spawn_blocking`.
+	fn open(directory: &Path, enc: &Encryption) -> Result<Database> {
+		fs::create_dir_all(directory)?;
+		let db = Database::create(directory.join(DATABASE_FILE))?;
+
+		// Create all tables, so that they can always be opened for reading
+		let txn = db.begin_write()?;
+		txn.open_table(REDIRECTS)?;
+		txn.open_table(REDIRECT_EXPIRY)?;
+		txn.open_table(VANITY)?;
+		txn.open_table(OPTIONS)?;
+		txn.open_table(STATISTICS)?;
+		txn.open_table(TOMBSTONES)?;
+		txn.open_table(USAGE)?;
+
+		// Make sure that the database is used with the same encryption key
+		{
+			let mut metadata = txn.open_table(METADATA)?;
+			let check = metadata
+				.get(ENCRYPTION_CHECK)?
+				.map(|v| v.value().to_string());
+
+			match (check, &enc.0) {
+				(Some(check), Some(cipher)) => {
+					let check = cipher.decrypt(&check, aad(&METADATA, ENCRYPTION_CHECK).as_bytes());
+					if check.ok().as_deref() != Some(ENCRYPTION_CHECK_VALUE.as_bytes()) {
+						bail!("the database is encrypted with a different key");
+					}
+				}
+				(Some(_), None) => bail!("the database is encrypted, but no key is configured"),
+				(None, Some(cipher)) => {
+					let has_data = !txn.open_table(REDIRECTS)?.is_empty()?
+						|| !txn.open_table(OPTIONS)?.is_empty()?
+						|| !txn.open_table(STATISTICS)?.is_empty()?
+						|| !txn.open_table(TOMBSTONES)?.is_empty()?;
+
+					if has_data {
+						bail!("encryption can not be enabled for a database with existing data");
+					}
+
+					let check = cipher.encrypt(
+						ENCRYPTION_CHECK_VALUE.as_bytes(),
+						aad(&METADATA, ENCRYPTION_CHECK).as_bytes(),
+					)?;
+					metadata.insert(ENCRYPTION_CHECK, check.as_str())?;
+				}
+				(None, None) => (),
+			}
+		}
+
+		// Index redirects and vanity paths created before the indexes existed
+		{
+			let redirects = txn.open_table(REDIRECTS)?;
+			let mut by_link = txn.open_multimap_table(REDIRECTS_BY_LINK)?;
+
+			if by_link.is_empty()? && !redirects.is_empty()? {
+				for redirect in redirects.iter()? {
+					let (id, link) = redirect?;
+					let link =
+						enc.encrypt_key(&enc.decrypt(REDIRECTS, id.value(), link.value())?)?;
+					by_link.insert(link.as_str(), id.value())?;
+				}
+			}
+
+			let vanities = txn.open_table(VANITY)?;
+			let mut by_id = txn.open_multimap_table(VANITIES_BY_ID)?;
+
+			if by_id.is_empty()? && !vanities.is_empty()? {
+				for vanity in vanities.iter()? {
+					let (vanity, id) = vanity?;
+					by_id.insert(id.value(), vanity.value())?;
+				}
+			}
+		}
+
+		txn.commit()?;
+
+		Ok(db)
+	}
+
+	async fn get(
+		&self,
+		table: TableDefinition<'static, &'static str, &'static str>,
+		key: String,
+	) -> Result<Option<String>> {
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let table = txn.open_table(table)?;
+			let value = table.get(key.as_str())?.map(|v| v.value().to_string());
+			Ok(value)
+		})
+		.await
+	}
+
+	/// Set `key` to `value` in the string `table`, returning the old value
+	async fn set(
+		&self,
+		table: TableDefinition<'static, &'static str, &'static str>,
+		key: String,
+		value: String,
+	) -> Result<Option<String>> {
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = txn
+				.open_table(table)?
+				.insert(key.as_str(), value.as_str())?
+				.map(|v| v.value().to_string());
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	/// Decrypt and deserialize the link options of the `id` stored in the
+	/// database
+	fn decode_options(&self, id: &str, options: &str) -> Result<LinkOptions> {
+		Ok(serde_json::from_str(
+			&self.encryption.decrypt(OPTIONS, id, options)?,
+		)?)
+	}
+
+	/// Set the redirect from `from` to `to`, expiring at `expires_at` (as a
+	/// unix timestamp in milliseconds) if provided, returning the old value
+	async fn set_redirect_expiring(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: Option<i64>,
+	) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = insert_redirect(&txn, &enc, &from, &to, expires_at)?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	/// Remove `key` from the string `table`, returning the old value
+	async fn rem(
+		&self,
+		table: TableDefinition<'static, &'static str, &'static str>,
+		key: String,
+	) -> Result<Option<String>> {
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = txn
+				.open_table(table)?
+				.remove(key.as_str())?
+				.map(|v| v.value().to_string());
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+}
+
+/// Set the redirect from `from` to `to` in the `txn`, expiring at `expires_at`
+/// (as a unix timestamp in milliseconds) if provided, returning the old value
+fn insert_redirect(
+	txn: &WriteTransaction,
+	enc: &Encryption,
+	from: &str,
+	to: &Link,
+	expires_at: Option<i64>,
+) -> Result<Option<Link>> {
+	let mut expiry = txn.open_table(REDIRECT_EXPIRY)?;
+	let old_expiry = match expires_at {
+		Some(expires_at) => expiry.insert(from, expires_at)?,
+		None => expiry.remove(from)?,
+	}
+	.map(|v| v.value());
+
+	let old = txn
+		.open_table(REDIRECTS)?
+		.insert(from, enc.encrypt(REDIRECTS, from, to.as_str())?.as_str())?
+		.map(|v| enc.decrypt_link(REDIRECTS, from, v.value()))
+		.transpose()?;
+
+	let mut by_link = txn.open_multimap_table(REDIRECTS_BY_LINK)?;
+	if let Some(ref old) = old {
+		by_link.remove(enc.encrypt_key(old.as_str())?.as_str(), from)?;
+	}
+	by_link.insert(enc.encrypt_key(to.as_str())?.as_str(), from)?;
+
+	Ok(old.filter(|_| !is_expired(old_expiry)))
+}
+
+/// Remove the redirect from `from` in the `txn`, returning the old value
+fn remove_redirect(txn: &WriteTransaction, enc: &Encryption, from: &str) -> Result<Option<Link>> {
+	let expiry = txn
+		.open_table(REDIRECT_EXPIRY)?
+		.remove(from)?
+		.map(|v| v.value());
+
+	let old = txn
+		.open_table(REDIRECTS)?
+		.remove(from)?
+		.map(|v| enc.decrypt_link(REDIRECTS, from, v.value()))
+		.transpose()?;
+
+	if let Some(ref old) = old {
+		txn.open_multimap_table(REDIRECTS_BY_LINK)?
+			.remove(enc.encrypt_key(old.as_str())?.as_str(), from)?;
+	}
+
+	Ok(old.filter(|_| !is_expired(expiry)))
+}
+
+/// Set the vanity path `from` to the ID `to` in the `txn`, returning the old ID
+fn insert_vanity(txn: &WriteTransaction, from: &Normalized, to: Id) -> Result<Option<Id>> {
+	let to = to.to_string();
+	let old = txn
+		.open_table(VANITY)?
+		.insert(from.as_str(), to.as_str())?
+		.map(|v| v.value().to_string());
+
+	let mut by_id = txn.open_multimap_table(VANITIES_BY_ID)?;
+	if let Some(ref old) = old {
+		by_id.remove(old.as_str(), from.as_str())?;
+	}
+	by_id.insert(to.as_str(), from.as_str())?;
+
+	Ok(old.map(Id::try_from).transpose()?)
+}
+
+/// Check whether a redirect with the `expiry` (as a unix timestamp in
+/// milliseconds) has expired
+fn is_expired(expiry: Option<i64>) -> bool {
+	expiry.is_some_and(|expiry| {
+		i128::from(expiry) <= OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000
+	})
+}
+
+#[async_trait]
+impl StoreBackend for Store {
+	fn store_type() -> BackendType
+	where
+		Self: Sized,
+	{
+		BackendType::Redb
+	}
+
+	fn get_store_type(&self) -> BackendType {
+		BackendType::Redb
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn new(config: &HashMap<String, String>) -> Result<Self> {
+		let directory = PathBuf::from(
+			config
+				.get("directory")
+				.ok_or_else(|| anyhow!("missing directory option"))?,
+		);
+		let sync_statistics = config
+			.get("sync_statistics")
+			.map_or(Ok(false), |s| s.parse())?;
+		let encryption = Encryption(Cipher::from_config(config)?.map(Arc::new));
+		let enc = encryption.clone();
+
+		let db = spawn_blocking({
+			let directory = directory.clone();
+			move || Self::open(&directory, &enc)
+		})
+		.await??;
+
+		Ok(Self {
+			db: Arc::new(db),
+			directory,
+			sync_statistics,
+			encryption,
+			namespaces: Arc::default(),
+		})
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let link = txn
+				.open_table(REDIRECTS)?
+				.get(from.as_str())?
+				.map(|v| enc.decrypt_link(REDIRECTS, &from, v.value()))
+				.transpose()?;
+			let expiry = txn
+				.open_table(REDIRECT_EXPIRY)?
+				.get(from.as_str())?
+				.map(|v| v.value());
+			drop(txn);
+
+			if !is_expired(expiry) {
+				return Ok(link);
+			}
+
+			// Lazily remove the expired redirect, unless it was replaced
+			let txn = db.begin_write()?;
+			{
+				let mut expiry = txn.open_table(REDIRECT_EXPIRY)?;
+				let current = expiry.get(from.as_str())?.map(|v| v.value());
+
+				if is_expired(current) {
+					expiry.remove(from.as_str())?;
+
+					if let Some(old) = txn.open_table(REDIRECTS)?.remove(from.as_str())? {
+						let old =
+							enc.encrypt_key(&enc.decrypt(REDIRECTS, &from, old.value())?)?;
+						txn.open_multimap_table(REDIRECTS_BY_LINK)?
+							.remove(old.as_str(), from.as_str())?;
+					}
+				}
+			}
+			txn.commit()?;
+
+			Ok(None)
+		})
+		.await
+	}
+
+	/// Gets all redirects in one read transaction. Expired redirects are not
+	/// removed here, only when they are next read individually.
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let from = from.iter().map(ToString::to_string).collect::<Vec<_>>();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let redirects = txn.open_table(REDIRECTS)?;
+			let expiry = txn.open_table(REDIRECT_EXPIRY)?;
+
+			from.iter()
+				.map(|from| {
+					if is_expired(expiry.get(from.as_str())?.map(|v| v.value())) {
+						return Ok(None);
+					}
+
+					redirects
+						.get(from.as_str())?
+						.map(|v| enc.decrypt_link(REDIRECTS, from, v.value()))
+						.transpose()
+				})
+				.collect()
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
+		self.set_redirect_expiring(from, to, None).await
+	}
+
+	/// Sets all redirects in one write transaction
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirects(&self, redirects: &[(Id, Link)]) -> Result<Vec<Option<Link>>> {
+		let redirects = redirects
+			.iter()
+			.map(|(from, to)| (from.to_string(), to.clone()))
+			.collect::<Vec<_>>();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = redirects
+				.iter()
+				.map(|(from, to)| insert_redirect(&txn, &enc, from, to, None))
+				.collect::<Result<Vec<_>>>()?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let millis = i64::try_from(expires_at.unix_timestamp_nanos() / 1_000_000)?;
+		self.set_redirect_expiring(from, to, Some(millis)).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = remove_redirect(&txn, &enc, &from)?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	/// Removes all redirects in one write transaction
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let from = from.iter().map(ToString::to_string).collect::<Vec<_>>();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = from
+				.iter()
+				.map(|from| remove_redirect(&txn, &enc, from))
+				.collect::<Result<Vec<_>>>()?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	/// Expired tombstones are removed lazily, whenever a redirect is
+	/// soft-deleted
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let keep_until = i64::try_from(keep_until.unix_timestamp_nanos() / 1_000_000)?;
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = remove_redirect(&txn, &enc, &from)?;
+
+			if let Some(ref old) = old {
+				let old_link = enc.encrypt(TOMBSTONES, &from, old.as_str())?;
+				let mut tombstones = txn.open_table(TOMBSTONES)?;
+				tombstones.retain(|_, (_, keep_until)| !is_expired(Some(keep_until)))?;
+				tombstones.insert(from.as_str(), (old_link.as_str(), keep_until))?;
+			}
+
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let from = from.to_string();
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let restored = {
+				let exists = txn.open_table(REDIRECTS)?.get(from.as_str())?.is_some();
+				let expiry = txn
+					.open_table(REDIRECT_EXPIRY)?
+					.get(from.as_str())?
+					.map(|v| v.value());
+
+				if exists && !is_expired(expiry) {
+					bail!(NonTransient::RedirectExists);
+				}
+
+				let tombstone = txn
+					.open_table(TOMBSTONES)?
+					.remove(from.as_str())?
+					.map(|v| {
+						let (link, keep_until) = v.value();
+						Ok::<_, anyhow::Error>((
+							enc.decrypt_link(TOMBSTONES, &from, link)?,
+							keep_until,
+						))
+					})
+					.transpose()?;
+
+				match tombstone {
+					Some((link, keep_until)) if !is_expired(Some(keep_until)) => {
+						insert_redirect(&txn, &enc, &from, &link, None)?;
+						Some(link)
+					}
+					_ => None,
+				}
+			};
+			txn.commit()?;
+			Ok(restored)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn purge_redirects(&self) -> Result<usize> {
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let purged = usize::try_from(txn.open_table(TOMBSTONES)?.len()?)?;
+			txn.delete_table(TOMBSTONES)?;
+			txn.open_table(TOMBSTONES)?;
+			txn.commit()?;
+			Ok(purged)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		let link = self.encryption.encrypt_key(link.as_str())?;
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let expiry = txn.open_table(REDIRECT_EXPIRY)?;
+			let mut ids = Vec::new();
+
+			for id in txn
+				.open_multimap_table(REDIRECTS_BY_LINK)?
+				.get(link.as_str())?
+			{
+				let id = id?;
+				let id = id.value();
+
+				if !is_expired(expiry.get(id)?.map(|v| v.value())) {
+					ids.push(Id::try_from(id)?);
+				}
+			}
+
+			ids.sort_unstable();
+			Ok(ids)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		Ok(self
+			.get(VANITY, from.into_string())
+			.await?
+			.map(Id::try_from)
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		let from = from.to_vec();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let vanity = txn.open_table(VANITY)?;
+
+			from.iter()
+				.map(|from| {
+					vanity
+						.get(from.as_str())?
+						.map(|v| Id::try_from(v.value()))
+						.transpose()
+						.map_err(Into::into)
+				})
+				.collect()
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = insert_vanity(&txn, &from, to)?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	/// Sets all vanity paths in one write transaction
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanities(&self, vanities: &[(Normalized, Id)]) -> Result<Vec<Option<Id>>> {
+		let vanities = vanities.to_vec();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = vanities
+				.iter()
+				.map(|(from, to)| insert_vanity(&txn, from, *to))
+				.collect::<Result<Vec<_>>>()?;
+			txn.commit()?;
+			Ok(old)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let old = {
+				let old = txn
+					.open_table(VANITY)?
+					.remove(from.as_str())?
+					.map(|v| v.value().to_string());
+
+				if let Some(ref old) = old {
+					txn.open_multimap_table(VANITIES_BY_ID)?
+						.remove(old.as_str(), from.as_str())?;
+				}
+
+				old
+			};
+			txn.commit()?;
+			Ok(old.map(Id::try_from).transpose()?)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		let id = id.to_string();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+
+			txn.open_multimap_table(VANITIES_BY_ID)?
+				.get(id.as_str())?
+				.map(|vanity| Ok(Normalized::new(vanity?.value())))
+				.collect()
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let id = id.to_string();
+
+		Ok(self
+			.get(OPTIONS, id.clone())
+			.await?
+			.map(|s| self.decode_options(&id, &s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		let id = id.to_string();
+		let options = self
+			.encryption
+			.encrypt(OPTIONS, &id, &serde_json::to_string(&options)?)?;
+
+		Ok(self
+			.set(OPTIONS, id.clone(), options)
+			.await?
+			.map(|s| self.decode_options(&id, &s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let id = id.to_string();
+
+		Ok(self
+			.rem(OPTIONS, id.clone())
+			.await?
+			.map(|s| self.decode_options(&id, &s))
+			.transpose()?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let table = txn.open_table(STATISTICS)?;
+			let mut stats = Vec::new();
+
+			for entry in table.iter()? {
+				let (stat, value) = entry?;
+				let stat = serde_json::from_str::<Statistic>(&enc.decrypt_key(stat.value())?)?;
+
+				if description.matches(&stat) {
+					if let Some(value) = StatisticValue::new(value.value()) {
+						stats.push((stat, value));
+					}
+				}
+			}
+
+			Ok(stats)
+		})
+		.await
+	}
+
+	/// Statistics are scanned in the order of their (encrypted, if encryption
+	/// is enabled) keys, starting after the cursor's key
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		let cursor = cursor
+			.map(|stat| self.encryption.encrypt_key(&serde_json::to_string(&stat)?))
+			.transpose()?;
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let start = cursor.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+			let mut stats = Vec::new();
+
+			for entry in txn
+				.open_table(STATISTICS)?
+				.range::<&str>((start, Bound::Unbounded))?
+			{
+				let (stat, value) = entry?;
+				let stat = serde_json::from_str::<Statistic>(&enc.decrypt_key(stat.value())?)?;
+
+				if !description.matches(&stat) {
+					continue;
+				}
+
+				if let Some(value) = StatisticValue::new(value.value()) {
+					stats.push((stat, value));
+				}
+
+				if stats.len() > limit {
+					break;
+				}
+			}
+
+			Ok(Page::from_sorted(stats, limit))
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_statistic(&self, statistic: Statistic) -> Result<Option<StatisticValue>> {
+		let stat_json = self
+			.encryption
+			.encrypt_key(&serde_json::to_string(&statistic)?)?;
+		let durability = if self.sync_statistics {
+			Durability::Immediate
+		} else {
+			Durability::Eventual
+		};
+
+		self.blocking(move |db| {
+			let mut txn = db.begin_write()?;
+			txn.set_durability(durability);
+
+			let value = {
+				let mut table = txn.open_table(STATISTICS)?;
+				let value = table
+					.get(stat_json.as_str())?
+					.map_or(0, |v| v.value())
+					.saturating_add(1);
+				table.insert(stat_json.as_str(), value)?;
+				value
+			};
+
+			txn.commit()?;
+			Ok(StatisticValue::new(value))
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+			let mut removed = Vec::new();
+
+			{
+				let mut table = txn.open_table(STATISTICS)?;
+				let mut matching = Vec::new();
+
+				for entry in table.iter()? {
+					let (stat_json, value) = entry?;
+					let stat =
+						serde_json::from_str::<Statistic>(&enc.decrypt_key(stat_json.value())?)?;
+
+					if description.matches(&stat) {
+						matching.push((stat_json.value().to_string(), stat, value.value()));
+					}
+				}
+
+				for (stat_json, stat, value) in matching {
+					table.remove(stat_json.as_str())?;
+
+					if let Some(value) = StatisticValue::new(value) {
+						removed.push((stat, value));
+					}
+				}
+			}
+
+			txn.commit()?;
+			Ok(removed)
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		let key = key.to_string();
+		let expires_at = i64::try_from(expires_at.unix_timestamp_nanos() / 1_000_000)?;
+
+		self.blocking(move |db| {
+			let txn = db.begin_write()?;
+
+			let value = {
+				let mut table = txn.open_table(USAGE)?;
+				table.retain(|_, (_, until)| !is_expired(Some(until)))?;
+
+				let value = table
+					.get(key.as_str())?
+					.map_or(0, |v| v.value().0)
+					.saturating_add(amount);
+				table.insert(key.as_str(), (value, expires_at))?;
+				value
+			};
+
+			txn.commit()?;
+			Ok(value)
+		})
+		.await
+	}
+
+	fn supports_usage(&self) -> bool {
+		true
+	}
+
+	/// Namespaced stores use a separate database in the
+	/// `namespaces/[namespace]` subdirectory, with the same configuration
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		let directory = self.directory.join("namespaces").join(namespace);
+		let cached = self.namespaces.lock().get(namespace).cloned();
+		let db = if let Some(db) = cached {
+			db
+		} else {
+			let (directory, enc) = (directory.clone(), self.encryption.clone());
+			let db = Arc::new(spawn_blocking(move || Self::open(&directory, &enc)).await??);
+
+			Arc::clone(
+				self.namespaces
+					.lock()
+					.entry(namespace.to_string())
+					.or_insert(db),
+			)
+		};
+
+		Ok(Arc::new(Self {
+			db,
+			directory,
+			sync_statistics: self.sync_statistics,
+			encryption: self.encryption.clone(),
+			namespaces: Arc::default(),
+		}))
+	}
+
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		let enc = self.encryption.clone();
+		let items = self
+			.blocking(move |db| {
+				let txn = db.begin_read()?;
+				let mut items = Vec::new();
+
+				let expiry = txn.open_table(REDIRECT_EXPIRY)?;
+				for entry in txn.open_table(REDIRECTS)?.iter()? {
+					let (id, link) = entry?;
+
+					if is_expired(expiry.get(id.value())?.map(|v| v.value())) {
+						continue;
+					}
+
+					items.push(ExportItem::Redirect {
+						id: Id::try_from(id.value())?,
+						link: enc.decrypt_link(REDIRECTS, id.value(), link.value())?,
+					});
+				}
+
+				for entry in txn.open_table(VANITY)?.iter()? {
+					let (vanity, id) = entry?;
+					items.push(ExportItem::Vanity {
+						vanity: Normalized::new(vanity.value()),
+						id: Id::try_from(id.value())?,
+					});
+				}
+
+				for entry in txn.open_table(OPTIONS)?.iter()? {
+					let (id, options) = entry?;
+					items.push(ExportItem::Options {
+						id: Id::try_from(id.value())?,
+						options: serde_json::from_str(&enc.decrypt(
+							OPTIONS,
+							id.value(),
+							options.value(),
+						)?)?,
+					});
+				}
+
+				for entry in txn.open_table(STATISTICS)?.iter()? {
+					let (statistic, value) = entry?;
+
+					if let Some(value) = StatisticValue::new(value.value()) {
+						items.push(ExportItem::Statistic {
+							statistic: serde_json::from_str(&enc.decrypt_key(statistic.value())?)?,
+							value,
+						});
+					}
+				}
+
+				Ok(items)
+			})
+			.await?;
+
+		Ok(Box::pin(stream::iter(items.into_iter().map(Ok))))
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		let cursor = cursor.map(|id| id.to_string());
+		let enc = self.encryption.clone();
+
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let expiry = txn.open_table(REDIRECT_EXPIRY)?;
+			let start = cursor.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+			let mut redirects = Vec::new();
+
+			for entry in txn
+				.open_table(REDIRECTS)?
+				.range::<&str>((start, Bound::Unbounded))?
+			{
+				let (id, link) = entry?;
+
+				if is_expired(expiry.get(id.value())?.map(|v| v.value())) {
+					continue;
+				}
+
+				redirects.push((
+					Id::try_from(id.value())?,
+					enc.decrypt_link(REDIRECTS, id.value(), link.value())?,
+				));
+
+				if redirects.len() > limit {
+					break;
+				}
+			}
+
+			Ok(Page::from_sorted(redirects, limit))
+		})
+		.await
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		self.blocking(move |db| {
+			let txn = db.begin_read()?;
+			let start = cursor
+				.as_ref()
+				.map_or(Bound::Unbounded, |cursor| Bound::Excluded(cursor.as_str()));
+			let mut vanities = Vec::new();
+
+			for entry in txn
+				.open_table(VANITY)?
+				.range::<&str>((start, Bound::Unbounded))?
+				.take(limit.saturating_add(1))
+			{
+				let (vanity, id) = entry?;
+				vanities.push((Normalized::new(vanity.value()), Id::try_from(id.value())?));
+			}
+
+			Ok(Page::from_sorted(vanities, limit))
+		})
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashMap, env};
+
+	use super::*;
+	use crate::store::tests;
+
+	/// Create a new store in a new temporary directory, which is removed when
+	/// the returned guard is dropped
+	async fn get_store() -> (Store, TempDir) {
+		let dir = TempDir(env::temp_dir().join(format!("links-redb-test-{}", Id::new())));
+		let store = Store::new(&HashMap::from([(
+			"directory".to_string(),
+			dir.0.to_string_lossy().to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		(store, dir)
+	}
+
+	struct TempDir(PathBuf);
+
+	impl Drop for TempDir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.0);
+		}
+	}
+
+	#[test]
+	fn store_type() {
+		tests::store_type::<Store>();
+	}
+
+	#[tokio::test]
+	async fn get_store_type() {
+		tests::get_store_type::<Store>(&get_store().await.0);
+	}
+
+	#[tokio::test]
+	async fn get_redirect() {
+		tests::get_redirect(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_redirects() {
+		tests::get_redirects(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect() {
+		tests::set_redirect(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirects() {
+		tests::set_redirects(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirect() {
+		tests::rem_redirect(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirects() {
+		tests::rem_redirects(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn soft_delete() {
+		tests::soft_delete(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect_with_expiry() {
+		tests::set_redirect_with_expiry(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn find_by_link() {
+		tests::find_by_link(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanity() {
+		tests::get_vanity(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities() {
+		tests::get_vanities(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanity() {
+		tests::set_vanity(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanities() {
+		tests::set_vanities(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn rem_vanity() {
+		tests::rem_vanity(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities_of() {
+		tests::get_vanities_of(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_options() {
+		tests::get_options(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn set_options() {
+		tests::set_options(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn rem_options() {
+		tests::rem_options(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics() {
+		tests::get_statistics(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics_page() {
+		tests::get_statistics_page(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn incr_statistic() {
+		tests::incr_statistic(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn rem_statistics() {
+		tests::rem_statistics(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn incr_usage() {
+		tests::incr_usage(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn export_all() {
+		tests::export_all(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn import() {
+		tests::import(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn list_redirects() {
+		tests::list_redirects(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn list_vanities() {
+		tests::list_vanities(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn health() {
+		tests::health(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn namespaced() {
+		tests::namespaced(&get_store().await.0).await;
+	}
+
+	#[tokio::test]
+	async fn persistence() {
+		let (store, dir) = get_store().await;
+		let id = Id::new();
+		let link = Link::new("https://example.com/persistent").unwrap();
+
+		store.set_redirect(id, link.clone()).await.unwrap();
+		store
+			.set_vanity(Normalized::new("persistent"), id)
+			.await
+			.unwrap();
+		drop(store);
+
+		let store = Store::new(&HashMap::from([(
+			"directory".to_string(),
+			dir.0.to_string_lossy().to_string(),
+		)]))
+		.await
+		.unwrap();
+
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+		assert_eq!(
+			store
+				.get_vanity(Normalized::new("persistent"))
+				.await
+				.unwrap(),
+			Some(id)
+		);
+	}
+
+	#[tokio::test]
+	async fn encryption() {
+		let dir = TempDir(env::temp_dir().join(format!("links-redb-test-{}", Id::new())));
+		let directory = ("directory".to_string(), dir.0.to_string_lossy().to_string());
+		let key_path = dir.0.with_extension("key");
+		fs::write(&key_path, "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+		let config = HashMap::from([
+			directory.clone(),
+			(
+				"encryption_key_file".to_string(),
+				key_path.to_string_lossy().to_string(),
+			),
+		]);
+
+		let store = Store::new(&config).await.unwrap();
+		tests::find_by_link(&store).await;
+		tests::soft_delete(&store).await;
+		tests::get_options(&store).await;
+		tests::rem_statistics(&store).await;
+		tests::export_all(&store).await;
+
+		let id = Id::new();
+		let link = Link::new("https://example.com/very-secret-destination").unwrap();
+		store.set_redirect(id, link.clone()).await.unwrap();
+		drop(store);
+
+		let file = fs::read(dir.0.join(DATABASE_FILE)).unwrap();
+		assert!(!file
+			.windows(b"very-secret-destination".len())
+			.any(|w| w == b"very-secret-destination"));
+
+		let store = Store::new(&config).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+
+		// Ciphertexts moved to a different key can't be decrypted
+		let other = Id::new();
+		let ciphertext = store.get(REDIRECTS, id.to_string()).await.unwrap().unwrap();
+		store
+			.set(REDIRECTS, other.to_string(), ciphertext)
+			.await
+			.unwrap();
+		assert!(store.get_redirect(other).await.is_err());
+		drop(store);
+
+		// The database can only be opened with the same key
+		assert!(Store::new(&HashMap::from([directory.clone()]))
+			.await
+			.is_err());
+		fs::write(&key_path, "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=").unwrap();
+		assert!(Store::new(&config).await.is_err());
+
+		// Encryption can't be enabled for an existing unencrypted database
+		let (store, other_dir) = get_store().await;
+		store
+			.set_redirect(id, Link::new("https://example.com").unwrap())
+			.await
+			.unwrap();
+		drop(store);
+		let mut config = config;
+		config.insert(
+			"directory".to_string(),
+			other_dir.0.to_string_lossy().to_string(),
+		);
+		assert!(Store::new(&config).await.is_err());
+
+		let _ = fs::remove_file(&key_path);
+	}
+
+	#[tokio::test]
+	async fn missing_directory() {
+		assert!(Store::new(&HashMap::new()).await.is_err());
+	}
+}
@@ -14,7 +14,12 @@
 //! with keys in the following format:
 //! - `links:redirect:[ID]` for redirects (with string values of URLs)
 //! - `links:vanity:[vanity]` for vanity paths (with string values of IDs)
+//! - `links:options:[ID]` for link options (with json string values)
+//! - `links:tombstone:[ID]` for soft-deleted redirects (with string values of
+//!   URLs, expiring when the tombstone does)
 //! - `links:stat:[link]:[type]:[time]:[data]` for statistics (with int values)
+//! - `links:usage:[key]` for API usage counters (with int values, expiring when
+//!   they are no longer needed)
 //!
 //! Some extra metadata is also needed for certain operations:
 //! - `links:stat-all` set of all statistics (json)
@@ -22,30 +27,53 @@
 //! - `links:stat-type:[type]` set of all statistics with that type (json)
 //! - `links:stat-time:[time]` set of all statistics with that time (json)
 //! - `links:stat-data:[data]` set of all statistics with that data (json)
+//! - `links:by-link:[URL]` set of IDs of redirects to that URL, which may also
+//!   contain IDs that no longer redirect there (e.g. after they expired)
+//! - `links:vanities-of:[ID]` set of vanity paths of that ID, which may also
+//!   contain vanity paths that no longer correspond to that ID
+//! - `links:by-link-indexed` and `links:vanities-of-indexed` markers that the
+//!   above indexes have been built for data set before they existed
+//!
+//! Data of namespaces is stored the same way, but with the `links:` prefix of
+//! all keys replaced by `links:ns:[namespace]:`.
 
 use std::{
 	collections::HashMap,
 	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::Arc,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use fred::{
 	bytes_utils::Str,
 	prelude::*,
 	types::{ClusterDiscoveryPolicy, RespVersion},
 };
+use futures_util::{future::try_join_all, stream, StreamExt, TryStreamExt};
 use links_id::Id;
 use links_normalized::{Link, Normalized};
+use time::OffsetDateTime;
 use tokio::try_join;
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use super::BackendType;
 use crate::{
+	options::LinkOptions,
 	stats::{Statistic, StatisticDescription, StatisticValue},
-	store::StoreBackend,
+	store::{
+		backend::{export_pages, ExportItem, ExportStream, Health, NonTransient, Page},
+		StoreBackend,
+	},
 };
 
+/// The number of keys requested at a time when scanning for keys to export or
+/// list
+const SCAN_COUNT: u32 = 1000;
+
+/// The number of values fetched concurrently when exporting or indexing
+const EXPORT_CHUNK_SIZE: usize = 100;
+
 /// A Redis-backed `StoreBackend` implementation. The best option for most
 /// links deployments.
 ///
@@ -65,23 +93,39 @@ use crate::{
 ///   others will be automatically discovered). Note that this is not a full
 ///   URL, just the host and port.
 /// - `username`: The username to use for the connection, when using ACLs on the
-///   server. Don't specify this when using password-based auth.
+///   server. Don't specify this when using password-based auth. Requires
+///   `password`.
 /// - `password`: The password to use for the Redis connection. This can either
 ///   be the user's password (when using ACLs) or the global server password
 ///   when using password-based authentication.
-/// - `database`: The database number to use for the Redis connection. **Default
+/// - `database`: The database number to use for the Redis connection. Can not
+///   be used in cluster mode, which only supports database `0`. **Default
 ///   `0`**.
 /// - `tls`: Enable TLS (using system root CAs) when communicating with the
-///   Redis server. *`true` / `false`*. **Default `false`**.
+///   Redis server. This must match the server's configuration, connecting with
+///   TLS to a server without TLS (or the other way around) fails. *`true` /
+///   `false`*. **Default `false`**.
+/// - `client_name`: The name set for all of links' connections (using `CLIENT
+///   SETNAME`), to identify them in e.g. `CLIENT LIST`. Must only contain
+///   printable ASCII characters other than spaces. **Default none**.
 /// - `pool_size`: The number of connections to use in the connection pool.
 ///   **Default `8`**.
+/// - `replicas`: Read-only replicas to send reads to, as comma-separated
+///   `host:port` pairs (e.g. `replica1:6379,replica2:6379`). All other options
+///   are shared with the primary, except `cluster`, which is always disabled
+///   for replicas. Reads fall back to the primary if a replica fails. See
+///   [`Store`][crate::store::Store] for details. **Default none**.
 pub struct Store {
 	pool: RedisPool,
+	/// The prefix of all keys, `links:` or `links:ns:[namespace]:`
+	prefix: String,
 }
 
 impl Debug for Store {
 	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-		f.debug_struct("Store").finish_non_exhaustive()
+		f.debug_struct("Store")
+			.field("prefix", &self.prefix)
+			.finish_non_exhaustive()
 	}
 }
 
@@ -100,7 +144,26 @@ impl StoreBackend for Store {
 
 	#[instrument(level = "trace", ret, err)]
 	async fn new(config: &HashMap<String, String>) -> Result<Self> {
-		let server_config = if config.get("cluster").map_or(Ok(false), |s| s.parse())? {
+		let cluster = config.get("cluster").map_or(Ok(false), |s| s.parse())?;
+		let tls = config.get("tls").map_or(Ok(false), |s| s.parse())?;
+		let client_name = config.get("client_name").cloned();
+
+		if config.contains_key("username") && !config.contains_key("password") {
+			bail!("the username option requires a password");
+		}
+
+		if cluster && config.contains_key("database") {
+			bail!("the database option can not be used in cluster mode");
+		}
+
+		if client_name
+			.as_deref()
+			.is_some_and(|name| name.is_empty() || !name.bytes().all(|b| b.is_ascii_graphic()))
+		{
+			bail!("client_name must only contain printable ASCII characters other than spaces");
+		}
+
+		let server_config = if cluster {
 			ServerConfig::Clustered {
 				hosts: config
 					.get("connect")
@@ -154,7 +217,7 @@ impl StoreBackend for Store {
 				enabled: true,
 				..Default::default()
 			},
-			tls: if config.get("tls").map_or(Ok(false), |s| s.parse())? {
+			tls: if tls {
 				Some(TlsConnector::default_rustls()?.into())
 			} else {
 				None
@@ -174,118 +237,364 @@ impl StoreBackend for Store {
 				.unwrap_or(8),
 		)?;
 
+		if let Some(name) = client_name {
+			for client in pool.clients() {
+				set_client_name(client, name.clone());
+			}
+		}
+
 		pool.connect();
-		pool.wait_for_connect().await?;
+		pool.wait_for_connect()
+			.await
+			.map_err(|err| connect_error(err, tls))?;
 
-		Ok(Self { pool })
+		Ok(Self {
+			pool,
+			prefix: "links:".to_string(),
+		})
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
-		Ok(self.pool.get(format!("links:redirect:{from}")).await?)
+		Ok(self
+			.pool
+			.get(format!("{}redirect:{from}", self.prefix))
+			.await?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		self.get_many(
+			from.iter()
+				.map(|id| format!("{}redirect:{id}", self.prefix)),
+		)
+		.await
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
-		Ok(self
+		self.set_redirect_expiring(from, to, None).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let millis = i64::try_from(expires_at.unix_timestamp_nanos() / 1_000_000)?;
+
+		self.set_redirect_expiring(from, to, Some(Expiration::PXAT(millis)))
+			.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let old: Option<Link> = self
+			.pool
+			.getdel(format!("{}redirect:{from}", self.prefix))
+			.await?;
+
+		if let Some(ref old) = old {
+			let () = self
+				.pool
+				.srem(format!("{}by-link:{old}", self.prefix), from.to_string())
+				.await?;
+		}
+
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let key = format!("{}tombstone:{from}", self.prefix);
+		let expiration = Expiration::PXAT(i64::try_from(
+			keep_until.unix_timestamp_nanos() / 1_000_000,
+		)?);
+
+		// The tombstone is created before the redirect is removed, so that the
+		// redirect can't be lost if creating the tombstone fails
+		let Some(link) = self.get_redirect(from).await? else {
+			return Ok(None);
+		};
+
+		let () = self
+			.pool
+			.set(&key, link.as_str(), Some(expiration.clone()), None, false)
+			.await?;
+
+		let old = self.rem_redirect(from).await?;
+
+		match old {
+			Some(ref old) if *old != link => {
+				let () = self
+					.pool
+					.set(&key, old.as_str(), Some(expiration), None, false)
+					.await?;
+			}
+			Some(_) => (),
+			None => {
+				let () = self.pool.del(&key).await?;
+			}
+		}
+
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let key = format!("{}tombstone:{from}", self.prefix);
+
+		let Some(link): Option<Link> = self.pool.get(&key).await? else {
+			return Ok(None);
+		};
+
+		let () = self
+			.pool
+			.sadd(format!("{}by-link:{link}", self.prefix), from.to_string())
+			.await?;
+
+		// Only restore the redirect if it doesn't exist (anymore)
+		let set: RedisValue = self
 			.pool
 			.set(
-				format!("links:redirect:{from}"),
-				to.into_string(),
-				None,
+				format!("{}redirect:{from}", self.prefix),
+				link.as_str(),
 				None,
-				true,
+				Some(SetOptions::NX),
+				false,
 			)
-			.await?)
+			.await?;
+
+		if set.is_null() {
+			bail!(NonTransient::RedirectExists);
+		}
+
+		let () = self.pool.del(&key).await?;
+		Ok(Some(link))
 	}
 
 	#[instrument(level = "trace", ret, err)]
-	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
-		Ok(self.pool.getdel(format!("links:redirect:{from}")).await?)
+	async fn purge_redirects(&self) -> Result<usize> {
+		let keys = self.scan(&format!("{}tombstone:*", self.prefix)).await?;
+		let mut purged = 0;
+
+		for chunk in keys.chunks(EXPORT_CHUNK_SIZE) {
+			let deleted: Vec<usize> =
+				try_join_all(chunk.iter().map(|key| self.pool.del(key))).await?;
+			purged += deleted.into_iter().sum::<usize>();
+		}
+
+		Ok(purged)
+	}
+
+	/// Uses the `links:by-link:[URL]` index, building it first if it doesn't
+	/// exist yet. Index entries which no longer match are skipped, but not
+	/// removed, because they may belong to a redirect which is being set.
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		self.build_index("redirect", "by-link").await?;
+
+		let mut ids = self
+			.pool
+			.smembers::<Vec<String>, _>(format!("{}by-link:{link}", self.prefix))
+			.await?
+			.into_iter()
+			.map(Id::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		ids.sort_unstable();
+
+		let current: Vec<Option<Link>> = self
+			.get_many(ids.iter().map(|id| format!("{}redirect:{id}", self.prefix)))
+			.await?;
+
+		Ok(ids
+			.into_iter()
+			.zip(current)
+			.filter(|(_, current)| current.as_ref() == Some(link))
+			.map(|(id, _)| id)
+			.collect())
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
-		Ok(self.pool.get(format!("links:vanity:{from}")).await?)
+		Ok(self
+			.pool
+			.get(format!("{}vanity:{from}", self.prefix))
+			.await?)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		self.get_many(
+			from.iter()
+				.map(|vanity| format!("{}vanity:{vanity}", self.prefix)),
+		)
+		.await
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
-		Ok(self
+		// The index entry is added first, like in `set_redirect_expiring`
+		let () = self
+			.pool
+			.sadd(format!("{}vanities-of:{to}", self.prefix), from.as_str())
+			.await?;
+
+		let old: Option<Id> = self
 			.pool
 			.set(
-				format!("links:vanity:{from}"),
+				format!("{}vanity:{from}", self.prefix),
 				to.to_string(),
 				None,
 				None,
 				true,
 			)
-			.await?)
+			.await?;
+
+		if let Some(old) = old.filter(|&old| old != to) {
+			let () = self
+				.pool
+				.srem(format!("{}vanities-of:{old}", self.prefix), from.as_str())
+				.await?;
+		}
+
+		Ok(old)
 	}
 
 	#[instrument(level = "trace", ret, err)]
 	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
-		Ok(self.pool.getdel(format!("links:vanity:{from}")).await?)
+		let old: Option<Id> = self
+			.pool
+			.getdel(format!("{}vanity:{from}", self.prefix))
+			.await?;
+
+		if let Some(old) = old {
+			let () = self
+				.pool
+				.srem(format!("{}vanities-of:{old}", self.prefix), from.as_str())
+				.await?;
+		}
+
+		Ok(old)
 	}
 
+	/// Uses the `links:vanities-of:[ID]` index, building it first if it doesn't
+	/// exist yet. Like in `find_by_link`, index entries which no longer match
+	/// are skipped, but not removed.
 	#[instrument(level = "trace", ret, err)]
-	async fn get_statistics(
-		&self,
-		description: StatisticDescription,
-	) -> Result<Vec<(Statistic, StatisticValue)>> {
-		let mut keys = Vec::with_capacity(5);
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		self.build_index("vanity", "vanities-of").await?;
 
-		keys.push("links:stat-all".to_string());
+		let mut vanities = self
+			.pool
+			.smembers::<Vec<String>, _>(format!("{}vanities-of:{id}", self.prefix))
+			.await?
+			.into_iter()
+			.map(|vanity| Normalized::new(&vanity))
+			.collect::<Vec<_>>();
+		vanities.sort_unstable();
 
-		if let Some(link) = description.link {
-			keys.push(format!("links:stat-link:{link}"));
-		}
+		let current: Vec<Option<Id>> = self
+			.get_many(
+				vanities
+					.iter()
+					.map(|vanity| format!("{}vanity:{vanity}", self.prefix)),
+			)
+			.await?;
 
-		if let Some(stat_type) = description.stat_type {
-			keys.push(format!("links:stat-type:{stat_type}"));
-		}
+		Ok(vanities
+			.into_iter()
+			.zip(current)
+			.filter(|&(_, current)| current == Some(id))
+			.map(|(vanity, _)| vanity)
+			.collect())
+	}
 
-		if let Some(data) = description.data {
-			keys.push(format!("links:stat-data:{data}"));
-		}
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let options: Option<String> = self
+			.pool
+			.get(format!("{}options:{id}", self.prefix))
+			.await?;
+		Ok(options.map(|s| serde_json::from_str(&s)).transpose()?)
+	}
 
-		if let Some(time) = description.time {
-			keys.push(format!("links:stat-time:{time}"));
-		}
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		let old: Option<String> = self
+			.pool
+			.set(
+				format!("{}options:{id}", self.prefix),
+				serde_json::to_string(&options)?,
+				None,
+				None,
+				true,
+			)
+			.await?;
+		Ok(old.and_then(|s| serde_json::from_str(&s).ok()))
+	}
 
-		let stats: Vec<Statistic> = self
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let old: Option<String> = self
 			.pool
-			.sinter::<Vec<String>, _>(keys)
+			.getdel(format!("{}options:{id}", self.prefix))
+			.await?;
+		Ok(old.and_then(|s| serde_json::from_str(&s).ok()))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		let stats: Vec<Statistic> = self
+			.statistic_names(description)
 			.await?
 			.into_iter()
 			.filter_map(|s| serde_json::from_str(&s).ok())
 			.collect();
 
-		let stat_keys = stats
-			.iter()
-			.map(
-				|Statistic {
-				     link,
-				     stat_type,
-				     time,
-				     data,
-				 }| format!("links:stat:{link}:{stat_type}:{time}:{data}"),
-			)
-			.collect::<Vec<String>>();
-
-		let values: Vec<Option<u64>> = if stat_keys.is_empty() {
-			Vec::new()
-		} else {
-			self.pool.mget(stat_keys).await?
-		};
+		self.statistic_values(stats).await
+	}
 
-		let res = stats
+	/// Statistics are sorted by their JSON representation. The names of all
+	/// matching statistics are read, but only the values of the listed
+	/// statistics are fetched.
+	#[instrument(level = "trace", ret, err)]
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		let cursor = cursor
+			.map(|stat| serde_json::to_string(&stat))
+			.transpose()?;
+		let mut names = self
+			.statistic_names(description)
+			.await?
 			.into_iter()
-			.zip(values.into_iter())
-			.filter_map(|(s, v)| Some((s, StatisticValue::new(v?)?)))
+			.filter(|name| cursor.as_ref().map_or(true, |cursor| name > cursor))
+			.collect::<Vec<_>>();
+		names.sort_unstable();
+		names.truncate(limit.saturating_add(1));
+
+		let stats = names
+			.iter()
+			.filter_map(|s| serde_json::from_str(s).ok())
 			.collect();
 
-		Ok(res)
+		Ok(Page::from_sorted(
+			self.statistic_values(stats).await?,
+			limit,
+		))
 	}
 
 	#[instrument(level = "trace", ret, err)]
@@ -301,21 +610,24 @@ impl StoreBackend for Store {
 
 		let values: Vec<RedisValue> = self
 			.pool
-			.incr(format!("links:stat:{link}:{stat_type}:{time}:{data}"))
+			.incr(format!(
+				"{}stat:{link}:{stat_type}:{time}:{data}",
+				self.prefix
+			))
 			.await?;
 
 		Box::pin(async {
 			try_join!(
 				self.pool
-					.sadd::<(), _, _>("links:stat-all".to_string(), &stat_json),
+					.sadd::<(), _, _>(format!("{}stat-all", self.prefix), &stat_json),
 				self.pool
-					.sadd::<(), _, _>(format!("links:stat-link:{link}"), &stat_json),
+					.sadd::<(), _, _>(format!("{}stat-link:{link}", self.prefix), &stat_json),
 				self.pool
-					.sadd::<(), _, _>(format!("links:stat-type:{stat_type}"), &stat_json),
+					.sadd::<(), _, _>(format!("{}stat-type:{stat_type}", self.prefix), &stat_json),
 				self.pool
-					.sadd::<(), _, _>(format!("links:stat-data:{data}"), &stat_json),
+					.sadd::<(), _, _>(format!("{}stat-data:{data}", self.prefix), &stat_json),
 				self.pool
-					.sadd::<(), _, _>(format!("links:stat-time:{time}"), &stat_json),
+					.sadd::<(), _, _>(format!("{}stat-time:{time}", self.prefix), &stat_json),
 			)
 		})
 		.await?;
@@ -333,22 +645,22 @@ impl StoreBackend for Store {
 	) -> Result<Vec<(Statistic, StatisticValue)>> {
 		let mut keys = Vec::with_capacity(5);
 
-		keys.push("links:stat-all".to_string());
+		keys.push(format!("{}stat-all", self.prefix));
 
 		if let Some(link) = description.link {
-			keys.push(format!("links:stat-link:{link}"));
+			keys.push(format!("{}stat-link:{link}", self.prefix));
 		}
 
 		if let Some(stat_type) = description.stat_type {
-			keys.push(format!("links:stat-type:{stat_type}"));
+			keys.push(format!("{}stat-type:{stat_type}", self.prefix));
 		}
 
 		if let Some(data) = description.data {
-			keys.push(format!("links:stat-data:{data}"));
+			keys.push(format!("{}stat-data:{data}", self.prefix));
 		}
 
 		if let Some(time) = description.time {
-			keys.push(format!("links:stat-time:{time}"));
+			keys.push(format!("{}stat-time:{time}", self.prefix));
 		}
 
 		let stats_json: Vec<String> = self.pool.sinter(keys.clone()).await?;
@@ -365,7 +677,7 @@ impl StoreBackend for Store {
 				     stat_type,
 				     time,
 				     data,
-				 }| format!("links:stat:{link}:{stat_type}:{time}:{data}"),
+				 }| format!("{}stat:{link}:{stat_type}:{time}:{data}", self.prefix),
 			)
 			.collect::<Vec<String>>();
 
@@ -388,6 +700,363 @@ impl StoreBackend for Store {
 
 		Ok(res)
 	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		let key = format!("{}usage:{key}", self.prefix);
+
+		let value: i64 = self.pool.incr_by(&key, i64::try_from(amount)?).await?;
+		let () = self
+			.pool
+			.expire_at(&key, expires_at.unix_timestamp())
+			.await?;
+
+		Ok(u64::try_from(value)?)
+	}
+
+	fn supports_usage(&self) -> bool {
+		true
+	}
+
+	async fn health(&self) -> Health {
+		Health::check(async { Ok(self.pool.next().ping::<()>().await?) }).await
+	}
+
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		Ok(Arc::new(Self {
+			pool: self.pool.clone(),
+			prefix: format!("links:ns:{namespace}:"),
+		}))
+	}
+
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		let mut keys = Vec::new();
+
+		for kind in ["redirect", "vanity", "options"] {
+			keys.extend(self.scan(&format!("{}{kind}:*", self.prefix)).await?);
+		}
+
+		let chunks = keys
+			.chunks(EXPORT_CHUNK_SIZE)
+			.map(<[String]>::to_vec)
+			.collect::<Vec<_>>();
+
+		let values = stream::iter(chunks).then(move |chunk| self.export_chunk(chunk));
+		let statistics = stream::once(async move {
+			Ok(self
+				.get_statistics(StatisticDescription::default())
+				.await?
+				.into_iter()
+				.map(|(statistic, value)| ExportItem::Statistic { statistic, value })
+				.collect())
+		});
+
+		Ok(export_pages(values.chain(statistics)))
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		let redirects = self
+			.list("redirect", cursor.map(|id| id.to_string()), limit)
+			.await?
+			.into_iter()
+			.map(|(id, link)| Ok((Id::try_from(id)?, Link::new_unchecked(link))))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Page::from_sorted(redirects, limit))
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		let vanities = self
+			.list("vanity", cursor.map(Normalized::into_string), limit)
+			.await?
+			.into_iter()
+			.map(|(vanity, id)| Ok((Normalized::new(&vanity), Id::try_from(id)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Page::from_sorted(vanities, limit))
+	}
+}
+
+/// Set the name of all of the `client`'s connections to `name` whenever they
+/// are (re)connected
+fn set_client_name(client: &RedisClient, name: String) {
+	let client = client.clone();
+	let listener = client.clone();
+
+	listener.on_reconnect(move |server| {
+		let client = client.clone();
+		let name = name.clone();
+
+		tokio::spawn(async move {
+			let res = if client.is_clustered() {
+				client.with_cluster_node(server).client_setname(name).await
+			} else {
+				client.client_setname(name).await
+			};
+
+			if let Err(err) = res {
+				warn!(?err, "Could not set Redis client name");
+			}
+		});
+
+		Ok(())
+	});
+}
+
+/// Add a hint about the likely cause of a Redis connection error, depending on
+/// whether `tls` is enabled
+fn connect_error(err: RedisError, tls: bool) -> anyhow::Error {
+	let hint = match (err.kind(), tls) {
+		(RedisErrorKind::Auth, _) => "check the username and password options",
+		(RedisErrorKind::Tls, _) => "check that the server's TLS certificate is valid",
+		(_, true) => "TLS is enabled, check that the server is configured to use TLS",
+		(_, false) => "check the connect option, and enable tls if the server requires TLS",
+	};
+
+	anyhow::Error::new(err).context(format!("could not connect to Redis ({hint})"))
+}
+
+impl Store {
+	/// Get the values of all `keys` with a single `MGET`, or with concurrent
+	/// `GET`s when using Redis Cluster (where the keys may be on different
+	/// nodes)
+	async fn get_many<T: FromRedis + Send>(
+		&self,
+		keys: impl Iterator<Item = String> + Send,
+	) -> Result<Vec<Option<T>>> {
+		let keys = keys.collect::<Vec<_>>();
+
+		if keys.is_empty() {
+			Ok(Vec::new())
+		} else if self.pool.next().is_clustered() {
+			Ok(try_join_all(keys.into_iter().map(|key| self.pool.get(key))).await?)
+		} else {
+			Ok(self.pool.mget(keys).await?)
+		}
+	}
+
+	/// Set the redirect from `from` to `to` with the `expiration` (if any),
+	/// returning the old value. The `links:by-link:[URL]` index entry is added
+	/// before the redirect is set, so that failures can only leave behind
+	/// extra entries in the index, never missing ones.
+	async fn set_redirect_expiring(
+		&self,
+		from: Id,
+		to: Link,
+		expiration: Option<Expiration>,
+	) -> Result<Option<Link>> {
+		let () = self
+			.pool
+			.sadd(format!("{}by-link:{to}", self.prefix), from.to_string())
+			.await?;
+
+		let old: Option<Link> = self
+			.pool
+			.set(
+				format!("{}redirect:{from}", self.prefix),
+				to.as_str(),
+				expiration,
+				None,
+				true,
+			)
+			.await?;
+
+		if let Some(old) = old.as_ref().filter(|&old| *old != to) {
+			let () = self
+				.pool
+				.srem(format!("{}by-link:{old}", self.prefix), from.to_string())
+				.await?;
+		}
+
+		Ok(old)
+	}
+
+	/// Build the `index` of the `kind` of data (e.g. the `by-link` index of
+	/// `redirect`s), unless it is already marked as built. All `links:[kind]:*`
+	/// keys are added to the `links:[index]:[value]` sets, then the index is
+	/// marked as built using the `links:[index]-indexed` key. Data set
+	/// concurrently is added to the index when it is set anyway.
+	async fn build_index(&self, kind: &str, index: &str) -> Result<()> {
+		let marker = format!("{}{index}-indexed", self.prefix);
+		let indexed: bool = self.pool.exists(&marker).await?;
+
+		if indexed {
+			return Ok(());
+		}
+
+		let prefix = format!("{}{kind}:", self.prefix);
+		let keys = self.scan(&format!("{prefix}*")).await?;
+
+		for chunk in keys.chunks(EXPORT_CHUNK_SIZE) {
+			let values: Vec<Option<String>> =
+				try_join_all(chunk.iter().map(|key| self.pool.get(key))).await?;
+
+			try_join_all(chunk.iter().zip(values).filter_map(|(key, value)| {
+				let name = key.strip_prefix(&prefix)?.to_string();
+				Some(
+					self.pool
+						.sadd::<(), _, _>(format!("{}{index}:{}", self.prefix, value?), name),
+				)
+			}))
+			.await?;
+		}
+
+		let () = self.pool.set(marker, 1, None, None, false).await?;
+		Ok(())
+	}
+
+	/// Get all keys matching the `pattern`, scanning all nodes of a cluster
+	async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+		let client = self.pool.next();
+
+		let scanned: Vec<RedisKey> = if client.is_clustered() {
+			client
+				.scan_cluster_buffered(pattern, Some(SCAN_COUNT), None)
+				.try_collect()
+				.await?
+		} else {
+			client
+				.scan_buffered(pattern, Some(SCAN_COUNT), None)
+				.try_collect()
+				.await?
+		};
+
+		Ok(scanned
+			.into_iter()
+			.filter_map(RedisKey::into_string)
+			.collect())
+	}
+
+	/// Get the names (JSON representations) of all statistics matching the
+	/// `description`, in no particular order
+	async fn statistic_names(&self, description: StatisticDescription) -> Result<Vec<String>> {
+		let mut keys = Vec::with_capacity(5);
+
+		keys.push(format!("{}stat-all", self.prefix));
+
+		if let Some(link) = description.link {
+			keys.push(format!("{}stat-link:{link}", self.prefix));
+		}
+
+		if let Some(stat_type) = description.stat_type {
+			keys.push(format!("{}stat-type:{stat_type}", self.prefix));
+		}
+
+		if let Some(data) = description.data {
+			keys.push(format!("{}stat-data:{data}", self.prefix));
+		}
+
+		if let Some(time) = description.time {
+			keys.push(format!("{}stat-time:{time}", self.prefix));
+		}
+
+		Ok(self.pool.sinter::<Vec<String>, _>(keys).await?)
+	}
+
+	/// Fetch the values of the `stats`, keeping their order. Statistics
+	/// without a value are skipped.
+	async fn statistic_values(
+		&self,
+		stats: Vec<Statistic>,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		let stat_keys = stats
+			.iter()
+			.map(
+				|Statistic {
+				     link,
+				     stat_type,
+				     time,
+				     data,
+				 }| format!("{}stat:{link}:{stat_type}:{time}:{data}", self.prefix),
+			)
+			.collect::<Vec<String>>();
+
+		let values: Vec<Option<u64>> = if stat_keys.is_empty() {
+			Vec::new()
+		} else {
+			self.pool.mget(stat_keys).await?
+		};
+
+		let res = stats
+			.into_iter()
+			.zip(values.into_iter())
+			.filter_map(|(s, v)| Some((s, StatisticValue::new(v?)?)))
+			.collect();
+
+		Ok(res)
+	}
+
+	/// List the names and values of up to `limit + 1` keys of the `kind` (e.g.
+	/// `redirect` for `links:redirect:*` keys) sorted after the `cursor`. All
+	/// keys of the `kind` are scanned, but only the listed values are fetched.
+	/// Keys removed since they were scanned are skipped.
+	async fn list(
+		&self,
+		kind: &str,
+		cursor: Option<String>,
+		limit: usize,
+	) -> Result<Vec<(String, String)>> {
+		let prefix = format!("{}{kind}:", self.prefix);
+		let mut names = self
+			.scan(&format!("{prefix}*"))
+			.await?
+			.into_iter()
+			.filter_map(|key| key.strip_prefix(&prefix).map(ToOwned::to_owned))
+			.filter(|name| cursor.as_ref().map_or(true, |cursor| name > cursor))
+			.collect::<Vec<_>>();
+		names.sort_unstable();
+		names.truncate(limit.saturating_add(1));
+
+		let values: Vec<Option<String>> = try_join_all(
+			names
+				.iter()
+				.map(|name| self.pool.get(format!("{prefix}{name}"))),
+		)
+		.await?;
+
+		Ok(names
+			.into_iter()
+			.zip(values)
+			.filter_map(|(name, value)| Some((name, value?)))
+			.collect())
+	}
+
+	/// Fetch the values of the redirect, vanity path, and options `keys` for
+	/// exporting. Keys removed since they were scanned are skipped.
+	async fn export_chunk(&self, keys: Vec<String>) -> Result<Vec<ExportItem>> {
+		let values: Vec<Option<String>> =
+			try_join_all(keys.iter().map(|key| self.pool.get(key))).await?;
+		let mut items = Vec::with_capacity(keys.len());
+
+		for (key, value) in keys.into_iter().zip(values) {
+			let (Some(value), Some(key)) = (value, key.strip_prefix(self.prefix.as_str())) else {
+				continue;
+			};
+
+			if let Some(id) = key.strip_prefix("redirect:") {
+				items.push(ExportItem::Redirect {
+					id: Id::try_from(id)?,
+					link: Link::new_unchecked(value),
+				});
+			} else if let Some(vanity) = key.strip_prefix("vanity:") {
+				items.push(ExportItem::Vanity {
+					vanity: Normalized::new(vanity),
+					id: Id::try_from(value)?,
+				});
+			} else if let Some(id) = key.strip_prefix("options:") {
+				items.push(ExportItem::Options {
+					id: Id::try_from(id)?,
+					options: serde_json::from_str(&value)?,
+				});
+			}
+		}
+
+		Ok(items)
+	}
 }
 
 /// Note:
@@ -428,36 +1097,101 @@ mod tests {
 		tests::get_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_redirects() {
+		tests::get_redirects(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn set_redirect() {
 		tests::set_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn set_redirects() {
+		tests::set_redirects(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn rem_redirect() {
 		tests::rem_redirect(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn rem_redirects() {
+		tests::rem_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn soft_delete() {
+		tests::soft_delete(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect_with_expiry() {
+		tests::set_redirect_with_expiry(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn find_by_link() {
+		tests::find_by_link(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn get_vanity() {
 		tests::get_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_vanities() {
+		tests::get_vanities(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn set_vanity() {
 		tests::set_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn set_vanities() {
+		tests::set_vanities(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn rem_vanity() {
 		tests::rem_vanity(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_vanities_of() {
+		tests::get_vanities_of(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_options() {
+		tests::get_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_options() {
+		tests::set_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_options() {
+		tests::rem_options(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn get_statistics() {
 		tests::get_statistics(&get_store().await).await;
 	}
 
+	#[tokio::test]
+	async fn get_statistics_page() {
+		tests::get_statistics_page(&get_store().await).await;
+	}
+
 	#[tokio::test]
 	async fn incr_statistic() {
 		tests::incr_statistic(&get_store().await).await;
@@ -467,4 +1201,82 @@ mod tests {
 	async fn rem_statistics() {
 		tests::rem_statistics(&get_store().await).await;
 	}
+
+	#[tokio::test]
+	async fn incr_usage() {
+		tests::incr_usage(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn export_all() {
+		tests::export_all(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn import() {
+		tests::import(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_redirects() {
+		tests::list_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_vanities() {
+		tests::list_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn health() {
+		tests::health(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn namespaced() {
+		tests::namespaced(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn inconsistent_config() {
+		let config = |options: &[(&str, &str)]| {
+			options
+				.iter()
+				.map(|&(k, v)| (k.to_string(), v.to_string()))
+				.chain([("connect".to_string(), "localhost:6379".to_string())])
+				.collect::<HashMap<_, _>>()
+		};
+
+		assert!(Store::new(&config(&[("username", "links")])).await.is_err());
+		assert!(
+			Store::new(&config(&[("cluster", "true"), ("database", "1")]))
+				.await
+				.is_err()
+		);
+		assert!(Store::new(&config(&[("client_name", "links server")]))
+			.await
+			.is_err());
+		assert!(Store::new(&config(&[("tls", "true")])).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn client_name() {
+		use fred::{interfaces::ClientInterface as _, prelude::ClientLike as _};
+
+		let store = Store::new(&HashMap::from_iter([
+			("connect".to_string(), "localhost:6379".to_string()),
+			("client_name".to_string(), "links-test".to_string()),
+		]))
+		.await
+		.unwrap();
+
+		// The name is set asynchronously after connecting
+		tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+		for client in store.pool.clients() {
+			let name: Option<String> = client.client_getname().await.unwrap();
+			assert_eq!(name.as_deref(), Some("links-test"));
+			assert!(client.is_connected());
+		}
+	}
 }
@@ -0,0 +1,342 @@
+//! Retrying of failed store operations with exponential backoff, and a circuit
+//! breaker to stop sending requests to a store backend which keeps failing.
+//!
+//! With retries enabled, transient failures (e.g. a dropped connection to a
+//! database server) delay operations instead of failing them. Once an
+//! operation fails even after all retries a configurable number of times in a
+//! row, the circuit breaker opens, and all operations fail immediately until
+//! the breaker's cooldown is over. After that, a single operation is tried
+//! again (while others keep failing immediately), closing the breaker if it
+//! succeeds. [Non-transient][NonTransient] errors, e.g. of operations not
+//! supported by the store backend, are neither retried nor counted.
+
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use parking_lot::Mutex;
+use rand::Rng;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, warn};
+
+use super::backend::NonTransient;
+
+/// The initial delay between retries if not configured (in milliseconds)
+const DEFAULT_BACKOFF: u64 = 50;
+
+/// The maximum delay between retries if not configured (in milliseconds)
+const DEFAULT_MAX_BACKOFF: u64 = 2000;
+
+/// How long the circuit breaker stays open if not configured (in seconds)
+const DEFAULT_BREAKER_COOLDOWN: u64 = 30;
+
+/// The retry and circuit breaker policy of a [`Store`][super::Store].
+///
+/// # Configuration
+///
+/// The policy is configured together with the store backend, using the
+/// following options:
+/// - `retries`: How many times a failed operation is retried. **Default `0`**
+///   (no retries).
+/// - `retry_backoff`: The delay before the first retry (in milliseconds),
+///   doubled after every further retry. A random jitter of up to half of the
+///   delay is subtracted from it. **Default `50`**.
+/// - `retry_max_backoff`: The maximum delay between retries (in milliseconds).
+///   **Default `2000`**.
+/// - `breaker_threshold`: After how many failed operations in a row (each after
+///   all retries) the circuit breaker opens. **Default `0`** (no circuit
+///   breaker).
+/// - `breaker_cooldown`: How long the circuit breaker stays open (in seconds),
+///   before operations are tried again. **Default `30`**.
+#[derive(Debug)]
+pub struct Retry {
+	retries: u32,
+	backoff: Duration,
+	max_backoff: Duration,
+	breaker_threshold: u32,
+	breaker_cooldown: Duration,
+	breaker: Mutex<Breaker>,
+}
+
+/// The state of a circuit breaker
+#[derive(Debug, Default)]
+struct Breaker {
+	/// The number of operations which failed in a row
+	failures: u32,
+	/// The time until which the breaker is open, if it is
+	open_until: Option<Instant>,
+	/// Whether an operation is currently being tried after the breaker's
+	/// cooldown is over (the breaker is half-open)
+	probing: bool,
+}
+
+/// An operation being tried while the circuit breaker is half-open. If it is
+/// dropped before finishing (e.g. because the operation was cancelled), the
+/// next operation is tried instead.
+struct Probe<'a>(Option<&'a Mutex<Breaker>>);
+
+impl Drop for Probe<'_> {
+	fn drop(&mut self) {
+		if let Some(breaker) = self.0.take() {
+			breaker.lock().probing = false;
+		}
+	}
+}
+
+impl Retry {
+	/// Parse the retry policy from the store `config`
+	///
+	/// # Errors
+	/// This returns an error if any of the options are invalid.
+	pub fn from_config(config: &HashMap<String, String>) -> Result<Self> {
+		let parse = |key: &str, default: u64| {
+			config
+				.get(key)
+				.map(|s| s.parse::<u64>())
+				.transpose()
+				.map_err(|err| anyhow!("couldn't parse {key} value: {err}"))
+				.map(|v| v.unwrap_or(default))
+		};
+
+		let retry = Self {
+			retries: u32::try_from(parse("retries", 0)?)?,
+			backoff: Duration::from_millis(parse("retry_backoff", DEFAULT_BACKOFF)?),
+			max_backoff: Duration::from_millis(parse("retry_max_backoff", DEFAULT_MAX_BACKOFF)?),
+			breaker_threshold: u32::try_from(parse("breaker_threshold", 0)?)?,
+			breaker_cooldown: Duration::from_secs(parse(
+				"breaker_cooldown",
+				DEFAULT_BREAKER_COOLDOWN,
+			)?),
+			breaker: Mutex::new(Breaker::default()),
+		};
+
+		if retry.max_backoff < retry.backoff {
+			bail!("retry_max_backoff must not be less than retry_backoff");
+		}
+
+		Ok(retry)
+	}
+
+	/// A policy without any retries or circuit breaker
+	#[must_use]
+	pub fn disabled() -> Self {
+		Self {
+			retries: 0,
+			backoff: Duration::from_millis(DEFAULT_BACKOFF),
+			max_backoff: Duration::from_millis(DEFAULT_MAX_BACKOFF),
+			breaker_threshold: 0,
+			breaker_cooldown: Duration::from_secs(DEFAULT_BREAKER_COOLDOWN),
+			breaker: Mutex::new(Breaker::default()),
+		}
+	}
+
+	/// Run the operation `op`, retrying it according to this policy if it
+	/// fails. If the circuit breaker is open (or half-open with another
+	/// operation already being tried), `op` is not run at all.
+	///
+	/// # Errors
+	/// This returns the last error of `op` if it failed even after all
+	/// retries, or an error if the circuit breaker is open.
+	pub async fn run<T, F: Future<Output = Result<T>>>(
+		&self,
+		mut op: impl FnMut() -> F,
+	) -> Result<T> {
+		let mut probe = Probe(None);
+		if self.breaker_threshold > 0 {
+			let mut breaker = self.breaker.lock();
+			match breaker.open_until {
+				Some(until) if until > Instant::now() => {
+					bail!("the store circuit breaker is open");
+				}
+				Some(_) if breaker.probing => bail!("the store circuit breaker is half-open"),
+				Some(_) => {
+					breaker.probing = true;
+					probe.0 = Some(&self.breaker);
+				}
+				None => (),
+			}
+		}
+
+		let mut attempt = 0;
+		let res = loop {
+			match op().await {
+				Ok(res) => break Ok(res),
+				Err(err) if attempt < self.retries && !NonTransient::is_cause_of(&err) => {
+					let delay = self.delay(attempt);
+					debug!(?err, attempt, ?delay, "Store operation failed, retrying");
+					sleep(delay).await;
+					attempt += 1;
+				}
+				Err(err) => break Err(err),
+			}
+		};
+
+		if self.breaker_threshold > 0 {
+			let mut breaker = self.breaker.lock();
+
+			// The probe is finished while the lock is held, so that no other
+			// operation is tried before the breaker's new state is known
+			if probe.0.take().is_some() {
+				breaker.probing = false;
+			}
+
+			match &res {
+				Ok(_) => *breaker = Breaker::default(),
+				Err(err) if NonTransient::is_cause_of(err) => (),
+				Err(_) => {
+					breaker.failures = breaker.failures.saturating_add(1);
+
+					if breaker.failures >= self.breaker_threshold {
+						if breaker.open_until.is_none() {
+							warn!(
+								failures = breaker.failures,
+								"Store operations keep failing, opening the circuit breaker"
+							);
+						}
+
+						breaker.open_until = Some(Instant::now() + self.breaker_cooldown);
+					}
+				}
+			}
+		}
+
+		res
+	}
+
+	/// The delay before retry number `attempt` (starting at 0), with jitter
+	fn delay(&self, attempt: u32) -> Duration {
+		let delay = self
+			.backoff
+			.saturating_mul(2_u32.saturating_pow(attempt))
+			.min(self.max_backoff);
+
+		delay.saturating_sub(delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use super::*;
+
+	fn config(options: &[(&str, &str)]) -> HashMap<String, String> {
+		options
+			.iter()
+			.map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+			.collect()
+	}
+
+	/// An operation which fails the first `failures` times it is called
+	fn flaky(calls: &AtomicU32, failures: u32) -> impl Future<Output = Result<u32>> {
+		let call = calls.fetch_add(1, Ordering::SeqCst);
+
+		std::future::ready(if call < failures {
+			Err(anyhow!("failure {call}"))
+		} else {
+			Ok(call)
+		})
+	}
+
+	#[tokio::test]
+	async fn retries() {
+		let retry =
+			Retry::from_config(&config(&[("retries", "3"), ("retry_backoff", "1")])).unwrap();
+
+		let calls = AtomicU32::new(0);
+		assert_eq!(retry.run(|| flaky(&calls, 3)).await.unwrap(), 3);
+
+		let calls = AtomicU32::new(0);
+		assert!(retry.run(|| flaky(&calls, 4)).await.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+		let calls = AtomicU32::new(0);
+		assert!(Retry::disabled().run(|| flaky(&calls, 1)).await.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn circuit_breaker() {
+		let retry = Retry::from_config(&config(&[
+			("breaker_threshold", "2"),
+			("breaker_cooldown", "1"),
+		]))
+		.unwrap();
+		let calls = AtomicU32::new(0);
+
+		assert!(retry.run(|| flaky(&calls, 2)).await.is_err());
+		assert!(retry.run(|| flaky(&calls, 2)).await.is_err());
+
+		// The breaker is open, so the operation isn't run
+		assert!(retry.run(|| flaky(&calls, 2)).await.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+		sleep(Duration::from_millis(1100)).await;
+		assert_eq!(retry.run(|| flaky(&calls, 2)).await.unwrap(), 2);
+		assert_eq!(retry.run(|| flaky(&calls, 2)).await.unwrap(), 3);
+	}
+
+	#[tokio::test]
+	async fn half_open() {
+		let retry = Retry::from_config(&config(&[
+			("breaker_threshold", "1"),
+			("breaker_cooldown", "1"),
+		]))
+		.unwrap();
+		let calls = &AtomicU32::new(0);
+
+		assert!(retry.run(|| flaky(calls, 1)).await.is_err());
+		sleep(Duration::from_millis(1100)).await;
+
+		// Only a single operation is tried while the breaker is half-open
+		let probe = retry.run(move || async move {
+			sleep(Duration::from_millis(100)).await;
+			flaky(calls, 1).await
+		});
+		let other = async {
+			sleep(Duration::from_millis(10)).await;
+			retry.run(|| flaky(calls, 1)).await
+		};
+
+		let (probe, other) = tokio::join!(probe, other);
+		assert_eq!(probe.unwrap(), 1);
+		assert!(other.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test]
+	async fn non_transient() {
+		let retry = Retry::from_config(&config(&[
+			("retries", "3"),
+			("retry_backoff", "1"),
+			("breaker_threshold", "1"),
+		]))
+		.unwrap();
+		let calls = AtomicU32::new(0);
+		let unsupported = || {
+			calls.fetch_add(1, Ordering::SeqCst);
+			std::future::ready(Err::<(), _>(NonTransient::Unsupported("testing is").into()))
+		};
+
+		// Neither retried nor counted by the circuit breaker
+		assert!(retry.run(&unsupported).await.is_err());
+		assert!(retry.run(&unsupported).await.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn backoff() {
+		let retry = Retry::from_config(&config(&[
+			("retry_backoff", "100"),
+			("retry_max_backoff", "300"),
+		]))
+		.unwrap();
+
+		assert!((50..=100).contains(&retry.delay(0).as_millis()));
+		assert!((100..=200).contains(&retry.delay(1).as_millis()));
+		assert!((150..=300).contains(&retry.delay(2).as_millis()));
+		assert!((150..=300).contains(&retry.delay(40).as_millis()));
+
+		assert!(Retry::from_config(&config(&[("retries", "many")])).is_err());
+		assert!(Retry::from_config(&config(&[("retry_max_backoff", "1")])).is_err());
+	}
+}
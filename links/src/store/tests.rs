@@ -5,7 +5,10 @@ use links_id::Id;
 use links_normalized::{Link, Normalized};
 
 use super::*;
-use crate::stats::{StatisticData, StatisticTime, StatisticType};
+use crate::{
+	options::{Cors, LinkOptions},
+	stats::{StatisticData, StatisticTime, StatisticType},
+};
 
 pub fn store_type<S: StoreBackend>() {
 	let name = S::store_type().as_str();
@@ -42,6 +45,26 @@ pub async fn get_redirect(store: &impl StoreBackend) {
 	assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
 }
 
+pub async fn get_redirects(store: &impl StoreBackend) {
+	let id_a = Id::from([0x1f, 0x2f, 0x3f, 0x4f, 0x5f]);
+	let id_b = Id::from([0x20, 0x30, 0x40, 0x50, 0x60]);
+	let link_a = Link::new("https://example.com/batch/a").unwrap();
+	let link_b = Link::new("https://example.com/batch/b").unwrap();
+
+	store.set_redirect(id_a, link_a.clone()).await.unwrap();
+	store.set_redirect(id_b, link_b.clone()).await.unwrap();
+
+	assert_eq!(
+		store.get_redirects(&[id_b, Id::new(), id_a]).await.unwrap(),
+		vec![Some(link_b), None, Some(link_a.clone())]
+	);
+	assert_eq!(store.get_redirects(&[id_a, id_a]).await.unwrap(), vec![
+		Some(link_a.clone()),
+		Some(link_a)
+	]);
+	assert_eq!(store.get_redirects(&[]).await.unwrap(), Vec::new());
+}
+
 pub async fn set_redirect(store: &impl StoreBackend) {
 	let id = Id::from([0x11, 0x21, 0x31, 0x41, 0x51]);
 	let link = Link::new("https://example.com/test/2").unwrap();
@@ -51,6 +74,31 @@ pub async fn set_redirect(store: &impl StoreBackend) {
 	assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
 }
 
+pub async fn set_redirects(store: &impl StoreBackend) {
+	let id_a = Id::from([0x27, 0x37, 0x47, 0x57, 0x67]);
+	let id_b = Id::from([0x28, 0x38, 0x48, 0x58, 0x68]);
+	let link_a = Link::new("https://example.com/bulk/a").unwrap();
+	let link_b = Link::new("https://example.com/bulk/b").unwrap();
+	let link_c = Link::new("https://example.com/bulk/c").unwrap();
+
+	store.set_redirect(id_a, link_a.clone()).await.unwrap();
+
+	assert_eq!(
+		store
+			.set_redirects(&[
+				(id_a, link_b.clone()),
+				(id_b, link_b.clone()),
+				(id_b, link_c.clone())
+			])
+			.await
+			.unwrap(),
+		vec![Some(link_a), None, Some(link_b.clone())]
+	);
+	assert_eq!(store.get_redirect(id_a).await.unwrap(), Some(link_b));
+	assert_eq!(store.get_redirect(id_b).await.unwrap(), Some(link_c));
+	assert_eq!(store.set_redirects(&[]).await.unwrap(), Vec::new());
+}
+
 pub async fn rem_redirect(store: &impl StoreBackend) {
 	let id = Id::from([0x12, 0x22, 0x32, 0x42, 0x52]);
 	let link = Link::new("https://example.com/test/3").unwrap();
@@ -62,6 +110,135 @@ pub async fn rem_redirect(store: &impl StoreBackend) {
 	assert_eq!(store.get_redirect(id).await.unwrap(), None);
 }
 
+pub async fn rem_redirects(store: &impl StoreBackend) {
+	let id_a = Id::from([0x29, 0x39, 0x49, 0x59, 0x69]);
+	let id_b = Id::from([0x2a, 0x3a, 0x4a, 0x5a, 0x6a]);
+	let link = Link::new("https://example.com/bulk/rem").unwrap();
+
+	store.set_redirect(id_a, link.clone()).await.unwrap();
+
+	assert_eq!(
+		store.rem_redirects(&[id_b, id_a, id_a]).await.unwrap(),
+		vec![None, Some(link), None]
+	);
+	assert_eq!(store.get_redirect(id_a).await.unwrap(), None);
+	assert_eq!(store.rem_redirects(&[]).await.unwrap(), Vec::new());
+}
+
+pub async fn soft_delete(store: &impl StoreBackend) {
+	use time::{Duration, OffsetDateTime};
+
+	let id = Id::from([0x25, 0x35, 0x45, 0x55, 0x65]);
+	let link = Link::new("https://example.com/soft-delete").unwrap();
+	let now = OffsetDateTime::now_utc();
+
+	store.set_redirect(id, link.clone()).await.unwrap();
+	assert_eq!(
+		store
+			.rem_redirect_soft(id, now + Duration::hours(1))
+			.await
+			.unwrap(),
+		Some(link.clone())
+	);
+	assert_eq!(store.get_redirect(id).await.unwrap(), None);
+
+	// Removed redirects can be restored once
+	assert_eq!(
+		store.restore_redirect(id).await.unwrap(),
+		Some(link.clone())
+	);
+	assert_eq!(store.get_redirect(id).await.unwrap(), Some(link.clone()));
+	assert!(store.restore_redirect(id).await.is_err());
+	store.rem_redirect(id).await.unwrap();
+	assert_eq!(store.restore_redirect(id).await.unwrap(), None);
+
+	// Purged redirects can't be restored
+	store.set_redirect(id, link.clone()).await.unwrap();
+	store
+		.rem_redirect_soft(id, now + Duration::hours(1))
+		.await
+		.unwrap();
+	assert!(store.purge_redirects().await.unwrap() >= 1);
+	assert_eq!(store.restore_redirect(id).await.unwrap(), None);
+
+	// Neither can ones past their retention
+	store.set_redirect(id, link.clone()).await.unwrap();
+	store
+		.rem_redirect_soft(id, now - Duration::seconds(1))
+		.await
+		.unwrap();
+	assert_eq!(store.restore_redirect(id).await.unwrap(), None);
+	assert_eq!(store.get_redirect(id).await.unwrap(), None);
+}
+
+pub async fn set_redirect_with_expiry(store: &impl StoreBackend) {
+	use time::{Duration, OffsetDateTime};
+
+	let id = Id::from([0x1c, 0x2c, 0x3c, 0x4c, 0x5c]);
+	let link = Link::new("https://example.com/expiring").unwrap();
+	let now = OffsetDateTime::now_utc();
+
+	store
+		.set_redirect_with_expiry(id, link.clone(), now + Duration::hours(1))
+		.await
+		.unwrap();
+	assert_eq!(store.get_redirect(id).await.unwrap(), Some(link.clone()));
+
+	// Expired redirects don't exist
+	store
+		.set_redirect_with_expiry(id, link.clone(), now - Duration::seconds(1))
+		.await
+		.unwrap();
+	assert_eq!(store.get_redirect(id).await.unwrap(), None);
+	assert_eq!(store.rem_redirect(id).await.unwrap(), None);
+
+	// Setting a redirect without an expiry removes the expiry
+	store
+		.set_redirect_with_expiry(id, link.clone(), now + Duration::seconds(1))
+		.await
+		.unwrap();
+	store.set_redirect(id, link.clone()).await.unwrap();
+	tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+	assert_eq!(store.get_redirect(id).await.unwrap(), Some(link));
+}
+
+pub async fn find_by_link(store: &impl StoreBackend) {
+	use time::{Duration, OffsetDateTime};
+
+	let a = Id::from([0x23, 0x33, 0x43, 0x53, 0x63]);
+	let b = Id::from([0x23, 0x33, 0x43, 0x53, 0x64]);
+	let c = Id::from([0x23, 0x33, 0x43, 0x53, 0x65]);
+	let link = Link::new(&format!("https://example.com/find/{}", Id::new())).unwrap();
+	let other = Link::new("https://example.com/find/other").unwrap();
+
+	assert_eq!(store.find_by_link(&link).await.unwrap(), Vec::new());
+
+	store.set_redirect(b, link.clone()).await.unwrap();
+	store.set_redirect(a, link.clone()).await.unwrap();
+	store.set_redirect(c, other.clone()).await.unwrap();
+	assert_eq!(store.find_by_link(&link).await.unwrap(), vec![a, b]);
+
+	// Expired redirects are not found
+	store
+		.set_redirect_with_expiry(
+			c,
+			link.clone(),
+			OffsetDateTime::now_utc() - Duration::seconds(1),
+		)
+		.await
+		.unwrap();
+	assert_eq!(store.find_by_link(&link).await.unwrap(), vec![a, b]);
+
+	store.set_redirect(b, other).await.unwrap();
+	assert_eq!(store.find_by_link(&link).await.unwrap(), vec![a]);
+
+	store.rem_redirect(a).await.unwrap();
+	assert_eq!(store.find_by_link(&link).await.unwrap(), Vec::new());
+
+	store.rem_redirect(b).await.unwrap();
+	store.rem_redirect(c).await.unwrap();
+}
+
 pub async fn get_vanity(store: &impl StoreBackend) {
 	let vanity = Normalized::new("Example Test One");
 	let id = Id::from([0x13, 0x23, 0x33, 0x43, 0x53]);
@@ -78,6 +255,36 @@ pub async fn get_vanity(store: &impl StoreBackend) {
 	assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), Some(id));
 }
 
+pub async fn get_vanities(store: &impl StoreBackend) {
+	let vanity_a = Normalized::new("Batch Test A");
+	let vanity_b = Normalized::new("Batch Test B");
+	let id_a = Id::from([0x21, 0x31, 0x41, 0x51, 0x61]);
+	let id_b = Id::from([0x22, 0x32, 0x42, 0x52, 0x62]);
+
+	store.set_vanity(vanity_a.clone(), id_a).await.unwrap();
+	store.set_vanity(vanity_b.clone(), id_b).await.unwrap();
+
+	assert_eq!(
+		store
+			.get_vanities(&[
+				vanity_b.clone(),
+				Normalized::new("Doesn't exist."),
+				vanity_a.clone()
+			])
+			.await
+			.unwrap(),
+		vec![Some(id_b), None, Some(id_a)]
+	);
+	assert_eq!(
+		store
+			.get_vanities(&[vanity_a.clone(), vanity_a])
+			.await
+			.unwrap(),
+		vec![Some(id_a), Some(id_a)]
+	);
+	assert_eq!(store.get_vanities(&[]).await.unwrap(), Vec::new());
+}
+
 pub async fn set_vanity(store: &impl StoreBackend) {
 	let vanity = Normalized::new("Example Test Two");
 	let id = Id::from([0x14, 0x24, 0x34, 0x44, 0x54]);
@@ -87,6 +294,30 @@ pub async fn set_vanity(store: &impl StoreBackend) {
 	assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), Some(id));
 }
 
+pub async fn set_vanities(store: &impl StoreBackend) {
+	let vanity_a = Normalized::new("Bulk Test A");
+	let vanity_b = Normalized::new("Bulk Test B");
+	let id_a = Id::from([0x2b, 0x3b, 0x4b, 0x5b, 0x6b]);
+	let id_b = Id::from([0x2c, 0x3c, 0x4c, 0x5c, 0x6c]);
+
+	store.set_vanity(vanity_a.clone(), id_a).await.unwrap();
+
+	assert_eq!(
+		store
+			.set_vanities(&[
+				(vanity_a.clone(), id_b),
+				(vanity_b.clone(), id_a),
+				(vanity_b.clone(), id_b)
+			])
+			.await
+			.unwrap(),
+		vec![Some(id_a), None, Some(id_a)]
+	);
+	assert_eq!(store.get_vanity(vanity_a).await.unwrap(), Some(id_b));
+	assert_eq!(store.get_vanity(vanity_b).await.unwrap(), Some(id_b));
+	assert_eq!(store.set_vanities(&[]).await.unwrap(), Vec::new());
+}
+
 pub async fn rem_vanity(store: &impl StoreBackend) {
 	let vanity = Normalized::new("Example Test Three");
 	let id = Id::from([0x15, 0x25, 0x35, 0x45, 0x55]);
@@ -98,6 +329,88 @@ pub async fn rem_vanity(store: &impl StoreBackend) {
 	assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), None);
 }
 
+pub async fn get_vanities_of(store: &impl StoreBackend) {
+	let id = Id::new();
+	let other = Id::from([0x24, 0x34, 0x44, 0x54, 0x64]);
+	let a = Normalized::new(&format!("vanities of {id} a"));
+	let b = Normalized::new(&format!("vanities of {id} b"));
+	let c = Normalized::new(&format!("vanities of {id} c"));
+
+	assert_eq!(store.get_vanities_of(id).await.unwrap(), Vec::new());
+
+	store.set_vanity(b.clone(), id).await.unwrap();
+	store.set_vanity(a.clone(), id).await.unwrap();
+	store.set_vanity(c.clone(), other).await.unwrap();
+	assert_eq!(store.get_vanities_of(id).await.unwrap(), vec![
+		a.clone(),
+		b.clone()
+	]);
+
+	store.set_vanity(b.clone(), other).await.unwrap();
+	assert_eq!(store.get_vanities_of(id).await.unwrap(), vec![a.clone()]);
+
+	store.rem_vanity(a).await.unwrap();
+	assert_eq!(store.get_vanities_of(id).await.unwrap(), Vec::new());
+
+	store.rem_vanity(b).await.unwrap();
+	store.rem_vanity(c).await.unwrap();
+}
+
+fn test_options(origin: &str) -> LinkOptions {
+	LinkOptions {
+		cors: Some(Cors {
+			allowed_origins: vec![origin.to_string()],
+			allowed_methods: vec!["GET".to_string()],
+		}),
+		path_passthrough: None,
+		expires_at: None,
+		max_clicks: None,
+		geo_destinations: None,
+		device_destinations: None,
+		split: None,
+		query_params: None,
+		flagged: None,
+		enabled: None,
+		interstitial: None,
+	}
+}
+
+pub async fn get_options(store: &impl StoreBackend) {
+	let id = Id::from([0x16, 0x26, 0x36, 0x46, 0x56]);
+	let options = test_options("https://example.com");
+
+	store.set_options(id, options.clone()).await.unwrap();
+
+	assert_eq!(store.get_options(Id::new()).await.unwrap(), None);
+	assert_eq!(store.get_options(id).await.unwrap(), Some(options));
+}
+
+pub async fn set_options(store: &impl StoreBackend) {
+	let id = Id::from([0x17, 0x27, 0x37, 0x47, 0x57]);
+	let first = test_options("https://example.com");
+	let second = test_options("https://example.net");
+
+	store.rem_options(id).await.unwrap();
+
+	assert_eq!(store.set_options(id, first.clone()).await.unwrap(), None);
+	assert_eq!(
+		store.set_options(id, second.clone()).await.unwrap(),
+		Some(first)
+	);
+	assert_eq!(store.get_options(id).await.unwrap(), Some(second));
+}
+
+pub async fn rem_options(store: &impl StoreBackend) {
+	let id = Id::from([0x18, 0x28, 0x38, 0x48, 0x58]);
+	let options = test_options("*");
+
+	store.set_options(id, options.clone()).await.unwrap();
+
+	assert_eq!(store.rem_options(id).await.unwrap(), Some(options));
+	assert_eq!(store.get_options(id).await.unwrap(), None);
+	assert_eq!(store.rem_options(id).await.unwrap(), None);
+}
+
 pub async fn get_statistics(store: &impl StoreBackend) {
 	let id = Id::from([0x16, 0x26, 0x36, 0x46, 0x56]);
 	let vanity = Normalized::new("Statistics Test One");
@@ -143,6 +456,47 @@ pub async fn get_statistics(store: &impl StoreBackend) {
 	assert_eq!(res_d[0], (statistic_b, StatisticValue::new(1).unwrap()));
 }
 
+pub async fn get_statistics_page(store: &impl StoreBackend) {
+	let id = Id::from([0x2d, 0x3d, 0x4d, 0x5d, 0x6d]);
+	let statistics = ["a.example.com", "b.example.com", "c.example.com"].map(|host| Statistic {
+		link: id.into(),
+		stat_type: StatisticType::HostRequest,
+		data: host.into(),
+		time: StatisticTime::now(),
+	});
+
+	let description = StatisticDescription {
+		link: Some(id.into()),
+		..Default::default()
+	};
+
+	for statistic in &statistics {
+		store.incr_statistic(statistic.clone()).await.unwrap();
+	}
+
+	let mut listed = Vec::new();
+	let mut cursor = None;
+	loop {
+		let page = store
+			.get_statistics_page(description.clone(), cursor, 2)
+			.await
+			.unwrap();
+		assert!(page.items.len() <= 2);
+		listed.extend(page.items);
+
+		cursor = page.next;
+		if cursor.is_none() {
+			break;
+		}
+	}
+
+	// Every statistic is listed exactly once
+	assert_eq!(listed.len(), statistics.len());
+	for statistic in statistics {
+		assert!(listed.contains(&(statistic, StatisticValue::new(1).unwrap())));
+	}
+}
+
 pub async fn incr_statistic(store: &impl StoreBackend) {
 	let id = Id::from([0x17, 0x27, 0x37, 0x47, 0x57]);
 	let vanity = Normalized::new("Statistics Test Two");
@@ -220,3 +574,280 @@ pub async fn rem_statistics(store: &impl StoreBackend) {
 	assert!(res_e.is_empty());
 	assert!(res_f.is_empty());
 }
+
+pub async fn incr_usage(store: &impl StoreBackend) {
+	// Unique keys, because counters may persist between test runs
+	let now = time::OffsetDateTime::now_utc();
+	let key_a = format!("usage-test-a:{}", now.unix_timestamp_nanos());
+	let key_b = format!("usage-test-b:{}", now.unix_timestamp_nanos());
+	let expires_at = now + time::Duration::minutes(5);
+
+	assert!(store.supports_usage());
+	assert_eq!(store.incr_usage(&key_a, 1, expires_at).await.unwrap(), 1);
+	assert_eq!(store.incr_usage(&key_a, 1, expires_at).await.unwrap(), 2);
+	assert_eq!(store.incr_usage(&key_b, 5, expires_at).await.unwrap(), 5);
+	assert_eq!(store.incr_usage(&key_a, 10, expires_at).await.unwrap(), 12);
+	assert_eq!(store.incr_usage(&key_b, 0, expires_at).await.unwrap(), 5);
+}
+
+pub async fn export_all(store: &impl StoreBackend) {
+	use futures_util::TryStreamExt;
+
+	use crate::store::backend::ExportItem;
+
+	let id = Id::from([0x18, 0x28, 0x38, 0x48, 0x58]);
+	let link = Link::new("https://example.com/export").unwrap();
+	let vanity = Normalized::new("Export Test");
+	let options = test_options("https://export.example.com");
+	let statistic = Statistic {
+		link: id.into(),
+		stat_type: StatisticType::Request,
+		data: StatisticData::default(),
+		time: StatisticTime::now(),
+	};
+
+	store.set_redirect(id, link.clone()).await.unwrap();
+	store.set_vanity(vanity.clone(), id).await.unwrap();
+	store.set_options(id, options.clone()).await.unwrap();
+	let value = store.incr_statistic(statistic.clone()).await.unwrap();
+
+	let items = store
+		.export_all()
+		.await
+		.unwrap()
+		.try_collect::<Vec<_>>()
+		.await
+		.unwrap();
+
+	assert!(items.contains(&ExportItem::Redirect { id, link }));
+	assert!(items.contains(&ExportItem::Vanity { vanity, id }));
+	assert!(items.contains(&ExportItem::Options { id, options }));
+
+	if value.is_some() {
+		assert!(items.iter().any(|item| matches!(
+			item,
+			ExportItem::Statistic { statistic: s, .. } if s == &statistic
+		)));
+	}
+}
+
+pub async fn import(store: &impl StoreBackend) {
+	use futures_util::stream;
+
+	use crate::store::backend::{ExportItem, ImportPolicy, ImportSummary};
+
+	let id_a = Id::from([0x19, 0x29, 0x39, 0x49, 0x59]);
+	let id_b = Id::from([0x1a, 0x2a, 0x3a, 0x4a, 0x5a]);
+	let link_a = Link::new("https://example.com/import/a").unwrap();
+	let link_b = Link::new("https://example.com/import/b").unwrap();
+	let vanity = Normalized::new("Import Test");
+	let options = test_options("https://import.example.com");
+
+	store.set_redirect(id_a, link_a.clone()).await.unwrap();
+
+	let items = || {
+		Box::pin(stream::iter(
+			[
+				ExportItem::Redirect {
+					id: id_a,
+					link: link_b.clone(),
+				},
+				ExportItem::Redirect {
+					id: id_b,
+					link: link_b.clone(),
+				},
+				ExportItem::Vanity {
+					vanity: vanity.clone(),
+					id: id_b,
+				},
+				ExportItem::Options {
+					id: id_b,
+					options: options.clone(),
+				},
+			]
+			.map(Ok),
+		))
+	};
+
+	let summary = store.import(items(), ImportPolicy::Skip).await.unwrap();
+	assert_eq!(summary, ImportSummary {
+		imported: 3,
+		skipped: 1
+	});
+	assert_eq!(store.get_redirect(id_a).await.unwrap(), Some(link_a));
+	assert_eq!(
+		store.get_redirect(id_b).await.unwrap(),
+		Some(link_b.clone())
+	);
+	assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), Some(id_b));
+	assert_eq!(
+		store.get_options(id_b).await.unwrap(),
+		Some(options.clone())
+	);
+
+	assert!(store.import(items(), ImportPolicy::Error).await.is_err());
+
+	let summary = store
+		.import(items(), ImportPolicy::Overwrite)
+		.await
+		.unwrap();
+	assert_eq!(summary.imported, 4);
+	assert_eq!(
+		store.get_redirect(id_a).await.unwrap(),
+		Some(link_b.clone())
+	);
+
+	// Identical values are not conflicts
+	let summary = store.import(items(), ImportPolicy::Error).await.unwrap();
+	assert_eq!(summary, ImportSummary {
+		imported: 0,
+		skipped: 4
+	});
+}
+
+pub async fn list_redirects(store: &impl StoreBackend) {
+	let ids = [
+		Id::from([0x1b, 0x2b, 0x3b, 0x4b, 0x5b]),
+		Id::from([0x1c, 0x2c, 0x3c, 0x4c, 0x5c]),
+		Id::from([0x1d, 0x2d, 0x3d, 0x4d, 0x5d]),
+	];
+	let link = Link::new("https://example.com/list").unwrap();
+
+	for id in ids {
+		store.set_redirect(id, link.clone()).await.unwrap();
+	}
+
+	let mut listed = Vec::new();
+	let mut cursor = None;
+	loop {
+		let page = store.list_redirects(cursor, 2).await.unwrap();
+		assert!(page.items.len() <= 2);
+		listed.extend(page.items);
+
+		cursor = page.next;
+		if cursor.is_none() {
+			break;
+		}
+	}
+
+	let keys = listed
+		.iter()
+		.map(|(id, _)| id.to_string())
+		.collect::<Vec<_>>();
+	assert!(keys.windows(2).all(|w| w[0] < w[1]));
+	for id in ids {
+		assert!(listed.contains(&(id, link.clone())));
+	}
+
+	// Listing starts after the cursor
+	let page = store.list_redirects(Some(ids[0]), 1).await.unwrap();
+	assert!(page
+		.items
+		.iter()
+		.all(|(id, _)| id.to_string() > ids[0].to_string()));
+}
+
+pub async fn list_vanities(store: &impl StoreBackend) {
+	let id = Id::from([0x1e, 0x2e, 0x3e, 0x4e, 0x5e]);
+	let vanities = [
+		Normalized::new("List Test A"),
+		Normalized::new("List Test B"),
+		Normalized::new("List Test C"),
+	];
+
+	for vanity in &vanities {
+		store.set_vanity(vanity.clone(), id).await.unwrap();
+	}
+
+	let mut listed = Vec::new();
+	let mut cursor = None;
+	loop {
+		let page = store.list_vanities(cursor, 2).await.unwrap();
+		assert!(page.items.len() <= 2);
+		listed.extend(page.items);
+
+		cursor = page.next;
+		if cursor.is_none() {
+			break;
+		}
+	}
+
+	assert!(listed.windows(2).all(|w| w[0].0.as_str() < w[1].0.as_str()));
+	for vanity in &vanities {
+		assert!(listed.contains(&(vanity.clone(), id)));
+	}
+
+	// Listing starts after the cursor
+	let page = store
+		.list_vanities(Some(vanities[0].clone()), 1)
+		.await
+		.unwrap();
+	assert!(page
+		.items
+		.iter()
+		.all(|(vanity, _)| vanity.as_str() > vanities[0].as_str()));
+}
+
+pub async fn health(store: &impl StoreBackend) {
+	let health = store.health().await;
+
+	assert!(health.healthy, "{:?}", health.error);
+	assert_eq!(health.error, None);
+	assert!(health.latency < crate::store::backend::HEALTH_CHECK_TIMEOUT);
+}
+
+pub async fn namespaced(store: &impl StoreBackend) {
+	let id = Id::from([0x26, 0x36, 0x46, 0x56, 0x66]);
+	let vanity = Normalized::new("namespaced");
+	let link_a = Link::new("https://example.com/namespace/a").unwrap();
+	let link_b = Link::new("https://example.com/namespace/b").unwrap();
+
+	let a = store.namespaced("test-a").await.unwrap();
+	let b = store.namespaced("test-b").await.unwrap();
+
+	a.set_redirect(id, link_a.clone()).await.unwrap();
+	b.set_redirect(id, link_b.clone()).await.unwrap();
+	a.set_vanity(vanity.clone(), id).await.unwrap();
+	a.set_options(id, test_options("*")).await.unwrap();
+	a.incr_statistic(Statistic {
+		link: id.into(),
+		stat_type: StatisticType::Request,
+		data: StatisticData::default(),
+		time: StatisticTime::now(),
+	})
+	.await
+	.unwrap();
+
+	// Namespaces are separate from each other and from the store itself
+	assert_eq!(store.get_redirect(id).await.unwrap(), None);
+	assert_eq!(a.get_redirect(id).await.unwrap(), Some(link_a.clone()));
+	assert_eq!(b.get_redirect(id).await.unwrap(), Some(link_b));
+	assert_eq!(store.get_vanity(vanity.clone()).await.unwrap(), None);
+	assert_eq!(b.get_vanity(vanity.clone()).await.unwrap(), None);
+	assert_eq!(a.get_vanity(vanity.clone()).await.unwrap(), Some(id));
+	assert_eq!(store.get_options(id).await.unwrap(), None);
+	assert_eq!(b.get_options(id).await.unwrap(), None);
+	assert!(store
+		.get_statistics(StatisticDescription {
+			link: Some(id.into()),
+			..Default::default()
+		})
+		.await
+		.unwrap()
+		.is_empty());
+
+	// The same namespace always has the same data
+	let a = store.namespaced("test-a").await.unwrap();
+	assert_eq!(a.get_redirect(id).await.unwrap(), Some(link_a));
+
+	a.rem_redirect(id).await.unwrap();
+	a.rem_vanity(vanity).await.unwrap();
+	a.rem_options(id).await.unwrap();
+	a.rem_statistics(StatisticDescription {
+		link: Some(id.into()),
+		..Default::default()
+	})
+	.await
+	.unwrap();
+	b.rem_redirect(id).await.unwrap();
+}
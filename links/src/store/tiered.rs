@@ -0,0 +1,923 @@
+//! A tiered [`StoreBackend`], which keeps an in-memory replica of any other
+//! (remote) store backend, so that redirects keep working during transient
+//! outages of the remote store (e.g. a Redis server restarting).
+//!
+//! All reads of redirects, vanity paths, and options are served from memory.
+//! Values not yet in memory are read from the remote store once, and are then
+//! kept up to date by periodically re-reading them from the remote store.
+//! Writes are applied to the in-memory replica immediately, and are then
+//! written to the remote store asynchronously, in order, retrying for as long
+//! as the remote store is unavailable.
+//!
+//! Expiring redirects set through the tiered store expire in memory at the same
+//! time as in the remote store, but the expiry of redirects set in any other
+//! way is only seen after the next refresh.
+//!
+//! Statistics are not replicated, and are always read from and written to the
+//! remote store directly. Exports and listings are also read from the remote
+//! store, after waiting for pending writes to be written to it (for a limited
+//! time, so they may be missing recent writes if the remote store is
+//! unavailable). Namespaces of the remote store are not replicated either.
+
+use std::{
+	collections::HashMap,
+	future::Future,
+	hash::Hash,
+	sync::{
+		atomic::{AtomicU64, AtomicUsize, Ordering},
+		Arc, Weak,
+	},
+	time::Duration,
+};
+
+use ::time::OffsetDateTime;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use parking_lot::RwLock;
+use tokio::{
+	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+	time,
+};
+use tracing::{debug, instrument, warn};
+
+use crate::{
+	options::LinkOptions,
+	stats::{Statistic, StatisticDescription, StatisticValue},
+	store::{
+		backend::{ExportStream, Health, Page},
+		BackendType, StoreBackend,
+	},
+};
+
+/// How often values are re-read from the remote store if not configured (in
+/// seconds)
+const DEFAULT_REFRESH: u64 = 30;
+
+/// The delay before the first retry of a failed write to the remote store
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay between retries of a failed write to the remote store
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long to wait for pending writes to be written to the remote store
+/// before exporting
+const EXPORT_FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for pending writes to be written to the remote store
+/// before operations using the remote store directly (e.g. listing redirects)
+const LIST_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often to check for pending writes while waiting for them
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A [`StoreBackend`] wrapping another (remote) backend `B`, serving reads from
+/// an in-memory replica and writing to the remote store asynchronously.
+///
+/// # Configuration
+///
+/// The tiered store is configured together with the remote backend, using the
+/// following options in addition to the backend's own:
+/// - `tiered`: Enable the tiered store. *`true` / `false`*. **Default
+///   `false`**.
+/// - `tiered_refresh`: How often all values in memory are re-read from the
+///   remote store (in seconds), so that changes made by other links instances
+///   are seen. `0` disables refreshing. **Default `30`**.
+///
+/// If the tiered store is enabled, the read cache options (see
+/// [`Cached`][crate::store::Cached]) are ignored.
+#[derive(Debug)]
+pub struct Tiered<B> {
+	replica: Arc<Replica<B>>,
+	writes: UnboundedSender<Write>,
+}
+
+/// The in-memory replica of the remote store
+#[derive(Debug)]
+struct Replica<B> {
+	remote: B,
+	redirects: RwLock<HashMap<Id, Option<Link>>>,
+	vanity: RwLock<HashMap<Normalized, Option<Id>>>,
+	options: RwLock<HashMap<Id, Option<LinkOptions>>>,
+	/// The expiry times of expiring redirects set through this store
+	expiry: RwLock<HashMap<Id, OffsetDateTime>>,
+	/// The number of writes not yet written to the remote store
+	pending: AtomicUsize,
+	/// The total number of writes made, to detect writes during a refresh
+	writes: AtomicU64,
+}
+
+/// A write to the remote store, with `None` values meaning removal
+#[derive(Debug, Clone)]
+enum Write {
+	Redirect(Id, Option<Link>),
+	ExpiringRedirect(Id, Link, OffsetDateTime),
+	SoftRemoval(Id, OffsetDateTime),
+	Vanity(Normalized, Option<Id>),
+	Options(Id, Option<LinkOptions>),
+}
+
+impl<B: StoreBackend> Tiered<B> {
+	/// Check whether the `config` enables the tiered store
+	///
+	/// # Errors
+	/// Returns an error if `tiered` is not a valid boolean
+	pub fn is_enabled(config: &HashMap<String, String>) -> Result<bool> {
+		Ok(config.get("tiered").map_or(Ok(false), |s| s.parse())?)
+	}
+
+	/// Replicate the `remote` store in memory, re-reading values from it every
+	/// `refresh` (if not zero)
+	pub fn with_backend(remote: B, refresh: Duration) -> Self
+	where
+		B: 'static,
+	{
+		let replica = Arc::new(Replica {
+			remote,
+			redirects: RwLock::default(),
+			vanity: RwLock::default(),
+			options: RwLock::default(),
+			expiry: RwLock::default(),
+			pending: AtomicUsize::new(0),
+			writes: AtomicU64::new(0),
+		});
+
+		let (writes, queue) = mpsc::unbounded_channel();
+		tokio::spawn(sync(Arc::clone(&replica), queue));
+
+		if !refresh.is_zero() {
+			tokio::spawn(refresh_periodically(Arc::downgrade(&replica), refresh));
+		}
+
+		Self { replica, writes }
+	}
+
+	/// Queue the `write` to be written to the remote store
+	fn queue(&self, write: Write) {
+		self.replica.pending.fetch_add(1, Ordering::AcqRel);
+		self.replica.writes.fetch_add(1, Ordering::AcqRel);
+
+		if self.writes.send(write).is_err() {
+			self.replica.pending.fetch_sub(1, Ordering::AcqRel);
+			warn!("Remote store sync stopped, write not replicated");
+		}
+	}
+}
+
+impl<B: StoreBackend> Replica<B> {
+	/// Get the value of the `key` from `map`, reading it from the remote store
+	/// using `read` if it is not yet known
+	async fn get<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send + Sync>(
+		map: &RwLock<HashMap<K, Option<V>>>,
+		key: K,
+		read: impl Future<Output = Result<Option<V>>> + Send,
+	) -> Result<Option<V>> {
+		if let Some(value) = map.read().get(&key) {
+			return Ok(value.clone());
+		}
+
+		let value = read.await?;
+		Ok(map.write().entry(key).or_insert(value).clone())
+	}
+
+	/// Get the values of all `keys` from `map`, reading the ones not yet known
+	/// from the remote store using `read` (all at once, in the same order)
+	async fn get_many<
+		K: Hash + Eq + Clone + Send + Sync,
+		V: Clone + Send + Sync,
+		F: Future<Output = Result<Vec<Option<V>>>> + Send,
+	>(
+		map: &RwLock<HashMap<K, Option<V>>>,
+		keys: &[K],
+		read: impl FnOnce(Vec<K>) -> F + Send,
+	) -> Result<Vec<Option<V>>> {
+		let known = {
+			let map = map.read();
+			keys.iter()
+				.map(|key| map.get(key).cloned())
+				.collect::<Vec<_>>()
+		};
+		let missing = keys
+			.iter()
+			.zip(&known)
+			.filter(|(_, known)| known.is_none())
+			.map(|(key, _)| key.clone())
+			.collect::<Vec<_>>();
+
+		let values = if missing.is_empty() {
+			Vec::new()
+		} else {
+			read(missing.clone()).await?
+		};
+
+		if values.len() != missing.len() {
+			bail!("the remote store returned the wrong number of values");
+		}
+
+		let read = {
+			let mut map = map.write();
+			missing
+				.into_iter()
+				.zip(values)
+				.map(|(key, value)| map.entry(key).or_insert(value).clone())
+				.collect::<Vec<_>>()
+		};
+
+		let mut read = read.into_iter();
+		Ok(known
+			.into_iter()
+			.map(|known| known.unwrap_or_else(|| read.next().flatten()))
+			.collect())
+	}
+
+	/// Set the `key` to the `value` in `map`, returning the old value, which is
+	/// read from the remote store using `read` if it is not yet known (or
+	/// assumed to be `None` if that fails)
+	async fn set<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send + Sync>(
+		map: &RwLock<HashMap<K, Option<V>>>,
+		key: K,
+		value: Option<V>,
+		read: impl Future<Output = Result<Option<V>>> + Send,
+	) -> Option<V> {
+		let known = map.read().get(&key).cloned();
+		let old = match known {
+			Some(old) => old,
+			None => read.await.unwrap_or_else(|err| {
+				debug!(?err, "Could not read old value from remote store");
+				None
+			}),
+		};
+
+		map.write().insert(key, value).unwrap_or(old)
+	}
+
+	/// Wait up to `timeout` for all pending writes to be written to the remote
+	/// store. Returns whether all writes were written.
+	async fn flush(&self, timeout: Duration) -> bool {
+		time::timeout(timeout, async {
+			while self.pending.load(Ordering::Acquire) != 0 {
+				time::sleep(FLUSH_POLL_INTERVAL).await;
+			}
+		})
+		.await
+		.is_ok()
+	}
+
+	/// Set the expiry time of the redirect `id` to `expires_at` (or remove it),
+	/// returning whether the previous expiry time has passed
+	fn set_expiry(&self, id: Id, expires_at: Option<OffsetDateTime>) -> bool {
+		let old = expires_at.map_or_else(
+			|| self.expiry.write().remove(&id),
+			|expires_at| self.expiry.write().insert(id, expires_at),
+		);
+
+		old.is_some_and(|old| old <= OffsetDateTime::now_utc())
+	}
+
+	/// Write the `write` to the remote store
+	async fn write(&self, write: Write) -> Result<()> {
+		match write {
+			Write::Redirect(id, Some(link)) => self.remote.set_redirect(id, link).await.map(drop),
+			Write::Redirect(id, None) => self.remote.rem_redirect(id).await.map(drop),
+			Write::ExpiringRedirect(id, link, expires_at) => self
+				.remote
+				.set_redirect_with_expiry(id, link, expires_at)
+				.await
+				.map(drop),
+			Write::SoftRemoval(id, keep_until) => self
+				.remote
+				.rem_redirect_soft(id, keep_until)
+				.await
+				.map(drop),
+			Write::Vanity(vanity, Some(id)) => self.remote.set_vanity(vanity, id).await.map(drop),
+			Write::Vanity(vanity, None) => self.remote.rem_vanity(vanity).await.map(drop),
+			Write::Options(id, Some(options)) => {
+				self.remote.set_options(id, options).await.map(drop)
+			}
+			Write::Options(id, None) => self.remote.rem_options(id).await.map(drop),
+		}
+	}
+
+	/// Re-read all known values from the remote store, unless there are
+	/// pending writes or writes were made during the refresh (which would be
+	/// overwritten by outdated values)
+	async fn refresh(&self) -> Result<()> {
+		let writes = self.writes.load(Ordering::Acquire);
+		let redirects = self.redirects.read().keys().copied().collect::<Vec<_>>();
+		let vanity = self.vanity.read().keys().cloned().collect::<Vec<_>>();
+		let options = self.options.read().keys().copied().collect::<Vec<_>>();
+
+		let mut new_redirects = HashMap::with_capacity(redirects.len());
+		for id in redirects {
+			new_redirects.insert(id, self.remote.get_redirect(id).await?);
+		}
+
+		let mut new_vanity = HashMap::with_capacity(vanity.len());
+		for vanity in vanity {
+			let id = self.remote.get_vanity(vanity.clone()).await?;
+			new_vanity.insert(vanity, id);
+		}
+
+		let mut new_options = HashMap::with_capacity(options.len());
+		for id in options {
+			new_options.insert(id, self.remote.get_options(id).await?);
+		}
+
+		if self.pending.load(Ordering::Acquire) == 0
+			&& self.writes.load(Ordering::Acquire) == writes
+		{
+			self.redirects.write().extend(new_redirects);
+			self.vanity.write().extend(new_vanity);
+			self.options.write().extend(new_options);
+		}
+
+		Ok(())
+	}
+}
+
+/// Write all queued writes to the remote store in order, retrying each one
+/// until it succeeds. This returns once the [`Tiered`] store is dropped and
+/// all queued writes are written.
+async fn sync<B: StoreBackend>(replica: Arc<Replica<B>>, mut queue: UnboundedReceiver<Write>) {
+	while let Some(write) = queue.recv().await {
+		let mut delay = MIN_RETRY_DELAY;
+
+		while let Err(err) = replica.write(write.clone()).await {
+			warn!(
+				?err,
+				?write,
+				"Writing to remote store failed, retrying in {delay:?}"
+			);
+			time::sleep(delay).await;
+			delay = (delay * 2).min(MAX_RETRY_DELAY);
+		}
+
+		replica.pending.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+/// Refresh the `replica` every `interval`, until it is dropped
+async fn refresh_periodically<B: StoreBackend>(replica: Weak<Replica<B>>, interval: Duration) {
+	loop {
+		time::sleep(interval).await;
+
+		let Some(replica) = replica.upgrade() else {
+			return;
+		};
+
+		if let Err(err) = replica.refresh().await {
+			debug!(?err, "Refreshing from remote store failed");
+		}
+	}
+}
+
+#[async_trait]
+impl<B: StoreBackend + 'static> StoreBackend for Tiered<B> {
+	fn store_type() -> BackendType
+	where
+		Self: Sized,
+	{
+		B::store_type()
+	}
+
+	fn get_store_type(&self) -> BackendType {
+		self.replica.remote.get_store_type()
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn new(config: &HashMap<String, String>) -> Result<Self> {
+		let refresh = config
+			.get("tiered_refresh")
+			.map_or(Ok(DEFAULT_REFRESH), |s| s.parse())?;
+
+		Ok(Self::with_backend(
+			B::new(config).await?,
+			Duration::from_secs(refresh),
+		))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let replica = &self.replica;
+
+		let now = OffsetDateTime::now_utc();
+		let expired = replica
+			.expiry
+			.read()
+			.get(&from)
+			.is_some_and(|&expires_at| expires_at <= now);
+
+		if expired {
+			// The remote store removes the redirect itself, so just forget it
+			replica.expiry.write().remove(&from);
+			replica.redirects.write().insert(from, None);
+			return Ok(None);
+		}
+
+		Replica::<B>::get(&replica.redirects, from, replica.remote.get_redirect(from)).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_redirects(&self, from: &[Id]) -> Result<Vec<Option<Link>>> {
+		let replica = &self.replica;
+		let links = Replica::<B>::get_many(&replica.redirects, from, |missing| async move {
+			replica.remote.get_redirects(&missing).await
+		})
+		.await?;
+
+		let now = OffsetDateTime::now_utc();
+		let expiry = replica.expiry.read();
+		Ok(from
+			.iter()
+			.zip(links)
+			.map(|(id, link)| {
+				link.filter(|_| !expiry.get(id).is_some_and(|&expires_at| expires_at <= now))
+			})
+			.collect())
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect(&self, from: Id, to: Link) -> Result<Option<Link>> {
+		let replica = &self.replica;
+		let expired = replica.set_expiry(from, None);
+		let old = Replica::<B>::set(
+			&replica.redirects,
+			from,
+			Some(to.clone()),
+			replica.remote.get_redirect(from),
+		)
+		.await;
+
+		self.queue(Write::Redirect(from, Some(to)));
+		Ok(old.filter(|_| !expired))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_redirect_with_expiry(
+		&self,
+		from: Id,
+		to: Link,
+		expires_at: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let replica = &self.replica;
+		let expired = replica.set_expiry(from, Some(expires_at));
+		let old = Replica::<B>::set(
+			&replica.redirects,
+			from,
+			Some(to.clone()),
+			replica.remote.get_redirect(from),
+		)
+		.await;
+
+		self.queue(Write::ExpiringRedirect(from, to, expires_at));
+		Ok(old.filter(|_| !expired))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let replica = &self.replica;
+		let expired = replica.set_expiry(from, None);
+		let old = Replica::<B>::set(
+			&replica.redirects,
+			from,
+			None,
+			replica.remote.get_redirect(from),
+		)
+		.await;
+
+		self.queue(Write::Redirect(from, None));
+		Ok(old.filter(|_| !expired))
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_redirect_soft(
+		&self,
+		from: Id,
+		keep_until: OffsetDateTime,
+	) -> Result<Option<Link>> {
+		let replica = &self.replica;
+		let expired = replica.set_expiry(from, None);
+		let old = Replica::<B>::set(
+			&replica.redirects,
+			from,
+			None,
+			replica.remote.get_redirect(from),
+		)
+		.await;
+
+		self.queue(Write::SoftRemoval(from, keep_until));
+		Ok(old.filter(|_| !expired))
+	}
+
+	/// Restores the redirect in the remote store directly (after waiting
+	/// briefly for pending writes to reach it), because tombstones are not
+	/// replicated
+	#[instrument(level = "trace", ret, err)]
+	async fn restore_redirect(&self, from: Id) -> Result<Option<Link>> {
+		let replica = &self.replica;
+
+		if !replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Restoring before all writes were written to the remote store");
+		}
+
+		let restored = replica.remote.restore_redirect(from).await?;
+
+		if let Some(ref link) = restored {
+			replica.writes.fetch_add(1, Ordering::AcqRel);
+			replica.set_expiry(from, None);
+			replica.redirects.write().insert(from, Some(link.clone()));
+		}
+
+		Ok(restored)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn purge_redirects(&self) -> Result<usize> {
+		if !self.replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Purging before all writes were written to the remote store");
+		}
+
+		self.replica.remote.purge_redirects().await
+	}
+
+	/// Uses the remote store, after waiting (briefly) for pending writes to
+	/// reach it
+	#[instrument(level = "trace", ret, err)]
+	async fn find_by_link(&self, link: &Link) -> Result<Vec<Id>> {
+		if !self.replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Searching before all writes were written to the remote store");
+		}
+
+		self.replica.remote.find_by_link(link).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		let replica = &self.replica;
+		Replica::<B>::get(
+			&replica.vanity,
+			from.clone(),
+			replica.remote.get_vanity(from),
+		)
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities(&self, from: &[Normalized]) -> Result<Vec<Option<Id>>> {
+		let replica = &self.replica;
+		Replica::<B>::get_many(&replica.vanity, from, |missing| async move {
+			replica.remote.get_vanities(&missing).await
+		})
+		.await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_vanity(&self, from: Normalized, to: Id) -> Result<Option<Id>> {
+		let replica = &self.replica;
+		let old = Replica::<B>::set(
+			&replica.vanity,
+			from.clone(),
+			Some(to),
+			replica.remote.get_vanity(from.clone()),
+		)
+		.await;
+
+		self.queue(Write::Vanity(from, Some(to)));
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_vanity(&self, from: Normalized) -> Result<Option<Id>> {
+		let replica = &self.replica;
+		let old = Replica::<B>::set(
+			&replica.vanity,
+			from.clone(),
+			None,
+			replica.remote.get_vanity(from.clone()),
+		)
+		.await;
+
+		self.queue(Write::Vanity(from, None));
+		Ok(old)
+	}
+
+	/// Uses the remote store, like `find_by_link`
+	#[instrument(level = "trace", ret, err)]
+	async fn get_vanities_of(&self, id: Id) -> Result<Vec<Normalized>> {
+		if !self.replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Searching before all writes were written to the remote store");
+		}
+
+		self.replica.remote.get_vanities_of(id).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn get_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let replica = &self.replica;
+		Replica::<B>::get(&replica.options, id, replica.remote.get_options(id)).await
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn set_options(&self, id: Id, options: LinkOptions) -> Result<Option<LinkOptions>> {
+		let replica = &self.replica;
+		let old = Replica::<B>::set(
+			&replica.options,
+			id,
+			Some(options.clone()),
+			replica.remote.get_options(id),
+		)
+		.await;
+
+		self.queue(Write::Options(id, Some(options)));
+		Ok(old)
+	}
+
+	#[instrument(level = "trace", ret, err)]
+	async fn rem_options(&self, id: Id) -> Result<Option<LinkOptions>> {
+		let replica = &self.replica;
+		let old =
+			Replica::<B>::set(&replica.options, id, None, replica.remote.get_options(id)).await;
+
+		self.queue(Write::Options(id, None));
+		Ok(old)
+	}
+
+	async fn get_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		self.replica.remote.get_statistics(description).await
+	}
+
+	async fn get_statistics_page(
+		&self,
+		description: StatisticDescription,
+		cursor: Option<Statistic>,
+		limit: usize,
+	) -> Result<Page<Statistic, StatisticValue>> {
+		self.replica
+			.remote
+			.get_statistics_page(description, cursor, limit)
+			.await
+	}
+
+	async fn incr_statistic(&self, statistic: Statistic) -> Result<Option<StatisticValue>> {
+		self.replica.remote.incr_statistic(statistic).await
+	}
+
+	async fn rem_statistics(
+		&self,
+		description: StatisticDescription,
+	) -> Result<Vec<(Statistic, StatisticValue)>> {
+		self.replica.remote.rem_statistics(description).await
+	}
+
+	async fn incr_usage(&self, key: &str, amount: u64, expires_at: OffsetDateTime) -> Result<u64> {
+		self.replica
+			.remote
+			.incr_usage(key, amount, expires_at)
+			.await
+	}
+
+	fn supports_usage(&self) -> bool {
+		self.replica.remote.supports_usage()
+	}
+
+	/// Checks the health of the remote store, because reads served from memory
+	/// always succeed
+	async fn health(&self) -> Health {
+		self.replica.remote.health().await
+	}
+
+	/// Namespaced stores are not replicated, and use the remote store directly
+	async fn namespaced(&self, namespace: &str) -> Result<Arc<dyn StoreBackend>> {
+		self.replica.remote.namespaced(namespace).await
+	}
+
+	async fn export_all(&self) -> Result<ExportStream<'_>> {
+		if !self.replica.flush(EXPORT_FLUSH_TIMEOUT).await {
+			warn!("Exporting before all writes were written to the remote store");
+		}
+
+		self.replica.remote.export_all().await
+	}
+
+	async fn list_redirects(&self, cursor: Option<Id>, limit: usize) -> Result<Page<Id, Link>> {
+		if !self.replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Listing before all writes were written to the remote store");
+		}
+
+		self.replica.remote.list_redirects(cursor, limit).await
+	}
+
+	async fn list_vanities(
+		&self,
+		cursor: Option<Normalized>,
+		limit: usize,
+	) -> Result<Page<Normalized, Id>> {
+		if !self.replica.flush(LIST_FLUSH_TIMEOUT).await {
+			debug!("Listing before all writes were written to the remote store");
+		}
+
+		self.replica.remote.list_vanities(cursor, limit).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::store::{tests, Memory};
+
+	async fn get_store() -> Tiered<Memory> {
+		Tiered::new(&HashMap::from([("tiered".to_string(), "true".to_string())]))
+			.await
+			.unwrap()
+	}
+
+	/// Wait until all pending writes are written to the remote store
+	async fn flush<B: StoreBackend>(store: &Tiered<B>) {
+		assert!(store.replica.flush(Duration::from_secs(10)).await);
+	}
+
+	#[test]
+	fn store_type() {
+		tests::store_type::<Tiered<Memory>>();
+	}
+
+	#[tokio::test]
+	async fn get_store_type() {
+		tests::get_store_type::<Tiered<Memory>>(&get_store().await);
+	}
+
+	#[tokio::test]
+	async fn get_redirect() {
+		tests::get_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_redirects() {
+		tests::get_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect() {
+		tests::set_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirects() {
+		tests::set_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirect() {
+		tests::rem_redirect(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_redirects() {
+		tests::rem_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn soft_delete() {
+		tests::soft_delete(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_redirect_with_expiry() {
+		tests::set_redirect_with_expiry(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn find_by_link() {
+		tests::find_by_link(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanity() {
+		tests::get_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities() {
+		tests::get_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanity() {
+		tests::set_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_vanities() {
+		tests::set_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_vanity() {
+		tests::rem_vanity(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_vanities_of() {
+		tests::get_vanities_of(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_options() {
+		tests::get_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn set_options() {
+		tests::set_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_options() {
+		tests::rem_options(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics() {
+		tests::get_statistics(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn get_statistics_page() {
+		tests::get_statistics_page(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn incr_statistic() {
+		tests::incr_statistic(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn rem_statistics() {
+		tests::rem_statistics(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn incr_usage() {
+		tests::incr_usage(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn export_all() {
+		tests::export_all(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn import() {
+		tests::import(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_redirects() {
+		tests::list_redirects(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn list_vanities() {
+		tests::list_vanities(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn health() {
+		tests::health(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn namespaced() {
+		tests::namespaced(&get_store().await).await;
+	}
+
+	#[tokio::test]
+	async fn replication() {
+		let store = get_store().await;
+		let id = Id::from([0x14, 0x24, 0x34, 0x44, 0x54]);
+		let link = Link::new("https://example.com/tiered").unwrap();
+		let remote = &store.replica.remote;
+
+		// Writes are replicated to the remote store
+		store.set_redirect(id, link.clone()).await.unwrap();
+		flush(&store).await;
+		assert_eq!(remote.get_redirect(id).await.unwrap(), Some(link.clone()));
+
+		// Reads are served from memory, even if the remote store changes
+		remote.rem_redirect(id).await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), Some(link.clone()));
+
+		// Until the value is refreshed
+		store.replica.refresh().await.unwrap();
+		assert_eq!(store.get_redirect(id).await.unwrap(), None);
+
+		// Unknown values are read from the remote store
+		let other = Id::from([0x15, 0x25, 0x35, 0x45, 0x55]);
+		remote.set_redirect(other, link.clone()).await.unwrap();
+		assert_eq!(store.get_redirect(other).await.unwrap(), Some(link));
+	}
+}
@@ -0,0 +1,280 @@
+//! Distributed tracing context propagation using [W3C Trace Context] headers
+//!
+//! The redirector parses the `traceparent` and `tracestate` headers of
+//! incoming requests into a [`TraceContext`], and attaches its IDs to the
+//! request's tracing span, so that links' logs can be correlated with the rest
+//! of a distributed trace. Outbound requests made on behalf of an incoming
+//! request can continue the same trace using [`TraceContext::child`] and
+//! [`TraceContext::insert_into`].
+//!
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+
+use std::fmt::{Display, Formatter, Result as FmtResult, Write};
+
+use hyper::{header::HeaderValue, HeaderMap};
+
+/// The name of the header containing the trace and parent IDs
+pub const TRACEPARENT: &str = "traceparent";
+
+/// The name of the header containing vendor-specific trace information
+pub const TRACESTATE: &str = "tracestate";
+
+/// The maximum length of a propagated `tracestate` value
+const MAX_TRACESTATE_LEN: usize = 512;
+
+/// The trace context of a request, as specified by the `traceparent` and
+/// `tracestate` headers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+	/// The ID of the whole trace
+	pub trace_id: [u8; 16],
+	/// The ID of the caller's span (the parent of any spans created here)
+	pub parent_id: [u8; 8],
+	/// Trace flags, of which only the lowest bit (sampled) is currently defined
+	pub flags: u8,
+	/// The vendor-specific trace state, which is propagated unchanged
+	pub state: Option<String>,
+}
+
+impl TraceContext {
+	/// The `sampled` trace flag
+	pub const SAMPLED: u8 = 0x01;
+
+	/// Parse the trace context from the `traceparent` and `tracestate`
+	/// `headers`, returning `None` if there is no valid `traceparent` header.
+	/// Multiple `tracestate` headers are combined, and an invalid or overly
+	/// long `tracestate` is ignored.
+	#[must_use]
+	pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+		let mut traceparent = headers.get_all(TRACEPARENT).iter();
+		let (Some(traceparent), None) = (traceparent.next(), traceparent.next()) else {
+			return None;
+		};
+
+		let mut context = Self::parse_traceparent(traceparent.to_str().ok()?)?;
+
+		let state = headers
+			.get_all(TRACESTATE)
+			.iter()
+			.map(HeaderValue::to_str)
+			.collect::<Result<Vec<_>, _>>()
+			.ok()
+			.map(|values| values.join(","))
+			.filter(|state| {
+				!state.trim().is_empty()
+					&& state.len() <= MAX_TRACESTATE_LEN
+					&& state.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+			});
+
+		context.state = state;
+		Some(context)
+	}
+
+	/// Parse a `traceparent` header value, returning `None` if it is invalid
+	///
+	/// Values with a version other than `00` are accepted as long as they
+	/// start with the fields defined in version `00`, as the specification
+	/// requires.
+	#[must_use]
+	pub fn parse_traceparent(value: &str) -> Option<Self> {
+		let value = value.trim();
+		let mut fields = value.split('-');
+		let (version, trace_id, parent_id, flags) = (
+			fields.next()?,
+			fields.next()?,
+			fields.next()?,
+			fields.next()?,
+		);
+
+		let [version] = parse_hex::<1>(version)?;
+		if version == 0xff || (version == 0 && fields.next().is_some()) {
+			return None;
+		}
+
+		let trace_id = parse_hex::<16>(trace_id)?;
+		let parent_id = parse_hex::<8>(parent_id)?;
+		let [flags] = parse_hex::<1>(flags)?;
+
+		if trace_id == [0; 16] || parent_id == [0; 8] {
+			return None;
+		}
+
+		Some(Self {
+			trace_id,
+			parent_id,
+			flags,
+			state: None,
+		})
+	}
+
+	/// Whether the caller may have recorded this trace
+	#[must_use]
+	pub const fn is_sampled(&self) -> bool {
+		self.flags & Self::SAMPLED != 0
+	}
+
+	/// Get the trace ID as a lowercase hex string
+	#[must_use]
+	pub fn trace_id_hex(&self) -> String {
+		to_hex(&self.trace_id)
+	}
+
+	/// Get the parent span ID as a lowercase hex string
+	#[must_use]
+	pub fn parent_id_hex(&self) -> String {
+		to_hex(&self.parent_id)
+	}
+
+	/// Create the context for an outbound request continuing this trace, with
+	/// a new random span ID as the parent
+	#[must_use]
+	pub fn child(&self) -> Self {
+		let parent_id = loop {
+			let id = rand::random::<[u8; 8]>();
+			if id != [0; 8] {
+				break id;
+			}
+		};
+
+		Self {
+			parent_id,
+			..self.clone()
+		}
+	}
+
+	/// Insert the `traceparent` and `tracestate` headers for this context into
+	/// `headers` (e.g. of an outbound request), replacing any existing ones
+	pub fn insert_into(&self, headers: &mut HeaderMap) {
+		if let Ok(traceparent) = HeaderValue::try_from(self.to_string()) {
+			headers.insert(TRACEPARENT, traceparent);
+		}
+
+		headers.remove(TRACESTATE);
+		if let Some(state) = self
+			.state
+			.as_ref()
+			.and_then(|s| HeaderValue::try_from(s).ok())
+		{
+			headers.insert(TRACESTATE, state);
+		}
+	}
+}
+
+/// Formats this context as a (version `00`) `traceparent` header value
+impl Display for TraceContext {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		write!(
+			f,
+			"00-{}-{}-{:02x}",
+			self.trace_id_hex(),
+			self.parent_id_hex(),
+			self.flags
+		)
+	}
+}
+
+/// Parse exactly `N` bytes from lowercase hex
+fn parse_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+	if hex.len() != N * 2 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+		return None;
+	}
+
+	let mut bytes = [0; N];
+	for (i, byte) in bytes.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+	}
+
+	Some(bytes)
+}
+
+/// Format `bytes` as lowercase hex
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+			let _ = write!(hex, "{b:02x}");
+			hex
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TRACEPARENT_EXAMPLE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+	#[test]
+	fn parse_traceparent() {
+		let context = TraceContext::parse_traceparent(TRACEPARENT_EXAMPLE).unwrap();
+		assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+		assert_eq!(context.parent_id_hex(), "00f067aa0ba902b7");
+		assert!(context.is_sampled());
+		assert_eq!(context.to_string(), TRACEPARENT_EXAMPLE);
+
+		// Future versions may add fields
+		let context = TraceContext::parse_traceparent(
+			"cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-what-the-future-holds",
+		)
+		.unwrap();
+		assert!(!context.is_sampled());
+		assert_eq!(
+			context.to_string(),
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"
+		);
+
+		for invalid in [
+			"",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+			"ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+			"00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+			"00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902bx-01",
+			"0-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+		] {
+			assert_eq!(TraceContext::parse_traceparent(invalid), None, "{invalid}");
+		}
+	}
+
+	#[test]
+	fn from_headers() {
+		let mut headers = HeaderMap::new();
+		assert_eq!(TraceContext::from_headers(&headers), None);
+
+		headers.insert(TRACEPARENT, HeaderValue::from_static(TRACEPARENT_EXAMPLE));
+		headers.append(
+			TRACESTATE,
+			HeaderValue::from_static("rojo=00f067aa0ba902b7"),
+		);
+		headers.append(TRACESTATE, HeaderValue::from_static("congo=t61rcWkgMzE"));
+
+		let context = TraceContext::from_headers(&headers).unwrap();
+		assert_eq!(
+			context.state.as_deref(),
+			Some("rojo=00f067aa0ba902b7,congo=t61rcWkgMzE")
+		);
+
+		// Multiple `traceparent` headers are invalid
+		headers.append(TRACEPARENT, HeaderValue::from_static(TRACEPARENT_EXAMPLE));
+		assert_eq!(TraceContext::from_headers(&headers), None);
+	}
+
+	#[test]
+	fn propagate() {
+		let mut context = TraceContext::parse_traceparent(TRACEPARENT_EXAMPLE).unwrap();
+		context.state = Some("rojo=00f067aa0ba902b7".to_string());
+
+		let child = context.child();
+		assert_eq!(child.trace_id, context.trace_id);
+		assert_ne!(child.parent_id, context.parent_id);
+		assert_eq!(child.flags, context.flags);
+
+		let mut headers = HeaderMap::new();
+		headers.insert(TRACESTATE, HeaderValue::from_static("old=state"));
+		child.insert_into(&mut headers);
+
+		assert_eq!(TraceContext::from_headers(&headers), Some(child));
+	}
+}
@@ -93,10 +93,13 @@ EXAMPLE:
 
 FLAGS:
  -h --help                   Print this and exit
+    --dry-run                Only report what would be migrated with --migrate-to, without writing anything
     --example-redirect       Set an example redirect on server start ("example" -> "9dDbKpJP" -> "https://example.com/")
 
 OPTIONS:
  -c --config PATH            Configuration file path. Supported formats: toml (*.toml), yaml/json (*.yaml, *.yml, *.json)
+    --migrate-to PATH        Copy all data from the configured store to the store configured in the file at PATH (only its "store" and "store-config" options are used), then exit
+    --migrate-policy POLICY  What to do with data already in the store being migrated to: "skip" (default), "overwrite", or "error"
     --watcher-timeout MS     File watcher timeout in milliseconds, default 10000
     --watcher-debounce MS    File watcher debounce time in milliseconds, default 1000
 
@@ -0,0 +1,412 @@
+//! Webhooks notifying external systems of changes to links.
+//!
+//! Whenever a redirect or vanity path is created, updated, or removed via the
+//! [gRPC API][crate::api] or the [HTTP API][crate::http_api], a JSON payload
+//! describing the change is `POST`ed to every configured [`Webhook`]. The
+//! payload has an `event` (one of `redirect-created`, `redirect-updated`,
+//! `redirect-removed`, `vanity-created`, `vanity-updated`, and
+//! `vanity-removed`), the store `namespace` (or `null`), a unix `timestamp`
+//! (in seconds), and the event's details, e.g.:
+//!
+//! ```json
+//! {
+//!   "event": "redirect-updated",
+//!   "id": "9dDbKpJP",
+//!   "link": "https://example.com/new",
+//!   "previous": "https://example.com/old",
+//!   "namespace": null,
+//!   "timestamp": 1700000000
+//! }
+//! ```
+//!
+//! Each request is signed with the webhook's secret, with the lowercase hex
+//! HMAC-SHA256 of the request body sent in the `Links-Signature` header (as
+//! `sha256=[HMAC]`), so that receivers can check that the request came from
+//! links. The event type is also sent in the `Links-Event` header.
+//!
+//! Webhooks are delivered in the background, so they don't slow down API
+//! requests. Failed deliveries (network errors or non-2xx responses) are
+//! retried a few times before being given up on with a warning.
+
+use std::{
+	fmt::{Debug, Formatter, Result as FmtResult},
+	sync::LazyLock,
+	time::Duration,
+};
+
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use reqwest::{header::CONTENT_TYPE, Client};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::{spawn, time::sleep};
+use tracing::{debug, warn};
+
+use crate::{config::Config, trace::to_hex, util::SERVER_NAME};
+
+/// The name of the header containing the payload's signature
+const SIGNATURE_HEADER: &str = "links-signature";
+
+/// The name of the header containing the payload's event type
+const EVENT_HEADER: &str = "links-event";
+
+/// The number of attempts made to deliver each webhook
+const MAX_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a failed delivery, doubled after each
+/// further attempt
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The timeout of each webhook request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The HTTP client used to deliver all webhooks
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+	Client::builder()
+		.user_agent(SERVER_NAME)
+		.timeout(REQUEST_TIMEOUT)
+		.build()
+		.expect("the webhook HTTP client configuration is valid")
+});
+
+/// A webhook endpoint, to which changes to links are sent.
+///
+/// # Example
+/// ```toml
+/// { url = "https://example.com/links-webhook", secret = "random secret value" }
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+	/// The URL to `POST` payloads to
+	pub url: String,
+	/// The secret used to sign payloads, which should be long and random
+	pub secret: String,
+}
+
+impl Debug for Webhook {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+		fmt.debug_struct("Webhook")
+			.field("url", &self.url)
+			.field("secret", &"...")
+			.finish()
+	}
+}
+
+/// A change to a redirect or vanity path, as sent to webhooks
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum LinkChange {
+	/// A new redirect was created (or a soft-deleted one was restored)
+	RedirectCreated {
+		/// The redirect's ID
+		id: String,
+		/// The redirect's link
+		link: String,
+	},
+	/// An existing redirect's link was changed
+	RedirectUpdated {
+		/// The redirect's ID
+		id: String,
+		/// The redirect's new link
+		link: String,
+		/// The redirect's previous link
+		previous: String,
+	},
+	/// A redirect was removed
+	RedirectRemoved {
+		/// The removed redirect's ID
+		id: String,
+		/// The removed redirect's link
+		link: String,
+	},
+	/// A new vanity path was created
+	VanityCreated {
+		/// The vanity path
+		vanity: String,
+		/// The ID the vanity path points to
+		id: String,
+	},
+	/// An existing vanity path was changed to point to a different ID
+	VanityUpdated {
+		/// The vanity path
+		vanity: String,
+		/// The ID the vanity path now points to
+		id: String,
+		/// The ID the vanity path previously pointed to
+		previous: String,
+	},
+	/// A vanity path was removed
+	VanityRemoved {
+		/// The removed vanity path
+		vanity: String,
+		/// The ID the removed vanity path pointed to
+		id: String,
+	},
+}
+
+impl LinkChange {
+	/// Get the change of the redirect with the `id` being set to `link`, when
+	/// it previously had the link `previous`
+	#[must_use]
+	pub fn redirect_set(id: Id, link: &Link, previous: Option<Link>) -> Self {
+		let (id, link) = (id.to_string(), link.to_string());
+
+		match previous {
+			None => Self::RedirectCreated { id, link },
+			Some(previous) => Self::RedirectUpdated {
+				id,
+				link,
+				previous: previous.into_string(),
+			},
+		}
+	}
+
+	/// Get the change of the redirect with the `id` being removed, if it
+	/// existed (with the link `previous`)
+	#[must_use]
+	pub fn redirect_removed(id: Id, previous: Option<Link>) -> Option<Self> {
+		let link = previous?;
+
+		Some(Self::RedirectRemoved {
+			id: id.to_string(),
+			link: link.into_string(),
+		})
+	}
+
+	/// Get the change of the `vanity` path being set to `id`, when it
+	/// previously pointed to `previous`
+	#[must_use]
+	pub fn vanity_set(vanity: &Normalized, id: Id, previous: Option<Id>) -> Self {
+		let (vanity, id) = (vanity.to_string(), id.to_string());
+
+		match previous {
+			None => Self::VanityCreated { vanity, id },
+			Some(previous) => Self::VanityUpdated {
+				vanity,
+				id,
+				previous: previous.to_string(),
+			},
+		}
+	}
+
+	/// Get the change of the `vanity` path being removed, if it existed (and
+	/// pointed to `previous`)
+	#[must_use]
+	pub fn vanity_removed(vanity: &Normalized, previous: Option<Id>) -> Option<Self> {
+		let id = previous?;
+
+		Some(Self::VanityRemoved {
+			vanity: vanity.to_string(),
+			id: id.to_string(),
+		})
+	}
+
+	/// Get the name of this change's event type
+	#[must_use]
+	pub const fn event(&self) -> &'static str {
+		match self {
+			Self::RedirectCreated { .. } => "redirect-created",
+			Self::RedirectUpdated { .. } => "redirect-updated",
+			Self::RedirectRemoved { .. } => "redirect-removed",
+			Self::VanityCreated { .. } => "vanity-created",
+			Self::VanityUpdated { .. } => "vanity-updated",
+			Self::VanityRemoved { .. } => "vanity-removed",
+		}
+	}
+}
+
+/// A webhook payload, a [`LinkChange`] with some additional context
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+	#[serde(flatten)]
+	change: &'a LinkChange,
+	namespace: Option<&'a str>,
+	timestamp: i64,
+}
+
+/// Get the value of the signature header for the `body` signed with `secret`
+fn sign(secret: &str, body: &[u8]) -> String {
+	let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+	format!("sha256={}", to_hex(hmac::sign(&key, body).as_ref()))
+}
+
+/// Send the `changes` (made in the store `namespace`) to all webhooks
+/// configured in `config`, in the background.
+///
+/// This doesn't wait for the webhooks to be delivered, and does nothing if no
+/// webhooks are configured.
+pub fn notify(
+	config: &Config,
+	namespace: Option<&str>,
+	changes: impl IntoIterator<Item = LinkChange>,
+) {
+	let webhooks = config.webhooks();
+	if webhooks.is_empty() {
+		return;
+	}
+
+	let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+	for change in changes {
+		let payload = Payload {
+			change: &change,
+			namespace,
+			timestamp,
+		};
+
+		let body = match serde_json::to_vec(&payload) {
+			Ok(body) => body,
+			Err(err) => {
+				warn!(%err, "could not serialize webhook payload");
+				continue;
+			}
+		};
+
+		for webhook in &webhooks {
+			let signature = sign(&webhook.secret, &body);
+			spawn(deliver(
+				webhook.url.clone(),
+				change.event(),
+				signature,
+				body.clone(),
+			));
+		}
+	}
+}
+
+/// Deliver a webhook payload `body` for the `event` with the `signature` to
+/// the `url`, retrying failed attempts
+async fn deliver(url: String, event: &'static str, signature: String, body: Vec<u8>) {
+	let mut delay = RETRY_DELAY;
+
+	for attempt in 1..=MAX_ATTEMPTS {
+		let res = CLIENT
+			.post(&url)
+			.header(CONTENT_TYPE, "application/json")
+			.header(EVENT_HEADER, event)
+			.header(SIGNATURE_HEADER, &signature)
+			.body(body.clone())
+			.send()
+			.await
+			.and_then(reqwest::Response::error_for_status);
+
+		match res {
+			Ok(_) => {
+				debug!(%url, %event, "webhook delivered");
+				return;
+			}
+			Err(err) if attempt < MAX_ATTEMPTS => {
+				debug!(%url, %event, %err, attempt, "webhook delivery failed, retrying");
+				sleep(delay).await;
+				delay *= 2;
+			}
+			Err(err) => {
+				warn!(%url, %event, %err, "webhook delivery failed, giving up");
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+		time::timeout,
+	};
+
+	use super::*;
+
+	#[test]
+	fn change() {
+		let id = Id::try_from("9dDbKpJP").unwrap();
+		let link = Link::new("https://example.com/").unwrap();
+		let vanity = Normalized::new("example");
+
+		assert_eq!(
+			LinkChange::redirect_set(id, &link, None).event(),
+			"redirect-created"
+		);
+		assert_eq!(
+			LinkChange::redirect_set(id, &link, Some(link.clone())).event(),
+			"redirect-updated"
+		);
+		assert_eq!(LinkChange::redirect_removed(id, None), None);
+		assert_eq!(
+			LinkChange::vanity_set(&vanity, id, Some(id)).event(),
+			"vanity-updated"
+		);
+		assert_eq!(
+			LinkChange::vanity_removed(&vanity, Some(id)).map(|c| c.event()),
+			Some("vanity-removed")
+		);
+
+		let change = LinkChange::redirect_removed(id, Some(link)).unwrap();
+		let payload = serde_json::to_value(Payload {
+			change: &change,
+			namespace: Some("test"),
+			timestamp: 1_700_000_000,
+		})
+		.unwrap();
+
+		assert_eq!(
+			payload,
+			serde_json::json!({
+				"event": "redirect-removed",
+				"id": "9dDbKpJP",
+				"link": "https://example.com/",
+				"namespace": "test",
+				"timestamp": 1_700_000_000,
+			})
+		);
+	}
+
+	#[test]
+	fn signature() {
+		// From RFC 4231, test case 2
+		assert_eq!(
+			sign("Jefe", b"what do ya want for nothing?"),
+			"sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+		);
+	}
+
+	#[tokio::test]
+	async fn delivery() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+		spawn(deliver(
+			url,
+			"redirect-created",
+			sign("secret", b"{}"),
+			b"{}".to_vec(),
+		));
+
+		let (mut stream, _) = timeout(Duration::from_secs(5), listener.accept())
+			.await
+			.unwrap()
+			.unwrap();
+
+		let mut req = Vec::new();
+		while !req.ends_with(b"\r\n\r\n{}") {
+			let mut buf = [0; 1024];
+			let len = stream.read(&mut buf).await.unwrap();
+			assert_ne!(len, 0);
+			req.extend_from_slice(&buf[..len]);
+		}
+
+		stream
+			.write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+			.await
+			.unwrap();
+
+		let req = String::from_utf8(req).unwrap().to_lowercase();
+		assert!(req.starts_with("post /hook http/1.1\r\n"));
+		assert!(req.contains("\r\nlinks-event: redirect-created\r\n"));
+		assert!(req.contains(&format!(
+			"\r\nlinks-signature: {}\r\n",
+			sign("secret", b"{}")
+		)));
+	}
+}
@@ -3,6 +3,16 @@
 
 mod util;
 
+use std::{
+	io::{self, Read, Write},
+	net::{Ipv4Addr, TcpListener, TcpStream},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	thread,
+};
+
 use links_id::Id;
 
 /// Test `cli id` without TLS
@@ -106,6 +116,48 @@ async fn set() {
 	assert_re!(r#"^"06666666" ---> "https://example.com/other"$"#, res);
 }
 
+/// Test `cli set <ID> <URL> --expires-at <TIME>` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn set_expiry() {
+	let _terminator = util::start_server(false);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"set",
+		"07777777",
+		"https://example.com/campaign",
+		"--expires-at",
+		"2999-01-01T00:00:00Z",
+	];
+
+	let res = util::run_cli(args);
+
+	assert_re!(
+		r#"^"07777777" ---> "https://example.com/campaign" until 2999-01-01T00:00:00Z$"#,
+		res
+	);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"set",
+		"07777777",
+		"https://example.com/campaign",
+		"--expires-at",
+		"2000-01-01T00:00:00Z",
+	];
+
+	let res = util::run_cli(args);
+
+	assert_re!(r"expiry must be in the future", res);
+}
+
 /// Test `cli add <VANITY> <ID>` without TLS
 #[tokio::test]
 #[serial_test::serial]
@@ -160,6 +212,141 @@ async fn rem_vanity() {
 	assert_re!(r#"^"example" -X-> "9dDbKpJP"$"#, res);
 }
 
+/// Test `cli list` and `cli list-vanities` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn list() {
+	let _terminator = util::start_server(false);
+
+	let args = vec!["--host", "localhost", "--token", "abc123", "list"];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://example.com/"$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"list-vanities",
+		"--limit",
+		"10",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"example" ---> "9dDbKpJP"$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"list",
+		"--cursor",
+		"9dDbKpJP",
+	];
+	let res = util::run_cli(args);
+	assert_eq!(res.trim(), "");
+}
+
+/// Test `cli search-vanities` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn search_vanities() {
+	let _terminator = util::start_server(false);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"search-vanities",
+		"--prefix",
+		"exa",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"example" ---> "9dDbKpJP"$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"search-vanities",
+		"--prefix",
+		"ample",
+	];
+	let res = util::run_cli(args);
+	assert_eq!(res.trim(), "");
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"search-vanities",
+		"ample",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"example" ---> "9dDbKpJP"$"#, res);
+}
+
+/// Test `cli --namespace <NAMESPACE>` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn namespace() {
+	let _terminator = util::start_server(false);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"--namespace",
+		"tenant",
+		"set",
+		"07777777",
+		"https://example.com/tenant",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"07777777" ---> "https://example.com/tenant"$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"--namespace",
+		"tenant",
+		"get",
+		"07777777",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"07777777" ---> "https://example.com/tenant"$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"get",
+		"07777777",
+	];
+	let res = util::run_cli(args);
+	assert_re!(r#"^"07777777" ---> \?\?\?$"#, res);
+
+	let args = vec![
+		"--host",
+		"localhost",
+		"--token",
+		"abc123",
+		"--namespace",
+		"not/valid",
+		"get",
+		"07777777",
+	];
+	let res = util::run_cli(args);
+	assert!(!res.contains("--->"));
+}
+
 /// Test `cli stats-get` without TLS
 #[tokio::test]
 #[serial_test::serial]
@@ -211,6 +398,30 @@ async fn stats_get_vanity_type() {
 	);
 }
 
+/// Test `cli stats-summary <VANITY> --group-by type` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn stats_summary() {
+	let _terminator = util::start_server(false);
+	let args = vec![
+		"--token",
+		"abc123",
+		"stats-summary",
+		"test",
+		"--group-by",
+		"type",
+	];
+
+	let res = util::run_cli(args.clone());
+	assert_re!(r"^$", res);
+
+	reqwest::get("http://localhost/test").await.unwrap();
+	reqwest::get("http://localhost/test").await.unwrap();
+
+	let res = util::run_cli(args);
+	assert_re!(r"(?m)^\* request \* \* - 2$", res);
+}
+
 /// Test `cli stats-rem` without TLS
 #[tokio::test]
 #[serial_test::serial]
@@ -258,3 +469,290 @@ async fn stats_rem_vanity_type() {
 	let res = util::run_cli(args);
 	assert_re!(r#"^Removed 1 statistics$"#, res);
 }
+
+/// Test `cli options-set`, `cli options-get`, and `cli options-rem` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn options() {
+	let _terminator = util::start_server(false);
+
+	let get = vec!["--token", "abc123", "options-get", "9dDbKpJP"];
+	let rem = vec!["--token", "abc123", "options-rem", "9dDbKpJP"];
+	let set = vec![
+		"--token",
+		"abc123",
+		"options-set",
+		"9dDbKpJP",
+		r#"{"cors": {"allowed_origins": ["*"]}}"#,
+	];
+
+	assert_eq!(util::run_cli(get.clone()).trim(), "{}");
+
+	let res = util::run_cli(set);
+	assert_re!(r#"^"9dDbKpJP" ---> \{"cors":\{.*\}\}$"#, res);
+
+	let res = util::run_cli(get.clone());
+	assert_re!(
+		r#"^\{"cors":\{"allowed_origins":\["\*"\],"allowed_methods":\[\]\}\}$"#,
+		res
+	);
+
+	let res = util::run_cli(rem);
+	assert_re!(r#"^"9dDbKpJP" -X-> \{"cors":\{.*\}\}$"#, res);
+
+	assert_eq!(util::run_cli(get).trim(), "{}");
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"options-set",
+		"9dDbKpJP",
+		"not json",
+	]);
+	assert_re!(r#"The provided options are invalid"#, res);
+}
+
+/// Start a minimal HTTP `CONNECT` and SOCKS5 proxy (without authentication) on
+/// a random local port in the background, returning that port and a counter of
+/// proxied connections
+fn start_proxy() -> (u16, Arc<AtomicUsize>) {
+	fn handle(mut client: TcpStream) -> io::Result<()> {
+		let mut first = [0];
+		client.read_exact(&mut first)?;
+
+		let target = if first[0] == 5 {
+			// SOCKS5: no authentication, then a domain name or IPv4 `CONNECT`
+			let mut methods = [0; 2];
+			client.read_exact(&mut methods[..1])?;
+			client.read_exact(&mut vec![0; usize::from(methods[0])])?;
+			client.write_all(&[5, 0])?;
+
+			let mut req = [0; 4];
+			client.read_exact(&mut req)?;
+			let host = if req[3] == 3 {
+				let mut len = [0];
+				client.read_exact(&mut len)?;
+				let mut host = vec![0; usize::from(len[0])];
+				client.read_exact(&mut host)?;
+				String::from_utf8(host).unwrap()
+			} else {
+				let mut ip = [0; 4];
+				client.read_exact(&mut ip)?;
+				Ipv4Addr::from(ip).to_string()
+			};
+			let mut port = [0; 2];
+			client.read_exact(&mut port)?;
+
+			client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+			format!("{host}:{}", u16::from_be_bytes(port))
+		} else {
+			// HTTP: `CONNECT host:port HTTP/1.1`, then headers until an empty line
+			let mut req = vec![first[0]];
+			while !req.ends_with(b"\r\n\r\n") {
+				client.read_exact(&mut first)?;
+				req.push(first[0]);
+			}
+			let req = String::from_utf8(req).unwrap();
+			assert!(req.starts_with("CONNECT "));
+
+			client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+			req.split_ascii_whitespace().nth(1).unwrap().to_string()
+		};
+
+		let mut server = TcpStream::connect(target)?;
+		let (mut client_read, mut server_write) = (client.try_clone()?, server.try_clone()?);
+		thread::spawn(move || io::copy(&mut client_read, &mut server_write));
+		io::copy(&mut server, &mut client)?;
+
+		Ok(())
+	}
+
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let connections = Arc::new(AtomicUsize::new(0));
+	let counter = Arc::clone(&connections);
+
+	thread::spawn(move || {
+		for client in listener.incoming() {
+			counter.fetch_add(1, Ordering::SeqCst);
+			thread::spawn(move || handle(client.unwrap()));
+		}
+	});
+
+	(port, connections)
+}
+
+/// Test `cli --proxy` with HTTP and SOCKS5 proxies without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn proxy() {
+	let _terminator = util::start_server(false);
+	let (port, connections) = start_proxy();
+
+	let direct = util::run_cli(vec!["--token", "abc123", "get", "9dDbKpJP"]);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://"#, direct);
+
+	for proxy in [
+		format!("http://127.0.0.1:{port}"),
+		format!("socks5://127.0.0.1:{port}"),
+	] {
+		let before = connections.load(Ordering::SeqCst);
+		let res = util::run_cli(vec![
+			"--token", "abc123", "--proxy", &proxy, "get", "9dDbKpJP",
+		]);
+
+		assert_eq!(res, direct, "{proxy}");
+		assert!(connections.load(Ordering::SeqCst) > before, "{proxy}");
+	}
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"--proxy",
+		"http://127.0.0.1:1",
+		"get",
+		"9dDbKpJP",
+	]);
+	assert_re!(
+		r#"Could not connect to gRPC API server through the proxy"#,
+		res
+	);
+}
+
+/// Test `cli listener-add` and `cli listener-rem` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn listener_add_rem() {
+	let _terminator = util::start_server(false);
+
+	let client = reqwest::Client::builder()
+		.redirect(reqwest::redirect::Policy::none())
+		.build()
+		.unwrap();
+	assert!(client
+		.get("http://127.0.0.1:8081/example")
+		.send()
+		.await
+		.is_err());
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"listener-add",
+		"http:127.0.0.1:8081",
+	]);
+	assert_eq!(res.trim(), "+++ http:127.0.0.1:8081");
+
+	let res = client
+		.get("http://127.0.0.1:8081/example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), 302);
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"listener-add",
+		"http:127.0.0.1:8081",
+	]);
+	assert_eq!(res.trim(), "=== http:127.0.0.1:8081");
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"listener-rem",
+		"http:127.0.0.1:8081",
+	]);
+	assert_eq!(res.trim(), "--- http:127.0.0.1:8081");
+
+	assert!(reqwest::Client::new()
+		.get("http://127.0.0.1:8081/example")
+		.send()
+		.await
+		.is_err());
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"listener-rem",
+		"http:127.0.0.1:8081",
+	]);
+	assert_eq!(res.trim(), "=== http:127.0.0.1:8081");
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"listener-add",
+		"http:127.0.0.1:8081",
+		"--persist",
+	]);
+	assert_re!(r#"no configuration file"#, res);
+}
+
+/// Test `cli token-rotate` without TLS
+#[tokio::test]
+#[serial_test::serial]
+async fn token_rotate() {
+	let _terminator = util::start_server(false);
+
+	let res = util::run_cli(vec![
+		"--token",
+		"abc123",
+		"token-rotate",
+		"test",
+		"--token",
+		"def456",
+		"--grace-period",
+		"60",
+	]);
+	assert_eq!(res.trim(), "def456");
+
+	let res = util::run_cli(vec!["--token", "def456", "get", "9dDbKpJP"]);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://example.com/"$"#, res);
+
+	let res = util::run_cli(vec!["--token", "abc123", "get", "9dDbKpJP"]);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://example.com/"$"#, res);
+
+	let res = util::run_cli(vec!["--token", "def456", "token-rotate", "test"]);
+	let new_token = res.trim().to_string();
+	assert_eq!(new_token.len(), 32);
+
+	let res = util::run_cli(vec!["--token", &new_token, "get", "9dDbKpJP"]);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://example.com/"$"#, res);
+
+	let res = util::run_cli(vec!["--token", "abc123", "get", "9dDbKpJP"]);
+	assert_re!(r#"auth token is invalid"#, res);
+
+	let res = util::run_cli(vec!["--token", &new_token, "token-rotate", "missing"]);
+	assert_re!(r#"token does not exist"#, res);
+}
+
+/// Test connecting to the RPC API via a `grpc+unix` listener without TLS
+#[cfg(unix)]
+#[tokio::test]
+#[serial_test::serial]
+async fn unix_socket() {
+	let _terminator = util::start_server(false);
+
+	let path = format!("{}/links-cli-test.sock", env!("CARGO_TARGET_TMPDIR"));
+	let addr = format!("grpc+unix:{path}");
+
+	let res = util::run_cli(vec!["--token", "abc123", "listener-add", &addr]);
+	assert_eq!(res.trim(), format!("+++ {addr}"));
+	assert!(std::path::Path::new(&path).exists());
+
+	let res = util::run_cli(vec![
+		"--socket", &path, "--token", "abc123", "get", "9dDbKpJP",
+	]);
+	assert_re!(r#"^"9dDbKpJP" ---> "https://example.com/"$"#, res);
+
+	let res = util::run_cli(vec!["--token", "abc123", "listener-rem", &addr]);
+	assert_eq!(res.trim(), format!("--- {addr}"));
+	assert!(!std::path::Path::new(&path).exists());
+
+	let res = util::run_cli(vec![
+		"--socket", &path, "--token", "abc123", "get", "9dDbKpJP",
+	]);
+	assert_re!(r#"Could not connect to gRPC API server"#, res);
+}
@@ -0,0 +1,288 @@
+//! End to end tests of the typed links client against the redirector server
+
+mod util;
+
+use std::{
+	convert::Infallible,
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use futures_util::stream;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+	body::{Bytes, Frame, Incoming},
+	header::{HeaderMap, HeaderValue},
+	server::conn::http2,
+	service::service_fn,
+	Request, Response,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use links_client::{Client, ClientConfig, Error, RetryPolicy, StatisticsFilter};
+use links_id::Id;
+use links_normalized::{Link, Normalized};
+use tokio::net::TcpListener;
+use tonic::{transport::Channel, Code};
+
+/// Test redirect and vanity path management with the typed client
+#[tokio::test]
+#[serial_test::serial]
+async fn redirects_and_vanities() {
+	let _terminator = util::start_server(false);
+
+	let client = Client::connect(ClientConfig::new("localhost", "abc123"))
+		.await
+		.unwrap();
+
+	let id = Id::new();
+	let link = Link::new("https://example.com/client").unwrap();
+	let vanity = Normalized::new("client-test");
+
+	assert_eq!(client.get_redirect(id).await.unwrap(), None);
+	assert_eq!(client.set_redirect(id, link.clone()).await.unwrap(), None);
+	assert_eq!(client.get_redirect(id).await.unwrap(), Some(link.clone()));
+
+	assert_eq!(client.set_vanity(vanity.clone(), id).await.unwrap(), None);
+	assert_eq!(client.get_vanity(vanity.clone()).await.unwrap(), Some(id));
+
+	let res = reqwest::Client::builder()
+		.redirect(reqwest::redirect::Policy::none())
+		.build()
+		.unwrap()
+		.get("http://localhost/client-test")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.headers()["location"], link.to_string().as_str());
+
+	let stats = client
+		.get_statistics(StatisticsFilter {
+			link: Some(id.to_string()),
+			..StatisticsFilter::default()
+		})
+		.await
+		.unwrap();
+	assert!(!stats.is_empty());
+
+	assert_eq!(client.rem_vanity(vanity.clone()).await.unwrap(), Some(id));
+	assert_eq!(client.rem_redirect(id).await.unwrap(), Some(link));
+	assert_eq!(client.get_vanity(vanity).await.unwrap(), None);
+	assert_eq!(client.get_redirect(id).await.unwrap(), None);
+}
+
+/// Test batch redirect and vanity path management with the typed client
+#[tokio::test]
+#[serial_test::serial]
+async fn batch() {
+	let _terminator = util::start_server(false);
+
+	let client = Client::connect(ClientConfig::new("localhost", "abc123"))
+		.await
+		.unwrap();
+
+	let (id_a, id_b) = (Id::new(), Id::new());
+	let link_a = Link::new("https://example.com/batch/a").unwrap();
+	let link_b = Link::new("https://example.com/batch/b").unwrap();
+	let vanity_a = Normalized::new("batch-a");
+	let vanity_b = Normalized::new("batch-b");
+
+	assert_eq!(
+		client
+			.set_redirects(vec![(id_a, link_a.clone()), (id_b, link_a.clone())])
+			.await
+			.unwrap(),
+		vec![None, None]
+	);
+	assert_eq!(
+		client
+			.set_redirects(vec![(id_b, link_b.clone())])
+			.await
+			.unwrap(),
+		vec![Some(link_a.clone())]
+	);
+	assert_eq!(
+		client.get_redirect(id_b).await.unwrap(),
+		Some(link_b.clone())
+	);
+
+	assert_eq!(
+		client
+			.set_vanities(vec![(vanity_a.clone(), id_a), (vanity_b.clone(), id_b)])
+			.await
+			.unwrap(),
+		vec![None, None]
+	);
+	assert_eq!(client.get_vanity(vanity_b).await.unwrap(), Some(id_b));
+
+	assert_eq!(
+		client.rem_redirects(vec![id_a, id_b, id_a]).await.unwrap(),
+		vec![Some(link_a), Some(link_b), None]
+	);
+	assert_eq!(client.get_redirect(id_a).await.unwrap(), None);
+
+	assert!(matches!(
+		client
+			.set_redirects(vec![(Id::new(), Link::new("https://example.com/").unwrap()); 1001])
+			.await,
+		Err(Error::Status(status)) if status.code() == Code::InvalidArgument
+	));
+}
+
+/// Test creating links and getting their statistics with the typed client
+#[tokio::test]
+#[serial_test::serial]
+async fn create_link() {
+	let _terminator = util::start_server(false);
+
+	let client = Client::connect(ClientConfig::new("localhost", "abc123"))
+		.await
+		.unwrap();
+
+	let link = Link::new("https://example.com/create").unwrap();
+	let vanity = Normalized::new("create-test");
+
+	let id = client
+		.create_link(link.clone(), Some(vanity.clone()))
+		.await
+		.unwrap();
+	let other_id = client.create_link(link.clone(), None).await.unwrap();
+
+	assert_ne!(id, other_id);
+	assert_eq!(client.get_redirect(id).await.unwrap(), Some(link.clone()));
+	assert_eq!(client.get_redirect(other_id).await.unwrap(), Some(link));
+	assert_eq!(client.get_vanity(vanity).await.unwrap(), Some(id));
+
+	assert!(client.stats_for(id).await.unwrap().is_empty());
+
+	reqwest::Client::builder()
+		.redirect(reqwest::redirect::Policy::none())
+		.build()
+		.unwrap()
+		.get("http://localhost/create-test")
+		.send()
+		.await
+		.unwrap();
+
+	let stats = client.stats_for(id).await.unwrap();
+	assert!(!stats.is_empty());
+	assert!(stats.iter().all(|stat| stat.link == id.to_string()));
+	assert!(client.stats_for(other_id).await.unwrap().is_empty());
+}
+
+/// Test connecting with TLS using a custom CA certificate with the typed client
+#[tokio::test]
+#[serial_test::serial]
+async fn custom_ca() {
+	let _terminator = util::start_server(true);
+
+	let config = ClientConfig::new("127.0.0.1", "abc123")
+		.tls(true)
+		.ca_certificate(include_bytes!("cert.pem").as_slice())
+		.tls_domain("localhost");
+	let client = Client::connect(config).await.unwrap();
+
+	assert_eq!(client.get_redirect(Id::new()).await.unwrap(), None);
+}
+
+/// Test that the typed client reports an invalid token
+#[tokio::test]
+#[serial_test::serial]
+async fn unauthenticated() {
+	let _terminator = util::start_server(false);
+
+	let config = ClientConfig::new("localhost", "wrong-token").retry(RetryPolicy::NONE);
+	let client = Client::connect(config).await.unwrap();
+
+	assert!(matches!(
+		client.get_redirect(Id::new()).await,
+		Err(Error::Unauthenticated(_))
+	));
+}
+
+/// Start a fake gRPC server, recording the path of every request in
+/// `requests`. `GetRedirect` calls always find no redirect, and all other calls
+/// fail with the (retryable) `UNAVAILABLE` status.
+async fn start_unavailable_server(requests: Arc<Mutex<Vec<String>>>) -> SocketAddr {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let service = service_fn(move |req: Request<Incoming>| {
+		let requests = Arc::clone(&requests);
+
+		async move {
+			let path = req.uri().path().to_string();
+			req.into_body().collect().await.unwrap();
+			requests.lock().unwrap().push(path.clone());
+
+			let mut frames = Vec::new();
+			let mut trailers = HeaderMap::new();
+			if path == "/links.Links/GetRedirect" {
+				// An empty `GetRedirectResponse`, i.e. no redirect
+				frames.push(Frame::data(Bytes::from_static(&[0; 5])));
+				trailers.insert("grpc-status", HeaderValue::from_static("0"));
+			} else {
+				trailers.insert("grpc-status", HeaderValue::from_static("14"));
+			}
+			frames.push(Frame::trailers(trailers));
+
+			Ok::<_, Infallible>(
+				Response::builder()
+					.header("content-type", "application/grpc")
+					.body(StreamBody::new(stream::iter(
+						frames.into_iter().map(Ok::<_, Infallible>),
+					)))
+					.unwrap(),
+			)
+		}
+	});
+
+	tokio::spawn(async move {
+		loop {
+			let (stream, _) = listener.accept().await.unwrap();
+			tokio::spawn(
+				http2::Builder::new(TokioExecutor::new())
+					.serve_connection(TokioIo::new(stream), service.clone()),
+			);
+		}
+	});
+
+	addr
+}
+
+/// Test that the typed client doesn't replay a failed link creation
+#[tokio::test]
+async fn create_link_not_retried() {
+	let requests = Arc::new(Mutex::new(Vec::new()));
+	let addr = start_unavailable_server(Arc::clone(&requests)).await;
+
+	let channel = Channel::from_shared(format!("http://{addr}"))
+		.unwrap()
+		.connect_lazy();
+	let policy = RetryPolicy {
+		max_retries: 2,
+		initial_backoff: Duration::from_millis(10),
+		max_backoff: Duration::from_millis(10),
+	};
+	let client = Client::from_channel(channel, "abc123", policy).unwrap();
+
+	// Read-only calls are retried
+	assert!(matches!(
+		client.get_vanity(Normalized::new("retried")).await,
+		Err(Error::Status(status)) if status.code() == Code::Unavailable
+	));
+	assert_eq!(requests.lock().unwrap().len(), 3);
+	requests.lock().unwrap().clear();
+
+	// Creating the redirect is only attempted once
+	assert!(matches!(
+		client
+			.create_link(Link::new("https://example.com/").unwrap(), None)
+			.await,
+		Err(Error::Status(status)) if status.code() == Code::Unavailable
+	));
+	assert_eq!(*requests.lock().unwrap(), [
+		"/links.Links/GetRedirect",
+		"/links.Links/SetRedirect"
+	]);
+}
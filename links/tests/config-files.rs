@@ -109,3 +109,41 @@ fn docker_config_is_valid() {
 
 	assert!(config.is_ok());
 }
+
+#[test]
+fn listeners_are_written_to_files() {
+	let path = PathBuf::from_str(env!("CARGO_MANIFEST_DIR"))
+		.unwrap()
+		.join("example-config");
+	let listeners = ["http:127.0.0.1:8080".parse().unwrap()];
+
+	for ext in ["json", "toml", "yaml"] {
+		// JSON must first have comments removed to be parsed.
+		let contents = fs::read_to_string(path.with_extension(ext))
+			.unwrap()
+			.lines()
+			.filter(|l| ext != "json" || !l.trim().starts_with("//"))
+			.collect::<Vec<_>>()
+			.join("\n");
+		let file_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+			.unwrap()
+			.join("links_test_write-listeners_to_files")
+			.with_extension(ext);
+		fs::write(&file_path, contents).unwrap();
+
+		let before = Partial::from_file(&file_path).unwrap();
+		Partial::write_listeners_to_file(&file_path, &listeners).unwrap();
+		let after = Partial::from_file(&file_path).unwrap();
+
+		assert_eq!(after.listeners.as_deref(), Some(&listeners[..]));
+		assert_eq!(
+			Partial {
+				listeners: before.listeners.clone(),
+				..after
+			},
+			before
+		);
+
+		fs::remove_file(file_path).unwrap();
+	}
+}
@@ -4,9 +4,16 @@ mod util;
 
 use std::{path::PathBuf, str::FromStr, time::Duration};
 
+use links::{
+	api::{
+		AddListenerRequest, ReloadCertificatesRequest, ReloadConfigRequest, RemoveListenerRequest,
+	},
+	config::{ListenAddress, Partial},
+};
 use reqwest::{redirect::Policy, Certificate, Client, ClientBuilder};
 use serde_json::json;
 use tokio::{fs, time};
+use tonic::{Code, Request};
 
 const TEST_CONFIG: &str = include_str!("test-config.toml");
 
@@ -81,6 +88,72 @@ async fn config_reload() {
 	assert!(dbg!(res_after.headers()).get("Server").is_none());
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn config_reload_rpc() {
+	let config_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-config_reload_rpc")
+		.with_extension("toml");
+	let config_path_str = util::convert_path(config_path.to_str().unwrap());
+	fs::write(&config_path, TEST_CONFIG).await.unwrap();
+
+	// The file watcher is too slow to reload the config during this test
+	let _terminator = util::start_server_with_args(vec![
+		"-c",
+		config_path_str.as_str(),
+		"--watcher-timeout",
+		"60000",
+		"--watcher-debounce",
+		"60000",
+	]);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let res_before = get_client()
+		.get("http://localhost/example")
+		.send()
+		.await
+		.unwrap();
+
+	fs::write(
+		&config_path,
+		TEST_CONFIG.replace("send_server = true", "send_server = false"),
+	)
+	.await
+	.unwrap();
+
+	let mut req = Request::new(ReloadConfigRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let changed = rpc_client
+		.reload_config(req)
+		.await
+		.unwrap()
+		.into_inner()
+		.changed;
+
+	let res_after = get_client()
+		.get("http://localhost/example")
+		.send()
+		.await
+		.unwrap();
+
+	assert_eq!(changed, ["send_server"]);
+	assert!(dbg!(res_before.headers()).get("Server").is_some());
+	assert!(dbg!(res_after.headers()).get("Server").is_none());
+
+	let mut req = Request::new(ReloadConfigRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let changed = rpc_client
+		.reload_config(req)
+		.await
+		.unwrap()
+		.into_inner()
+		.changed;
+
+	assert!(changed.is_empty());
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn tls_reconfigure_default() {
@@ -332,6 +405,92 @@ async fn tls_reload_default() {
 	assert!(dbg!(other_res_after).is_ok());
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn tls_reload_rpc() {
+	let cert_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-tls_reload_rpc-cert")
+		.with_extension("pem");
+	let cert_path_str = util::convert_path(cert_path.to_str().unwrap());
+	fs::write(&cert_path, TEST_CERT).await.unwrap();
+
+	let key_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-tls_reload_rpc-key")
+		.with_extension("pem");
+	let key_path_str = util::convert_path(key_path.to_str().unwrap());
+	fs::write(&key_path, TEST_KEY).await.unwrap();
+
+	let certificate = json! {
+		{
+			"source": "files",
+			"cert": cert_path_str,
+			"key": key_path_str
+		}
+	}
+	.to_string();
+
+	// The file watcher is too slow to reload the certificate during this test
+	let _terminator = util::start_server_with_args(vec![
+		"-c",
+		"tests/test-config.toml",
+		"--default-certificate",
+		certificate.as_str(),
+		"--watcher-timeout",
+		"60000",
+		"--watcher-debounce",
+		"60000",
+	]);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let res_before = get_client_with_cert(OTHER_TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	fs::write(&cert_path, OTHER_TEST_CERT).await.unwrap();
+	fs::write(&key_path, OTHER_TEST_KEY).await.unwrap();
+
+	let mut req = Request::new(ReloadCertificatesRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client
+		.reload_certificates(req)
+		.await
+		.unwrap()
+		.into_inner();
+
+	let res_after = get_client_with_cert(OTHER_TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	assert_eq!(res.reloaded, 1);
+	assert!(res.errors.is_empty());
+	assert!(dbg!(res_before).is_err());
+	assert!(dbg!(res_after).is_ok());
+
+	fs::write(&key_path, TEST_KEY).await.unwrap();
+
+	let mut req = Request::new(ReloadCertificatesRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client
+		.reload_certificates(req)
+		.await
+		.unwrap()
+		.into_inner();
+
+	let res_mismatched = get_client_with_cert(OTHER_TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	assert_eq!(res.reloaded, 0);
+	assert_eq!(res.errors.len(), 1);
+	assert!(dbg!(res_mismatched).is_ok());
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn tls_key_mismatch_detection() {
@@ -488,6 +647,80 @@ async fn tls_reload_domains() {
 	assert!(dbg!(other_res_after).is_ok());
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn tls_reload_directory() {
+	let dir_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-tls_reload_directory");
+	let dir_path_str = util::convert_path(dir_path.to_str().unwrap());
+	let _ = fs::remove_dir_all(&dir_path).await;
+	fs::create_dir_all(dir_path.join("other")).await.unwrap();
+	fs::write(dir_path.join("tls.crt"), TEST_CERT)
+		.await
+		.unwrap();
+	fs::write(dir_path.join("tls.key"), TEST_KEY).await.unwrap();
+
+	let certificates = json! {
+		[{
+			"source": "directory",
+			"path": dir_path_str
+		}]
+	}
+	.to_string();
+
+	let _terminator = util::start_server_with_args(vec![
+		"-c",
+		"tests/test-config.toml",
+		"--certificates",
+		certificates.as_str(),
+		"--watcher-timeout",
+		"50",
+		"--watcher-debounce",
+		"50",
+	]);
+
+	// Can't reuse the client, because the connection would be kept alive, and
+	// the certificate reloading wouldn't be noticed
+	let res_before = get_client_with_cert(TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	fs::remove_file(dir_path.join("tls.key")).await.unwrap();
+	fs::write(dir_path.join("other/fullchain.pem"), OTHER_TEST_CERT)
+		.await
+		.unwrap();
+	fs::write(dir_path.join("other/privkey.pem"), OTHER_TEST_KEY)
+		.await
+		.unwrap();
+
+	time::sleep(Duration::from_millis(500)).await;
+
+	let res_after = get_client_with_cert(TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+	let other_res_after = get_client_with_cert(OTHER_TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	fs::remove_dir_all(dir_path.join("other")).await.unwrap();
+
+	time::sleep(Duration::from_millis(500)).await;
+
+	let other_res_removed = get_client_with_cert(OTHER_TEST_CERT)
+		.get("https://localhost/example")
+		.send()
+		.await;
+
+	assert!(dbg!(res_before).is_ok());
+	assert!(dbg!(res_after).is_err());
+	assert!(dbg!(other_res_after).is_ok());
+	assert!(dbg!(other_res_removed).is_err());
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn listeners_reload() {
@@ -545,3 +778,139 @@ async fn listeners_reload() {
 	assert!(dbg!(res_after_b.headers()).get("Server").is_some());
 	assert!(dbg!(res_after_c).is_err());
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn listener_persist() {
+	let config_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-listener_persist")
+		.with_extension("toml");
+	let config_path_str = util::convert_path(config_path.to_str().unwrap());
+	fs::write(&config_path, TEST_CONFIG).await.unwrap();
+
+	let _terminator = util::start_server_with_args(vec![
+		"-c",
+		config_path_str.as_str(),
+		"--watcher-timeout",
+		"50",
+		"--watcher-debounce",
+		"50",
+	]);
+
+	let address: ListenAddress = "http:127.0.0.1:8082".parse().unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(AddListenerRequest {
+		address: address.to_string(),
+		persist: true,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	assert!(
+		rpc_client
+			.add_listener(req)
+			.await
+			.unwrap()
+			.into_inner()
+			.added
+	);
+
+	let listeners = Partial::from_file(&config_path).unwrap().listeners.unwrap();
+	assert_eq!(listeners.len(), 6);
+	assert!(listeners.contains(&address));
+
+	// The config reload caused by the file change keeps the new listener
+	time::sleep(Duration::from_millis(500)).await;
+
+	assert!(get_client()
+		.get("http://127.0.0.1:8082/example")
+		.send()
+		.await
+		.is_ok());
+
+	let mut req = Request::new(RemoveListenerRequest {
+		address: address.to_string(),
+		persist: true,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	assert!(
+		rpc_client
+			.remove_listener(req)
+			.await
+			.unwrap()
+			.into_inner()
+			.removed
+	);
+
+	let listeners = Partial::from_file(&config_path).unwrap().listeners.unwrap();
+	assert_eq!(listeners.len(), 5);
+	assert!(!listeners.contains(&address));
+
+	time::sleep(Duration::from_millis(500)).await;
+
+	assert!(get_client()
+		.get("http://127.0.0.1:8082/example")
+		.send()
+		.await
+		.is_err());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn listener_remove_default_port() {
+	let config_path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_file_reload-listener_remove_default_port")
+		.with_extension("toml");
+	let config_path_str = util::convert_path(config_path.to_str().unwrap());
+	fs::write(&config_path, TEST_CONFIG).await.unwrap();
+
+	let _terminator = util::start_server_with_args(vec!["-c", config_path_str.as_str()]);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	// This request is served by the `grpc::` listener on the default port
+	let mut req = Request::new(RemoveListenerRequest {
+		address: "grpc::".to_string(),
+		persist: true,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	assert_eq!(
+		rpc_client.remove_listener(req).await.unwrap_err().code(),
+		Code::FailedPrecondition
+	);
+
+	let listeners = Partial::from_file(&config_path).unwrap().listeners.unwrap();
+	assert_eq!(listeners.len(), 5);
+
+	// Other listeners on a default port can be removed without their port
+	assert!(get_client()
+		.get("http://localhost:80/example")
+		.send()
+		.await
+		.is_ok());
+
+	let mut req = Request::new(RemoveListenerRequest {
+		address: "http::".to_string(),
+		persist: true,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	assert!(
+		rpc_client
+			.remove_listener(req)
+			.await
+			.unwrap()
+			.into_inner()
+			.removed
+	);
+
+	let listeners = Partial::from_file(&config_path).unwrap().listeners.unwrap();
+	assert_eq!(listeners.len(), 4);
+	assert!(!listeners.contains(&"http::80".parse::<ListenAddress>().unwrap()));
+
+	assert!(get_client()
+		.get("http://localhost:80/example")
+		.send()
+		.await
+		.is_err());
+}
@@ -2,7 +2,11 @@
 
 mod util;
 
-use reqwest::{header::HeaderValue, redirect::Policy, ClientBuilder, StatusCode};
+use links::api::{
+	AddListenerRequest, GetRedirectRequest, SetOptionsRequest, SetRedirectRequest, SetVanityRequest,
+};
+use reqwest::{header::HeaderValue, redirect::Policy, ClientBuilder, Method, StatusCode};
+use tonic::Request;
 
 /// HTTP/1.1 redirect tests
 #[tokio::test]
@@ -67,3 +71,881 @@ async fn http2_redirect() {
 	let redirect_id = redirect_res.headers().get("Link-ID");
 	assert_eq!(redirect_id, Some(&HeaderValue::from_static("9dDbKpJP")));
 }
+
+/// CORS header tests, with global and per-link settings
+#[tokio::test]
+#[serial_test::serial]
+async fn cors() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--cors",
+		r#"{"allowed_origins": ["https://allowed.example"]}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Origin", "https://allowed.example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Origin"),
+		Some(&HeaderValue::from_static("https://allowed.example"))
+	);
+	assert_eq!(
+		res.headers().get("Vary"),
+		Some(&HeaderValue::from_static("Origin"))
+	);
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Origin", "https://other.example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(res.headers().get("Access-Control-Allow-Origin"), None);
+
+	let res = client
+		.request(Method::OPTIONS, "http://localhost/example")
+		.header("Origin", "https://allowed.example")
+		.header("Access-Control-Request-Method", "GET")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Methods"),
+		Some(&HeaderValue::from_static("GET, HEAD"))
+	);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"cors": {"allowed_origins": ["*"], "allowed_methods": ["POST"]}}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Origin", "https://other.example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Origin"),
+		Some(&HeaderValue::from_static("*"))
+	);
+
+	let res = client
+		.request(Method::OPTIONS, "http://localhost/example")
+		.header("Origin", "https://other.example")
+		.header("Access-Control-Request-Method", "GET")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	assert_eq!(res.headers().get("Access-Control-Allow-Methods"), None);
+}
+
+/// Path pass-through tests, with global and per-link settings
+#[tokio::test]
+#[serial_test::serial]
+async fn path_passthrough() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--path-passthrough",
+		"true",
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost/example/docs/installation")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static(
+			"https://example.com/docs/installation"
+		))
+	);
+	assert_eq!(
+		res.headers().get("Link-ID"),
+		Some(&HeaderValue::from_static("9dDbKpJP"))
+	);
+
+	let res = client
+		.get("http://localhost/9dDbKpJP/docs")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/docs"))
+	);
+
+	let res = client
+		.get("http://localhost/nonexistent/docs")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"path_passthrough": false}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client
+		.get("http://localhost/example/docs/installation")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn path_normalization() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--path-normalization",
+		r#"{"trim_trailing_slash": true, "percent_decode": true, "segments": "first"}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	for path in [
+		"/example/",
+		"/ex%61mple",
+		"/example/ignored/path",
+		"/9dDbKpJP/",
+	] {
+		let res = client
+			.get(format!("http://localhost{path}"))
+			.send()
+			.await
+			.unwrap();
+		assert_eq!(res.status(), StatusCode::FOUND, "{path}");
+		assert_eq!(
+			res.headers().get("Location"),
+			Some(&HeaderValue::from_static("https://example.com/")),
+			"{path}"
+		);
+	}
+
+	let res = client
+		.get("http://localhost/nonexistent/example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+/// Expired link tests, with lazy removal of expired redirects
+#[tokio::test]
+#[serial_test::serial]
+async fn expired_links() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"expires_at": 4102444800}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"expires_at": 946684800}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::GONE);
+	assert_eq!(res.headers().get("Location"), None);
+	assert!(res.text().await.unwrap().contains("expired"));
+
+	let res = client
+		.get("http://localhost/9dDbKpJP")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::GONE);
+
+	// The expired redirect is removed in the background
+	let mut removed = false;
+	for _ in 0..50 {
+		let mut req = Request::new(GetRedirectRequest {
+			id: "9dDbKpJP".to_string(),
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+
+		let res = rpc_client.get_redirect(req).await.unwrap().into_inner();
+		if res.link.is_none() {
+			removed = true;
+			break;
+		}
+
+		tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+	}
+	assert!(removed);
+}
+
+/// Click-limited link tests
+#[tokio::test]
+#[serial_test::serial]
+async fn click_limited_links() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"max_clicks": 2}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	for _ in 0..2 {
+		let res = client.get("http://localhost/example").send().await.unwrap();
+		assert_eq!(res.status(), StatusCode::FOUND);
+	}
+
+	for _ in 0..2 {
+		let res = client.get("http://localhost/example").send().await.unwrap();
+		assert_eq!(res.status(), StatusCode::GONE);
+		assert_eq!(res.headers().get("Location"), None);
+	}
+}
+
+/// Device-targeted destination tests
+#[tokio::test]
+#[serial_test::serial]
+async fn device_destinations() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"device_destinations": {"ios": "https://example.com/ios", "mobile": "https://example.com/mobile"}}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let location = |res: reqwest::Response| res.headers().get("Location").cloned();
+
+	let res = client
+		.get("http://localhost/example")
+		.header(
+			"User-Agent",
+			"Mozilla/5.0 (iPhone; CPU iPhone OS 18_0 like Mac OS X) Mobile/15E148",
+		)
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(
+		location(res),
+		Some(HeaderValue::from_static("https://example.com/ios"))
+	);
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Sec-CH-UA-Mobile", "?1")
+		.header("Sec-CH-UA-Platform", "\"Android\"")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(
+		location(res),
+		Some(HeaderValue::from_static("https://example.com/mobile"))
+	);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(
+		location(res),
+		Some(HeaderValue::from_static("https://example.com/"))
+	);
+}
+
+/// Query parameter injection tests
+#[tokio::test]
+#[serial_test::serial]
+async fn query_params() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"query_params": {"utm_source": "links", "utm_medium": "short link"}}"#
+			.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static(
+			"https://example.com/?utm_medium=short+link&utm_source=links"
+		))
+	);
+}
+
+/// Configured `Referrer-Policy` and extra response header tests
+#[tokio::test]
+#[serial_test::serial]
+async fn extra_headers() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--referrer-policy",
+		"strict-origin-when-cross-origin",
+		"--extra-headers",
+		r#"{"Permissions-Policy": "interest-cohort=()", "X-Frame-Options": "DENY"}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	for path in ["/example", "/not-found"] {
+		let res = client
+			.get(format!("http://localhost{path}"))
+			.send()
+			.await
+			.unwrap();
+		assert_eq!(
+			res.headers().get("Referrer-Policy"),
+			Some(&HeaderValue::from_static("strict-origin-when-cross-origin"))
+		);
+		assert_eq!(
+			res.headers().get("Permissions-Policy"),
+			Some(&HeaderValue::from_static("interest-cohort=()"))
+		);
+		assert_eq!(
+			res.headers().get("X-Frame-Options"),
+			Some(&HeaderValue::from_static("DENY"))
+		);
+	}
+}
+
+/// Link preview page tests
+#[tokio::test]
+#[serial_test::serial]
+async fn link_previews() {
+	let _terminator =
+		util::start_server_with_args(vec!["--example-redirect", "--preview-suffix", "+"]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost/example+")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.headers().get("Location"), None);
+	assert_eq!(
+		res.headers().get("Link-ID"),
+		Some(&HeaderValue::from_static("9dDbKpJP"))
+	);
+	assert!(res.text().await.unwrap().contains("https://example.com/"));
+
+	let res = client
+		.get("http://localhost/9dDbKpJP+")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let res = client
+		.get("http://localhost/nonexistent+")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+/// Interstitial page tests
+#[tokio::test]
+#[serial_test::serial]
+async fn interstitial_pages() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--interstitial-delay",
+		"5",
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"interstitial": true}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.headers().get("Location"), None);
+	assert_eq!(
+		res.headers().get("Link-ID"),
+		Some(&HeaderValue::from_static("9dDbKpJP"))
+	);
+	let body = res.text().await.unwrap();
+	assert!(body.contains("5; url=https://example.com/"));
+	assert!(body.contains("in 5 seconds"));
+}
+
+/// Built-in `/robots.txt` and `/favicon.ico` tests
+#[tokio::test]
+#[serial_test::serial]
+async fn well_known_paths() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost/robots.txt")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.text().await.unwrap(), "User-agent: *\nAllow: /\n");
+
+	let res = client
+		.get("http://localhost/favicon.ico")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(
+		res.headers().get("Content-Type"),
+		Some(&HeaderValue::from_static("image/svg+xml"))
+	);
+}
+
+/// Configured `/robots.txt` and `/favicon.ico` tests
+#[tokio::test]
+#[serial_test::serial]
+async fn well_known_paths_configured() {
+	let _terminator = util::start_server_with_args(vec![
+		"--robots-txt",
+		"User-agent: *\nDisallow: /\n",
+		"--favicon",
+		"https://example.com/favicon.ico",
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost/robots.txt")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.text().await.unwrap(), "User-agent: *\nDisallow: /\n");
+
+	let res = client
+		.get("http://localhost/favicon.ico")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/favicon.ico"))
+	);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn redirector_policies() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--redirector-policies",
+		r#"[
+			{"domains": ["*.example.com"], "https_redirect": true},
+			{"domains": ["go.example.com"], "redirect_status": 301}
+		]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Host", "links.example.com")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static(
+			"https://links.example.com/example"
+		))
+	);
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Host", "go.example.com")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+
+	let res = client
+		.post("http://localhost/example")
+		.header("Host", "go.example.com")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn default_destination() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--default-destination",
+		"https://example.com/home",
+		"--redirector-policies",
+		r#"[{"domains": ["go.example.org"], "default_destination": "https://example.org/"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+
+	let res = client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/home"))
+	);
+	assert_eq!(res.headers().get("Link-ID"), None);
+
+	let res = client
+		.get("http://localhost/nonexistent")
+		.header("Host", "go.example.org")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.org/"))
+	);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn search_fallback() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--default-destination",
+		"https://example.com/home",
+		"--search-fallback",
+		"https://example.com/search?q={path}",
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+
+	let res = client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static(
+			"https://example.com/search?q=nonexistent"
+		))
+	);
+	assert_eq!(res.headers().get("Link-ID"), None);
+
+	let res = client.get("http://localhost/").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/home"))
+	);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn redirect_rate_limits() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--redirect-rate-limits",
+		r#"{"client": {"per_minute": 1, "burst": 2}}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let res = client
+		.get("http://localhost/9dDbKpJP")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+	assert_eq!(
+		res.headers().get("Retry-After"),
+		Some(&HeaderValue::from_static("60"))
+	);
+	assert_eq!(res.headers().get("Location"), None);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn redirector_policy_namespace() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--redirector-policies",
+		r#"[{"domains": ["go.example.com"], "namespace": "brand"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(SetRedirectRequest {
+		id: "1wqLjdjd".to_string(),
+		link: "https://example.org/brand".to_string(),
+		expires_at: None,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	req.metadata_mut()
+		.append("namespace", "brand".parse().unwrap());
+	rpc_client.set_redirect(req).await.unwrap();
+
+	let mut req = Request::new(SetVanityRequest {
+		vanity: "example".to_string(),
+		id: "1wqLjdjd".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	req.metadata_mut()
+		.append("namespace", "brand".parse().unwrap());
+	rpc_client.set_vanity(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.com/"))
+	);
+
+	let res = client
+		.get("http://localhost/example")
+		.header("Host", "go.example.com")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+	assert_eq!(
+		res.headers().get("Location"),
+		Some(&HeaderValue::from_static("https://example.org/brand"))
+	);
+
+	let res = client
+		.get("http://localhost/9dDbKpJP")
+		.header("Host", "go.example.com")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[serial_test::serial]
+async fn unix_socket_listener() {
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::UnixStream,
+	};
+
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let path = format!("{}/links-http-test.sock", env!("CARGO_TARGET_TMPDIR"));
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+	let mut req = Request::new(AddListenerRequest {
+		address: format!("http+unix:{path}"),
+		persist: false,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	assert!(
+		rpc_client
+			.add_listener(req)
+			.await
+			.unwrap()
+			.into_inner()
+			.added
+	);
+
+	let mut stream = UnixStream::connect(&path).await.unwrap();
+	stream
+		.write_all(b"GET /example HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+		.await
+		.unwrap();
+
+	let mut res = String::new();
+	stream.read_to_string(&mut res).await.unwrap();
+
+	assert!(res.starts_with("HTTP/1.1 302 Found\r\n"));
+	assert!(res
+		.to_ascii_lowercase()
+		.contains("\r\nlocation: https://example.com/\r\n"));
+}
@@ -2,7 +2,19 @@
 
 mod util;
 
-use reqwest::{header::HeaderValue, redirect::Policy, ClientBuilder, StatusCode};
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
+
+use links::api::{
+	GetCertificateLookupsRequest, GetRedirectRequest, LinksClient, ListListenersRequest,
+	PurgeRedirectsRequest, RemRedirectRequest, SetOptionsRequest, SetRedirectEnabledRequest,
+	SetRedirectRequest, SetVanityRequest, WatchRedirectsRequest,
+};
+use reqwest::{header::HeaderValue, redirect::Policy, ClientBuilder, Method, StatusCode};
+use tokio::time::timeout;
+use tonic::{codec::CompressionEncoding, transport::Channel, Code, Request};
+use tonic_health::pb::{
+	health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
 
 /// Test random API secret generation
 #[tokio::test]
@@ -23,8 +35,8 @@ async fn random_secret() {
 async fn http_to_https_redirect() {
 	let _terminator = util::start_server_with_args(vec![
 		"--example-redirect",
-		"--token",
-		"abc123",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
 		"--default-certificate",
 		r#"{"source": "files", "cert": "tests/cert.pem", "key": "tests/key.pem"}"#,
 		"tests/key.pem",
@@ -70,8 +82,8 @@ async fn http_to_https_redirect() {
 async fn https_to_https_no_redirect() {
 	let _terminator = util::start_server_with_args(vec![
 		"--example-redirect",
-		"--token",
-		"abc123",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
 		"--default-certificate",
 		r#"{"source": "files", "cert": "tests/cert.pem", "key": "tests/key.pem"}"#,
 		"--https-redirect",
@@ -117,8 +129,8 @@ async fn https_to_https_no_redirect() {
 async fn listeners_args() {
 	let _terminator = util::start_server_with_args(vec![
 		"--example-redirect",
-		"--token",
-		"abc123",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
 		"--default-certificate",
 		r#"{"source": "files", "cert": "tests/cert.pem", "key": "tests/key.pem"}"#,
 		"--https-redirect",
@@ -150,3 +162,909 @@ async fn listeners_args() {
 		.await
 		.is_ok());
 }
+
+/// Client addresses from PROXY protocol headers, used for rate limiting
+#[tokio::test]
+#[serial_test::serial]
+async fn proxy_protocol() {
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpStream,
+	};
+
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--listeners",
+		r#"["http::80", "http::8080"]"#,
+		"--proxy-protocol-listeners",
+		r#"["http::8080"]"#,
+		"--redirect-rate-limits",
+		r#"{"client": {"per_minute": 1, "burst": 1}}"#,
+	]);
+
+	let request = |header: &'static [u8]| async move {
+		let mut stream = TcpStream::connect("localhost:8080").await.unwrap();
+		stream.write_all(header).await.unwrap();
+		stream
+			.write_all(b"GET /example HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+			.await
+			.unwrap();
+
+		let mut res = String::new();
+		let _ = stream.read_to_string(&mut res).await;
+		res
+	};
+
+	let first = b"PROXY TCP4 192.0.2.1 127.0.0.1 56324 8080\r\n";
+	let second =
+		b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c\xc0\x00\x02\x02\x7f\x00\x00\x01\xdc\x04\x1f\x90";
+
+	assert!(request(first).await.starts_with("HTTP/1.1 302 Found\r\n"));
+	assert!(request(second).await.starts_with("HTTP/1.1 302 Found\r\n"));
+	assert!(request(first)
+		.await
+		.starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+
+	// Connections without a header are closed on PROXY protocol listeners
+	assert!(request(b"").await.is_empty());
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost:80/example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+/// Connections that don't send a complete request in time are closed
+#[tokio::test]
+#[serial_test::serial]
+async fn http_header_read_timeout() {
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpStream,
+	};
+
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--http-header-read-timeout",
+		"1",
+	]);
+
+	for partial in [&b""[..], b"GET /example HTTP/1.1\r\nHost: loc"] {
+		let mut stream = TcpStream::connect("localhost:80").await.unwrap();
+		stream.write_all(partial).await.unwrap();
+
+		let mut res = Vec::new();
+		let _ = timeout(Duration::from_secs(5), stream.read_to_end(&mut res))
+			.await
+			.expect("connection was not closed");
+		assert!(res.is_empty());
+	}
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.get("http://localhost:80/example")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+/// CORS headers of the HTTP API, including preflight requests
+#[tokio::test]
+#[serial_test::serial]
+async fn http_api_cors() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--listeners",
+		r#"["http_api::8080"]"#,
+		"--http-api-cors",
+		r#"{"allowed_origins": ["https://admin.example"]}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client
+		.request(
+			Method::OPTIONS,
+			"http://localhost:8080/api/v1/redirects/9dDbKpJP",
+		)
+		.header("Origin", "https://admin.example")
+		.header("Access-Control-Request-Method", "PUT")
+		.header(
+			"Access-Control-Request-Headers",
+			"authorization, content-type",
+		)
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Origin"),
+		Some(&HeaderValue::from_static("https://admin.example"))
+	);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Methods"),
+		Some(&HeaderValue::from_static("GET, PUT, POST, DELETE"))
+	);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Headers"),
+		Some(&HeaderValue::from_static(
+			"Authorization, Content-Type, Links-Namespace"
+		))
+	);
+	assert_eq!(
+		res.headers().get("Access-Control-Max-Age"),
+		Some(&HeaderValue::from_static("3600"))
+	);
+
+	let res = client
+		.get("http://localhost:8080/api/v1/redirects/9dDbKpJP")
+		.header("Origin", "https://admin.example")
+		.bearer_auth("abc123")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(
+		res.headers().get("Access-Control-Allow-Origin"),
+		Some(&HeaderValue::from_static("https://admin.example"))
+	);
+	assert_eq!(
+		res.headers().get("Vary"),
+		Some(&HeaderValue::from_static("Origin"))
+	);
+
+	let res = client
+		.request(
+			Method::OPTIONS,
+			"http://localhost:8080/api/v1/redirects/9dDbKpJP",
+		)
+		.header("Origin", "https://other.example")
+		.header("Access-Control-Request-Method", "GET")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NO_CONTENT);
+	assert_eq!(res.headers().get("Access-Control-Allow-Origin"), None);
+	assert_eq!(res.headers().get("Access-Control-Allow-Methods"), None);
+}
+
+/// Reserved paths are never looked up as links, and can't be used as vanity
+/// paths
+#[tokio::test]
+#[serial_test::serial]
+async fn reserved_paths() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--reserved-paths",
+		r#"["/example"]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	let res = client
+		.get("http://localhost/9dDbKpJP")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	for vanity in ["example", "Example/Sub"] {
+		let mut req = Request::new(SetVanityRequest {
+			vanity: vanity.to_string(),
+			id: "9dDbKpJP".to_string(),
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+		let err = rpc_client.set_vanity(req).await.unwrap_err();
+		assert_eq!(err.code(), Code::InvalidArgument, "{vanity}");
+	}
+
+	let mut req = Request::new(SetVanityRequest {
+		vanity: "examples".to_string(),
+		id: "9dDbKpJP".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_vanity(req).await.unwrap();
+
+	let res = client
+		.get("http://localhost/examples")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+/// Structured access log written to a file
+#[tokio::test]
+#[serial_test::serial]
+async fn access_log() {
+	let path = PathBuf::from_str(env!("CARGO_TARGET_TMPDIR"))
+		.unwrap()
+		.join("links_test_access_log.log");
+	let _ = fs::remove_file(&path);
+
+	let settings = serde_json::json!({ "path": path, "client_ip": "anonymized" }).to_string();
+	let _terminator =
+		util::start_server_with_args(vec!["--example-redirect", "--access-log", &settings]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let res = client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	let log = fs::read_to_string(&path).unwrap();
+	let lines = log
+		.lines()
+		.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+		.collect::<Vec<_>>();
+
+	assert_eq!(lines.len(), 2);
+	assert_eq!(lines[0]["path"], "/example");
+	assert_eq!(lines[0]["id"], "9dDbKpJP");
+	assert_eq!(lines[0]["vanity"], "example");
+	assert_eq!(lines[0]["status"], 302);
+	assert_eq!(lines[1]["path"], "/nonexistent");
+	assert_eq!(lines[1]["id"], serde_json::Value::Null);
+	assert_eq!(lines[1]["status"], 404);
+}
+
+/// Redirect loop detection, when setting redirects and when redirecting
+#[tokio::test]
+#[serial_test::serial]
+async fn redirect_loops() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--loop-detection",
+		r#"{"domains": ["go.example.com"], "max_chain": 1, "recheck": true}"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(SetRedirectRequest {
+		id: "1wqLjdjd".to_string(),
+		link: "https://go.example.com/example".to_string(),
+		expires_at: None,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_redirect(req).await.unwrap();
+
+	let mut req = Request::new(SetRedirectRequest {
+		id: "9dDbKpJP".to_string(),
+		link: "https://go.example.com/1wqLjdjd".to_string(),
+		expires_at: None,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let err = rpc_client.set_redirect(req).await.unwrap_err();
+	assert_eq!(err.code(), Code::InvalidArgument);
+
+	let mut req = Request::new(SetRedirectRequest {
+		id: "1wqLjdjd".to_string(),
+		link: "https://go.example.com/other".to_string(),
+		expires_at: None,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_redirect(req).await.unwrap();
+
+	let res = client
+		.get("http://localhost/1wqLjdjd")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let mut req = Request::new(SetVanityRequest {
+		vanity: "other".to_string(),
+		id: "1wqLjdjd".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_vanity(req).await.unwrap();
+
+	let res = client.get("http://localhost/other").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+/// API token roles, with read-only tokens not being able to modify the store
+#[tokio::test]
+#[serial_test::serial]
+async fn token_roles() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[
+			{"id": "reader", "token": "read123", "role": "read-only"},
+			{"id": "writer", "token": "write123", "role": "write"},
+			{"id": "admin", "token": "admin123"}
+		]"#,
+	]);
+
+	let mut client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let request = |token: &str| {
+		let mut req = Request::new(SetRedirectRequest {
+			id: "0fDbKpJP".to_string(),
+			link: "https://example.com/roles".to_string(),
+			expires_at: None,
+		});
+		req.metadata_mut().append("auth", token.parse().unwrap());
+		req
+	};
+
+	let status = client.set_redirect(request("read123")).await.unwrap_err();
+	assert_eq!(status.code(), Code::PermissionDenied);
+	client.set_redirect(request("write123")).await.unwrap();
+	client.set_redirect(request("admin123")).await.unwrap();
+
+	let mut req = Request::new(GetRedirectRequest {
+		id: "0fDbKpJP".to_string(),
+	});
+	req.metadata_mut()
+		.append("auth", "read123".parse().unwrap());
+	let res = client.get_redirect(req).await.unwrap().into_inner();
+	assert_eq!(res.link.as_deref(), Some("https://example.com/roles"));
+
+	for (token, code) in [
+		("read123", Some(Code::PermissionDenied)),
+		("write123", Some(Code::PermissionDenied)),
+		("admin123", None),
+	] {
+		let mut req = Request::new(PurgeRedirectsRequest {});
+		req.metadata_mut().append("auth", token.parse().unwrap());
+		let res = client.purge_redirects(req).await;
+		assert_eq!(res.err().map(|status| status.code()), code, "{token}");
+	}
+}
+
+/// Setting redirects to blocked domains via the RPC API
+#[tokio::test]
+#[serial_test::serial]
+async fn blocked_domains() {
+	let _terminator = util::start_server_with_args(vec![
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--blocked-domains",
+		r#"["example.com", "*.example.net"]"#,
+	]);
+
+	let mut client = util::get_rpc_client("localhost", 50051, false).await;
+
+	for (link, blocked) in [
+		("https://example.com/", true),
+		("https://www.example.com/", false),
+		("https://example.net/", false),
+		("https://a.b.example.net/", true),
+	] {
+		let mut req = Request::new(SetRedirectRequest {
+			id: "0fDbKpJP".to_string(),
+			link: link.to_string(),
+			expires_at: None,
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+		let res = client.set_redirect(req).await;
+		assert_eq!(
+			res.err().map(|status| status.code()),
+			blocked.then_some(Code::InvalidArgument),
+			"{link}"
+		);
+	}
+}
+
+/// Redirects to blocked domains created before the domain was blocked
+#[tokio::test]
+#[serial_test::serial]
+async fn blocked_domains_recheck() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--blocked-domains",
+		r#"["example.com"]"#,
+		"--recheck-blocked-domains",
+		"true",
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+	assert_eq!(res.headers().get("Location"), None);
+}
+
+/// Links flagged by reputation checks, which are disabled
+#[tokio::test]
+#[serial_test::serial]
+async fn flagged_links() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: r#"{"flagged": "MALWARE"}"#.to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	let mut req = Request::new(GetRedirectRequest {
+		id: "9dDbKpJP".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client.get_redirect(req).await.unwrap().into_inner();
+	assert_eq!(res.link.as_deref(), Some("https://example.com/"));
+	assert_eq!(res.flagged.as_deref(), Some("MALWARE"));
+
+	let mut req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options: "{}".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+/// Disabled links are treated as if they didn't exist, but are kept, and can
+/// be enabled again
+#[tokio::test]
+#[serial_test::serial]
+async fn disabled_links() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--listeners",
+		r#"["http::", "grpc:[::1]:", "http_api::8080"]"#,
+	]);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(SetRedirectEnabledRequest {
+		id: "9dDbKpJP".to_string(),
+		enabled: false,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client.set_redirect_enabled(req).await.unwrap();
+	assert!(res.into_inner().enabled);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+	let mut req = Request::new(GetRedirectRequest {
+		id: "9dDbKpJP".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client.get_redirect(req).await.unwrap().into_inner();
+	assert_eq!(res.link.as_deref(), Some("https://example.com/"));
+	assert!(!res.enabled);
+
+	let res = client
+		.post("http://localhost:8080/api/v1/redirects/9dDbKpJP/enable")
+		.bearer_auth("abc123")
+		.send()
+		.await
+		.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.text().await.unwrap(), r#"{"enabled":false}"#);
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+}
+
+/// Standard gRPC health checks, which don't need authentication
+#[tokio::test]
+#[serial_test::serial]
+async fn health_check() {
+	let _terminator = util::start_server(false);
+
+	let mut client = HealthClient::new(
+		Channel::from_static("http://localhost:50051")
+			.connect()
+			.await
+			.unwrap(),
+	);
+
+	for service in ["", "links.Links"] {
+		let res = client
+			.check(HealthCheckRequest {
+				service: service.to_string(),
+			})
+			.await
+			.unwrap()
+			.into_inner();
+		assert_eq!(res.status(), ServingStatus::Serving, "{service}");
+	}
+
+	let status = client
+		.check(HealthCheckRequest {
+			service: "unknown".to_string(),
+		})
+		.await
+		.unwrap_err();
+	assert_eq!(status.code(), Code::NotFound);
+
+	let mut watch = client
+		.watch(HealthCheckRequest {
+			service: String::new(),
+		})
+		.await
+		.unwrap()
+		.into_inner();
+	let res = watch.message().await.unwrap().unwrap();
+	assert_eq!(res.status(), ServingStatus::Serving);
+}
+
+/// HTTP liveness and readiness endpoints, without any TLS certificates
+#[tokio::test]
+#[serial_test::serial]
+async fn health_endpoints() {
+	let _terminator = util::start_server(false);
+	let client = ClientBuilder::new().build().unwrap();
+
+	let res = client.get("http://localhost/healthz").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.text().await.unwrap(), "ok");
+
+	// HTTPS listeners are configured, but there are no certificates for them
+	let res = client.get("http://localhost/readyz").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+	assert_eq!(res.text().await.unwrap(), "no certificates");
+}
+
+/// HTTP readiness endpoint, with TLS certificates
+#[tokio::test]
+#[serial_test::serial]
+async fn health_endpoints_tls() {
+	let _terminator = util::start_server(true);
+	let client = ClientBuilder::new().build().unwrap();
+
+	let res = client.get("http://localhost/readyz").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(res.text().await.unwrap(), "ok");
+
+	let res = client.get("https://localhost/readyz").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+}
+
+/// Prometheus metrics endpoint, which is disabled by default
+#[tokio::test]
+#[serial_test::serial]
+async fn metrics_endpoint_disabled() {
+	let _terminator = util::start_server(false);
+	let client = ClientBuilder::new().build().unwrap();
+
+	let res = client.get("http://localhost/metrics").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+/// Prometheus metrics endpoint
+#[tokio::test]
+#[serial_test::serial]
+async fn metrics_endpoint() {
+	let _terminator = util::start_server_with_args(vec!["--example-redirect", "--metrics", "true"]);
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::FOUND);
+
+	let res = client.get("http://localhost/metrics").send().await.unwrap();
+	assert_eq!(res.status(), StatusCode::OK);
+	assert_eq!(
+		res.headers().get("Content-Type"),
+		Some(&HeaderValue::from_static(
+			"text/plain; version=0.0.4; charset=utf-8"
+		))
+	);
+
+	let body = res.text().await.unwrap();
+	assert!(body.contains("links_http_requests_total{status=\"302\"} 1\n"));
+	assert!(body.contains("# TYPE links_http_request_duration_seconds histogram\n"));
+	assert!(body.contains("links_store_operations_total{kind=\"read\",result=\"ok\"} "));
+	assert!(body.contains("links_tls_handshakes_total{result=\"success\"} 0\n"));
+}
+
+/// RPC rate limiting per API token
+#[tokio::test]
+#[serial_test::serial]
+async fn rpc_rate_limits() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
+		"--rpc-rate-limits",
+		r#"{"token": {"per_minute": 1, "burst": 2}}"#,
+	]);
+
+	let mut client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let request = || {
+		let mut req = Request::new(GetRedirectRequest {
+			id: "9dDbKpJP".to_string(),
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+		req
+	};
+
+	client.get_redirect(request()).await.unwrap();
+	client.get_redirect(request()).await.unwrap();
+
+	let status = client.get_redirect(request()).await.unwrap_err();
+	assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+/// Daily quotas of API tokens, only counting requests which modify the store
+#[tokio::test]
+#[serial_test::serial]
+async fn token_quotas() {
+	let _terminator = util::start_server_with_args(vec![
+		"--example-redirect",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123", "quota": {"daily": 2}}]"#,
+	]);
+
+	let mut client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let request = || {
+		let mut req = Request::new(SetRedirectRequest {
+			id: "0fDbKpJP".to_string(),
+			link: "https://example.com/quota".to_string(),
+			expires_at: None,
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+		req
+	};
+
+	client.set_redirect(request()).await.unwrap();
+	client.set_redirect(request()).await.unwrap();
+
+	let status = client.set_redirect(request()).await.unwrap_err();
+	assert_eq!(status.code(), Code::ResourceExhausted);
+	assert_eq!(
+		status.metadata().get("quota-period"),
+		Some(&"daily".parse().unwrap())
+	);
+	assert_eq!(
+		status.metadata().get("quota-limit"),
+		Some(&"2".parse().unwrap())
+	);
+	assert!(status.metadata().get("quota-reset").is_some());
+
+	let mut req = Request::new(GetRedirectRequest {
+		id: "0fDbKpJP".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = client.get_redirect(req).await.unwrap().into_inner();
+	assert_eq!(res.link.as_deref(), Some("https://example.com/quota"));
+}
+
+/// RPC response compression using zstd only on listeners where it's enabled
+#[tokio::test]
+#[serial_test::serial]
+async fn rpc_zstd() {
+	let encoding = |zstd_listeners: &'static str| async move {
+		let _terminator = util::start_server_with_args(vec![
+			"--example-redirect",
+			"--tokens",
+			r#"[{"id": "test", "token": "abc123"}]"#,
+			"--rpc-zstd-listeners",
+			zstd_listeners,
+		]);
+
+		let mut client = LinksClient::connect("http://localhost:50051")
+			.await
+			.unwrap()
+			.accept_compressed(CompressionEncoding::Zstd)
+			.accept_compressed(CompressionEncoding::Gzip);
+
+		let mut req = Request::new(GetRedirectRequest {
+			id: "9dDbKpJP".to_string(),
+		});
+		req.metadata_mut().append("auth", "abc123".parse().unwrap());
+
+		let res = client.get_redirect(req).await.unwrap();
+		assert_eq!(res.get_ref().link.as_deref(), Some("https://example.com/"));
+
+		res.metadata()
+			.get("grpc-encoding")
+			.map(|e| e.to_str().unwrap().to_string())
+	};
+
+	assert_eq!(
+		encoding(r#"["grpc:[::1]:"]"#).await.as_deref(),
+		Some("zstd")
+	);
+	assert_eq!(
+		encoding(r#"["grpc:[::1]:50051"]"#).await.as_deref(),
+		Some("zstd")
+	);
+	assert_eq!(encoding(r#"["grpcs::"]"#).await.as_deref(), Some("gzip"));
+	assert_eq!(encoding("[]").await.as_deref(), Some("gzip"));
+}
+
+/// Watching redirect changes via the RPC API
+#[tokio::test]
+#[serial_test::serial]
+async fn watch_redirects() {
+	let _terminator = util::start_server(false);
+
+	let mut client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(WatchRedirectsRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let mut changes = client.watch_redirects(req).await.unwrap().into_inner();
+
+	let mut req = Request::new(SetRedirectRequest {
+		id: "0fDbKpJP".to_string(),
+		link: "https://example.com/watched".to_string(),
+		expires_at: None,
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	client.set_redirect(req).await.unwrap();
+
+	let mut req = Request::new(RemRedirectRequest {
+		id: "0fDbKpJP".to_string(),
+	});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	client.rem_redirect(req).await.unwrap();
+
+	let change = timeout(Duration::from_secs(5), changes.message())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+
+	assert_eq!(change.id, "0fDbKpJP");
+	assert_eq!(change.old_link, None);
+	assert_eq!(
+		change.new_link.as_deref(),
+		Some("https://example.com/watched")
+	);
+
+	let change = timeout(Duration::from_secs(5), changes.message())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+
+	assert_eq!(change.id, "0fDbKpJP");
+	assert_eq!(
+		change.old_link.as_deref(),
+		Some("https://example.com/watched")
+	);
+	assert_eq!(change.new_link, None);
+}
+
+/// Test listing listeners and their counters via RPC
+#[tokio::test]
+#[serial_test::serial]
+async fn list_listeners() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	client.get("http://localhost/example").send().await.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(ListListenersRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let listeners = rpc_client
+		.list_listeners(req)
+		.await
+		.unwrap()
+		.into_inner()
+		.listeners;
+
+	assert!(!listeners.is_empty());
+	assert!(listeners.windows(2).all(|w| w[0].address <= w[1].address));
+	assert!(listeners
+		.iter()
+		.any(|l| l.protocol == "http" && l.port == 80 && l.accepted >= 1));
+	assert!(listeners
+		.iter()
+		.any(|l| l.protocol == "grpc" && l.port == 50051 && l.accepted >= 1));
+	assert!(listeners.iter().all(|l| l.errors == 0));
+}
+
+/// Test getting the certificate lookup counters via RPC
+#[tokio::test]
+#[serial_test::serial]
+async fn certificate_lookups() {
+	let _terminator = util::start_server(true);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	client
+		.get("https://localhost/example")
+		.send()
+		.await
+		.unwrap();
+
+	let mut rpc_client = util::get_rpc_client("localhost", 50051, false).await;
+
+	let mut req = Request::new(GetCertificateLookupsRequest {});
+	req.metadata_mut().append("auth", "abc123".parse().unwrap());
+	let res = rpc_client
+		.get_certificate_lookups(req)
+		.await
+		.unwrap()
+		.into_inner();
+
+	let localhost = res
+		.domains
+		.iter()
+		.find(|d| d.domain == "localhost")
+		.and_then(|d| d.lookups.as_ref())
+		.unwrap();
+	assert!(localhost.default >= 1);
+	assert_eq!(localhost.exact, 0);
+	assert_eq!(localhost.unresolved, 0);
+	assert_eq!(res.untracked.unwrap_or_default().default, 0);
+}
@@ -2,8 +2,14 @@
 
 mod util;
 
-use links::api::GetStatisticsRequest;
+use std::time::Duration;
+
+use links::api::{
+	Code, GetStatisticsRequest, GetStatisticsSummaryRequest, SetOptionsRequest,
+	WatchStatisticsRequest,
+};
 use reqwest::{redirect::Policy, ClientBuilder};
+use tokio::time::timeout;
 use tonic::Request;
 use util::get_rpc_client;
 
@@ -316,3 +322,274 @@ async fn https2_collection() {
 	assert_eq!(stats[0].link, "example");
 	assert_eq!(stats[0].value, 1);
 }
+
+/// Statistic watching tests
+#[tokio::test]
+#[serial_test::serial]
+async fn watch_statistics() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.http1_only()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = get_rpc_client("localhost", 50051, false).await;
+
+	let mut rpc_req = Request::new(WatchStatisticsRequest {
+		link: Some("example".to_string()),
+		r#type: Some("http_version".to_string()),
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let mut stats = rpc_client
+		.watch_statistics(rpc_req)
+		.await
+		.unwrap()
+		.into_inner();
+
+	client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+	client.get("http://localhost/example").send().await.unwrap();
+
+	let stat = timeout(Duration::from_secs(5), stats.message())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+
+	assert_eq!(stat.data, "HTTP/1.1");
+	assert_eq!(stat.r#type, "http_version");
+	assert_eq!(stat.link, "example");
+	assert_eq!(stat.value, 1);
+
+	let mut rpc_req = Request::new(WatchStatisticsRequest {
+		r#type: Some("invalid".to_string()),
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let status = rpc_client.watch_statistics(rpc_req).await.unwrap_err();
+
+	assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+/// Statistic pagination tests
+#[tokio::test]
+#[serial_test::serial]
+async fn paginated_statistics() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = get_rpc_client("localhost", 50051, false).await;
+
+	client.get("http://localhost/example").send().await.unwrap();
+	client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+
+	let mut stats = Vec::new();
+	let mut cursor = None;
+	loop {
+		let mut rpc_req = Request::new(GetStatisticsRequest {
+			cursor,
+			limit: Some(5),
+			..Default::default()
+		});
+		rpc_req
+			.metadata_mut()
+			.append("auth", "abc123".parse().unwrap());
+		let res = rpc_client
+			.get_statistics(rpc_req)
+			.await
+			.unwrap()
+			.into_inner();
+
+		assert!(res.statistics.len() <= 5);
+		stats.extend(res.statistics);
+
+		cursor = res.next;
+		if cursor.is_none() {
+			break;
+		}
+	}
+
+	// 4 from "/example", 4 from its ID, 4 from "/nonexistent"
+	assert_eq!(stats.len(), 12);
+
+	let mut rpc_req = Request::new(GetStatisticsRequest {
+		limit: Some(0),
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let status = rpc_client.get_statistics(rpc_req).await.unwrap_err();
+
+	assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+/// Split destination variant statistic tests
+#[tokio::test]
+#[serial_test::serial]
+async fn split_variant_collection() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = get_rpc_client("localhost", 50051, false).await;
+
+	let mut rpc_req = Request::new(SetOptionsRequest {
+		id: "9dDbKpJP".to_string(),
+		options:
+			r#"{"split": {"variants": [{"name": "only", "link": "https://example.com/split"}]}}"#
+				.to_string(),
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	rpc_client.set_options(rpc_req).await.unwrap();
+
+	let res = client.get("http://localhost/example").send().await.unwrap();
+	assert_eq!(
+		res.headers().get("Location").unwrap(),
+		"https://example.com/split"
+	);
+
+	let mut rpc_req = Request::new(GetStatisticsRequest {
+		link: Some("example".to_string()),
+		r#type: Some("variant".to_string()),
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let stats = rpc_client
+		.get_statistics(rpc_req)
+		.await
+		.unwrap()
+		.into_inner()
+		.statistics;
+
+	assert_eq!(stats.len(), 1);
+	assert_eq!(stats[0].data, "only");
+}
+
+/// Statistic summary tests
+#[tokio::test]
+#[serial_test::serial]
+async fn statistics_summary() {
+	let _terminator = util::start_server(false);
+
+	let client = ClientBuilder::new()
+		.redirect(Policy::none())
+		.build()
+		.unwrap();
+
+	let mut rpc_client = get_rpc_client("localhost", 50051, false).await;
+
+	client.get("http://localhost/example").send().await.unwrap();
+	client.get("http://localhost/example").send().await.unwrap();
+	client
+		.get("http://localhost/nonexistent")
+		.send()
+		.await
+		.unwrap();
+
+	let mut rpc_req = Request::new(GetStatisticsSummaryRequest {
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let totals = rpc_client
+		.get_statistics_summary(rpc_req)
+		.await
+		.unwrap()
+		.into_inner()
+		.totals;
+
+	// 4 from "/example", 4 from its ID, 4 from "/nonexistent"
+	assert_eq!(totals.len(), 1);
+	assert_eq!(totals[0].count, 12);
+	assert_eq!(totals[0].total, 20);
+	assert!(totals[0].link.is_none());
+	assert!(totals[0].r#type.is_none());
+
+	let mut rpc_req = Request::new(GetStatisticsSummaryRequest {
+		group_by: vec!["type".to_string(), "time".to_string()],
+		time_bucket: Some(86400),
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let totals = rpc_client
+		.get_statistics_summary(rpc_req)
+		.await
+		.unwrap()
+		.into_inner()
+		.totals;
+
+	assert!(totals.len() >= 4);
+	assert_eq!(totals.iter().map(|t| t.total).sum::<u64>(), 20);
+	assert!(totals.iter().all(
+		|t| t.r#type.is_some() && t.time.as_deref().is_some_and(|t| t.ends_with("T00:00:00Z"))
+	));
+
+	let mut rpc_req = Request::new(GetStatisticsSummaryRequest {
+		link: Some("example".to_string()),
+		group_by: vec!["type".to_string()],
+		..Default::default()
+	});
+	rpc_req
+		.metadata_mut()
+		.append("auth", "abc123".parse().unwrap());
+	let totals = rpc_client
+		.get_statistics_summary(rpc_req)
+		.await
+		.unwrap()
+		.into_inner()
+		.totals;
+
+	assert_eq!(totals.len(), 4);
+	assert!(totals.iter().all(|t| t.total == 2 && t.count == 1));
+
+	for (group_by, time_bucket) in [
+		(vec!["nothing".to_string()], None),
+		(vec!["type".to_string()], Some(900)),
+		(vec!["time".to_string()], Some(1000)),
+		(vec!["time".to_string()], Some(0)),
+	] {
+		let mut rpc_req = Request::new(GetStatisticsSummaryRequest {
+			group_by,
+			time_bucket,
+			..Default::default()
+		});
+		rpc_req
+			.metadata_mut()
+			.append("auth", "abc123".parse().unwrap());
+		let status = rpc_client
+			.get_statistics_summary(rpc_req)
+			.await
+			.unwrap_err();
+
+		assert_eq!(status.code(), Code::InvalidArgument);
+	}
+}
@@ -69,8 +69,8 @@ pub fn convert_path(path: impl AsRef<str>) -> String {
 pub fn start_server(tls: bool) -> Terminator<impl FnOnce()> {
 	let mut args = vec![
 		"--example-redirect",
-		"--token",
-		"abc123",
+		"--tokens",
+		r#"[{"id": "test", "token": "abc123"}]"#,
 		"--watcher-timeout",
 		"100",
 		"--watcher-debounce",